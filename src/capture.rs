@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::ThreadId;
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+
+
+/// A span recorded by [`Capture`], with the values of all its fields formatted as strings.
+#[derive(Clone, Debug)]
+pub(crate) struct CapturedSpan {
+    pub(crate) name: &'static str,
+    pub(crate) target: &'static str,
+    pub(crate) fields: HashMap<&'static str, String>,
+}
+
+/// An event recorded by [`Capture`], together with the index of the span it occurred in.
+#[derive(Clone, Debug)]
+pub(crate) struct CapturedEvent {
+    pub(crate) target: &'static str,
+    pub(crate) fields: HashMap<&'static str, String>,
+    pub(crate) span: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct Captured {
+    spans: Vec<CapturedSpan>,
+    events: Vec<CapturedEvent>,
+    /// The entered spans of every thread, innermost last.
+    entered: HashMap<ThreadId, Vec<usize>>,
+}
+
+impl Captured {
+    fn current(&self) -> Option<usize> {
+        self.entered.get(&std::thread::current().id())?.last().copied()
+    }
+}
+
+/// A subscriber which records all spans and events, so tests can assert on them.
+///
+/// Spans keep the subscriber they were created with, so fields recorded on other threads are
+/// captured as well. Events only get captured on threads using the subscriber, e.g. within
+/// [`tracing::subscriber::with_default`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Capture {
+    inner: Arc<Mutex<Captured>>,
+}
+
+impl Capture {
+    pub(crate) fn spans(&self) -> Vec<CapturedSpan> {
+        self.lock().spans.clone()
+    }
+
+    pub(crate) fn events(&self) -> Vec<CapturedEvent> {
+        self.lock().events.clone()
+    }
+
+    /// Returns the first span named `name` together with its index.
+    pub(crate) fn span(&self, name: &str) -> Option<(usize, CapturedSpan)> {
+        self.spans().into_iter().enumerate().find(|(_, span)| span.name == name)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Captured> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Formats the values of the visited fields.
+struct FieldVisitor<'a>(&'a mut HashMap<&'static str, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name(), format!("{:?}", value));
+    }
+}
+
+/// Span ids start at 1, so they are the index of the span plus one.
+fn index(id: &Id) -> usize {
+    id.into_u64() as usize - 1
+}
+
+impl Subscriber for Capture {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let mut fields = HashMap::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+
+        let mut captured = self.lock();
+        captured.spans.push(CapturedSpan {
+            name: attrs.metadata().name(),
+            target: attrs.metadata().target(),
+            fields,
+        });
+
+        Id::from_u64(captured.spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut captured = self.lock();
+
+        values.record(&mut FieldVisitor(&mut captured.spans[index(span)].fields));
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = HashMap::new();
+        event.record(&mut FieldVisitor(&mut fields));
+
+        let mut captured = self.lock();
+        let span = match event.parent() {
+            Some(parent) => Some(index(parent)),
+            None if event.is_contextual() => captured.current(),
+            None => None,
+        };
+
+        captured.events.push(CapturedEvent {
+            target: event.metadata().target(),
+            fields,
+            span,
+        });
+    }
+
+    fn enter(&self, span: &Id) {
+        let mut captured = self.lock();
+
+        captured.entered.entry(std::thread::current().id()).or_default().push(index(span));
+    }
+
+    fn exit(&self, span: &Id) {
+        let mut captured = self.lock();
+        let entered = captured.entered.entry(std::thread::current().id()).or_default();
+
+        if let Some(pos) = entered.iter().rposition(|entered| *entered == index(span)) {
+            entered.remove(pos);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::error::Error;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::stream::{Stream, StreamExt};
+use futures::future::FutureExt;
+
+use async_trait::async_trait;
+
+
+
+/// Unbounded channels which pick their implementation based on the current runtime.
+mod unbounded;
+pub use self::unbounded::*;
+
+
+
+/// The sending half of a channel.
+pub trait Sender<T>: Clone {
+    /// Attempts to send `value` without waiting.
+    ///
+    /// Fails if all receivers have been dropped or closed, or if a bounded channel is full. The
+    /// value gets returned within the error.
+    fn try_send(&self, value: T) -> Result<(), TrySendError<T>>;
+
+    /// Returns `true` if all receivers have been dropped or closed.
+    fn is_closed(&self) -> bool;
+}
+
+/// The receiving half of a channel.
+///
+/// The values can be received as a [`Stream`] as well, which ends once the channel has been closed
+/// and all values have been received.
+#[async_trait]
+pub trait Receiver<T: Send>: Stream<Item = T> + Unpin + Send {
+    /// Waits for the next value.
+    ///
+    /// Returns `None` once the channel has been closed, e.g. because all senders have been
+    /// dropped, and all values have been received.
+    async fn recv(&mut self) -> Option<T> {
+        self.next().await
+    }
+
+    /// Attempts to receive the next value without waiting.
+    fn try_recv(&mut self) -> Result<T, TryRecvError>;
+
+    /// Closes the channel, so no further values can be sent.
+    ///
+    /// The values sent before can still be received.
+    fn close(&mut self);
+}
+
+
+
+/// The error returned when sending on a closed channel, containing the value which could not be
+/// sent.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sending on a closed channel")
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+
+/// The error returned by [`Sender::try_send`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// The channel is full. Never returned by unbounded channels.
+    Full(T),
+    /// The channel has been closed.
+    Closed(T),
+}
+
+impl<T> TrySendError<T> {
+    /// Consumes the `TrySendError` object and returns the value which could not be sent.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Full(value) => value,
+            Self::Closed(value) => value,
+        }
+    }
+
+    /// Returns `true` if the channel is full.
+    pub fn is_full(&self) -> bool {
+        matches!(self, Self::Full(_))
+    }
+
+    /// Returns `true` if the channel has been closed.
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Self::Closed(_))
+    }
+}
+
+impl<T> From<SendError<T>> for TrySendError<T> {
+    fn from(err: SendError<T>) -> Self {
+        Self::Closed(err.0)
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => write!(f, "Full(..)"),
+            Self::Closed(_) => write!(f, "Closed(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => write!(f, "sending on a full channel"),
+            Self::Closed(_) => write!(f, "sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
+
+
+/// The error returned by [`Receiver::try_recv`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TryRecvError {
+    /// The channel is empty, but values may still be sent.
+    Empty,
+    /// The channel is empty and has been closed.
+    Closed,
+}
+
+impl TryRecvError {
+    /// Returns `true` if the channel is empty, but values may still be sent.
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Self::Empty)
+    }
+
+    /// Returns `true` if the channel is empty and has been closed.
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Self::Closed)
+    }
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "receiving on an empty channel"),
+            Self::Closed => write!(f, "receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
@@ -0,0 +1,340 @@
+use super::*;
+
+use crate::runtime::RuntimeKind;
+
+
+
+/// Creates an unbounded channel, returning its sending and receiving half.
+///
+/// Sending never waits, as the channel buffers any number of values, which makes it suitable for
+/// fire-and-forget events. The implementation gets picked like the runtime of
+/// [`DefaultExecutor`](crate::task::DefaultExecutor):
+///
+/// 1. Within the context of a [`tokio`](https://docs.rs/tokio) runtime, the unbounded channel of
+///    tokio gets used. Requires the `tokio-rt` feature.
+/// 2. Otherwise the channel of [`async_std`](https://docs.rs/async-std) gets used. Requires the
+///    `async-std-rt` feature.
+/// 3. Otherwise the channel of [`smol`](https://docs.rs/smol) gets used. Requires the `smol-rt`
+///    feature.
+/// 4. Otherwise the unbounded channel of [`futures`](https://docs.rs/futures) gets used, so no
+///    runtime is required.
+///
+/// All of them work on any executor, the choice only avoids pulling in another implementation.
+///
+/// # Example
+///
+/// ```
+/// use fut_compat::channel::{unbounded, Receiver};
+///
+/// # futures::executor::block_on(async {
+/// let (tx, mut rx) = unbounded();
+///
+/// tx.send(1).unwrap();
+/// tx.send(2).unwrap();
+/// drop(tx);
+///
+/// assert_eq!(rx.recv().await, Some(1));
+/// assert_eq!(rx.recv().await, Some(2));
+/// assert_eq!(rx.recv().await, None);
+/// # });
+/// ```
+pub fn unbounded<T>() -> (UnboundedSender<T>, UnboundedReceiver<T>) {
+    let (tx, rx) = match RuntimeKind::current() {
+        #[cfg(feature = "tokio-rt")]
+        Some(RuntimeKind::Tokio) => {
+            let (tx, rx) = ::tokio::sync::mpsc::unbounded_channel();
+
+            (SenderInner::Tokio(tx), ReceiverInner::Tokio(rx))
+        }
+        #[cfg(feature = "async-std-rt")]
+        Some(RuntimeKind::AsyncStd) => {
+            let (tx, rx) = ::async_std::channel::unbounded();
+
+            (SenderInner::AsyncStd(tx), ReceiverInner::AsyncStd(Box::pin(rx)))
+        }
+        #[cfg(feature = "smol-rt")]
+        Some(RuntimeKind::Smol) => {
+            let (tx, rx) = ::smol::channel::unbounded();
+
+            (SenderInner::Smol(tx), ReceiverInner::Smol(Box::pin(rx)))
+        }
+        None => {
+            let (tx, rx) = futures::channel::mpsc::unbounded();
+
+            (SenderInner::Futures(tx), ReceiverInner::Futures(rx))
+        }
+    };
+
+    (UnboundedSender { inner: tx }, UnboundedReceiver { inner: rx })
+}
+
+
+
+/// The sending half of a channel created by [`unbounded`].
+///
+/// It can be cloned for sending from multiple places. The channel gets closed once all senders
+/// have been dropped.
+pub struct UnboundedSender<T> {
+    inner: SenderInner<T>,
+}
+
+enum SenderInner<T> {
+    #[cfg(feature = "tokio-rt")]
+    Tokio(::tokio::sync::mpsc::UnboundedSender<T>),
+    #[cfg(feature = "async-std-rt")]
+    AsyncStd(::async_std::channel::Sender<T>),
+    #[cfg(feature = "smol-rt")]
+    Smol(::smol::channel::Sender<T>),
+    Futures(futures::channel::mpsc::UnboundedSender<T>),
+}
+
+impl<T> UnboundedSender<T> {
+    /// Sends `value` without waiting.
+    ///
+    /// Fails if the receiver has been dropped or closed, returning the value within the error.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        match &self.inner {
+            #[cfg(feature = "tokio-rt")]
+            SenderInner::Tokio(tx) => tx.send(value).map_err(|err| SendError(err.0)),
+            #[cfg(feature = "async-std-rt")]
+            SenderInner::AsyncStd(tx) => tx.try_send(value).map_err(|err| SendError(err.into_inner())),
+            #[cfg(feature = "smol-rt")]
+            SenderInner::Smol(tx) => tx.try_send(value).map_err(|err| SendError(err.into_inner())),
+            SenderInner::Futures(tx) => tx.unbounded_send(value).map_err(|err| SendError(err.into_inner())),
+        }
+    }
+}
+
+impl<T> Sender<T> for UnboundedSender<T> {
+    fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        self.send(value).map_err(TrySendError::from)
+    }
+
+    fn is_closed(&self) -> bool {
+        match &self.inner {
+            #[cfg(feature = "tokio-rt")]
+            SenderInner::Tokio(tx) => tx.is_closed(),
+            #[cfg(feature = "async-std-rt")]
+            SenderInner::AsyncStd(tx) => tx.is_closed(),
+            #[cfg(feature = "smol-rt")]
+            SenderInner::Smol(tx) => tx.is_closed(),
+            SenderInner::Futures(tx) => tx.is_closed(),
+        }
+    }
+}
+
+impl<T> Clone for UnboundedSender<T> {
+    fn clone(&self) -> Self {
+        let inner = match &self.inner {
+            #[cfg(feature = "tokio-rt")]
+            SenderInner::Tokio(tx) => SenderInner::Tokio(tx.clone()),
+            #[cfg(feature = "async-std-rt")]
+            SenderInner::AsyncStd(tx) => SenderInner::AsyncStd(tx.clone()),
+            #[cfg(feature = "smol-rt")]
+            SenderInner::Smol(tx) => SenderInner::Smol(tx.clone()),
+            SenderInner::Futures(tx) => SenderInner::Futures(tx.clone()),
+        };
+
+        Self { inner }
+    }
+}
+
+impl<T> fmt::Debug for UnboundedSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnboundedSender").finish_non_exhaustive()
+    }
+}
+
+
+
+/// The receiving half of a channel created by [`unbounded`].
+///
+/// Dropping it closes the channel, so sending fails afterwards.
+pub struct UnboundedReceiver<T> {
+    inner: ReceiverInner<T>,
+}
+
+enum ReceiverInner<T> {
+    #[cfg(feature = "tokio-rt")]
+    Tokio(::tokio::sync::mpsc::UnboundedReceiver<T>),
+    // Boxed, as the receivers of async-channel are not `Unpin`.
+    #[cfg(feature = "async-std-rt")]
+    AsyncStd(Pin<Box<::async_std::channel::Receiver<T>>>),
+    #[cfg(feature = "smol-rt")]
+    Smol(Pin<Box<::smol::channel::Receiver<T>>>),
+    Futures(futures::channel::mpsc::UnboundedReceiver<T>),
+}
+
+impl<T> Stream for UnboundedReceiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        match &mut Pin::into_inner(self).inner {
+            #[cfg(feature = "tokio-rt")]
+            ReceiverInner::Tokio(rx) => rx.poll_recv(cx),
+            #[cfg(feature = "async-std-rt")]
+            ReceiverInner::AsyncStd(rx) => rx.as_mut().poll_next(cx),
+            #[cfg(feature = "smol-rt")]
+            ReceiverInner::Smol(rx) => rx.as_mut().poll_next(cx),
+            ReceiverInner::Futures(rx) => rx.poll_next_unpin(cx),
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Send> Receiver<T> for UnboundedReceiver<T> {
+    fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        match &mut self.inner {
+            #[cfg(feature = "tokio-rt")]
+            ReceiverInner::Tokio(rx) => rx.try_recv().map_err(|err| match err {
+                ::tokio::sync::mpsc::error::TryRecvError::Empty => TryRecvError::Empty,
+                ::tokio::sync::mpsc::error::TryRecvError::Disconnected => TryRecvError::Closed,
+            }),
+            #[cfg(feature = "async-std-rt")]
+            ReceiverInner::AsyncStd(rx) => rx.try_recv().map_err(|err| match err.is_empty() {
+                true => TryRecvError::Empty,
+                false => TryRecvError::Closed,
+            }),
+            #[cfg(feature = "smol-rt")]
+            ReceiverInner::Smol(rx) => rx.try_recv().map_err(|err| match err.is_empty() {
+                true => TryRecvError::Empty,
+                false => TryRecvError::Closed,
+            }),
+            // Polling once is the only way of telling an empty channel from a closed one, which
+            // works on all versions of futures.
+            ReceiverInner::Futures(rx) => match rx.next().now_or_never() {
+                Some(Some(value)) => Ok(value),
+                Some(None) => Err(TryRecvError::Closed),
+                None => Err(TryRecvError::Empty),
+            },
+        }
+    }
+
+    fn close(&mut self) {
+        match &mut self.inner {
+            #[cfg(feature = "tokio-rt")]
+            ReceiverInner::Tokio(rx) => rx.close(),
+            #[cfg(feature = "async-std-rt")]
+            ReceiverInner::AsyncStd(rx) => {
+                rx.close();
+            }
+            #[cfg(feature = "smol-rt")]
+            ReceiverInner::Smol(rx) => {
+                rx.close();
+            }
+            ReceiverInner::Futures(rx) => rx.close(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for UnboundedReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnboundedReceiver").finish_non_exhaustive()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+
+    /// Returns a channel of every backend enabled, together with its name.
+    fn backends<T>() -> Vec<(&'static str, UnboundedSender<T>, UnboundedReceiver<T>)> {
+        let mut backends = Vec::new();
+
+        #[cfg(feature = "tokio-rt")]
+        {
+            let (tx, rx) = ::tokio::sync::mpsc::unbounded_channel();
+            backends.push(("tokio", SenderInner::Tokio(tx), ReceiverInner::Tokio(rx)));
+        }
+        #[cfg(feature = "async-std-rt")]
+        {
+            let (tx, rx) = ::async_std::channel::unbounded();
+            backends.push(("async-std", SenderInner::AsyncStd(tx), ReceiverInner::AsyncStd(Box::pin(rx))));
+        }
+        #[cfg(feature = "smol-rt")]
+        {
+            let (tx, rx) = ::smol::channel::unbounded();
+            backends.push(("smol", SenderInner::Smol(tx), ReceiverInner::Smol(Box::pin(rx))));
+        }
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        backends.push(("futures", SenderInner::Futures(tx), ReceiverInner::Futures(rx)));
+
+        backends.into_iter()
+            .map(|(name, tx, rx)| (name, UnboundedSender { inner: tx }, UnboundedReceiver { inner: rx }))
+            .collect()
+    }
+
+    #[test]
+    fn values_arrive_in_order() {
+        for (name, tx, mut rx) in backends() {
+            let other = tx.clone();
+            for value in 0..100 {
+                match value % 2 {
+                    0 => tx.send(value).unwrap(),
+                    _ => other.send(value).unwrap(),
+                }
+            }
+            drop((tx, other));
+
+            let received: Vec<i32> = block_on(async {
+                let mut received = Vec::new();
+                while let Some(value) = rx.recv().await {
+                    received.push(value);
+                }
+                received
+            });
+
+            assert_eq!(received, (0..100).collect::<Vec<_>>(), "{}", name);
+            assert_eq!(rx.try_recv(), Err(TryRecvError::Closed), "{}", name);
+        }
+    }
+
+    #[test]
+    fn sending_fails_once_the_receiver_is_gone() {
+        for (name, tx, mut rx) in backends() {
+            assert_eq!(rx.try_recv(), Err(TryRecvError::Empty), "{}", name);
+
+            // Closing keeps the values which have been sent before.
+            tx.send(1).unwrap();
+            rx.close();
+            assert!(tx.is_closed(), "{}", name);
+            assert!(tx.send(2) == Err(SendError(2)), "{}", name);
+            assert_eq!(block_on(rx.recv()), Some(1), "{}", name);
+            assert_eq!(block_on(rx.recv()), None, "{}", name);
+
+            drop(rx);
+            assert!(tx.send(3) == Err(SendError(3)), "{}", name);
+            assert!(tx.try_send(4) == Err(TrySendError::Closed(4)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn receivers_are_streams() {
+        for (name, tx, rx) in backends() {
+            let producer = std::thread::spawn(move || {
+                for value in 0..10 {
+                    tx.send(value).unwrap();
+                }
+            });
+
+            let doubled: Vec<i32> = block_on(rx.map(|value| value * 2).collect());
+            producer.join().unwrap();
+
+            assert_eq!(doubled, (0..10).map(|value| value * 2).collect::<Vec<_>>(), "{}", name);
+        }
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_context_picks_the_tokio_channel() {
+        let runtime = ::tokio::runtime::Runtime::new().unwrap();
+
+        let (tx, _rx) = runtime.block_on(async { unbounded::<()>() });
+
+        assert!(matches!(tx.inner, SenderInner::Tokio(_)));
+    }
+}
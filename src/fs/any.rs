@@ -0,0 +1,568 @@
+use super::*;
+
+use std::fmt;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+
+use crate::runtime::UnknownBackendError;
+
+
+
+/// Calls the associated function `$name` of the [`Filesystem`] of the variant of `$fs`.
+macro_rules! dispatch {
+    ($fs:expr, $name:ident($($arg:expr),*)) => {
+        match $fs {
+            #[cfg(feature = "tokio-rt")]
+            AnyFs::Tokio(_) => TokioFs::$name($($arg),*).await,
+            #[cfg(feature = "async-std-rt")]
+            AnyFs::AsyncStd(_) => AsyncStdFs::$name($($arg),*).await,
+            #[cfg(all(feature = "uring", target_os = "linux"))]
+            AnyFs::Uring(_) => UringFs::$name($($arg),*).await,
+        }
+    };
+}
+
+
+
+/// A [`Filesystem`] chosen at runtime, e.g. from a configuration file.
+///
+/// As the functions of [`Filesystem`] take no `self`, they are provided as methods here, which
+/// call the ones of the filesystem of the variant.
+///
+/// It can be parsed from the name of a backend, which is `tokio`, `async-std` or `uring`. Parsing
+/// fails for the names of backends whose feature is not enabled.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "tokio-rt")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// use fut_compat::fs::AnyFs;
+///
+/// let fs: AnyFs = "tokio".parse()?;
+///
+/// let dir = std::env::temp_dir().join("fut-compat-any-fs");
+/// fs.create_dir_all(&dir).await?;
+/// fs.write(dir.join("hello.txt"), "hello").await?;
+///
+/// assert_eq!(fs.read_to_string(dir.join("hello.txt")).await?, "hello");
+///
+/// fs.remove_file(dir.join("hello.txt")).await?;
+/// fs.remove_dir(&dir).await?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # }).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AnyFs {
+    /// The filesystem of [`tokio`](https://docs.rs/tokio).
+    #[cfg(feature = "tokio-rt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
+    Tokio(TokioFs),
+    /// The filesystem of [`async_std`](https://docs.rs/async-std).
+    #[cfg(feature = "async-std-rt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
+    AsyncStd(AsyncStdFs),
+    /// The filesystem using io_uring.
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "uring", target_os = "linux"))))]
+    Uring(UringFs),
+}
+
+impl AnyFs {
+    /// See [`Filesystem::canonicalize`].
+    pub async fn canonicalize<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<PathBuf> {
+        dispatch!(self, canonicalize(path))
+    }
+
+    /// See [`Filesystem::copy`].
+    pub async fn copy<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        &self,
+        from: S,
+        to: D,
+    ) -> std::io::Result<u64> {
+        dispatch!(self, copy(from, to))
+    }
+
+    /// See [`Filesystem::copy_reflink`].
+    pub async fn copy_reflink<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        &self,
+        from: S,
+        to: D,
+    ) -> std::io::Result<ReflinkOutcome> {
+        dispatch!(self, copy_reflink(from, to))
+    }
+
+    /// See [`Filesystem::create_dir`].
+    pub async fn create_dir<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        dispatch!(self, create_dir(path))
+    }
+
+    /// See [`Filesystem::create_dir_all`].
+    pub async fn create_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        dispatch!(self, create_dir_all(path))
+    }
+
+    /// See [`Filesystem::create_dir_with`].
+    pub async fn create_dir_with<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        options: DirOptions,
+    ) -> std::io::Result<()> {
+        dispatch!(self, create_dir_with(path, options))
+    }
+
+    /// See [`Filesystem::create_dir_all_with`].
+    pub async fn create_dir_all_with<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        options: DirOptions,
+    ) -> std::io::Result<()> {
+        dispatch!(self, create_dir_all_with(path, options))
+    }
+
+    /// See [`Filesystem::hard_link`].
+    pub async fn hard_link<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        &self,
+        from: S,
+        to: D,
+    ) -> std::io::Result<()> {
+        dispatch!(self, hard_link(from, to))
+    }
+
+    /// See [`Filesystem::metadata`].
+    pub async fn metadata<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<Metadata> {
+        dispatch!(self, metadata(path))
+    }
+
+    /// See [`Filesystem::read`].
+    pub async fn read<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<Vec<u8>> {
+        dispatch!(self, read(path))
+    }
+
+    /// See [`Filesystem::read_dir`].
+    pub async fn read_dir<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<AnyReadDir> {
+        let inner = match self {
+            #[cfg(feature = "tokio-rt")]
+            AnyFs::Tokio(_) => ReadDirInner::Tokio(TokioFs::read_dir(path).await?),
+            #[cfg(feature = "async-std-rt")]
+            AnyFs::AsyncStd(_) => ReadDirInner::AsyncStd(AsyncStdFs::read_dir(path).await?),
+            #[cfg(all(feature = "uring", target_os = "linux"))]
+            AnyFs::Uring(_) => ReadDirInner::Uring(UringFs::read_dir(path).await?),
+        };
+
+        Ok(AnyReadDir { inner })
+    }
+
+    /// See [`Filesystem::read_if_modified_since`].
+    pub async fn read_if_modified_since<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        since: SystemTime,
+    ) -> std::io::Result<Option<(Vec<u8>, SystemTime)>> {
+        dispatch!(self, read_if_modified_since(path, since))
+    }
+
+    /// See [`Filesystem::read_link`].
+    pub async fn read_link<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<PathBuf> {
+        dispatch!(self, read_link(path))
+    }
+
+    /// See [`Filesystem::read_link_canonical`].
+    pub async fn read_link_canonical<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        max_hops: u32,
+    ) -> std::io::Result<PathBuf> {
+        dispatch!(self, read_link_canonical(path, max_hops))
+    }
+
+    /// See [`Filesystem::read_to_string`].
+    pub async fn read_to_string<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<String> {
+        dispatch!(self, read_to_string(path))
+    }
+
+    /// See [`Filesystem::remove_dir`].
+    pub async fn remove_dir<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        dispatch!(self, remove_dir(path))
+    }
+
+    /// See [`Filesystem::remove_dir_all`].
+    pub async fn remove_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        dispatch!(self, remove_dir_all(path))
+    }
+
+    /// See [`Filesystem::remove_dir_contents`].
+    pub async fn remove_dir_contents<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        dispatch!(self, remove_dir_contents(path))
+    }
+
+    /// See [`Filesystem::remove_file`].
+    pub async fn remove_file<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        dispatch!(self, remove_file(path))
+    }
+
+    /// See [`Filesystem::rename`].
+    pub async fn rename<O: AsRef<Path> + Send, N: AsRef<Path> + Send>(
+        &self,
+        from: O,
+        to: N,
+    ) -> std::io::Result<()> {
+        dispatch!(self, rename(from, to))
+    }
+
+    /// See [`Filesystem::set_permissions`].
+    pub async fn set_permissions<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        perm: Permissions,
+    ) -> std::io::Result<()> {
+        dispatch!(self, set_permissions(path, perm))
+    }
+
+    /// See [`Filesystem::symlink_metadata`].
+    pub async fn symlink_metadata<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<Metadata> {
+        dispatch!(self, symlink_metadata(path))
+    }
+
+    /// See [`Filesystem::touch`].
+    pub async fn touch<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        dispatch!(self, touch(path))
+    }
+
+    /// See [`Filesystem::write`].
+    pub async fn write<P: AsRef<Path> + Send, C: AsRef<[u8]> + Send>(
+        &self,
+        path: P,
+        contents: C,
+    ) -> std::io::Result<()> {
+        dispatch!(self, write(path, contents))
+    }
+
+    /// See [`Filesystem::write_vectored`].
+    pub async fn write_vectored<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        bufs: &[IoSlice<'_>],
+    ) -> std::io::Result<()> {
+        dispatch!(self, write_vectored(path, bufs))
+    }
+
+    /// See [`Filesystem::write_bytes`].
+    #[cfg(feature = "bytes")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+    pub async fn write_bytes<P: AsRef<Path> + Send, B: Buf + Send>(
+        &self,
+        path: P,
+        buf: B,
+    ) -> std::io::Result<()> {
+        dispatch!(self, write_bytes(path, buf))
+    }
+}
+
+impl FromStr for AnyFs {
+    type Err = UnknownBackendError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            #[cfg(feature = "tokio-rt")]
+            "tokio" => Ok(Self::Tokio(TokioFs::default())),
+            #[cfg(feature = "async-std-rt")]
+            "async-std" | "async_std" => Ok(Self::AsyncStd(AsyncStdFs::default())),
+            #[cfg(all(feature = "uring", target_os = "linux"))]
+            "uring" => Ok(Self::Uring(UringFs::default())),
+            _ => Err(UnknownBackendError::new(name)),
+        }
+    }
+}
+
+#[cfg(feature = "tokio-rt")]
+impl From<TokioFs> for AnyFs {
+    fn from(fs: TokioFs) -> Self {
+        Self::Tokio(fs)
+    }
+}
+
+#[cfg(feature = "async-std-rt")]
+impl From<AsyncStdFs> for AnyFs {
+    fn from(fs: AsyncStdFs) -> Self {
+        Self::AsyncStd(fs)
+    }
+}
+
+#[cfg(all(feature = "uring", target_os = "linux"))]
+impl From<UringFs> for AnyFs {
+    fn from(fs: UringFs) -> Self {
+        Self::Uring(fs)
+    }
+}
+
+
+
+/// The entries of a directory read by [`AnyFs::read_dir`].
+pub struct AnyReadDir {
+    inner: ReadDirInner,
+}
+
+enum ReadDirInner {
+    #[cfg(feature = "tokio-rt")]
+    Tokio(<TokioFs as Filesystem>::ReadDir),
+    #[cfg(feature = "async-std-rt")]
+    AsyncStd(<AsyncStdFs as Filesystem>::ReadDir),
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    Uring(<UringFs as Filesystem>::ReadDir),
+}
+
+impl Stream for AnyReadDir {
+    type Item = std::io::Result<AnyDirEntry>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let entry = match &mut Pin::into_inner(self).inner {
+            #[cfg(feature = "tokio-rt")]
+            ReadDirInner::Tokio(read_dir) => Pin::new(read_dir)
+                .poll_next(cx)
+                .map_ok(DirEntryInner::Tokio),
+            #[cfg(feature = "async-std-rt")]
+            ReadDirInner::AsyncStd(read_dir) => Pin::new(read_dir)
+                .poll_next(cx)
+                .map_ok(DirEntryInner::AsyncStd),
+            #[cfg(all(feature = "uring", target_os = "linux"))]
+            ReadDirInner::Uring(read_dir) => Pin::new(read_dir)
+                .poll_next(cx)
+                .map_ok(DirEntryInner::Uring),
+        };
+
+        entry.map_ok(|inner| AnyDirEntry { inner })
+    }
+}
+
+impl fmt::Debug for AnyReadDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnyReadDir").finish_non_exhaustive()
+    }
+}
+
+
+
+/// An entry of a directory read by [`AnyFs::read_dir`].
+pub struct AnyDirEntry {
+    inner: DirEntryInner,
+}
+
+enum DirEntryInner {
+    #[cfg(feature = "tokio-rt")]
+    Tokio(<TokioFs as Filesystem>::DirEntry),
+    #[cfg(feature = "async-std-rt")]
+    AsyncStd(<AsyncStdFs as Filesystem>::DirEntry),
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    Uring(<UringFs as Filesystem>::DirEntry),
+}
+
+#[async_trait]
+impl DirEntry for AnyDirEntry {
+    fn path(&self) -> PathBuf {
+        match &self.inner {
+            #[cfg(feature = "tokio-rt")]
+            DirEntryInner::Tokio(entry) => DirEntry::path(entry),
+            #[cfg(feature = "async-std-rt")]
+            DirEntryInner::AsyncStd(entry) => DirEntry::path(entry),
+            #[cfg(all(feature = "uring", target_os = "linux"))]
+            DirEntryInner::Uring(entry) => DirEntry::path(entry),
+        }
+    }
+
+    fn file_name(&self) -> OsString {
+        match &self.inner {
+            #[cfg(feature = "tokio-rt")]
+            DirEntryInner::Tokio(entry) => DirEntry::file_name(entry),
+            #[cfg(feature = "async-std-rt")]
+            DirEntryInner::AsyncStd(entry) => DirEntry::file_name(entry),
+            #[cfg(all(feature = "uring", target_os = "linux"))]
+            DirEntryInner::Uring(entry) => DirEntry::file_name(entry),
+        }
+    }
+
+    async fn metadata(&self) -> std::io::Result<Metadata> {
+        match &self.inner {
+            #[cfg(feature = "tokio-rt")]
+            DirEntryInner::Tokio(entry) => DirEntry::metadata(entry).await,
+            #[cfg(feature = "async-std-rt")]
+            DirEntryInner::AsyncStd(entry) => DirEntry::metadata(entry).await,
+            #[cfg(all(feature = "uring", target_os = "linux"))]
+            DirEntryInner::Uring(entry) => DirEntry::metadata(entry).await,
+        }
+    }
+
+    async fn file_type(&self) -> std::io::Result<FileType> {
+        match &self.inner {
+            #[cfg(feature = "tokio-rt")]
+            DirEntryInner::Tokio(entry) => DirEntry::file_type(entry).await,
+            #[cfg(feature = "async-std-rt")]
+            DirEntryInner::AsyncStd(entry) => DirEntry::file_type(entry).await,
+            #[cfg(all(feature = "uring", target_os = "linux"))]
+            DirEntryInner::Uring(entry) => DirEntry::file_type(entry).await,
+        }
+    }
+}
+
+impl fmt::Debug for AnyDirEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnyDirEntry")
+            .field("path", &self.path())
+            .finish_non_exhaustive()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::stream::TryStreamExt;
+
+    use super::super::tests::ScratchDir;
+
+    /// Runs through the operations of [`Filesystem`], checking that `fs` passes them on.
+    async fn conformance(fs: AnyFs) {
+        let scratch = ScratchDir::new("any-fs");
+        let dir = scratch.join("dir");
+
+        fs.create_dir_all(dir.join("sub/deeper")).await.unwrap();
+        let err = fs.create_dir(dir.join("sub")).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+
+        fs.write(dir.join("a.txt"), "hello").await.unwrap();
+        assert_eq!(fs.read(dir.join("a.txt")).await.unwrap(), b"hello");
+        assert_eq!(fs.read_to_string(dir.join("a.txt")).await.unwrap(), "hello");
+
+        let metadata = fs.metadata(dir.join("a.txt")).await.unwrap();
+        assert!(metadata.is_file());
+        assert_eq!(metadata.len(), 5);
+
+        fs.write_vectored(dir.join("b.txt"), &[IoSlice::new(b"hel"), IoSlice::new(b"lo")]).await.unwrap();
+        assert_eq!(fs.read_to_string(dir.join("b.txt")).await.unwrap(), "hello");
+
+        assert_eq!(fs.copy(dir.join("a.txt"), dir.join("c.txt")).await.unwrap(), 5);
+        fs.rename(dir.join("c.txt"), dir.join("d.txt")).await.unwrap();
+        let err = fs.metadata(dir.join("c.txt")).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(fs.read_to_string(dir.join("d.txt")).await.unwrap(), "hello");
+
+        fs.hard_link(dir.join("a.txt"), dir.join("e.txt")).await.unwrap();
+        assert_eq!(fs.read_to_string(dir.join("e.txt")).await.unwrap(), "hello");
+
+        assert_eq!(
+            fs.canonicalize(dir.join("sub/../a.txt")).await.unwrap(),
+            std::fs::canonicalize(dir.join("a.txt")).unwrap(),
+        );
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("a.txt", dir.join("f")).unwrap();
+
+            assert!(fs.symlink_metadata(dir.join("f")).await.unwrap().file_type().is_symlink());
+            assert_eq!(fs.read_link(dir.join("f")).await.unwrap(), Path::new("a.txt"));
+            assert_eq!(fs.read_link_canonical(dir.join("f"), 8).await.unwrap(), dir.join("a.txt"));
+        }
+
+        let mut permissions = fs.metadata(dir.join("d.txt")).await.unwrap().permissions();
+        permissions.set_readonly(true);
+        fs.set_permissions(dir.join("d.txt"), permissions).await.unwrap();
+        assert!(fs.metadata(dir.join("d.txt")).await.unwrap().permissions().readonly());
+
+        fs.touch(dir.join("g.txt")).await.unwrap();
+        assert_eq!(fs.read(dir.join("g.txt")).await.unwrap(), b"");
+
+        let (contents, _) = fs.read_if_modified_since(dir.join("a.txt"), SystemTime::UNIX_EPOCH)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(contents, b"hello");
+
+        let mut entries = Vec::new();
+        let mut read_dir = fs.read_dir(&dir).await.unwrap();
+        while let Some(entry) = read_dir.try_next().await.unwrap() {
+            assert_eq!(entry.path(), dir.join(entry.file_name()));
+            let file_type = entry.file_type().await.unwrap();
+            assert_eq!(entry.metadata().await.unwrap().is_dir(), file_type.is_dir());
+
+            entries.push((entry.file_name().into_string().unwrap(), file_type.is_dir()));
+        }
+        entries.sort();
+
+        let mut expected = vec![
+            ("a.txt".to_string(), false),
+            ("b.txt".to_string(), false),
+            ("d.txt".to_string(), false),
+            ("e.txt".to_string(), false),
+            ("g.txt".to_string(), false),
+            ("sub".to_string(), true),
+        ];
+        if cfg!(unix) {
+            expected.insert(4, ("f".to_string(), false));
+        }
+        assert_eq!(entries, expected);
+
+        fs.remove_file(dir.join("e.txt")).await.unwrap();
+        fs.remove_dir(dir.join("sub/deeper")).await.unwrap();
+        assert!(fs.metadata(dir.join("sub")).await.unwrap().is_dir());
+
+        let mut permissions = fs.metadata(dir.join("d.txt")).await.unwrap().permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs.set_permissions(dir.join("d.txt"), permissions).await.unwrap();
+
+        fs.remove_dir_contents(&dir).await.unwrap();
+        assert!(fs.read_dir(&dir).await.unwrap().try_next().await.unwrap().is_none());
+
+        fs.create_dir_all(dir.join("sub")).await.unwrap();
+        fs.remove_dir_all(&dir).await.unwrap();
+        let err = fs.metadata(&dir).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_passes_the_conformance_suite() {
+        super::super::tests::on_tokio(conformance("tokio".parse().unwrap()));
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_passes_the_conformance_suite() {
+        super::super::tests::on_async_std(conformance("async-std".parse().unwrap()));
+    }
+
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    #[test]
+    fn uring_passes_the_conformance_suite() {
+        super::super::tests::on_uring(conformance("uring".parse().unwrap()));
+    }
+
+    #[test]
+    fn enabled_backends_get_parsed_by_name() {
+        #[cfg(feature = "tokio-rt")]
+        assert_eq!("tokio".parse::<AnyFs>(), Ok(AnyFs::Tokio(TokioFs::default())));
+        #[cfg(feature = "async-std-rt")]
+        {
+            assert_eq!("async-std".parse::<AnyFs>(), Ok(AnyFs::AsyncStd(AsyncStdFs::default())));
+            assert_eq!("async_std".parse::<AnyFs>(), Ok(AnyFs::AsyncStd(AsyncStdFs::default())));
+        }
+        #[cfg(all(feature = "uring", target_os = "linux"))]
+        assert_eq!("uring".parse::<AnyFs>(), Ok(AnyFs::Uring(UringFs::default())));
+        #[cfg(not(all(feature = "uring", target_os = "linux")))]
+        assert_eq!("uring".parse::<AnyFs>(), Err(UnknownBackendError::new("uring")));
+    }
+
+    #[test]
+    fn unknown_backends_are_refused() {
+        for name in ["", "Tokio", " tokio", "smol", "std"] {
+            let err = name.parse::<AnyFs>().unwrap_err();
+
+            assert_eq!(err.name(), name);
+            assert_eq!(err.to_string(), format!("unknown backend `{}`, or its feature is not enabled", name));
+        }
+    }
+}
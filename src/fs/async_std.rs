@@ -39,6 +39,16 @@ impl Filesystem for AsyncStdFs {
         fs::copy(from, to).await
     }
 
+    async fn copy_reflink<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        from: S,
+        to: D,
+    ) -> std::io::Result<ReflinkOutcome> {
+        let from = from.as_ref().to_owned();
+        let to = to.as_ref().to_owned();
+
+        ::async_std::task::spawn_blocking(move || reflink::copy_reflink(&from, &to)).await
+    }
+
     async fn create_dir<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
         let path = path.as_ref();
         let path: &Path = path.into();
@@ -53,6 +63,32 @@ impl Filesystem for AsyncStdFs {
         fs::create_dir_all(path).await
     }
 
+    async fn create_dir_with<P: AsRef<Path> + Send>(
+        path: P,
+        options: DirOptions,
+    ) -> std::io::Result<()> {
+        #[cfg(unix)]
+        let builder = {
+            let mut builder = fs::DirBuilder::new();
+
+            if let Some(mode) = options.get_mode() {
+                use ::async_std::os::unix::fs::DirBuilderExt;
+
+                builder.mode(mode);
+            }
+
+            builder
+        };
+        #[cfg(not(unix))]
+        let builder = {
+            let _ = options;
+
+            fs::DirBuilder::new()
+        };
+
+        builder.create(path.as_ref()).await
+    }
+
     async fn hard_link<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
         from: S,
         to: D,
@@ -87,6 +123,15 @@ impl Filesystem for AsyncStdFs {
         fs::read_dir(path).await
     }
 
+    async fn read_if_modified_since<P: AsRef<Path> + Send>(
+        path: P,
+        since: SystemTime,
+    ) -> std::io::Result<Option<(Vec<u8>, SystemTime)>> {
+        let path = path.as_ref().to_owned();
+
+        ::async_std::task::spawn_blocking(move || read_if_modified_since_blocking(&path, since)).await
+    }
+
     async fn read_link<P: AsRef<Path> + Send>(path: P) -> std::io::Result<PathBuf> {
         let path = path.as_ref();
         let path: &Path = path.into();
@@ -155,6 +200,12 @@ impl Filesystem for AsyncStdFs {
         fs::symlink_metadata(path).await
     }
 
+    async fn touch<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref().to_owned();
+
+        ::async_std::task::spawn_blocking(move || touch_blocking(&path)).await
+    }
+
     async fn write<P: AsRef<Path> + Send, C: AsRef<[u8]> + Send>(
         path: P,
         contents: C
@@ -164,10 +215,53 @@ impl Filesystem for AsyncStdFs {
 
         fs::write(path, contents).await
     }
+
+    async fn write_vectored<P: AsRef<Path> + Send>(
+        path: P,
+        bufs: &[IoSlice<'_>],
+    ) -> std::io::Result<()> {
+        use futures::io::AsyncWriteExt;
+
+        let mut file = fs::File::create(path.as_ref()).await?;
+
+        crate::io::write_all_vectored(&mut file, &mut bufs.to_vec()).await?;
+
+        // Writes of async-std's files complete in the background, flushing waits for them.
+        file.flush().await
+    }
 }
 
 
 
+#[cfg(unix)]
+#[async_trait]
+impl SecureOpen for AsyncStdFs {
+    type File = fs::File;
+
+    async fn open_no_follow<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self::File> {
+        use ::async_std::os::unix::fs::OpenOptionsExt;
+
+        fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(path.as_ref())
+            .await
+    }
+
+    async fn open_beneath<R, P>(root: R, path: P) -> std::io::Result<Self::File>
+    where
+        R: AsRef<Path> + Send,
+        P: AsRef<Path> + Send,
+    {
+        let root = root.as_ref().to_owned();
+        let path = path.as_ref().to_owned();
+
+        let file = ::async_std::task::spawn_blocking(move || beneath::open_beneath(&root, &path)).await?;
+
+        Ok(fs::File::from(file))
+    }
+}
+
 #[async_trait]
 impl DirEntry for fs::DirEntry {
     fn path(&self) -> PathBuf {
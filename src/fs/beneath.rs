@@ -0,0 +1,257 @@
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::path::{Component, Path};
+
+
+
+/// The error returned when `path` resolves to a location outside of the root directory.
+fn escapes_root(path: &Path) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        format!("`{}` escapes the root directory", path.display()),
+    )
+}
+
+/// Opens `path` for reading, refusing to resolve symbolic links in any of its components and
+/// to leave `root` through absolute paths or `..` components.
+///
+/// This blocks, so it has to run on the blocking pool of the runtime.
+pub(super) fn open_beneath(root: &Path, path: &Path) -> std::io::Result<File> {
+    let root = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECTORY)
+        .open(root)?;
+
+    #[cfg(target_os = "linux")]
+    match openat2(&root, path) {
+        // The kernel is older than 5.6, or a seccomp filter denies the syscall.
+        Err(err) if matches!(err.raw_os_error(), Some(libc::ENOSYS | libc::EPERM)) => {}
+        Err(err) if err.raw_os_error() == Some(libc::EXDEV) => return Err(escapes_root(path)),
+        result => return result,
+    }
+
+    walk(root, path)
+}
+
+/// Opens `path` relative to `dir` with `openat2`, letting the kernel enforce the restrictions.
+#[cfg(target_os = "linux")]
+fn openat2(dir: &File, path: &Path) -> std::io::Result<File> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+
+    // SAFETY: `open_how` only consists of integers, for which all zeroes are valid.
+    let mut how: libc::open_how = unsafe { std::mem::zeroed() };
+    how.flags = (libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOCTTY) as u64;
+    how.resolve = libc::RESOLVE_BENEATH | libc::RESOLVE_NO_SYMLINKS;
+
+    // SAFETY: The descriptor and the string stay valid for the duration of the call, and `how`
+    // is passed along with its size.
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_openat2,
+            dir.as_raw_fd(),
+            path.as_ptr(),
+            &how as *const libc::open_how,
+            std::mem::size_of::<libc::open_how>(),
+        )
+    };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    // SAFETY: The descriptor has just been opened and is owned by nothing else.
+    Ok(unsafe { File::from_raw_fd(fd as RawFd) })
+}
+
+/// Opens `name` relative to `dir` without following a symbolic link in its place.
+fn openat(dir: &File, name: &Path, flags: libc::c_int) -> std::io::Result<File> {
+    let name = CString::new(name.as_os_str().as_bytes())?;
+    let flags = flags | libc::O_RDONLY | libc::O_NOFOLLOW | libc::O_CLOEXEC | libc::O_NOCTTY;
+
+    // SAFETY: The descriptor and the string stay valid for the duration of the call.
+    let fd = unsafe { libc::openat(dir.as_raw_fd(), name.as_ptr(), flags) };
+    if fd < 0 {
+        let err = std::io::Error::last_os_error();
+
+        // Opening a symbolic link with `O_DIRECTORY` fails with `ENOTDIR` on some platforms,
+        // report it like a symbolic link in the last component.
+        if err.raw_os_error() == Some(libc::ENOTDIR) && is_symlink(dir, &name) {
+            return Err(std::io::Error::from_raw_os_error(libc::ELOOP));
+        }
+
+        return Err(err);
+    }
+
+    // SAFETY: The descriptor has just been opened and is owned by nothing else.
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Returns `true` if `name` within `dir` is a symbolic link.
+fn is_symlink(dir: &File, name: &CStr) -> bool {
+    let mut stat = MaybeUninit::<libc::stat>::uninit();
+
+    // SAFETY: The descriptor and the string stay valid for the duration of the call, and `stat`
+    // provides enough space for the result.
+    let res = unsafe {
+        libc::fstatat(dir.as_raw_fd(), name.as_ptr(), stat.as_mut_ptr(), libc::AT_SYMLINK_NOFOLLOW)
+    };
+    if res != 0 {
+        return false;
+    }
+
+    // SAFETY: `fstatat` succeeded, so it initialized `stat`.
+    let stat = unsafe { stat.assume_init() };
+
+    stat.st_mode & libc::S_IFMT == libc::S_IFLNK
+}
+
+/// Opens `path` relative to `root` one component at a time, which works on every unix.
+fn walk(root: File, path: &Path) -> std::io::Result<File> {
+    let components: Vec<Component<'_>> = path.components().collect();
+    if components.is_empty() {
+        return Err(std::io::Error::from_raw_os_error(libc::ENOENT));
+    }
+
+    // The directories opened so far, to return to on `..`.
+    let mut dirs = vec![root];
+
+    for (i, component) in components.iter().enumerate() {
+        match component {
+            Component::Normal(name) => {
+                let dir = dirs.last().expect("the root is never removed");
+                let last = i + 1 == components.len();
+
+                dirs.push(openat(dir, Path::new(name), if last { 0 } else { libc::O_DIRECTORY })?);
+            }
+            Component::CurDir => {}
+            Component::ParentDir if dirs.len() > 1 => {
+                dirs.pop();
+            }
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(escapes_root(path));
+            }
+        }
+    }
+
+    Ok(dirs.pop().expect("the root is never removed"))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{ErrorKind, Read};
+    use std::path::PathBuf;
+
+    use super::super::tests::ScratchDir;
+
+    /// Sets up `root/dir/file`, `root/dir/link -> file`, `root/dirlink -> dir` and a file next to
+    /// `root`, returning the scratch directory and the path of `root`.
+    fn tree() -> (ScratchDir, PathBuf) {
+        let scratch = ScratchDir::new("beneath");
+        let root = scratch.join("root");
+
+        std::fs::create_dir_all(root.join("dir")).unwrap();
+        std::fs::write(root.join("dir/file"), b"inside").unwrap();
+        std::os::unix::fs::symlink("file", root.join("dir/link")).unwrap();
+        std::os::unix::fs::symlink("dir", root.join("dirlink")).unwrap();
+        std::fs::write(scratch.join("outside"), b"outside").unwrap();
+
+        (scratch, root)
+    }
+
+    fn open_root(root: &Path) -> File {
+        std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_DIRECTORY)
+            .open(root)
+            .unwrap()
+    }
+
+    fn read(mut file: File) -> String {
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+
+        contents
+    }
+
+    /// Runs the cases every strategy has to agree on through `open`.
+    fn check(root: &Path, open: impl Fn(&Path, &Path) -> std::io::Result<File>) {
+        assert_eq!(read(open(root, Path::new("dir/file")).unwrap()), "inside");
+        assert_eq!(read(open(root, Path::new("./dir/../dir/file")).unwrap()), "inside");
+
+        // A symbolic link in the final component.
+        let err = open(root, Path::new("dir/link")).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ELOOP), "{}", err);
+
+        // A symbolic link in the middle of the path.
+        let err = open(root, Path::new("dirlink/file")).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ELOOP), "{}", err);
+
+        let err = open(root, Path::new("../outside")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied, "{}", err);
+
+        let err = open(root, Path::new("dir/../../outside")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied, "{}", err);
+
+        let absolute = root.join("dir/file");
+        let err = open(root, &absolute).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied, "{}", err);
+    }
+
+    #[test]
+    fn open_beneath_refuses_symlinks_and_escapes() {
+        let (_scratch, root) = tree();
+
+        check(&root, open_beneath);
+    }
+
+    #[test]
+    fn walk_refuses_symlinks_and_escapes() {
+        let (_scratch, root) = tree();
+
+        check(&root, |root, path| walk(open_root(root), path));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn openat2_refuses_symlinks_and_escapes() {
+        let (_scratch, root) = tree();
+
+        match openat2(&open_root(&root), Path::new("dir/file")) {
+            // Not available here, `open_beneath` falls back to `walk`, which is tested above.
+            Err(err) if matches!(err.raw_os_error(), Some(libc::ENOSYS | libc::EPERM)) => return,
+            result => assert_eq!(read(result.unwrap()), "inside"),
+        }
+
+        check(&root, |root, path| {
+            openat2(&open_root(root), path).map_err(|err| match err.raw_os_error() {
+                Some(libc::EXDEV) => escapes_root(path),
+                _ => err,
+            })
+        });
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn open_no_follow_refuses_a_final_symlink() {
+        use crate::fs::{SecureOpen, TokioFs};
+
+        let (_scratch, root) = tree();
+
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            TokioFs::open_no_follow(root.join("dir/file")).await.unwrap();
+
+            let err = TokioFs::open_no_follow(root.join("dir/link")).await.unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::ELOOP), "{}", err);
+
+            let err = TokioFs::open_beneath(&root, "dirlink/file").await.unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::ELOOP), "{}", err);
+        });
+    }
+}
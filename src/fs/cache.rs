@@ -0,0 +1,876 @@
+use super::*;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::task::Timer;
+
+
+
+/// The number of paths cached by default.
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// A cached value, which is valid until `expires`.
+struct Cached<T> {
+    value: T,
+    expires: Instant,
+}
+
+/// The values cached for a path.
+#[derive(Default)]
+struct CacheEntry {
+    metadata: Option<Cached<Metadata>>,
+    symlink_metadata: Option<Cached<Metadata>>,
+    contents: Option<Cached<Vec<u8>>>,
+}
+
+impl CacheEntry {
+    fn is_empty(&self) -> bool {
+        self.metadata.is_none() && self.symlink_metadata.is_none() && self.contents.is_none()
+    }
+
+    /// Returns the instant the last of the cached values expires.
+    fn expires(&self) -> Option<Instant> {
+        let metadata = self.metadata.as_ref().map(|cached| cached.expires);
+        let symlink_metadata = self.symlink_metadata.as_ref().map(|cached| cached.expires);
+        let contents = self.contents.as_ref().map(|cached| cached.expires);
+
+        metadata.max(symlink_metadata).max(contents)
+    }
+
+    /// Removes the values which have expired at `now`.
+    fn remove_expired(&mut self, now: Instant) {
+        fn remove<T>(cached: &mut Option<Cached<T>>, now: Instant) {
+            if cached.as_ref().is_some_and(|cached| now >= cached.expires) {
+                *cached = None;
+            }
+        }
+
+        remove(&mut self.metadata, now);
+        remove(&mut self.symlink_metadata, now);
+        remove(&mut self.contents, now);
+    }
+}
+
+/// Selects one of the values of a [`CacheEntry`].
+type Field<T> = fn(&mut CacheEntry) -> &mut Option<Cached<T>>;
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<PathBuf, CacheEntry>,
+    /// Incremented on every invalidation, so results read before it do not get cached.
+    generation: u64,
+    /// When to remove the expired values of all paths next.
+    next_sweep: Option<Instant>,
+}
+
+impl CacheState {
+    /// Removes the values which have expired at `now`, and the paths left without any.
+    fn sweep(&mut self, now: Instant) {
+        self.entries.retain(|_, entry| {
+            entry.remove_expired(now);
+
+            !entry.is_empty()
+        });
+    }
+
+    /// Removes the path whose values expire first, other than `keep`.
+    fn evict(&mut self, keep: &Path) {
+        let oldest = self.entries
+            .iter()
+            .filter(|(path, _)| path.as_path() != keep)
+            .min_by_key(|(_, entry)| entry.expires())
+            .map(|(path, _)| path.clone());
+
+        if let Some(path) = oldest {
+            self.entries.remove(&path);
+        }
+    }
+}
+
+/// Wraps the [`Filesystem`] `F` and caches the results of [`metadata`](Self::metadata) and
+/// [`symlink_metadata`](Self::symlink_metadata), as well as the contents of small files, e.g. for
+/// serving static files.
+///
+/// The cached values expire after a time to live, measured with the timer `Tm`. Files are cached
+/// by [`read`](Self::read) and [`read_to_string`](Self::read_to_string) if they are at most
+/// `max_file_size` bytes large.
+///
+/// Writing, renaming, removing or otherwise changing files through the wrapper invalidates the
+/// values cached for the paths involved, everything below them and their parent directories.
+/// Changes made in other ways only become visible once the cached values expire, or after
+/// [`invalidate`](Self::invalidate) or [`clear`](Self::clear).
+///
+/// Paths are cached as they are given, so different paths to the same file, e.g. `a.txt` and
+/// `./a.txt`, are cached separately.
+///
+/// At most [`max_entries`](Self::max_entries) paths are cached, 10000 by default. When caching
+/// another path, the values of the path expiring first get evicted. Besides, expired values are
+/// removed from all paths once per time to live, so paths which are not looked up again do not
+/// linger in memory.
+///
+/// As the functions of [`Filesystem`] take no `self`, they are provided as methods here, which
+/// call the ones of `F` unless the value is cached.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "tokio-rt")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// use std::time::Duration;
+///
+/// use fut_compat::fs::{CacheFs, TokioFs};
+/// use fut_compat::task::TokioExecutor;
+///
+/// let fs = CacheFs::<TokioFs, TokioExecutor>::new(Duration::from_secs(10), 64 * 1024);
+///
+/// let path = std::env::temp_dir().join("fut-compat-cache-fs.html");
+/// fs.write(&path, "<h1>hello</h1>").await?;
+///
+/// // The first read gets cached, the second one is served from memory.
+/// assert_eq!(fs.read(&path).await?, b"<h1>hello</h1>");
+/// assert_eq!(fs.read(&path).await?, b"<h1>hello</h1>");
+///
+/// fs.remove_file(&path).await?;
+/// # Ok::<(), std::io::Error>(())
+/// # }).unwrap();
+/// ```
+pub struct CacheFs<F, Tm> {
+    ttl: Duration,
+    max_file_size: u64,
+    max_entries: usize,
+    state: Mutex<CacheState>,
+    _marker: PhantomData<fn() -> (F, Tm)>,
+}
+
+impl<F, Tm> CacheFs<F, Tm> {
+    /// Creates a new instance, which caches values for `ttl` and the contents of files of at most
+    /// `max_file_size` bytes.
+    pub fn new(ttl: Duration, max_file_size: u64) -> Self {
+        Self {
+            ttl,
+            max_file_size,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            state: Mutex::new(CacheState::default()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the time to live of the cached values.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns the size up to which the contents of files get cached.
+    pub fn max_file_size(&self) -> u64 {
+        self.max_file_size
+    }
+
+    /// Returns the maximum number of paths cached at the same time.
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Sets the maximum number of paths cached at the same time, evicting the ones expiring
+    /// first when caching another path.
+    ///
+    /// The number must be at least 1.
+    pub fn set_max_entries(&mut self, max_entries: usize) {
+        self.max_entries = max_entries.max(1);
+    }
+
+    /// Removes the values cached for `path` and everything below it.
+    pub fn invalidate<P: AsRef<Path>>(&self, path: P) {
+        let path = path.as_ref();
+        let mut state = self.state();
+
+        state.entries.retain(|cached, _| !cached.starts_with(path));
+        state.generation += 1;
+    }
+
+    /// Removes all cached values.
+    pub fn clear(&self) {
+        let mut state = self.state();
+
+        state.entries.clear();
+        state.generation += 1;
+    }
+
+    fn state(&self) -> MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Removes the values cached for `path`, everything below it and its parent directory, after
+    /// `path` got changed.
+    fn invalidate_changed(&self, path: &Path) {
+        let parent = path.parent();
+        let mut state = self.state();
+
+        state.entries.retain(|cached, _| !cached.starts_with(path) && Some(cached.as_path()) != parent);
+        state.generation += 1;
+    }
+}
+
+impl<F: Filesystem + Send, Tm: Timer> CacheFs<F, Tm> {
+    /// Returns the value of `field` cached for `path` if it has not expired yet, or the current
+    /// generation, which has to be passed to [`store`](Self::store) along with the fresh value.
+    fn lookup<T: Clone>(&self, path: &Path, field: Field<T>) -> Result<T, u64> {
+        let mut state = self.state();
+        let generation = state.generation;
+
+        let Some(entry) = state.entries.get_mut(path) else {
+            return Err(generation);
+        };
+
+        match field(entry) {
+            Some(cached) if Tm::now() < cached.expires => Ok(cached.value.clone()),
+            Some(_) => {
+                *field(entry) = None;
+                if entry.is_empty() {
+                    state.entries.remove(path);
+                }
+
+                Err(generation)
+            }
+            None => Err(generation),
+        }
+    }
+
+    /// Caches `value` as `field` of `path`, unless the cache got invalidated since `generation`.
+    ///
+    /// Sweeps the expired values once per time to live, and evicts a path if there are too many.
+    fn store<T>(&self, path: &Path, field: Field<T>, value: T, generation: u64) {
+        let mut state = self.state();
+
+        if state.generation != generation {
+            return;
+        }
+
+        let now = Tm::now();
+        if state.next_sweep.is_none_or(|next_sweep| now >= next_sweep) {
+            state.sweep(now);
+            state.next_sweep = Some(now + self.ttl);
+        }
+
+        let entry = state.entries.entry(path.to_path_buf()).or_default();
+        *field(entry) = Some(Cached {
+            value,
+            expires: now + self.ttl,
+        });
+
+        if state.entries.len() > self.max_entries {
+            state.evict(path);
+        }
+    }
+
+    /// See [`Filesystem::canonicalize`].
+    pub async fn canonicalize<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<PathBuf> {
+        F::canonicalize(path).await
+    }
+
+    /// See [`Filesystem::copy`].
+    pub async fn copy<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        &self,
+        from: S,
+        to: D,
+    ) -> std::io::Result<u64> {
+        let to = to.as_ref();
+        let result = F::copy(from, to).await;
+        self.invalidate_changed(to);
+
+        result
+    }
+
+    /// See [`Filesystem::copy_reflink`].
+    pub async fn copy_reflink<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        &self,
+        from: S,
+        to: D,
+    ) -> std::io::Result<ReflinkOutcome> {
+        let to = to.as_ref();
+        let result = F::copy_reflink(from, to).await;
+        self.invalidate_changed(to);
+
+        result
+    }
+
+    /// See [`Filesystem::create_dir`].
+    pub async fn create_dir<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let result = F::create_dir(path).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+
+    /// See [`Filesystem::create_dir_all`].
+    pub async fn create_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let result = F::create_dir_all(path).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+
+    /// See [`Filesystem::create_dir_with`].
+    pub async fn create_dir_with<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        options: DirOptions,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let result = F::create_dir_with(path, options).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+
+    /// See [`Filesystem::create_dir_all_with`].
+    pub async fn create_dir_all_with<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        options: DirOptions,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let result = F::create_dir_all_with(path, options).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+
+    /// See [`Filesystem::hard_link`].
+    ///
+    /// Invalidates the values cached for `from` as well, as its link count changes.
+    pub async fn hard_link<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        &self,
+        from: S,
+        to: D,
+    ) -> std::io::Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        let result = F::hard_link(from, to).await;
+        self.invalidate_changed(from);
+        self.invalidate_changed(to);
+
+        result
+    }
+
+    /// See [`Filesystem::metadata`]. The result is cached.
+    pub async fn metadata<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<Metadata> {
+        let path = path.as_ref();
+
+        let generation = match self.lookup(path, |entry| &mut entry.metadata) {
+            Ok(metadata) => return Ok(metadata),
+            Err(generation) => generation,
+        };
+
+        let metadata = F::metadata(path).await?;
+        self.store(path, |entry| &mut entry.metadata, metadata.clone(), generation);
+
+        Ok(metadata)
+    }
+
+    /// See [`Filesystem::read`]. The contents of files of at most `max_file_size` bytes are
+    /// cached.
+    pub async fn read<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<Vec<u8>> {
+        let path = path.as_ref();
+
+        let generation = match self.lookup(path, |entry| &mut entry.contents) {
+            Ok(contents) => return Ok(contents),
+            Err(generation) => generation,
+        };
+
+        let contents = F::read(path).await?;
+        if contents.len() as u64 <= self.max_file_size {
+            self.store(path, |entry| &mut entry.contents, contents.clone(), generation);
+        }
+
+        Ok(contents)
+    }
+
+    /// See [`Filesystem::read_dir`]. The entries are not cached.
+    pub async fn read_dir<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<F::ReadDir> {
+        F::read_dir(path).await
+    }
+
+    /// See [`Filesystem::read_if_modified_since`].
+    ///
+    /// This always asks `F`, as the answer depends on the current modification time of the file.
+    pub async fn read_if_modified_since<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        since: SystemTime,
+    ) -> std::io::Result<Option<(Vec<u8>, SystemTime)>> {
+        F::read_if_modified_since(path, since).await
+    }
+
+    /// See [`Filesystem::read_link`].
+    pub async fn read_link<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<PathBuf> {
+        F::read_link(path).await
+    }
+
+    /// See [`Filesystem::read_link_canonical`].
+    pub async fn read_link_canonical<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        max_hops: u32,
+    ) -> std::io::Result<PathBuf> {
+        F::read_link_canonical(path, max_hops).await
+    }
+
+    /// See [`Filesystem::read_to_string`]. The contents are cached like by [`read`](Self::read).
+    pub async fn read_to_string<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<String> {
+        let contents = self.read(path).await?;
+
+        String::from_utf8(contents).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    /// See [`Filesystem::remove_dir`].
+    pub async fn remove_dir<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let result = F::remove_dir(path).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+
+    /// See [`Filesystem::remove_dir_all`].
+    pub async fn remove_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let result = F::remove_dir_all(path).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+
+    /// See [`Filesystem::remove_dir_contents`].
+    pub async fn remove_dir_contents<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()>
+    where
+        F::ReadDir: Send,
+        F::DirEntry: Send,
+    {
+        let path = path.as_ref();
+        let result = F::remove_dir_contents(path).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+
+    /// See [`Filesystem::remove_file`].
+    pub async fn remove_file<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let result = F::remove_file(path).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+
+    /// See [`Filesystem::rename`].
+    pub async fn rename<O: AsRef<Path> + Send, N: AsRef<Path> + Send>(
+        &self,
+        from: O,
+        to: N,
+    ) -> std::io::Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        let result = F::rename(from, to).await;
+        self.invalidate_changed(from);
+        self.invalidate_changed(to);
+
+        result
+    }
+
+    /// See [`Filesystem::set_permissions`].
+    pub async fn set_permissions<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        perm: Permissions,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let result = F::set_permissions(path, perm).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+
+    /// See [`Filesystem::symlink_metadata`]. The result is cached.
+    pub async fn symlink_metadata<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<Metadata> {
+        let path = path.as_ref();
+
+        let generation = match self.lookup(path, |entry| &mut entry.symlink_metadata) {
+            Ok(metadata) => return Ok(metadata),
+            Err(generation) => generation,
+        };
+
+        let metadata = F::symlink_metadata(path).await?;
+        self.store(path, |entry| &mut entry.symlink_metadata, metadata.clone(), generation);
+
+        Ok(metadata)
+    }
+
+    /// See [`Filesystem::touch`].
+    pub async fn touch<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let result = F::touch(path).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+
+    /// See [`Filesystem::write`].
+    pub async fn write<P: AsRef<Path> + Send, C: AsRef<[u8]> + Send>(
+        &self,
+        path: P,
+        contents: C,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let result = F::write(path, contents).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+
+    /// See [`Filesystem::write_vectored`].
+    pub async fn write_vectored<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        bufs: &[IoSlice<'_>],
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let result = F::write_vectored(path, bufs).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+
+    /// See [`Filesystem::write_bytes`].
+    #[cfg(feature = "bytes")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+    pub async fn write_bytes<P: AsRef<Path> + Send, B: Buf + Send>(
+        &self,
+        path: P,
+        buf: B,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let result = F::write_bytes(path, buf).await;
+        self.invalidate_changed(path);
+
+        result
+    }
+}
+
+impl<F, Tm> fmt::Debug for CacheFs<F, Tm> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CacheFs")
+            .field("ttl", &self.ttl)
+            .field("max_file_size", &self.max_file_size)
+            .field("max_entries", &self.max_entries)
+            .field("entries", &self.state().entries.len())
+            .finish()
+    }
+}
+
+
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    use futures::executor::block_on;
+
+    use crate::task::MockTimer;
+
+    thread_local! {
+        /// The files of [`CountingFs`] on this thread, so tests running in parallel do not share
+        /// them.
+        static FILES: RefCell<HashMap<PathBuf, Vec<u8>>> = RefCell::new(HashMap::new());
+        /// How often [`CountingFs`] has been called on this thread, by the name of the function.
+        static CALLS: RefCell<HashMap<&'static str, usize>> = RefCell::new(HashMap::new());
+    }
+
+    fn count(name: &'static str) {
+        CALLS.with(|calls| *calls.borrow_mut().entry(name).or_default() += 1);
+    }
+
+    fn calls(name: &'static str) -> usize {
+        CALLS.with(|calls| calls.borrow().get(name).copied().unwrap_or_default())
+    }
+
+    fn not_found() -> std::io::Error {
+        std::io::Error::from(std::io::ErrorKind::NotFound)
+    }
+
+    fn unsupported<T>() -> std::io::Result<T> {
+        Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+    }
+
+    /// An in-memory filesystem which counts the calls made to it.
+    struct CountingFs;
+
+    struct NoDirEntry;
+
+    #[async_trait]
+    impl DirEntry for NoDirEntry {
+        fn path(&self) -> PathBuf {
+            PathBuf::new()
+        }
+
+        fn file_name(&self) -> std::ffi::OsString {
+            std::ffi::OsString::new()
+        }
+
+        async fn metadata(&self) -> std::io::Result<Metadata> {
+            unsupported()
+        }
+
+        async fn file_type(&self) -> std::io::Result<FileType> {
+            unsupported()
+        }
+    }
+
+    #[async_trait]
+    impl Filesystem for CountingFs {
+        type ReadDir = futures::stream::Empty<std::io::Result<NoDirEntry>>;
+        type DirEntry = NoDirEntry;
+
+        async fn canonicalize<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<PathBuf> {
+            unsupported()
+        }
+
+        async fn copy<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+            _from: S,
+            _to: D,
+        ) -> std::io::Result<u64> {
+            unsupported()
+        }
+
+        async fn create_dir<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+            unsupported()
+        }
+
+        async fn create_dir_all<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+            unsupported()
+        }
+
+        async fn hard_link<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+            _from: S,
+            _to: D,
+        ) -> std::io::Result<()> {
+            unsupported()
+        }
+
+        /// Returns the metadata of the temporary directory for every file, as metadata can not be
+        /// created otherwise.
+        async fn metadata<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Metadata> {
+            count("metadata");
+
+            match FILES.with(|files| files.borrow().contains_key(path.as_ref())) {
+                true => std::fs::metadata(std::env::temp_dir()),
+                false => Err(not_found()),
+            }
+        }
+
+        async fn read<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Vec<u8>> {
+            count("read");
+
+            FILES.with(|files| files.borrow().get(path.as_ref()).cloned()).ok_or_else(not_found)
+        }
+
+        async fn read_dir<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<Self::ReadDir> {
+            unsupported()
+        }
+
+        async fn read_link<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<PathBuf> {
+            unsupported()
+        }
+
+        async fn read_to_string<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<String> {
+            unsupported()
+        }
+
+        async fn remove_dir<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+            unsupported()
+        }
+
+        async fn remove_dir_all<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+            unsupported()
+        }
+
+        async fn remove_file<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+            FILES.with(|files| files.borrow_mut().remove(path.as_ref())).map(|_| ()).ok_or_else(not_found)
+        }
+
+        async fn rename<O: AsRef<Path> + Send, N: AsRef<Path> + Send>(
+            from: O,
+            to: N,
+        ) -> std::io::Result<()> {
+            FILES.with(|files| {
+                let mut files = files.borrow_mut();
+                let contents = files.remove(from.as_ref()).ok_or_else(not_found)?;
+                files.insert(to.as_ref().to_path_buf(), contents);
+
+                Ok(())
+            })
+        }
+
+        async fn set_permissions<P: AsRef<Path> + Send>(
+            _path: P,
+            _perm: Permissions,
+        ) -> std::io::Result<()> {
+            unsupported()
+        }
+
+        async fn symlink_metadata<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<Metadata> {
+            unsupported()
+        }
+
+        async fn write<P: AsRef<Path> + Send, C: AsRef<[u8]> + Send>(
+            path: P,
+            contents: C,
+        ) -> std::io::Result<()> {
+            FILES.with(|files| files.borrow_mut().insert(path.as_ref().to_path_buf(), contents.as_ref().to_vec()));
+
+            Ok(())
+        }
+    }
+
+    fn cache() -> CacheFs<CountingFs, MockTimer> {
+        CacheFs::new(Duration::from_secs(10), 64)
+    }
+
+    #[test]
+    fn repeated_lookups_are_served_from_the_cache() {
+        block_on(async {
+            let fs = cache();
+            CountingFs::write("a", "hello").await.unwrap();
+
+            for _ in 0..3 {
+                assert_eq!(fs.read("a").await.unwrap(), b"hello");
+                assert!(fs.metadata("a").await.is_ok());
+            }
+
+            assert_eq!((calls("read"), calls("metadata")), (1, 1));
+        });
+    }
+
+    #[test]
+    fn large_files_and_errors_are_not_cached() {
+        block_on(async {
+            let fs = cache();
+            CountingFs::write("large", [0u8; 65]).await.unwrap();
+
+            fs.read("large").await.unwrap();
+            fs.read("large").await.unwrap();
+            fs.read("missing").await.unwrap_err();
+            fs.read("missing").await.unwrap_err();
+
+            assert_eq!(calls("read"), 4);
+        });
+    }
+
+    #[test]
+    fn values_expire_after_the_ttl() {
+        block_on(async {
+            let fs = cache();
+            CountingFs::write("a", "hello").await.unwrap();
+
+            fs.read("a").await.unwrap();
+            MockTimer::advance(Duration::from_millis(9_999));
+            fs.read("a").await.unwrap();
+            assert_eq!(calls("read"), 1);
+
+            MockTimer::advance(Duration::from_millis(1));
+            fs.read("a").await.unwrap();
+            assert_eq!(calls("read"), 2);
+        });
+    }
+
+    #[test]
+    fn writes_invalidate_the_cached_contents() {
+        block_on(async {
+            let fs = cache();
+            CountingFs::write("a", "old").await.unwrap();
+
+            assert_eq!(fs.read("a").await.unwrap(), b"old");
+            fs.write("a", "new").await.unwrap();
+
+            assert_eq!(fs.read("a").await.unwrap(), b"new");
+        });
+    }
+
+    #[test]
+    fn renames_invalidate_both_paths() {
+        block_on(async {
+            let fs = cache();
+            CountingFs::write("from", "moved").await.unwrap();
+            CountingFs::write("to", "replaced").await.unwrap();
+
+            fs.read("from").await.unwrap();
+            fs.read("to").await.unwrap();
+            fs.rename("from", "to").await.unwrap();
+
+            assert_eq!(fs.read("to").await.unwrap(), b"moved");
+            assert_eq!(fs.read("from").await.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        });
+    }
+
+    #[test]
+    fn explicit_invalidation_removes_the_path() {
+        block_on(async {
+            let fs = cache();
+            CountingFs::write("a", "hello").await.unwrap();
+
+            fs.read("a").await.unwrap();
+            fs.invalidate("a");
+            fs.read("a").await.unwrap();
+
+            assert_eq!(calls("read"), 2);
+        });
+    }
+
+    #[test]
+    fn the_number_of_paths_is_bounded() {
+        block_on(async {
+            let mut fs = cache();
+            fs.set_max_entries(2);
+
+            for path in ["a", "b", "c"] {
+                CountingFs::write(path, path).await.unwrap();
+                fs.read(path).await.unwrap();
+
+                MockTimer::advance(Duration::from_secs(1));
+            }
+
+            // The path expiring first got evicted, the others are still cached.
+            assert_eq!(fs.state().entries.len(), 2);
+            fs.read("b").await.unwrap();
+            fs.read("c").await.unwrap();
+            assert_eq!(calls("read"), 3);
+
+            fs.read("a").await.unwrap();
+            assert_eq!(calls("read"), 4);
+        });
+    }
+
+    #[test]
+    fn expired_paths_are_swept_without_looking_them_up() {
+        block_on(async {
+            let fs = cache();
+
+            for i in 0..100 {
+                let path = format!("file-{}", i);
+                CountingFs::write(&path, "hello").await.unwrap();
+                fs.read(&path).await.unwrap();
+            }
+
+            MockTimer::advance(Duration::from_secs(10));
+            CountingFs::write("fresh", "hello").await.unwrap();
+            fs.read("fresh").await.unwrap();
+
+            assert_eq!(fs.state().entries.len(), 1);
+        });
+    }
+}
@@ -0,0 +1,867 @@
+use super::*;
+
+use std::fmt;
+use std::future::Future;
+use std::io::ErrorKind;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+use crate::task::{MockTimer, Timer};
+
+
+
+/// The error injected into a call of `op` with `paths`.
+fn injected(op: &str, paths: &[&Path], kind: ErrorKind) -> std::io::Error {
+    let paths = paths
+        .iter()
+        .map(|path| format!("`{}`", path.display()))
+        .collect::<Vec<_>>()
+        .join(" to ");
+
+    std::io::Error::new(kind, format!("`{op}` of {paths} failed with an injected fault"))
+}
+
+/// A [`Filesystem`] wrapping the backend `F`, which injects the faults registered with
+/// [`FaultPlan`] into its calls, for testing the error handling of code generic over
+/// [`Filesystem`].
+///
+/// Every call gets logged by [`FaultPlan`] and is passed on to `F`, unless a [`Fault`] fails it.
+/// Faults can fail calls with an error, delay them on the clock of [`MockTimer`] and truncate the
+/// data of reads and writes.
+///
+/// The default implementations of [`Filesystem`] which `F` does not override, like
+/// [`remove_dir_contents`](Filesystem::remove_dir_contents), are kept, so faults can be injected
+/// into every single step of them.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "tokio-rt")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// use std::io::ErrorKind;
+/// use std::path::Path;
+///
+/// use fut_compat::fs::{Fault, FaultFs, FaultPlan, Filesystem, TokioFs};
+///
+/// /// Reads `path`, trying again while the read gets interrupted, at most `attempts` times.
+/// async fn read_with_retry<F: Filesystem>(path: &Path, attempts: u32) -> std::io::Result<Vec<u8>> {
+///     let mut attempt = 1;
+///
+///     loop {
+///         match F::read(path).await {
+///             Err(err) if err.kind() == ErrorKind::Interrupted && attempt < attempts => attempt += 1,
+///             result => return result,
+///         }
+///     }
+/// }
+///
+/// let path = std::env::temp_dir().join("fut-compat-fault-fs.txt");
+/// TokioFs::write(&path, "hello").await?;
+///
+/// // The first two reads get interrupted, the third one succeeds.
+/// FaultPlan::inject(Fault::new().op("read").times(2).error(ErrorKind::Interrupted));
+///
+/// assert_eq!(read_with_retry::<FaultFs<TokioFs>>(&path, 3).await?, b"hello");
+///
+/// let injected: Vec<bool> = FaultPlan::calls().iter().map(|call| call.injected).collect();
+/// assert_eq!(injected, [true, true, false]);
+///
+/// // With fewer attempts, the error gets through.
+/// FaultPlan::reset();
+/// FaultPlan::inject(Fault::new().op("read").times(2).error(ErrorKind::Interrupted));
+///
+/// let err = read_with_retry::<FaultFs<TokioFs>>(&path, 2).await.unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::Interrupted);
+///
+/// TokioFs::remove_file(&path).await?;
+/// # Ok::<(), std::io::Error>(())
+/// # }).unwrap();
+/// ```
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FaultFs<F> {
+    _marker: PhantomData<fn() -> F>,
+}
+
+
+
+/// Decides whether a [`Fault`] applies to a path.
+type PathMatcherFn = dyn Fn(&Path) -> bool + Send + Sync + 'static;
+
+/// A fault injected into the calls of [`FaultFs`], registered with [`FaultPlan::inject`].
+///
+/// By default, it applies to every call without doing anything, apart from marking the call as
+/// injected in the log. The calls it applies to are narrowed down with [`op`](Self::op),
+/// [`path`](Self::path), [`nth`](Self::nth) and [`times`](Self::times), and its effects are set
+/// with [`error`](Self::error), [`delay`](Self::delay) and [`truncate`](Self::truncate), which can
+/// be combined.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "tokio-rt")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// use std::io::ErrorKind;
+/// use std::path::Path;
+///
+/// use fut_compat::fs::{Fault, FaultFs, FaultPlan, Filesystem, TokioFs};
+///
+/// /// Replaces the contents of `path` by writing a temporary file and renaming it, so `path`
+/// /// either keeps its old contents or gets all of the new ones.
+/// async fn write_atomic<F: Filesystem>(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+///     let tmp = path.with_extension("tmp");
+///
+///     let result = match F::write(&tmp, contents).await {
+///         Ok(()) => F::rename(&tmp, path).await,
+///         Err(err) => Err(err),
+///     };
+///     if result.is_err() {
+///         let _ = F::remove_file(&tmp).await;
+///     }
+///
+///     result
+/// }
+///
+/// let path = std::env::temp_dir().join("fut-compat-fault.json");
+/// TokioFs::write(&path, "old").await?;
+///
+/// // The disk fills up after the first 3 bytes of the temporary file.
+/// FaultPlan::inject(
+///     Fault::new()
+///         .op("write")
+///         .path(|path| path.extension().is_some_and(|ext| ext == "tmp"))
+///         .truncate(3)
+///         .error(ErrorKind::StorageFull),
+/// );
+///
+/// let err = write_atomic::<FaultFs<TokioFs>>(&path, b"new").await.unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::StorageFull);
+/// assert_eq!(TokioFs::read_to_string(&path).await?, "old");
+/// assert!(!path.with_extension("tmp").exists());
+///
+/// // Renaming the temporary file fails.
+/// FaultPlan::reset();
+/// FaultPlan::inject(Fault::new().op("rename").error(ErrorKind::PermissionDenied));
+///
+/// let err = write_atomic::<FaultFs<TokioFs>>(&path, b"new").await.unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+/// assert_eq!(TokioFs::read_to_string(&path).await?, "old");
+/// assert!(!path.with_extension("tmp").exists());
+///
+/// FaultPlan::reset();
+/// write_atomic::<FaultFs<TokioFs>>(&path, b"new").await?;
+/// assert_eq!(TokioFs::read_to_string(&path).await?, "new");
+///
+/// TokioFs::remove_file(&path).await?;
+/// # Ok::<(), std::io::Error>(())
+/// # }).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Fault {
+    op: Option<&'static str>,
+    path: Option<Arc<PathMatcherFn>>,
+    /// The number of matching calls to let through before applying.
+    skip: u64,
+    times: Option<u64>,
+    effect: Effect,
+}
+
+/// What a [`Fault`] does to the calls it applies to.
+#[derive(Clone, Copy, Debug, Default)]
+struct Effect {
+    error: Option<ErrorKind>,
+    delay: Duration,
+    truncate: Option<usize>,
+}
+
+impl Fault {
+    /// Creates a new fault, which applies to every call.
+    pub fn new() -> Self {
+        Self {
+            op: None,
+            path: None,
+            skip: 0,
+            times: None,
+            effect: Effect::default(),
+        }
+    }
+
+    /// Only applies to calls of the operation `op`, named like the function of [`Filesystem`],
+    /// e.g. `"read"`.
+    pub fn op(mut self, op: &'static str) -> Self {
+        self.op = Some(op);
+        self
+    }
+
+    /// Only applies to calls for which `matcher` returns `true` for one of their paths, e.g. the
+    /// source or the destination of [`rename`](Filesystem::rename).
+    pub fn path<M>(mut self, matcher: M) -> Self
+    where
+        M: Fn(&Path) -> bool + Send + Sync + 'static,
+    {
+        self.path = Some(Arc::new(matcher));
+        self
+    }
+
+    /// Only applies to the `n`th call matching the other conditions, counting from `1` and from
+    /// the registration of the fault.
+    ///
+    /// Unless [`times`](Self::times) is set as well, the calls following it are let through.
+    pub fn nth(mut self, n: u64) -> Self {
+        self.skip = n.saturating_sub(1);
+        self.times.get_or_insert(1);
+        self
+    }
+
+    /// Applies to at most `n` calls, following the ones skipped by [`nth`](Self::nth).
+    pub fn times(mut self, n: u64) -> Self {
+        self.times = Some(n);
+        self
+    }
+
+    /// Fails the calls with an error of this kind.
+    ///
+    /// Unless [`truncate`](Self::truncate) is set as well, the calls fail without being passed on.
+    pub fn error(mut self, kind: ErrorKind) -> Self {
+        self.effect.error = Some(kind);
+        self
+    }
+
+    /// Delays the calls by `dur` on the clock of [`MockTimer`], so they only proceed once the test
+    /// advances it with [`MockTimer::advance`].
+    pub fn delay(mut self, dur: Duration) -> Self {
+        self.effect.delay = dur;
+        self
+    }
+
+    /// Truncates the data of reads and writes to at most `len` bytes, to simulate partial IO.
+    ///
+    /// Reads return only the start of the contents, while writes only write the start of the
+    /// contents, and then fail with the [`error`](Self::error) if one is set. The contents of
+    /// [`read_to_string`](Filesystem::read_to_string) get truncated at a character boundary.
+    /// Other operations are not affected.
+    pub fn truncate(mut self, len: usize) -> Self {
+        self.effect.truncate = Some(len);
+        self
+    }
+
+    fn matches(&self, op: &str, paths: &[&Path]) -> bool {
+        if self.op.is_some_and(|fault_op| fault_op != op) {
+            return false;
+        }
+
+        match &self.path {
+            Some(matcher) => paths.iter().any(|path| matcher(path)),
+            None => true,
+        }
+    }
+}
+
+impl Default for Fault {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fault")
+            .field("op", &self.op)
+            .field("path", &self.path.as_ref().map(|_| "<matcher>"))
+            .field("skip", &self.skip)
+            .field("times", &self.times)
+            .field("error", &self.effect.error)
+            .field("delay", &self.effect.delay)
+            .field("truncate", &self.effect.truncate)
+            .finish()
+    }
+}
+
+
+
+/// A call intercepted by [`FaultFs`], as logged by [`FaultPlan`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FaultCall {
+    /// The operation, named like the function of [`Filesystem`], e.g. `"read"`.
+    pub op: &'static str,
+    /// The paths passed to the operation.
+    pub paths: Vec<PathBuf>,
+    /// Whether a [`Fault`] got injected into the call.
+    pub injected: bool,
+}
+
+/// The faults injected into the calls of [`FaultFs`], and the log of the calls.
+///
+/// Faults get registered with [`inject`](Self::inject) and apply to the calls made from then on,
+/// so the plan can be changed while the code under test is running, e.g. for letting a retry
+/// succeed.
+///
+/// The plan is global to the process, as [`Filesystem`] has no receiver to carry it, so tests
+/// which use it should not run in parallel with each other, and should start with
+/// [`reset`](Self::reset). The delays are driven by the clock of [`MockTimer`] on the thread
+/// making the call.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FaultPlan {}
+
+struct Plan {
+    faults: Vec<Registered>,
+    calls: Vec<FaultCall>,
+}
+
+struct Registered {
+    fault: Fault,
+    /// The number of calls the fault matched so far.
+    matched: u64,
+}
+
+impl Plan {
+    /// Counts the call for all faults it matches, and returns the effect of the first one which
+    /// applies to it.
+    fn effect(&mut self, op: &str, paths: &[&Path]) -> Option<Effect> {
+        let mut effect = None;
+
+        for registered in &mut self.faults {
+            let fault = &registered.fault;
+            if !fault.matches(op, paths) {
+                continue;
+            }
+
+            registered.matched += 1;
+
+            let applies = registered.matched > fault.skip
+                && fault.times.is_none_or(|times| registered.matched - fault.skip <= times);
+            if applies && effect.is_none() {
+                effect = Some(fault.effect);
+            }
+        }
+
+        effect
+    }
+}
+
+fn plan() -> MutexGuard<'static, Plan> {
+    static PLAN: OnceLock<Mutex<Plan>> = OnceLock::new();
+
+    PLAN.get_or_init(|| Mutex::new(Plan {
+        faults: Vec::new(),
+        calls: Vec::new(),
+    })).lock().unwrap_or_else(|err| err.into_inner())
+}
+
+impl FaultPlan {
+    /// Registers `fault`, which applies to the calls made from now on.
+    ///
+    /// The faults get checked in the order they have been registered, and only the first one
+    /// applying to a call gets injected into it. The call still counts for the others it matches,
+    /// e.g. for [`Fault::nth`].
+    pub fn inject(fault: Fault) {
+        plan().faults.push(Registered {
+            fault,
+            matched: 0,
+        });
+    }
+
+    /// Returns the calls intercepted so far, oldest first.
+    pub fn calls() -> Vec<FaultCall> {
+        plan().calls.clone()
+    }
+
+    /// Removes all faults, so the following calls get passed on untouched, but keeps the log.
+    pub fn clear_faults() {
+        plan().faults.clear();
+    }
+
+    /// Removes all faults and clears the log.
+    pub fn reset() {
+        let mut plan = plan();
+
+        plan.faults.clear();
+        plan.calls.clear();
+    }
+}
+
+
+
+/// The remaining effects of the fault injected into a call, after its delay.
+#[derive(Default)]
+struct Injection {
+    error: Option<std::io::Error>,
+    truncate: Option<usize>,
+}
+
+impl Injection {
+    /// Returns how many of `len` bytes to read or write.
+    fn limit(&self, len: usize) -> usize {
+        self.truncate.map_or(len, |truncate| truncate.min(len))
+    }
+
+    /// Returns `value`, unless the call fails after its truncated read or write.
+    fn finish<T>(self, value: T) -> std::io::Result<T> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(value),
+        }
+    }
+}
+
+/// Logs the call of `op` with `paths` and delays it if a fault applies to it.
+///
+/// Fails right away if the fault has an error but no truncation, otherwise the returned
+/// [`Injection`] applies the rest of the fault.
+async fn intercept(op: &'static str, paths: &[&Path]) -> std::io::Result<Injection> {
+    let effect = {
+        let mut plan = plan();
+
+        let effect = plan.effect(op, paths);
+        plan.calls.push(FaultCall {
+            op,
+            paths: paths.iter().map(|path| path.to_path_buf()).collect(),
+            injected: effect.is_some(),
+        });
+
+        effect
+    };
+
+    let Some(effect) = effect else {
+        return Ok(Injection::default());
+    };
+
+    if !effect.delay.is_zero() {
+        MockTimer::sleep(effect.delay).await;
+    }
+
+    let error = effect.error.map(|kind| injected(op, paths, kind));
+    match (error, effect.truncate) {
+        (Some(err), None) => Err(err),
+        (error, truncate) => Ok(Injection { error, truncate }),
+    }
+}
+
+/// Runs `future` unless the fault injected into the call of `op` with `paths` fails it.
+async fn run<T, Fut>(op: &'static str, paths: &[&Path], future: Fut) -> std::io::Result<T>
+where
+    Fut: Future<Output = std::io::Result<T>>,
+{
+    let injection = intercept(op, paths).await?;
+    let value = future.await?;
+
+    injection.finish(value)
+}
+
+#[async_trait]
+impl<F: Filesystem + Send> Filesystem for FaultFs<F> {
+    type ReadDir = F::ReadDir;
+    type DirEntry = F::DirEntry;
+
+    async fn canonicalize<P: AsRef<Path> + Send>(path: P) -> std::io::Result<PathBuf> {
+        let path = path.as_ref();
+
+        run("canonicalize", &[path], F::canonicalize(path)).await
+    }
+
+    async fn copy<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        from: S,
+        to: D,
+    ) -> std::io::Result<u64> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+
+        run("copy", &[from, to], F::copy(from, to)).await
+    }
+
+    async fn copy_reflink<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        from: S,
+        to: D,
+    ) -> std::io::Result<ReflinkOutcome> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+
+        run("copy_reflink", &[from, to], F::copy_reflink(from, to)).await
+    }
+
+    async fn create_dir<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        run("create_dir", &[path], F::create_dir(path)).await
+    }
+
+    async fn create_dir_all<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        run("create_dir_all", &[path], F::create_dir_all(path)).await
+    }
+
+    async fn create_dir_with<P: AsRef<Path> + Send>(
+        path: P,
+        options: DirOptions,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        run("create_dir_with", &[path], F::create_dir_with(path, options)).await
+    }
+
+    async fn hard_link<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        src: S,
+        dst: D,
+    ) -> std::io::Result<()> {
+        let (src, dst) = (src.as_ref(), dst.as_ref());
+
+        run("hard_link", &[src, dst], F::hard_link(src, dst)).await
+    }
+
+    async fn metadata<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Metadata> {
+        let path = path.as_ref();
+
+        run("metadata", &[path], F::metadata(path)).await
+    }
+
+    async fn read<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Vec<u8>> {
+        let path = path.as_ref();
+
+        let injection = intercept("read", &[path]).await?;
+        let mut contents = F::read(path).await?;
+        contents.truncate(injection.limit(contents.len()));
+
+        injection.finish(contents)
+    }
+
+    async fn read_dir<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self::ReadDir> {
+        let path = path.as_ref();
+
+        run("read_dir", &[path], F::read_dir(path)).await
+    }
+
+    async fn read_if_modified_since<P: AsRef<Path> + Send>(
+        path: P,
+        since: SystemTime,
+    ) -> std::io::Result<Option<(Vec<u8>, SystemTime)>> {
+        let path = path.as_ref();
+
+        let injection = intercept("read_if_modified_since", &[path]).await?;
+        let mut result = F::read_if_modified_since(path, since).await?;
+        if let Some((contents, _)) = &mut result {
+            contents.truncate(injection.limit(contents.len()));
+        }
+
+        injection.finish(result)
+    }
+
+    async fn read_link<P: AsRef<Path> + Send>(path: P) -> std::io::Result<PathBuf> {
+        let path = path.as_ref();
+
+        run("read_link", &[path], F::read_link(path)).await
+    }
+
+    async fn read_to_string<P: AsRef<Path> + Send>(path: P) -> std::io::Result<String> {
+        let path = path.as_ref();
+
+        let injection = intercept("read_to_string", &[path]).await?;
+        let mut contents = F::read_to_string(path).await?;
+
+        let mut len = injection.limit(contents.len());
+        while !contents.is_char_boundary(len) {
+            len -= 1;
+        }
+        contents.truncate(len);
+
+        injection.finish(contents)
+    }
+
+    async fn remove_dir<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        run("remove_dir", &[path], F::remove_dir(path)).await
+    }
+
+    async fn remove_dir_all<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        run("remove_dir_all", &[path], F::remove_dir_all(path)).await
+    }
+
+    async fn remove_file<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        run("remove_file", &[path], F::remove_file(path)).await
+    }
+
+    async fn rename<O: AsRef<Path> + Send, N: AsRef<Path> + Send>(
+        from: O,
+        to: N,
+    ) -> std::io::Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+
+        run("rename", &[from, to], F::rename(from, to)).await
+    }
+
+    async fn set_permissions<P: AsRef<Path> + Send>(
+        path: P,
+        perm: Permissions,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        run("set_permissions", &[path], F::set_permissions(path, perm)).await
+    }
+
+    async fn symlink_metadata<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Metadata> {
+        let path = path.as_ref();
+
+        run("symlink_metadata", &[path], F::symlink_metadata(path)).await
+    }
+
+    async fn touch<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        run("touch", &[path], F::touch(path)).await
+    }
+
+    async fn write<P: AsRef<Path> + Send, C: AsRef<[u8]> + Send>(
+        path: P,
+        contents: C,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        let injection = intercept("write", &[path]).await?;
+        let contents = contents.as_ref();
+        F::write(path, &contents[..injection.limit(contents.len())]).await?;
+
+        injection.finish(())
+    }
+
+    async fn write_vectored<P: AsRef<Path> + Send>(
+        path: P,
+        bufs: &[IoSlice<'_>],
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        let injection = intercept("write_vectored", &[path]).await?;
+
+        let mut remaining = injection.limit(bufs.iter().map(|buf| buf.len()).sum());
+        let bufs: Vec<IoSlice<'_>> = bufs
+            .iter()
+            .map(|buf| {
+                let len = buf.len().min(remaining);
+                remaining -= len;
+
+                IoSlice::new(&buf[..len])
+            })
+            .collect();
+        F::write_vectored(path, &bufs).await?;
+
+        injection.finish(())
+    }
+
+    #[cfg(feature = "bytes")]
+    async fn write_bytes<P: AsRef<Path> + Send, B: Buf + Send>(
+        path: P,
+        buf: B,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        let injection = intercept("write_bytes", &[path]).await?;
+        let len = injection.limit(buf.remaining());
+        F::write_bytes(path, buf.take(len)).await?;
+
+        injection.finish(())
+    }
+}
+
+
+
+#[cfg(all(test, feature = "tokio-rt"))]
+mod tests {
+    use super::*;
+
+    use futures::FutureExt;
+
+    use super::super::tests::ScratchDir;
+
+    type Fs = FaultFs<TokioFs>;
+
+    /// Serializes the tests, as they share the plan, and resets it beforehand.
+    fn serial() -> MutexGuard<'static, ()> {
+        static LOCK: Mutex<()> = Mutex::new(());
+
+        let guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        FaultPlan::reset();
+
+        guard
+    }
+
+    fn runtime() -> ::tokio::runtime::Runtime {
+        ::tokio::runtime::Runtime::new().unwrap()
+    }
+
+    /// Reads `path`, trying again while the read gets interrupted, at most `attempts` times.
+    async fn read_with_retry<F: Filesystem>(path: &Path, attempts: u32) -> std::io::Result<Vec<u8>> {
+        let mut attempt = 1;
+
+        loop {
+            match F::read(path).await {
+                Err(err) if err.kind() == ErrorKind::Interrupted && attempt < attempts => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+
+    /// Replaces the contents of `path` by writing a temporary file and renaming it.
+    async fn write_atomic<F: Filesystem>(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+        let tmp = path.with_extension("tmp");
+
+        let result = match F::write(&tmp, contents).await {
+            Ok(()) => F::rename(&tmp, path).await,
+            Err(err) => Err(err),
+        };
+        if result.is_err() {
+            let _ = F::remove_file(&tmp).await;
+        }
+
+        result
+    }
+
+    /// Returns the logged calls as their operation, the file names of their paths, and whether a
+    /// fault got injected.
+    fn log() -> Vec<(&'static str, Vec<String>, bool)> {
+        FaultPlan::calls()
+            .into_iter()
+            .map(|call| {
+                let names = call.paths
+                    .iter()
+                    .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+                    .collect();
+
+                (call.op, names, call.injected)
+            })
+            .collect()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn retry_wrapper_retries_interrupted_reads() {
+        let _serial = serial();
+        let scratch = ScratchDir::new("fault-retry");
+        let path = scratch.join("data");
+        std::fs::write(&path, "hello").unwrap();
+
+        runtime().block_on(async {
+            FaultPlan::inject(Fault::new().op("read").times(2).error(ErrorKind::Interrupted));
+            assert_eq!(read_with_retry::<Fs>(&path, 3).await.unwrap(), b"hello");
+
+            assert_eq!(log(), [
+                ("read", names(&["data"]), true),
+                ("read", names(&["data"]), true),
+                ("read", names(&["data"]), false),
+            ]);
+
+            // Other errors are not retried.
+            FaultPlan::reset();
+            FaultPlan::inject(Fault::new().op("read").error(ErrorKind::PermissionDenied));
+
+            let err = read_with_retry::<Fs>(&path, 3).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+            assert!(err.to_string().contains("`read` of"), "{}", err);
+            assert_eq!(log(), [("read", names(&["data"]), true)]);
+        });
+    }
+
+    #[test]
+    fn retry_wrapper_gives_up_after_the_last_attempt() {
+        let _serial = serial();
+        let scratch = ScratchDir::new("fault-give-up");
+        let path = scratch.join("data");
+        std::fs::write(&path, "hello").unwrap();
+
+        runtime().block_on(async {
+            FaultPlan::inject(Fault::new().op("read").error(ErrorKind::Interrupted));
+
+            let err = read_with_retry::<Fs>(&path, 4).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Interrupted);
+            assert_eq!(FaultPlan::calls().len(), 4);
+
+            // Changing the plan lets the next attempt through.
+            FaultPlan::clear_faults();
+            assert_eq!(read_with_retry::<Fs>(&path, 1).await.unwrap(), b"hello");
+
+            let injected: Vec<bool> = FaultPlan::calls().iter().map(|call| call.injected).collect();
+            assert_eq!(injected, [true, true, true, true, false]);
+        });
+    }
+
+    #[test]
+    fn delayed_calls_wait_for_the_mock_timer() {
+        let _serial = serial();
+        let scratch = ScratchDir::new("fault-delay");
+        let path = scratch.join("data");
+        std::fs::write(&path, "hello").unwrap();
+
+        let runtime = runtime();
+        let _context = runtime.enter();
+
+        FaultPlan::inject(Fault::new().op("read").delay(Duration::from_secs(30)));
+
+        let mut read = Box::pin(read_with_retry::<Fs>(&path, 1));
+        assert!((&mut read).now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_secs(30));
+        assert_eq!(runtime.block_on(read).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn atomic_write_keeps_the_old_contents_when_writing_fails() {
+        let _serial = serial();
+        let scratch = ScratchDir::new("fault-atomic-write");
+        let path = scratch.join("config.json");
+        std::fs::write(&path, "old").unwrap();
+
+        runtime().block_on(async {
+            // The disk fills up after the first 3 bytes of the temporary file.
+            FaultPlan::inject(
+                Fault::new()
+                    .op("write")
+                    .path(|path| path.extension().is_some_and(|ext| ext == "tmp"))
+                    .truncate(3)
+                    .error(ErrorKind::StorageFull),
+            );
+
+            let err = write_atomic::<Fs>(&path, b"new contents").await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::StorageFull);
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+            assert!(!path.with_extension("tmp").exists());
+
+            assert_eq!(log(), [
+                ("write", names(&["config.tmp"]), true),
+                ("remove_file", names(&["config.tmp"]), false),
+            ]);
+        });
+    }
+
+    #[test]
+    fn atomic_write_keeps_the_old_contents_when_renaming_fails() {
+        let _serial = serial();
+        let scratch = ScratchDir::new("fault-atomic-rename");
+        let path = scratch.join("config.json");
+        std::fs::write(&path, "old").unwrap();
+
+        runtime().block_on(async {
+            FaultPlan::inject(Fault::new().op("rename").nth(1).error(ErrorKind::PermissionDenied));
+
+            let err = write_atomic::<Fs>(&path, b"new").await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+            assert!(!path.with_extension("tmp").exists());
+
+            // The fault only applied to the first rename.
+            write_atomic::<Fs>(&path, b"new").await.unwrap();
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+
+            assert_eq!(log(), [
+                ("write", names(&["config.tmp"]), false),
+                ("rename", names(&["config.tmp", "config.json"]), true),
+                ("remove_file", names(&["config.tmp"]), false),
+                ("write", names(&["config.tmp"]), false),
+                ("rename", names(&["config.tmp", "config.json"]), false),
+            ]);
+        });
+    }
+}
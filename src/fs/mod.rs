@@ -1,9 +1,14 @@
 use std::path::{Path, PathBuf};
 use std::fs::{Metadata, Permissions, FileType};
 use std::ffi::OsString;
+use std::io::IoSlice;
+use std::time::SystemTime;
 
 use futures::stream::Stream;
 
+#[cfg(feature = "bytes")]
+use ::bytes::Buf;
+
 use async_trait::async_trait;
 
 
@@ -24,7 +29,120 @@ mod async_std;
 #[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
 pub use self::async_std::*;
 
+/// Contains the objects backed by [`tokio-uring`](https://docs.rs/tokio-uring).
+#[cfg(all(feature = "uring", target_os = "linux"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "uring", target_os = "linux"))))]
+mod uring;
+#[cfg(all(feature = "uring", target_os = "linux"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "uring", target_os = "linux"))))]
+pub use self::uring::*;
+
+/// A filesystem chosen at runtime out of the enabled backends.
+#[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "tokio-rt", feature = "async-std-rt"))))]
+mod any;
+#[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "tokio-rt", feature = "async-std-rt"))))]
+pub use self::any::*;
+
+/// A filesystem wrapper failing operations which do not complete within a deadline.
+mod timeout;
+pub use self::timeout::*;
+
+/// A filesystem wrapper caching metadata and the contents of small files.
+mod cache;
+pub use self::cache::*;
+
+/// A filesystem wrapper injecting faults for testing.
+#[cfg(feature = "test-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+mod fault;
+#[cfg(feature = "test-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+pub use self::fault::*;
+
+/// Combinators for filtering the entries of directories.
+mod read_dir_ext;
+pub use self::read_dir_ext::*;
+
+/// Copying files by cloning them on filesystems supporting copy-on-write.
+#[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+mod reflink;
+
+/// Resolving paths beneath a directory without following symbolic links.
+#[cfg(all(unix, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod beneath;
+
+
+
+/// The error returned when a component of a path to create is not a directory.
+fn not_a_directory(path: &Path) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::AlreadyExists,
+        format!("`{}` exists, but is not a directory", path.display()),
+    )
+}
+
+/// Creates the file at `path` if it does not exist yet, and sets its times to now.
+///
+/// This blocks, so it has to run on the blocking pool of the runtime.
+#[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+fn touch_blocking(path: &Path) -> std::io::Result<()> {
+    // Existing files are not opened for writing, so read-only files can be touched as well.
+    let file = match open_for_times(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            std::fs::File::options().create(true).write(true).truncate(false).open(path)?
+        }
+        Err(err) => return Err(err),
+    };
+
+    let now = std::time::SystemTime::now();
+    file.set_times(std::fs::FileTimes::new().set_accessed(now).set_modified(now))
+}
+
+/// Opens an existing file with just enough access to change its times.
+#[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+fn open_for_times(path: &Path) -> std::io::Result<std::fs::File> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+
+        /// The access right `FILE_WRITE_ATTRIBUTES`, which is granted for read-only files too.
+        const FILE_WRITE_ATTRIBUTES: u32 = 0x0100;
+
+        std::fs::File::options().access_mode(FILE_WRITE_ATTRIBUTES).open(path)
+    }
+
+    // `futimens` only needs an open file, which does not have to be writable.
+    #[cfg(not(windows))]
+    std::fs::File::open(path)
+}
+
+/// Reads the file at `path` if it has been modified after `since`, taking the modification time
+/// from the opened file.
+///
+/// This blocks, so it has to run on the blocking pool of the runtime.
+#[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+fn read_if_modified_since_blocking(
+    path: &Path,
+    since: SystemTime,
+) -> std::io::Result<Option<(Vec<u8>, SystemTime)>> {
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path)?;
+    let metadata = file.metadata()?;
+
+    let modified = metadata.modified()?;
+    if modified <= since {
+        return Ok(None);
+    }
 
+    let mut contents = Vec::with_capacity(metadata.len().try_into().unwrap_or(0));
+    file.read_to_end(&mut contents)?;
+
+    Ok(Some((contents, modified)))
+}
 
 /// An async abstraction over the functions in [`std::fs`].
 #[async_trait]
@@ -139,6 +257,46 @@ pub trait Filesystem {
         to: D,
     ) -> std::io::Result<u64>;
 
+    /// Copies the contents of one file to another, cloning it instead if the filesystem supports
+    /// copy-on-write, e.g. btrfs, XFS or APFS.
+    ///
+    /// A clone shares the data of the original until either gets modified, so it is created
+    /// instantly and takes no additional space. If the file can not be cloned, e.g. because the
+    /// filesystem does not support it or the files are on different filesystems, it gets copied
+    /// like by [`copy`](Filesystem::copy). The returned [`ReflinkOutcome`] tells which happened.
+    /// Either way the permissions of `from` are copied to `to`.
+    ///
+    /// Files get cloned with the `FICLONE` ioctl on Linux, and with `clonefile` on Apple
+    /// platforms, which can not replace an existing file, so existing files are always copied
+    /// there. The default implementation always copies.
+    ///
+    /// # Examples
+    ///
+    /// Using the [`tokio`](https://docs.rs/tokio) runtime:
+    ///
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// #
+    /// use fut_compat::fs::{Filesystem, ReflinkOutcome};
+    /// use fut_compat::fs::TokioFs;
+    ///
+    /// if TokioFs::copy_reflink("disk.img", "snapshot.img").await? == ReflinkOutcome::Copied {
+    ///     println!("the filesystem does not support cloning");
+    /// }
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn copy_reflink<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        from: S,
+        to: D,
+    ) -> std::io::Result<ReflinkOutcome> {
+        Self::copy(from, to).await?;
+
+        Ok(ReflinkOutcome::Copied)
+    }
+
     /// Creates a new directory.
     ///
     /// Note that this function will only create the final directory in `path`. If you want to create
@@ -234,6 +392,106 @@ pub trait Filesystem {
     /// ```
     async fn create_dir_all<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()>;
 
+    /// Creates a new directory with the given options.
+    ///
+    /// The implementations of this crate create the directory with the configured mode right
+    /// away, which is subject to the umask of the process like with [`std::fs::DirBuilder`]. The
+    /// default implementation creates it with [`create_dir`] and sets its permissions afterwards,
+    /// so they are the default ones for a moment and the umask does not apply.
+    ///
+    /// [`create_dir`]: #tymethod.create_dir
+    async fn create_dir_with<P: AsRef<Path> + Send>(
+        path: P,
+        options: DirOptions,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        Self::create_dir(path).await?;
+
+        #[cfg(unix)]
+        if let Some(mode) = options.get_mode() {
+            use std::os::unix::fs::PermissionsExt;
+
+            Self::set_permissions(path, Permissions::from_mode(mode)).await?;
+        }
+        #[cfg(not(unix))]
+        let _ = options;
+
+        Ok(())
+    }
+
+    /// Creates a new directory and all of its missing parents with the given options, e.g. with
+    /// the mode `0o700` for private cache directories.
+    ///
+    /// Every missing component gets created with [`create_dir_with`], so all of them get the
+    /// configured mode. Existing parents are left as they are. It is not an error if the
+    /// directory already exists, or if another process creates a component concurrently.
+    ///
+    /// [`create_dir_with`]: #method.create_dir_with
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned in the following situations:
+    ///
+    /// * A component of `path` exists, but is not a directory.
+    /// * The current process lacks permissions to create the directory or its missing parents.
+    /// * Some other I/O error occurred.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// #
+    /// use fut_compat::fs::{DirOptions, Filesystem, TokioFs};
+    ///
+    /// TokioFs::create_dir_all_with("./cache/thumbnails", DirOptions::new().mode(0o700)).await?;
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn create_dir_all_with<P: AsRef<Path> + Send>(
+        path: P,
+        options: DirOptions,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        // Walk up to the first existing component, collecting the missing ones.
+        let mut missing = Vec::new();
+        let mut current = Some(path);
+
+        while let Some(dir) = current {
+            if dir.as_os_str().is_empty() {
+                break;
+            }
+
+            match Self::metadata(dir).await {
+                Ok(metadata) if metadata.is_dir() => break,
+                Ok(_) => return Err(not_a_directory(dir)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    missing.push(dir);
+                    current = dir.parent();
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        for dir in missing.into_iter().rev() {
+            match Self::create_dir_with(dir, options).await {
+                Ok(()) => {}
+                // Another process created the component in the meantime.
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if !Self::metadata(dir).await?.is_dir() {
+                        return Err(not_a_directory(dir));
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
     /// Creates a hard link on the filesystem.
     ///
     /// The `dst` path will be a link pointing to the `src` path. Note that operating systems often
@@ -448,6 +706,82 @@ pub trait Filesystem {
     /// ```
     async fn read_dir<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self::ReadDir>;
 
+    /// Reads the entire contents of a file, unless it has not been modified after `since`.
+    ///
+    /// Returns the contents along with the modification time of the file, which can be passed as
+    /// `since` the next time, or `None` if the file has not been modified after `since`. This is
+    /// useful for conditional requests, e.g. with the `If-Modified-Since` header of HTTP.
+    ///
+    /// The modification time is taken from the opened file, so it belongs to the same file as the
+    /// contents even if the path gets replaced in the meantime, e.g. by renaming another file onto
+    /// it. Writes to the file while it is being read may still be missed by the returned time.
+    ///
+    /// The default implementation takes the modification time from [`metadata`](Filesystem::metadata)
+    /// of the path and then uses [`read`](Filesystem::read), so the contents may belong to a file
+    /// which replaced the path in between. The implementations of this crate override it.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned in the following situations:
+    ///
+    /// * `path` does not point to an existing file.
+    /// * The current process lacks permissions to read the file.
+    /// * The platform does not support the modification time.
+    /// * Some other I/O error occurred.
+    ///
+    /// # Examples
+    ///
+    /// Using the [`tokio`](https://docs.rs/tokio) runtime:
+    ///
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// #
+    /// use std::time::SystemTime;
+    ///
+    /// use fut_compat::fs::Filesystem;
+    /// use fut_compat::fs::TokioFs;
+    ///
+    /// if let Some((contents, modified)) = TokioFs::read_if_modified_since("a.txt", SystemTime::UNIX_EPOCH).await? {
+    ///     // Serve `contents` and remember `modified` for the next request.
+    /// }
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Using the [`async_std`](https://docs.rs/async-std) runtime:
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use std::time::SystemTime;
+    ///
+    /// use fut_compat::fs::Filesystem;
+    /// use fut_compat::fs::AsyncStdFs;
+    ///
+    /// if let Some((contents, modified)) = AsyncStdFs::read_if_modified_since("a.txt", SystemTime::UNIX_EPOCH).await? {
+    ///     // Serve `contents` and remember `modified` for the next request.
+    /// }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    async fn read_if_modified_since<P: AsRef<Path> + Send>(
+        path: P,
+        since: SystemTime,
+    ) -> std::io::Result<Option<(Vec<u8>, SystemTime)>> {
+        let path = path.as_ref();
+
+        let modified = Self::metadata(path).await?.modified()?;
+        if modified <= since {
+            return Ok(None);
+        }
+
+        let contents = Self::read(path).await?;
+
+        Ok(Some((contents, modified)))
+    }
+
     /// Reads a symbolic link and returns the path it points to.
     ///
     /// This function is an async version of [`std::fs::read_link`].
@@ -492,6 +826,77 @@ pub trait Filesystem {
     /// ```
     async fn read_link<P: AsRef<Path> + Send>(path: P) -> std::io::Result<PathBuf>;
 
+    /// Follows the chain of symbolic links at `path` until reaching something which is not a
+    /// symbolic link, and returns the path to it.
+    ///
+    /// Unlike [`canonicalize`](Filesystem::canonicalize), only symbolic links in the last
+    /// component get followed, and the returned path is not normalized. Relative link targets are
+    /// resolved against the directory containing the link. If `path` is not a symbolic link, it
+    /// gets returned as it is.
+    ///
+    /// At most `max_hops` links get followed, which bounds the work done for long chains and
+    /// detects loops.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned in the following situations:
+    ///
+    /// * `path`, or the target of a link in the chain, does not exist.
+    /// * Following the chain takes more than `max_hops` links, e.g. because of a loop. The error
+    ///   is of kind [`InvalidData`](std::io::ErrorKind::InvalidData).
+    /// * Some other I/O error occurred.
+    ///
+    /// # Examples
+    ///
+    /// Using the [`tokio`](https://docs.rs/tokio) runtime:
+    ///
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// #
+    /// use fut_compat::fs::Filesystem;
+    /// use fut_compat::fs::TokioFs;
+    ///
+    /// // E.g. `/usr/bin/python3` pointing to `python3.12`.
+    /// let path = TokioFs::read_link_canonical("/usr/bin/python3", 8).await?;
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn read_link_canonical<P: AsRef<Path> + Send>(
+        path: P,
+        max_hops: u32,
+    ) -> std::io::Result<PathBuf> {
+        let start = path.as_ref();
+        let mut path = start.to_path_buf();
+        let mut hops = 0;
+
+        loop {
+            if !Self::symlink_metadata(&path).await?.file_type().is_symlink() {
+                return Ok(path);
+            }
+
+            if hops == max_hops {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "`{}` is a chain of more than {} symbolic links, which may be a loop",
+                        start.display(),
+                        max_hops,
+                    ),
+                ));
+            }
+
+            // Joining replaces the parent if the target is absolute.
+            let target = Self::read_link(&path).await?;
+            path = match path.parent() {
+                Some(parent) => parent.join(target),
+                None => target,
+            };
+            hops += 1;
+        }
+    }
+
     /// Reads the entire contents of a file as a string.
     ///
     /// This is a convenience function for reading entire files. It pre-allocates a string based on the
@@ -635,6 +1040,117 @@ pub trait Filesystem {
     /// ```
     async fn remove_dir_all<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()>;
 
+    /// Removes all of the contents of a directory, but not the directory itself.
+    ///
+    /// Unlike removing the directory and creating it again, this preserves its permissions, its
+    /// ownership and anything mounted on it. Symbolic links get removed, but not followed.
+    ///
+    /// Entries which get removed concurrently are skipped. If removing an entry fails, the
+    /// remaining ones still get removed and the first error is returned afterwards.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned in the following situations:
+    ///
+    /// * `path` does not point to an existing directory.
+    /// * The current process lacks permissions to read the directory or remove one of its entries.
+    /// * Some other I/O error occurred.
+    ///
+    /// # Examples
+    ///
+    /// Using the [`tokio`](https://docs.rs/tokio) runtime:
+    ///
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// #
+    /// use fut_compat::fs::Filesystem;
+    /// use fut_compat::fs::TokioFs;
+    ///
+    /// TokioFs::remove_dir_contents("./some/directory").await?;
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Using the [`async_std`](https://docs.rs/async-std) runtime:
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use fut_compat::fs::Filesystem;
+    /// use fut_compat::fs::AsyncStdFs;
+    ///
+    /// AsyncStdFs::remove_dir_contents("./some/directory").await?;
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    async fn remove_dir_contents<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()>
+    where
+        Self::ReadDir: Send,
+        Self::DirEntry: Send,
+    {
+        use futures::stream::StreamExt;
+
+        /// Keeps the first error, ignoring the ones of entries which no longer exist.
+        fn record(result: &mut std::io::Result<()>, err: std::io::Error) {
+            if result.is_ok() && err.kind() != std::io::ErrorKind::NotFound {
+                *result = Err(err);
+            }
+        }
+
+        let mut result = Ok(());
+
+        // The directory and all subdirectories found so far, parents before their children.
+        let mut dirs = vec![path.as_ref().to_path_buf()];
+        let mut i = 0;
+
+        while i < dirs.len() {
+            let entries = match Self::read_dir(&dirs[i]).await {
+                Ok(entries) => entries,
+                Err(err) if i == 0 => return Err(err),
+                Err(err) => {
+                    record(&mut result, err);
+                    i += 1;
+
+                    continue;
+                }
+            };
+            futures::pin_mut!(entries);
+            i += 1;
+
+            while let Some(entry) = entries.next().await {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        record(&mut result, err);
+
+                        continue;
+                    }
+                };
+
+                match entry.file_type().await {
+                    Ok(file_type) if file_type.is_dir() => dirs.push(entry.path()),
+                    Ok(_) => {
+                        if let Err(err) = Self::remove_file(entry.path()).await {
+                            record(&mut result, err);
+                        }
+                    }
+                    Err(err) => record(&mut result, err),
+                }
+            }
+        }
+
+        // Remove the subdirectories children first, keeping the directory itself.
+        for dir in dirs[1..].iter().rev() {
+            if let Err(err) = Self::remove_dir(dir).await {
+                record(&mut result, err);
+            }
+        }
+
+        result
+    }
+
     /// Removes a file.
     ///
     /// This function is an async version of [`std::fs::remove_file`].
@@ -833,6 +1349,73 @@ pub trait Filesystem {
     /// ```
     async fn symlink_metadata<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Metadata>;
 
+    /// Creates a file if it does not exist yet, and sets its access and modification times to
+    /// now, like the `touch` command.
+    ///
+    /// An existing file keeps its contents and only gets its times set, so read-only files can be
+    /// touched as well. A missing file gets opened with [`create`] and [`write`] but without
+    /// truncating, so a file created concurrently keeps its contents.
+    ///
+    /// The default implementation only uses the other methods of this trait: it writes empty
+    /// contents to a missing file and rewrites the contents of an existing one to update its
+    /// times. This needs permission to write, truncates a file created concurrently and is costly
+    /// for large files, so the implementations of this crate override it.
+    ///
+    /// [`create`]: trait.OpenOptions.html#tymethod.create
+    /// [`write`]: trait.OpenOptions.html#tymethod.write
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned in the following situations:
+    ///
+    /// * The parent directory of `path` does not exist.
+    /// * `path` points to a directory.
+    /// * The current process lacks permissions to create the file or to change its times.
+    /// * Some other I/O error occurred.
+    ///
+    /// # Examples
+    ///
+    /// Using the [`tokio`](https://docs.rs/tokio) runtime:
+    ///
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// #
+    /// use fut_compat::fs::Filesystem;
+    /// use fut_compat::fs::TokioFs;
+    ///
+    /// TokioFs::touch("build.stamp").await?;
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Using the [`async_std`](https://docs.rs/async-std) runtime:
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use fut_compat::fs::Filesystem;
+    /// use fut_compat::fs::AsyncStdFs;
+    ///
+    /// AsyncStdFs::touch("build.stamp").await?;
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    async fn touch<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        match Self::metadata(path).await {
+            Ok(_) => {
+                let contents = Self::read(path).await?;
+
+                Self::write(path, contents).await
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Self::write(path, []).await,
+            Err(err) => Err(err),
+        }
+    }
+
     /// Writes a slice of bytes as the new contents of a file.
     ///
     /// This function will create a file if it does not exist, and will entirely replace its contents
@@ -883,6 +1466,145 @@ pub trait Filesystem {
         path: P,
         contents: C
     ) -> std::io::Result<()>;
+
+    /// Writes the concatenation of `bufs` as the new contents of a file.
+    ///
+    /// This function will create a file if it does not exist, and will entirely replace its contents
+    /// if it does. Unlike [`write`](Filesystem::write), the buffers do not need to be concatenated
+    /// first, e.g. a header and a body produced separately.
+    ///
+    /// The buffers are written with the vectored writes of the file, which fall back to writing them
+    /// one after another if the runtime does not support them. Either all bytes get written, or an
+    /// error is returned.
+    ///
+    /// The default implementation copies the buffers into a single one and passes it to
+    /// [`write`](Filesystem::write). The filesystems of the runtimes, `TokioFs`, `AsyncStdFs` and
+    /// `UringFs`, override it to avoid the copy, and so should other implementations which can.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned in the following situations:
+    ///
+    /// * The file's parent directory does not exist.
+    /// * The current process lacks permissions to write to the file.
+    /// * The file stops accepting bytes before all of them have been written. The error is of kind
+    ///   [`WriteZero`](std::io::ErrorKind::WriteZero).
+    /// * Some other I/O error occurred.
+    ///
+    /// # Examples
+    ///
+    /// Using the [`tokio`](https://docs.rs/tokio) runtime:
+    ///
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// #
+    /// use std::io::IoSlice;
+    ///
+    /// use fut_compat::fs::Filesystem;
+    /// use fut_compat::fs::TokioFs;
+    ///
+    /// let bufs = [IoSlice::new(b"Hello "), IoSlice::new(b"world!")];
+    /// TokioFs::write_vectored("a.txt", &bufs).await?;
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Using the [`async_std`](https://docs.rs/async-std) runtime:
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use std::io::IoSlice;
+    ///
+    /// use fut_compat::fs::Filesystem;
+    /// use fut_compat::fs::AsyncStdFs;
+    ///
+    /// let bufs = [IoSlice::new(b"Hello "), IoSlice::new(b"world!")];
+    /// AsyncStdFs::write_vectored("a.txt", &bufs).await?;
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    async fn write_vectored<P: AsRef<Path> + Send>(
+        path: P,
+        bufs: &[IoSlice<'_>],
+    ) -> std::io::Result<()> {
+        let mut contents = Vec::with_capacity(bufs.iter().map(|buf| buf.len()).sum());
+        for buf in bufs {
+            contents.extend_from_slice(buf);
+        }
+
+        Self::write(path, contents).await
+    }
+
+    /// Writes the remaining bytes of `buf` as the new contents of a file, e.g. a chain of
+    /// [`Bytes`](::bytes::Bytes).
+    ///
+    /// The chunks of `buf` are written with [`write_vectored`](Filesystem::write_vectored), so
+    /// they do not get concatenated, unless the implementation falls back to its default. Buffers
+    /// which can not provide all of their chunks at once through [`Buf::chunks_vectored`] get
+    /// copied into a single one first.
+    ///
+    /// # Errors
+    ///
+    /// See [`write_vectored`](Filesystem::write_vectored).
+    ///
+    /// # Examples
+    ///
+    /// Using the [`tokio`](https://docs.rs/tokio) runtime:
+    ///
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// #
+    /// use bytes::{Buf, Bytes};
+    /// use fut_compat::fs::Filesystem;
+    /// use fut_compat::fs::TokioFs;
+    ///
+    /// let header = Bytes::from_static(b"Hello ");
+    /// let body = Bytes::from(b"world!".to_vec());
+    /// TokioFs::write_bytes("a.txt", header.chain(body)).await?;
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "bytes")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+    async fn write_bytes<P: AsRef<Path> + Send, B: Buf + Send>(
+        path: P,
+        mut buf: B,
+    ) -> std::io::Result<()> {
+        let len = buf.remaining();
+        let mut capacity = 16;
+
+        loop {
+            // Only the slices are kept across the write, borrowing `buf` temporarily, so `B` does
+            // not need to be `Sync`.
+            let (slices, complete) = {
+                let chunks: &B = &buf;
+                let mut slices = vec![IoSlice::new(&[]); capacity];
+                let filled = chunks.chunks_vectored(&mut slices);
+                slices.truncate(filled);
+
+                let total: usize = slices.iter().map(|slice| slice.len()).sum();
+
+                (slices, total == len)
+            };
+
+            if complete {
+                return Self::write_vectored(path, &slices).await;
+            }
+
+            // The buffer provides fewer chunks than it was asked for, so it will never provide all.
+            if slices.len() < capacity {
+                break;
+            }
+            capacity *= 2;
+        }
+
+        Self::write(path, buf.copy_to_bytes(len)).await
+    }
 }
 
 
@@ -1388,3 +2110,498 @@ pub trait DirBuilder: Sized {
     /// It is considered an error if the directory already exists unless recursive mode is enabled.
     async fn create<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()>;
 }
+
+
+
+/// Opening files without following symbolic links, for code which must not be tricked into
+/// opening files outside of where it expects them, e.g. by a symbolic link swapped in between
+/// checking a path and opening it.
+///
+/// Implemented by the [`Filesystem`]s of the runtimes on unix.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::fs::{SecureOpen, TokioFs};
+///
+/// // Fails if `config.toml` or any directory on the way is a symbolic link, or if the path
+/// // leads out of `/srv/uploads`.
+/// let file = TokioFs::open_beneath("/srv/uploads", "user-42/config.toml").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[async_trait]
+pub trait SecureOpen: Filesystem {
+    /// The file object which gets returned when opening a file.
+    type File: File;
+
+    /// Opens a file in read-only mode, failing if the last component of `path` is a symbolic
+    /// link.
+    ///
+    /// The file gets opened with `O_NOFOLLOW`, so on most platforms a symbolic link fails with
+    /// `ELOOP`. Symbolic links in the directories leading to the file still get followed, use
+    /// [`open_beneath`](SecureOpen::open_beneath) to reject them as well.
+    async fn open_no_follow<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self::File>;
+
+    /// Opens the file at the relative `path` within the directory `root` in read-only mode,
+    /// refusing to follow symbolic links in any component of `path`.
+    ///
+    /// Absolute paths and `..` components leading out of `root` fail with
+    /// [`PermissionDenied`](std::io::ErrorKind::PermissionDenied), symbolic links on most
+    /// platforms with `ELOOP`. `root` itself is trusted, so symbolic links within it get followed.
+    ///
+    /// On Linux 5.6 and later the kernel resolves the path with `openat2` and `RESOLVE_BENEATH`,
+    /// which also guards against concurrent renames. Elsewhere the path gets opened one component
+    /// at a time with `O_NOFOLLOW`, relative to the directory opened before. Either way the work
+    /// is done on the blocking pool of the runtime.
+    async fn open_beneath<R, P>(root: R, path: P) -> std::io::Result<Self::File>
+    where
+        R: AsRef<Path> + Send,
+        P: AsRef<Path> + Send;
+}
+
+
+
+/// The options for creating directories with [`Filesystem::create_dir_with`] and
+/// [`Filesystem::create_dir_all_with`].
+///
+/// The options only apply to some platforms, on the others they get ignored. This way the same
+/// code works everywhere, e.g. creating private directories on unix, where the mode matters.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DirOptions {
+    mode: Option<u32>,
+}
+
+impl DirOptions {
+    /// Creates a new set of options, which creates directories like [`Filesystem::create_dir`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the mode to create directories with, e.g. `0o700`.
+    ///
+    /// Only used on unix platforms, where the umask of the process gets applied to it.
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+
+        self
+    }
+
+    /// Returns the mode set with [`mode`](Self::mode), if any.
+    pub fn get_mode(&self) -> Option<u32> {
+        self.mode
+    }
+}
+
+
+
+/// How [`Filesystem::copy_reflink`] copied a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReflinkOutcome {
+    /// The file got cloned, so it shares its data with the original.
+    Cloned,
+    /// The file got copied, as the filesystem could not clone it.
+    Copied,
+}
+
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// A fresh directory within the temporary directory, removed along with its contents on drop.
+    pub(super) struct ScratchDir(PathBuf);
+
+    impl ScratchDir {
+        pub(super) fn new(name: &str) -> Self {
+            static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+            let path = std::env::temp_dir().join(format!(
+                "fut-compat-{}-{}-{}",
+                name,
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed),
+            ));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+
+            Self(path)
+        }
+
+        pub(super) fn path(&self) -> &Path {
+            &self.0
+        }
+
+        pub(super) fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+            self.path().join(path)
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    pub(super) fn on_tokio<Fut: std::future::Future>(future: Fut) -> Fut::Output {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(future)
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    pub(super) fn on_async_std<Fut: std::future::Future>(future: Fut) -> Fut::Output {
+        ::async_std::task::block_on(future)
+    }
+
+    /// Runs `future`, unless io_uring is not available, e.g. in a sandbox denying it.
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    pub(super) fn on_uring<Fut: std::future::Future<Output = ()>>(future: Fut) {
+        if let Err(err) = futures::executor::block_on(UringFs::metadata(std::env::temp_dir())) {
+            eprintln!("skipping, io_uring is not available: {}", err);
+            return;
+        }
+
+        futures::executor::block_on(future)
+    }
+
+    #[cfg(unix)]
+    async fn create_dir_all_with_applies_the_mode<F: Filesystem + Send>() {
+        use std::os::unix::fs::PermissionsExt;
+
+        fn mode(path: &Path) -> u32 {
+            std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+        }
+
+        let scratch = ScratchDir::new("create-dir-all-with");
+        let parent = scratch.join("parent");
+        std::fs::create_dir(&parent).unwrap();
+        std::fs::set_permissions(&parent, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        F::create_dir_all_with(parent.join("a/b/c"), DirOptions::new().mode(0o700)).await.unwrap();
+
+        assert_eq!(mode(&parent), 0o755);
+        for dir in ["a", "a/b", "a/b/c"] {
+            assert_eq!(mode(&parent.join(dir)), 0o700, "{}", dir);
+        }
+
+        // Existing components are left as they are.
+        F::create_dir_all_with(parent.join("a/b/c/d"), DirOptions::new().mode(0o750)).await.unwrap();
+        assert_eq!(mode(&parent.join("a/b/c")), 0o700);
+        // The umask may remove the bits of the group.
+        assert_eq!(mode(&parent.join("a/b/c/d")) & !0o050, 0o700);
+
+        std::fs::write(parent.join("file"), "").unwrap();
+        let err = F::create_dir_all_with(parent.join("file/x"), DirOptions::new()).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotADirectory);
+    }
+
+    async fn vectored_writes_write_the_concatenation<F: Filesystem + Send>() {
+        let scratch = ScratchDir::new("write-vectored");
+        let path = scratch.join("payload");
+
+        // Large enough for the file to accept fewer bytes than given at once.
+        let body: Vec<u8> = (0..4 << 20).map(|i| (i % 251) as u8).collect();
+        let bufs = [
+            IoSlice::new(b"header\n"),
+            IoSlice::new(&[]),
+            IoSlice::new(&body),
+            IoSlice::new(b"\ntrailer"),
+        ];
+        let expected = [&b"header\n"[..], &body, b"\ntrailer"].concat();
+
+        std::fs::write(&path, "previous contents, which are longer than nothing").unwrap();
+        F::write_vectored(&path, &bufs).await.unwrap();
+        assert!(std::fs::read(&path).unwrap() == expected);
+
+        F::write_vectored(&path, &[]).await.unwrap();
+        assert!(std::fs::read(&path).unwrap().is_empty());
+
+        #[cfg(feature = "bytes")]
+        {
+            use ::bytes::{Buf, Bytes};
+
+            let chain = Bytes::from_static(b"header\n")
+                .chain(Bytes::from(body.clone()))
+                .chain(&b"\ntrailer"[..]);
+            F::write_bytes(&path, chain).await.unwrap();
+            assert!(std::fs::read(&path).unwrap() == expected);
+
+            // A deque only provides one chunk at a time, so it gets copied.
+            let deque: std::collections::VecDeque<u8> = expected.iter().copied().collect();
+            F::write_bytes(&path, deque).await.unwrap();
+            assert!(std::fs::read(&path).unwrap() == expected);
+        }
+    }
+
+    async fn read_if_modified_since_compares_the_times<F: Filesystem + Send>() {
+        fn set_modified(path: &Path, modified: SystemTime) {
+            let file = std::fs::File::options().write(true).open(path).unwrap();
+            file.set_modified(modified).unwrap();
+        }
+
+        let scratch = ScratchDir::new("read-if-modified-since");
+        let path = scratch.join("config");
+        // Whole seconds, which every filesystem stores exactly.
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(now.as_secs() - 3600);
+
+        std::fs::write(&path, "first").unwrap();
+        set_modified(&path, start);
+
+        // Unchanged since the last read.
+        assert_eq!(F::read_if_modified_since(&path, start).await.unwrap(), None);
+        assert_eq!(F::read_if_modified_since(&path, start + Duration::from_secs(1)).await.unwrap(), None);
+
+        let (contents, modified) = F::read_if_modified_since(&path, start - Duration::from_secs(1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((contents, modified), (b"first".to_vec(), start));
+
+        // Changed in place.
+        std::fs::write(&path, "second").unwrap();
+        set_modified(&path, start + Duration::from_secs(60));
+
+        let (contents, modified) = F::read_if_modified_since(&path, start).await.unwrap().unwrap();
+        assert_eq!((contents, modified), (b"second".to_vec(), start + Duration::from_secs(60)));
+        assert_eq!(F::read_if_modified_since(&path, modified).await.unwrap(), None);
+
+        // Replaced by renaming another file over it, the time is the one of the new file.
+        let replacement = scratch.join("config.new");
+        std::fs::write(&replacement, "third").unwrap();
+        set_modified(&replacement, start + Duration::from_secs(120));
+        std::fs::rename(&replacement, &path).unwrap();
+
+        let (contents, modified) = F::read_if_modified_since(&path, start + Duration::from_secs(60))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((contents, modified), (b"third".to_vec(), start + Duration::from_secs(120)));
+
+        let err = F::read_if_modified_since(&replacement, start).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    async fn remove_dir_contents_keeps_the_root<F>()
+    where
+        F: Filesystem + Send,
+        F::ReadDir: Send,
+        F::DirEntry: Send,
+    {
+        let scratch = ScratchDir::new("remove-dir-contents");
+        let root = scratch.join("root");
+
+        std::fs::create_dir_all(root.join("a/b/c")).unwrap();
+        std::fs::create_dir(root.join("empty")).unwrap();
+        std::fs::write(root.join("top.txt"), "top").unwrap();
+        std::fs::write(root.join("a/one.txt"), "one").unwrap();
+        std::fs::write(root.join("a/b/c/deep.txt"), "deep").unwrap();
+
+        // A link to a directory elsewhere gets removed, without touching what it points to.
+        #[cfg(unix)]
+        {
+            std::fs::create_dir(scratch.join("outside")).unwrap();
+            std::fs::write(scratch.join("outside/kept.txt"), "kept").unwrap();
+            std::os::unix::fs::symlink(scratch.join("outside"), root.join("a/link")).unwrap();
+        }
+
+        let mut permissions = std::fs::metadata(&root).unwrap().permissions();
+        #[cfg(unix)]
+        std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o750);
+        #[cfg(not(unix))]
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&root, permissions.clone()).unwrap();
+        let before = std::fs::metadata(&root).unwrap();
+
+        F::remove_dir_contents(&root).await.unwrap();
+
+        let after = std::fs::metadata(&root).unwrap();
+        assert!(after.is_dir());
+        assert_eq!(after.permissions(), before.permissions());
+        #[cfg(unix)]
+        assert_eq!(
+            std::os::unix::fs::MetadataExt::ino(&after),
+            std::os::unix::fs::MetadataExt::ino(&before),
+        );
+        assert_eq!(after.created().ok(), before.created().ok());
+        assert_eq!(std::fs::read_dir(&root).unwrap().count(), 0);
+
+        #[cfg(unix)]
+        assert_eq!(std::fs::read_to_string(scratch.join("outside/kept.txt")).unwrap(), "kept");
+
+        // An empty directory stays as it is, a missing one is an error.
+        F::remove_dir_contents(&root).await.unwrap();
+        assert!(root.is_dir());
+
+        let err = F::remove_dir_contents(scratch.join("missing")).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+        #[cfg(not(unix))]
+        {
+            #[allow(clippy::permissions_set_readonly_false)]
+            permissions.set_readonly(false);
+            std::fs::set_permissions(&root, permissions).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    async fn read_link_canonical_follows_chains<F: Filesystem + Send>() {
+        use std::os::unix::fs::symlink;
+
+        let scratch = ScratchDir::new("read-link-canonical");
+        let target = scratch.join("target.txt");
+        std::fs::write(&target, "target").unwrap();
+        std::fs::create_dir(scratch.join("sub")).unwrap();
+
+        // Two hops, `first` -> `second` -> `target.txt`, the last one absolute.
+        symlink("second", scratch.join("first")).unwrap();
+        symlink(&target, scratch.join("second")).unwrap();
+        assert_eq!(F::read_link_canonical(scratch.join("first"), 2).await.unwrap(), target);
+        assert_eq!(F::read_link_canonical(&target, 0).await.unwrap(), target);
+
+        let err = F::read_link_canonical(scratch.join("first"), 1).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // A relative target gets resolved against the directory of the link.
+        symlink("../target.txt", scratch.join("sub/up")).unwrap();
+        let resolved = F::read_link_canonical(scratch.join("sub/up"), 8).await.unwrap();
+        assert_eq!(resolved, scratch.join("sub/../target.txt"));
+        assert_eq!(std::fs::read_to_string(resolved).unwrap(), "target");
+
+        // A -> B -> A never ends.
+        symlink("b", scratch.join("a")).unwrap();
+        symlink("a", scratch.join("b")).unwrap();
+        let err = F::read_link_canonical(scratch.join("a"), 8).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("more than 8 symbolic links"), "{}", err);
+
+        let err = F::read_link_canonical(scratch.join("missing"), 8).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn touch_updates_read_only_files_without_writing() {
+        let path = std::env::temp_dir().join(format!("fut-compat-touch-{}.txt", std::process::id()));
+        let past = SystemTime::now() - Duration::from_secs(3600);
+
+        std::fs::write(&path, b"contents").unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+        file.set_times(std::fs::FileTimes::new().set_accessed(past).set_modified(past)).unwrap();
+        drop(file);
+
+        let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&path, permissions.clone()).unwrap();
+
+        let result = touch_blocking(&path);
+        let metadata = std::fs::metadata(&path).unwrap();
+        let contents = std::fs::read(&path).unwrap();
+
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        std::fs::set_permissions(&path, permissions).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        result.unwrap();
+        assert!(metadata.modified().unwrap() > past + Duration::from_secs(60));
+        assert_eq!(contents, b"contents");
+    }
+
+    #[test]
+    fn touch_creates_missing_files() {
+        let path = std::env::temp_dir().join(format!("fut-compat-touch-missing-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        touch_blocking(&path).unwrap();
+        let contents = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(contents.is_empty());
+    }
+
+    #[cfg(all(unix, feature = "tokio-rt"))]
+    #[test]
+    fn tokio_read_link_canonical_follows_chains() {
+        on_tokio(read_link_canonical_follows_chains::<TokioFs>());
+    }
+
+    #[cfg(all(unix, feature = "async-std-rt"))]
+    #[test]
+    fn async_std_read_link_canonical_follows_chains() {
+        on_async_std(read_link_canonical_follows_chains::<AsyncStdFs>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_remove_dir_contents_keeps_the_root() {
+        on_tokio(remove_dir_contents_keeps_the_root::<TokioFs>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_remove_dir_contents_keeps_the_root() {
+        on_async_std(remove_dir_contents_keeps_the_root::<AsyncStdFs>());
+    }
+
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    #[test]
+    fn uring_remove_dir_contents_keeps_the_root() {
+        on_uring(remove_dir_contents_keeps_the_root::<UringFs>());
+    }
+
+    #[cfg(all(unix, feature = "tokio-rt"))]
+    #[test]
+    fn tokio_create_dir_all_with_applies_the_mode() {
+        on_tokio(create_dir_all_with_applies_the_mode::<TokioFs>());
+    }
+
+    #[cfg(all(unix, feature = "async-std-rt"))]
+    #[test]
+    fn async_std_create_dir_all_with_applies_the_mode() {
+        on_async_std(create_dir_all_with_applies_the_mode::<AsyncStdFs>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_vectored_writes_write_the_concatenation() {
+        on_tokio(vectored_writes_write_the_concatenation::<TokioFs>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_vectored_writes_write_the_concatenation() {
+        on_async_std(vectored_writes_write_the_concatenation::<AsyncStdFs>());
+    }
+
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    #[test]
+    fn uring_vectored_writes_write_the_concatenation() {
+        on_uring(vectored_writes_write_the_concatenation::<UringFs>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_read_if_modified_since_compares_the_times() {
+        on_tokio(read_if_modified_since_compares_the_times::<TokioFs>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_read_if_modified_since_compares_the_times() {
+        on_async_std(read_if_modified_since_compares_the_times::<AsyncStdFs>());
+    }
+
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    #[test]
+    fn uring_read_if_modified_since_compares_the_times() {
+        on_uring(read_if_modified_since_compares_the_times::<UringFs>());
+    }
+}
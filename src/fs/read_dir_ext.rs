@@ -0,0 +1,539 @@
+use super::*;
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::ready;
+
+
+
+/// Combinators for filtering the entries of a directory.
+///
+/// Implemented for every stream of [`DirEntry`]s, e.g. the one returned by
+/// [`Filesystem::read_dir`]. Errors of the stream are passed through untouched.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "tokio-rt")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// use futures::stream::TryStreamExt;
+/// use fut_compat::fs::{Filesystem, ReadDirExt, TokioFs};
+///
+/// let dir = std::env::temp_dir().join("fut-compat-read-dir-ext");
+/// TokioFs::create_dir_all(dir.join("old.log")).await?;
+/// TokioFs::write(dir.join("app.log"), "").await?;
+/// TokioFs::write(dir.join("app.txt"), "").await?;
+///
+/// let logs: Vec<_> = TokioFs::read_dir(&dir).await?
+///     .files_only()
+///     .with_extension("log")
+///     .map_ok(|entry| entry.file_name())
+///     .try_collect()
+///     .await?;
+///
+/// assert_eq!(logs, ["app.log"]);
+///
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), std::io::Error>(())
+/// # }).unwrap();
+/// ```
+pub trait ReadDirExt<D: DirEntry>: Stream<Item = std::io::Result<D>> {
+    /// Keeps only the entries which are regular files.
+    ///
+    /// The file types are queried with [`DirEntry::file_type`], which does not follow symbolic
+    /// links, so links to files are skipped as well. Failing to query the file type of an entry
+    /// returns the error in its place.
+    fn files_only(self) -> FilterFileType<Self, D>
+    where
+        Self: Sized,
+    {
+        FilterFileType::new(self, FileType::is_file)
+    }
+
+    /// Keeps only the entries which are directories.
+    ///
+    /// The file types are queried with [`DirEntry::file_type`], which does not follow symbolic
+    /// links, so links to directories are skipped as well. Failing to query the file type of an
+    /// entry returns the error in its place.
+    fn dirs_only(self) -> FilterFileType<Self, D>
+    where
+        Self: Sized,
+    {
+        FilterFileType::new(self, FileType::is_dir)
+    }
+
+    /// Keeps only the entries whose name has the extension `extension`, given without the leading
+    /// dot.
+    ///
+    /// The extension is determined like by [`Path::extension`], so only the part after the last
+    /// dot is compared, e.g. `backup.tar.gz` has the extension `gz`. The comparison is case
+    /// sensitive.
+    fn with_extension<E: AsRef<OsStr>>(self, extension: E) -> FilterName<Self>
+    where
+        Self: Sized,
+    {
+        FilterName {
+            stream: self,
+            filter: NameFilter::Extension(extension.as_ref().to_os_string()),
+        }
+    }
+
+    /// Keeps only the entries whose name matches the glob `pattern`, e.g. `*.tar.*`.
+    ///
+    /// The pattern is matched against the whole name of the entry and supports the following
+    /// syntax:
+    ///
+    /// - `?` matches any single character.
+    /// - `*` matches any sequence of characters, including the empty one.
+    /// - `[abc]` and `[a-z]` match any character of the set, `[!abc]` and `[^abc]` any character
+    ///   not in the set. A `]` directly after the opening bracket is part of the set.
+    /// - `\` matches the following character literally.
+    ///
+    /// Other characters, including a `[` without a closing bracket, match themselves. Names which
+    /// are not valid UTF-8 are matched with their invalid parts replaced by `U+FFFD`.
+    fn matching_glob(self, pattern: &str) -> FilterName<Self>
+    where
+        Self: Sized,
+    {
+        FilterName {
+            stream: self,
+            filter: NameFilter::Glob(Glob::new(pattern)),
+        }
+    }
+}
+
+impl<S, D> ReadDirExt<D> for S
+where
+    S: Stream<Item = std::io::Result<D>> + ?Sized,
+    D: DirEntry,
+{}
+
+
+
+/// The future querying the file type of an entry, which returns the entry alongside.
+type FileTypeFuture<D> = Pin<Box<dyn Future<Output = (D, std::io::Result<FileType>)> + Send>>;
+
+/// The stream returned by [`ReadDirExt::files_only`] and [`ReadDirExt::dirs_only`].
+pub struct FilterFileType<S, D> {
+    stream: S,
+    pending: Option<FileTypeFuture<D>>,
+    keep: fn(&FileType) -> bool,
+}
+
+impl<S, D> FilterFileType<S, D> {
+    fn new(stream: S, keep: fn(&FileType) -> bool) -> Self {
+        Self {
+            stream,
+            pending: None,
+            keep,
+        }
+    }
+
+    /// Get a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Consumes the `FilterFileType` object and returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S, D> Stream for FilterFileType<S, D>
+where
+    S: Stream<Item = std::io::Result<D>> + Unpin,
+    D: DirEntry + Send + Sync + 'static,
+{
+    type Item = std::io::Result<D>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = Pin::into_inner(self);
+
+        loop {
+            if let Some(pending) = &mut this.pending {
+                let (entry, file_type) = ready!(pending.as_mut().poll(cx));
+                this.pending = None;
+
+                match file_type {
+                    Ok(file_type) if (this.keep)(&file_type) => return Poll::Ready(Some(Ok(entry))),
+                    Ok(_) => {}
+                    Err(err) => return Poll::Ready(Some(Err(err))),
+                }
+            }
+
+            match ready!(Pin::new(&mut this.stream).poll_next(cx)) {
+                Some(Ok(entry)) => {
+                    this.pending = Some(Box::pin(async move {
+                        let file_type = entry.file_type().await;
+
+                        (entry, file_type)
+                    }));
+                }
+                item => return Poll::Ready(item),
+            }
+        }
+    }
+}
+
+impl<S: fmt::Debug, D> fmt::Debug for FilterFileType<S, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilterFileType")
+            .field("stream", &self.stream)
+            .finish_non_exhaustive()
+    }
+}
+
+
+
+/// The stream returned by [`ReadDirExt::with_extension`] and [`ReadDirExt::matching_glob`].
+#[derive(Debug)]
+pub struct FilterName<S> {
+    stream: S,
+    filter: NameFilter,
+}
+
+#[derive(Debug)]
+enum NameFilter {
+    Extension(OsString),
+    Glob(Glob),
+}
+
+impl NameFilter {
+    fn matches(&self, name: &OsStr) -> bool {
+        match self {
+            NameFilter::Extension(extension) => Path::new(name).extension() == Some(extension.as_os_str()),
+            NameFilter::Glob(glob) => glob.matches(&name.to_string_lossy()),
+        }
+    }
+}
+
+impl<S> FilterName<S> {
+    /// Get a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Consumes the `FilterName` object and returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S, D> Stream for FilterName<S>
+where
+    S: Stream<Item = std::io::Result<D>> + Unpin,
+    D: DirEntry,
+{
+    type Item = std::io::Result<D>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = Pin::into_inner(self);
+
+        loop {
+            match ready!(Pin::new(&mut this.stream).poll_next(cx)) {
+                Some(Ok(entry)) if !this.filter.matches(&entry.file_name()) => {}
+                item => return Poll::Ready(item),
+            }
+        }
+    }
+}
+
+
+
+/// A token of a compiled glob pattern.
+#[derive(Debug)]
+enum GlobToken {
+    /// Matches this character.
+    Literal(char),
+    /// Matches any character, `?`.
+    Any,
+    /// Matches any sequence of characters, `*`.
+    Star,
+    /// Matches any character within one of the inclusive ranges, or outside all of them if
+    /// `negated`.
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+/// A compiled glob pattern, matched against file names.
+#[derive(Debug)]
+struct Glob {
+    tokens: Vec<GlobToken>,
+}
+
+impl Glob {
+    fn new(pattern: &str) -> Self {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let token = match chars[i] {
+                '?' => GlobToken::Any,
+                '*' => GlobToken::Star,
+                '\\' if i + 1 < chars.len() => {
+                    i += 1;
+
+                    GlobToken::Literal(chars[i])
+                }
+                '[' => match Self::parse_class(&chars[i + 1..]) {
+                    Some((token, len)) => {
+                        i += len;
+
+                        token
+                    }
+                    None => GlobToken::Literal('['),
+                },
+                c => GlobToken::Literal(c),
+            };
+
+            tokens.push(token);
+            i += 1;
+        }
+
+        Self {
+            tokens,
+        }
+    }
+
+    /// Parses the character class following an opening bracket, returning it and the number of
+    /// characters it spans including the closing bracket, or `None` if it is not closed.
+    fn parse_class(chars: &[char]) -> Option<(GlobToken, usize)> {
+        let mut i = 0;
+
+        let negated = matches!(chars.first(), Some('!' | '^'));
+        if negated {
+            i += 1;
+        }
+
+        let mut ranges = Vec::new();
+        let start = i;
+
+        loop {
+            let c = *chars.get(i)?;
+
+            if c == ']' && i > start {
+                return Some((GlobToken::Class { negated, ranges }, i + 1));
+            }
+
+            match chars.get(i + 1..i + 3) {
+                Some(&['-', end]) if end != ']' => {
+                    ranges.push((c, end));
+                    i += 3;
+                }
+                _ => {
+                    ranges.push((c, c));
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        let name: Vec<char> = name.chars().collect();
+
+        let (mut t, mut n) = (0, 0);
+        // The position of the last `*` and of the name where it started matching, to backtrack
+        // to with the `*` matching one more character.
+        let mut backtrack = None;
+
+        while n < name.len() {
+            let matched = match self.tokens.get(t) {
+                Some(GlobToken::Star) => {
+                    backtrack = Some((t, n));
+                    t += 1;
+
+                    continue;
+                }
+                Some(GlobToken::Literal(c)) => *c == name[n],
+                Some(GlobToken::Any) => true,
+                Some(GlobToken::Class { negated, ranges }) => {
+                    ranges.iter().any(|&(start, end)| (start..=end).contains(&name[n])) != *negated
+                }
+                None => false,
+            };
+
+            if matched {
+                t += 1;
+                n += 1;
+            } else if let Some((star, start)) = backtrack {
+                t = star + 1;
+                n = start + 1;
+                backtrack = Some((star, start + 1));
+            } else {
+                return false;
+            }
+        }
+
+        self.tokens[t..].iter().all(|token| matches!(token, GlobToken::Star))
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::stream;
+
+    /// An entry with a fixed name, whose file type is the one of `file_type`, or an error.
+    struct MockEntry {
+        name: &'static str,
+        file_type: Result<FileType, std::io::ErrorKind>,
+    }
+
+    #[async_trait]
+    impl DirEntry for MockEntry {
+        fn path(&self) -> PathBuf {
+            PathBuf::from(self.name)
+        }
+
+        fn file_name(&self) -> OsString {
+            self.name.into()
+        }
+
+        async fn metadata(&self) -> std::io::Result<Metadata> {
+            Err(std::io::ErrorKind::Unsupported.into())
+        }
+
+        async fn file_type(&self) -> std::io::Result<FileType> {
+            self.file_type.map_err(std::io::Error::from)
+        }
+    }
+
+    fn dir_type() -> FileType {
+        std::fs::metadata(std::env::temp_dir()).unwrap().file_type()
+    }
+
+    /// Collects the names of the entries, or the kinds of the errors.
+    fn names<S>(stream: S) -> Vec<Result<&'static str, std::io::ErrorKind>>
+    where
+        S: Stream<Item = std::io::Result<MockEntry>>,
+    {
+        use futures::stream::StreamExt;
+
+        block_on(stream.map(|entry| entry.map(|entry| entry.name).map_err(|err| err.kind())).collect())
+    }
+
+    fn entries() -> stream::Iter<std::vec::IntoIter<std::io::Result<MockEntry>>> {
+        stream::iter(vec![
+            Ok(MockEntry { name: "a.log", file_type: Ok(dir_type()) }),
+            Err(std::io::ErrorKind::PermissionDenied.into()),
+            Ok(MockEntry { name: "b.log", file_type: Err(std::io::ErrorKind::NotFound) }),
+            Ok(MockEntry { name: "c.txt", file_type: Ok(dir_type()) }),
+        ])
+    }
+
+    #[test]
+    fn errors_are_passed_through() {
+        use std::io::ErrorKind::*;
+
+        assert_eq!(names(entries().dirs_only()), [Ok("a.log"), Err(PermissionDenied), Err(NotFound), Ok("c.txt")]);
+        assert_eq!(names(entries().files_only()), [Err(PermissionDenied), Err(NotFound)]);
+        assert_eq!(names(entries().with_extension("log")), [Ok("a.log"), Err(PermissionDenied), Ok("b.log")]);
+        assert_eq!(names(entries().matching_glob("c*")), [Err(PermissionDenied), Ok("c.txt")]);
+    }
+
+    #[test]
+    fn globs_match_whole_names() {
+        let cases = [
+            ("*.log", "app.log", true),
+            ("*.log", "app.log.1", false),
+            ("*.tar.*", "backup.tar.gz", true),
+            ("*.tar.*", "backup.tar", false),
+            ("a?c", "abc", true),
+            ("a?c", "ac", false),
+            ("*", "", true),
+            ("a*b*c", "aXbYbZc", true),
+            ("a*b*c", "aXbYbZ", false),
+            ("[abc]x", "bx", true),
+            ("[a-c]x", "dx", false),
+            ("[!a-c]x", "dx", true),
+            ("[^a-c]x", "ax", false),
+            ("[]]", "]", true),
+            ("[a-]", "-", true),
+            ("\\*", "*", true),
+            ("\\*", "a", false),
+            ("[abc", "[abc", true),
+            ("ä?", "äö", true),
+        ];
+
+        for (pattern, name, expected) in cases {
+            assert_eq!(Glob::new(pattern).matches(name), expected, "{:?} on {:?}", pattern, name);
+        }
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn filters_pick_the_entries_of_a_mixed_directory() {
+        use futures::stream::TryStreamExt;
+
+        use super::super::tests::ScratchDir;
+
+        async fn names<S, D>(stream: S) -> Vec<String>
+        where
+            S: Stream<Item = std::io::Result<D>>,
+            D: DirEntry,
+        {
+            let mut names: Vec<String> = stream
+                .map_ok(|entry| entry.file_name().into_string().unwrap())
+                .try_collect()
+                .await
+                .unwrap();
+            names.sort();
+
+            names
+        }
+
+        let scratch = ScratchDir::new("read-dir-ext");
+        let dir = scratch.join("dir");
+
+        std::fs::create_dir_all(dir.join("data")).unwrap();
+        std::fs::create_dir(dir.join("old.log")).unwrap();
+        for name in ["app.log", "APP.LOG", "notes.txt", "backup.tar.gz", "log"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("app.log", dir.join("link.log")).unwrap();
+            std::os::unix::fs::symlink("data", dir.join("link")).unwrap();
+        }
+
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let read_dir = || async { TokioFs::read_dir(&dir).await.unwrap() };
+
+            // Links are neither files nor directories.
+            assert_eq!(
+                names(read_dir().await.files_only()).await,
+                ["APP.LOG", "app.log", "backup.tar.gz", "log", "notes.txt"],
+            );
+            assert_eq!(names(read_dir().await.dirs_only()).await, ["data", "old.log"]);
+
+            let mut logs = vec!["app.log", "old.log"];
+            if cfg!(unix) {
+                logs.insert(1, "link.log");
+            }
+            assert_eq!(names(read_dir().await.with_extension("log")).await, logs);
+            assert_eq!(names(read_dir().await.files_only().with_extension("log")).await, ["app.log"]);
+            assert_eq!(names(read_dir().await.dirs_only().with_extension("log")).await, ["old.log"]);
+            assert_eq!(names(read_dir().await.with_extension("gz")).await, ["backup.tar.gz"]);
+
+            assert_eq!(names(read_dir().await.matching_glob("*.tar.*")).await, ["backup.tar.gz"]);
+            assert_eq!(names(read_dir().await.matching_glob("[A-Z]*")).await, ["APP.LOG"]);
+            assert_eq!(
+                names(read_dir().await.files_only().matching_glob("*o*")).await,
+                ["app.log", "log", "notes.txt"],
+            );
+            assert!(names(read_dir().await.matching_glob("*.md")).await.is_empty());
+        });
+    }
+}
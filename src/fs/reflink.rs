@@ -0,0 +1,185 @@
+use super::ReflinkOutcome;
+
+use std::path::Path;
+
+
+
+/// Returns `true` if `err` means that the filesystem can not clone the file, so it has to be
+/// copied instead.
+#[cfg(any(target_os = "linux", target_os = "android", target_vendor = "apple"))]
+fn unsupported(err: &std::io::Error) -> bool {
+    let Some(code) = err.raw_os_error() else {
+        return false;
+    };
+
+    // `EOPNOTSUPP` and `ENOTSUP` are the same on some platforms, so they can not share a pattern.
+    code == libc::EOPNOTSUPP
+        || code == libc::ENOTSUP
+        || matches!(code, libc::EXDEV | libc::EINVAL | libc::ENOTTY | libc::ENOSYS)
+}
+
+/// Copies `from` to `to`, cloning it if the filesystem supports it.
+///
+/// This blocks, so it has to run on the blocking pool of the runtime.
+pub(super) fn copy_reflink(from: &Path, to: &Path) -> std::io::Result<ReflinkOutcome> {
+    #[cfg(any(target_os = "linux", target_os = "android", target_vendor = "apple"))]
+    return clone_or_copy(from, to, clone);
+
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_vendor = "apple")))]
+    {
+        std::fs::copy(from, to)?;
+
+        Ok(ReflinkOutcome::Copied)
+    }
+}
+
+/// Clones `from` into `to` with `clone`, or copies it if the filesystem does not support that.
+#[cfg(any(target_os = "linux", target_os = "android", target_vendor = "apple"))]
+fn clone_or_copy<C>(from: &Path, to: &Path, clone: C) -> std::io::Result<ReflinkOutcome>
+where
+    C: FnOnce(&Path, &Path) -> std::io::Result<()>,
+{
+    match clone(from, to) {
+        Ok(()) => return Ok(ReflinkOutcome::Cloned),
+        Err(err) if unsupported(&err) => {}
+        Err(err) => return Err(err),
+    }
+
+    std::fs::copy(from, to)?;
+
+    Ok(ReflinkOutcome::Copied)
+}
+
+/// Clones `from` into `to` with the `FICLONE` ioctl, which requires both to be on the same
+/// filesystem.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn clone(from: &Path, to: &Path) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let src = std::fs::File::open(from)?;
+    let metadata = src.metadata()?;
+    if !metadata.is_file() {
+        // Let the copy report the error.
+        return Err(std::io::Error::from_raw_os_error(libc::EINVAL));
+    }
+
+    let dst = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(to)?;
+
+    // SAFETY: Both descriptors stay open for the duration of the call.
+    if unsafe { libc::ioctl(dst.as_raw_fd(), libc::FICLONE, src.as_raw_fd()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    dst.set_permissions(metadata.permissions())
+}
+
+/// Clones `from` into `to` with `clonefile`, which requires both to be on the same APFS volume
+/// and `to` to not exist yet.
+#[cfg(target_vendor = "apple")]
+fn clone(from: &Path, to: &Path) -> std::io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    // `clonefile` clones symbolic links themselves, while copying follows them.
+    if !std::fs::symlink_metadata(from)?.is_file() {
+        return Err(std::io::Error::from_raw_os_error(libc::ENOTSUP));
+    }
+
+    let src = CString::new(from.as_os_str().as_bytes())?;
+    let dst = CString::new(to.as_os_str().as_bytes())?;
+
+    // SAFETY: Both strings stay valid for the duration of the call.
+    if unsafe { libc::clonefile(src.as_ptr(), dst.as_ptr(), 0) } != 0 {
+        let err = std::io::Error::last_os_error();
+
+        // Unlike copying, cloning can not replace an existing file.
+        if err.raw_os_error() == Some(libc::EEXIST) {
+            return Err(std::io::Error::from_raw_os_error(libc::ENOTSUP));
+        }
+
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::super::tests::ScratchDir;
+
+    /// Creates a source file with contents and permissions which have to be preserved.
+    fn source(scratch: &ScratchDir) -> std::path::PathBuf {
+        let from = scratch.join("from");
+
+        std::fs::write(&from, "contents to copy").unwrap();
+        std::fs::set_permissions(&from, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        from
+    }
+
+    fn assert_copied(to: &Path) {
+        assert_eq!(std::fs::read_to_string(to).unwrap(), "contents to copy");
+        assert_eq!(std::fs::metadata(to).unwrap().permissions().mode() & 0o777, 0o640);
+    }
+
+    #[test]
+    fn unsupported_clones_fall_back_to_copying() {
+        let scratch = ScratchDir::new("reflink-fallback");
+        let from = source(&scratch);
+
+        for code in [libc::EOPNOTSUPP, libc::EXDEV, libc::EINVAL, libc::ENOTTY, libc::ENOSYS] {
+            let to = scratch.join(format!("to-{}", code));
+
+            // Like `FICLONE` failing after the destination has been created.
+            let outcome = clone_or_copy(&from, &to, |_, to| {
+                std::fs::write(to, "partial").unwrap();
+                Err(std::io::Error::from_raw_os_error(code))
+            });
+
+            assert_eq!(outcome.unwrap(), ReflinkOutcome::Copied);
+            assert_copied(&to);
+        }
+    }
+
+    #[test]
+    fn other_clone_errors_are_returned() {
+        let scratch = ScratchDir::new("reflink-error");
+        let from = source(&scratch);
+        let to = scratch.join("to");
+
+        let err = clone_or_copy(&from, &to, |_, _| Err(std::io::Error::from_raw_os_error(libc::EACCES)))
+            .unwrap_err();
+
+        assert_eq!(err.raw_os_error(), Some(libc::EACCES));
+        assert!(!to.exists());
+    }
+
+    #[test]
+    fn copies_preserve_the_permissions_either_way() {
+        let scratch = ScratchDir::new("reflink");
+        let from = source(&scratch);
+        let to = scratch.join("to");
+
+        // Clones where the filesystem supports it, e.g. on btrfs or XFS, and copies elsewhere.
+        let outcome = copy_reflink(&from, &to).unwrap();
+        assert_copied(&to);
+
+        // Replacing the existing destination takes the same path.
+        std::fs::write(&to, "old contents, which are longer than the new ones").unwrap();
+        assert_eq!(copy_reflink(&from, &to).unwrap(), outcome);
+        assert_copied(&to);
+
+        // A directory can not be cloned, so copying it reports the error.
+        assert!(copy_reflink(scratch.path(), &scratch.join("dir")).is_err());
+    }
+}
@@ -0,0 +1,636 @@
+use super::*;
+
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::task::{timeout, Timer};
+
+
+
+/// The error returned when the operation `op` on `paths` did not complete within `duration`.
+fn timed_out(op: &str, paths: &[&Path], duration: Duration) -> std::io::Error {
+    let paths = paths
+        .iter()
+        .map(|path| format!("`{}`", path.display()))
+        .collect::<Vec<_>>()
+        .join(" to ");
+
+    std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        format!("`{op}` of {paths} timed out after {duration:?}"),
+    )
+}
+
+/// Wraps the [`Filesystem`] `F` and fails every operation which does not complete within a
+/// deadline, measured with the timer `Tm`.
+///
+/// Filesystems like NFS or FUSE mounts can hang indefinitely. With this wrapper a hanging operation
+/// fails with an error of kind [`TimedOut`](std::io::ErrorKind::TimedOut) instead, whose message
+/// names the operation and its paths, e.g. ``"`read` of `/mnt/nfs/data` timed out after 5s"``.
+///
+/// As the functions of [`Filesystem`] take no `self`, they are provided as methods here, which
+/// call the ones of `F` within [`timeout`](crate::task::timeout). The stream returned by
+/// [`read_dir`](Self::read_dir) applies the deadline to every entry separately.
+///
+/// Note that operations which run on a thread pool keep running after the deadline elapsed, only
+/// waiting for them gets cancelled.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "tokio-rt")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// use std::time::Duration;
+///
+/// use fut_compat::fs::{TimeoutFs, TokioFs};
+/// use fut_compat::task::TokioExecutor;
+///
+/// let fs = TimeoutFs::<TokioFs, TokioExecutor>::new(Duration::from_secs(5));
+///
+/// let path = std::env::temp_dir().join("fut-compat-timeout-fs.txt");
+/// fs.write(&path, "hello").await?;
+///
+/// assert_eq!(fs.read_to_string(&path).await?, "hello");
+///
+/// fs.remove_file(&path).await?;
+/// # Ok::<(), std::io::Error>(())
+/// # }).unwrap();
+/// ```
+pub struct TimeoutFs<F, Tm> {
+    duration: Duration,
+    _marker: PhantomData<fn() -> (F, Tm)>,
+}
+
+impl<F, Tm> TimeoutFs<F, Tm> {
+    /// Creates a new instance which fails every operation not completing within `duration`.
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the duration every operation has to complete within.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+impl<F: Filesystem + Send, Tm: Timer> TimeoutFs<F, Tm> {
+    /// Runs `future`, the operation `op` on `paths`, within the deadline.
+    async fn run<T, Fut>(&self, op: &str, paths: &[&Path], future: Fut) -> std::io::Result<T>
+    where
+        Fut: Future<Output = std::io::Result<T>>,
+    {
+        match timeout::<Tm, _>(self.duration, future).await {
+            Ok(result) => result,
+            Err(_) => Err(timed_out(op, paths, self.duration)),
+        }
+    }
+
+    /// See [`Filesystem::canonicalize`].
+    pub async fn canonicalize<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<PathBuf> {
+        let path = path.as_ref();
+
+        self.run("canonicalize", &[path], F::canonicalize(path)).await
+    }
+
+    /// See [`Filesystem::copy`].
+    pub async fn copy<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        &self,
+        from: S,
+        to: D,
+    ) -> std::io::Result<u64> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+
+        self.run("copy", &[from, to], F::copy(from, to)).await
+    }
+
+    /// See [`Filesystem::copy_reflink`].
+    pub async fn copy_reflink<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        &self,
+        from: S,
+        to: D,
+    ) -> std::io::Result<ReflinkOutcome> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+
+        self.run("copy_reflink", &[from, to], F::copy_reflink(from, to)).await
+    }
+
+    /// See [`Filesystem::create_dir`].
+    pub async fn create_dir<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        self.run("create_dir", &[path], F::create_dir(path)).await
+    }
+
+    /// See [`Filesystem::create_dir_all`].
+    pub async fn create_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        self.run("create_dir_all", &[path], F::create_dir_all(path)).await
+    }
+
+    /// See [`Filesystem::create_dir_with`].
+    pub async fn create_dir_with<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        options: DirOptions,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        self.run("create_dir_with", &[path], F::create_dir_with(path, options)).await
+    }
+
+    /// See [`Filesystem::create_dir_all_with`].
+    pub async fn create_dir_all_with<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        options: DirOptions,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        self.run("create_dir_all_with", &[path], F::create_dir_all_with(path, options)).await
+    }
+
+    /// See [`Filesystem::hard_link`].
+    pub async fn hard_link<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        &self,
+        from: S,
+        to: D,
+    ) -> std::io::Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+
+        self.run("hard_link", &[from, to], F::hard_link(from, to)).await
+    }
+
+    /// See [`Filesystem::metadata`].
+    pub async fn metadata<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<Metadata> {
+        let path = path.as_ref();
+
+        self.run("metadata", &[path], F::metadata(path)).await
+    }
+
+    /// See [`Filesystem::read`].
+    pub async fn read<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<Vec<u8>> {
+        let path = path.as_ref();
+
+        self.run("read", &[path], F::read(path)).await
+    }
+
+    /// See [`Filesystem::read_dir`].
+    ///
+    /// Opening the directory and reading each entry of the returned stream have to complete within
+    /// the deadline separately.
+    pub async fn read_dir<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+    ) -> std::io::Result<TimeoutReadDir<F::ReadDir, Tm>> {
+        let path = path.as_ref();
+        let inner = self.run("read_dir", &[path], F::read_dir(path)).await?;
+
+        Ok(TimeoutReadDir {
+            inner,
+            sleep: Box::pin(Tm::sleep(self.duration)),
+            armed: false,
+            duration: self.duration,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// See [`Filesystem::read_if_modified_since`].
+    pub async fn read_if_modified_since<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        since: SystemTime,
+    ) -> std::io::Result<Option<(Vec<u8>, SystemTime)>> {
+        let path = path.as_ref();
+
+        self.run("read_if_modified_since", &[path], F::read_if_modified_since(path, since)).await
+    }
+
+    /// See [`Filesystem::read_link`].
+    pub async fn read_link<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<PathBuf> {
+        let path = path.as_ref();
+
+        self.run("read_link", &[path], F::read_link(path)).await
+    }
+
+    /// See [`Filesystem::read_link_canonical`].
+    pub async fn read_link_canonical<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        max_hops: u32,
+    ) -> std::io::Result<PathBuf> {
+        let path = path.as_ref();
+
+        self.run("read_link_canonical", &[path], F::read_link_canonical(path, max_hops)).await
+    }
+
+    /// See [`Filesystem::read_to_string`].
+    pub async fn read_to_string<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<String> {
+        let path = path.as_ref();
+
+        self.run("read_to_string", &[path], F::read_to_string(path)).await
+    }
+
+    /// See [`Filesystem::remove_dir`].
+    pub async fn remove_dir<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        self.run("remove_dir", &[path], F::remove_dir(path)).await
+    }
+
+    /// See [`Filesystem::remove_dir_all`].
+    pub async fn remove_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        self.run("remove_dir_all", &[path], F::remove_dir_all(path)).await
+    }
+
+    /// See [`Filesystem::remove_dir_contents`].
+    pub async fn remove_dir_contents<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()>
+    where
+        F::ReadDir: Send,
+        F::DirEntry: Send,
+    {
+        let path = path.as_ref();
+
+        self.run("remove_dir_contents", &[path], F::remove_dir_contents(path)).await
+    }
+
+    /// See [`Filesystem::remove_file`].
+    pub async fn remove_file<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        self.run("remove_file", &[path], F::remove_file(path)).await
+    }
+
+    /// See [`Filesystem::rename`].
+    pub async fn rename<O: AsRef<Path> + Send, N: AsRef<Path> + Send>(
+        &self,
+        from: O,
+        to: N,
+    ) -> std::io::Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+
+        self.run("rename", &[from, to], F::rename(from, to)).await
+    }
+
+    /// See [`Filesystem::set_permissions`].
+    pub async fn set_permissions<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        perm: Permissions,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        self.run("set_permissions", &[path], F::set_permissions(path, perm)).await
+    }
+
+    /// See [`Filesystem::symlink_metadata`].
+    pub async fn symlink_metadata<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<Metadata> {
+        let path = path.as_ref();
+
+        self.run("symlink_metadata", &[path], F::symlink_metadata(path)).await
+    }
+
+    /// See [`Filesystem::touch`].
+    pub async fn touch<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        self.run("touch", &[path], F::touch(path)).await
+    }
+
+    /// See [`Filesystem::write`].
+    pub async fn write<P: AsRef<Path> + Send, C: AsRef<[u8]> + Send>(
+        &self,
+        path: P,
+        contents: C,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        self.run("write", &[path], F::write(path, contents)).await
+    }
+
+    /// See [`Filesystem::write_vectored`].
+    pub async fn write_vectored<P: AsRef<Path> + Send>(
+        &self,
+        path: P,
+        bufs: &[IoSlice<'_>],
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        self.run("write_vectored", &[path], F::write_vectored(path, bufs)).await
+    }
+
+    /// See [`Filesystem::write_bytes`].
+    #[cfg(feature = "bytes")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+    pub async fn write_bytes<P: AsRef<Path> + Send, B: Buf + Send>(
+        &self,
+        path: P,
+        buf: B,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+
+        self.run("write_bytes", &[path], F::write_bytes(path, buf)).await
+    }
+}
+
+impl<F, Tm> Clone for TimeoutFs<F, Tm> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<F, Tm> Copy for TimeoutFs<F, Tm> {}
+
+impl<F, Tm> fmt::Debug for TimeoutFs<F, Tm> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimeoutFs")
+            .field("duration", &self.duration)
+            .finish()
+    }
+}
+
+
+
+/// The entries of a directory read by [`TimeoutFs::read_dir`].
+///
+/// Every entry has to be read within the deadline, which starts once waiting for the entry starts.
+/// If it elapses, an error of kind [`TimedOut`](std::io::ErrorKind::TimedOut) is returned. The
+/// stream can be polled again afterwards, which waits for the same entry within a new deadline.
+pub struct TimeoutReadDir<S, Tm: Timer> {
+    inner: S,
+    sleep: Pin<Box<Tm::Sleep>>,
+    armed: bool,
+    duration: Duration,
+    path: PathBuf,
+}
+
+impl<S, Tm: Timer> TimeoutReadDir<S, Tm> {
+    /// Get a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consumes the `TimeoutReadDir` object and returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, Tm, T> Stream for TimeoutReadDir<S, Tm>
+where
+    S: Stream<Item = std::io::Result<T>> + Unpin,
+    Tm: Timer,
+{
+    type Item = std::io::Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = Pin::into_inner(self);
+
+        if !this.armed {
+            Tm::reset(this.sleep.as_mut(), Tm::now() + this.duration);
+            this.armed = true;
+        }
+
+        if let Poll::Ready(item) = Pin::new(&mut this.inner).poll_next(cx) {
+            this.armed = false;
+
+            return Poll::Ready(item);
+        }
+
+        match this.sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                this.armed = false;
+
+                Poll::Ready(Some(Err(timed_out("read_dir", &[&this.path], this.duration))))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S, Tm: Timer> fmt::Debug for TimeoutReadDir<S, Tm> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimeoutReadDir")
+            .field("duration", &self.duration)
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    use futures::channel::mpsc;
+    use futures::executor::block_on;
+    use futures::{FutureExt, StreamExt};
+
+    use crate::task::MockTimer;
+
+    type Entries = mpsc::UnboundedReceiver<std::io::Result<NoDirEntry>>;
+
+    thread_local! {
+        /// The entries returned by the next [`StallingFs::read_dir`] on this thread.
+        static ENTRIES: RefCell<Option<Entries>> = const { RefCell::new(None) };
+    }
+
+    /// Never completes, like a call to a hung network filesystem.
+    async fn stall<T>() -> std::io::Result<T> {
+        futures::future::pending().await
+    }
+
+    /// A filesystem whose calls never complete, except for opening directories, whose entries get
+    /// sent by the test.
+    struct StallingFs;
+
+    struct NoDirEntry;
+
+    #[async_trait]
+    impl DirEntry for NoDirEntry {
+        fn path(&self) -> PathBuf {
+            PathBuf::new()
+        }
+
+        fn file_name(&self) -> std::ffi::OsString {
+            std::ffi::OsString::new()
+        }
+
+        async fn metadata(&self) -> std::io::Result<Metadata> {
+            stall().await
+        }
+
+        async fn file_type(&self) -> std::io::Result<FileType> {
+            stall().await
+        }
+    }
+
+    #[async_trait]
+    impl Filesystem for StallingFs {
+        type ReadDir = Entries;
+        type DirEntry = NoDirEntry;
+
+        async fn canonicalize<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<PathBuf> {
+            stall().await
+        }
+
+        async fn copy<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+            _from: S,
+            _to: D,
+        ) -> std::io::Result<u64> {
+            stall().await
+        }
+
+        async fn create_dir<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+            stall().await
+        }
+
+        async fn create_dir_all<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+            stall().await
+        }
+
+        async fn hard_link<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+            _from: S,
+            _to: D,
+        ) -> std::io::Result<()> {
+            stall().await
+        }
+
+        async fn metadata<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<Metadata> {
+            stall().await
+        }
+
+        async fn read<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<Vec<u8>> {
+            stall().await
+        }
+
+        async fn read_dir<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<Self::ReadDir> {
+            Ok(ENTRIES.with(|entries| entries.borrow_mut().take()).expect("no entries to read"))
+        }
+
+        async fn read_link<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<PathBuf> {
+            stall().await
+        }
+
+        async fn read_to_string<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<String> {
+            stall().await
+        }
+
+        async fn remove_dir<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+            stall().await
+        }
+
+        async fn remove_dir_all<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+            stall().await
+        }
+
+        async fn remove_file<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+            stall().await
+        }
+
+        async fn rename<O: AsRef<Path> + Send, N: AsRef<Path> + Send>(
+            _from: O,
+            _to: N,
+        ) -> std::io::Result<()> {
+            stall().await
+        }
+
+        async fn set_permissions<P: AsRef<Path> + Send>(
+            _path: P,
+            _perm: Permissions,
+        ) -> std::io::Result<()> {
+            stall().await
+        }
+
+        async fn symlink_metadata<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<Metadata> {
+            stall().await
+        }
+
+        async fn write<P: AsRef<Path> + Send, C: AsRef<[u8]> + Send>(
+            _path: P,
+            _contents: C,
+        ) -> std::io::Result<()> {
+            stall().await
+        }
+    }
+
+    fn fs() -> TimeoutFs<StallingFs, MockTimer> {
+        TimeoutFs::new(Duration::from_secs(5))
+    }
+
+    #[test]
+    fn stalled_operations_time_out_naming_the_operation_and_paths() {
+        let fs = fs();
+
+        let mut read = Box::pin(fs.read("/mnt/nfs/data"));
+        assert!((&mut read).now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_secs(4));
+        assert!((&mut read).now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_secs(1));
+        let err = block_on(read).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert_eq!(err.to_string(), "`read` of `/mnt/nfs/data` timed out after 5s");
+
+        let mut rename = Box::pin(fs.rename("/mnt/nfs/a", "/mnt/nfs/b"));
+        assert!((&mut rename).now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_secs(5));
+        let err = block_on(rename).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert!(err.to_string().contains("`rename`"), "{}", err);
+        assert!(err.to_string().contains("`/mnt/nfs/a` to `/mnt/nfs/b`"), "{}", err);
+    }
+
+    #[test]
+    fn read_dir_applies_the_deadline_to_every_entry() {
+        let fs = fs();
+        let (entries, receiver) = mpsc::unbounded();
+        ENTRIES.with(|current| *current.borrow_mut() = Some(receiver));
+
+        let mut dir = block_on(fs.read_dir("/mnt/nfs")).unwrap();
+
+        // Every entry arrives within the deadline, even though reading all of them takes longer.
+        for _ in 0..3 {
+            assert!(dir.next().now_or_never().is_none());
+
+            MockTimer::advance(Duration::from_secs(4));
+            assert!(dir.next().now_or_never().is_none());
+
+            entries.unbounded_send(Ok(NoDirEntry)).unwrap();
+            assert!(matches!(dir.next().now_or_never(), Some(Some(Ok(_)))));
+        }
+
+        // The next one does not.
+        assert!(dir.next().now_or_never().is_none());
+        MockTimer::advance(Duration::from_secs(5));
+
+        let err = match dir.next().now_or_never() {
+            Some(Some(Err(err))) => err,
+            _ => panic!("the entry did not time out"),
+        };
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert!(err.to_string().contains("`read_dir` of `/mnt/nfs`"), "{}", err);
+
+        // Waiting for the entry again starts a new deadline.
+        assert!(dir.next().now_or_never().is_none());
+        entries.unbounded_send(Ok(NoDirEntry)).unwrap();
+        assert!(matches!(dir.next().now_or_never(), Some(Some(Ok(_)))));
+
+        drop(entries);
+        assert!(matches!(dir.next().now_or_never(), Some(None)));
+    }
+}
@@ -30,6 +30,16 @@ impl Filesystem for TokioFs {
         fs::copy(from, to).await
     }
 
+    async fn copy_reflink<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        from: S,
+        to: D,
+    ) -> std::io::Result<ReflinkOutcome> {
+        let from = from.as_ref().to_owned();
+        let to = to.as_ref().to_owned();
+
+        ::tokio::task::spawn_blocking(move || reflink::copy_reflink(&from, &to)).await?
+    }
+
     async fn create_dir<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
         fs::create_dir(path).await
     }
@@ -38,6 +48,30 @@ impl Filesystem for TokioFs {
         fs::create_dir_all(path).await
     }
 
+    async fn create_dir_with<P: AsRef<Path> + Send>(
+        path: P,
+        options: DirOptions,
+    ) -> std::io::Result<()> {
+        #[cfg(unix)]
+        let builder = {
+            let mut builder = fs::DirBuilder::new();
+
+            if let Some(mode) = options.get_mode() {
+                builder.mode(mode);
+            }
+
+            builder
+        };
+        #[cfg(not(unix))]
+        let builder = {
+            let _ = options;
+
+            fs::DirBuilder::new()
+        };
+
+        builder.create(path).await
+    }
+
     async fn hard_link<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
         from: S,
         to: D,
@@ -57,6 +91,15 @@ impl Filesystem for TokioFs {
         fs::read_dir(path).await.map(|read_dir| ReadDirStream::new(read_dir))
     }
 
+    async fn read_if_modified_since<P: AsRef<Path> + Send>(
+        path: P,
+        since: SystemTime,
+    ) -> std::io::Result<Option<(Vec<u8>, SystemTime)>> {
+        let path = path.as_ref().to_owned();
+
+        ::tokio::task::spawn_blocking(move || read_if_modified_since_blocking(&path, since)).await?
+    }
+
     async fn read_link<P: AsRef<Path> + Send>(path: P) -> std::io::Result<PathBuf> {
         fs::read_link(path).await
     }
@@ -70,7 +113,7 @@ impl Filesystem for TokioFs {
     }
 
     async fn remove_dir_all<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
-        fs::remove_dir(path).await
+        fs::remove_dir_all(path).await
     }
 
     async fn remove_file<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
@@ -95,12 +138,59 @@ impl Filesystem for TokioFs {
         fs::symlink_metadata(path).await
     }
 
+    async fn touch<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref().to_owned();
+
+        ::tokio::task::spawn_blocking(move || touch_blocking(&path)).await?
+    }
+
     async fn write<P: AsRef<Path> + Send, C: AsRef<[u8]> + Send>(
         path: P,
         contents: C
     ) -> std::io::Result<()> {
         fs::write(path, contents).await
     }
+
+    async fn write_vectored<P: AsRef<Path> + Send>(
+        path: P,
+        bufs: &[IoSlice<'_>],
+    ) -> std::io::Result<()> {
+        use futures::io::AsyncWriteExt;
+
+        let mut file = TokioCompat::new(fs::File::create(path).await?);
+
+        crate::io::write_all_vectored(&mut file, &mut bufs.to_vec()).await?;
+
+        // Writes of tokio's files complete in the background, flushing waits for them.
+        file.flush().await
+    }
+}
+
+#[cfg(unix)]
+#[async_trait]
+impl SecureOpen for TokioFs {
+    type File = fs::File;
+
+    async fn open_no_follow<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self::File> {
+        fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(path)
+            .await
+    }
+
+    async fn open_beneath<R, P>(root: R, path: P) -> std::io::Result<Self::File>
+    where
+        R: AsRef<Path> + Send,
+        P: AsRef<Path> + Send,
+    {
+        let root = root.as_ref().to_owned();
+        let path = path.as_ref().to_owned();
+
+        let file = ::tokio::task::spawn_blocking(move || beneath::open_beneath(&root, &path)).await??;
+
+        Ok(fs::File::from_std(file))
+    }
 }
 
 #[async_trait]
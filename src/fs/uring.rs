@@ -0,0 +1,860 @@
+use super::*;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io::{ErrorKind, SeekFrom};
+use std::os::fd::{AsRawFd, BorrowedFd};
+use std::os::unix::fs::OpenOptionsExt;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::task::{ready, Context, Poll};
+
+use futures::channel::oneshot;
+use futures::future::{BoxFuture, FutureExt};
+use futures::io::{AsyncRead, AsyncSeek, AsyncWrite};
+use futures::stream;
+
+use ::tokio::sync::mpsc;
+
+
+
+/// A job run on the io_uring thread. It creates its future there, so the future does not need
+/// to be [`Send`].
+type Job = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()>>> + Send>;
+
+/// The error with which the io_uring thread failed to start.
+type StartError = (ErrorKind, String);
+
+thread_local! {
+    /// The files opened on the io_uring thread, by the id of their [`UringFile`].
+    static FILES: RefCell<HashMap<u64, Rc<tokio_uring::fs::File>>> = RefCell::new(HashMap::new());
+}
+
+/// Returns the queue of the io_uring thread, starting the thread on first use.
+fn worker() -> std::io::Result<&'static mpsc::UnboundedSender<Job>> {
+    static WORKER: OnceLock<Result<mpsc::UnboundedSender<Job>, StartError>> = OnceLock::new();
+
+    match WORKER.get_or_init(start_worker) {
+        Ok(jobs) => Ok(jobs),
+        Err((kind, msg)) => Err(std::io::Error::new(*kind, msg.clone())),
+    }
+}
+
+fn start_worker() -> Result<mpsc::UnboundedSender<Job>, StartError> {
+    let (started_tx, started_rx) = std::sync::mpsc::sync_channel(1);
+
+    let spawned = std::thread::Builder::new()
+        .name("fut-compat-uring".to_string())
+        .spawn(move || {
+            // Fails if the kernel does not support io_uring, or if it is forbidden.
+            let runtime = match tokio_uring::Runtime::new(&tokio_uring::builder()) {
+                Ok(runtime) => runtime,
+                Err(err) => {
+                    let _ = started_tx.send(Err((err.kind(), format!("failed to start io_uring: {}", err))));
+                    return;
+                }
+            };
+
+            let (jobs_tx, mut jobs_rx) = mpsc::unbounded_channel::<Job>();
+            let _ = started_tx.send(Ok(jobs_tx));
+
+            runtime.block_on(async move {
+                while let Some(job) = jobs_rx.recv().await {
+                    tokio_uring::spawn(job());
+                }
+            });
+        });
+
+    if let Err(err) = spawned {
+        return Err((err.kind(), format!("failed to start the io_uring thread: {}", err)));
+    }
+
+    started_rx.recv().unwrap_or_else(|_| Err((ErrorKind::Other, "the io_uring thread panicked".to_string())))
+}
+
+/// Runs the future created by `f` on the io_uring thread and returns its output.
+///
+/// The job keeps running if the returned future gets dropped, as the kernel may still use its
+/// buffers.
+fn run<F, Fut, T>(f: F) -> impl Future<Output = std::io::Result<T>> + Send + 'static
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = std::io::Result<T>> + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+
+    let job: Job = Box::new(move || Box::pin(async move {
+        let _ = tx.send(f().await);
+    }));
+
+    let submitted = worker().and_then(|jobs| jobs.send(job).map_err(|_| stopped()));
+
+    async move {
+        submitted?;
+
+        rx.await.map_err(|_| stopped())?
+    }
+}
+
+/// Runs the future created by `f` with the file `id` on the io_uring thread.
+fn with_file<F, Fut, T>(id: u64, f: F) -> impl Future<Output = std::io::Result<T>> + Send + 'static
+where
+    F: FnOnce(Rc<tokio_uring::fs::File>) -> Fut + Send + 'static,
+    Fut: Future<Output = std::io::Result<T>> + 'static,
+    T: Send + 'static,
+{
+    run(move || async move {
+        let file = FILES.with(|files| files.borrow().get(&id).cloned())
+            .ok_or_else(|| std::io::Error::other("the file has been closed"))?;
+
+        f(file).await
+    })
+}
+
+/// Runs `f` with a duplicate of the descriptor of `file` on the blocking pool, for the
+/// operations io_uring does not support.
+async fn blocking<F, T>(file: &tokio_uring::fs::File, f: F) -> std::io::Result<T>
+where
+    F: FnOnce(std::fs::File) -> std::io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    // SAFETY: The descriptor stays open while `file` is borrowed.
+    let fd = unsafe { BorrowedFd::borrow_raw(file.as_raw_fd()) };
+    let file = std::fs::File::from(fd.try_clone_to_owned()?);
+
+    ::tokio::task::spawn_blocking(move || f(file)).await?
+}
+
+/// Stores `file` on the io_uring thread and returns a handle to it.
+fn register(file: tokio_uring::fs::File) -> UringFile {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+    FILES.with(|files| files.borrow_mut().insert(id, Rc::new(file)));
+
+    UringFile::new(id)
+}
+
+fn stopped() -> std::io::Error {
+    std::io::Error::other("the io_uring thread has stopped")
+}
+
+
+
+/// An abstraction of a [`Filesystem`] backed by [`tokio-uring`](https://docs.rs/tokio-uring),
+/// using io_uring on Linux.
+///
+/// # Threading
+///
+/// tokio-uring runs a single threaded runtime, and its files can not leave the thread of that
+/// runtime. To present them through the traits of this crate, which expect [`Send`] futures, all
+/// operations run on a dedicated thread, which gets started on first use. The futures and
+/// [`UringFile`]s returned to the caller only exchange messages and owned buffers with that
+/// thread, so they can be used from any executor, including the ones of
+/// [`Spawn`](futures::task::Spawn) implementations like
+/// [`TokioExecutor`](crate::task::TokioExecutor). Spawning tasks onto the io_uring thread itself
+/// is not supported.
+///
+/// Reading, writing, syncing, opening, renaming and removing files and removing directories use
+/// io_uring. The operations tokio-uring does not support run on the blocking pool of the
+/// io_uring thread instead.
+///
+/// If io_uring is not available, e.g. on older kernels, every operation fails with the error of
+/// starting it.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> std::io::Result<()> {
+/// use futures::io::AsyncWriteExt;
+/// use fut_compat::fs::{File, Filesystem, UringFile, UringFs};
+///
+/// let mut file = UringFile::create("hello.txt").await?;
+/// file.write_all(b"hello").await?;
+/// file.flush().await?;
+///
+/// assert_eq!(UringFs::read("hello.txt").await?, b"hello");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UringFs {}
+
+#[async_trait]
+impl Filesystem for UringFs {
+    type ReadDir = stream::Iter<std::vec::IntoIter<std::io::Result<UringDirEntry>>>;
+    type DirEntry = UringDirEntry;
+
+    async fn canonicalize<P: AsRef<Path> + Send>(path: P) -> std::io::Result<PathBuf> {
+        let path = path.as_ref().to_owned();
+
+        run(move || ::tokio::fs::canonicalize(path)).await
+    }
+
+    async fn copy<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        from: S,
+        to: D,
+    ) -> std::io::Result<u64> {
+        let from = from.as_ref().to_owned();
+        let to = to.as_ref().to_owned();
+
+        run(move || ::tokio::fs::copy(from, to)).await
+    }
+
+    async fn copy_reflink<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        from: S,
+        to: D,
+    ) -> std::io::Result<ReflinkOutcome> {
+        let from = from.as_ref().to_owned();
+        let to = to.as_ref().to_owned();
+
+        run(move || TokioFs::copy_reflink(from, to)).await
+    }
+
+    async fn create_dir<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref().to_owned();
+
+        run(move || ::tokio::fs::create_dir(path)).await
+    }
+
+    async fn create_dir_all<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref().to_owned();
+
+        run(move || ::tokio::fs::create_dir_all(path)).await
+    }
+
+    async fn create_dir_with<P: AsRef<Path> + Send>(
+        path: P,
+        options: DirOptions,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref().to_owned();
+
+        run(move || TokioFs::create_dir_with(path, options)).await
+    }
+
+    async fn hard_link<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        from: S,
+        to: D,
+    ) -> std::io::Result<()> {
+        let from = from.as_ref().to_owned();
+        let to = to.as_ref().to_owned();
+
+        run(move || ::tokio::fs::hard_link(from, to)).await
+    }
+
+    async fn metadata<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Metadata> {
+        let path = path.as_ref().to_owned();
+
+        run(move || ::tokio::fs::metadata(path)).await
+    }
+
+    async fn read<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Vec<u8>> {
+        let path = path.as_ref().to_owned();
+
+        run(move || async move {
+            let file = tokio_uring::fs::File::open(path).await?;
+
+            let mut contents = Vec::new();
+            loop {
+                let (result, chunk) = file.read_at(Vec::with_capacity(CHUNK_SIZE), contents.len() as u64).await;
+
+                match result {
+                    Ok(0) => break,
+                    Ok(_) => contents.extend_from_slice(&chunk),
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+
+            file.close().await?;
+
+            Ok(contents)
+        }).await
+    }
+
+    /// Reads all entries of the directory on the blocking pool before returning them.
+    async fn read_dir<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self::ReadDir> {
+        let path = path.as_ref().to_owned();
+
+        let entries = run(move || async move {
+            let mut read_dir = ::tokio::fs::read_dir(path).await?;
+
+            let mut entries = Vec::new();
+            loop {
+                match read_dir.next_entry().await {
+                    Ok(Some(entry)) => entries.push(UringDirEntry::new(&entry).await),
+                    Ok(None) => break,
+                    Err(err) => entries.push(Err(err)),
+                }
+            }
+
+            Ok(entries)
+        }).await?;
+
+        Ok(stream::iter(entries))
+    }
+
+    async fn read_if_modified_since<P: AsRef<Path> + Send>(
+        path: P,
+        since: SystemTime,
+    ) -> std::io::Result<Option<(Vec<u8>, SystemTime)>> {
+        let path = path.as_ref().to_owned();
+
+        run(move || TokioFs::read_if_modified_since(path, since)).await
+    }
+
+    async fn read_link<P: AsRef<Path> + Send>(path: P) -> std::io::Result<PathBuf> {
+        let path = path.as_ref().to_owned();
+
+        run(move || ::tokio::fs::read_link(path)).await
+    }
+
+    async fn read_to_string<P: AsRef<Path> + Send>(path: P) -> std::io::Result<String> {
+        let contents = Self::read(path).await?;
+
+        String::from_utf8(contents).map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err))
+    }
+
+    async fn remove_dir<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref().to_owned();
+
+        run(move || tokio_uring::fs::remove_dir(path)).await
+    }
+
+    async fn remove_dir_all<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref().to_owned();
+
+        run(move || ::tokio::fs::remove_dir_all(path)).await
+    }
+
+    async fn remove_file<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref().to_owned();
+
+        run(move || tokio_uring::fs::remove_file(path)).await
+    }
+
+    async fn rename<O: AsRef<Path> + Send, N: AsRef<Path> + Send>(
+        from: O,
+        to: N,
+    ) -> std::io::Result<()> {
+        let from = from.as_ref().to_owned();
+        let to = to.as_ref().to_owned();
+
+        run(move || tokio_uring::fs::rename(from, to)).await
+    }
+
+    async fn set_permissions<P: AsRef<Path> + Send>(
+        path: P,
+        perm: Permissions,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref().to_owned();
+
+        run(move || ::tokio::fs::set_permissions(path, perm)).await
+    }
+
+    async fn symlink_metadata<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Metadata> {
+        let path = path.as_ref().to_owned();
+
+        run(move || ::tokio::fs::symlink_metadata(path)).await
+    }
+
+    async fn touch<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        let path = path.as_ref().to_owned();
+
+        run(move || TokioFs::touch(path)).await
+    }
+
+    async fn write<P: AsRef<Path> + Send, C: AsRef<[u8]> + Send>(
+        path: P,
+        contents: C
+    ) -> std::io::Result<()> {
+        let path = path.as_ref().to_owned();
+        let contents = contents.as_ref().to_vec();
+
+        run(move || async move {
+            let file = tokio_uring::fs::File::create(path).await?;
+
+            write_all_at(&file, contents, 0).await?;
+
+            file.close().await
+        }).await
+    }
+
+    async fn write_vectored<P: AsRef<Path> + Send>(
+        path: P,
+        bufs: &[IoSlice<'_>],
+    ) -> std::io::Result<()> {
+        // The buffers have to be owned by the ring, so they get copied anyway.
+        let mut contents = Vec::with_capacity(bufs.iter().map(|buf| buf.len()).sum());
+        for buf in bufs {
+            contents.extend_from_slice(buf);
+        }
+
+        Self::write(path, contents).await
+    }
+}
+
+#[async_trait]
+impl SecureOpen for UringFs {
+    type File = UringFile;
+
+    async fn open_no_follow<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self::File> {
+        let path = path.as_ref().to_owned();
+
+        run(move || async move {
+            tokio_uring::fs::OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_NOFOLLOW)
+                .open(path)
+                .await
+                .map(register)
+        }).await
+    }
+
+    async fn open_beneath<R, P>(root: R, path: P) -> std::io::Result<Self::File>
+    where
+        R: AsRef<Path> + Send,
+        P: AsRef<Path> + Send,
+    {
+        let root = root.as_ref().to_owned();
+        let path = path.as_ref().to_owned();
+
+        let file = ::tokio::task::spawn_blocking(move || beneath::open_beneath(&root, &path)).await??;
+
+        run(move || async move {
+            Ok(register(tokio_uring::fs::File::from_std(file)))
+        }).await
+    }
+}
+
+/// The size of the chunks read by [`UringFs::read`].
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Writes all of `buf` to `file`, starting at `pos`.
+async fn write_all_at(file: &tokio_uring::fs::File, mut buf: Vec<u8>, mut pos: u64) -> std::io::Result<()> {
+    while !buf.is_empty() {
+        let (result, written) = file.write_at(buf, pos).await;
+        buf = written;
+
+        match result {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(n) => {
+                buf.drain(..n);
+                pos += n as u64;
+            }
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+
+
+/// An entry of a directory read by [`UringFs::read_dir`].
+///
+/// The path and type of the entry get read together with the directory.
+#[derive(Debug)]
+pub struct UringDirEntry {
+    path: PathBuf,
+    file_name: OsString,
+    file_type: FileType,
+}
+
+impl UringDirEntry {
+    async fn new(entry: &::tokio::fs::DirEntry) -> std::io::Result<Self> {
+        Ok(Self {
+            path: entry.path(),
+            file_name: entry.file_name(),
+            file_type: entry.file_type().await?,
+        })
+    }
+}
+
+#[async_trait]
+impl DirEntry for UringDirEntry {
+    fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    fn file_name(&self) -> OsString {
+        self.file_name.clone()
+    }
+
+    async fn metadata(&self) -> std::io::Result<Metadata> {
+        UringFs::symlink_metadata(&self.path).await
+    }
+
+    async fn file_type(&self) -> std::io::Result<FileType> {
+        Ok(self.file_type)
+    }
+}
+
+
+
+/// A [`File`] backed by [`tokio-uring`](https://docs.rs/tokio-uring), see [`UringFs`].
+///
+/// The file itself stays on the io_uring thread. Reads and writes exchange owned buffers with
+/// it, so they implement the io traits of [`futures`].
+///
+/// Like the file of [`tokio`](https://docs.rs/tokio), writes complete once the data got handed
+/// to the io_uring thread. [`flush`](futures::io::AsyncWriteExt::flush) waits for the last write,
+/// returning its error. The file gets closed on drop.
+pub struct UringFile {
+    id: u64,
+    /// The position of the cursor.
+    pos: u64,
+    /// The data read ahead of the cursor by the last read.
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    op: Option<Op>,
+}
+
+// SAFETY: The running operation is the only field which is not `Sync`, and it only gets
+// accessed through mutable references.
+unsafe impl Sync for UringFile {}
+
+/// An operation running on the io_uring thread.
+enum Op {
+    Read(BoxFuture<'static, std::io::Result<Vec<u8>>>),
+    Write(BoxFuture<'static, std::io::Result<usize>>),
+    Seek(BoxFuture<'static, std::io::Result<u64>>),
+}
+
+impl UringFile {
+    fn new(id: u64) -> Self {
+        Self {
+            id,
+            pos: 0,
+            read_buf: Vec::new(),
+            read_pos: 0,
+            op: None,
+        }
+    }
+
+    /// Waits for the running operation to complete, if any.
+    fn poll_complete(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let result = match &mut self.op {
+            None => return Poll::Ready(Ok(())),
+            Some(Op::Read(fut)) => ready!(fut.poll_unpin(cx)).map(|data| {
+                self.read_buf = data;
+                self.read_pos = 0;
+            }),
+            Some(Op::Write(fut)) => ready!(fut.poll_unpin(cx)).map(|n| {
+                self.pos += n as u64;
+            }),
+            Some(Op::Seek(fut)) => ready!(fut.poll_unpin(cx)).map(|pos| {
+                self.pos = pos;
+            }),
+        };
+
+        self.op = None;
+
+        Poll::Ready(result)
+    }
+
+    /// Discards the data read ahead of the cursor.
+    fn discard_read_buf(&mut self) {
+        self.read_buf.clear();
+        self.read_pos = 0;
+    }
+}
+
+#[async_trait]
+impl File for UringFile {
+    async fn open<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref().to_owned();
+
+        run(move || async move {
+            tokio_uring::fs::File::open(path).await.map(register)
+        }).await
+    }
+
+    async fn create<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref().to_owned();
+
+        run(move || async move {
+            tokio_uring::fs::File::create(path).await.map(register)
+        }).await
+    }
+
+    async fn sync_all(&self) -> std::io::Result<()> {
+        with_file(self.id, |file| async move { file.sync_all().await }).await
+    }
+
+    async fn sync_data(&self) -> std::io::Result<()> {
+        with_file(self.id, |file| async move { file.sync_data().await }).await
+    }
+
+    async fn set_len(&self, size: u64) -> std::io::Result<()> {
+        with_file(self.id, move |file| async move {
+            blocking(&file, move |file| file.set_len(size)).await
+        }).await
+    }
+
+    async fn metadata(&self) -> std::io::Result<Metadata> {
+        with_file(self.id, |file| async move {
+            blocking(&file, |file| file.metadata()).await
+        }).await
+    }
+
+    async fn set_permissions(&self, perm: Permissions) -> std::io::Result<()> {
+        with_file(self.id, move |file| async move {
+            blocking(&file, move |file| file.set_permissions(perm)).await
+        }).await
+    }
+}
+
+impl AsyncRead for UringFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = Pin::into_inner(self);
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        loop {
+            if this.read_pos < this.read_buf.len() {
+                let available = &this.read_buf[this.read_pos..];
+                let n = available.len().min(buf.len());
+
+                buf[..n].copy_from_slice(&available[..n]);
+                this.read_pos += n;
+                this.pos += n as u64;
+
+                return Poll::Ready(Ok(n));
+            }
+
+            match this.op {
+                Some(Op::Read(_)) => {
+                    ready!(this.poll_complete(cx))?;
+
+                    if this.read_buf.is_empty() {
+                        // The end of the file.
+                        return Poll::Ready(Ok(0));
+                    }
+                }
+                Some(_) => ready!(this.poll_complete(cx))?,
+                None => {
+                    let len = buf.len();
+                    let pos = this.pos;
+
+                    this.op = Some(Op::Read(Box::pin(with_file(this.id, move |file| async move {
+                        let (result, mut data) = file.read_at(Vec::with_capacity(len), pos).await;
+
+                        data.truncate(result?);
+
+                        Ok(data)
+                    }))));
+                }
+            }
+        }
+    }
+}
+
+impl AsyncWrite for UringFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = Pin::into_inner(self);
+
+        ready!(this.poll_complete(cx))?;
+
+        // The data read ahead is about to be overwritten.
+        this.discard_read_buf();
+
+        let data = buf.to_vec();
+        let pos = this.pos;
+
+        this.op = Some(Op::Write(Box::pin(with_file(this.id, move |file| async move {
+            let len = data.len();
+
+            write_all_at(&file, data, pos).await?;
+
+            Ok(len)
+        }))));
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::into_inner(self).poll_complete(cx)
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl AsyncSeek for UringFile {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<std::io::Result<u64>> {
+        let this = Pin::into_inner(self);
+
+        loop {
+            match this.op {
+                Some(Op::Seek(_)) => {
+                    ready!(this.poll_complete(cx))?;
+
+                    return Poll::Ready(Ok(this.pos));
+                }
+                Some(_) => ready!(this.poll_complete(cx))?,
+                None => {
+                    this.discard_read_buf();
+
+                    let (base, offset) = match pos {
+                        SeekFrom::Start(pos) => {
+                            this.pos = pos;
+
+                            return Poll::Ready(Ok(pos));
+                        }
+                        SeekFrom::Current(offset) => (this.pos, offset),
+                        SeekFrom::End(offset) => {
+                            this.op = Some(Op::Seek(Box::pin(with_file(this.id, move |file| async move {
+                                let len = blocking(&file, |file| file.metadata()).await?.len();
+
+                                checked_seek(len, offset)
+                            }))));
+
+                            continue;
+                        }
+                    };
+
+                    let pos = checked_seek(base, offset)?;
+                    this.pos = pos;
+
+                    return Poll::Ready(Ok(pos));
+                }
+            }
+        }
+    }
+}
+
+fn checked_seek(base: u64, offset: i64) -> std::io::Result<u64> {
+    base.checked_add_signed(offset).ok_or_else(|| {
+        std::io::Error::new(ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")
+    })
+}
+
+impl Drop for UringFile {
+    fn drop(&mut self) {
+        let id = self.id;
+
+        // The job gets submitted right away. tokio-uring closes the file once the running
+        // operations are done with it.
+        let close = run(move || async move {
+            FILES.with(|files| files.borrow_mut().remove(&id));
+
+            Ok(())
+        });
+
+        drop(close);
+    }
+}
+
+impl fmt::Debug for UringFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UringFile")
+            .field("pos", &self.pos)
+            .finish_non_exhaustive()
+    }
+}
+
+
+
+/// The options for opening a [`UringFile`].
+#[derive(Clone, Debug)]
+pub struct UringOpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
+
+#[async_trait]
+impl OpenOptions for UringOpenOptions {
+    type File = UringFile;
+
+    fn new() -> Self {
+        Self {
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+        }
+    }
+
+    fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    async fn open<P: AsRef<Path> + Send>(&self, path: P) -> std::io::Result<Self::File> {
+        let path = path.as_ref().to_owned();
+        let options = self.clone();
+
+        run(move || async move {
+            tokio_uring::fs::OpenOptions::new()
+                .read(options.read)
+                .write(options.write)
+                .append(options.append)
+                .truncate(options.truncate)
+                .create(options.create)
+                .create_new(options.create_new)
+                .open(path)
+                .await
+                .map(register)
+        }).await
+    }
+}
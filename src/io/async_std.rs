@@ -0,0 +1,69 @@
+use super::Stdio;
+
+
+
+/// [`async_std`](https://docs.rs/async-std)'s abstraction of the standard streams.
+#[cfg(feature = "async-std-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AsyncStdStdio {}
+
+impl Stdio for AsyncStdStdio {
+    type Stdin = ::async_std::io::Stdin;
+    type Stdout = ::async_std::io::Stdout;
+    type Stderr = ::async_std::io::Stderr;
+
+    fn stdin() -> Self::Stdin {
+        ::async_std::io::stdin()
+    }
+
+    fn stdout() -> Self::Stdout {
+        ::async_std::io::stdout()
+    }
+
+    fn stderr() -> Self::Stderr {
+        ::async_std::io::stderr()
+    }
+}
+
+
+
+/// Waits for `socket` to become writable through a duplicate of its file descriptor.
+///
+/// [`async_std`](https://docs.rs/async-std) does not expose the readiness of its sockets, but it
+/// uses the same reactor as [`async_io`](https://docs.rs/async-io), so registering a duplicate
+/// there works just as well.
+#[cfg(all(unix, feature = "zero-copy"))]
+async fn write_with_dup<S, R, F>(socket: &S, mut op: F) -> std::io::Result<R>
+where
+    S: std::os::unix::io::AsFd,
+    F: FnMut() -> std::io::Result<R>,
+{
+    let dup = async_io::Async::new(socket.as_fd().try_clone_to_owned()?)?;
+
+    dup.write_with(|_| op()).await
+}
+
+#[cfg(all(unix, feature = "zero-copy"))]
+#[async_trait::async_trait]
+impl super::SendfileSocket for ::async_std::net::TcpStream {
+    async fn write_with<R, F>(&self, op: F) -> std::io::Result<R>
+    where
+        F: FnMut() -> std::io::Result<R> + Send,
+        R: Send,
+    {
+        write_with_dup(self, op).await
+    }
+}
+
+#[cfg(all(unix, feature = "zero-copy"))]
+#[async_trait::async_trait]
+impl super::SendfileSocket for ::async_std::os::unix::net::UnixStream {
+    async fn write_with<R, F>(&self, op: F) -> std::io::Result<R>
+    where
+        F: FnMut() -> std::io::Result<R> + Send,
+        R: Send,
+    {
+        write_with_dup(self, op).await
+    }
+}
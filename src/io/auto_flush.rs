@@ -0,0 +1,522 @@
+use std::future::Future;
+use std::io::Error;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use futures::io::{AsyncWrite, BufWriter};
+use futures::task::{Spawn, SpawnError, SpawnExt};
+use futures::future::poll_fn;
+use futures::ready;
+
+use crate::task::Timer;
+
+
+
+/// A buffered writer which flushes on its own, once the buffered data has been idle for a given
+/// duration.
+///
+/// Writes are buffered like with [`BufWriter`]. After each write leaving data in the buffer, the
+/// idle timer gets restarted with the [`Timer`] `Tm`. An explicit flush stops it.
+///
+/// The writer has no task of its own, so the flush only happens when it is polled after the timer
+/// expired. This is the case for the next write, or while waiting on
+/// [`idle_flush`](Self::idle_flush), e.g. alongside the source of the data. Alternatively
+/// [`spawn_flusher`](Self::spawn_flusher) moves the writer behind a handle and spawns a task
+/// performing the flushes.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use std::time::Duration;
+///
+/// use fut_compat::io::{AsyncWriteExt, AutoFlushWriter, TokioCompat};
+/// use fut_compat::task::TokioExecutor;
+///
+/// let socket = TokioCompat::new(tokio::net::TcpStream::connect("127.0.0.1:8080").await?);
+/// let writer = AutoFlushWriter::<_, TokioExecutor>::new(socket, Duration::from_millis(50));
+/// let mut writer = writer.spawn_flusher(&TokioExecutor::default()).map_err(std::io::Error::other)?;
+///
+/// // Goes out after 50ms without any further call.
+/// writer.write_all(b"status: ok\n").await?;
+///
+/// writer.close().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AutoFlushWriter<W, Tm: Timer> {
+    inner: BufWriter<W>,
+    idle: Duration,
+    /// The instant after which the buffered data gets flushed.
+    deadline: Option<Instant>,
+    sleep: Option<Pin<Box<Tm::Sleep>>>,
+}
+
+impl<W, Tm> AutoFlushWriter<W, Tm>
+where
+    W: AsyncWrite,
+    Tm: Timer,
+{
+    /// Creates a new instance with a default buffer capacity, flushing after `idle` has passed
+    /// since the last write.
+    pub fn new(inner: W, idle: Duration) -> Self {
+        Self::from_buf_writer(BufWriter::new(inner), idle)
+    }
+
+    /// Creates a new instance with a buffer of `capacity` bytes, flushing after `idle` has passed
+    /// since the last write.
+    pub fn with_capacity(capacity: usize, inner: W, idle: Duration) -> Self {
+        Self::from_buf_writer(BufWriter::with_capacity(capacity, inner), idle)
+    }
+
+    fn from_buf_writer(inner: BufWriter<W>, idle: Duration) -> Self {
+        Self {
+            inner,
+            idle,
+            deadline: None,
+            sleep: None,
+        }
+    }
+}
+
+impl<W, Tm: Timer> AutoFlushWriter<W, Tm> {
+    /// Returns the buffered data which has not yet been written.
+    pub fn buffer(&self) -> &[u8] {
+        self.inner.buffer()
+    }
+
+    /// Get a reference to the wrapped writer.
+    pub fn get_ref(&self) -> &W {
+        self.inner.get_ref()
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Writing directly to the wrapped writer mixes up the order with the buffered data.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.get_mut()
+    }
+
+    /// Consumes the `AutoFlushWriter` object and returns the wrapped writer.
+    ///
+    /// The buffered data is lost, flush the writer beforehand.
+    pub fn into_inner(self) -> W {
+        self.inner.into_inner()
+    }
+}
+
+impl<W, Tm> AutoFlushWriter<W, Tm>
+where
+    W: AsyncWrite + Unpin,
+    Tm: Timer,
+{
+    /// Waits until the buffered data has been idle for the configured duration and flushes it.
+    ///
+    /// Never completes while the buffer is empty.
+    pub async fn idle_flush(&mut self) -> std::io::Result<()> {
+        poll_fn(|cx| self.poll_idle_flush(cx)).await
+    }
+
+    /// Polls for the buffered data being idle for the configured duration and flushes it, see
+    /// [`idle_flush`](Self::idle_flush).
+    pub fn poll_idle_flush(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let deadline = match self.deadline {
+            Some(deadline) => deadline,
+            None => return Poll::Pending,
+        };
+
+        loop {
+            if let Some(sleep) = &mut self.sleep {
+                ready!(sleep.as_mut().poll(cx));
+
+                self.sleep = None;
+            }
+
+            let remaining = deadline.saturating_duration_since(Tm::now());
+            if remaining.is_zero() {
+                break;
+            }
+
+            self.sleep = Some(Box::pin(Tm::sleep(remaining)));
+        }
+
+        ready!(Pin::new(&mut self.inner).poll_flush(cx))?;
+        self.deadline = None;
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Moves the writer behind a shared handle and spawns a task with `spawner`, which flushes the
+    /// buffered data once it has been idle for the configured duration.
+    ///
+    /// The task ends once the handle has been dropped. Errors of the flushes are returned by the
+    /// next operation of the handle.
+    pub fn spawn_flusher<S>(self, spawner: &S) -> Result<AutoFlushHandle<W, Tm>, SpawnError>
+    where
+        S: Spawn + ?Sized,
+        W: Send + 'static,
+        Tm: 'static,
+    {
+        let shared = Arc::new(Mutex::new(Shared {
+            writer: self,
+            flusher: None,
+            handle: None,
+            error: None,
+        }));
+
+        spawner.spawn(flusher(Arc::downgrade(&shared)))?;
+
+        Ok(AutoFlushHandle {
+            shared,
+        })
+    }
+
+    /// Restarts the idle timer if data is left in the buffer.
+    fn restart_timer(&mut self) {
+        if self.inner.buffer().is_empty() {
+            self.deadline = None;
+        } else {
+            self.deadline = Some(Tm::now() + self.idle);
+        }
+
+        self.sleep = None;
+    }
+}
+
+impl<W, Tm> AsyncWrite for AutoFlushWriter<W, Tm>
+where
+    W: AsyncWrite + Unpin,
+    Tm: Timer,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        // Data which is due gets flushed before it is joined by newer data.
+        if let Poll::Ready(result) = this.poll_idle_flush(cx) {
+            result?;
+        }
+
+        let n = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+        this.restart_timer();
+
+        // Registers the timer, so the task gets woken once the data is due.
+        if let Poll::Ready(Err(err)) = this.poll_idle_flush(cx) {
+            return Poll::Ready(Err(err));
+        }
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let this = Pin::into_inner(self);
+
+        ready!(Pin::new(&mut this.inner).poll_flush(cx))?;
+        this.restart_timer();
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let this = Pin::into_inner(self);
+
+        ready!(Pin::new(&mut this.inner).poll_close(cx))?;
+        this.restart_timer();
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+
+
+struct Shared<W, Tm: Timer> {
+    writer: AutoFlushWriter<W, Tm>,
+    /// The waker of the flusher task, which waits for data to be written.
+    flusher: Option<Waker>,
+    /// The waker of the task using the handle, if its last operation is pending.
+    handle: Option<Waker>,
+    /// The error of a flush performed by the flusher task.
+    error: Option<Error>,
+}
+
+fn lock<W, Tm: Timer>(shared: &Mutex<Shared<W, Tm>>) -> MutexGuard<'_, Shared<W, Tm>> {
+    shared.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// The task spawned by [`AutoFlushWriter::spawn_flusher`].
+async fn flusher<W, Tm>(shared: Weak<Mutex<Shared<W, Tm>>>)
+where
+    W: AsyncWrite + Unpin,
+    Tm: Timer,
+{
+    poll_fn(|cx| {
+        loop {
+            let shared = match shared.upgrade() {
+                Some(shared) => shared,
+                None => return Poll::Ready(()),
+            };
+            let mut shared = lock(&shared);
+
+            shared.flusher = Some(cx.waker().clone());
+
+            let result = shared.writer.poll_idle_flush(cx);
+
+            // Polling the wrapped writer may have replaced the waker registered by the task using
+            // the handle, so it has to check again.
+            if let Some(handle) = shared.handle.take() {
+                handle.wake();
+            }
+
+            match result {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(err)) => {
+                    // Stop flushing until the handle got the error.
+                    shared.writer.deadline = None;
+                    shared.error = Some(err);
+                }
+            }
+        }
+    }).await
+}
+
+/// A handle to an [`AutoFlushWriter`] whose buffered data gets flushed by a spawned task, see
+/// [`AutoFlushWriter::spawn_flusher`].
+pub struct AutoFlushHandle<W, Tm: Timer> {
+    shared: Arc<Mutex<Shared<W, Tm>>>,
+}
+
+impl<W, Tm> AutoFlushHandle<W, Tm>
+where
+    W: AsyncWrite + Unpin,
+    Tm: Timer,
+{
+    /// Runs `op` on the writer, returning the error of a previous flush of the flusher task first.
+    fn poll_op<T>(
+        &self,
+        cx: &mut Context<'_>,
+        op: impl FnOnce(Pin<&mut AutoFlushWriter<W, Tm>>, &mut Context<'_>) -> Poll<std::io::Result<T>>,
+    ) -> Poll<std::io::Result<T>> {
+        let mut shared = lock(&self.shared);
+
+        if let Some(err) = shared.error.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        let result = op(Pin::new(&mut shared.writer), cx);
+
+        shared.handle = match result {
+            Poll::Pending => Some(cx.waker().clone()),
+            Poll::Ready(_) => None,
+        };
+
+        // The flusher task has to pick up the new deadline, and the wrapped writer may have
+        // replaced its waker.
+        if let Some(flusher) = shared.flusher.take() {
+            flusher.wake();
+        }
+
+        result
+    }
+}
+
+impl<W, Tm> AsyncWrite for AutoFlushHandle<W, Tm>
+where
+    W: AsyncWrite + Unpin,
+    Tm: Timer,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Error>> {
+        self.poll_op(cx, |writer, cx| writer.poll_write(cx, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.poll_op(cx, |writer, cx| writer.poll_flush(cx))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.poll_op(cx, |writer, cx| writer.poll_close(cx))
+    }
+}
+
+impl<W, Tm: Timer> Drop for AutoFlushHandle<W, Tm> {
+    fn drop(&mut self) {
+        // Lets the flusher task notice that the handle is gone.
+        let flusher = lock(&self.shared).flusher.take();
+
+        if let Some(flusher) = flusher {
+            flusher.wake();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::ErrorKind;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::executor::{block_on, LocalPool};
+    use futures::io::AsyncWriteExt;
+    use futures::task::{waker, ArcWake};
+    use futures::FutureExt;
+
+    use crate::io::{ScriptAction, ScriptedIo};
+    use crate::task::MockTimer;
+
+    const IDLE: Duration = Duration::from_millis(50);
+
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl ArcWake for CountingWaker {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// A writer whose data can be inspected while it is owned by the flusher task.
+    #[derive(Clone, Default)]
+    struct SharedVec(Arc<Mutex<Vec<u8>>>);
+
+    impl AsyncWrite for SharedVec {
+        fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn writer() -> AutoFlushWriter<Vec<u8>, MockTimer> {
+        AutoFlushWriter::new(Vec::new(), IDLE)
+    }
+
+    #[test]
+    fn idle_data_gets_flushed_without_a_flush_call() {
+        let mut writer = writer();
+
+        block_on(writer.write_all(b"hello")).unwrap();
+        assert_eq!((writer.get_ref().as_slice(), writer.buffer()), (&b""[..], &b"hello"[..]));
+
+        MockTimer::advance(IDLE - Duration::from_millis(1));
+        assert!(writer.idle_flush().now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_millis(1));
+        writer.idle_flush().now_or_never().unwrap().unwrap();
+        assert_eq!((writer.get_ref().as_slice(), writer.buffer()), (&b"hello"[..], &b""[..]));
+
+        // Nothing is buffered anymore, so there is nothing to wait for.
+        MockTimer::advance(IDLE * 10);
+        assert!(writer.idle_flush().now_or_never().is_none());
+    }
+
+    #[test]
+    fn writes_wake_the_task_once_the_data_is_due() {
+        let mut writer = writer();
+
+        let counter = Arc::new(CountingWaker::default());
+        let waker = waker(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(matches!(Pin::new(&mut writer).poll_write(&mut cx, b"hello"), Poll::Ready(Ok(5))));
+        assert_eq!(MockTimer::pending_sleep_count(), 1);
+
+        MockTimer::advance(IDLE);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        // Polling the writer again, e.g. for the next write, flushes the due data first.
+        assert!(matches!(Pin::new(&mut writer).poll_write(&mut cx, b" world"), Poll::Ready(Ok(6))));
+        assert_eq!((writer.get_ref().as_slice(), writer.buffer()), (&b"hello"[..], &b" world"[..]));
+    }
+
+    #[test]
+    fn writes_restart_the_timer() {
+        let mut writer = writer();
+
+        block_on(writer.write_all(b"a")).unwrap();
+        MockTimer::advance(IDLE - Duration::from_millis(10));
+        block_on(writer.write_all(b"b")).unwrap();
+
+        MockTimer::advance(IDLE - Duration::from_millis(10));
+        assert!(writer.idle_flush().now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_millis(10));
+        writer.idle_flush().now_or_never().unwrap().unwrap();
+        assert_eq!(writer.get_ref(), b"ab");
+    }
+
+    #[test]
+    fn explicit_flush_stops_the_timer() {
+        let mut writer = writer();
+
+        block_on(writer.write_all(b"hello")).unwrap();
+        block_on(writer.flush()).unwrap();
+
+        assert_eq!(writer.get_ref(), b"hello");
+        assert_eq!(MockTimer::pending_sleep_count(), 0);
+
+        MockTimer::advance(IDLE);
+        assert!(writer.idle_flush().now_or_never().is_none());
+    }
+
+    #[test]
+    fn flusher_task_flushes_idle_data() {
+        let mut pool = LocalPool::new();
+        let sink = SharedVec::default();
+
+        let writer = AutoFlushWriter::<_, MockTimer>::new(sink.clone(), IDLE);
+        let mut handle = writer.spawn_flusher(&pool.spawner()).unwrap();
+
+        block_on(handle.write_all(b"hello")).unwrap();
+        pool.run_until_stalled();
+        assert!(sink.0.lock().unwrap().is_empty());
+
+        MockTimer::advance(IDLE);
+        pool.run_until_stalled();
+        assert_eq!(*sink.0.lock().unwrap(), b"hello");
+
+        // The flusher picks up the deadline of the following writes as well.
+        block_on(handle.write_all(b" world")).unwrap();
+        MockTimer::advance(IDLE);
+        pool.run_until_stalled();
+        assert_eq!(*sink.0.lock().unwrap(), b"hello world");
+
+        // Without the handle, the flusher task ends.
+        drop(handle);
+        pool.run();
+    }
+
+    #[test]
+    fn flusher_errors_are_returned_by_the_handle() {
+        let mut pool = LocalPool::new();
+
+        let writer = AutoFlushWriter::<_, MockTimer>::new(ScriptedIo::new([ScriptAction::Err(ErrorKind::BrokenPipe)]), IDLE);
+        let mut handle = writer.spawn_flusher(&pool.spawner()).unwrap();
+
+        block_on(handle.write_all(b"hello")).unwrap();
+        MockTimer::advance(IDLE);
+        pool.run_until_stalled();
+
+        let err = block_on(handle.write_all(b"again")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+    }
+}
@@ -0,0 +1,461 @@
+use std::io::{Error, Read, Seek, SeekFrom, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::future::Future;
+
+use futures::io::{AsyncRead, AsyncWrite, AsyncSeek};
+use futures::future::poll_fn;
+use futures::ready;
+
+use crate::task::{SpawnBlocking, JoinHandle};
+
+
+
+/// The result of an operation performed on the blocking thread pool.
+enum Op {
+    Read(std::io::Result<usize>),
+    Write(std::io::Result<usize>),
+    Flush(std::io::Result<()>),
+    Seek(std::io::Result<u64>),
+}
+
+enum State<T> {
+    /// The wrapped object is available.
+    Idle(Option<T>),
+    /// The wrapped object has been moved to the blocking thread pool.
+    Busy(JoinHandle<(T, Vec<u8>, Op)>),
+    /// The wrapped object got lost because the blocking task failed.
+    Poisoned,
+}
+
+/// Wraps an object implementing the sync io traits and performs every operation on the blocking
+/// thread pool of the executor `E`.
+///
+/// Unlike [`AllowStdIo`](super::AllowStdIo), which performs the operations directly on the
+/// calling thread, this never blocks the executor. Each operation moves the wrapped object, along
+/// with an owned buffer, to [`SpawnBlocking::spawn_blocking`]. An operation which has been started
+/// is never issued a second time. If the caller polls a different operation in the meantime, the
+/// result is kept until the original operation is polled again.
+///
+/// The buffer of a read is sized according to the buffer provided by the caller. Writes are not
+/// buffered, so every write results in one write on the blocking thread pool.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::io::{AsyncReadExt, BlockingIo};
+/// use fut_compat::task::TokioExecutor;
+///
+/// let file = std::fs::File::open("Cargo.toml")?;
+/// let mut file = BlockingIo::<_, TokioExecutor>::new(file);
+///
+/// let mut contents = String::new();
+/// file.read_to_string(&mut contents).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct BlockingIo<T, E> {
+    state: State<T>,
+    /// The data of a completed read which has not yet been consumed by the caller.
+    read_buf: Option<Vec<u8>>,
+    read_pos: usize,
+    /// The results of completed operations which have not yet been reported to the caller.
+    read_error: Option<Error>,
+    write_result: Option<std::io::Result<usize>>,
+    flush_result: Option<std::io::Result<()>>,
+    seek_result: Option<std::io::Result<u64>>,
+    executor: E,
+}
+
+// The wrapped object is never pinned, it gets moved to the blocking thread pool anyways.
+impl<T, E> Unpin for BlockingIo<T, E> {}
+
+impl<T, E: Default> BlockingIo<T, E> {
+    /// Creates a new instance by wrapping the `inner` object.
+    pub fn new(inner: T) -> Self {
+        Self::with_executor(inner, E::default())
+    }
+}
+
+impl<T, E> BlockingIo<T, E> {
+    /// Creates a new instance by wrapping the `inner` object, spawning the operations with
+    /// `executor`.
+    pub fn with_executor(inner: T, executor: E) -> Self {
+        Self {
+            state: State::Idle(Some(inner)),
+            read_buf: None,
+            read_pos: 0,
+            read_error: None,
+            write_result: None,
+            flush_result: None,
+            seek_result: None,
+            executor,
+        }
+    }
+}
+
+impl<T, E> BlockingIo<T, E>
+where
+    T: Read + Write + Seek + Send + 'static,
+    E: SpawnBlocking,
+{
+    /// Waits for the operation in progress to complete and returns the wrapped object.
+    ///
+    /// Data which has been read but not yet consumed is lost.
+    pub async fn into_inner(mut self) -> std::io::Result<T> {
+        poll_fn(|cx| self.poll_idle(cx)).await?;
+
+        Ok(self.take_inner())
+    }
+
+    /// Waits until no operation is in progress and stores the result of the completed operation.
+    fn poll_idle(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let handle = match &mut self.state {
+            State::Idle(_) => return Poll::Ready(Ok(())),
+            State::Busy(handle) => handle,
+            State::Poisoned => return Poll::Ready(Err(Error::other("the blocking task of BlockingIo failed"))),
+        };
+
+        let (inner, mut buf, op) = match ready!(Pin::new(handle).poll(cx)) {
+            Ok(result) => result,
+            Err(err) => {
+                self.state = State::Poisoned;
+
+                return Poll::Ready(Err(Error::other(err.to_string())));
+            }
+        };
+
+        self.state = State::Idle(Some(inner));
+
+        match op {
+            Op::Read(Ok(n)) => {
+                buf.truncate(n);
+
+                self.read_buf = Some(buf);
+                self.read_pos = 0;
+            }
+            Op::Read(Err(err)) => self.read_error = Some(err),
+            Op::Write(result) => self.write_result = Some(result),
+            Op::Flush(result) => self.flush_result = Some(result),
+            Op::Seek(result) => self.seek_result = Some(result),
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn take_inner(&mut self) -> T {
+        match &mut self.state {
+            State::Idle(inner) => inner.take().expect("BlockingIo holds the wrapped object"),
+            _ => unreachable!("BlockingIo must be idle"),
+        }
+    }
+
+    /// Moves the wrapped object to the blocking thread pool in order to perform `op` on it.
+    ///
+    /// Must only be called after [`poll_idle`](Self::poll_idle) returned `Ready(Ok(()))`. `failed`
+    /// creates the result of the operation in case the position could not be moved back.
+    fn start<F>(&mut self, buf: Vec<u8>, op: F, failed: fn(Error) -> Op)
+    where
+        F: FnOnce(&mut T, &mut Vec<u8>) -> Op + Send + 'static,
+    {
+        let mut inner = self.take_inner();
+
+        // Move the position of the wrapped object back to where the caller expects it.
+        let rewind = match self.read_buf.take() {
+            Some(read_buf) => (read_buf.len() - self.read_pos) as i64,
+            None => 0,
+        };
+        self.read_pos = 0;
+
+        let handle = self.executor.spawn_blocking(move || {
+            let mut buf = buf;
+
+            if rewind > 0 {
+                if let Err(err) = inner.seek(SeekFrom::Current(-rewind)) {
+                    return (inner, buf, failed(err));
+                }
+            }
+
+            let op = op(&mut inner, &mut buf);
+
+            (inner, buf, op)
+        });
+
+        self.state = State::Busy(handle);
+    }
+}
+
+impl<T, E> AsyncRead for BlockingIo<T, E>
+where
+    T: Read + Write + Seek + Send + 'static,
+    E: SpawnBlocking,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        loop {
+            ready!(this.poll_idle(cx))?;
+
+            if let Some(err) = this.read_error.take() {
+                return Poll::Ready(Err(err));
+            }
+
+            if let Some(read_buf) = &this.read_buf {
+                let available = &read_buf[this.read_pos..];
+                let n = available.len().min(buf.len());
+
+                buf[..n].copy_from_slice(&available[..n]);
+                this.read_pos += n;
+
+                if this.read_pos == read_buf.len() {
+                    this.read_buf = None;
+                    this.read_pos = 0;
+                }
+
+                return Poll::Ready(Ok(n));
+            }
+
+            this.start(
+                vec![0u8; buf.len()],
+                |inner, buf| Op::Read(inner.read(buf)),
+                |err| Op::Read(Err(err)),
+            );
+        }
+    }
+}
+
+impl<T, E> AsyncWrite for BlockingIo<T, E>
+where
+    T: Read + Write + Seek + Send + 'static,
+    E: SpawnBlocking,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        loop {
+            ready!(this.poll_idle(cx))?;
+
+            if let Some(result) = this.write_result.take() {
+                return Poll::Ready(result);
+            }
+
+            this.start(
+                buf.to_vec(),
+                |inner, buf| Op::Write(inner.write(buf)),
+                |err| Op::Write(Err(err)),
+            );
+        }
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Error>> {
+        let this = Pin::into_inner(self);
+
+        loop {
+            ready!(this.poll_idle(cx))?;
+
+            if let Some(result) = this.flush_result.take() {
+                return Poll::Ready(result);
+            }
+
+            this.start(
+                Vec::new(),
+                |inner, _| Op::Flush(inner.flush()),
+                |err| Op::Flush(Err(err)),
+            );
+        }
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Error>> {
+        // The sync io traits have no notion of closing, so flushing is all we can do.
+        self.poll_flush(cx)
+    }
+}
+
+impl<T, E> AsyncSeek for BlockingIo<T, E>
+where
+    T: Read + Write + Seek + Send + 'static,
+    E: SpawnBlocking,
+{
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<Result<u64, Error>> {
+        let this = Pin::into_inner(self);
+
+        loop {
+            ready!(this.poll_idle(cx))?;
+
+            if let Some(result) = this.seek_result.take() {
+                return Poll::Ready(result);
+            }
+
+            this.start(
+                Vec::new(),
+                move |inner, _| Op::Seek(inner.seek(pos)),
+                |err| Op::Seek(Err(err)),
+            );
+        }
+    }
+}
+
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use std::io::ErrorKind;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use futures::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    /// An in-memory file which takes a while for every read, counts the reads and fails the ones
+    /// after `fail_after` with `TimedOut`.
+    struct SlowFile {
+        inner: std::io::Cursor<Vec<u8>>,
+        reads: Arc<AtomicUsize>,
+        fail_after: usize,
+    }
+
+    impl Read for SlowFile {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            std::thread::sleep(Duration::from_millis(20));
+
+            if self.reads.fetch_add(1, Ordering::SeqCst) >= self.fail_after {
+                return Err(Error::new(ErrorKind::TimedOut, "too slow"));
+            }
+
+            self.inner.read(buf)
+        }
+    }
+
+    impl Write for SlowFile {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for SlowFile {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    /// Writes a file, reads it back and overwrites a part in the middle of it.
+    async fn file_round_trip<E: SpawnBlocking + Default>(name: &str) {
+        let path = std::env::temp_dir().join(format!("fut-compat-blocking-{}-{}", name, std::process::id()));
+        let file = std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+        let mut file = BlockingIo::<_, E>::new(file);
+
+        let data: Vec<u8> = (0..1024 * 1024 + 17).map(|i: usize| (i % 251) as u8).collect();
+        file.write_all(&data).await.unwrap();
+        file.flush().await.unwrap();
+
+        assert_eq!(file.seek(SeekFrom::Start(0)).await.unwrap(), 0);
+        let mut read = Vec::new();
+        file.read_to_end(&mut read).await.unwrap();
+        assert!(read == data);
+
+        file.seek(SeekFrom::Start(10)).await.unwrap();
+        file.write_all(b"patched").await.unwrap();
+        let file = file.into_inner().await.unwrap();
+        drop(file);
+
+        let written = std::fs::read(&path).unwrap();
+        assert_eq!(&written[10..17], b"patched");
+        assert!(written[..10] == data[..10] && written[17..] == data[17..]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Polls a read repeatedly while it is running, with a smaller buffer than the one it got
+    /// started with, and then writes behind the bytes actually handed out.
+    async fn slow_read_runs_once<E: SpawnBlocking + Default>() {
+        let reads = Arc::new(AtomicUsize::new(0));
+        let mut file = BlockingIo::<_, E>::new(SlowFile {
+            inner: std::io::Cursor::new(b"0123456789".to_vec()),
+            reads: reads.clone(),
+            fail_after: 2,
+        });
+
+        let mut polls = 0;
+        let mut buf = [0u8; 4];
+        let n = poll_fn(|cx| {
+            polls += 1;
+
+            // The first poll starts a read of 8 bytes, the later ones only have room for 4.
+            if polls == 1 {
+                assert!(Pin::new(&mut file).poll_read(cx, &mut [0u8; 8]).is_pending());
+
+                return Poll::Pending;
+            }
+
+            Pin::new(&mut file).poll_read(cx, &mut buf)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!((n, &buf), (4, b"0123"));
+        assert!(polls >= 2);
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+
+        // The wrapped file is at 8 already, but the write belongs behind the 4 bytes handed out.
+        file.write_all(b"ab").await.unwrap();
+        file.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"6789");
+        assert_eq!(reads.load(Ordering::SeqCst), 2);
+
+        // The third read fails, with its original error kind.
+        let err = file.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert_eq!(reads.load(Ordering::SeqCst), 3);
+
+        let file = file.into_inner().await.unwrap();
+        assert_eq!(file.inner.get_ref(), b"0123ab6789");
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_blocking_io() {
+        use crate::task::TokioExecutor;
+
+        let rt = ::tokio::runtime::Runtime::new().unwrap();
+
+        rt.block_on(file_round_trip::<TokioExecutor>("tokio"));
+        rt.block_on(slow_read_runs_once::<TokioExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_blocking_io() {
+        use crate::task::AsyncStdExecutor;
+
+        ::async_std::task::block_on(async {
+            file_round_trip::<AsyncStdExecutor>("async-std").await;
+            slow_read_runs_once::<AsyncStdExecutor>().await;
+        });
+    }
+}
@@ -0,0 +1,321 @@
+use std::future::Future;
+use std::io::{Error, ErrorKind};
+use std::mem::MaybeUninit;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::ready;
+
+use ::bytes::{Buf, BufMut};
+
+
+
+/// Reading into a [`BufMut`] of the [`bytes`](https://docs.rs/bytes) crate.
+///
+/// Implemented for every type implementing [`AsyncRead`]. [`TokioCompat`](super::TokioCompat)
+/// provides an inherent `read_buf` method taking precedence, which avoids zeroing the spare
+/// capacity of the buffer.
+pub trait AsyncReadBufExt: AsyncRead {
+    /// Reads some bytes into the spare capacity of `buf`, advancing it by the number of bytes
+    /// read.
+    ///
+    /// Returns the number of bytes read. A return value of `0` means that the reader reached EOF,
+    /// or that `buf` has no spare capacity left.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example() -> std::io::Result<()> {
+    /// use bytes::BytesMut;
+    /// use fut_compat::io::AsyncReadBufExt;
+    ///
+    /// let mut reader = futures::io::Cursor::new(b"hello world".to_vec());
+    /// let mut buf = BytesMut::with_capacity(1024);
+    ///
+    /// while reader.read_buf(&mut buf).await? > 0 {}
+    ///
+    /// assert_eq!(&buf[..], b"hello world");
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn read_buf<'a, B>(&'a mut self, buf: &'a mut B) -> ReadBufFuture<'a, Self, B>
+    where
+        Self: Unpin,
+        B: BufMut + ?Sized,
+    {
+        ReadBufFuture {
+            reader: self,
+            buf,
+        }
+    }
+}
+
+impl<R: AsyncRead + ?Sized> AsyncReadBufExt for R {}
+
+/// Writing from a [`Buf`] of the [`bytes`](https://docs.rs/bytes) crate.
+///
+/// Implemented for every type implementing [`AsyncWrite`].
+pub trait AsyncWriteBufExt: AsyncWrite {
+    /// Writes some bytes of `buf`, advancing it by the number of bytes written.
+    ///
+    /// Returns the number of bytes written.
+    fn write_buf<'a, B>(&'a mut self, buf: &'a mut B) -> WriteBufFuture<'a, Self, B>
+    where
+        Self: Unpin,
+        B: Buf + ?Sized,
+    {
+        WriteBufFuture {
+            writer: self,
+            buf,
+        }
+    }
+
+    /// Writes all the remaining bytes of `buf`, advancing it accordingly.
+    ///
+    /// Returns an error of kind [`WriteZero`](ErrorKind::WriteZero) if the writer stops accepting
+    /// bytes before all of them have been written.
+    fn write_all_buf<'a, B>(&'a mut self, buf: &'a mut B) -> WriteAllBufFuture<'a, Self, B>
+    where
+        Self: Unpin,
+        B: Buf + ?Sized,
+    {
+        WriteAllBufFuture {
+            writer: self,
+            buf,
+        }
+    }
+}
+
+impl<W: AsyncWrite + ?Sized> AsyncWriteBufExt for W {}
+
+
+
+/// The future returned by [`AsyncReadBufExt::read_buf`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct ReadBufFuture<'a, R: ?Sized, B: ?Sized> {
+    reader: &'a mut R,
+    buf: &'a mut B,
+}
+
+impl<R, B> Future for ReadBufFuture<'_, R, B>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    B: BufMut + ?Sized,
+{
+    type Output = std::io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = Pin::into_inner(self);
+
+        if !this.buf.has_remaining_mut() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let n = {
+            // SAFETY: The chunk gets initialized before it is handed out as `&mut [u8]`, and no
+            // uninitialized bytes are written into it.
+            let dst = unsafe {
+                let dst = this.buf.chunk_mut().as_uninit_slice_mut();
+                dst.fill(MaybeUninit::new(0));
+
+                &mut *(dst as *mut [MaybeUninit<u8>] as *mut [u8])
+            };
+
+            let n = ready!(Pin::new(&mut *this.reader).poll_read(cx, dst))?;
+            assert!(n <= dst.len(), "the reader returned more bytes than requested");
+
+            n
+        };
+
+        // SAFETY: The first `n` bytes of the chunk have been initialized above.
+        unsafe { this.buf.advance_mut(n) };
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+/// The future returned by [`AsyncWriteBufExt::write_buf`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct WriteBufFuture<'a, W: ?Sized, B: ?Sized> {
+    writer: &'a mut W,
+    buf: &'a mut B,
+}
+
+impl<W, B> Future for WriteBufFuture<'_, W, B>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+    B: Buf + ?Sized,
+{
+    type Output = std::io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = Pin::into_inner(self);
+
+        if !this.buf.has_remaining() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let n = ready!(Pin::new(&mut *this.writer).poll_write(cx, this.buf.chunk()))?;
+        this.buf.advance(n);
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+/// The future returned by [`AsyncWriteBufExt::write_all_buf`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct WriteAllBufFuture<'a, W: ?Sized, B: ?Sized> {
+    writer: &'a mut W,
+    buf: &'a mut B,
+}
+
+impl<W, B> Future for WriteAllBufFuture<'_, W, B>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+    B: Buf + ?Sized,
+{
+    type Output = std::io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = Pin::into_inner(self);
+
+        while this.buf.has_remaining() {
+            let n = ready!(Pin::new(&mut *this.writer).poll_write(cx, this.buf.chunk()))?;
+            if n == 0 {
+                return Poll::Ready(Err(Error::new(ErrorKind::WriteZero, "write zero bytes into writer")));
+            }
+
+            this.buf.advance(n);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+
+    use ::bytes::{Bytes, BytesMut};
+
+    use crate::io::{ScriptAction, ScriptedIo};
+
+    #[cfg(feature = "tokio-rt")]
+    fn payload(len: usize) -> BytesMut {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn read_buf_fills_the_spare_capacity() {
+        let mut reader = Cursor::new(b"hello world".to_vec());
+
+        let mut buf = BytesMut::with_capacity(4).limit(4);
+        assert_eq!(block_on(reader.read_buf(&mut buf)).unwrap(), 4);
+        // Without spare capacity the reader does not get polled.
+        assert_eq!(block_on(reader.read_buf(&mut buf)).unwrap(), 0);
+        assert_eq!(&buf.into_inner()[..], b"hell");
+
+        let mut buf = BytesMut::from(&b">"[..]);
+        while block_on(reader.read_buf(&mut buf)).unwrap() > 0 {}
+        assert_eq!(&buf[..], b">o world");
+    }
+
+    #[test]
+    fn write_buf_advances_by_the_bytes_written() {
+        let mut writer = ScriptedIo::new([ScriptAction::ShortWrite(3)]);
+
+        let mut buf = Bytes::from_static(b"hello");
+        assert_eq!(block_on(writer.write_buf(&mut buf)).unwrap(), 3);
+        assert_eq!(&buf[..], b"lo");
+        assert_eq!(writer.written(), b"hel");
+
+        // An empty buffer does not poll the writer.
+        assert_eq!(block_on(writer.write_buf(&mut Bytes::new())).unwrap(), 0);
+    }
+
+    #[test]
+    fn write_all_buf_writes_every_chunk() {
+        let mut writer = ScriptedIo::new([
+            ScriptAction::ShortWrite(4),
+            ScriptAction::Pending,
+            ScriptAction::ShortWrite(4),
+            ScriptAction::ShortWrite(100),
+        ]);
+
+        let mut buf = Bytes::from_static(b"hello ").chain(&b"world"[..]);
+        block_on(writer.write_all_buf(&mut buf)).unwrap();
+
+        assert!(!buf.has_remaining());
+        assert_eq!(writer.written(), b"hello world");
+        writer.assert_done();
+    }
+
+    #[test]
+    fn write_all_buf_fails_on_write_zero() {
+        let mut writer = ScriptedIo::new([ScriptAction::ShortWrite(2), ScriptAction::ShortWrite(0)]);
+
+        let mut buf = Bytes::from_static(b"hello");
+        let err = block_on(writer.write_all_buf(&mut buf)).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert_eq!(&buf[..], b"llo");
+    }
+
+    /// Sends a payload through a duplex pipe, reading it with the inherent `read_buf` of
+    /// `TokioCompat` or with the generic one.
+    #[cfg(feature = "tokio-rt")]
+    fn duplex_round_trip(read_buffer: usize, inherent: bool) {
+        use crate::io::TokioCompat;
+
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let (client, server) = ::tokio::io::duplex(1024);
+            let mut server = TokioCompat::with_read_buffer_capacity(server, read_buffer);
+
+            let sent = payload(256 * 1024 + 7);
+            let mut outgoing = sent.clone().freeze();
+
+            let send = ::tokio::spawn(async move {
+                let mut client = TokioCompat::new(client);
+                client.write_all_buf(&mut outgoing).await.unwrap();
+            });
+
+            let mut received = BytesMut::new();
+            loop {
+                received.reserve(4096);
+
+                let n = match inherent {
+                    true => server.read_buf(&mut received).await.unwrap(),
+                    false => AsyncReadBufExt::read_buf(&mut server, &mut received).await.unwrap(),
+                };
+                if n == 0 {
+                    break;
+                }
+            }
+
+            send.await.unwrap();
+            assert_eq!(received, sent);
+        });
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_duplex_round_trip() {
+        duplex_round_trip(0, true);
+        duplex_round_trip(0, false);
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_duplex_round_trip_through_the_read_buffer() {
+        duplex_round_trip(8 * 1024, true);
+        duplex_round_trip(8 * 1024, false);
+    }
+}
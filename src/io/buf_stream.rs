@@ -0,0 +1,287 @@
+use std::io::{Error, IoSlice};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncBufRead, AsyncWrite, AsyncWriteExt};
+use futures::io::{BufReader, BufWriter};
+use futures::ready;
+
+
+
+/// Wraps a stream and buffers both its input and its output.
+///
+/// This is the combination of a [`BufReader`] and a [`BufWriter`] on the same stream. Unlike
+/// stacking the two by hand, pending writes are flushed before the stream is read from. This
+/// keeps request/response protocols from waiting on a response to a request which is still sitting
+/// in the write buffer.
+pub struct BufStream<S> {
+    inner: BufReader<BufWriter<S>>,
+}
+
+impl<S> BufStream<S>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    /// Creates a new instance with the default buffer capacities.
+    pub fn new(stream: S) -> Self {
+        Self {
+            inner: BufReader::new(BufWriter::new(stream)),
+        }
+    }
+
+    /// Creates a new instance with the given read and write buffer capacities.
+    pub fn with_capacity(read_capacity: usize, write_capacity: usize, stream: S) -> Self {
+        Self {
+            inner: BufReader::with_capacity(
+                read_capacity,
+                BufWriter::with_capacity(write_capacity, stream),
+            ),
+        }
+    }
+}
+
+impl<S> BufStream<S> {
+    /// Get a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref().get_ref()
+    }
+
+    /// Get a mutable reference to the wrapped stream.
+    ///
+    /// Reading from or writing to the stream directly may corrupt the buffered data.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.inner.get_mut().get_mut()
+    }
+
+    /// Returns the data which has been read from the stream but not yet been consumed.
+    pub fn read_buffer(&self) -> &[u8] {
+        self.inner.buffer()
+    }
+
+    /// Returns the data which has been written but not yet been flushed to the stream.
+    pub fn write_buffer(&self) -> &[u8] {
+        self.inner.get_ref().buffer()
+    }
+}
+
+impl<S> BufStream<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    /// Flushes the pending writes and returns the wrapped stream.
+    ///
+    /// Any data left in the read buffer is lost, see [`read_buffer`](Self::read_buffer).
+    pub async fn into_inner(mut self) -> std::io::Result<S> {
+        self.inner.get_mut().flush().await?;
+
+        Ok(self.inner.into_inner().into_inner())
+    }
+
+    /// Flushes the pending writes if the read buffer has been drained.
+    fn poll_flush_before_read(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        if self.inner.buffer().is_empty() && !self.inner.get_ref().buffer().is_empty() {
+            ready!(Pin::new(self.inner.get_mut()).poll_flush(cx))?;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> AsyncRead for BufStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        ready!(this.poll_flush_before_read(cx))?;
+
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S> AsyncBufRead for BufStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<&[u8], Error>> {
+        let this = Pin::into_inner(self);
+
+        ready!(this.poll_flush_before_read(cx))?;
+
+        Pin::new(&mut this.inner).poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        Pin::new(&mut Pin::into_inner(self).inner).consume(amt)
+    }
+}
+
+impl<S> AsyncWrite for BufStream<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Error>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, Error>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Error>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_flush(cx)
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Error>> {
+        // The inner `BufWriter` flushes its buffer before closing the stream.
+        Pin::new(&mut Pin::into_inner(self).inner).poll_close(cx)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::ErrorKind;
+
+    use futures::executor::block_on;
+    use futures::io::{AsyncBufReadExt, AsyncReadExt};
+
+    use crate::io::{ScriptAction, ScriptedIo};
+
+    #[test]
+    fn reading_flushes_the_request_first() {
+        // A read before the write would not match the script and panic.
+        let io = ScriptedIo::new([
+            ScriptAction::ShortWrite(64),
+            ScriptAction::Read(b"OK\n".to_vec()),
+            ScriptAction::Eof,
+        ]);
+        let mut stream = BufStream::new(io);
+
+        block_on(stream.write_all(b"GET\n")).unwrap();
+        assert_eq!(stream.write_buffer(), b"GET\n");
+        assert!(stream.get_ref().written().is_empty());
+
+        let mut line = String::new();
+        block_on(stream.read_line(&mut line)).unwrap();
+        assert_eq!(line, "OK\n");
+        assert_eq!(stream.get_ref().written(), b"GET\n");
+        assert!(stream.write_buffer().is_empty());
+
+        assert_eq!(block_on(stream.read(&mut [0; 8])).unwrap(), 0);
+        stream.get_ref().assert_done();
+    }
+
+    #[test]
+    fn buffered_reads_do_not_flush() {
+        let io = ScriptedIo::new([
+            ScriptAction::Read(b"abcd".to_vec()),
+            ScriptAction::ShortWrite(1),
+            ScriptAction::Eof,
+        ]);
+        let mut stream = BufStream::new(io);
+        let mut buf = [0; 2];
+
+        block_on(stream.read_exact(&mut buf)).unwrap();
+        assert_eq!(&buf, b"ab");
+        block_on(stream.write_all(b"x")).unwrap();
+
+        // The rest of the response is already buffered, so the write stays buffered as well.
+        block_on(stream.read_exact(&mut buf)).unwrap();
+        assert_eq!(&buf, b"cd");
+        assert!(stream.get_ref().written().is_empty());
+
+        // Only reading from the stream again sends the write.
+        assert_eq!(block_on(stream.read(&mut buf)).unwrap(), 0);
+        assert_eq!(stream.get_ref().written(), b"x");
+        stream.get_ref().assert_done();
+    }
+
+    #[test]
+    fn capacities_bound_the_buffers() {
+        let io = ScriptedIo::new([ScriptAction::Read(b"abc".to_vec())]);
+        let mut stream = BufStream::with_capacity(2, 4, io);
+
+        assert_eq!(block_on(stream.fill_buf()).unwrap(), b"ab");
+        Pin::new(&mut stream).consume(1);
+        assert_eq!(stream.read_buffer(), b"b");
+        assert_eq!(stream.get_ref().remaining().collect::<Vec<_>>(), [&ScriptAction::Read(b"c".to_vec())]);
+
+        // Writes stay buffered while they fit, one not fitting sends the buffer on first.
+        let io = ScriptedIo::new([ScriptAction::ShortWrite(64)]);
+        let mut stream = BufStream::with_capacity(2, 4, io);
+
+        block_on(stream.write_all(b"123")).unwrap();
+        assert_eq!(stream.write_buffer(), b"123");
+        block_on(stream.write_all(b"45")).unwrap();
+        assert_eq!(stream.get_ref().written(), b"123");
+        assert_eq!(stream.write_buffer(), b"45");
+        stream.get_ref().assert_done();
+    }
+
+    #[test]
+    fn close_flushes_before_closing() {
+        let io = ScriptedIo::new([
+            ScriptAction::ShortWrite(2),
+            ScriptAction::Pending,
+            ScriptAction::ShortWrite(8),
+        ]);
+        let mut stream = BufStream::new(io);
+
+        block_on(stream.write_all(b"bye")).unwrap();
+        block_on(stream.close()).unwrap();
+
+        assert_eq!(stream.get_ref().written(), b"bye");
+        stream.get_ref().assert_done();
+    }
+
+    #[test]
+    fn into_inner_flushes_pending_writes() {
+        let io = ScriptedIo::new([ScriptAction::ShortWrite(64)]);
+        let mut stream = BufStream::new(io);
+
+        block_on(stream.write_all(b"pending")).unwrap();
+        let io = block_on(stream.into_inner()).unwrap();
+
+        assert_eq!(io.written(), b"pending");
+        io.assert_done();
+    }
+
+    #[test]
+    fn failed_flush_fails_the_read() {
+        let io = ScriptedIo::new([ScriptAction::Err(ErrorKind::BrokenPipe)]);
+        let mut stream = BufStream::new(io);
+
+        block_on(stream.write_all(b"request")).unwrap();
+        let err = block_on(stream.read(&mut [0; 8])).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+        assert_eq!(stream.write_buffer(), b"request");
+        stream.get_ref().assert_done();
+    }
+}
@@ -0,0 +1,126 @@
+use super::FuturesCompat;
+
+use futures::io::{AsyncRead, AsyncWrite};
+
+pub use tokio_util::codec::{
+    Decoder,
+    Encoder,
+    Framed,
+    FramedParts,
+    BytesCodec,
+    LinesCodec,
+    LinesCodecError,
+    AnyDelimiterCodec,
+    AnyDelimiterCodecError,
+    LengthDelimitedCodec,
+    LengthDelimitedCodecError,
+};
+
+
+
+/// Frames a stream implementing the futures io traits with a [`tokio-util`](https://docs.rs/tokio-util)
+/// codec.
+///
+/// The stream gets wrapped in a [`FuturesCompat`], so it works with any runtime. The returned
+/// [`Framed`] implements the [`Stream`](futures::stream::Stream) and [`Sink`](futures::sink::Sink)
+/// traits of the [`futures`](https://docs.rs/futures) crate.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use futures::{SinkExt, StreamExt};
+/// use fut_compat::io::{framed, LinesCodec};
+///
+/// let stream = async_std::net::TcpStream::connect("127.0.0.1:8080").await?;
+/// let mut frames = framed(stream, LinesCodec::new());
+///
+/// frames.send("ping").await?;
+///
+/// if let Some(line) = frames.next().await {
+///     println!("received {}", line?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn framed<S, C>(stream: S, codec: C) -> Framed<FuturesCompat<S>, C>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    Framed::new(FuturesCompat::new(stream), codec)
+}
+
+
+
+#[cfg(all(test, feature = "async-std-rt"))]
+mod tests {
+    use super::*;
+
+    use futures::{SinkExt, StreamExt, TryStreamExt};
+    use tokio_util::bytes::Bytes;
+
+    /// Echoes length delimited frames over an async-std loopback connection.
+    #[test]
+    fn length_delimited_echo_over_async_std() {
+        ::async_std::task::block_on(async {
+            let listener = ::async_std::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            let server = ::async_std::task::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let (mut sink, stream) = framed(stream, LengthDelimitedCodec::new()).split();
+
+                // Echoes every frame until the client disconnects.
+                sink.send_all(&mut stream.map_ok(|frame| frame.freeze())).await.unwrap();
+            });
+
+            let stream = ::async_std::net::TcpStream::connect(addr).await.unwrap();
+            let (mut sink, mut frames) = framed(stream, LengthDelimitedCodec::new()).split();
+
+            let payloads = vec![
+                Bytes::from_static(b"hello"),
+                Bytes::new(),
+                Bytes::from((0..1024 * 1024).map(|i| (i % 251) as u8).collect::<Vec<u8>>()),
+                Bytes::from_static(b"bye"),
+            ];
+
+            // The large frame does not fit into the socket buffers, so the echoes have to be
+            // received while sending.
+            let send = async {
+                for payload in &payloads {
+                    sink.feed(payload.clone()).await.unwrap();
+                }
+                sink.flush().await.unwrap();
+            };
+            let receive = async {
+                for payload in &payloads {
+                    let frame = frames.next().await.unwrap().unwrap();
+                    assert!(frame == payload, "frame of {} bytes", payload.len());
+                }
+            };
+            futures::join!(send, receive);
+
+            // Closing does not shut down async-std streams, so dropping the client is what ends
+            // the echo loop.
+            drop((sink, frames));
+            server.await;
+        });
+    }
+
+    #[test]
+    fn oversized_frames_fail() {
+        ::async_std::task::block_on(async {
+            let listener = ::async_std::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let (accepted, client) = futures::join!(listener.accept(), ::async_std::net::TcpStream::connect(addr));
+
+            let mut sender = framed(client.unwrap(), LengthDelimitedCodec::new());
+            let mut receiver = framed(accepted.unwrap().0, LengthDelimitedCodec::builder().max_frame_length(16).new_codec());
+
+            sender.send(Bytes::from_static(b"a frame longer than 16 bytes")).await.unwrap();
+
+            let err = receiver.next().await.unwrap().unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        });
+    }
+}
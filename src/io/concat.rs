@@ -0,0 +1,347 @@
+use std::io::Error;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncBufRead, BufReader};
+use futures::stream::{self, Stream, StreamExt};
+use futures::ready;
+
+use crate::fs::File;
+
+
+
+/// Reads the files at `paths` back-to-back, as if they were a single file.
+///
+/// The files get opened lazily one at a time with the [`File`] implementation `F`, i.e. a file is
+/// only opened once the previous one reached EOF. Each file is wrapped in a [`BufReader`], so the
+/// returned reader also implements [`AsyncBufRead`]. The first error, including an error while
+/// opening a file, gets returned and ends the reader.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::io::{concat_files, AsyncReadExt, TokioCompat};
+///
+/// let paths = vec!["upload.part0".into(), "upload.part1".into(), "upload.part2".into()];
+/// let mut upload = concat_files::<TokioCompat<tokio::fs::File>>(paths);
+///
+/// let mut contents = Vec::new();
+/// upload.read_to_end(&mut contents).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn concat_files<F>(paths: Vec<PathBuf>) -> impl AsyncBufRead + Send + Unpin
+where
+    F: File + AsyncRead + Send + Unpin + 'static,
+{
+    let files = stream::iter(paths)
+        .then(|path| async move { F::open(path).await.map(BufReader::new) })
+        .boxed();
+
+    Concat::new(files)
+}
+
+/// Reads a sequence of readers back-to-back, as if they were a single reader.
+///
+/// The readers are taken lazily from a [`Stream`] of `io::Result<R>`, i.e. the next reader is only
+/// requested once the previous one reached EOF. The first error, whether yielded by the stream or
+/// returned by a reader, gets returned and ends the reader. Implements [`AsyncBufRead`] if the
+/// readers do.
+///
+/// # Example
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use futures::io::Cursor;
+/// use fut_compat::io::{AsyncReadExt, Concat};
+///
+/// let parts = vec![Cursor::new(b"hello ".to_vec()), Cursor::new(b"world".to_vec())];
+/// let mut reader = Concat::new(futures::stream::iter(parts.into_iter().map(Ok)));
+///
+/// let mut contents = String::new();
+/// reader.read_to_string(&mut contents).await?;
+///
+/// assert_eq!(contents, "hello world");
+/// # std::io::Result::Ok(())
+/// # }).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Concat<S, R> {
+    readers: S,
+    current: Option<R>,
+    done: bool,
+}
+
+impl<S, R> Concat<S, R>
+where
+    S: Stream<Item = std::io::Result<R>> + Unpin,
+    R: AsyncRead + Unpin,
+{
+    /// Creates a new reader over the readers yielded by `readers`.
+    pub fn new(readers: S) -> Self {
+        Self {
+            readers,
+            current: None,
+            done: false,
+        }
+    }
+}
+
+impl<S, R> Concat<S, R>
+where
+    S: Stream<Item = std::io::Result<R>> + Unpin,
+{
+    /// Makes sure a reader is available, returning `false` once all readers are exhausted.
+    fn poll_current(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<bool>> {
+        if self.current.is_some() {
+            return Poll::Ready(Ok(true));
+        }
+        if self.done {
+            return Poll::Ready(Ok(false));
+        }
+
+        match ready!(self.readers.poll_next_unpin(cx)) {
+            Some(Ok(reader)) => {
+                self.current = Some(reader);
+
+                Poll::Ready(Ok(true))
+            }
+            Some(Err(err)) => Poll::Ready(Err(self.fail(err))),
+            None => {
+                self.done = true;
+
+                Poll::Ready(Ok(false))
+            }
+        }
+    }
+
+    /// Ends the reader because of `err`.
+    fn fail(&mut self, err: Error) -> Error {
+        self.current = None;
+        self.done = true;
+
+        err
+    }
+}
+
+impl<S, R> AsyncRead for Concat<S, R>
+where
+    S: Stream<Item = std::io::Result<R>> + Unpin,
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        loop {
+            if !ready!(this.poll_current(cx))? {
+                return Poll::Ready(Ok(0));
+            }
+
+            let current = this.current.as_mut().expect("a reader is available");
+
+            match ready!(Pin::new(current).poll_read(cx, buf)) {
+                Ok(0) => this.current = None,
+                Ok(n) => return Poll::Ready(Ok(n)),
+                Err(err) => return Poll::Ready(Err(this.fail(err))),
+            }
+        }
+    }
+}
+
+impl<S, R> AsyncBufRead for Concat<S, R>
+where
+    S: Stream<Item = std::io::Result<R>> + Unpin,
+    R: AsyncBufRead + Unpin,
+{
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<&[u8], Error>> {
+        let this = Pin::into_inner(self);
+
+        loop {
+            if !ready!(this.poll_current(cx))? {
+                return Poll::Ready(Ok(&[]));
+            }
+
+            let current = this.current.as_mut().expect("a reader is available");
+
+            // The returned slice borrows the reader, so we only check for EOF here and fill the
+            // buffer a second time below, which is cheap as the data is already buffered.
+            match ready!(Pin::new(current).poll_fill_buf(cx)) {
+                Ok([]) => this.current = None,
+                Ok(_) => break,
+                Err(err) => return Poll::Ready(Err(this.fail(err))),
+            }
+        }
+
+        let current = this.current.as_mut().expect("a reader is available");
+
+        Pin::new(current).poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        if let Some(current) = &mut Pin::into_inner(self).current {
+            Pin::new(current).consume(amt)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::ErrorKind;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::executor::block_on;
+    use futures::io::{AsyncBufReadExt, AsyncReadExt, Cursor};
+
+    use crate::io::{ScriptAction, ScriptedIo};
+
+    fn cursors(parts: Vec<&'static str>) -> impl Stream<Item = std::io::Result<Cursor<&'static [u8]>>> + Unpin {
+        stream::iter(parts.into_iter().map(|part| Ok(Cursor::new(part.as_bytes()))))
+    }
+
+    #[test]
+    fn readers_are_read_back_to_back() {
+        let mut reader = Concat::new(cursors(vec!["hello", "", " ", "world", ""]));
+
+        let mut contents = String::new();
+        block_on(reader.read_to_string(&mut contents)).unwrap();
+        assert_eq!(contents, "hello world");
+
+        // The reader stays at EOF.
+        assert_eq!(block_on(reader.read(&mut [0; 8])).unwrap(), 0);
+    }
+
+    #[test]
+    fn readers_are_taken_lazily() {
+        let taken = AtomicUsize::new(0);
+        let readers = stream::iter(["abc", "def"]).map(|part| {
+            taken.fetch_add(1, Ordering::Relaxed);
+            Ok(Cursor::new(part.as_bytes()))
+        });
+        let mut reader = Concat::new(readers);
+
+        let mut buf = [0u8; 3];
+        block_on(reader.read_exact(&mut buf)).unwrap();
+        assert_eq!(taken.load(Ordering::Relaxed), 1);
+
+        // The next reader is only requested once the first one reached EOF.
+        block_on(reader.read_exact(&mut buf)).unwrap();
+        assert_eq!((&buf, taken.load(Ordering::Relaxed)), (b"def", 2));
+    }
+
+    #[test]
+    fn lines_span_readers() {
+        let mut reader = Concat::new(cursors(vec!["ab", "", "c\nd", "e\n"]));
+
+        let mut line = String::new();
+        block_on(reader.read_line(&mut line)).unwrap();
+        assert_eq!(line, "abc\n");
+
+        line.clear();
+        block_on(reader.read_line(&mut line)).unwrap();
+        assert_eq!(line, "de\n");
+
+        assert_eq!(block_on(reader.fill_buf()).unwrap(), b"");
+    }
+
+    #[test]
+    fn errors_of_the_stream_end_the_reader() {
+        let readers = stream::iter([
+            Ok(Cursor::new(&b"hello"[..])),
+            Err(Error::new(ErrorKind::NotFound, "missing part")),
+            Ok(Cursor::new(&b"world"[..])),
+        ]);
+        let mut reader = Concat::new(readers);
+
+        let mut contents = Vec::new();
+        let err = block_on(reader.read_to_end(&mut contents)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(contents, b"hello");
+
+        assert_eq!(block_on(reader.read(&mut [0; 8])).unwrap(), 0);
+    }
+
+    #[test]
+    fn errors_of_a_reader_end_the_reader() {
+        let readers = stream::iter([
+            Ok(ScriptedIo::new([ScriptAction::Read(b"he".to_vec()), ScriptAction::Pending, ScriptAction::Eof])),
+            Ok(ScriptedIo::new([ScriptAction::Read(b"llo".to_vec()), ScriptAction::Err(ErrorKind::ConnectionReset)])),
+            Ok(ScriptedIo::new([])),
+        ]);
+        let mut reader = Concat::new(readers);
+
+        let mut contents = Vec::new();
+        let err = block_on(reader.read_to_end(&mut contents)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+        assert_eq!(contents, b"hello");
+
+        // Neither the failed reader nor the following one get polled anymore.
+        assert_eq!(block_on(reader.read(&mut [0; 8])).unwrap(), 0);
+    }
+
+    /// Writes some files and reads them back through [`concat_files`] with the files of a
+    /// runtime.
+    #[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+    async fn concat_written_files<F>(name: &str)
+    where
+        F: File + AsyncRead + Send + Unpin + 'static,
+    {
+        let dir = std::env::temp_dir();
+        let parts: Vec<Vec<u8>> = [70_000, 0, 1, 12_345]
+            .iter()
+            .enumerate()
+            .map(|(i, len)| (0..*len).map(|j: usize| ((i + j) % 251) as u8).collect())
+            .collect();
+
+        let mut paths = Vec::new();
+        for (i, part) in parts.iter().enumerate() {
+            let path = dir.join(format!("fut-compat-concat-{}-{}-{}", name, std::process::id(), i));
+            std::fs::write(&path, part).unwrap();
+            paths.push(path);
+        }
+
+        let mut contents = Vec::new();
+        concat_files::<F>(paths.clone()).read_to_end(&mut contents).await.unwrap();
+        assert!(contents == parts.concat());
+
+        // A missing file fails the reader once the previous files have been read.
+        let missing = dir.join(format!("fut-compat-concat-{}-{}-missing", name, std::process::id()));
+        let mut reader = concat_files::<F>(vec![paths[0].clone(), missing, paths[1].clone()]);
+
+        let mut contents = Vec::new();
+        let err = reader.read_to_end(&mut contents).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(contents == parts[0]);
+
+        for path in paths {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_concat_files() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(
+            concat_written_files::<crate::io::TokioCompat<::tokio::fs::File>>("tokio"),
+        );
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_concat_files() {
+        ::async_std::task::block_on(concat_written_files::<::async_std::fs::File>("async-std"));
+    }
+}
@@ -0,0 +1,687 @@
+use std::future::Future;
+use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::future::poll_fn;
+use futures::ready;
+
+
+
+/// The size of the buffer used by [`copy_with_progress`].
+const BUF_SIZE: usize = 8 * 1024;
+
+/// Copies the entire contents of `reader` into `writer`, reporting the progress to `progress`.
+///
+/// After each successful write `progress` is called with the total number of bytes copied so far.
+/// Once `reader` reached EOF, `writer` gets flushed and the total number of bytes copied is
+/// returned.
+///
+/// The progress can also be observed from another task through the [`CopyProgress`] handle
+/// returned by [`CopyWithProgress::progress`].
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> std::io::Result<()> {
+/// use futures::io::Cursor;
+/// use fut_compat::io::copy_with_progress;
+///
+/// let reader = Cursor::new(vec![0u8; 1024 * 1024]);
+/// let mut writer = Cursor::new(Vec::new());
+///
+/// let copy = copy_with_progress(reader, &mut writer, |copied| println!("{} bytes copied", copied));
+/// let progress = copy.progress();
+///
+/// let total = copy.await?;
+///
+/// assert_eq!(total, progress.bytes());
+/// # Ok(())
+/// # }
+/// ```
+pub fn copy_with_progress<R, W, F>(reader: R, writer: W, progress: F) -> CopyWithProgress<R, W, F>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+    F: FnMut(u64) + Unpin,
+{
+    CopyWithProgress {
+        reader,
+        writer,
+        progress,
+        counter: Arc::new(AtomicU64::new(0)),
+        copy: CopyBuffer::new(vec![0u8; BUF_SIZE].into_boxed_slice()),
+    }
+}
+
+/// A handle for observing the progress of a [`CopyWithProgress`] future, e.g. from another task.
+#[derive(Clone, Debug)]
+pub struct CopyProgress {
+    counter: Arc<AtomicU64>,
+}
+
+impl CopyProgress {
+    /// Returns the number of bytes copied so far.
+    pub fn bytes(&self) -> u64 {
+        self.counter.load(Ordering::Relaxed)
+    }
+}
+
+/// The future returned by [`copy_with_progress`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct CopyWithProgress<R, W, F> {
+    reader: R,
+    writer: W,
+    progress: F,
+    counter: Arc<AtomicU64>,
+    copy: CopyBuffer<Box<[u8]>>,
+}
+
+impl<R, W, F> CopyWithProgress<R, W, F> {
+    /// Returns a handle for observing the progress of the copy.
+    pub fn progress(&self) -> CopyProgress {
+        CopyProgress {
+            counter: self.counter.clone(),
+        }
+    }
+}
+
+impl<R, W, F> Future for CopyWithProgress<R, W, F>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+    F: FnMut(u64) + Unpin,
+{
+    type Output = std::io::Result<u64>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = Pin::into_inner(self);
+
+        let counter = &this.counter;
+        let progress = &mut this.progress;
+
+        this.copy.poll_copy(cx, &mut this.reader, &mut this.writer, |amt| {
+            counter.store(amt, Ordering::Relaxed);
+            progress(amt);
+        })
+    }
+}
+
+
+
+/// The state of a copy through a buffer.
+struct CopyBuffer<B> {
+    buf: B,
+    pos: usize,
+    cap: usize,
+    amt: u64,
+    read_done: bool,
+}
+
+impl<B> CopyBuffer<B>
+where
+    B: DerefMut<Target = [u8]>,
+{
+    fn new(buf: B) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            cap: 0,
+            amt: 0,
+            read_done: false,
+        }
+    }
+
+    /// Copies from `reader` into `writer` until EOF, calling `progress` after each write.
+    fn poll_copy<R, W>(
+        &mut self,
+        cx: &mut Context<'_>,
+        reader: &mut R,
+        writer: &mut W,
+        mut progress: impl FnMut(u64),
+    ) -> Poll<std::io::Result<u64>>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        loop {
+            // Refill the buffer once everything read so far has been written.
+            if self.pos == self.cap && !self.read_done {
+                let n = ready!(Pin::new(&mut *reader).poll_read(cx, &mut self.buf))?;
+
+                if n == 0 {
+                    self.read_done = true;
+                } else {
+                    self.pos = 0;
+                    self.cap = n;
+                }
+            }
+
+            while self.pos < self.cap {
+                let n = ready!(Pin::new(&mut *writer).poll_write(cx, &self.buf[self.pos..self.cap]))?;
+
+                if n == 0 {
+                    return Poll::Ready(Err(Error::new(
+                        ErrorKind::WriteZero,
+                        "write zero bytes into writer",
+                    )));
+                }
+
+                self.pos += n;
+                self.amt += n as u64;
+
+                progress(self.amt);
+            }
+
+            if self.read_done {
+                ready!(Pin::new(&mut *writer).poll_flush(cx))?;
+
+                return Poll::Ready(Ok(self.amt));
+            }
+        }
+    }
+}
+
+/// Copies the entire contents of `reader` into `writer`, using a buffer of `buf_size` bytes.
+///
+/// Unlike [`copy`](super::copy), which always uses a buffer of 8 KiB, this allows using a larger
+/// buffer for high throughput transfers or a smaller one for many concurrent copies. A size of `0`
+/// is treated as `1`. Once `reader` reached EOF, `writer` gets flushed and the total number of
+/// bytes copied is returned.
+pub async fn copy_with_buffer<R, W>(mut reader: R, mut writer: W, buf_size: usize) -> std::io::Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut copy = CopyBuffer::new(vec![0u8; buf_size.max(1)].into_boxed_slice());
+
+    poll_fn(|cx| copy.poll_copy(cx, &mut reader, &mut writer, |_| {})).await
+}
+
+/// Copies the entire contents of `reader` into `writer`, using a buffer borrowed from `pool`.
+///
+/// The buffer gets returned to the pool once the copy is done, so e.g. a proxy does not need to
+/// allocate a new buffer for every connection. Once `reader` reached EOF, `writer` gets flushed and
+/// the total number of bytes copied is returned.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::io::{copy_with_pool, BufferPool};
+///
+/// let pool = BufferPool::new(64 * 1024);
+///
+/// for _ in 0..100 {
+///     let reader = futures::io::Cursor::new(vec![0u8; 1024 * 1024]);
+///     copy_with_pool(reader, futures::io::sink(), &pool).await?;
+/// }
+///
+/// assert_eq!(pool.allocations(), 1);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn copy_with_pool<R, W>(mut reader: R, mut writer: W, pool: &BufferPool) -> std::io::Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut copy = CopyBuffer::new(pool.get());
+
+    poll_fn(|cx| copy.poll_copy(cx, &mut reader, &mut writer, |_| {})).await
+}
+
+
+
+struct PoolInner {
+    buf_size: usize,
+    max_idle: usize,
+    idle: Mutex<Vec<Box<[u8]>>>,
+    allocations: AtomicU64,
+}
+
+/// A pool of buffers of a fixed size, shared by clones of it.
+///
+/// Buffers get allocated on demand and returned to the pool when the [`PooledBuffer`] gets
+/// dropped. By default all returned buffers are kept, see [`with_max_idle`](Self::with_max_idle).
+/// A new buffer is only allocated while all kept buffers are in use, so the number of allocations
+/// is bounded by the highest number of buffers in use at the same time, plus the surplus which got
+/// dropped because the pool was full.
+///
+/// The pool is `Send` and `Sync` and does not depend on any executor, so clones of it can be
+/// handed to the tasks of any runtime.
+#[derive(Clone)]
+pub struct BufferPool {
+    inner: Arc<PoolInner>,
+}
+
+impl BufferPool {
+    /// Creates a new, empty pool of buffers holding `buf_size` bytes each.
+    ///
+    /// A size of `0` is treated as `1`.
+    pub fn new(buf_size: usize) -> Self {
+        Self::with_max_idle(buf_size, usize::MAX)
+    }
+
+    /// Creates a new, empty pool of buffers holding `buf_size` bytes each, keeping at most
+    /// `max_idle` returned buffers and dropping the surplus.
+    ///
+    /// A size of `0` is treated as `1`.
+    pub fn with_max_idle(buf_size: usize, max_idle: usize) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                buf_size: buf_size.max(1),
+                max_idle,
+                idle: Mutex::new(Vec::new()),
+                allocations: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Takes a buffer out of the pool, allocating a new one if the pool is empty.
+    pub fn get(&self) -> PooledBuffer {
+        let buf = self.inner.idle.lock().unwrap_or_else(|err| err.into_inner()).pop();
+        let buf = match buf {
+            Some(buf) => buf,
+            None => {
+                self.inner.allocations.fetch_add(1, Ordering::Relaxed);
+
+                vec![0u8; self.inner.buf_size].into_boxed_slice()
+            }
+        };
+
+        PooledBuffer {
+            buf: Some(buf),
+            pool: self.inner.clone(),
+        }
+    }
+
+    /// Returns the size of the buffers.
+    pub fn buf_size(&self) -> usize {
+        self.inner.buf_size
+    }
+
+    /// Returns the number of buffers allocated by the pool so far.
+    pub fn allocations(&self) -> u64 {
+        self.inner.allocations.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of buffers currently kept in the pool.
+    pub fn idle(&self) -> usize {
+        self.inner.idle.lock().unwrap_or_else(|err| err.into_inner()).len()
+    }
+}
+
+impl std::fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("buf_size", &self.inner.buf_size)
+            .field("max_idle", &self.inner.max_idle)
+            .field("allocations", &self.allocations())
+            .field("idle", &self.idle())
+            .finish()
+    }
+}
+
+/// A buffer borrowed from a [`BufferPool`], which gets returned to the pool when dropped.
+pub struct PooledBuffer {
+    buf: Option<Box<[u8]>>,
+    pool: Arc<PoolInner>,
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.buf.as_deref().expect("PooledBuffer holds a buffer until dropped")
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.buf.as_deref_mut().expect("PooledBuffer holds a buffer until dropped")
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if let Some(buf) = self.buf.take() {
+            let mut idle = self.pool.idle.lock().unwrap_or_else(|err| err.into_inner());
+
+            if idle.len() < self.pool.max_idle {
+                idle.push(buf);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for PooledBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PooledBuffer")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+
+    /// A writer accepting at most `max_write` bytes per write, which records its flushes.
+    #[derive(Default)]
+    struct ChunkedWriter {
+        data: Vec<u8>,
+        max_write: usize,
+        flushes: usize,
+    }
+
+    impl ChunkedWriter {
+        fn new(max_write: usize) -> Self {
+            Self {
+                max_write,
+                ..Self::default()
+            }
+        }
+    }
+
+    impl AsyncWrite for ChunkedWriter {
+        fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+            let this = Pin::into_inner(self);
+            let n = buf.len().min(this.max_write);
+            this.data.extend_from_slice(&buf[..n]);
+
+            Poll::Ready(Ok(n))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Pin::into_inner(self).flushes += 1;
+
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn progress_reports_every_write() {
+        let data = data(20_017);
+        let mut writer = ChunkedWriter::new(1000);
+        let mut reported = Vec::new();
+
+        let copy = copy_with_progress(Cursor::new(&data), &mut writer, |copied| reported.push(copied));
+        let progress = copy.progress();
+
+        let copied = block_on(copy).unwrap();
+        assert_eq!(copied, data.len() as u64);
+        assert_eq!(progress.bytes(), copied);
+        assert!(writer.data == data);
+        assert_eq!(writer.flushes, 1);
+
+        // One report per write, none of which is larger than the writer accepts. The reads of
+        // 8192, 8192 and 3633 bytes take 9, 9 and 4 writes.
+        assert_eq!(reported.len(), 9 + 9 + 4);
+        assert_eq!(reported.last(), Some(&copied));
+        assert!(reported.windows(2).all(|pair| pair[0] < pair[1] && pair[1] - pair[0] <= 1000));
+    }
+
+    #[test]
+    fn progress_is_observable_while_copying() {
+        let data = data(10_000);
+        let reader = YieldingReader {
+            inner: Cursor::new(data.clone()),
+            yielded: false,
+        };
+        let mut writer = ChunkedWriter::new(usize::MAX);
+
+        let mut last = 0;
+        let mut copy = copy_with_progress(reader, &mut writer, |copied| last = copied);
+        let progress = copy.progress();
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut observed = Vec::new();
+
+        let copied = loop {
+            match Pin::new(&mut copy).poll(&mut cx) {
+                Poll::Ready(result) => break result.unwrap(),
+                Poll::Pending => observed.push(progress.bytes()),
+            }
+        };
+        drop(copy);
+
+        // The reader returns 1000 bytes after every `Pending`.
+        assert_eq!(observed, (0..=10).map(|i| i * 1000).collect::<Vec<u64>>());
+        assert_eq!((copied, last), (10_000, 10_000));
+        assert_eq!(writer.flushes, 1);
+    }
+
+    #[test]
+    fn empty_reader_only_flushes() {
+        let mut writer = ChunkedWriter::new(usize::MAX);
+        let mut calls = 0;
+
+        let copied = block_on(copy_with_progress(futures::io::empty(), &mut writer, |_| calls += 1)).unwrap();
+
+        assert_eq!((copied, calls), (0, 0));
+        assert_eq!(writer.flushes, 1);
+    }
+
+    #[test]
+    fn writing_zero_bytes_fails() {
+        let mut writer = ChunkedWriter::new(0);
+
+        let err = block_on(copy_with_progress(Cursor::new(data(10)), &mut writer, |_| ())).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert_eq!(writer.flushes, 0);
+    }
+
+    /// Copies a file written beforehand into a second one, with the files of a runtime.
+    #[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+    async fn copy_between_files<Fl, O, Fut>(name: &str, open: O)
+    where
+        Fl: AsyncRead + AsyncWrite + Unpin,
+        O: Fn(std::path::PathBuf, bool) -> Fut,
+        Fut: Future<Output = std::io::Result<Fl>>,
+    {
+        use futures::io::AsyncWriteExt;
+
+        let dir = std::env::temp_dir();
+        let (from, to) = (
+            dir.join(format!("fut-compat-copy-{}-{}-from", name, std::process::id())),
+            dir.join(format!("fut-compat-copy-{}-{}-to", name, std::process::id())),
+        );
+        let data = data(1024 * 1024 + 17);
+        std::fs::write(&from, &data).unwrap();
+
+        let reader = open(from.clone(), false).await.unwrap();
+        let mut writer = open(to.clone(), true).await.unwrap();
+        let mut last = 0;
+
+        let copied = copy_with_progress(reader, &mut writer, |copied| last = copied).await.unwrap();
+        writer.close().await.unwrap();
+
+        assert_eq!((copied, last), (data.len() as u64, data.len() as u64));
+        assert!(std::fs::read(&to).unwrap() == data);
+
+        std::fs::remove_file(from).unwrap();
+        std::fs::remove_file(to).unwrap();
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_copy_between_files() {
+        use crate::io::TokioCompat;
+
+        ::tokio::runtime::Runtime::new().unwrap().block_on(copy_between_files("tokio", |path, create| async move {
+            let file = match create {
+                true => ::tokio::fs::File::create(path).await?,
+                false => ::tokio::fs::File::open(path).await?,
+            };
+
+            Ok(TokioCompat::new(file))
+        }));
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_copy_between_files() {
+        ::async_std::task::block_on(copy_between_files("async-std", |path, create| async move {
+            match create {
+                true => ::async_std::fs::File::create(path).await,
+                false => ::async_std::fs::File::open(path).await,
+            }
+        }));
+    }
+
+    #[test]
+    fn copies_with_any_buffer_size() {
+        let data = data(3 * 1024 * 1024 + 17);
+
+        for (buf_size, max_write) in [(0, usize::MAX), (1, usize::MAX), (1024 * 1024, usize::MAX), (1024 * 1024, 1000)] {
+            let mut writer = ChunkedWriter::new(max_write);
+
+            let copied = block_on(copy_with_buffer(Cursor::new(&data), &mut writer, buf_size)).unwrap();
+
+            assert_eq!(copied, data.len() as u64, "buffer of {} bytes", buf_size);
+            assert!(writer.data == data, "buffer of {} bytes", buf_size);
+            assert_eq!(writer.flushes, 1);
+        }
+    }
+
+    #[test]
+    fn sequential_copies_reuse_one_buffer() {
+        let pool = BufferPool::new(64 * 1024);
+        let data = data(1024 * 1024);
+
+        for _ in 0..100 {
+            let mut writer = ChunkedWriter::new(usize::MAX);
+
+            assert_eq!(block_on(copy_with_pool(Cursor::new(&data), &mut writer, &pool)).unwrap(), data.len() as u64);
+            assert!(writer.data == data);
+        }
+
+        assert_eq!(pool.allocations(), 1);
+        assert_eq!(pool.idle(), 1);
+        assert_eq!(pool.buf_size(), 64 * 1024);
+    }
+
+    #[test]
+    fn surplus_buffers_get_dropped() {
+        let pool = BufferPool::with_max_idle(16, 2);
+
+        let bufs: Vec<PooledBuffer> = (0..5).map(|_| pool.get()).collect();
+        assert!(bufs.iter().all(|buf| buf.len() == 16));
+        assert_eq!((pool.allocations(), pool.idle()), (5, 0));
+
+        drop(bufs);
+        assert_eq!(pool.idle(), 2);
+
+        let _bufs: Vec<PooledBuffer> = (0..3).map(|_| pool.get()).collect();
+        assert_eq!((pool.allocations(), pool.idle()), (6, 0));
+    }
+
+    /// A reader returning at most 1000 bytes per read, and `Pending` before each, so that copies
+    /// joined on one task are all in flight at the same time.
+    struct YieldingReader {
+        inner: Cursor<Vec<u8>>,
+        yielded: bool,
+    }
+
+    impl AsyncRead for YieldingReader {
+        fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<std::io::Result<usize>> {
+            let this = Pin::into_inner(self);
+
+            if !this.yielded {
+                this.yielded = true;
+                cx.waker().wake_by_ref();
+
+                return Poll::Pending;
+            }
+            this.yielded = false;
+
+            let len = buf.len().min(1000);
+            Pin::new(&mut this.inner).poll_read(cx, &mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn concurrent_copies_allocate_up_to_the_concurrency() {
+        const COPIES: usize = 32;
+        const MAX_IDLE: usize = 4;
+
+        let pool = BufferPool::with_max_idle(4096, MAX_IDLE);
+        let data = data(10_000);
+
+        for round in 1..=3 {
+            let copies = (0..COPIES).map(|_| async {
+                let reader = YieldingReader {
+                    inner: Cursor::new(data.clone()),
+                    yielded: false,
+                };
+                let mut writer = ChunkedWriter::new(usize::MAX);
+
+                let copied = copy_with_pool(reader, &mut writer, &pool).await.unwrap();
+
+                (copied, writer.data)
+            });
+
+            for (copied, written) in block_on(futures::future::join_all(copies)) {
+                assert_eq!(copied, data.len() as u64);
+                assert!(written == data);
+            }
+
+            // Every copy held a buffer at the same time, of which only `MAX_IDLE` are kept for
+            // the next round.
+            assert_eq!(pool.allocations(), (COPIES + (round - 1) * (COPIES - MAX_IDLE)) as u64);
+            assert_eq!(pool.idle(), MAX_IDLE);
+        }
+    }
+
+    #[test]
+    fn copies_on_many_threads_share_the_pool() {
+        const THREADS: usize = 8;
+
+        let pool = BufferPool::with_max_idle(4096, THREADS);
+        let data = Arc::new(data(50_000));
+
+        let threads: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let (pool, data) = (pool.clone(), data.clone());
+
+                std::thread::spawn(move || {
+                    for _ in 0..50 {
+                        let mut writer = ChunkedWriter::new(3000);
+                        block_on(copy_with_pool(Cursor::new(&data[..]), &mut writer, &pool)).unwrap();
+
+                        assert!(writer.data == *data);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // No more buffers than copies running at the same time, as all of them are kept.
+        assert!(pool.allocations() >= 1);
+        assert!(pool.allocations() <= THREADS as u64, "{:?}", pool);
+        assert!(pool.idle() <= THREADS);
+        assert_eq!(pool.idle() as u64, pool.allocations());
+    }
+}
@@ -0,0 +1,302 @@
+use std::io::{Error, IoSlice, IoSliceMut};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncBufRead, AsyncWrite};
+use futures::ready;
+
+
+
+/// Wraps a reader and counts the bytes read from it.
+///
+/// The count can be observed from another task through the handle returned by
+/// [`counter_handle`](Self::counter_handle).
+#[derive(Debug)]
+pub struct CountingReader<R> {
+    inner: R,
+    counter: Arc<AtomicU64>,
+}
+
+impl<R> CountingReader<R> {
+    /// Creates a new instance by wrapping the `inner` reader.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            counter: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn bytes(&self) -> u64 {
+        self.counter.load(Ordering::Relaxed)
+    }
+
+    /// Returns a handle to the counter which is shared with this reader.
+    pub fn counter_handle(&self) -> Arc<AtomicU64> {
+        self.counter.clone()
+    }
+
+    /// Get a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped reader.
+    ///
+    /// Bytes read directly from the wrapped reader are not counted.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Consumes the `CountingReader` object and returns the wrapped reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R> AsyncRead for CountingReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        let n = ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.counter.fetch_add(n as u64, Ordering::Relaxed);
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        let n = ready!(Pin::new(&mut this.inner).poll_read_vectored(cx, bufs))?;
+        this.counter.fetch_add(n as u64, Ordering::Relaxed);
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<R> AsyncBufRead for CountingReader<R>
+where
+    R: AsyncBufRead + Unpin,
+{
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<&[u8], Error>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = Pin::into_inner(self);
+
+        // Buffered data only counts as read once it has been consumed.
+        this.counter.fetch_add(amt as u64, Ordering::Relaxed);
+
+        Pin::new(&mut this.inner).consume(amt)
+    }
+}
+
+
+
+/// Wraps a writer and counts the bytes written to it.
+///
+/// Only the bytes accepted by the wrapped writer are counted. The count can be observed from
+/// another task through the handle returned by [`counter_handle`](Self::counter_handle).
+#[derive(Debug)]
+pub struct CountingWriter<W> {
+    inner: W,
+    counter: Arc<AtomicU64>,
+}
+
+impl<W> CountingWriter<W> {
+    /// Creates a new instance by wrapping the `inner` writer.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            counter: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn bytes(&self) -> u64 {
+        self.counter.load(Ordering::Relaxed)
+    }
+
+    /// Returns a handle to the counter which is shared with this writer.
+    pub fn counter_handle(&self) -> Arc<AtomicU64> {
+        self.counter.clone()
+    }
+
+    /// Get a reference to the wrapped writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Bytes written directly to the wrapped writer are not counted.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Consumes the `CountingWriter` object and returns the wrapped writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W> AsyncWrite for CountingWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        let n = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+        this.counter.fetch_add(n as u64, Ordering::Relaxed);
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        let n = ready!(Pin::new(&mut this.inner).poll_write_vectored(cx, bufs))?;
+        this.counter.fetch_add(n as u64, Ordering::Relaxed);
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_close(cx)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::ErrorKind;
+
+    use futures::executor::block_on;
+    use futures::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Cursor};
+
+    use crate::io::{ScriptAction, ScriptedIo};
+
+    #[test]
+    fn reads_are_counted() {
+        let mut reader = CountingReader::new(Cursor::new(b"hello world".to_vec()));
+        let handle = reader.counter_handle();
+
+        let mut buf = [0u8; 5];
+        block_on(reader.read_exact(&mut buf)).unwrap();
+        assert_eq!((reader.bytes(), handle.load(Ordering::Relaxed)), (5, 5));
+
+        let mut rest = Vec::new();
+        block_on(reader.read_to_end(&mut rest)).unwrap();
+        assert_eq!(reader.bytes(), 11);
+
+        // Reading past the wrapper is not counted.
+        block_on(reader.get_mut().read(&mut buf)).unwrap();
+        assert_eq!(handle.load(Ordering::Relaxed), 11);
+    }
+
+    #[test]
+    fn vectored_reads_are_counted() {
+        let mut reader = CountingReader::new(Cursor::new(b"hello world".to_vec()));
+
+        let (mut head, mut tail) = ([0u8; 4], [0u8; 4]);
+        let n = block_on(reader.read_vectored(&mut [IoSliceMut::new(&mut head), IoSliceMut::new(&mut tail)])).unwrap();
+
+        assert_eq!(n, 8);
+        assert_eq!(reader.bytes(), 8);
+    }
+
+    #[test]
+    fn buffered_reads_count_once_consumed() {
+        let mut reader = CountingReader::new(BufReader::new(Cursor::new(b"first\nsecond\n".to_vec())));
+
+        assert_eq!(block_on(reader.fill_buf()).unwrap().len(), 13);
+        assert_eq!(reader.bytes(), 0);
+
+        let mut line = String::new();
+        block_on(reader.read_line(&mut line)).unwrap();
+        assert_eq!(reader.bytes(), 6);
+    }
+
+    #[test]
+    fn failed_reads_are_not_counted() {
+        let io = ScriptedIo::new([
+            ScriptAction::Read(b"ab".to_vec()),
+            ScriptAction::Pending,
+            ScriptAction::Err(ErrorKind::ConnectionReset),
+        ]);
+        let mut reader = CountingReader::new(io);
+        let mut buf = [0u8; 8];
+
+        assert_eq!(block_on(reader.read(&mut buf)).unwrap(), 2);
+        assert_eq!(block_on(reader.read(&mut buf)).unwrap_err().kind(), ErrorKind::ConnectionReset);
+        assert_eq!(reader.bytes(), 2);
+    }
+
+    #[test]
+    fn short_writes_count_what_was_accepted() {
+        let io = ScriptedIo::new([
+            ScriptAction::ShortWrite(3),
+            ScriptAction::Pending,
+            ScriptAction::ShortWrite(1),
+            ScriptAction::ShortWrite(100),
+            ScriptAction::Err(ErrorKind::BrokenPipe),
+        ]);
+        let mut writer = CountingWriter::new(io);
+        let handle = writer.counter_handle();
+
+        assert_eq!(block_on(writer.write(b"hello world")).unwrap(), 3);
+        assert_eq!(writer.bytes(), 3);
+
+        block_on(writer.write_all(b"lo world")).unwrap();
+        assert_eq!(handle.load(Ordering::Relaxed), 11);
+
+        assert_eq!(block_on(writer.write(b"again")).unwrap_err().kind(), ErrorKind::BrokenPipe);
+        assert_eq!(writer.bytes(), 11);
+
+        block_on(writer.flush()).unwrap();
+        block_on(writer.close()).unwrap();
+        assert_eq!(writer.get_ref().written(), b"hello world");
+        writer.into_inner().assert_done();
+    }
+
+    #[test]
+    fn vectored_writes_are_counted() {
+        let mut writer = CountingWriter::new(Vec::new());
+
+        let n = block_on(writer.write_vectored(&[IoSlice::new(b"hello"), IoSlice::new(b" world")])).unwrap();
+
+        assert_eq!(n, 11);
+        assert_eq!(writer.bytes(), 11);
+        assert_eq!(writer.get_ref(), b"hello world");
+    }
+}
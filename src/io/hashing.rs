@@ -0,0 +1,409 @@
+use std::io::{Error, IoSlice, IoSliceMut};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::ready;
+
+use digest::{Digest, FixedOutputReset, Output};
+
+
+
+/// Wraps a reader and updates a digest with the bytes read from it.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "digest")]
+/// # futures::executor::block_on(async {
+/// use sha2::Sha256;
+/// use fut_compat::io::{copy, HashingReader};
+///
+/// # let file = futures::io::Cursor::new(b"abc".to_vec());
+/// # let mut writer = futures::io::sink();
+/// let mut reader = HashingReader::<_, Sha256>::new(file);
+/// copy(&mut reader, &mut writer).await?;
+/// let hash = reader.finalize();
+///
+/// assert_eq!(
+///     format!("{:x}", hash),
+///     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+/// );
+/// # std::io::Result::Ok(())
+/// # }).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct HashingReader<R, D> {
+    inner: R,
+    digest: D,
+}
+
+impl<R, D: Digest> HashingReader<R, D> {
+    /// Creates a new instance by wrapping the `inner` reader, starting with a fresh digest.
+    pub fn new(inner: R) -> Self {
+        Self::with_digest(inner, D::new())
+    }
+
+    /// Creates a new instance by wrapping the `inner` reader, continuing the given digest.
+    pub fn with_digest(inner: R, digest: D) -> Self {
+        Self {
+            inner,
+            digest,
+        }
+    }
+
+    /// Consumes the `HashingReader` object and returns the digest of the bytes read.
+    pub fn finalize(self) -> Output<D> {
+        self.digest.finalize()
+    }
+
+    /// Returns the digest of the bytes read so far and resets it.
+    pub fn finalize_reset(&mut self) -> Output<D>
+    where
+        D: FixedOutputReset,
+    {
+        self.digest.finalize_reset()
+    }
+
+    /// Get a reference to the digest.
+    pub fn digest(&self) -> &D {
+        &self.digest
+    }
+
+    /// Get a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped reader.
+    ///
+    /// Bytes read directly from the wrapped reader are not hashed.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Consumes the `HashingReader` object and returns the wrapped reader and the digest.
+    pub fn into_inner(self) -> (R, D) {
+        (self.inner, self.digest)
+    }
+}
+
+impl<R, D> AsyncRead for HashingReader<R, D>
+where
+    R: AsyncRead + Unpin,
+    D: Digest + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        let n = ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.digest.update(&buf[..n]);
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        let n = ready!(Pin::new(&mut this.inner).poll_read_vectored(cx, bufs))?;
+        update_vectored(&mut this.digest, bufs.iter().map(|buf| &buf[..]), n);
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+
+
+/// Wraps a writer and updates a digest with the bytes written to it.
+///
+/// Only the bytes accepted by the wrapped writer are hashed.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "digest")]
+/// # futures::executor::block_on(async {
+/// use futures::io::AsyncWriteExt;
+/// use sha2::Sha256;
+/// use fut_compat::io::HashingWriter;
+///
+/// let mut writer = HashingWriter::<_, Sha256>::new(Vec::new());
+/// writer.write_all(b"abc").await?;
+/// let (written, digest) = writer.into_inner();
+///
+/// assert_eq!(written, b"abc");
+/// assert_eq!(
+///     format!("{:x}", sha2::Digest::finalize(digest)),
+///     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+/// );
+/// # std::io::Result::Ok(())
+/// # }).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct HashingWriter<W, D> {
+    inner: W,
+    digest: D,
+}
+
+impl<W, D: Digest> HashingWriter<W, D> {
+    /// Creates a new instance by wrapping the `inner` writer, starting with a fresh digest.
+    pub fn new(inner: W) -> Self {
+        Self::with_digest(inner, D::new())
+    }
+
+    /// Creates a new instance by wrapping the `inner` writer, continuing the given digest.
+    pub fn with_digest(inner: W, digest: D) -> Self {
+        Self {
+            inner,
+            digest,
+        }
+    }
+
+    /// Consumes the `HashingWriter` object and returns the digest of the bytes written.
+    pub fn finalize(self) -> Output<D> {
+        self.digest.finalize()
+    }
+
+    /// Returns the digest of the bytes written so far and resets it.
+    pub fn finalize_reset(&mut self) -> Output<D>
+    where
+        D: FixedOutputReset,
+    {
+        self.digest.finalize_reset()
+    }
+
+    /// Get a reference to the digest.
+    pub fn digest(&self) -> &D {
+        &self.digest
+    }
+
+    /// Get a reference to the wrapped writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Bytes written directly to the wrapped writer are not hashed.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Consumes the `HashingWriter` object and returns the wrapped writer and the digest.
+    pub fn into_inner(self) -> (W, D) {
+        (self.inner, self.digest)
+    }
+}
+
+impl<W, D> AsyncWrite for HashingWriter<W, D>
+where
+    W: AsyncWrite + Unpin,
+    D: Digest + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        let n = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+        this.digest.update(&buf[..n]);
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        let n = ready!(Pin::new(&mut this.inner).poll_write_vectored(cx, bufs))?;
+        update_vectored(&mut this.digest, bufs.iter().map(|buf| &buf[..]), n);
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_close(cx)
+    }
+}
+
+/// Updates `digest` with the first `n` bytes of `bufs`.
+fn update_vectored<'a, D: Digest>(digest: &mut D, bufs: impl Iterator<Item = &'a [u8]>, mut n: usize) {
+    for buf in bufs {
+        if n == 0 {
+            break;
+        }
+
+        let len = buf.len().min(n);
+        digest.update(&buf[..len]);
+        n -= len;
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::io::{AsyncReadExt, AsyncWriteExt, Cursor};
+    use sha2::Sha256;
+
+    /// The test vectors of FIPS 180-2 for SHA-256, plus the empty input.
+    const VECTORS: [(&[u8], &str); 3] = [
+        (b"", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+        (b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+        (
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        ),
+    ];
+
+    /// Reads at most `chunk` bytes at a time, so a read never fills the whole buffer.
+    struct ShortReader {
+        inner: Cursor<Vec<u8>>,
+        chunk: usize,
+    }
+
+    impl AsyncRead for ShortReader {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<Result<usize, Error>> {
+            let this = Pin::into_inner(self);
+            let len = buf.len().min(this.chunk);
+
+            Pin::new(&mut this.inner).poll_read(cx, &mut buf[..len])
+        }
+    }
+
+    /// Accepts at most `chunk` bytes per write.
+    struct ShortWriter {
+        written: Vec<u8>,
+        chunk: usize,
+    }
+
+    impl AsyncWrite for ShortWriter {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize, Error>> {
+            let this = Pin::into_inner(self);
+            let len = buf.len().min(this.chunk);
+            this.written.extend_from_slice(&buf[..len]);
+
+            Poll::Ready(Ok(len))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn abc_through_copy() {
+        let mut reader = HashingReader::<_, Sha256>::new(Cursor::new(b"abc".to_vec()));
+        let mut writer = HashingWriter::<_, Sha256>::new(Vec::new());
+
+        let copied = block_on(crate::io::copy(&mut reader, &mut writer)).unwrap();
+        assert_eq!(copied, 3);
+
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(format!("{:x}", reader.finalize()), expected);
+        assert_eq!(format!("{:x}", writer.finalize()), expected);
+    }
+
+    #[test]
+    fn known_vectors_in_short_reads_and_writes() {
+        for chunk in [1, 3, 64] {
+            for (input, expected) in VECTORS {
+                let mut reader = HashingReader::<_, Sha256>::new(ShortReader {
+                    inner: Cursor::new(input.to_vec()),
+                    chunk,
+                });
+                let mut read = Vec::new();
+                block_on(reader.read_to_end(&mut read)).unwrap();
+                assert_eq!(read, input);
+                assert_eq!(format!("{:x}", reader.finalize()), expected);
+
+                // A short write only hashes what the wrapped writer accepted.
+                let mut writer = HashingWriter::<_, Sha256>::new(ShortWriter {
+                    written: Vec::new(),
+                    chunk,
+                });
+                block_on(writer.write_all(input)).unwrap();
+                let (inner, digest) = writer.into_inner();
+                assert_eq!(inner.written, input);
+                assert_eq!(format!("{:x}", digest.finalize()), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn vectored_writes_hash_the_accepted_prefix() {
+        let mut writer = HashingWriter::<_, Sha256>::new(ShortWriter {
+            written: Vec::new(),
+            chunk: 2,
+        });
+
+        // The default vectored write only passes on the first buffer, of which 2 bytes fit.
+        let bufs = [IoSlice::new(b"ab"), IoSlice::new(b"c")];
+        let n = block_on(writer.write_vectored(&bufs)).unwrap();
+        assert_eq!(n, 2);
+        block_on(writer.write_all(b"c")).unwrap();
+
+        assert_eq!(format!("{:x}", writer.finalize()), VECTORS[1].1);
+    }
+
+    #[test]
+    fn vectored_reads_hash_the_filled_prefix() {
+        let mut reader = HashingReader::<_, Sha256>::new(Cursor::new(b"abc".to_vec()));
+
+        let (mut first, mut second) = ([0u8; 2], [0u8; 4]);
+        let mut bufs = [IoSliceMut::new(&mut first), IoSliceMut::new(&mut second)];
+        let n = block_on(reader.read_vectored(&mut bufs)).unwrap();
+        assert_eq!(n, 3);
+        assert_eq!((&first, &second[..1]), (b"ab", &b"c"[..]));
+
+        assert_eq!(format!("{:x}", reader.finalize()), VECTORS[1].1);
+    }
+
+    #[test]
+    fn finalize_reset_starts_over() {
+        let mut writer = HashingWriter::<_, Sha256>::new(Vec::new());
+
+        block_on(writer.write_all(b"abc")).unwrap();
+        assert_eq!(format!("{:x}", writer.finalize_reset()), VECTORS[1].1);
+        assert_eq!(format!("{:x}", writer.finalize_reset()), VECTORS[0].1);
+
+        // Writing past the wrapper is not hashed.
+        block_on(writer.get_mut().write_all(b"xyz")).unwrap();
+        block_on(writer.write_all(b"abc")).unwrap();
+        block_on(writer.close()).unwrap();
+        assert_eq!(format!("{:x}", writer.finalize_reset()), VECTORS[1].1);
+        assert_eq!(writer.get_ref(), b"abcxyzabc");
+    }
+}
@@ -0,0 +1,365 @@
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::AsyncBufRead;
+use futures::stream::Stream;
+use futures::future::poll_fn;
+use futures::ready;
+
+
+
+/// The error wrapped by the [`InvalidData`](ErrorKind::InvalidData) error returned by
+/// [`read_line_limited`] and [`LinesLimited`] when a line exceeds the limit.
+///
+/// It can be retrieved with [`std::io::Error::get_ref`] and a downcast.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LineTooLong {
+    max: usize,
+}
+
+impl LineTooLong {
+    /// Returns the limit which has been exceeded.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+}
+
+impl fmt::Display for LineTooLong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line exceeds the limit of {} bytes", self.max)
+    }
+}
+
+impl std::error::Error for LineTooLong {}
+
+fn invalid_utf8() -> Error {
+    Error::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8")
+}
+
+fn is_line_too_long(err: &Error) -> bool {
+    err.get_ref().is_some_and(|err| err.is::<LineTooLong>())
+}
+
+/// Reads bytes into `line` until a newline has been read or `reader` reached EOF.
+///
+/// If `line` would exceed `max` bytes, exactly as many bytes as still fit get consumed and
+/// discarded, leaving the remainder of the line in `reader`.
+fn poll_read_line_limited<R>(
+    mut reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    line: &mut Vec<u8>,
+    max: usize,
+) -> Poll<std::io::Result<usize>>
+where
+    R: AsyncBufRead + ?Sized,
+{
+    loop {
+        let available = ready!(reader.as_mut().poll_fill_buf(cx))?;
+        if available.is_empty() {
+            return Poll::Ready(Ok(line.len()));
+        }
+
+        let (used, done) = match available.iter().position(|b| *b == b'\n') {
+            Some(pos) => (pos + 1, true),
+            None => (available.len(), false),
+        };
+
+        if line.len() + used > max {
+            let discard = max - line.len();
+
+            reader.as_mut().consume(discard);
+            line.clear();
+
+            return Poll::Ready(Err(Error::new(ErrorKind::InvalidData, LineTooLong { max })));
+        }
+
+        line.extend_from_slice(&available[..used]);
+        reader.as_mut().consume(used);
+
+        if done {
+            return Poll::Ready(Ok(line.len()));
+        }
+    }
+}
+
+/// Like [`AsyncBufReadExt::read_line`](futures::io::AsyncBufReadExt::read_line), but fails once
+/// the line exceeds `max` bytes, including the newline.
+///
+/// The line gets appended to `buf` including the newline, and its length gets returned. A return
+/// value of `0` means that `reader` reached EOF.
+///
+/// If the line exceeds `max` bytes, an [`InvalidData`](ErrorKind::InvalidData) error wrapping a
+/// [`LineTooLong`] is returned and `buf` is left unchanged. The first `max` bytes of the line
+/// have been consumed at that point, while the remainder is left in `reader`. Use
+/// [`LinesLimited`] to skip it automatically.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::io::read_line_limited;
+///
+/// let mut reader = futures::io::Cursor::new(b"GET / HTTP/1.1\r\n".to_vec());
+///
+/// let mut line = String::new();
+/// read_line_limited(&mut reader, &mut line, 8 * 1024).await?;
+///
+/// assert_eq!(line, "GET / HTTP/1.1\r\n");
+/// # Ok(())
+/// # }
+/// ```
+pub async fn read_line_limited<R>(reader: &mut R, buf: &mut String, max: usize) -> std::io::Result<usize>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    let mut line = Vec::new();
+
+    let n = poll_fn(|cx| poll_read_line_limited(Pin::new(&mut *reader), cx, &mut line, max)).await?;
+
+    let line = String::from_utf8(line).map_err(|_| invalid_utf8())?;
+    buf.push_str(&line);
+
+    Ok(n)
+}
+
+
+
+/// A stream over the lines of a reader, failing for lines exceeding a limit.
+///
+/// Like [`AsyncBufReadExt::lines`](futures::io::AsyncBufReadExt::lines), the yielded lines do not
+/// include the newline, nor a carriage return preceding it.
+///
+/// A line exceeding `max` bytes, including the newline, yields an
+/// [`InvalidData`](ErrorKind::InvalidData) error wrapping a [`LineTooLong`]. At most `max` bytes
+/// of it are ever buffered. The stream then resyncs by discarding the remainder of the line, so
+/// the following item is the next line.
+#[derive(Debug)]
+pub struct LinesLimited<R> {
+    reader: R,
+    line: Vec<u8>,
+    max: usize,
+    /// Set while the remainder of an oversized line gets discarded.
+    skipping: bool,
+}
+
+impl<R> LinesLimited<R>
+where
+    R: AsyncBufRead + Unpin,
+{
+    /// Creates a new stream over the lines of `reader`, allowing lines of up to `max` bytes.
+    pub fn new(reader: R, max: usize) -> Self {
+        Self {
+            reader,
+            line: Vec::new(),
+            max,
+            skipping: false,
+        }
+    }
+}
+
+impl<R> LinesLimited<R> {
+    /// Get a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the wrapped reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Consumes the `LinesLimited` object and returns the wrapped reader.
+    ///
+    /// A partially read line is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R> LinesLimited<R>
+where
+    R: AsyncBufRead + Unpin,
+{
+    /// Discards the remainder of an oversized line, up to and including the newline.
+    fn poll_skip(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        while self.skipping {
+            let available = ready!(Pin::new(&mut self.reader).poll_fill_buf(cx))?;
+            if available.is_empty() {
+                self.skipping = false;
+                break;
+            }
+
+            let used = match available.iter().position(|b| *b == b'\n') {
+                Some(pos) => {
+                    self.skipping = false;
+
+                    pos + 1
+                }
+                None => available.len(),
+            };
+
+            Pin::new(&mut self.reader).consume(used);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R> Stream for LinesLimited<R>
+where
+    R: AsyncBufRead + Unpin,
+{
+    type Item = std::io::Result<String>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = Pin::into_inner(self);
+
+        if let Err(err) = ready!(this.poll_skip(cx)) {
+            return Poll::Ready(Some(Err(err)));
+        }
+
+        let n = match ready!(poll_read_line_limited(Pin::new(&mut this.reader), cx, &mut this.line, this.max)) {
+            Ok(n) => n,
+            Err(err) => {
+                this.skipping = is_line_too_long(&err);
+
+                return Poll::Ready(Some(Err(err)));
+            }
+        };
+
+        if n == 0 {
+            return Poll::Ready(None);
+        }
+
+        let mut line = std::mem::take(&mut this.line);
+
+        if line.ends_with(b"\n") {
+            line.pop();
+
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+
+        Poll::Ready(Some(String::from_utf8(line).map_err(|_| invalid_utf8())))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::io::BufReader;
+    use futures::stream::StreamExt;
+
+    use crate::io::{ScriptAction, ScriptedIo};
+
+    fn reader<const N: usize>(chunks: [&str; N]) -> BufReader<ScriptedIo> {
+        let mut script: Vec<_> = chunks.iter()
+            .map(|chunk| ScriptAction::Read(chunk.as_bytes().to_vec()))
+            .collect();
+        script.insert(1, ScriptAction::Pending);
+        // Each of the last calls reads EOF once.
+        script.extend([ScriptAction::Eof, ScriptAction::Eof]);
+
+        BufReader::new(ScriptedIo::new(script))
+    }
+
+    fn assert_too_long(err: &Error, max: usize) {
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(err.get_ref().and_then(|err| err.downcast_ref::<LineTooLong>()), Some(&LineTooLong { max }));
+    }
+
+    #[test]
+    fn lines_are_joined_across_chunks() {
+        let mut reader = reader(["hel", "lo\nwo", "rld\r", "\nlast"]);
+
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if block_on(read_line_limited(&mut reader, &mut line, 16)).unwrap() == 0 {
+                break;
+            }
+
+            lines.push(line);
+        }
+
+        assert_eq!(lines, ["hello\n", "world\r\n", "last"]);
+    }
+
+    #[test]
+    fn the_limit_includes_the_newline() {
+        let mut reader = reader(["1234", "567\n", "12345678\n"]);
+
+        let mut line = String::new();
+        assert_eq!(block_on(read_line_limited(&mut reader, &mut line, 8)).unwrap(), 8);
+        assert_eq!(line, "1234567\n");
+
+        let err = block_on(read_line_limited(&mut reader, &mut line, 8)).unwrap_err();
+        assert_too_long(&err, 8);
+    }
+
+    #[test]
+    fn oversized_lines_leave_the_remainder() {
+        let mut reader = reader(["0123", "456789ab", "\nok\n"]);
+
+        let mut line = String::from("x");
+        let err = block_on(read_line_limited(&mut reader, &mut line, 8)).unwrap_err();
+        assert_too_long(&err, 8);
+        assert_eq!(line, "x");
+
+        // Exactly the first `max` bytes were consumed.
+        line.clear();
+        block_on(read_line_limited(&mut reader, &mut line, 8)).unwrap();
+        assert_eq!(line, "89ab\n");
+
+        line.clear();
+        block_on(read_line_limited(&mut reader, &mut line, 8)).unwrap();
+        assert_eq!(line, "ok\n");
+    }
+
+    #[test]
+    fn invalid_utf8_fails() {
+        let mut reader = BufReader::new(ScriptedIo::new([ScriptAction::Read(b"\xff\xfe\n".to_vec())]));
+
+        let mut line = String::new();
+        let err = block_on(read_line_limited(&mut reader, &mut line, 8)).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(!is_line_too_long(&err));
+        assert!(line.is_empty());
+    }
+
+    #[test]
+    fn stream_resyncs_after_an_oversized_line() {
+        let reader = reader(["short\nwaaaa", "aaaay too", " long\r\nnext\r", "\n"]);
+        let mut lines = LinesLimited::new(reader, 8);
+
+        assert_eq!(block_on(lines.next()).unwrap().unwrap(), "short");
+        assert_too_long(&block_on(lines.next()).unwrap().unwrap_err(), 8);
+        assert_eq!(block_on(lines.next()).unwrap().unwrap(), "next");
+        assert!(block_on(lines.next()).is_none());
+    }
+
+    #[test]
+    fn stream_ends_within_an_oversized_line() {
+        let reader = reader(["ok\n0123456", "789"]);
+        let mut lines = LinesLimited::new(reader, 8);
+
+        assert_eq!(block_on(lines.next()).unwrap().unwrap(), "ok");
+        assert_too_long(&block_on(lines.next()).unwrap().unwrap_err(), 8);
+        assert!(block_on(lines.next()).is_none());
+    }
+
+    #[test]
+    fn stream_yields_the_last_line_without_newline() {
+        let lines = LinesLimited::new(reader(["a\r\n", "\n", "b"]), 8);
+
+        let lines: Vec<_> = block_on(lines.map(Result::unwrap).collect());
+        assert_eq!(lines, ["a", "", "b"]);
+    }
+}
@@ -24,6 +24,102 @@ pub use futures::io::{
 
 
 
+/// Performing sync io on the blocking thread pool.
+mod blocking;
+pub use self::blocking::*;
+
+/// Performing async io from sync code.
+mod sync_bridge;
+pub use self::sync_bridge::*;
+
+/// Buffering of both directions of a stream.
+mod buf_stream;
+pub use self::buf_stream::*;
+
+/// Conversions between readers and streams of byte chunks.
+mod stream;
+pub use self::stream::*;
+
+/// Counting the bytes passing through readers and writers.
+mod counting;
+pub use self::counting::*;
+
+/// Helpers for vectored reads and writes.
+mod vectored;
+pub use self::vectored::*;
+
+/// Reading lines of limited length.
+mod lines;
+pub use self::lines::*;
+
+/// Reading several readers back-to-back.
+mod concat;
+pub use self::concat::*;
+
+/// Broadcasting writes to several sinks.
+mod multi_writer;
+pub use self::multi_writer::*;
+
+/// Mirroring the bytes read from a reader into a writer.
+mod tee;
+pub use self::tee::*;
+
+/// Buffered writing with time-based automatic flushing.
+mod auto_flush;
+pub use self::auto_flush::*;
+
+/// Limiting the throughput of readers and writers.
+mod throttle;
+pub use self::throttle::*;
+
+/// Copying with progress reporting or custom buffers.
+mod copy;
+pub use self::copy::*;
+
+/// Zero-copy transfers from files to sockets.
+#[cfg(all(unix, feature = "zero-copy"))]
+#[cfg_attr(docsrs, doc(cfg(all(unix, feature = "zero-copy"))))]
+mod sendfile;
+#[cfg(all(unix, feature = "zero-copy"))]
+#[cfg_attr(docsrs, doc(cfg(all(unix, feature = "zero-copy"))))]
+pub use self::sendfile::*;
+
+/// Reading and writing with the buffers of [`bytes`](https://docs.rs/bytes).
+#[cfg(feature = "bytes")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+mod buf_ext;
+#[cfg(feature = "bytes")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+pub use self::buf_ext::*;
+
+/// Convenience methods for readers and writers.
+mod util_ext;
+pub use self::util_ext::*;
+
+/// Hashing the bytes passing through readers and writers.
+#[cfg(feature = "digest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+mod hashing;
+#[cfg(feature = "digest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+pub use self::hashing::*;
+
+/// Emitting tracing events for every poll.
+#[cfg(feature = "tracing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+mod traced;
+#[cfg(feature = "tracing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+pub use self::traced::*;
+
+/// An io object playing back a script, for tests.
+#[cfg(feature = "test-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+mod scripted;
+#[cfg(feature = "test-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+pub use self::scripted::*;
+
 
 
 /// Contains the compatibility objects for the [`tokio`](https://docs.rs/tokio) runtime.
@@ -33,3 +129,97 @@ mod tokio;
 #[cfg(feature = "tokio-rt")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
 pub use self::tokio::*;
+
+/// Framing of streams with the codecs of [`tokio-util`](https://docs.rs/tokio-util).
+#[cfg(feature = "codec")]
+#[cfg_attr(docsrs, doc(cfg(feature = "codec")))]
+mod codec;
+#[cfg(feature = "codec")]
+#[cfg_attr(docsrs, doc(cfg(feature = "codec")))]
+pub use self::codec::*;
+
+/// Contains the compatibility objects for the [`async_std`](https://docs.rs/async-std) runtime.
+#[cfg(feature = "async-std-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
+mod async_std;
+#[cfg(feature = "async-std-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
+pub use self::async_std::*;
+
+
+
+/// An async abstraction over the standard streams of the process.
+///
+/// Both runtimes forward the data to the handles of [`std::io`] on a blocking thread. As a result
+/// the output written to [`Stdout`](Stdio::Stdout) is line buffered on both runtimes, while the
+/// output written to [`Stderr`](Stdio::Stderr) is unbuffered. Flush the handle to make sure
+/// a partial line becomes visible.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::io::{AsyncWriteExt, Stdio, TokioStdio};
+///
+/// let mut stdout = TokioStdio::stdout();
+///
+/// stdout.write_all(b"Hello world!\n").await?;
+/// stdout.flush().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Stdio {
+    /// The handle to the standard input of the process.
+    type Stdin: AsyncRead + Send + Unpin;
+    /// The handle to the standard output of the process.
+    type Stdout: AsyncWrite + Send + Unpin;
+    /// The handle to the standard error of the process.
+    type Stderr: AsyncWrite + Send + Unpin;
+
+    /// Returns a handle to the standard input of the process.
+    fn stdin() -> Self::Stdin;
+
+    /// Returns a handle to the standard output of the process.
+    fn stdout() -> Self::Stdout;
+
+    /// Returns a handle to the standard error of the process.
+    fn stderr() -> Self::Stderr;
+}
+
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    /// Writes a line to stdout and stderr each, which ends up next to the output of the test
+    /// harness, and a partial line which only shows up because of the flush.
+    async fn write_to_stdout_and_stderr<S: Stdio>(runtime: &str) {
+        let mut stdout = S::stdout();
+        stdout.write_all(format!("{}: a line written to stdout\n", runtime).as_bytes()).await.unwrap();
+        stdout.write_all(b"and a partial one, ").await.unwrap();
+        stdout.flush().await.unwrap();
+
+        let mut stderr = S::stderr();
+        stderr.write_all(format!("{}: a line written to stderr\n", runtime).as_bytes()).await.unwrap();
+        stderr.flush().await.unwrap();
+
+        // Creating the handle to stdin does not read from it.
+        let _stdin = S::stdin();
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_write_to_stdout_and_stderr() {
+        ::tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(write_to_stdout_and_stderr::<TokioStdio>("tokio"));
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_write_to_stdout_and_stderr() {
+        ::async_std::task::block_on(write_to_stdout_and_stderr::<AsyncStdStdio>("async-std"));
+    }
+}
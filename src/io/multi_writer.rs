@@ -0,0 +1,398 @@
+use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::AsyncWrite;
+
+
+
+/// Decides what a [`MultiWriter`] does when one of its sinks fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum SinkErrorPolicy {
+    /// Return the error of the sink right away. The sink stays part of the writer, but the bytes
+    /// accepted by the other sinks during the failed write are not tracked anymore.
+    #[default]
+    FailFast,
+    /// Remove the failed sink and continue with the remaining ones. The error only gets returned
+    /// once no sink is left, and the following writes fail with
+    /// [`BrokenPipe`](ErrorKind::BrokenPipe).
+    DropSink,
+}
+
+#[derive(Debug)]
+struct Sink<W> {
+    writer: W,
+    /// The number of bytes of the write in progress which the sink has accepted.
+    written: usize,
+}
+
+/// A writer broadcasting everything written to it to several sinks.
+///
+/// A write only completes once every sink has accepted all the bytes. The progress of each sink is
+/// tracked across calls returning `Pending`, so a sink which is ahead of the others never receives
+/// the same bytes twice. This requires the following call to pass a buffer starting with the same
+/// bytes, like [`write_all`](futures::io::AsyncWriteExt::write_all) and [`copy`](super::copy) do.
+///
+/// Writing to an instance without any sinks fails with [`BrokenPipe`](ErrorKind::BrokenPipe)
+/// instead of discarding the bytes, while flushing and closing it succeed.
+///
+/// Sinks of different types can be combined by boxing them, e.g. as
+/// `Box<dyn AsyncWrite + Send + Unpin>`.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::io::{AsyncWrite, AsyncWriteExt, MultiWriter, SinkErrorPolicy, TokioCompat};
+///
+/// let socket = TokioCompat::new(tokio::net::TcpStream::connect("127.0.0.1:8080").await?);
+/// let cache = TokioCompat::new(tokio::fs::File::create("cache/index.html").await?);
+///
+/// let sinks: Vec<Box<dyn AsyncWrite + Send + Unpin>> = vec![Box::new(socket), Box::new(cache)];
+/// let mut writer = MultiWriter::new(sinks).error_policy(SinkErrorPolicy::DropSink);
+///
+/// writer.write_all(b"<html></html>").await?;
+/// writer.close().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MultiWriter<W> {
+    sinks: Vec<Sink<W>>,
+    policy: SinkErrorPolicy,
+}
+
+impl<W> MultiWriter<W> {
+    /// Creates a new instance broadcasting to `sinks`.
+    pub fn new(sinks: Vec<W>) -> Self {
+        Self {
+            sinks: sinks.into_iter().map(|writer| Sink { writer, written: 0 }).collect(),
+            policy: SinkErrorPolicy::default(),
+        }
+    }
+
+    /// Sets what happens when one of the sinks fails. Defaults to
+    /// [`FailFast`](SinkErrorPolicy::FailFast).
+    pub fn error_policy(mut self, policy: SinkErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Adds another sink, which receives the bytes of the following writes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a write is in progress.
+    pub fn push(&mut self, sink: W) {
+        assert!(
+            self.sinks.iter().all(|sink| sink.written == 0),
+            "MultiWriter::push called while a write is in progress",
+        );
+
+        self.sinks.push(Sink { writer: sink, written: 0 });
+    }
+
+    /// Returns the number of sinks.
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    /// Returns `true` if there are no sinks, e.g. because all of them have been dropped.
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Returns an iterator over references to the sinks.
+    pub fn sinks(&self) -> impl Iterator<Item = &W> {
+        self.sinks.iter().map(|sink| &sink.writer)
+    }
+
+    /// Returns an iterator over mutable references to the sinks.
+    pub fn sinks_mut(&mut self) -> impl Iterator<Item = &mut W> {
+        self.sinks.iter_mut().map(|sink| &mut sink.writer)
+    }
+
+    /// Consumes the `MultiWriter` object and returns the sinks.
+    pub fn into_inner(self) -> Vec<W> {
+        self.sinks.into_iter().map(|sink| sink.writer).collect()
+    }
+}
+
+impl<W> MultiWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    /// Handles the error of the sink at `index` according to the policy.
+    ///
+    /// Returns the error if it has to be returned to the caller.
+    fn fail(&mut self, index: usize, err: Error) -> Option<Error> {
+        match self.policy {
+            SinkErrorPolicy::FailFast => {
+                self.reset();
+
+                Some(err)
+            }
+            SinkErrorPolicy::DropSink => {
+                self.sinks.remove(index);
+
+                if self.sinks.is_empty() {
+                    Some(err)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Forgets about the write in progress.
+    fn reset(&mut self) {
+        for sink in &mut self.sinks {
+            sink.written = 0;
+        }
+    }
+
+    /// Calls `poll` for every sink, until all of them are ready.
+    fn poll_all(
+        &mut self,
+        cx: &mut Context<'_>,
+        mut poll: impl FnMut(Pin<&mut W>, &mut Context<'_>) -> Poll<std::io::Result<()>>,
+    ) -> Poll<std::io::Result<()>> {
+        let mut pending = false;
+        let mut index = 0;
+
+        while index < self.sinks.len() {
+            match poll(Pin::new(&mut self.sinks[index].writer), cx) {
+                Poll::Pending => pending = true,
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(err)) => match self.fail(index, err) {
+                    Some(err) => return Poll::Ready(Err(err)),
+                    // The sink got removed, so the next one moved to `index`.
+                    None => continue,
+                },
+            }
+
+            index += 1;
+        }
+
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+}
+
+impl<W> AsyncWrite for MultiWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        if this.sinks.is_empty() {
+            return Poll::Ready(Err(Error::new(ErrorKind::BrokenPipe, "MultiWriter has no sinks left")));
+        }
+        if this.sinks.iter().any(|sink| sink.written > buf.len()) {
+            return Poll::Ready(Err(Error::new(
+                ErrorKind::InvalidInput,
+                "buffer got shorter while a write of MultiWriter was in progress",
+            )));
+        }
+
+        let mut pending = false;
+        let mut index = 0;
+
+        while index < this.sinks.len() {
+            let sink = &mut this.sinks[index];
+
+            let result = loop {
+                if sink.written == buf.len() {
+                    break Ok(());
+                }
+
+                match Pin::new(&mut sink.writer).poll_write(cx, &buf[sink.written..]) {
+                    Poll::Pending => {
+                        pending = true;
+
+                        break Ok(());
+                    }
+                    Poll::Ready(Ok(0)) => break Err(Error::new(ErrorKind::WriteZero, "write zero bytes into sink")),
+                    Poll::Ready(Ok(n)) => sink.written += n,
+                    Poll::Ready(Err(err)) => break Err(err),
+                }
+            };
+
+            if let Err(err) = result {
+                match this.fail(index, err) {
+                    Some(err) => return Poll::Ready(Err(err)),
+                    // The sink got removed, so the next one moved to `index`.
+                    None => continue,
+                }
+            }
+
+            index += 1;
+        }
+
+        if pending {
+            return Poll::Pending;
+        }
+
+        this.reset();
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::into_inner(self).poll_all(cx, |writer, cx| writer.poll_flush(cx))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::into_inner(self).poll_all(cx, |writer, cx| writer.poll_close(cx))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::io::AsyncWriteExt;
+    use futures::task::noop_waker_ref;
+
+    use crate::io::{ScriptAction, ScriptedIo};
+
+    #[test]
+    fn sinks_progress_independently() {
+        let fast = ScriptedIo::new([
+            ScriptAction::ShortWrite(2),
+            ScriptAction::Pending,
+            ScriptAction::ShortWrite(10),
+            ScriptAction::ShortWrite(10),
+        ]);
+        let slow = ScriptedIo::new([
+            ScriptAction::ShortWrite(1),
+            ScriptAction::ShortWrite(1),
+            ScriptAction::ShortWrite(1),
+            ScriptAction::Pending,
+            ScriptAction::ShortWrite(1),
+            ScriptAction::Pending,
+            ScriptAction::ShortWrite(1),
+            ScriptAction::ShortWrite(10),
+        ]);
+        let mut writer = MultiWriter::new(vec![fast, slow]);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        assert!(Pin::new(&mut writer).poll_write(&mut cx, b"hello").is_pending());
+        assert!(Pin::new(&mut writer).poll_write(&mut cx, b"hello").is_pending());
+        // The fast sink is done, so only the slow one gets polled.
+        assert!(matches!(Pin::new(&mut writer).poll_write(&mut cx, b"hello"), Poll::Ready(Ok(5))));
+
+        // The progress got reset, so the next write starts from scratch.
+        assert!(matches!(Pin::new(&mut writer).poll_write(&mut cx, b"!"), Poll::Ready(Ok(1))));
+
+        for sink in writer.sinks() {
+            assert_eq!(sink.written(), b"hello!");
+            sink.assert_done();
+        }
+    }
+
+    #[test]
+    fn shorter_buffers_are_rejected() {
+        let sink = ScriptedIo::new([ScriptAction::ShortWrite(3), ScriptAction::Pending]);
+        let mut writer = MultiWriter::new(vec![sink]);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert!(Pin::new(&mut writer).poll_write(&mut cx, b"hello").is_pending());
+
+        match Pin::new(&mut writer).poll_write(&mut cx, b"he") {
+            Poll::Ready(Err(err)) => assert_eq!(err.kind(), ErrorKind::InvalidInput),
+            poll => panic!("unexpected {poll:?}"),
+        }
+    }
+
+    #[test]
+    fn fail_fast_returns_the_first_error() {
+        let failing = ScriptedIo::new([ScriptAction::Err(ErrorKind::BrokenPipe)]);
+        let healthy = ScriptedIo::new([]);
+        let mut writer = MultiWriter::new(vec![failing, healthy]);
+
+        let err = block_on(writer.write_all(b"hello")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+        assert_eq!(writer.len(), 2);
+    }
+
+    #[test]
+    fn drop_sink_continues_with_the_others() {
+        let failing = ScriptedIo::new([ScriptAction::ShortWrite(2), ScriptAction::Err(ErrorKind::BrokenPipe)]);
+        let healthy = ScriptedIo::new([ScriptAction::ShortWrite(10), ScriptAction::ShortWrite(2)]);
+        let mut writer = MultiWriter::new(vec![failing, healthy]).error_policy(SinkErrorPolicy::DropSink);
+
+        block_on(writer.write_all(b"hello")).unwrap();
+        assert_eq!(writer.len(), 1);
+
+        block_on(writer.write_all(b"!!")).unwrap();
+        let sinks = writer.into_inner();
+        assert_eq!(sinks[0].written(), b"hello!!");
+        sinks[0].assert_done();
+    }
+
+    #[test]
+    fn drop_sink_fails_once_every_sink_is_gone() {
+        let first = ScriptedIo::new([ScriptAction::Err(ErrorKind::BrokenPipe)]);
+        let second = ScriptedIo::new([ScriptAction::ShortWrite(5), ScriptAction::Err(ErrorKind::ConnectionReset)]);
+        let mut writer = MultiWriter::new(vec![first, second]).error_policy(SinkErrorPolicy::DropSink);
+
+        block_on(writer.write_all(b"hello")).unwrap();
+
+        // The error of the last sink gets returned.
+        let err = block_on(writer.write_all(b"hello")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+        assert!(writer.is_empty());
+
+        // The bytes of the following writes are not discarded silently.
+        let err = block_on(writer.write_all(b"hello")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+
+        block_on(writer.flush()).unwrap();
+        block_on(writer.close()).unwrap();
+    }
+
+    #[test]
+    fn flush_and_close_reach_every_sink() {
+        let first = ScriptedIo::new([ScriptAction::Pending, ScriptAction::Pending]);
+        let second = ScriptedIo::new([ScriptAction::Pending, ScriptAction::Err(ErrorKind::BrokenPipe)]);
+        let mut writer = MultiWriter::new(vec![first, second]).error_policy(SinkErrorPolicy::DropSink);
+
+        block_on(writer.flush()).unwrap();
+
+        // The failing sink gets dropped while the other one gets closed.
+        block_on(writer.close()).unwrap();
+        assert_eq!(writer.len(), 1);
+
+        for sink in writer.sinks() {
+            sink.assert_done();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "write is in progress")]
+    fn push_during_a_write_panics() {
+        let sink = ScriptedIo::new([ScriptAction::ShortWrite(1), ScriptAction::Pending]);
+        let mut writer = MultiWriter::new(vec![sink]);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert!(Pin::new(&mut writer).poll_write(&mut cx, b"hello").is_pending());
+
+        writer.push(ScriptedIo::new([]));
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite, AsyncSeek};
+
+
+
+/// An action of the script played back by [`ScriptedIo`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptAction {
+    /// A read returning these bytes. If the buffer of the read is too small, the remaining bytes
+    /// are returned by the following reads.
+    Read(Vec<u8>),
+    /// A poll of any method returning [`Poll::Pending`]. The waker gets woken right away, so the
+    /// object gets polled again.
+    Pending,
+    /// A poll of any method failing with an error of this kind.
+    Err(ErrorKind),
+    /// A write accepting at most this many bytes.
+    ShortWrite(usize),
+    /// A read returning `0`, i.e. the end of the stream.
+    Eof,
+    /// A seek completing at this position.
+    Seek(u64),
+}
+
+/// An io object whose behavior is programmed as a sequence of [`ScriptAction`]s, for testing
+/// code which has to cope with partial reads and writes, wake-ups and errors, e.g. adapters like
+/// [`TokioCompat`](crate::io::TokioCompat).
+///
+/// Every poll consumes the next action of the script:
+///
+/// - Reads consume [`Read`](ScriptAction::Read) and [`Eof`](ScriptAction::Eof), writes consume
+///   [`ShortWrite`](ScriptAction::ShortWrite) and seeks consume [`Seek`](ScriptAction::Seek).
+/// - [`Pending`](ScriptAction::Pending) and [`Err`](ScriptAction::Err) get consumed by whichever
+///   method gets polled next.
+/// - Flushing and closing succeed without consuming anything, unless the next action is
+///   `Pending` or `Err`.
+///
+/// Seeks to [`SeekFrom::Current(0)`](SeekFrom::Current), which some adapters use for querying the
+/// position, are seeks like any other and consume an action as well.
+///
+/// Polling a method which does not match the next action, or any read, write or seek once the
+/// script is exhausted, panics, which fails the test. The bytes written and the targets of the
+/// seeks are recorded, and [`assert_done`](Self::assert_done) checks that the full script has
+/// been played back.
+///
+/// It implements the io traits of [`futures`](https://docs.rs/futures), and with the `tokio-rt`
+/// feature the ones of [`tokio`](https://docs.rs/tokio) as well, so it can be wrapped by adapters
+/// in either direction.
+///
+/// # Example
+///
+/// ```
+/// use std::io::ErrorKind;
+///
+/// use fut_compat::io::{AsyncReadExt, AsyncWriteExt, ScriptAction, ScriptedIo};
+///
+/// let mut io = ScriptedIo::new([
+///     ScriptAction::Read(b"hello".to_vec()),
+///     ScriptAction::Pending,
+///     ScriptAction::Read(b" world".to_vec()),
+///     ScriptAction::Eof,
+///     ScriptAction::ShortWrite(2),
+///     ScriptAction::ShortWrite(8),
+///     ScriptAction::Err(ErrorKind::BrokenPipe),
+/// ]);
+///
+/// futures::executor::block_on(async {
+///     let mut buf = String::new();
+///     io.read_to_string(&mut buf).await.unwrap();
+///     assert_eq!(buf, "hello world");
+///
+///     // `write_all` writes the rest after the short write.
+///     io.write_all(b"reply").await.unwrap();
+///
+///     let err = io.write_all(b"again").await.unwrap_err();
+///     assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+/// });
+///
+/// assert_eq!(io.written(), b"reply");
+/// io.assert_done();
+/// ```
+#[derive(Default, Clone, Debug)]
+pub struct ScriptedIo {
+    script: VecDeque<ScriptAction>,
+    written: Vec<u8>,
+    seeks: Vec<SeekFrom>,
+    position: u64,
+    seeking: bool,
+}
+
+impl ScriptedIo {
+    /// Creates a new instance which plays back `script`.
+    pub fn new<I: IntoIterator<Item = ScriptAction>>(script: I) -> Self {
+        Self {
+            script: script.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Appends `action` to the end of the script.
+    pub fn push(&mut self, action: ScriptAction) {
+        self.script.push_back(action);
+    }
+
+    /// Returns the actions which have not been played back yet.
+    pub fn remaining(&self) -> impl Iterator<Item = &ScriptAction> {
+        self.script.iter()
+    }
+
+    /// Returns `true` if the full script has been played back.
+    pub fn is_done(&self) -> bool {
+        self.script.is_empty()
+    }
+
+    /// Panics if the script has not been played back completely, listing the remaining actions.
+    #[track_caller]
+    pub fn assert_done(&self) {
+        assert!(self.script.is_empty(), "ScriptedIo: the script has not been played back completely, remaining actions: {:?}", self.script);
+    }
+
+    /// Returns all bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    /// Returns the targets of all seeks started so far.
+    pub fn seeks(&self) -> &[SeekFrom] {
+        &self.seeks
+    }
+
+    /// Returns the position, i.e. the number of bytes read and written, or the position of the
+    /// last seek plus the bytes transferred since.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Consumes the next action if it is [`Pending`](ScriptAction::Pending) or
+    /// [`Err`](ScriptAction::Err), returning its result.
+    fn poll_interrupt<T>(&mut self, cx: &mut Context<'_>) -> Option<Poll<std::io::Result<T>>> {
+        match self.script.front()? {
+            ScriptAction::Pending => {
+                self.script.pop_front();
+                cx.waker().wake_by_ref();
+
+                Some(Poll::Pending)
+            }
+            ScriptAction::Err(kind) => {
+                let kind = *kind;
+                self.script.pop_front();
+
+                Some(Poll::Ready(Err(Error::new(kind, "scripted error"))))
+            }
+            _ => None,
+        }
+    }
+
+    /// Panics because `op` got polled while the script expected something else.
+    #[track_caller]
+    fn unexpected(&self, op: &str) -> ! {
+        match self.script.front() {
+            Some(action) => panic!("ScriptedIo: `{op}` got polled, but the next action is {action:?}"),
+            None => panic!("ScriptedIo: `{op}` got polled, but the script is exhausted"),
+        }
+    }
+
+    fn read(&mut self, cx: &mut Context<'_>, buf: &mut [u8], op: &str) -> Poll<std::io::Result<usize>> {
+        if let Some(poll) = self.poll_interrupt(cx) {
+            return poll;
+        }
+
+        let n = match self.script.front_mut() {
+            Some(ScriptAction::Read(bytes)) => {
+                let n = bytes.len().min(buf.len());
+                buf[..n].copy_from_slice(&bytes[..n]);
+                bytes.drain(..n);
+
+                if bytes.is_empty() {
+                    self.script.pop_front();
+                }
+
+                n
+            }
+            Some(ScriptAction::Eof) => {
+                self.script.pop_front();
+
+                0
+            }
+            _ => self.unexpected(op),
+        };
+
+        self.position += n as u64;
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn write(&mut self, cx: &mut Context<'_>, buf: &[u8], op: &str) -> Poll<std::io::Result<usize>> {
+        if let Some(poll) = self.poll_interrupt(cx) {
+            return poll;
+        }
+
+        let n = match self.script.front() {
+            Some(ScriptAction::ShortWrite(max)) => {
+                let n = (*max).min(buf.len());
+                self.script.pop_front();
+
+                n
+            }
+            _ => self.unexpected(op),
+        };
+
+        self.written.extend_from_slice(&buf[..n]);
+        self.position += n as u64;
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn flush(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.poll_interrupt(cx).unwrap_or(Poll::Ready(Ok(())))
+    }
+
+    fn complete_seek(&mut self, cx: &mut Context<'_>, op: &str) -> Poll<std::io::Result<u64>> {
+        if let Some(poll) = self.poll_interrupt(cx) {
+            if poll.is_ready() {
+                self.seeking = false;
+            }
+
+            return poll;
+        }
+
+        match self.script.front() {
+            Some(ScriptAction::Seek(position)) => {
+                self.position = *position;
+                self.script.pop_front();
+                self.seeking = false;
+
+                Poll::Ready(Ok(self.position))
+            }
+            _ => self.unexpected(op),
+        }
+    }
+}
+
+impl AsyncRead for ScriptedIo {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::into_inner(self).read(cx, buf, "poll_read")
+    }
+}
+
+impl AsyncWrite for ScriptedIo {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::into_inner(self).write(cx, buf, "poll_write")
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::into_inner(self).flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::into_inner(self).flush(cx)
+    }
+}
+
+impl AsyncSeek for ScriptedIo {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<std::io::Result<u64>> {
+        let this = Pin::into_inner(self);
+
+        // A seek which returned `Pending` gets polled again with the same target, which is only
+        // recorded once.
+        if !this.seeking {
+            this.seeks.push(pos);
+            this.seeking = true;
+        }
+
+        this.complete_seek(cx, "poll_seek")
+    }
+}
+
+#[cfg(feature = "tokio-rt")]
+impl ::tokio::io::AsyncRead for ScriptedIo {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ::tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = Pin::into_inner(self);
+
+        let n = futures::ready!(this.read(cx, buf.initialize_unfilled(), "poll_read"))?;
+        buf.advance(n);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "tokio-rt")]
+impl ::tokio::io::AsyncWrite for ScriptedIo {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::into_inner(self).write(cx, buf, "poll_write")
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::into_inner(self).flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::into_inner(self).flush(cx)
+    }
+}
+
+#[cfg(feature = "tokio-rt")]
+impl ::tokio::io::AsyncSeek for ScriptedIo {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        let this = Pin::into_inner(self);
+
+        if this.seeking {
+            return Err(Error::other("ScriptedIo: a seek is already in progress"));
+        }
+
+        this.seeks.push(position);
+        this.seeking = true;
+
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        let this = Pin::into_inner(self);
+
+        // Tokio polls this before starting a seek, which returns the current position.
+        if !this.seeking {
+            return Poll::Ready(Ok(this.position));
+        }
+
+        this.complete_seek(cx, "poll_complete")
+    }
+}
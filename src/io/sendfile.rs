@@ -0,0 +1,249 @@
+use std::os::unix::io::AsRawFd;
+
+use async_trait::async_trait;
+
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+
+
+/// The maximum number of bytes transferred by one call to `sendfile(2)`.
+#[cfg(target_os = "linux")]
+const MAX_CHUNK: u64 = 0x7fff_f000;
+
+/// A socket which can be the destination of [`sendfile`].
+#[async_trait]
+pub trait SendfileSocket: AsRawFd {
+    /// Calls `op` until it stops failing with [`WouldBlock`](std::io::ErrorKind::WouldBlock),
+    /// waiting for the socket to become writable in between.
+    ///
+    /// `op` is expected to write to the file descriptor of the socket directly.
+    async fn write_with<R, F>(&self, op: F) -> std::io::Result<R>
+    where
+        F: FnMut() -> std::io::Result<R> + Send,
+        R: Send;
+}
+
+/// Copies `len` bytes, or everything up to EOF if `len` is `None`, from `file` into `socket`.
+///
+/// On Linux the data gets transferred with `sendfile(2)`, which avoids copying it through
+/// userspace. The transfer starts at the current position of the file descriptor of `file` and
+/// advances it, so `file` must not have any buffered data or operations in progress. Whenever the
+/// socket can not take more data, the transfer waits for it to become writable, so the executor
+/// never blocks on the socket.
+///
+/// If `sendfile(2)` is not supported for the given file descriptors, or on other platforms, the
+/// data gets copied through a buffer using the async io traits instead.
+///
+/// Returns the number of bytes copied, which is less than `len` if `file` reached EOF first.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::io::{sendfile, TokioCompat};
+///
+/// let mut file = TokioCompat::new(tokio::fs::File::open("index.html").await?);
+/// let mut socket = TokioCompat::new(tokio::net::TcpStream::connect("127.0.0.1:8080").await?);
+///
+/// sendfile(&mut file, &mut socket, None).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn sendfile<F, S>(file: &mut F, socket: &mut S, len: Option<u64>) -> std::io::Result<u64>
+where
+    F: AsyncRead + AsRawFd + Unpin,
+    S: AsyncWrite + SendfileSocket + Unpin,
+{
+    // Data buffered by the socket must go out before the data of the file.
+    socket.flush().await?;
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(copied) = sendfile_linux(file, socket, len).await? {
+            return Ok(copied);
+        }
+    }
+
+    copy_buffered(file, socket, len).await
+}
+
+/// Performs the transfer with `sendfile(2)`.
+///
+/// Returns `None` if `sendfile(2)` is not supported for the given file descriptors and nothing
+/// has been transferred yet.
+#[cfg(target_os = "linux")]
+async fn sendfile_linux<F, S>(file: &F, socket: &S, len: Option<u64>) -> std::io::Result<Option<u64>>
+where
+    F: AsRawFd,
+    S: SendfileSocket,
+{
+    let in_fd = file.as_raw_fd();
+    let out_fd = socket.as_raw_fd();
+
+    let mut copied = 0u64;
+
+    loop {
+        let count = match len {
+            Some(len) => (len - copied).min(MAX_CHUNK),
+            None => MAX_CHUNK,
+        };
+        if count == 0 {
+            return Ok(Some(copied));
+        }
+
+        let result = socket.write_with(|| {
+            // SAFETY: Both file descriptors are valid for the duration of the call, as they are
+            // borrowed from `file` and `socket`. A null offset makes the kernel use and advance
+            // the position of `in_fd`.
+            let n = unsafe { libc::sendfile(out_fd, in_fd, std::ptr::null_mut(), count as usize) };
+
+            if n < 0 {
+                Err(std::io::Error::last_os_error())
+            } else {
+                Ok(n as u64)
+            }
+        }).await;
+
+        match result {
+            Ok(0) => return Ok(Some(copied)),
+            Ok(n) => copied += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) if copied == 0 && matches!(err.raw_os_error(), Some(libc::EINVAL) | Some(libc::ENOSYS)) => {
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+async fn copy_buffered<F, S>(file: &mut F, socket: &mut S, len: Option<u64>) -> std::io::Result<u64>
+where
+    F: AsyncRead + Unpin,
+    S: AsyncWrite + Unpin,
+{
+    let copied = match len {
+        Some(len) => futures::io::copy(file.take(len), socket).await?,
+        None => futures::io::copy(file, socket).await?,
+    };
+
+    socket.flush().await?;
+
+    Ok(copied)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+
+    #[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+    use sha2::{Digest, Sha256};
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn buffered_copy_respects_the_length() {
+        let data = data(10_000);
+
+        let mut socket = Vec::new();
+        assert_eq!(block_on(copy_buffered(&mut Cursor::new(&data), &mut socket, Some(4096))).unwrap(), 4096);
+        assert_eq!(socket, &data[..4096]);
+
+        let mut socket = Vec::new();
+        assert_eq!(block_on(copy_buffered(&mut Cursor::new(&data), &mut socket, None)).unwrap(), 10_000);
+        assert_eq!(socket, data);
+    }
+
+    /// Sends `file` over `socket` and reads everything from its peer `client`, until `socket`
+    /// gets dropped.
+    #[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+    async fn serve<Fl, S, C>(mut file: Fl, mut socket: S, mut client: C, len: Option<u64>) -> (u64, Vec<u8>)
+    where
+        Fl: AsyncRead + AsRawFd + Unpin,
+        S: AsyncWrite + SendfileSocket + Unpin,
+        C: AsyncRead + Unpin,
+    {
+        let send = async move {
+            let copied = sendfile(&mut file, &mut socket, len).await.unwrap();
+            // Closing the connection ends the read of the client.
+            drop(socket);
+
+            copied
+        };
+        let receive = async move {
+            let mut received = Vec::new();
+            client.read_to_end(&mut received).await.unwrap();
+
+            received
+        };
+
+        futures::join!(send, receive)
+    }
+
+    /// Serves a multi-MB file over loopback in full, limited and with a limit beyond its end.
+    #[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+    async fn serve_file<Fl, S, C, Fut>(name: &str, connect: impl Fn(std::path::PathBuf) -> Fut)
+    where
+        Fl: AsyncRead + AsRawFd + Unpin,
+        S: AsyncWrite + SendfileSocket + Unpin,
+        C: AsyncRead + Unpin,
+        Fut: std::future::Future<Output = (Fl, S, C)>,
+    {
+        let path = std::env::temp_dir().join(format!("fut-compat-sendfile-{}-{}", name, std::process::id()));
+        let data = data(3 * 1024 * 1024 + 5);
+        std::fs::write(&path, &data).unwrap();
+
+        let (file, socket, client) = connect(path.clone()).await;
+        let (copied, received) = serve(file, socket, client, None).await;
+        assert_eq!(copied, data.len() as u64);
+        assert_eq!(Sha256::digest(&received), Sha256::digest(&data));
+
+        let (file, socket, client) = connect(path.clone()).await;
+        let (copied, received) = serve(file, socket, client, Some(1024 * 1024)).await;
+        assert_eq!(copied, 1024 * 1024);
+        assert_eq!(Sha256::digest(&received), Sha256::digest(&data[..1024 * 1024]));
+
+        let (file, socket, client) = connect(path.clone()).await;
+        let (copied, received) = serve(file, socket, client, Some(u64::MAX)).await;
+        assert_eq!(copied, data.len() as u64);
+        assert_eq!(Sha256::digest(&received), Sha256::digest(&data));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_serve_file() {
+        use crate::io::TokioCompat;
+
+        ::tokio::runtime::Runtime::new().unwrap().block_on(serve_file("tokio", |path| async move {
+            let listener = ::tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            let (client, accepted) = futures::join!(::tokio::net::TcpStream::connect(addr), listener.accept());
+            let file = ::tokio::fs::File::open(path).await.unwrap();
+
+            (TokioCompat::new(file), TokioCompat::new(accepted.unwrap().0), TokioCompat::new(client.unwrap()))
+        }));
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_serve_file() {
+        ::async_std::task::block_on(serve_file("async-std", |path| async move {
+            let listener = ::async_std::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            let (client, accepted) = futures::join!(::async_std::net::TcpStream::connect(addr), listener.accept());
+            let file = ::async_std::fs::File::open(path).await.unwrap();
+
+            (file, accepted.unwrap().0, client.unwrap())
+        }));
+    }
+}
@@ -0,0 +1,335 @@
+use std::io::Error;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncBufRead};
+use futures::stream::{Stream, FusedStream};
+use futures::ready;
+
+
+
+/// The default chunk capacity of a [`ReaderStream`].
+const DEFAULT_CAPACITY: usize = 4096;
+
+/// Converts an [`AsyncRead`] into a [`Stream`] of byte chunks.
+///
+/// Every item holds the bytes returned by one read of at most the configured capacity. The stream
+/// ends after the reader reached EOF or after the first error.
+#[derive(Debug)]
+pub struct ReaderStream<R> {
+    reader: Option<R>,
+    capacity: usize,
+}
+
+impl<R> ReaderStream<R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Creates a new stream reading chunks of up to 4 KiB.
+    pub fn new(reader: R) -> Self {
+        Self::with_capacity(reader, DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new stream reading chunks of up to `capacity` bytes.
+    ///
+    /// A capacity of `0` is treated as `1`, as an empty read would be mistaken for EOF.
+    pub fn with_capacity(reader: R, capacity: usize) -> Self {
+        Self {
+            reader: Some(reader),
+            capacity: capacity.max(1),
+        }
+    }
+}
+
+impl<R> ReaderStream<R> {
+    /// Get a reference to the wrapped reader.
+    ///
+    /// Returns `None` once the stream has ended.
+    pub fn get_ref(&self) -> Option<&R> {
+        self.reader.as_ref()
+    }
+
+    /// Consumes the `ReaderStream` object and returns the wrapped reader.
+    ///
+    /// Returns `None` once the stream has ended.
+    pub fn into_inner(self) -> Option<R> {
+        self.reader
+    }
+}
+
+impl<R> Stream for ReaderStream<R>
+where
+    R: AsyncRead + Unpin,
+{
+    type Item = std::io::Result<Vec<u8>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = Pin::into_inner(self);
+
+        let reader = match &mut this.reader {
+            Some(reader) => reader,
+            None => return Poll::Ready(None),
+        };
+
+        let mut chunk = vec![0u8; this.capacity];
+
+        match ready!(Pin::new(reader).poll_read(cx, &mut chunk)) {
+            Ok(0) => {
+                this.reader = None;
+
+                Poll::Ready(None)
+            }
+            Ok(n) => {
+                chunk.truncate(n);
+
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Err(err) => {
+                this.reader = None;
+
+                Poll::Ready(Some(Err(err)))
+            }
+        }
+    }
+}
+
+impl<R> FusedStream for ReaderStream<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn is_terminated(&self) -> bool {
+        self.reader.is_none()
+    }
+}
+
+
+
+/// Converts a [`Stream`] of byte chunks into an [`AsyncRead`].
+///
+/// A chunk which does not fit into the buffer of a read is kept for the following reads. An error
+/// yielded by the stream is returned by the read which encounters it.
+#[derive(Debug)]
+pub struct StreamReader<S, B> {
+    stream: S,
+    chunk: Option<B>,
+    pos: usize,
+}
+
+impl<S, B> StreamReader<S, B>
+where
+    S: Stream<Item = std::io::Result<B>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    /// Creates a new reader over the chunks of `stream`.
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            chunk: None,
+            pos: 0,
+        }
+    }
+}
+
+impl<S, B> StreamReader<S, B> {
+    /// Get a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Get a mutable reference to the wrapped stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes the `StreamReader` object and returns the wrapped stream.
+    ///
+    /// The remainder of a partially read chunk is lost.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S, B> AsyncBufRead for StreamReader<S, B>
+where
+    S: Stream<Item = std::io::Result<B>> + Unpin,
+    B: AsRef<[u8]> + Unpin,
+{
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<&[u8], Error>> {
+        let this = Pin::into_inner(self);
+
+        loop {
+            if let Some(chunk) = &this.chunk {
+                if this.pos < chunk.as_ref().len() {
+                    break;
+                }
+            }
+
+            // Skips empty chunks, which would otherwise be mistaken for EOF.
+            match ready!(Pin::new(&mut this.stream).poll_next(cx)) {
+                Some(Ok(chunk)) => {
+                    this.chunk = Some(chunk);
+                    this.pos = 0;
+                }
+                Some(Err(err)) => return Poll::Ready(Err(err)),
+                None => {
+                    this.chunk = None;
+                    this.pos = 0;
+
+                    return Poll::Ready(Ok(&[]));
+                }
+            }
+        }
+
+        match &this.chunk {
+            Some(chunk) => Poll::Ready(Ok(&chunk.as_ref()[this.pos..])),
+            None => unreachable!("a non-empty chunk is available"),
+        }
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = Pin::into_inner(self);
+
+        if let Some(chunk) = &this.chunk {
+            this.pos = (this.pos + amt).min(chunk.as_ref().len());
+        }
+    }
+}
+
+impl<S, B> AsyncRead for StreamReader<S, B>
+where
+    S: Stream<Item = std::io::Result<B>> + Unpin,
+    B: AsRef<[u8]> + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, Error>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let available = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let n = available.len().min(buf.len());
+
+        buf[..n].copy_from_slice(&available[..n]);
+
+        self.consume(n);
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::ErrorKind;
+
+    use futures::executor::block_on;
+    use futures::io::{AsyncBufReadExt, AsyncReadExt, Cursor};
+    use futures::stream::{self, StreamExt, TryStreamExt};
+
+    use crate::io::{ScriptAction, ScriptedIo};
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn reader_to_stream_to_reader() {
+        let data = data(100_003);
+
+        for capacity in [0, 1, 7, 4096, 1024 * 1024] {
+            let stream = ReaderStream::with_capacity(Cursor::new(data.clone()), capacity);
+            let stream = stream.inspect_ok(|chunk| assert!(!chunk.is_empty() && chunk.len() <= capacity.max(1)));
+
+            // Reading in pieces which do not line up with the chunks.
+            let mut reader = StreamReader::new(stream);
+            let mut read = Vec::new();
+            let mut buf = [0u8; 1000];
+            loop {
+                let n = block_on(reader.read(&mut buf)).unwrap();
+                if n == 0 {
+                    break;
+                }
+                read.extend_from_slice(&buf[..n]);
+            }
+
+            assert!(read == data, "chunks of {} bytes", capacity);
+        }
+    }
+
+    #[test]
+    fn reader_stream_is_fused_after_eof() {
+        let mut stream = ReaderStream::with_capacity(Cursor::new(b"abc".to_vec()), 2);
+
+        assert_eq!(block_on(stream.next()).unwrap().unwrap(), b"ab");
+        assert_eq!(block_on(stream.next()).unwrap().unwrap(), b"c");
+        assert!(!stream.is_terminated());
+        assert!(block_on(stream.next()).is_none());
+        assert!(stream.is_terminated());
+        assert!(block_on(stream.next()).is_none());
+        assert!(stream.into_inner().is_none());
+    }
+
+    #[test]
+    fn reader_stream_ends_after_an_error() {
+        let io = ScriptedIo::new([
+            ScriptAction::Read(b"ab".to_vec()),
+            ScriptAction::Pending,
+            ScriptAction::Err(ErrorKind::ConnectionReset),
+            ScriptAction::Read(b"never read".to_vec()),
+        ]);
+        let mut stream = ReaderStream::new(io);
+
+        assert_eq!(block_on(stream.next()).unwrap().unwrap(), b"ab");
+        assert_eq!(stream.get_ref().unwrap().position(), 2);
+        assert_eq!(block_on(stream.next()).unwrap().unwrap_err().kind(), ErrorKind::ConnectionReset);
+        assert!(stream.is_terminated());
+        assert!(block_on(stream.next()).is_none());
+        assert!(stream.get_ref().is_none());
+    }
+
+    #[test]
+    fn stream_reader_keeps_partial_chunks_and_skips_empty_ones() {
+        let chunks: Vec<std::io::Result<&[u8]>> = vec![Ok(b"hello"), Ok(b""), Ok(b" wor"), Ok(b""), Ok(b"ld\nrest")];
+        let mut reader = StreamReader::new(stream::iter(chunks));
+
+        let mut buf = [0u8; 3];
+        block_on(reader.read_exact(&mut buf)).unwrap();
+        assert_eq!(&buf, b"hel");
+        assert_eq!(block_on(reader.fill_buf()).unwrap(), b"lo");
+
+        let mut line = String::new();
+        block_on(reader.read_line(&mut line)).unwrap();
+        assert_eq!(line, "lo world\n");
+
+        let mut rest = String::new();
+        block_on(reader.read_to_string(&mut rest)).unwrap();
+        assert_eq!(rest, "rest");
+        assert_eq!(block_on(reader.read(&mut buf)).unwrap(), 0);
+    }
+
+    #[test]
+    fn stream_reader_returns_errors_in_order() {
+        let chunks: Vec<std::io::Result<Vec<u8>>> = vec![
+            Ok(b"ab".to_vec()),
+            Err(Error::new(ErrorKind::InvalidData, "bad chunk")),
+            Ok(b"cd".to_vec()),
+        ];
+        let mut reader = StreamReader::new(stream::iter(chunks));
+        let mut buf = [0u8; 8];
+
+        assert_eq!(block_on(reader.read(&mut buf)).unwrap(), 2);
+        assert_eq!(block_on(reader.read(&mut buf)).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        // The stream decides whether anything follows an error.
+        assert_eq!(block_on(reader.read(&mut buf)).unwrap(), 2);
+        assert_eq!(&buf[..2], b"cd");
+        assert_eq!(block_on(reader.read(&mut [])).unwrap(), 0);
+        assert_eq!(block_on(reader.read(&mut buf)).unwrap(), 0);
+    }
+}
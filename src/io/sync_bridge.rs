@@ -0,0 +1,268 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::pin::Pin;
+
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, AsyncSeek, AsyncSeekExt};
+use futures::future::poll_fn;
+
+use crate::task::BlockOn;
+
+
+
+/// Wraps an object implementing the async io traits and implements the sync io traits for it, by
+/// blocking on every operation with an executor of type `E`.
+///
+/// This is the inverse of [`BlockingIo`](super::BlockingIo). It allows handing an async reader or
+/// writer to a synchronous library, e.g. for decompressing or decoding its data.
+///
+/// # Deadlocks
+///
+/// Every operation blocks the calling thread until it completes. The bridge must therefore only be
+/// used from threads which are allowed to block, i.e. inside of
+/// [`SpawnBlocking::spawn_blocking`](crate::task::SpawnBlocking::spawn_blocking). Using it on a
+/// thread of the async executor stalls all tasks scheduled on that thread, which deadlocks if the
+/// wrapped object depends on one of them, e.g. because its data is produced by another task.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use std::io::Read;
+///
+/// use fut_compat::io::{SyncIoBridge, TokioCompat};
+/// use fut_compat::task::{SpawnBlocking, TokioExecutor};
+///
+/// let stream = TokioCompat::new(tokio::net::TcpStream::connect("127.0.0.1:8080").await?);
+///
+/// let contents = TokioExecutor::default().spawn_blocking(move || {
+///     let mut bridge = SyncIoBridge::<_, TokioExecutor>::new(stream);
+///
+///     let mut contents = Vec::new();
+///     bridge.read_to_end(&mut contents).map(|_| contents)
+/// }).await.unwrap()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SyncIoBridge<T, E> {
+    inner: T,
+    executor: E,
+}
+
+impl<T, E: Default> SyncIoBridge<T, E> {
+    /// Creates a new instance by wrapping the `inner` object, blocking with the default executor.
+    pub fn new(inner: T) -> Self {
+        Self::with_executor(inner, E::default())
+    }
+}
+
+impl<T, E> SyncIoBridge<T, E> {
+    /// Creates a new instance by wrapping the `inner` object, blocking with `executor`.
+    pub fn with_executor(inner: T, executor: E) -> Self {
+        Self {
+            inner,
+            executor,
+        }
+    }
+
+    /// Get a reference to the executor used for blocking.
+    pub fn executor(&self) -> &E {
+        &self.executor
+    }
+
+    /// Get a reference to the wrapped object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped object.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes the `SyncIoBridge` object and returns the wrapped object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, E> SyncIoBridge<T, E>
+where
+    T: AsyncWrite + Unpin,
+    E: BlockOn,
+{
+    /// Closes the wrapped writer, blocking until it completes.
+    pub fn close(&mut self) -> std::io::Result<()> {
+        self.executor.block_on(self.inner.close())
+    }
+}
+
+impl<T, E> Read for SyncIoBridge<T, E>
+where
+    T: AsyncRead + Unpin,
+    E: BlockOn,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.executor.block_on(self.inner.read(buf))
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [std::io::IoSliceMut<'_>]) -> std::io::Result<usize> {
+        self.executor.block_on(poll_fn(|cx| Pin::new(&mut self.inner).poll_read_vectored(cx, bufs)))
+    }
+}
+
+impl<T, E> Write for SyncIoBridge<T, E>
+where
+    T: AsyncWrite + Unpin,
+    E: BlockOn,
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.executor.block_on(self.inner.write(buf))
+    }
+
+    fn write_vectored(&mut self, bufs: &[std::io::IoSlice<'_>]) -> std::io::Result<usize> {
+        self.executor.block_on(self.inner.write_vectored(bufs))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.executor.block_on(self.inner.flush())
+    }
+}
+
+impl<T, E> Seek for SyncIoBridge<T, E>
+where
+    T: AsyncSeek + Unpin,
+    E: BlockOn,
+{
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.executor.block_on(self.inner.seek(pos))
+    }
+}
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use futures::io::Cursor;
+
+    use flate2::Compression;
+    use flate2::read::GzDecoder;
+    use flate2::write::GzEncoder;
+
+    use crate::task::SpawnBlocking;
+
+    fn data() -> Vec<u8> {
+        (0..256 * 1024).map(|i: usize| ((i / 7) % 251) as u8).collect()
+    }
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+
+        encoder.finish().unwrap()
+    }
+
+    /// Writes `data` in small chunks into `socket` and closes the connection by dropping it.
+    async fn send_in_chunks<S: AsyncWrite + Unpin>(mut socket: S, data: Vec<u8>) {
+        for chunk in data.chunks(1000) {
+            socket.write_all(chunk).await.unwrap();
+            socket.flush().await.unwrap();
+        }
+    }
+
+    /// Decompresses the data received by `client` inside of `spawn_blocking`, while the current
+    /// task sends it from `server`.
+    async fn decompress_from_socket<E, S>(client: S, server: S)
+    where
+        E: SpawnBlocking + BlockOn + Default + 'static,
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let data = data();
+
+        let decompress = E::default().spawn_blocking(move || {
+            let mut decoder = GzDecoder::new(SyncIoBridge::<_, E>::new(client));
+
+            let mut decompressed = Vec::new();
+            decoder.read_to_end(&mut decompressed).map(|_| decompressed)
+        });
+
+        let ((), decompressed) = futures::join!(send_in_chunks(server, compress(&data)), decompress);
+
+        assert!(decompressed.unwrap().unwrap() == data);
+    }
+
+    /// Compresses into an async cursor through the bridge, then seeks back and decompresses it.
+    fn compress_seek_and_decompress<E: BlockOn + Default>() {
+        let data = data();
+        let mut bridge = SyncIoBridge::<_, E>::new(Cursor::new(Vec::new()));
+
+        let mut encoder = GzEncoder::new(&mut bridge, Compression::default());
+        encoder.write_all(&data).unwrap();
+        encoder.finish().unwrap();
+        bridge.flush().unwrap();
+
+        assert_eq!(bridge.seek(SeekFrom::Start(0)).unwrap(), 0);
+
+        let mut decompressed = Vec::new();
+        GzDecoder::new(&mut bridge).read_to_end(&mut decompressed).unwrap();
+        assert!(decompressed == data);
+
+        bridge.close().unwrap();
+        assert!(bridge.into_inner().into_inner() == compress(&data));
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_decompress_from_socket() {
+        use crate::io::TokioCompat;
+        use crate::task::TokioExecutor;
+
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let listener = ::tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            let (client, accepted) = futures::join!(::tokio::net::TcpStream::connect(addr), listener.accept());
+
+            decompress_from_socket::<TokioExecutor, _>(
+                TokioCompat::new(client.unwrap()),
+                TokioCompat::new(accepted.unwrap().0),
+            ).await;
+        });
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_decompress_from_socket() {
+        use crate::task::AsyncStdExecutor;
+
+        ::async_std::task::block_on(async {
+            let listener = ::async_std::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            let (client, accepted) = futures::join!(::async_std::net::TcpStream::connect(addr), listener.accept());
+
+            decompress_from_socket::<AsyncStdExecutor, _>(client.unwrap(), accepted.unwrap().0).await;
+        });
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_compress_seek_and_decompress() {
+        use crate::task::TokioExecutor;
+
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            TokioExecutor::default().spawn_blocking(compress_seek_and_decompress::<TokioExecutor>).await.unwrap();
+        });
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_compress_seek_and_decompress() {
+        use crate::task::AsyncStdExecutor;
+
+        ::async_std::task::block_on(async {
+            AsyncStdExecutor::default().spawn_blocking(compress_seek_and_decompress::<AsyncStdExecutor>).await.unwrap();
+        });
+    }
+}
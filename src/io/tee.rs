@@ -0,0 +1,287 @@
+use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::future::poll_fn;
+use futures::ready;
+
+
+
+/// Wraps a reader and writes every byte read from it into a mirror writer.
+///
+/// The bytes get passed to the mirror before the read returns. If the mirror does not accept all of
+/// them right away, the remainder is buffered and written before the next read, so the caller
+/// always gets the full result of the read. An error of the mirror is returned by the next read or
+/// flush.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::io::{AsyncReadExt, TeeReader};
+///
+/// let reader = futures::io::Cursor::new(b"hello world".to_vec());
+/// let mut tee = TeeReader::new(reader, Vec::new());
+///
+/// let mut contents = String::new();
+/// tee.read_to_string(&mut contents).await?;
+/// tee.flush().await?;
+///
+/// assert_eq!(tee.writer(), b"hello world");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TeeReader<R, W> {
+    reader: R,
+    writer: W,
+    /// Bytes which have been read but not yet accepted by the mirror.
+    pending: Vec<u8>,
+    pending_pos: usize,
+    error: Option<Error>,
+}
+
+impl<R, W> TeeReader<R, W> {
+    /// Creates a new instance mirroring the bytes read from `reader` into `writer`.
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            reader,
+            writer,
+            pending: Vec::new(),
+            pending_pos: 0,
+            error: None,
+        }
+    }
+
+    /// Get a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the wrapped reader.
+    ///
+    /// Bytes read directly from the wrapped reader are not mirrored.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Get a reference to the mirror writer.
+    pub fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the mirror writer.
+    pub fn writer_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Consumes the `TeeReader` object and returns the wrapped reader and the mirror writer.
+    ///
+    /// Bytes not yet written to the mirror are lost, call [`flush`](Self::flush) beforehand.
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+}
+
+impl<R, W> TeeReader<R, W>
+where
+    W: AsyncWrite + Unpin,
+{
+    /// Writes the buffered bytes into the mirror.
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        if let Some(err) = self.error.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        while self.pending_pos < self.pending.len() {
+            let n = ready!(Pin::new(&mut self.writer).poll_write(cx, &self.pending[self.pending_pos..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(Error::new(ErrorKind::WriteZero, "failed to write to the mirror of TeeReader")));
+            }
+
+            self.pending_pos += n;
+        }
+
+        self.pending.clear();
+        self.pending_pos = 0;
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Writes the buffered bytes into the mirror and flushes it.
+    pub fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        ready!(self.poll_write_pending(cx))?;
+
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    /// Writes the buffered bytes into the mirror and closes it.
+    pub fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        ready!(self.poll_write_pending(cx))?;
+
+        Pin::new(&mut self.writer).poll_close(cx)
+    }
+
+    /// Writes the buffered bytes into the mirror and flushes it.
+    pub async fn flush(&mut self) -> std::io::Result<()> {
+        poll_fn(|cx| self.poll_flush(cx)).await
+    }
+
+    /// Writes the buffered bytes into the mirror and closes it.
+    pub async fn close(&mut self) -> std::io::Result<()> {
+        poll_fn(|cx| self.poll_close(cx)).await
+    }
+}
+
+impl<R, W> AsyncRead for TeeReader<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        // Keep the mirror in the same order as the reads.
+        ready!(this.poll_write_pending(cx))?;
+
+        let n = ready!(Pin::new(&mut this.reader).poll_read(cx, buf))?;
+        this.pending.extend_from_slice(&buf[..n]);
+
+        // The read already happened, so an error of the mirror has to wait for the next call.
+        if let Poll::Ready(Err(err)) = this.poll_write_pending(cx) {
+            this.error = Some(err);
+        }
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::io::{AsyncReadExt, Cursor};
+
+    use crate::io::{ScriptAction, ScriptedIo};
+
+    fn tee(mirror: impl IntoIterator<Item = ScriptAction>) -> TeeReader<Cursor<&'static [u8]>, ScriptedIo> {
+        TeeReader::new(Cursor::new(&b"hello world"[..]), ScriptedIo::new(mirror))
+    }
+
+    #[test]
+    fn partial_writes_are_buffered() {
+        let mut tee = tee([
+            ScriptAction::ShortWrite(2),
+            ScriptAction::ShortWrite(1),
+            ScriptAction::Pending,
+            ScriptAction::ShortWrite(10),
+            ScriptAction::ShortWrite(3),
+            ScriptAction::ShortWrite(10),
+            ScriptAction::ShortWrite(10),
+        ]);
+
+        let mut buf = [0u8; 5];
+
+        // The mirror only took `hel`, but the read is not short.
+        assert_eq!(block_on(tee.read(&mut buf)).unwrap(), 5);
+        assert_eq!(&buf, b"hello");
+        assert_eq!(tee.writer().written(), b"hel");
+
+        // The remainder is written before the next read.
+        assert_eq!(block_on(tee.read(&mut buf)).unwrap(), 5);
+        assert_eq!(&buf, b" worl");
+        assert_eq!(tee.writer().written(), b"hello worl");
+
+        let mut rest = Vec::new();
+        block_on(tee.read_to_end(&mut rest)).unwrap();
+        assert_eq!(rest, b"d");
+
+        block_on(tee.flush()).unwrap();
+        assert_eq!(tee.writer().written(), b"hello world");
+        tee.writer().assert_done();
+    }
+
+    #[test]
+    fn flush_writes_the_remainder_and_flushes() {
+        let mut tee = tee([
+            ScriptAction::Pending,
+            ScriptAction::ShortWrite(3),
+            ScriptAction::ShortWrite(10),
+            // Consumed by the flush of the mirror.
+            ScriptAction::Pending,
+        ]);
+
+        let mut buf = [0u8; 5];
+        assert_eq!(block_on(tee.read(&mut buf)).unwrap(), 5);
+        assert!(tee.writer().written().is_empty());
+
+        block_on(tee.flush()).unwrap();
+        assert_eq!(tee.writer().written(), b"hello");
+        tee.writer().assert_done();
+    }
+
+    #[test]
+    fn close_writes_the_remainder_and_closes() {
+        let mut tee = tee([
+            ScriptAction::Pending,
+            ScriptAction::ShortWrite(10),
+            // Consumed by the close of the mirror.
+            ScriptAction::Err(ErrorKind::Other),
+        ]);
+
+        let mut buf = [0u8; 5];
+        block_on(tee.read(&mut buf)).unwrap();
+
+        let err = block_on(tee.close()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+        assert_eq!(tee.writer().written(), b"hello");
+        tee.writer().assert_done();
+    }
+
+    #[test]
+    fn mirror_errors_fail_the_next_read() {
+        let mut tee = tee([
+            ScriptAction::ShortWrite(2),
+            ScriptAction::Err(ErrorKind::BrokenPipe),
+        ]);
+
+        let mut buf = [0u8; 5];
+        assert_eq!(block_on(tee.read(&mut buf)).unwrap(), 5);
+
+        let err = block_on(tee.read(&mut buf)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+        // The failed call did not read anything.
+        assert_eq!(tee.get_ref().position(), 5);
+        assert_eq!(tee.writer().written(), b"he");
+    }
+
+    #[test]
+    fn mirror_errors_fail_the_next_flush() {
+        let mut tee = tee([ScriptAction::Err(ErrorKind::BrokenPipe)]);
+
+        let mut buf = [0u8; 11];
+        assert_eq!(block_on(tee.read(&mut buf)).unwrap(), 11);
+
+        let err = block_on(tee.flush()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+        tee.writer().assert_done();
+    }
+
+    #[test]
+    fn zero_length_writes_fail() {
+        let mut tee = tee([ScriptAction::ShortWrite(0)]);
+
+        let mut buf = [0u8; 5];
+        block_on(tee.read(&mut buf)).unwrap();
+
+        let err = block_on(tee.flush()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+    }
+}
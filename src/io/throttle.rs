@@ -0,0 +1,329 @@
+use std::future::Future;
+use std::io::Error;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::ready;
+
+use crate::task::Timer;
+
+
+
+/// A token bucket which refills at a fixed rate, one token per byte.
+struct Bucket<Tm: Timer> {
+    rate: u64,
+    burst: u64,
+    tokens: f64,
+    last_refill: Instant,
+    sleep: Option<Pin<Box<Tm::Sleep>>>,
+}
+
+impl<Tm: Timer> Bucket<Tm> {
+    fn new(rate: u64, burst: u64) -> Self {
+        let burst = burst.max(1);
+
+        Self {
+            rate: rate.max(1),
+            burst,
+            tokens: burst as f64,
+            last_refill: Tm::now(),
+            sleep: None,
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Tm::now();
+        let elapsed = now.saturating_duration_since(self.last_refill);
+
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate as f64).min(self.burst as f64);
+        self.last_refill = now;
+    }
+
+    fn set_rate(&mut self, rate: u64, burst: u64) {
+        // Account for the time which passed with the old rate.
+        self.refill();
+
+        self.rate = rate.max(1);
+        self.burst = burst.max(1);
+        self.tokens = self.tokens.min(self.burst as f64);
+        self.sleep = None;
+    }
+
+    /// Waits until at least one token is available and returns how many of the `wanted` tokens
+    /// may be used.
+    fn poll_acquire(&mut self, cx: &mut Context<'_>, wanted: usize) -> Poll<usize> {
+        loop {
+            if let Some(sleep) = &mut self.sleep {
+                ready!(sleep.as_mut().poll(cx));
+
+                self.sleep = None;
+            }
+
+            self.refill();
+
+            if self.tokens >= 1.0 {
+                return Poll::Ready(wanted.min(self.tokens as usize));
+            }
+
+            let wait = Duration::from_secs_f64((1.0 - self.tokens) / self.rate as f64);
+            self.sleep = Some(Box::pin(Tm::sleep(wait)));
+        }
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.tokens -= amount as f64;
+    }
+}
+
+/// Limits the throughput of a reader and/or writer.
+///
+/// Reads and writes are limited independently of each other by a token bucket, which holds up to
+/// `burst` bytes and gets refilled with `bytes_per_sec` bytes per second. Every read or write gets
+/// clamped to the bytes available in the bucket. If the bucket is empty, the operation waits on the
+/// [`Timer`] `Tm` until enough time has passed.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::io::{copy, Throttle};
+/// use fut_compat::task::TokioExecutor;
+///
+/// let reader = futures::io::Cursor::new(vec![0u8; 4 * 1024 * 1024]);
+///
+/// // Copy with 1 MiB per second.
+/// let mut reader = Throttle::<_, TokioExecutor>::new(reader, 1024 * 1024, 64 * 1024);
+/// copy(&mut reader, &mut futures::io::sink()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Throttle<T, Tm: Timer> {
+    inner: T,
+    read: Bucket<Tm>,
+    write: Bucket<Tm>,
+}
+
+impl<T, Tm: Timer> Throttle<T, Tm> {
+    /// Creates a new instance by wrapping the `inner` object.
+    ///
+    /// The rate and the burst are at least one byte.
+    pub fn new(inner: T, bytes_per_sec: u64, burst: u64) -> Self {
+        Self {
+            inner,
+            read: Bucket::new(bytes_per_sec, burst),
+            write: Bucket::new(bytes_per_sec, burst),
+        }
+    }
+
+    /// Changes the rate and the burst, taking effect with the next read or write.
+    pub fn set_rate(&mut self, bytes_per_sec: u64, burst: u64) {
+        self.read.set_rate(bytes_per_sec, burst);
+        self.write.set_rate(bytes_per_sec, burst);
+    }
+
+    /// Get a reference to the wrapped object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped object.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes the `Throttle` object and returns the wrapped object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, Tm> AsyncRead for Throttle<T, Tm>
+where
+    T: AsyncRead + Unpin,
+    Tm: Timer,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        if buf.is_empty() {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        }
+
+        let len = ready!(this.read.poll_acquire(cx, buf.len()));
+
+        let n = ready!(Pin::new(&mut this.inner).poll_read(cx, &mut buf[..len]))?;
+        this.read.consume(n);
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<T, Tm> AsyncWrite for Throttle<T, Tm>
+where
+    T: AsyncWrite + Unpin,
+    Tm: Timer,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        if buf.is_empty() {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        }
+
+        let len = ready!(this.write.poll_acquire(cx, buf.len()));
+
+        let n = ready!(Pin::new(&mut this.inner).poll_write(cx, &buf[..len]))?;
+        this.write.consume(n);
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_close(cx)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use futures::io::{AsyncReadExt, AsyncWriteExt, Cursor};
+    use futures::task::{waker, ArcWake};
+    use futures::FutureExt;
+
+    use crate::task::MockTimer;
+
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl ArcWake for CountingWaker {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn throttled(len: usize, bytes_per_sec: u64, burst: u64) -> Throttle<Cursor<Vec<u8>>, MockTimer> {
+        Throttle::new(Cursor::new(vec![0u8; len]), bytes_per_sec, burst)
+    }
+
+    /// Reads as much as possible without waiting and returns the number of bytes read.
+    fn read_available<T: AsyncRead + Unpin>(reader: &mut T) -> usize {
+        let mut buf = [0u8; 4096];
+        let mut total = 0;
+
+        while let Some(n) = reader.read(&mut buf).now_or_never() {
+            total += n.unwrap();
+        }
+
+        total
+    }
+
+    #[test]
+    fn rate_holds_over_a_simulated_window() {
+        let mut reader = throttled(1024 * 1024, 1000, 100);
+        let mut total = read_available(&mut reader);
+        assert_eq!(total, 100);
+
+        // Ten seconds in steps of 10ms, which refill 10 bytes each.
+        for _ in 0..1000 {
+            MockTimer::advance(Duration::from_millis(10));
+            total += read_available(&mut reader);
+        }
+
+        assert!((10_099..=10_100).contains(&total), "{} bytes read", total);
+    }
+
+    #[test]
+    fn idle_time_only_refills_the_burst() {
+        let mut reader = throttled(1024 * 1024, 1000, 100);
+        assert_eq!(read_available(&mut reader), 100);
+
+        MockTimer::advance(Duration::from_secs(60));
+        assert_eq!(read_available(&mut reader), 100);
+    }
+
+    #[test]
+    fn empty_bucket_schedules_a_wakeup() {
+        let mut reader = throttled(1024, 1000, 100);
+        let mut buf = [0u8; 1000];
+
+        // A read gets clamped to the bytes in the bucket.
+        assert_eq!(reader.read(&mut buf).now_or_never().unwrap().unwrap(), 100);
+
+        let wake = Arc::new(CountingWaker::default());
+        let waker = waker(wake.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(Pin::new(&mut reader).poll_read(&mut cx, &mut buf).is_pending());
+        assert_eq!(MockTimer::pending_sleep_count(), 1);
+
+        // One byte takes a millisecond.
+        MockTimer::advance(Duration::from_micros(999));
+        assert_eq!(wake.0.load(Ordering::SeqCst), 0);
+        MockTimer::advance(Duration::from_micros(1));
+        assert_eq!(wake.0.load(Ordering::SeqCst), 1);
+
+        MockTimer::advance(Duration::from_millis(49));
+        match Pin::new(&mut reader).poll_read(&mut cx, &mut buf) {
+            Poll::Ready(n) => assert_eq!(n.unwrap(), 50),
+            Poll::Pending => panic!("the bucket has been refilled"),
+        }
+    }
+
+    #[test]
+    fn set_rate_applies_to_the_next_read() {
+        let mut reader = throttled(1024 * 1024, 1000, 100);
+        assert_eq!(read_available(&mut reader), 100);
+
+        MockTimer::advance(Duration::from_millis(10));
+        reader.set_rate(10_000, 1000);
+
+        // The 10ms before the change count with the old rate, the 10ms after with the new one.
+        MockTimer::advance(Duration::from_millis(10));
+        assert_eq!(read_available(&mut reader), 10 + 100);
+
+        // The burst is bounded by the new one.
+        MockTimer::advance(Duration::from_secs(1));
+        assert_eq!(read_available(&mut reader), 1000);
+    }
+
+    #[test]
+    fn reads_and_writes_have_separate_buckets() {
+        let mut io = throttled(1024, 1000, 100);
+        assert_eq!(read_available(&mut io), 100);
+
+        // The write bucket is still full.
+        assert_eq!(io.write(&[1u8; 1000]).now_or_never().unwrap().unwrap(), 100);
+        assert!(io.write(&[1u8; 1000]).now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_millis(20));
+        assert_eq!(io.write(&[1u8; 1000]).now_or_never().unwrap().unwrap(), 20);
+        assert_eq!(read_available(&mut io), 20);
+
+        // Flushing and closing do not take tokens.
+        assert!(io.flush().now_or_never().unwrap().is_ok());
+        assert!(io.close().now_or_never().unwrap().is_ok());
+        // Reads and writes move the same cursor.
+        assert_eq!(io.into_inner().position(), 2 * (100 + 20));
+    }
+}
@@ -1,8 +1,13 @@
-use std::io::{Error, ErrorKind, SeekFrom};
+use std::io::{Error, ErrorKind, IoSlice, IoSliceMut, SeekFrom};
+use std::ops::{Deref, DerefMut};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::task::{Context, Poll};
+#[cfg(unix)]
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
 
 use futures::io::{AsyncRead, AsyncBufRead, AsyncWrite, AsyncSeek};
+use futures::ready;
 
 use ::tokio::io::{
     AsyncRead as TokioAsyncRead,
@@ -16,19 +21,908 @@ use ::tokio::io::{
 
 /// Provides compatibility between objects implementing [`tokio`](https://docs.rs/tokio)'s async io traits and
 /// the corresponding traits defined by the [`futures`](https://docs.rs/futures) crate.
+///
+/// The wrapped object can be accessed directly through [`Deref`] and [`DerefMut`], e.g. for
+/// calling [`tokio::net::TcpStream::peek`](::tokio::net::TcpStream::peek) on a
+/// `TokioCompat<tokio::net::TcpStream>`. The wrapped object is pinned whenever the wrapper is, so
+/// it does not need to implement [`Unpin`].
 #[cfg(feature = "tokio-rt")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
 pub struct TokioCompat<T> {
     inner: T,
-    seek_in_progress: bool,
+    state: CompatState,
+}
+
+/// The state of a [`TokioCompat`] besides the wrapped object.
+struct CompatState {
+    /// The position requested by a seek which has been started but not yet completed.
+    seek_pos: Option<SeekFrom>,
+    read_buf: Vec<u8>,
+    /// The position of the first unconsumed byte in `read_buf`. It is atomic, so reads through a
+    /// shared reference can take data out of the buffer as well.
+    read_pos: AtomicUsize,
+    error_mapper: ErrorMapper,
+}
+
+/// Maps the errors of the inner object before they get returned through the futures traits.
+struct ErrorMapper(Option<Box<dyn Fn(Error) -> Error + Send + Sync>>);
+
+impl ErrorMapper {
+    fn map(&self, err: Error) -> Error {
+        match &self.0 {
+            Some(mapper) => mapper(err),
+            None => err,
+        }
+    }
+}
+
+impl CompatState {
+    /// Discards the contents of the internal read buffer.
+    fn discard_read_buffer(&mut self) {
+        self.read_buf.clear();
+        *self.read_pos.get_mut() = 0;
+    }
+
+    /// Returns `true` if the internal read buffer holds data which has not yet been consumed.
+    fn has_buffered_data(&self) -> bool {
+        !self.buffered().is_empty()
+    }
+
+    /// Returns the data in the internal read buffer which has not yet been consumed.
+    fn buffered(&self) -> &[u8] {
+        &self.read_buf[self.read_pos.load(Ordering::Relaxed)..]
+    }
+
+    /// Marks up to `amt` bytes of the internal read buffer as consumed.
+    fn consume_buffered(&mut self, amt: usize) {
+        let pos = self.read_pos.get_mut();
+        *pos = (*pos + amt).min(self.read_buf.len());
+    }
+
+    /// Copies buffered data into `buf` through a shared reference and returns its length.
+    ///
+    /// The buffer itself can not change while shared references exist, so concurrent readers only
+    /// need to agree on who gets which part of it.
+    fn take_buffered(&self, buf: &mut [u8]) -> usize {
+        let mut pos = self.read_pos.load(Ordering::Relaxed);
+
+        loop {
+            let n = (self.read_buf.len() - pos).min(buf.len());
+            if n == 0 {
+                return 0;
+            }
+
+            match self.read_pos.compare_exchange_weak(pos, pos + n, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => {
+                    buf[..n].copy_from_slice(&self.read_buf[pos..pos + n]);
+                    return n;
+                }
+                Err(current) => pos = current,
+            }
+        }
+    }
 }
 
 impl<T> TokioCompat<T> {
+    /// Creates a new instance by wrapping the `inner` object.
+    pub fn new(inner: T) -> Self {
+        Self::with_read_buffer_capacity(inner, 0)
+    }
+
+    /// Creates a new instance by wrapping the `inner` object, using an internal read buffer of the
+    /// given capacity.
+    ///
+    /// Reads into slices smaller than `capacity` are served from the internal buffer, which gets
+    /// filled by the inner object without being zeroed first. This reduces the number of reads
+    /// issued to the inner object when the caller uses small buffers. Reads into larger slices
+    /// bypass the internal buffer once it is drained.
+    ///
+    /// A capacity of `0` disables the internal buffer, which is what [`new`](Self::new) does.
+    pub fn with_read_buffer_capacity(inner: T, capacity: usize) -> Self {
+        Self {
+            inner,
+            state: CompatState {
+                seek_pos: None,
+                read_buf: Vec::with_capacity(capacity),
+                read_pos: AtomicUsize::new(0),
+                error_mapper: ErrorMapper(None),
+            },
+        }
+    }
+
+    /// Routes every error returned by the inner object through `mapper` before it gets returned
+    /// through the traits of the [`futures`](https://docs.rs/futures) crate.
+    ///
+    /// The kind of the mapped error decides how it gets handled. By default the errors are not
+    /// changed, so an [`Interrupted`](ErrorKind::Interrupted) error leads to the operation being
+    /// retried once. A mapper can opt out of this by translating these errors, or opt in for other
+    /// errors by translating them into this kind. All other errors, including
+    /// [`WouldBlock`](ErrorKind::WouldBlock), get returned to the caller.
+    ///
+    /// The traits of [`tokio`](https://docs.rs/tokio) implemented by the wrapper are not affected.
+    pub fn with_error_mapper<F>(mut self, mapper: F) -> Self
+    where
+        F: Fn(Error) -> Error + Send + Sync + 'static,
+    {
+        self.state.error_mapper = ErrorMapper(Some(Box::new(mapper)));
+        self
+    }
+
+    /// Returns the data which has been read from the inner object but not yet been consumed.
+    pub fn read_buffer(&self) -> &[u8] {
+        self.state.buffered()
+    }
+
+    /// Get a reference to the wrapped object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped object.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Get a pinned mutable reference to the wrapped object.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut T> {
+        self.project().0
+    }
+
+    /// Consumes the `TokioCompat` object and returns the wrapped object.
+    ///
+    /// Any data left in the internal read buffer is lost, see [`read_buffer`](Self::read_buffer).
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Projects the pinned wrapper onto the pinned wrapped object and the unpinned state.
+    fn project(self: Pin<&mut Self>) -> (Pin<&mut T>, &mut CompatState) {
+        // SAFETY: `inner` is structurally pinned. It is never moved out of a pinned wrapper, as
+        // `TokioCompat` neither implements `Drop` nor `Unpin` unless `T` does. `state` is not
+        // pinned and never exposed as pinned.
+        unsafe {
+            let this = self.get_unchecked_mut();
+
+            (Pin::new_unchecked(&mut this.inner), &mut this.state)
+        }
+    }
+}
+
+impl<T> Deref for TokioCompat<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for TokioCompat<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<T> AsRef<T> for TokioCompat<T> {
+    fn as_ref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> AsMut<T> for TokioCompat<T> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T> From<T> for TokioCompat<T> {
+    fn from(inner: T) -> Self {
+        Self::new(inner)
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<T> TokioCompat<T>
+where
+    T: TokioAsyncRead + Unpin,
+{
+    /// Reads some bytes into the spare capacity of `buf`, advancing it by the number of bytes
+    /// read.
+    ///
+    /// This takes precedence over [`AsyncReadBufExt::read_buf`](super::AsyncReadBufExt::read_buf).
+    /// Unlike the generic implementation, the spare capacity is handed to the wrapped object
+    /// without being zeroed first. Data in the internal read buffer is served first.
+    pub async fn read_buf<B>(&mut self, buf: &mut B) -> std::io::Result<usize>
+    where
+        B: ::bytes::BufMut + ?Sized,
+    {
+        futures::future::poll_fn(|cx| {
+            let (inner, state) = Pin::new(&mut *self).project();
+
+            if state.has_buffered_data() {
+                let available = state.buffered();
+                let n = available.len().min(buf.remaining_mut());
+
+                buf.put_slice(&available[..n]);
+                state.consume_buffered(n);
+
+                return Poll::Ready(Ok(n));
+            }
+
+            if !buf.has_remaining_mut() {
+                return Poll::Ready(Ok(0));
+            }
+
+            let n = {
+                // SAFETY: `ReadBuf` never de-initializes memory, and the inner object can only
+                // initialize it.
+                let mut read_buf = ReadBuf::uninit(unsafe { buf.chunk_mut().as_uninit_slice_mut() });
+
+                ready!(poll_read_buf(inner, cx, &state.error_mapper, &mut read_buf))?;
+
+                read_buf.filled().len()
+            };
+
+            // SAFETY: The first `n` bytes of the chunk have been initialized by the inner object.
+            unsafe { buf.advance_mut(n) };
+
+            Poll::Ready(Ok(n))
+        }).await
+    }
+}
+
+#[cfg(unix)]
+impl<T: AsFd> AsFd for TokioCompat<T> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
+}
+
+#[cfg(unix)]
+impl<T: AsRawFd> AsRawFd for TokioCompat<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+/// Calls `poll`, calling it a second time if the first call failed with
+/// [`ErrorKind::Interrupted`].
+///
+/// The errors get routed through `mapper` first. An `Interrupted` error of the second call is
+/// passed through unchanged, so the caller can decide whether to retry again. All other errors,
+/// including `WouldBlock`, are returned as they are, as turning them into `Pending` without
+/// registering the waker would never wake the task again.
+fn poll_retry_interrupted<R>(
+    mapper: &ErrorMapper,
+    mut poll: impl FnMut() -> Poll<Result<R, Error>>,
+) -> Poll<Result<R, Error>> {
+    let mut poll = || match poll() {
+        Poll::Ready(Err(err)) => Poll::Ready(Err(mapper.map(err))),
+        result => result,
+    };
+
+    match poll() {
+        Poll::Ready(Err(err)) if err.kind() == ErrorKind::Interrupted => poll(),
+        result => result,
+    }
+}
+
+/// Reads from `inner` into `buf`, converting the errors like all other methods of [`TokioCompat`].
+fn poll_read_buf<T>(
+    mut inner: Pin<&mut T>,
+    cx: &mut Context<'_>,
+    mapper: &ErrorMapper,
+    buf: &mut ReadBuf<'_>,
+) -> Poll<Result<(), Error>>
+where
+    T: TokioAsyncRead,
+{
+    poll_retry_interrupted(mapper, || TokioAsyncRead::poll_read(inner.as_mut(), cx, buf))
+}
+
+/// Refills the internal read buffer from the inner reader, unless it still holds data.
+fn poll_fill_read_buf<T>(
+    inner: Pin<&mut T>,
+    cx: &mut Context<'_>,
+    state: &mut CompatState,
+) -> Poll<Result<(), Error>>
+where
+    T: TokioAsyncRead,
+{
+    if state.has_buffered_data() {
+        return Poll::Ready(Ok(()));
+    }
+
+    state.discard_read_buffer();
+
+    let mut read_buf = ReadBuf::uninit(state.read_buf.spare_capacity_mut());
+
+    ready!(poll_read_buf(inner, cx, &state.error_mapper, &mut read_buf))?;
+
+    let filled_len = read_buf.filled().len();
+
+    // SAFETY: The first `filled_len` bytes of the spare capacity have been initialized by the
+    // inner reader.
+    unsafe { state.read_buf.set_len(filled_len) };
+
+    Poll::Ready(Ok(()))
+}
+
+impl<T> AsyncRead for TokioCompat<T>
+where
+    T: TokioAsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8]
+    ) -> Poll<Result<usize, Error>> {
+        let (inner, state) = self.project();
+
+        // Bypass the internal buffer if it is empty and would not save us any reads.
+        if !state.has_buffered_data() && buf.len() >= state.read_buf.capacity() {
+            let mut buf = ReadBuf::new(buf);
+
+            return match poll_read_buf(inner, cx, &state.error_mapper, &mut buf) {
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(Ok(())) => Poll::Ready(Ok(buf.filled().len())),
+                Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            };
+        }
+
+        ready!(poll_fill_read_buf(inner, cx, state))?;
+
+        let available = state.buffered();
+        let n = available.len().min(buf.len());
+
+        buf[..n].copy_from_slice(&available[..n]);
+        state.consume_buffered(n);
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>]
+    ) -> Poll<Result<usize, Error>> {
+        let total: usize = bufs.iter().map(|buf| buf.len()).sum();
+
+        // tokio has no vectored reads. If a single buffer is large enough we read directly into
+        // it, otherwise we read into the internal buffer and spread its data across all buffers.
+        if total < self.state.read_buf.capacity() || self.state.has_buffered_data() {
+            let (inner, state) = self.project();
+
+            ready!(poll_fill_read_buf(inner, cx, state))?;
+
+            let n = super::vectored::copy_to_slices(state.buffered(), bufs);
+            state.consume_buffered(n);
+
+            return Poll::Ready(Ok(n));
+        }
+
+        match bufs.iter_mut().find(|buf| !buf.is_empty()) {
+            Some(buf) => AsyncRead::poll_read(self, cx, buf),
+            None => AsyncRead::poll_read(self, cx, &mut []),
+        }
+    }
+}
+
+impl<T> AsyncBufRead for TokioCompat<T>
+where
+    T: TokioAsyncBufRead,
+{
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<&[u8], Error>> {
+        let (mut inner, state) = self.project();
+
+        if state.has_buffered_data() {
+            return Poll::Ready(Ok(state.buffered()));
+        }
+
+        // The returned slice borrows the inner object, so the retry can not be expressed with
+        // `poll_retry_interrupted`. Polling a second time after a successful poll is cheap, as
+        // the data is already buffered by the inner object.
+        let result = TokioAsyncBufRead::poll_fill_buf(inner.as_mut(), cx);
+        match result {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Ok([])) => return Poll::Ready(Ok(&[])),
+            Poll::Ready(Ok(_)) => {}
+            Poll::Ready(Err(err)) => match state.error_mapper.map(err) {
+                err if err.kind() == ErrorKind::Interrupted => {}
+                err => return Poll::Ready(Err(err)),
+            },
+        }
+
+        match TokioAsyncBufRead::poll_fill_buf(inner, cx) {
+            Poll::Ready(Err(err)) => Poll::Ready(Err(state.error_mapper.map(err))),
+            result => result,
+        }
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let (inner, state) = self.project();
+
+        if state.has_buffered_data() {
+            state.consume_buffered(amt);
+
+            return;
+        }
+
+        TokioAsyncBufRead::consume(inner, amt)
+    }
+}
+
+impl<T> AsyncWrite for TokioCompat<T>
+where
+    T: TokioAsyncWrite,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8]
+    ) -> Poll<Result<usize, Error>> {
+        let (mut inner, state) = self.project();
+
+        poll_retry_interrupted(&state.error_mapper, || TokioAsyncWrite::poll_write(inner.as_mut(), cx, buf))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>]
+    ) -> Poll<Result<usize, Error>> {
+        let (mut inner, state) = self.project();
+
+        poll_retry_interrupted(&state.error_mapper, || TokioAsyncWrite::poll_write_vectored(inner.as_mut(), cx, bufs))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>
+    ) -> Poll<Result<(), Error>> {
+        let (mut inner, state) = self.project();
+
+        poll_retry_interrupted(&state.error_mapper, || TokioAsyncWrite::poll_flush(inner.as_mut(), cx))
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>
+    ) -> Poll<Result<(), Error>> {
+        let (mut inner, state) = self.project();
+
+        poll_retry_interrupted(&state.error_mapper, || TokioAsyncWrite::poll_shutdown(inner.as_mut(), cx))
+    }
+}
+
+impl<T> AsyncSeek for TokioCompat<T>
+where
+    T: TokioAsyncSeek,
+{
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<Result<u64, Error>> {
+        let (mut inner, state) = self.project();
+        let mut retried = false;
+
+        loop {
+            if state.seek_pos.is_none() {
+                // The inner position is ahead of ours by the amount of buffered data.
+                let buffered = state.buffered().len() as i64;
+                let inner_pos = match pos {
+                    SeekFrom::Current(offset) => SeekFrom::Current(offset - buffered),
+                    pos => pos,
+                };
+
+                state.discard_read_buffer();
+
+                if let Err(err) = inner.as_mut().start_seek(inner_pos) {
+                    return Poll::Ready(Err(state.error_mapper.map(err)));
+                }
+
+                state.seek_pos = Some(pos);
+            }
+
+            let result = match TokioAsyncSeek::poll_complete(inner.as_mut(), cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(result) => result.map_err(|err| state.error_mapper.map(err)),
+            };
+
+            // A seek to a different position was started by an earlier call. It had to be
+            // completed before the inner object accepts a new one, so we start ours now.
+            if state.seek_pos.take() != Some(pos) {
+                continue;
+            }
+
+            // The failed seek is over, so retrying means starting it again rather than polling
+            // for its completion, which would report the old position.
+            match result {
+                Err(err) if err.kind() == ErrorKind::Interrupted && !retried => retried = true,
+                result => return Poll::Ready(result),
+            }
+        }
+    }
+}
+
+
+
+// The wrapper also implements tokio's traits by passing the calls through, so it can be handed back
+// to tokio based code. Data in the internal read buffer is served first, so both sides observe the
+// same byte order.
+
+impl<T> TokioAsyncRead for TokioCompat<T>
+where
+    T: TokioAsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), Error>> {
+        let (inner, state) = self.project();
+
+        if state.has_buffered_data() {
+            let available = state.buffered();
+            let n = available.len().min(buf.remaining());
+
+            buf.put_slice(&available[..n]);
+            state.consume_buffered(n);
+
+            return Poll::Ready(Ok(()));
+        }
+
+        TokioAsyncRead::poll_read(inner, cx, buf)
+    }
+}
+
+impl<T> TokioAsyncBufRead for TokioCompat<T>
+where
+    T: TokioAsyncBufRead,
+{
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<&[u8], Error>> {
+        let (inner, state) = self.project();
+
+        if state.has_buffered_data() {
+            return Poll::Ready(Ok(state.buffered()));
+        }
+
+        TokioAsyncBufRead::poll_fill_buf(inner, cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        AsyncBufRead::consume(self, amt)
+    }
+}
+
+impl<T> TokioAsyncWrite for TokioCompat<T>
+where
+    T: TokioAsyncWrite,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Error>> {
+        TokioAsyncWrite::poll_write(self.project().0, cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, Error>> {
+        TokioAsyncWrite::poll_write_vectored(self.project().0, cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Error>> {
+        TokioAsyncWrite::poll_flush(self.project().0, cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Error>> {
+        TokioAsyncWrite::poll_shutdown(self.project().0, cx)
+    }
+}
+
+/// Seeking through tokio's trait and through the futures trait may be mixed, but only between
+/// completed seeks. Starting a seek through tokio's trait while one started through
+/// [`AsyncSeek::poll_seek`] is still pending fails.
+impl<T> TokioAsyncSeek for TokioCompat<T>
+where
+    T: TokioAsyncSeek,
+{
+    fn start_seek(
+        self: Pin<&mut Self>,
+        pos: SeekFrom,
+    ) -> Result<(), Error> {
+        let (inner, state) = self.project();
+
+        if state.seek_pos.is_some() {
+            return Err(Error::other("start_seek called while another seek is in progress"));
+        }
+
+        // The inner position is ahead of ours by the amount of buffered data.
+        let buffered = state.buffered().len() as i64;
+        let pos = match pos {
+            SeekFrom::Current(offset) => SeekFrom::Current(offset - buffered),
+            pos => pos,
+        };
+
+        state.discard_read_buffer();
+
+        inner.start_seek(pos)
+    }
+
+    fn poll_complete(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<u64, Error>> {
+        let (inner, state) = self.project();
+
+        if state.seek_pos.is_some() {
+            return Poll::Ready(Err(Error::other("a seek started through the futures trait is in progress")));
+        }
+
+        // Without a pending seek tokio returns the current position, which is ahead of ours by
+        // the amount of buffered data.
+        let buffered = state.buffered().len() as u64;
+
+        match TokioAsyncSeek::poll_complete(inner, cx) {
+            Poll::Ready(Ok(pos)) => Poll::Ready(Ok(pos.saturating_sub(buffered))),
+            poll => poll,
+        }
+    }
+}
+
+
+
+/// Objects of [`tokio`](https://docs.rs/tokio) which support io through a shared reference.
+///
+/// Implementing this trait enables the futures io traits for `&TokioCompat<T>`, so e.g. an
+/// `Arc<TokioCompat<tokio::net::TcpStream>>` can be read in one task while being written in
+/// another, without splitting it. tokio does not implement its own io traits for shared
+/// references, so the io is performed through the readiness based methods instead.
+///
+/// Reads through a shared reference first drain the internal read buffer of [`TokioCompat`], so
+/// data buffered by previous reads through the owned wrapper is not lost. The buffer is never
+/// refilled through a shared reference.
+pub trait TokioSharedIo {
+    /// Polls for read readiness, like [`tokio::net::TcpStream::poll_read_ready`](::tokio::net::TcpStream::poll_read_ready).
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>>;
+
+    /// Tries to read data without waiting, like [`tokio::net::TcpStream::try_read`](::tokio::net::TcpStream::try_read).
+    fn try_read(&self, buf: &mut [u8]) -> std::io::Result<usize>;
+
+    /// Polls for write readiness, like [`tokio::net::TcpStream::poll_write_ready`](::tokio::net::TcpStream::poll_write_ready).
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>>;
+
+    /// Tries to write data without waiting, like [`tokio::net::TcpStream::try_write`](::tokio::net::TcpStream::try_write).
+    fn try_write(&self, buf: &[u8]) -> std::io::Result<usize>;
+
+    /// Tries to write data from several buffers without waiting, like
+    /// [`tokio::net::TcpStream::try_write_vectored`](::tokio::net::TcpStream::try_write_vectored).
+    fn try_write_vectored(&self, bufs: &[IoSlice<'_>]) -> std::io::Result<usize>;
+
+    /// Shuts down the write half of the object.
+    fn shutdown_write(&self) -> std::io::Result<()>;
+}
+
+impl TokioSharedIo for ::tokio::net::TcpStream {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        ::tokio::net::TcpStream::poll_read_ready(self, cx)
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        ::tokio::net::TcpStream::try_read(self, buf)
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        ::tokio::net::TcpStream::poll_write_ready(self, cx)
+    }
+
+    fn try_write(&self, buf: &[u8]) -> std::io::Result<usize> {
+        ::tokio::net::TcpStream::try_write(self, buf)
+    }
+
+    fn try_write_vectored(&self, bufs: &[IoSlice<'_>]) -> std::io::Result<usize> {
+        ::tokio::net::TcpStream::try_write_vectored(self, bufs)
+    }
+
+    fn shutdown_write(&self) -> std::io::Result<()> {
+        // tokio only offers shutting down through a mutable reference, so we borrow the socket as
+        // a std stream instead.
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::FromRawFd;
+
+            // SAFETY: The file descriptor stays open while it is borrowed, and `ManuallyDrop`
+            // prevents the std stream from closing it.
+            let stream = std::mem::ManuallyDrop::new(unsafe { std::net::TcpStream::from_raw_fd(self.as_raw_fd()) });
+
+            stream.shutdown(std::net::Shutdown::Write)
+        }
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::io::{AsRawSocket, FromRawSocket};
+
+            // SAFETY: The socket stays open while it is borrowed, and `ManuallyDrop` prevents the
+            // std stream from closing it.
+            let stream = std::mem::ManuallyDrop::new(unsafe { std::net::TcpStream::from_raw_socket(self.as_raw_socket()) });
+
+            stream.shutdown(std::net::Shutdown::Write)
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        {
+            Err(Error::new(ErrorKind::Unsupported, "shutting down through a shared reference is not supported on this platform"))
+        }
+    }
+}
+
+#[cfg(unix)]
+impl TokioSharedIo for ::tokio::net::UnixStream {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        ::tokio::net::UnixStream::poll_read_ready(self, cx)
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        ::tokio::net::UnixStream::try_read(self, buf)
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        ::tokio::net::UnixStream::poll_write_ready(self, cx)
+    }
+
+    fn try_write(&self, buf: &[u8]) -> std::io::Result<usize> {
+        ::tokio::net::UnixStream::try_write(self, buf)
+    }
+
+    fn try_write_vectored(&self, bufs: &[IoSlice<'_>]) -> std::io::Result<usize> {
+        ::tokio::net::UnixStream::try_write_vectored(self, bufs)
+    }
+
+    fn shutdown_write(&self) -> std::io::Result<()> {
+        use std::os::unix::io::FromRawFd;
+
+        // SAFETY: The file descriptor stays open while it is borrowed, and `ManuallyDrop`
+        // prevents the std stream from closing it.
+        let stream = std::mem::ManuallyDrop::new(unsafe { std::os::unix::net::UnixStream::from_raw_fd(self.as_raw_fd()) });
+
+        stream.shutdown(std::net::Shutdown::Write)
+    }
+}
+
+/// Waits for readiness with `poll_ready` and performs `op`, until `op` stops failing with
+/// [`ErrorKind::WouldBlock`].
+///
+/// The errors are converted like with all other methods of [`TokioCompat`].
+fn poll_shared_io<R>(
+    mapper: &ErrorMapper,
+    mut poll_ready: impl FnMut() -> Poll<Result<(), Error>>,
+    mut op: impl FnMut() -> Result<R, Error>,
+) -> Poll<Result<R, Error>> {
+    loop {
+        if let Err(err) = ready!(poll_ready()) {
+            return Poll::Ready(Err(mapper.map(err)));
+        }
+
+        // A `WouldBlock` error of the inner object cleared the readiness, so we have to register
+        // for it again. This is checked before mapping the error, as `poll_ready` registers the
+        // waker for exactly this case.
+        let op = || match op() {
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Poll::Pending,
+            result => Poll::Ready(result),
+        };
+
+        match poll_retry_interrupted(mapper, op) {
+            Poll::Pending => continue,
+            result => return result,
+        }
+    }
+}
+
+impl<T> AsyncRead for &TokioCompat<T>
+where
+    T: TokioSharedIo,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8]
+    ) -> Poll<Result<usize, Error>> {
+        let this: &TokioCompat<T> = *self;
+
+        let n = this.state.take_buffered(buf);
+        if n > 0 {
+            return Poll::Ready(Ok(n));
+        }
+
+        poll_shared_io(&this.state.error_mapper, || this.inner.poll_read_ready(cx), || this.inner.try_read(buf))
+    }
+}
+
+impl<T> AsyncWrite for &TokioCompat<T>
+where
+    T: TokioSharedIo,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8]
+    ) -> Poll<Result<usize, Error>> {
+        let this: &TokioCompat<T> = *self;
+
+        poll_shared_io(&this.state.error_mapper, || this.inner.poll_write_ready(cx), || this.inner.try_write(buf))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>]
+    ) -> Poll<Result<usize, Error>> {
+        let this: &TokioCompat<T> = *self;
+
+        poll_shared_io(&this.state.error_mapper, || this.inner.poll_write_ready(cx), || this.inner.try_write_vectored(bufs))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>
+    ) -> Poll<Result<(), Error>> {
+        // Sockets do not buffer any data in userspace.
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>
+    ) -> Poll<Result<(), Error>> {
+        let this: &TokioCompat<T> = *self;
+
+        Poll::Ready(this.inner.shutdown_write().map_err(|err| this.state.error_mapper.map(err)))
+    }
+}
+
+
+
+/// Provides compatibility between objects implementing the async io traits defined by the
+/// [`futures`](https://docs.rs/futures) crate and the corresponding traits of
+/// [`tokio`](https://docs.rs/tokio).
+///
+/// This is the counterpart of [`TokioCompat`]. It allows e.g. an
+/// [`async_std::net::TcpStream`](https://docs.rs/async-std/latest/async_std/net/struct.TcpStream.html)
+/// to be used with a library which requires [`tokio::io::AsyncRead`](::tokio::io::AsyncRead) and
+/// [`tokio::io::AsyncWrite`](::tokio::io::AsyncWrite).
+#[cfg(feature = "tokio-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
+pub struct FuturesCompat<T> {
+    inner: T,
+    seek_pos: Option<SeekFrom>,
+}
+
+impl<T> FuturesCompat<T> {
     /// Creates a new instance by wrapping the `inner` object.
     pub fn new(inner: T) -> Self {
         Self {
             inner,
-            seek_in_progress: false,
+            seek_pos: None,
         }
     }
 
@@ -42,49 +936,42 @@ impl<T> TokioCompat<T> {
         &mut self.inner
     }
 
-    /// Consumes the `TokioCompat` object and returns the wrapped object.
+    /// Consumes the `FuturesCompat` object and returns the wrapped object.
     pub fn into_inner(self) -> T {
         self.inner
     }
 }
 
-impl<T> AsyncRead for TokioCompat<T>
+impl<T> TokioAsyncRead for FuturesCompat<T>
 where
-    T: TokioAsyncRead + Unpin,
+    T: AsyncRead + Unpin,
 {
     fn poll_read(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
-        buf: &mut [u8]
-    ) -> Poll<Result<usize, Error>> {
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), Error>> {
         let inner = Pin::into_inner(self);
 
         let inner = Pin::new(&mut inner.inner);
 
-        let mut buf = ReadBuf::new(buf);
-        let filled_len = buf.filled().len();
+        let unfilled = buf.initialize_unfilled();
 
-        match TokioAsyncRead::poll_read(inner, cx, &mut buf) {
-            Poll::Pending => return Poll::Pending,
-            Poll::Ready(Ok(())) => {
-                let filled_len = buf.filled().len()-filled_len;
+        match AsyncRead::poll_read(inner, cx, unfilled) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(n)) => {
+                buf.advance(n);
 
-                return Poll::Ready(Ok(filled_len));
-            }
-            Poll::Ready(Err(err)) => {
-                match err.kind() {
-                    ErrorKind::WouldBlock => return Poll::Pending,
-                    ErrorKind::Interrupted => return Poll::Ready(Err(Error::new(ErrorKind::Other, "Interrupted."))),
-                    _ => return Poll::Ready(Err(err))
-                }
+                Poll::Ready(Ok(()))
             }
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
         }
     }
 }
 
-impl<T> AsyncBufRead for TokioCompat<T>
+impl<T> TokioAsyncBufRead for FuturesCompat<T>
 where
-    T: TokioAsyncBufRead + Unpin,
+    T: AsyncBufRead + Unpin,
 {
     fn poll_fill_buf(
         self: Pin<&mut Self>,
@@ -94,17 +981,7 @@ where
 
         let inner = Pin::new(&mut inner.inner);
 
-        match TokioAsyncBufRead::poll_fill_buf(inner, cx) {
-            Poll::Pending => return Poll::Pending,
-            Poll::Ready(Ok(buf)) => Poll::Ready(Ok(buf)),
-            Poll::Ready(Err(err)) => {
-                match err.kind() {
-                    ErrorKind::WouldBlock => return Poll::Pending,
-                    ErrorKind::Interrupted => return Poll::Ready(Err(Error::new(ErrorKind::Other, "Interrupted."))),
-                    _ => return Poll::Ready(Err(err))
-                }
-            }
-        }
+        AsyncBufRead::poll_fill_buf(inner, cx)
     }
 
     fn consume(self: Pin<&mut Self>, amt: usize) {
@@ -112,114 +989,671 @@ where
 
         let inner = Pin::new(&mut inner.inner);
 
-        TokioAsyncBufRead::consume(inner, amt)
+        AsyncBufRead::consume(inner, amt)
     }
 }
 
-impl<T> AsyncWrite for TokioCompat<T>
+impl<T> TokioAsyncWrite for FuturesCompat<T>
 where
-    T: TokioAsyncWrite + Unpin,
+    T: AsyncWrite + Unpin,
 {
     fn poll_write(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
-        buf: &[u8]
+        buf: &[u8],
     ) -> Poll<Result<usize, Error>> {
         let inner = Pin::into_inner(self);
 
         let inner = Pin::new(&mut inner.inner);
 
-        match TokioAsyncWrite::poll_write(inner, cx, buf) {
-            Poll::Pending => return Poll::Pending,
-            Poll::Ready(Ok(n)) => Poll::Ready(Ok(n)),
-            Poll::Ready(Err(err)) => {
-                match err.kind() {
-                    ErrorKind::WouldBlock => return Poll::Pending,
-                    ErrorKind::Interrupted => return Poll::Ready(Err(Error::new(ErrorKind::Other, "Interrupted."))),
-                    _ => return Poll::Ready(Err(err))
-                }
-            }
-        }
+        AsyncWrite::poll_write(inner, cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, Error>> {
+        let inner = Pin::into_inner(self);
+
+        let inner = Pin::new(&mut inner.inner);
+
+        AsyncWrite::poll_write_vectored(inner, cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        // The futures traits provide no way of asking, so we always forward vectored writes.
+        true
     }
 
     fn poll_flush(
         self: Pin<&mut Self>,
-        cx: &mut Context<'_>
+        cx: &mut Context<'_>,
     ) -> Poll<Result<(), Error>> {
         let inner = Pin::into_inner(self);
 
         let inner = Pin::new(&mut inner.inner);
 
-        match TokioAsyncWrite::poll_flush(inner, cx) {
-            Poll::Pending => return Poll::Pending,
-            Poll::Ready(Ok(())) => Poll::Ready(Ok(())),
-            Poll::Ready(Err(err)) => {
-                match err.kind() {
-                    ErrorKind::WouldBlock => return Poll::Pending,
-                    ErrorKind::Interrupted => return Poll::Ready(Err(Error::new(ErrorKind::Other, "Interrupted."))),
-                    _ => return Poll::Ready(Err(err))
-                }
-            }
-        }
+        AsyncWrite::poll_flush(inner, cx)
     }
 
-    fn poll_close(
+    fn poll_shutdown(
         self: Pin<&mut Self>,
-        cx: &mut Context<'_>
+        cx: &mut Context<'_>,
     ) -> Poll<Result<(), Error>> {
         let inner = Pin::into_inner(self);
 
         let inner = Pin::new(&mut inner.inner);
 
-        match TokioAsyncWrite::poll_shutdown(inner, cx) {
-            Poll::Pending => return Poll::Pending,
-            Poll::Ready(Ok(())) => Poll::Ready(Ok(())),
-            Poll::Ready(Err(err)) => {
-                match err.kind() {
-                    ErrorKind::WouldBlock => return Poll::Pending,
-                    ErrorKind::Interrupted => return Poll::Ready(Err(Error::new(ErrorKind::Other, "Interrupted."))),
-                    _ => return Poll::Ready(Err(err))
-                }
-            }
-        }
+        AsyncWrite::poll_close(inner, cx)
     }
 }
 
-impl<T> AsyncSeek for TokioCompat<T>
+impl<T> TokioAsyncSeek for FuturesCompat<T>
 where
-    T: TokioAsyncSeek + Unpin,
+    T: AsyncSeek + Unpin,
 {
-    fn poll_seek(
+    fn start_seek(
         self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
         pos: SeekFrom,
+    ) -> Result<(), Error> {
+        let inner = Pin::into_inner(self);
+
+        if inner.seek_pos.is_some() {
+            return Err(Error::other("start_seek called while another seek is in progress"));
+        }
+
+        inner.seek_pos = Some(pos);
+
+        Ok(())
+    }
+
+    fn poll_complete(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
     ) -> Poll<Result<u64, Error>> {
         let inner = Pin::into_inner(self);
 
-        if !inner.seek_in_progress {
-            if let Err(err) = Pin::new(&mut inner.inner).start_seek(pos) {
-                return Poll::Ready(Err(err));
+        // Without a pending seek tokio expects the current position to be returned.
+        let pos = inner.seek_pos.unwrap_or(SeekFrom::Current(0));
+
+        match AsyncSeek::poll_seek(Pin::new(&mut inner.inner), cx, pos) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(result) => {
+                inner.seek_pos = None;
+
+                Poll::Ready(result)
             }
+        }
+    }
+}
+
+
+
+/// [`tokio`](https://docs.rs/tokio)'s abstraction of the standard streams.
+#[cfg(feature = "tokio-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TokioStdio {}
+
+impl super::Stdio for TokioStdio {
+    type Stdin = TokioCompat<::tokio::io::Stdin>;
+    type Stdout = TokioCompat<::tokio::io::Stdout>;
+    type Stderr = TokioCompat<::tokio::io::Stderr>;
+
+    fn stdin() -> Self::Stdin {
+        TokioCompat::new(::tokio::io::stdin())
+    }
+
+    fn stdout() -> Self::Stdout {
+        TokioCompat::new(::tokio::io::stdout())
+    }
+
+    fn stderr() -> Self::Stderr {
+        TokioCompat::new(::tokio::io::stderr())
+    }
+}
+
+
+
+#[cfg(all(unix, feature = "zero-copy"))]
+#[async_trait::async_trait]
+impl super::SendfileSocket for ::tokio::net::TcpStream {
+    async fn write_with<R, F>(&self, op: F) -> std::io::Result<R>
+    where
+        F: FnMut() -> std::io::Result<R> + Send,
+        R: Send,
+    {
+        self.async_io(::tokio::io::Interest::WRITABLE, op).await
+    }
+}
+
+#[cfg(all(unix, feature = "zero-copy"))]
+#[async_trait::async_trait]
+impl super::SendfileSocket for ::tokio::net::UnixStream {
+    async fn write_with<R, F>(&self, op: F) -> std::io::Result<R>
+    where
+        F: FnMut() -> std::io::Result<R> + Send,
+        R: Send,
+    {
+        self.async_io(::tokio::io::Interest::WRITABLE, op).await
+    }
+}
+
+#[cfg(all(unix, feature = "zero-copy"))]
+#[async_trait::async_trait]
+impl<T> super::SendfileSocket for TokioCompat<T>
+where
+    T: super::SendfileSocket + Sync,
+{
+    async fn write_with<R, F>(&self, op: F) -> std::io::Result<R>
+    where
+        F: FnMut() -> std::io::Result<R> + Send,
+        R: Send,
+    {
+        self.inner.write_with(op).await
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+    use futures::FutureExt;
+
+    use crate::io::{ScriptAction, ScriptedIo};
+
+    fn interrupted() -> ScriptAction {
+        ScriptAction::Err(ErrorKind::Interrupted)
+    }
+
+    #[test]
+    fn read_retries_interrupted_once() {
+        let mut io = TokioCompat::new(ScriptedIo::new([interrupted(), ScriptAction::Read(b"hello".to_vec())]));
+
+        let mut buf = [0; 8];
+        let n = block_on(io.read(&mut buf)).unwrap();
+
+        assert_eq!(&buf[..n], b"hello");
+        io.get_ref().assert_done();
+    }
+
+    #[test]
+    fn write_flush_and_close_retry_interrupted_once() {
+        // Flushing and closing succeed without consuming anything, so each retry succeeds.
+        let mut io = TokioCompat::new(ScriptedIo::new([interrupted(), ScriptAction::ShortWrite(5), interrupted()]));
+
+        block_on(async {
+            assert_eq!(io.write(b"hello").await.unwrap(), 5);
+            io.flush().await.unwrap();
+
+            io.get_mut().push(interrupted());
+            io.close().await.unwrap();
+        });
+
+        assert_eq!(io.get_ref().written(), b"hello");
+        io.get_ref().assert_done();
+    }
+
+    #[test]
+    fn seek_retries_interrupted_once() {
+        let mut io = TokioCompat::new(ScriptedIo::new([interrupted(), ScriptAction::Seek(7)]));
+
+        assert_eq!(block_on(io.seek(SeekFrom::Start(7))).unwrap(), 7);
+        assert_eq!(io.get_ref().seeks(), [SeekFrom::Start(7), SeekFrom::Start(7)]);
+        io.get_ref().assert_done();
+    }
+
+    fn poll_seek<T: TokioAsyncSeek + Unpin>(io: &mut TokioCompat<T>, pos: SeekFrom) -> Poll<std::io::Result<u64>> {
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        AsyncSeek::poll_seek(Pin::new(io), &mut cx, pos)
+    }
+
+    #[test]
+    fn pending_seek_completes_when_polled_again_with_the_same_position() {
+        let mut io = TokioCompat::new(ScriptedIo::new([ScriptAction::Pending, ScriptAction::Seek(3)]));
+
+        assert!(poll_seek(&mut io, SeekFrom::Start(3)).is_pending());
+        assert_eq!(poll_seek(&mut io, SeekFrom::Start(3)).map(Result::unwrap), Poll::Ready(3));
 
-            inner.seek_in_progress = true;
+        assert_eq!(io.get_ref().seeks(), [SeekFrom::Start(3)]);
+        io.get_ref().assert_done();
+    }
+
+    #[test]
+    fn pending_seek_is_followed_by_the_new_position() {
+        let script = [ScriptAction::Pending, ScriptAction::Seek(3), ScriptAction::Seek(9)];
+        let mut io = TokioCompat::new(ScriptedIo::new(script));
+
+        assert!(poll_seek(&mut io, SeekFrom::Start(3)).is_pending());
+
+        // The pending seek gets completed first, then the new one is started.
+        assert_eq!(poll_seek(&mut io, SeekFrom::Start(9)).map(Result::unwrap), Poll::Ready(9));
+
+        assert_eq!(io.get_ref().seeks(), [SeekFrom::Start(3), SeekFrom::Start(9)]);
+        io.get_ref().assert_done();
+    }
+
+    #[test]
+    fn mixed_seeks_agree_on_the_position() {
+        let mut io = TokioCompat::with_read_buffer_capacity(std::io::Cursor::new(b"0123456789".to_vec()), 4);
+
+        block_on(async {
+            // The futures read buffers 4 bytes, which the tokio side has to account for.
+            let mut buf = [0; 2];
+            AsyncReadExt::read_exact(&mut io, &mut buf).await.unwrap();
+            assert_eq!(&buf, b"01");
+            assert_eq!(::tokio::io::AsyncSeekExt::stream_position(&mut io).await.unwrap(), 2);
+
+            assert_eq!(::tokio::io::AsyncSeekExt::seek(&mut io, SeekFrom::Current(1)).await.unwrap(), 3);
+            ::tokio::io::AsyncReadExt::read_exact(&mut io, &mut buf).await.unwrap();
+            assert_eq!(&buf, b"34");
+
+            assert_eq!(AsyncSeekExt::seek(&mut io, SeekFrom::Current(2)).await.unwrap(), 7);
+            AsyncReadExt::read_exact(&mut io, &mut buf).await.unwrap();
+            assert_eq!(&buf, b"78");
+
+            assert_eq!(::tokio::io::AsyncSeekExt::seek(&mut io, SeekFrom::End(-3)).await.unwrap(), 7);
+            let mut rest = Vec::new();
+            ::tokio::io::AsyncReadExt::read_to_end(&mut io, &mut rest).await.unwrap();
+            assert_eq!(rest, b"789");
+        });
+    }
+
+    #[test]
+    fn tokio_seeks_are_refused_while_a_futures_seek_is_pending() {
+        let mut io = TokioCompat::new(ScriptedIo::new([ScriptAction::Pending, ScriptAction::Seek(3)]));
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        assert!(poll_seek(&mut io, SeekFrom::Start(3)).is_pending());
+
+        assert!(Pin::new(&mut io).start_seek(SeekFrom::Start(5)).is_err());
+        assert!(matches!(TokioAsyncSeek::poll_complete(Pin::new(&mut io), &mut cx), Poll::Ready(Err(_))));
+
+        // The futures seek is not affected by the refused ones.
+        assert_eq!(poll_seek(&mut io, SeekFrom::Start(3)).map(Result::unwrap), Poll::Ready(3));
+        assert_eq!(io.get_ref().seeks(), [SeekFrom::Start(3)]);
+        io.get_ref().assert_done();
+    }
+
+    #[test]
+    fn fill_buf_retries_interrupted_once() {
+        let inner = ::tokio::io::BufReader::new(ScriptedIo::new([interrupted(), ScriptAction::Read(b"hello".to_vec())]));
+        let mut io = TokioCompat::new(inner);
+
+        assert_eq!(block_on(io.fill_buf()).unwrap(), b"hello");
+        io.get_ref().get_ref().assert_done();
+    }
+
+    #[test]
+    fn second_interrupted_is_returned_with_its_kind() {
+        let mut io = TokioCompat::new(ScriptedIo::new([interrupted(), interrupted()]));
+
+        let err = block_on(io.read(&mut [0; 8])).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn would_block_is_returned_instead_of_pending() {
+        let mut io = TokioCompat::new(ScriptedIo::new([ScriptAction::Err(ErrorKind::WouldBlock)]));
+
+        let result = io.read(&mut [0; 8]).now_or_never().expect("the read must not return `Pending`");
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::WouldBlock);
+    }
+
+    /// Tags every error with the text `mapped`.
+    fn tagging_mapper(err: Error) -> Error {
+        Error::new(err.kind(), format!("mapped: {err}"))
+    }
+
+    fn assert_tagged<T: std::fmt::Debug>(result: std::io::Result<T>, kind: ErrorKind) {
+        let err = result.unwrap_err();
+
+        assert_eq!(err.kind(), kind);
+        assert!(err.to_string().starts_with("mapped: "), "the error was not mapped: {err}");
+    }
+
+    #[test]
+    fn error_mapper_is_applied_to_every_operation() {
+        let script = [
+            ScriptAction::Err(ErrorKind::ConnectionReset),
+            ScriptAction::Err(ErrorKind::BrokenPipe),
+            ScriptAction::Err(ErrorKind::PermissionDenied),
+            ScriptAction::Err(ErrorKind::NotConnected),
+            ScriptAction::Err(ErrorKind::InvalidInput),
+        ];
+        let mut io = TokioCompat::new(ScriptedIo::new(script)).with_error_mapper(tagging_mapper);
+
+        block_on(async {
+            assert_tagged(io.read(&mut [0; 8]).await, ErrorKind::ConnectionReset);
+            assert_tagged(io.write(b"hello").await, ErrorKind::BrokenPipe);
+            assert_tagged(io.flush().await, ErrorKind::PermissionDenied);
+            assert_tagged(io.close().await, ErrorKind::NotConnected);
+            assert_tagged(io.seek(SeekFrom::Start(3)).await, ErrorKind::InvalidInput);
+        });
+
+        io.get_ref().assert_done();
+    }
+
+    #[test]
+    fn error_mapper_decides_about_retrying() {
+        // Translating `Interrupted` opts out of the retry.
+        let mut io = TokioCompat::new(ScriptedIo::new([interrupted(), ScriptAction::Read(b"hello".to_vec())]))
+            .with_error_mapper(|err| match err.kind() {
+                ErrorKind::Interrupted => Error::other(err),
+                _ => err,
+            });
+
+        let err = block_on(io.read(&mut [0; 8])).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+
+        // Translating another error into `Interrupted` opts in.
+        let mut io = TokioCompat::new(ScriptedIo::new([ScriptAction::Err(ErrorKind::TimedOut), ScriptAction::Read(b"hello".to_vec())]))
+            .with_error_mapper(|err| match err.kind() {
+                ErrorKind::TimedOut => Error::new(ErrorKind::Interrupted, err),
+                _ => err,
+            });
+
+        assert_eq!(block_on(io.read(&mut [0; 8])).unwrap(), 5);
+    }
+
+    /// Records the slices of every write, so tests can check how writes arrive.
+    #[derive(Default)]
+    struct RecordingWriter {
+        writes: Vec<Vec<Vec<u8>>>,
+    }
+
+    impl TokioAsyncWrite for RecordingWriter {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize, Error>> {
+            Pin::into_inner(self).writes.push(vec![buf.to_vec()]);
+
+            Poll::Ready(Ok(buf.len()))
         }
 
-        match TokioAsyncSeek::poll_complete(Pin::new(&mut inner.inner), cx) {
-            Poll::Pending => return Poll::Pending,
-            Poll::Ready(result) => {
-                inner.seek_in_progress = false;
-
-                match result {
-                    Ok(pos) => return Poll::Ready(Ok(pos)),
-                    Err(err) => {
-                        match err.kind() {
-                            ErrorKind::WouldBlock => return Poll::Pending,
-                            ErrorKind::Interrupted => return Poll::Ready(Err(Error::new(ErrorKind::Other, "Interrupted."))),
-                            _ => return Poll::Ready(Err(err))
-                        }
-                    }
-                }
+        fn poll_write_vectored(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            bufs: &[IoSlice<'_>],
+        ) -> Poll<Result<usize, Error>> {
+            Pin::into_inner(self).writes.push(bufs.iter().map(|buf| buf.to_vec()).collect());
+
+            Poll::Ready(Ok(bufs.iter().map(|buf| buf.len()).sum()))
+        }
+
+        fn is_write_vectored(&self) -> bool {
+            true
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn vectored_writes_arrive_as_one_call() {
+        let mut io = TokioCompat::new(RecordingWriter::default());
+        let bufs = [IoSlice::new(b"GET / HTTP/1.1\r\n"), IoSlice::new(b"Host: a\r\n"), IoSlice::new(b"\r\n")];
+
+        let n = block_on(io.write_vectored(&bufs)).unwrap();
+
+        assert_eq!(n, 27);
+        assert_eq!(io.get_ref().writes, [vec![b"GET / HTTP/1.1\r\n".to_vec(), b"Host: a\r\n".to_vec(), b"\r\n".to_vec()]]);
+        assert!(TokioAsyncWrite::is_write_vectored(&io));
+    }
+
+    #[test]
+    fn vectored_reads_spread_one_read_across_the_buffers() {
+        let mut io = TokioCompat::with_read_buffer_capacity(ScriptedIo::new([ScriptAction::Read(b"hello world".to_vec())]), 64);
+
+        let (mut head, mut tail) = ([0; 5], [0; 6]);
+        let n = block_on(io.read_vectored(&mut [IoSliceMut::new(&mut head), IoSliceMut::new(&mut tail)])).unwrap();
+
+        assert_eq!(n, 11);
+        assert_eq!((&head, &tail), (b"hello", b" world"));
+        io.get_ref().assert_done();
+    }
+
+    /// Reads from `read` in chunks of at most `chunk` bytes until the end of the stream and returns
+    /// everything read.
+    fn read_in_chunks<F>(chunk: usize, mut read: F) -> Vec<u8>
+    where
+        F: FnMut(&mut [u8]) -> std::io::Result<usize>,
+    {
+        let mut buf = vec![0; chunk];
+        let mut received = Vec::new();
+
+        loop {
+            let n = read(&mut buf).unwrap();
+            if n == 0 {
+                return received;
             }
+
+            received.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    /// Reads the same data through the adapter with and without a read buffer, in chunks smaller
+    /// than, equal to and not dividing the buffer.
+    #[test]
+    fn chunked_reads_return_every_byte() {
+        let data: Vec<u8> = (0..1024 * 1024 + 17).map(|i: usize| (i % 251) as u8).collect();
+
+        for chunk in [512, 1000, 64 * 1024] {
+            let mut compat = TokioCompat::new(&data[..]);
+            assert!(read_in_chunks(chunk, |buf| block_on(AsyncReadExt::read(&mut compat, buf))) == data);
+
+            let mut buffered = TokioCompat::with_read_buffer_capacity(&data[..], 64 * 1024);
+            assert!(read_in_chunks(chunk, |buf| block_on(AsyncReadExt::read(&mut buffered, buf))) == data);
+        }
+    }
+
+    /// Connects a tcp stream over loopback and returns it together with the accepted peer.
+    async fn tcp_pair() -> (::tokio::net::TcpStream, ::tokio::net::TcpStream) {
+        let listener = ::tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (client, accepted) = futures::join!(::tokio::net::TcpStream::connect(addr), listener.accept());
+
+        (client.unwrap(), accepted.unwrap().0)
+    }
+
+    #[test]
+    fn inherent_methods_are_reachable_through_the_wrapper() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let (client, mut peer) = tcp_pair().await;
+            let mut io = TokioCompat::from(client);
+
+            ::tokio::io::AsyncWriteExt::write_all(&mut peer, b"hello").await.unwrap();
+
+            // `peek` and `local_addr` through `Deref`, `set_nodelay` through `AsMut`.
+            let mut buf = [0; 5];
+            assert_eq!(io.peek(&mut buf).await.unwrap(), 5);
+            assert_eq!(io.local_addr().unwrap(), peer.peer_addr().unwrap());
+            io.as_mut().set_nodelay(true).unwrap();
+            assert!(io.as_ref().nodelay().unwrap());
+
+            // Peeking left the data in place.
+            let mut read = [0; 5];
+            io.read_exact(&mut read).await.unwrap();
+            assert_eq!(&read, b"hello");
+        });
+    }
+
+    /// A reader which must not be moved once pinned, returning `b"pinned"` on every read.
+    #[derive(Default)]
+    struct PinnedReader {
+        _pinned: std::marker::PhantomPinned,
+    }
+
+    impl TokioAsyncRead for PinnedReader {
+        fn poll_read(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<Result<(), Error>> {
+            buf.put_slice(b"pinned");
+
+            Poll::Ready(Ok(()))
         }
     }
+
+    #[test]
+    fn pinned_inner_objects_are_reachable_through_the_projection() {
+        let mut io = Box::pin(TokioCompat::new(PinnedReader::default()));
+
+        block_on(async {
+            let mut buf = [0; 6];
+            io.as_mut().read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"pinned");
+
+            let mut inner: Pin<&mut PinnedReader> = io.as_mut().get_pin_mut();
+            let mut buf = [0; 6];
+            ::tokio::io::AsyncReadExt::read_exact(&mut inner, &mut buf).await.unwrap();
+            assert_eq!(&buf, b"pinned");
+        });
+    }
+
+    #[test]
+    fn shared_reads_drain_the_read_buffer_first() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let (client, mut peer) = tcp_pair().await;
+            let mut io = TokioCompat::with_read_buffer_capacity(client, 64);
+
+            ::tokio::io::AsyncWriteExt::write_all(&mut peer, b"hello world").await.unwrap();
+            ::tokio::io::AsyncWriteExt::shutdown(&mut peer).await.unwrap();
+
+            // The rest of the data which arrived with the first read stays in the read buffer.
+            let mut head = [0; 5];
+            io.read_exact(&mut head).await.unwrap();
+
+            let mut rest = Vec::new();
+            (&io).read_to_end(&mut rest).await.unwrap();
+
+            assert_eq!(&head, b"hello");
+            assert_eq!(rest, b" world");
+        });
+    }
+
+    #[test]
+    fn shared_io_from_two_tasks() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let (client, peer) = tcp_pair().await;
+            let io = std::sync::Arc::new(TokioCompat::with_read_buffer_capacity(client, 64));
+
+            // The peer echoes everything back until the writing task shuts down its side.
+            let echo = ::tokio::spawn(async move {
+                let (mut reader, mut writer) = peer.into_split();
+                ::tokio::io::copy(&mut reader, &mut writer).await.unwrap();
+            });
+
+            let data: Vec<u8> = (0..64 * 1024).map(|i| i as u8).collect();
+
+            let writer = ::tokio::spawn({
+                let io = io.clone();
+                let data = data.clone();
+
+                async move {
+                    (&*io).write_all(&data).await.unwrap();
+                    (&*io).close().await.unwrap();
+                }
+            });
+            let reader = ::tokio::spawn({
+                let io = io.clone();
+
+                async move {
+                    let mut received = Vec::new();
+                    (&*io).read_to_end(&mut received).await.unwrap();
+                    received
+                }
+            });
+
+            writer.await.unwrap();
+            assert_eq!(reader.await.unwrap(), data);
+            echo.await.unwrap();
+        });
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn futures_compat_round_trip_over_async_std() {
+        use ::tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+        ::async_std::task::block_on(async {
+            let listener = ::async_std::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            // The peer echoes everything back until the client shuts down its side.
+            let echo = ::async_std::task::spawn(async move {
+                let (peer, _) = listener.accept().await.unwrap();
+                let (reader, mut writer) = (&peer, &peer);
+
+                futures::io::copy(reader, &mut writer).await.unwrap();
+            });
+
+            let stream = ::async_std::net::TcpStream::connect(addr).await.unwrap();
+            let shutdown = stream.clone();
+            let mut io = FuturesCompat::new(stream);
+
+            let data: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+
+            // Both directions are driven at the same time, as the echo blocks once the socket
+            // buffers are full.
+            let (reader, mut writer) = ::tokio::io::split(&mut io);
+            let write = async {
+                writer.write_all(&data).await.unwrap();
+                writer.shutdown().await.unwrap();
+
+                // Closing an async-std stream only flushes it, so the end has to be sent directly.
+                shutdown.shutdown(std::net::Shutdown::Write).unwrap();
+            };
+            let read = async {
+                let mut reader = reader;
+                let mut received = Vec::new();
+                reader.read_to_end(&mut received).await.unwrap();
+                received
+            };
+
+            let ((), received) = futures::join!(write, read);
+
+            assert!(received == data);
+            echo.await;
+        });
+    }
+
+    #[test]
+    fn futures_compat_seeks_and_reads_lines() {
+        use ::tokio::io::{AsyncBufReadExt as _, AsyncReadExt as _, AsyncSeekExt as _};
+
+        let mut io = FuturesCompat::new(futures::io::Cursor::new(b"first\nsecond\nthird\n".to_vec()));
+
+        block_on(async {
+            assert_eq!(io.seek(SeekFrom::Start(6)).await.unwrap(), 6);
+
+            let mut line = String::new();
+            io.read_line(&mut line).await.unwrap();
+            assert_eq!(line, "second\n");
+
+            assert_eq!(io.seek(SeekFrom::Current(-7)).await.unwrap(), 6);
+            assert_eq!(io.stream_position().await.unwrap(), 6);
+
+            let mut rest = String::new();
+            io.read_to_string(&mut rest).await.unwrap();
+            assert_eq!(rest, "second\nthird\n");
+        });
+
+        // tokio forbids starting a second seek before the first one completed.
+        Pin::new(&mut io).start_seek(SeekFrom::Start(0)).unwrap();
+        assert!(Pin::new(&mut io).start_seek(SeekFrom::Start(1)).is_err());
+    }
 }
@@ -0,0 +1,386 @@
+use std::fmt;
+use std::io::{Error, IoSlice, IoSliceMut, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncBufRead, AsyncWrite, AsyncSeek};
+
+
+
+/// Wraps an io object and emits a [`tracing`](https://docs.rs/tracing) event for every poll.
+///
+/// The events are emitted at the `TRACE` level with the target `fut_compat::io::traced`. They
+/// carry the following fields:
+///
+/// - `label`: the label given to [`new`](Self::new), e.g. the address of a connection.
+/// - `op`: the name of the polled method, e.g. `poll_read`.
+/// - `len`: the length of the buffer passed in, if any.
+/// - `result`: `pending`, `ready` or `error`.
+/// - `bytes`: the number of bytes transferred, or the new position for seeks, if ready.
+/// - `error`: the [`ErrorKind`](std::io::ErrorKind) of an error.
+/// - `payload`: the bytes transferred, only if enabled with [`log_payload`](Self::log_payload).
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::io::{AsyncReadExt, Traced};
+///
+/// let reader = futures::io::Cursor::new(b"hello".to_vec());
+/// let mut reader = Traced::new(reader, "cursor");
+///
+/// let mut buf = Vec::new();
+/// reader.read_to_end(&mut buf).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Traced<T> {
+    inner: T,
+    label: String,
+    log_payload: bool,
+}
+
+impl<T> Traced<T> {
+    /// Creates a new instance by wrapping `inner`, adding `label` to all events.
+    pub fn new(inner: T, label: impl Into<String>) -> Self {
+        Self {
+            inner,
+            label: label.into(),
+            log_payload: false,
+        }
+    }
+
+    /// Sets whether the transferred bytes get added to the events. Disabled by default, as the
+    /// data may be sensitive.
+    pub fn log_payload(mut self, enabled: bool) -> Self {
+        self.log_payload = enabled;
+
+        self
+    }
+
+    /// Returns the label added to all events.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Get a reference to the wrapped io object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped io object.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes the `Traced` object and returns the wrapped io object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Emits the event for a poll of `op` and passes `result` through.
+    ///
+    /// `bytes` and `payload` extract the respective fields from a successful result. `payload` is
+    /// only called if enabled.
+    fn trace<R>(
+        &self,
+        op: &'static str,
+        len: Option<usize>,
+        result: Poll<Result<R, Error>>,
+        bytes: impl FnOnce(&R) -> Option<u64>,
+        payload: impl FnOnce(&R) -> Option<Vec<u8>>,
+    ) -> Poll<Result<R, Error>> {
+        match &result {
+            Poll::Pending => {
+                tracing::trace!(target: "fut_compat::io::traced", label = %self.label, op, len, result = "pending");
+            }
+            Poll::Ready(Ok(value)) => {
+                let bytes = bytes(value);
+                let payload = match self.log_payload {
+                    true => payload(value).map(|payload| tracing::field::debug(Payload(payload))),
+                    false => None,
+                };
+
+                tracing::trace!(target: "fut_compat::io::traced", label = %self.label, op, len, result = "ready", bytes, payload);
+            }
+            Poll::Ready(Err(err)) => {
+                tracing::trace!(target: "fut_compat::io::traced", label = %self.label, op, len, result = "error", error = %err.kind());
+            }
+        }
+
+        result
+    }
+}
+
+/// Formats bytes like a byte string literal.
+struct Payload(Vec<u8>);
+
+impl fmt::Debug for Payload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "b\"{}\"", self.0.escape_ascii())
+    }
+}
+
+/// Collects the first `n` bytes of `bufs`.
+fn collect_slices(bufs: &[&[u8]], n: usize) -> Vec<u8> {
+    bufs.iter()
+        .flat_map(|buf| buf.iter().copied())
+        .take(n)
+        .collect()
+}
+
+impl<T> AsyncRead for Traced<T>
+where
+    T: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8]
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+
+        this.trace("poll_read", Some(buf.len()), result, |n| Some(*n as u64), |n| Some(buf[..*n].to_vec()))
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>]
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        let len = bufs.iter().map(|buf| buf.len()).sum();
+
+        let result = Pin::new(&mut this.inner).poll_read_vectored(cx, bufs);
+
+        this.trace("poll_read_vectored", Some(len), result, |n| Some(*n as u64), |n| {
+            let bufs: Vec<&[u8]> = bufs.iter().map(|buf| &**buf).collect();
+
+            Some(collect_slices(&bufs, *n))
+        })
+    }
+}
+
+impl<T> AsyncBufRead for Traced<T>
+where
+    T: AsyncBufRead + Unpin,
+{
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<&[u8], Error>> {
+        let this = Pin::into_inner(self);
+
+        // The returned slice borrows the inner object, so the event can only carry its length.
+        match Pin::new(&mut this.inner).poll_fill_buf(cx) {
+            Poll::Pending => {
+                tracing::trace!(target: "fut_compat::io::traced", label = %this.label, op = "poll_fill_buf", result = "pending");
+
+                Poll::Pending
+            }
+            Poll::Ready(Ok(buf)) => {
+                let payload = this.log_payload.then(|| tracing::field::debug(Payload(buf.to_vec())));
+
+                tracing::trace!(target: "fut_compat::io::traced", label = %this.label, op = "poll_fill_buf", result = "ready", bytes = buf.len(), payload);
+
+                Poll::Ready(Ok(buf))
+            }
+            Poll::Ready(Err(err)) => {
+                tracing::trace!(target: "fut_compat::io::traced", label = %this.label, op = "poll_fill_buf", result = "error", error = %err.kind());
+
+                Poll::Ready(Err(err))
+            }
+        }
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = Pin::into_inner(self);
+
+        tracing::trace!(target: "fut_compat::io::traced", label = %this.label, op = "consume", len = amt);
+
+        Pin::new(&mut this.inner).consume(amt)
+    }
+}
+
+impl<T> AsyncWrite for Traced<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8]
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+
+        this.trace("poll_write", Some(buf.len()), result, |n| Some(*n as u64), |n| Some(buf[..*n].to_vec()))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>]
+    ) -> Poll<Result<usize, Error>> {
+        let this = Pin::into_inner(self);
+
+        let len = bufs.iter().map(|buf| buf.len()).sum();
+
+        let result = Pin::new(&mut this.inner).poll_write_vectored(cx, bufs);
+
+        this.trace("poll_write_vectored", Some(len), result, |n| Some(*n as u64), |n| {
+            let bufs: Vec<&[u8]> = bufs.iter().map(|buf| &**buf).collect();
+
+            Some(collect_slices(&bufs, *n))
+        })
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>
+    ) -> Poll<Result<(), Error>> {
+        let this = Pin::into_inner(self);
+
+        let result = Pin::new(&mut this.inner).poll_flush(cx);
+
+        this.trace("poll_flush", None, result, |_| None, |_| None)
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>
+    ) -> Poll<Result<(), Error>> {
+        let this = Pin::into_inner(self);
+
+        let result = Pin::new(&mut this.inner).poll_close(cx);
+
+        this.trace("poll_close", None, result, |_| None, |_| None)
+    }
+}
+
+impl<T> AsyncSeek for Traced<T>
+where
+    T: AsyncSeek + Unpin,
+{
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<Result<u64, Error>> {
+        let this = Pin::into_inner(self);
+
+        let result = Pin::new(&mut this.inner).poll_seek(cx, pos);
+
+        this.trace("poll_seek", None, result, |pos| Some(*pos), |_| None)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::ErrorKind;
+
+    use futures::executor::block_on;
+    use futures::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    use crate::capture::Capture;
+    use crate::io::{ScriptAction, ScriptedIo};
+
+    /// Runs `f` with a [`Capture`] as the default subscriber and returns the events of the
+    /// wrapper, with their fields in a fixed order.
+    fn capture(f: impl FnOnce()) -> Vec<String> {
+        let capture = Capture::default();
+
+        tracing::subscriber::with_default(capture.clone(), f);
+
+        capture.events()
+            .into_iter()
+            .filter(|event| event.target == "fut_compat::io::traced")
+            .map(|event| {
+                ["label", "op", "len", "result", "bytes", "error", "payload"]
+                    .iter()
+                    .filter_map(|name| Some(format!("{}={}", name, event.fields.get(name)?)))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exchange_emits_an_event_per_poll() {
+        let mut io = Traced::new(ScriptedIo::new([
+            ScriptAction::Pending,
+            ScriptAction::Read(b"hello".to_vec()),
+            ScriptAction::ShortWrite(2),
+            ScriptAction::ShortWrite(10),
+            ScriptAction::Err(ErrorKind::BrokenPipe),
+        ]), "conn");
+
+        let events = capture(|| {
+            let mut buf = [0u8; 8];
+            assert_eq!(block_on(io.read(&mut buf)).unwrap(), 5);
+
+            block_on(io.write_all(b"hey")).unwrap();
+
+            let err = block_on(io.flush()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+        });
+
+        // The payload is left out by default.
+        assert_eq!(events, [
+            "label=conn op=poll_read len=8 result=pending",
+            "label=conn op=poll_read len=8 result=ready bytes=5",
+            "label=conn op=poll_write len=3 result=ready bytes=2",
+            "label=conn op=poll_write len=1 result=ready bytes=1",
+            "label=conn op=poll_flush result=error error=broken pipe",
+        ]);
+    }
+
+    #[test]
+    fn payload_is_logged_when_enabled() {
+        let mut io = Traced::new(ScriptedIo::new([
+            ScriptAction::Read(b"hi\n".to_vec()),
+            ScriptAction::ShortWrite(2),
+            ScriptAction::Seek(7),
+        ]), "conn").log_payload(true);
+
+        let events = capture(|| {
+            let mut buf = [0u8; 8];
+            block_on(io.read(&mut buf)).unwrap();
+            block_on(io.write(b"\x00ok")).unwrap();
+            block_on(io.seek(SeekFrom::Start(7))).unwrap();
+            block_on(io.close()).unwrap();
+        });
+
+        assert_eq!(events, [
+            "label=conn op=poll_read len=8 result=ready bytes=3 payload=b\"hi\\n\"",
+            "label=conn op=poll_write len=3 result=ready bytes=2 payload=b\"\\x00o\"",
+            "label=conn op=poll_seek result=ready bytes=7",
+            "label=conn op=poll_close result=ready",
+        ]);
+    }
+
+    #[test]
+    fn buffered_reads_report_lengths() {
+        use futures::io::AsyncBufReadExt;
+
+        let mut io = Traced::new(futures::io::Cursor::new(b"abc".to_vec()), "cursor");
+
+        let events = capture(|| {
+            assert_eq!(block_on(io.fill_buf()).unwrap(), b"abc");
+            io.consume_unpin(2);
+        });
+
+        assert_eq!(events, [
+            "label=cursor op=poll_fill_buf result=ready bytes=3",
+            "label=cursor op=consume len=2",
+        ]);
+    }
+}
@@ -0,0 +1,143 @@
+use std::future::Future;
+use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::ready;
+
+
+
+/// The number of bytes [`AsyncReadUtilExt::read_to_vec`] reserves whenever the buffer is full.
+const READ_CHUNK: usize = 8 * 1024;
+
+/// Convenience methods for readers.
+///
+/// Implemented for every type implementing [`AsyncRead`].
+pub trait AsyncReadUtilExt: AsyncRead {
+    /// Reads all bytes until EOF into a new [`Vec`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fut_compat::prelude::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut reader = futures::io::Cursor::new(b"hello world".to_vec());
+    ///
+    /// assert_eq!(reader.read_to_vec().await.unwrap(), b"hello world");
+    /// # });
+    /// ```
+    fn read_to_vec(&mut self) -> ReadToVecFuture<'_, Self>
+    where
+        Self: Unpin,
+    {
+        ReadToVecFuture {
+            reader: self,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl<R: AsyncRead + ?Sized> AsyncReadUtilExt for R {}
+
+/// Convenience methods for writers.
+///
+/// Implemented for every type implementing [`AsyncWrite`].
+pub trait AsyncWriteUtilExt: AsyncWrite {
+    /// Writes all of `buf` and flushes the writer afterwards.
+    ///
+    /// Returns an error of kind [`WriteZero`](ErrorKind::WriteZero) if the writer stops accepting
+    /// bytes before all of them have been written.
+    fn write_all_and_flush<'a>(&'a mut self, buf: &'a [u8]) -> WriteAllAndFlushFuture<'a, Self>
+    where
+        Self: Unpin,
+    {
+        WriteAllAndFlushFuture {
+            writer: self,
+            buf,
+        }
+    }
+}
+
+impl<W: AsyncWrite + ?Sized> AsyncWriteUtilExt for W {}
+
+
+
+/// The future returned by [`AsyncReadUtilExt::read_to_vec`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct ReadToVecFuture<'a, R: ?Sized> {
+    reader: &'a mut R,
+    buf: Vec<u8>,
+}
+
+impl<R> Future for ReadToVecFuture<'_, R>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    type Output = std::io::Result<Vec<u8>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = Pin::into_inner(self);
+
+        loop {
+            let len = this.buf.len();
+            if len == this.buf.capacity() {
+                this.buf.reserve(READ_CHUNK);
+            }
+
+            // The window gets zeroed before reading into it, so it is kept small. Only the bytes
+            // actually read are kept afterwards.
+            let end = this.buf.capacity().min(len + READ_CHUNK);
+            this.buf.resize(end, 0);
+
+            let result = Pin::new(&mut *this.reader).poll_read(cx, &mut this.buf[len..]);
+
+            let n = match &result {
+                Poll::Ready(Ok(n)) => *n,
+                _ => 0,
+            };
+            assert!(len + n <= end, "the reader returned more bytes than requested");
+            this.buf.truncate(len + n);
+
+            match result {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Ok(std::mem::take(&mut this.buf))),
+                Poll::Ready(Ok(_)) => {}
+                Poll::Ready(Err(err)) if err.kind() == ErrorKind::Interrupted => {}
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// The future returned by [`AsyncWriteUtilExt::write_all_and_flush`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct WriteAllAndFlushFuture<'a, W: ?Sized> {
+    writer: &'a mut W,
+    buf: &'a [u8],
+}
+
+impl<W> Future for WriteAllAndFlushFuture<'_, W>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    type Output = std::io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = Pin::into_inner(self);
+
+        while !this.buf.is_empty() {
+            let n = ready!(Pin::new(&mut *this.writer).poll_write(cx, this.buf))?;
+            if n == 0 {
+                return Poll::Ready(Err(Error::new(ErrorKind::WriteZero, "write zero bytes into writer")));
+            }
+
+            this.buf = &this.buf[n..];
+        }
+
+        Pin::new(&mut *this.writer).poll_flush(cx)
+    }
+}
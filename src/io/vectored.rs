@@ -0,0 +1,222 @@
+use std::io::{Error, ErrorKind, IoSlice, IoSliceMut};
+use std::pin::Pin;
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::future::poll_fn;
+
+
+
+/// Writes all the buffers in `bufs` into `writer`.
+///
+/// Partial writes are continued with the remaining bytes, advancing the slices in `bufs` like
+/// [`IoSlice::advance_slices`]. Because of this, the contents of `bufs` are unspecified once this
+/// function returns.
+///
+/// Returns an error of kind [`WriteZero`](ErrorKind::WriteZero) if the writer stops accepting
+/// bytes before all of them have been written.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> std::io::Result<()> {
+/// use std::io::IoSlice;
+///
+/// use fut_compat::io::write_all_vectored;
+///
+/// let mut writer = futures::io::Cursor::new(Vec::new());
+/// let mut bufs = [IoSlice::new(b"hello "), IoSlice::new(b"world")];
+///
+/// write_all_vectored(&mut writer, &mut bufs).await?;
+///
+/// assert_eq!(writer.get_ref(), b"hello world");
+/// # Ok(())
+/// # }
+/// ```
+pub async fn write_all_vectored<W>(writer: &mut W, mut bufs: &mut [IoSlice<'_>]) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    // Skip the empty buffers at the start, so writing nothing is not mistaken for `WriteZero`.
+    IoSlice::advance_slices(&mut bufs, 0);
+
+    while !bufs.is_empty() {
+        match poll_fn(|cx| Pin::new(&mut *writer).poll_write_vectored(cx, bufs)).await {
+            Ok(0) => return Err(Error::new(ErrorKind::WriteZero, "failed to write whole buffer")),
+            Ok(n) => IoSlice::advance_slices(&mut bufs, n),
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the exact number of bytes required to fill all the buffers in `bufs` from `reader`.
+///
+/// Partial reads are continued into the remaining space, advancing the slices in `bufs` like
+/// [`IoSliceMut::advance_slices`]. Because of this, the contents of `bufs` are unspecified once
+/// this function returns, while the memory they point to holds the data read.
+///
+/// Returns an error of kind [`UnexpectedEof`](ErrorKind::UnexpectedEof) if the reader reaches
+/// EOF before all buffers have been filled.
+pub async fn read_vectored_exact<R>(reader: &mut R, mut bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    // Skip the empty buffers at the start, so reading nothing is not mistaken for EOF.
+    IoSliceMut::advance_slices(&mut bufs, 0);
+
+    while !bufs.is_empty() {
+        match poll_fn(|cx| Pin::new(&mut *reader).poll_read_vectored(cx, bufs)).await {
+            Ok(0) => return Err(Error::new(ErrorKind::UnexpectedEof, "failed to fill whole buffer")),
+            Ok(n) => IoSliceMut::advance_slices(&mut bufs, n),
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies as many bytes of `src` as fit into `bufs`, filling the buffers in order.
+///
+/// Returns the number of bytes copied.
+#[cfg(feature = "tokio-rt")]
+pub(crate) fn copy_to_slices(mut src: &[u8], bufs: &mut [IoSliceMut<'_>]) -> usize {
+    let mut copied = 0;
+
+    for buf in bufs {
+        if src.is_empty() {
+            break;
+        }
+
+        let n = src.len().min(buf.len());
+
+        buf[..n].copy_from_slice(&src[..n]);
+        src = &src[n..];
+        copied += n;
+    }
+
+    copied
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+
+    use crate::io::{ScriptAction, ScriptedIo};
+
+    #[test]
+    fn partial_writes_advance_the_slices() {
+        let mut writer = ScriptedIo::new([
+            ScriptAction::ShortWrite(3),
+            ScriptAction::Pending,
+            ScriptAction::Err(ErrorKind::Interrupted),
+            ScriptAction::ShortWrite(4),
+            ScriptAction::ShortWrite(2),
+            ScriptAction::ShortWrite(100),
+        ]);
+
+        let mut bufs = [IoSlice::new(b""), IoSlice::new(b"hello "), IoSlice::new(b""), IoSlice::new(b"world")];
+        block_on(write_all_vectored(&mut writer, &mut bufs)).unwrap();
+
+        assert_eq!(writer.written(), b"hello world");
+        writer.assert_done();
+    }
+
+    #[test]
+    fn writing_nothing_does_not_poll() {
+        let mut writer = ScriptedIo::new([]);
+
+        block_on(write_all_vectored(&mut writer, &mut [])).unwrap();
+        block_on(write_all_vectored(&mut writer, &mut [IoSlice::new(b""), IoSlice::new(b"")])).unwrap();
+    }
+
+    #[test]
+    fn write_errors_are_returned() {
+        let mut writer = ScriptedIo::new([ScriptAction::ShortWrite(2), ScriptAction::ShortWrite(0)]);
+
+        let err = block_on(write_all_vectored(&mut writer, &mut [IoSlice::new(b"hello")])).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert_eq!(writer.written(), b"he");
+
+        let mut writer = ScriptedIo::new([ScriptAction::Err(ErrorKind::BrokenPipe)]);
+
+        let err = block_on(write_all_vectored(&mut writer, &mut [IoSlice::new(b"hello")])).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn partial_reads_advance_the_slices() {
+        let mut reader = ScriptedIo::new([
+            ScriptAction::Read(b"hel".to_vec()),
+            ScriptAction::Pending,
+            ScriptAction::Err(ErrorKind::Interrupted),
+            ScriptAction::Read(b"lo wor".to_vec()),
+            ScriptAction::Read(b"ld!".to_vec()),
+        ]);
+
+        let (mut head, mut tail) = ([0u8; 4], [0u8; 7]);
+        let mut bufs = [IoSliceMut::new(&mut head), IoSliceMut::new(&mut []), IoSliceMut::new(&mut tail)];
+        block_on(read_vectored_exact(&mut reader, &mut bufs)).unwrap();
+
+        assert_eq!((&head, &tail), (b"hell", b"o world"));
+        // The read stopped once the buffers were full.
+        assert_eq!(reader.remaining().collect::<Vec<_>>(), [&ScriptAction::Read(b"!".to_vec())]);
+    }
+
+    #[test]
+    fn early_eof_fails_the_read() {
+        let mut reader = ScriptedIo::new([ScriptAction::Read(b"hel".to_vec()), ScriptAction::Eof]);
+
+        let mut buf = [0u8; 5];
+        let err = block_on(read_vectored_exact(&mut reader, &mut [IoSliceMut::new(&mut buf)])).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(&buf[..3], b"hel");
+        reader.assert_done();
+    }
+
+    #[test]
+    fn reading_nothing_does_not_poll() {
+        let mut reader = ScriptedIo::new([]);
+
+        block_on(read_vectored_exact(&mut reader, &mut [])).unwrap();
+        block_on(read_vectored_exact(&mut reader, &mut [IoSliceMut::new(&mut [])])).unwrap();
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn buffered_tokio_reads_fill_every_slice() {
+        use futures::io::AsyncReadExt;
+
+        use crate::io::TokioCompat;
+
+        let reader = ScriptedIo::new([ScriptAction::Read(b"hello world".to_vec()), ScriptAction::Eof]);
+        let mut compat = TokioCompat::with_read_buffer_capacity(reader, 64);
+
+        // A single read fills the internal buffer and spreads its data across the slices.
+        let (mut head, mut tail) = ([0u8; 4], [0u8; 4]);
+        let n = block_on(compat.read_vectored(&mut [IoSliceMut::new(&mut head), IoSliceMut::new(&mut tail)])).unwrap();
+        assert_eq!((n, &head, &tail), (8, b"hell", b"o wo"));
+
+        let mut rest = Vec::new();
+        block_on(compat.read_to_end(&mut rest)).unwrap();
+        assert_eq!(rest, b"rld");
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn copy_to_slices_stops_at_the_shorter_side() {
+        let (mut head, mut tail) = ([0u8; 2], [0u8; 3]);
+
+        assert_eq!(copy_to_slices(b"abcdefg", &mut [IoSliceMut::new(&mut head), IoSliceMut::new(&mut tail)]), 5);
+        assert_eq!((&head, &tail), (b"ab", b"cde"));
+
+        assert_eq!(copy_to_slices(b"xyz", &mut [IoSliceMut::new(&mut head), IoSliceMut::new(&mut tail)]), 3);
+        assert_eq!((&head, &tail), (b"xy", b"zde"));
+    }
+}
@@ -15,3 +15,25 @@ pub mod task;
 
 /// Async abstractions over [`std::net`] and the implementations for the different runtimes.
 pub mod net;
+
+/// Async abstractions over [`std::process`] and the implementations for the different runtimes.
+pub mod process;
+
+/// Channels for sending values between tasks, independent of the runtime.
+pub mod channel;
+
+/// Synchronization primitives for tasks, which work on any executor.
+pub mod sync;
+
+/// A single trait tying the abstractions of the other modules together for each runtime.
+pub mod runtime;
+
+/// All traits of this crate under collision-free names, for importing them with a single `use`.
+pub mod prelude;
+
+/// A subscriber capturing spans and events, for testing the traced wrappers.
+///
+/// Without a runtime only the events are used, by the tests of `io::Traced`.
+#[cfg(all(test, feature = "tracing"))]
+#[cfg_attr(not(any(feature = "tokio-rt", feature = "async-std-rt")), allow(dead_code))]
+mod capture;
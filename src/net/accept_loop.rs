@@ -0,0 +1,57 @@
+use super::*;
+
+use std::future::Future;
+
+use futures::task::{Spawn, SpawnExt};
+
+
+
+/// Convenience methods for listeners.
+///
+/// Implemented for every type implementing [`TcpListener`].
+#[async_trait]
+pub trait TcpListenerUtilExt: TcpListener + Sync {
+    /// Accepts connections until an error occurs, spawning the future returned by `handler` for
+    /// each of them onto `spawner`.
+    ///
+    /// Returns the first error of accepting a connection or of spawning its task. Errors of the
+    /// handlers are up to the handlers themselves.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "tokio-rt")]
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// use fut_compat::io::TokioCompat;
+    /// use fut_compat::prelude::*;
+    /// use fut_compat::task::TokioExecutor;
+    ///
+    /// let listener = TokioCompat::<tokio::net::TcpListener>::bind("127.0.0.1:0").await?;
+    /// let addr = listener.local_addr()?;
+    ///
+    /// TokioExecutor::default().spawn(async move {
+    ///     let _ = listener.accept_loop(&TokioExecutor::default(), |mut stream, _| async move {
+    ///         let _ = stream.write_all_and_flush(b"hello").await;
+    ///     }).await;
+    /// })?;
+    ///
+    /// let mut stream = TokioCompat::<tokio::net::TcpStream>::connect(addr).await?;
+    /// assert_eq!(stream.read_to_vec().await?, b"hello");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # }).unwrap();
+    /// ```
+    async fn accept_loop<S, F, Fut>(&self, spawner: &S, mut handler: F) -> std::io::Result<()>
+    where
+        S: Spawn + Sync + ?Sized,
+        F: FnMut(Self::TcpStream, SocketAddr) -> Fut + Send,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        loop {
+            let (stream, addr) = self.accept().await?;
+
+            spawner.spawn(handler(stream, addr)).map_err(std::io::Error::other)?;
+        }
+    }
+}
+
+impl<L: TcpListener + Sync> TcpListenerUtilExt for L {}
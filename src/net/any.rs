@@ -0,0 +1,389 @@
+use super::*;
+
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite, IoSlice, IoSliceMut};
+
+use crate::runtime::RuntimeKind;
+#[cfg(feature = "tokio-rt")]
+use crate::io::TokioCompat;
+
+
+
+/// Evaluates `$body` with `$inner` bound to the object wrapped by the variant of `$value`, an
+/// instance of the enum `$any`.
+macro_rules! dispatch {
+    ($any:ident, $value:expr, $inner:ident => $body:expr) => {
+        match $value {
+            #[cfg(feature = "tokio-rt")]
+            $any::Tokio($inner) => $body,
+            #[cfg(feature = "async-std-rt")]
+            $any::AsyncStd($inner) => $body,
+        }
+    };
+}
+
+/// The error returned when the chosen runtime provides no TCP sockets.
+fn unsupported(kind: Option<RuntimeKind>) -> std::io::Error {
+    let msg = match kind {
+        Some(kind) => format!("the {:?} runtime provides no TCP sockets", kind),
+        None => "no async runtime is available".to_string(),
+    };
+
+    std::io::Error::new(std::io::ErrorKind::Unsupported, msg)
+}
+
+
+
+/// A [`TcpStream`] of a runtime chosen at runtime, e.g. from a configuration file.
+///
+/// Implements [`TcpStream`], [`AsyncRead`] and [`AsyncWrite`] by calling the implementations of
+/// the variant. [`TcpStream::connect`] uses the runtime returned by [`RuntimeKind::current`],
+/// [`connect_with`](Self::connect_with) takes the runtime to use.
+#[non_exhaustive]
+pub enum AnyTcpStream {
+    /// A connection of [`tokio`](https://docs.rs/tokio).
+    #[cfg(feature = "tokio-rt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
+    Tokio(TokioCompat<::tokio::net::TcpStream>),
+    /// A connection of [`async_std`](https://docs.rs/async-std).
+    #[cfg(feature = "async-std-rt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
+    AsyncStd(::async_std::net::TcpStream),
+}
+
+impl AnyTcpStream {
+    /// Opens a TCP connection to a remote host with the given runtime.
+    ///
+    /// See [`TcpStream::connect`]. Fails with an error of kind
+    /// [`Unsupported`](std::io::ErrorKind::Unsupported) for runtimes without TCP sockets.
+    pub async fn connect_with<A: ToSocketAddrs + Send>(
+        kind: RuntimeKind,
+        addrs: A,
+    ) -> std::io::Result<Self> {
+        match kind {
+            #[cfg(feature = "tokio-rt")]
+            RuntimeKind::Tokio => Ok(Self::Tokio(TcpStream::connect(addrs).await?)),
+            #[cfg(feature = "async-std-rt")]
+            RuntimeKind::AsyncStd => Ok(Self::AsyncStd(TcpStream::connect(addrs).await?)),
+            #[cfg(feature = "smol-rt")]
+            RuntimeKind::Smol => Err(unsupported(Some(kind))),
+        }
+    }
+
+    /// Returns the runtime of the connection.
+    pub fn runtime(&self) -> RuntimeKind {
+        match self {
+            #[cfg(feature = "tokio-rt")]
+            Self::Tokio(_) => RuntimeKind::Tokio,
+            #[cfg(feature = "async-std-rt")]
+            Self::AsyncStd(_) => RuntimeKind::AsyncStd,
+        }
+    }
+}
+
+#[async_trait]
+impl TcpStream for AnyTcpStream {
+    async fn connect<A: ToSocketAddrs + Send>(addrs: A) -> std::io::Result<Self> {
+        match RuntimeKind::current() {
+            Some(kind) => Self::connect_with(kind, addrs).await,
+            None => Err(unsupported(None)),
+        }
+    }
+
+    async fn connect_from<A: ToSocketAddrs + Send>(
+        local: SocketAddr,
+        remote: A,
+    ) -> std::io::Result<Self> {
+        match RuntimeKind::current() {
+            #[cfg(feature = "tokio-rt")]
+            Some(RuntimeKind::Tokio) => Ok(Self::Tokio(TcpStream::connect_from(local, remote).await?)),
+            #[cfg(feature = "async-std-rt")]
+            Some(RuntimeKind::AsyncStd) => Ok(Self::AsyncStd(TcpStream::connect_from(local, remote).await?)),
+            #[cfg(feature = "smol-rt")]
+            Some(kind @ RuntimeKind::Smol) => Err(unsupported(Some(kind))),
+            None => Err(unsupported(None)),
+        }
+    }
+
+    async fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        dispatch!(Self, self, stream => TcpStream::peek(stream, buf).await)
+    }
+
+    fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+        dispatch!(Self, self, stream => TcpStream::peer_addr(stream))
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        dispatch!(Self, self, stream => TcpStream::local_addr(stream))
+    }
+
+    fn nodelay(&self) -> std::io::Result<bool> {
+        dispatch!(Self, self, stream => TcpStream::nodelay(stream))
+    }
+
+    fn set_nodelay(&self, nodelay: bool) -> std::io::Result<()> {
+        dispatch!(Self, self, stream => TcpStream::set_nodelay(stream, nodelay))
+    }
+
+    fn ttl(&self) -> std::io::Result<u32> {
+        dispatch!(Self, self, stream => TcpStream::ttl(stream))
+    }
+
+    fn set_ttl(&self, ttl: u32) -> std::io::Result<()> {
+        dispatch!(Self, self, stream => TcpStream::set_ttl(stream, ttl))
+    }
+
+    fn tos(&self) -> std::io::Result<u32> {
+        dispatch!(Self, self, stream => TcpStream::tos(stream))
+    }
+
+    fn set_tos(&self, tos: u32) -> std::io::Result<()> {
+        dispatch!(Self, self, stream => TcpStream::set_tos(stream, tos))
+    }
+
+    async fn readable(&self) -> std::io::Result<()> {
+        dispatch!(Self, self, stream => TcpStream::readable(stream).await)
+    }
+
+    async fn writable(&self) -> std::io::Result<()> {
+        dispatch!(Self, self, stream => TcpStream::writable(stream).await)
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        dispatch!(Self, self, stream => TcpStream::try_read(stream, buf))
+    }
+
+    fn try_write(&self, buf: &[u8]) -> std::io::Result<usize> {
+        dispatch!(Self, self, stream => TcpStream::try_write(stream, buf))
+    }
+}
+
+impl WithSocket for AnyTcpStream {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        dispatch!(Self, self, stream => stream.with_socket(f))
+    }
+}
+
+impl AsyncRead for AnyTcpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        dispatch!(Self, Pin::into_inner(self), stream => Pin::new(stream).poll_read(cx, buf))
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        dispatch!(Self, Pin::into_inner(self), stream => Pin::new(stream).poll_read_vectored(cx, bufs))
+    }
+}
+
+impl AsyncWrite for AnyTcpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        dispatch!(Self, Pin::into_inner(self), stream => Pin::new(stream).poll_write(cx, buf))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        dispatch!(Self, Pin::into_inner(self), stream => Pin::new(stream).poll_write_vectored(cx, bufs))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        dispatch!(Self, Pin::into_inner(self), stream => Pin::new(stream).poll_flush(cx))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        dispatch!(Self, Pin::into_inner(self), stream => Pin::new(stream).poll_close(cx))
+    }
+}
+
+#[cfg(feature = "tokio-rt")]
+impl From<TokioCompat<::tokio::net::TcpStream>> for AnyTcpStream {
+    fn from(stream: TokioCompat<::tokio::net::TcpStream>) -> Self {
+        Self::Tokio(stream)
+    }
+}
+
+#[cfg(feature = "async-std-rt")]
+impl From<::async_std::net::TcpStream> for AnyTcpStream {
+    fn from(stream: ::async_std::net::TcpStream) -> Self {
+        Self::AsyncStd(stream)
+    }
+}
+
+impl From<AnyTcpStream> for BoxedStream {
+    fn from(stream: AnyTcpStream) -> Self {
+        Self::new(stream)
+    }
+}
+
+impl fmt::Debug for AnyTcpStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnyTcpStream")
+            .field("runtime", &self.runtime())
+            .field("peer_addr", &TcpStream::peer_addr(self).ok())
+            .finish()
+    }
+}
+
+
+
+/// A [`TcpListener`] of a runtime chosen at runtime, e.g. from a configuration file.
+///
+/// Accepts connections as [`AnyTcpStream`]s of the same runtime. [`TcpListener::bind`] uses the
+/// runtime returned by [`RuntimeKind::current`], [`bind_with`](Self::bind_with) takes the
+/// runtime to use.
+///
+/// # Example
+///
+/// An echo server, whose runtime is chosen at startup:
+///
+/// ```
+/// # #[cfg(feature = "tokio-rt")]
+/// # {
+/// use futures::io::{AsyncReadExt, AsyncWriteExt};
+/// use fut_compat::net::{AnyTcpListener, AnyTcpStream, TcpListener};
+/// use fut_compat::runtime::RuntimeKind;
+///
+/// async fn echo_once(kind: RuntimeKind) -> std::io::Result<()> {
+///     let listener = AnyTcpListener::bind_with(kind, "127.0.0.1:0").await?;
+///     let addr = listener.local_addr()?;
+///
+///     let mut client = AnyTcpStream::connect_with(kind, addr).await?;
+///     let (mut server, _) = listener.accept().await?;
+///
+///     client.write_all(b"hello").await?;
+///
+///     let mut buf = [0; 5];
+///     server.read_exact(&mut buf).await?;
+///     server.write_all(&buf).await?;
+///
+///     client.read_exact(&mut buf).await?;
+///     assert_eq!(&buf, b"hello");
+///
+///     Ok(())
+/// }
+///
+/// let kind: RuntimeKind = "tokio".parse().unwrap();
+///
+/// tokio::runtime::Runtime::new()?.block_on(echo_once(kind))?;
+/// # }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[non_exhaustive]
+pub enum AnyTcpListener {
+    /// A listener of [`tokio`](https://docs.rs/tokio).
+    #[cfg(feature = "tokio-rt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
+    Tokio(TokioCompat<::tokio::net::TcpListener>),
+    /// A listener of [`async_std`](https://docs.rs/async-std).
+    #[cfg(feature = "async-std-rt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
+    AsyncStd(::async_std::net::TcpListener),
+}
+
+impl AnyTcpListener {
+    /// Creates a new listener of the given runtime, bound to the specified address.
+    ///
+    /// See [`TcpListener::bind`]. Fails with an error of kind
+    /// [`Unsupported`](std::io::ErrorKind::Unsupported) for runtimes without TCP sockets.
+    pub async fn bind_with<A: ToSocketAddrs + Send>(
+        kind: RuntimeKind,
+        addrs: A,
+    ) -> std::io::Result<Self> {
+        match kind {
+            #[cfg(feature = "tokio-rt")]
+            RuntimeKind::Tokio => Ok(Self::Tokio(TcpListener::bind(addrs).await?)),
+            #[cfg(feature = "async-std-rt")]
+            RuntimeKind::AsyncStd => Ok(Self::AsyncStd(TcpListener::bind(addrs).await?)),
+            #[cfg(feature = "smol-rt")]
+            RuntimeKind::Smol => Err(unsupported(Some(kind))),
+        }
+    }
+
+    /// Returns the runtime of the listener.
+    pub fn runtime(&self) -> RuntimeKind {
+        match self {
+            #[cfg(feature = "tokio-rt")]
+            Self::Tokio(_) => RuntimeKind::Tokio,
+            #[cfg(feature = "async-std-rt")]
+            Self::AsyncStd(_) => RuntimeKind::AsyncStd,
+        }
+    }
+}
+
+#[async_trait]
+impl TcpListener for AnyTcpListener {
+    type TcpStream = AnyTcpStream;
+
+    async fn bind<A: ToSocketAddrs + Send>(addrs: A) -> std::io::Result<Self> {
+        match RuntimeKind::current() {
+            Some(kind) => Self::bind_with(kind, addrs).await,
+            None => Err(unsupported(None)),
+        }
+    }
+
+    async fn accept(&self) -> std::io::Result<(Self::TcpStream, SocketAddr)> {
+        match self {
+            #[cfg(feature = "tokio-rt")]
+            Self::Tokio(listener) => {
+                let (stream, addr) = TcpListener::accept(listener).await?;
+
+                Ok((AnyTcpStream::Tokio(stream), addr))
+            }
+            #[cfg(feature = "async-std-rt")]
+            Self::AsyncStd(listener) => {
+                let (stream, addr) = TcpListener::accept(listener).await?;
+
+                Ok((AnyTcpStream::AsyncStd(stream), addr))
+            }
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        dispatch!(Self, self, listener => TcpListener::local_addr(listener))
+    }
+}
+
+impl WithSocket for AnyTcpListener {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        dispatch!(Self, self, listener => listener.with_socket(f))
+    }
+}
+
+#[cfg(feature = "tokio-rt")]
+impl From<TokioCompat<::tokio::net::TcpListener>> for AnyTcpListener {
+    fn from(listener: TokioCompat<::tokio::net::TcpListener>) -> Self {
+        Self::Tokio(listener)
+    }
+}
+
+#[cfg(feature = "async-std-rt")]
+impl From<::async_std::net::TcpListener> for AnyTcpListener {
+    fn from(listener: ::async_std::net::TcpListener) -> Self {
+        Self::AsyncStd(listener)
+    }
+}
+
+impl fmt::Debug for AnyTcpListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnyTcpListener")
+            .field("runtime", &self.runtime())
+            .field("local_addr", &TcpListener::local_addr(self).ok())
+            .finish()
+    }
+}
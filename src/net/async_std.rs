@@ -2,7 +2,54 @@ use super::*;
 
 use ::async_std::net;
 
+use socket2::{SockRef, Socket, Domain, Type, Protocol};
 
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+#[cfg(any(unix, windows))]
+use std::pin::Pin;
+#[cfg(any(unix, windows))]
+use std::task::{Context, Poll};
+#[cfg(unix)]
+use std::os::unix::io::{AsFd, BorrowedFd, RawFd};
+
+#[cfg(windows)]
+use futures::io::{AsyncRead, AsyncWrite};
+#[cfg(windows)]
+use super::overlapped::{wait_overlapped, OverlappedPipe};
+
+
+
+/// Connects a new socket bound to `local` to `addr`.
+///
+/// async-std can not connect an existing socket, so the socket gets connected in non-blocking
+/// mode, and the connection is awaited on the reactor of `async-io`, which async-std runs on.
+async fn connect_bound(local: SocketAddr, addr: SocketAddr) -> std::io::Result<std::net::TcpStream> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&local.into())?;
+
+    let in_progress = match socket.connect(&addr.into()) {
+        Ok(()) => false,
+        #[cfg(unix)]
+        Err(err) if err.raw_os_error() == Some(libc::EINPROGRESS) => true,
+        Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => true,
+        Err(err) => return Err(err),
+    };
+
+    let stream = async_io::Async::new(std::net::TcpStream::from(socket))?;
+
+    if in_progress {
+        stream.writable().await?;
+
+        if let Some(err) = stream.get_ref().take_error()? {
+            return Err(err);
+        }
+    }
+
+    stream.into_inner()
+}
 
 #[async_trait]
 impl TcpStream for net::TcpStream {
@@ -12,6 +59,24 @@ impl TcpStream for net::TcpStream {
         Self::connect(&addrs[..]).await
     }
 
+    async fn connect_from<A: ToSocketAddrs + Send>(
+        local: SocketAddr,
+        remote: A,
+    ) -> std::io::Result<Self> {
+        let addrs: Vec<SocketAddr> = ToSocketAddrs::to_socket_addrs(remote).await.collect();
+
+        let mut last_err = None;
+
+        for addr in addrs {
+            match connect_bound(local, addr).await {
+                Ok(stream) => return Ok(stream.into()),
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        Err(last_err.unwrap_or_else(no_addresses_error))
+    }
+
     async fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
         self.peek(buf).await
     }
@@ -39,6 +104,48 @@ impl TcpStream for net::TcpStream {
     fn set_ttl(&self, ttl: u32) -> std::io::Result<()> {
         self.set_ttl(ttl)
     }
+
+    fn tos(&self) -> std::io::Result<u32> {
+        socket_tos(SockRef::from(self))
+    }
+
+    fn set_tos(&self, tos: u32) -> std::io::Result<()> {
+        set_socket_tos(SockRef::from(self), tos)
+    }
+
+    async fn readable(&self) -> std::io::Result<()> {
+        // Peeking waits for the socket to become readable without consuming any data.
+        let mut buf = [0u8; 1];
+
+        self.peek(&mut buf).await.map(|_| ())
+    }
+
+    async fn writable(&self) -> std::io::Result<()> {
+        // async-std does not expose the write readiness of its sockets, so we register a duplicate
+        // of the socket with the reactor, which reports the readiness of the same connection.
+        let socket = async_io::Async::new(SockRef::from(self).try_clone()?)?;
+
+        socket.writable().await
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // The socket is in non-blocking mode, so this surfaces `WouldBlock` instead of waiting.
+        let socket = SockRef::from(self);
+
+        (&*socket).read(buf)
+    }
+
+    fn try_write(&self, buf: &[u8]) -> std::io::Result<usize> {
+        let socket = SockRef::from(self);
+
+        (&*socket).write(buf)
+    }
+}
+
+impl WithSocket for net::TcpStream {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self))
+    }
 }
 
 
@@ -62,6 +169,132 @@ impl TcpListener for net::TcpListener {
     }
 }
 
+impl WithSocket for net::TcpListener {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self))
+    }
+}
+
+
+
+#[async_trait]
+impl UdpSocket for net::UdpSocket {
+    async fn bind<A: ToSocketAddrs + Send>(addrs: A) -> std::io::Result<Self> {
+        let addrs: Vec<SocketAddr> = ToSocketAddrs::to_socket_addrs(addrs).await.collect();
+
+        Self::bind(&addrs[..]).await
+    }
+
+    async fn connect<A: ToSocketAddrs + Send>(&self, addrs: A) -> std::io::Result<()> {
+        let addrs: Vec<SocketAddr> = ToSocketAddrs::to_socket_addrs(addrs).await.collect();
+
+        self.connect(&addrs[..]).await
+    }
+
+    async fn send(&self, buf: &[u8]) -> std::io::Result<usize> {
+        self.send(buf).await
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.recv(buf).await
+    }
+
+    async fn send_to<A: ToSocketAddrs + Send>(
+        &self,
+        buf: &[u8],
+        addrs: A,
+    ) -> std::io::Result<usize> {
+        let addrs: Vec<SocketAddr> = ToSocketAddrs::to_socket_addrs(addrs).await.collect();
+
+        self.send_to(buf, &addrs[..]).await
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+        self.recv_from(buf).await
+    }
+
+    async fn peek_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+        self.peek_from(buf).await
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn send_batch(&self, msgs: &[(&[u8], SocketAddr)]) -> std::io::Result<usize> {
+        let (buf, addr) = match msgs.first() {
+            Some(msg) => *msg,
+            None => return Ok(0),
+        };
+
+        match super::mmsg::sendmmsg(self.as_raw_fd(), msgs) {
+            // async-std does not expose write readiness, so we wait by sending the first
+            // datagram the regular way.
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                self.send_to(buf, addr).await?;
+
+                Ok(1)
+            }
+            result => result,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn recv_batch(
+        &self,
+        bufs: &mut [Vec<u8>],
+    ) -> std::io::Result<Vec<ReceivedDatagram>> {
+        if bufs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        loop {
+            match super::mmsg::recvmmsg(self.as_raw_fd(), bufs) {
+                // Peeking waits for the socket to become readable without consuming a datagram.
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                    self.peek_from(&mut [0u8; 1]).await?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.local_addr()
+    }
+
+    fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+        self.peer_addr()
+    }
+
+    fn broadcast(&self) -> std::io::Result<bool> {
+        self.broadcast()
+    }
+
+    fn set_broadcast(&self, broadcast: bool) -> std::io::Result<()> {
+        self.set_broadcast(broadcast)
+    }
+
+    fn ttl(&self) -> std::io::Result<u32> {
+        self.ttl()
+    }
+
+    fn set_ttl(&self, ttl: u32) -> std::io::Result<()> {
+        self.set_ttl(ttl)
+    }
+
+    fn tos(&self) -> std::io::Result<u32> {
+        socket_tos(SockRef::from(self))
+    }
+
+    fn set_tos(&self, tos: u32) -> std::io::Result<()> {
+        set_socket_tos(SockRef::from(self), tos)
+    }
+}
+
+impl WithSocket for net::UdpSocket {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self))
+    }
+}
+
 
 
 #[cfg(unix)]
@@ -77,6 +310,23 @@ impl UnixStream for ::async_std::os::unix::net::UnixStream {
         Self::connect(path).await
     }
 
+    async fn connect_addr(addr: &Self::SocketAddr) -> std::io::Result<Self> {
+        // async-std can only connect to paths, so the socket gets connected through socket2, and
+        // the connection is awaited on the reactor of `async-io`, which async-std runs on.
+        let (socket, in_progress) = connect_unix(addr)?;
+        let stream = async_io::Async::new(std::os::unix::net::UnixStream::from(socket))?;
+
+        if in_progress {
+            stream.writable().await?;
+
+            if let Some(err) = stream.get_ref().take_error()? {
+                return Err(err);
+            }
+        }
+
+        Ok(stream.into_inner()?.into())
+    }
+
     fn pair() -> std::io::Result<(Self, Self)> {
         Self::pair()
     }
@@ -90,6 +340,14 @@ impl UnixStream for ::async_std::os::unix::net::UnixStream {
     }
 }
 
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl WithSocket for ::async_std::os::unix::net::UnixStream {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self))
+    }
+}
+
 
 
 #[cfg(unix)]
@@ -106,6 +364,12 @@ impl UnixListener for ::async_std::os::unix::net::UnixListener {
         Self::bind(path).await
     }
 
+    async fn bind_addr(addr: &Self::SocketAddr) -> std::io::Result<Self> {
+        let listener = std::os::unix::net::UnixListener::bind_addr(addr)?;
+
+        Ok(listener.into())
+    }
+
     async fn accept(&self) -> std::io::Result<(Self::UnixStream, Self::SocketAddr)> {
         self.accept().await
     }
@@ -114,3 +378,541 @@ impl UnixListener for ::async_std::os::unix::net::UnixListener {
         self.local_addr()
     }
 }
+
+
+
+/// [`async_std`](https://docs.rs/async-std)'s abstraction of a [`Pipe`].
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AsyncStdPipe {}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl Pipe for AsyncStdPipe {
+    type Reader = AsyncStdPipeReader;
+    type Writer = AsyncStdPipeWriter;
+
+    fn pipe() -> std::io::Result<(Self::Reader, Self::Writer)> {
+        let (reader, writer) = std::io::pipe()?;
+
+        let reader = AsyncStdPipeReader {
+            inner: async_io::Async::new(reader)?,
+        };
+        let writer = AsyncStdPipeWriter {
+            inner: async_io::Async::new(writer)?,
+        };
+
+        Ok((reader, writer))
+    }
+}
+
+/// The reading end of an [`AsyncStdPipe`].
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[derive(Debug)]
+pub struct AsyncStdPipeReader {
+    inner: async_io::Async<std::io::PipeReader>,
+}
+
+#[cfg(unix)]
+impl AsyncRead for AsyncStdPipeReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for AsyncStdPipeReader {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for AsyncStdPipeReader {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+/// The writing end of an [`AsyncStdPipe`].
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[derive(Debug)]
+pub struct AsyncStdPipeWriter {
+    inner: async_io::Async<std::io::PipeWriter>,
+}
+
+#[cfg(unix)]
+impl AsyncWrite for AsyncStdPipeWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for AsyncStdPipeWriter {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for AsyncStdPipeWriter {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+
+
+/// [`async_std`](https://docs.rs/async-std)'s abstraction of a [`UnixSeqpacketStream`].
+///
+/// async-std provides no such sockets, so the socket gets registered with the reactor of
+/// [`async-io`](https://docs.rs/async-io), which async-std uses as well.
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[derive(Debug)]
+pub struct AsyncStdUnixSeqpacketStream {
+    inner: async_io::Async<Socket>,
+}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[async_trait]
+impl UnixSeqpacketStream for AsyncStdUnixSeqpacketStream {
+    type SocketAddr = socket2::SockAddr;
+
+    async fn connect<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self> {
+        let (socket, in_progress) = super::seqpacket::connect(path.as_ref())?;
+        let inner = async_io::Async::new(socket)?;
+
+        if in_progress {
+            inner.writable().await?;
+
+            if let Some(err) = inner.get_ref().take_error()? {
+                return Err(err);
+            }
+        }
+
+        Ok(Self { inner })
+    }
+
+    fn pair() -> std::io::Result<(Self, Self)> {
+        let (a, b) = super::seqpacket::pair()?;
+
+        Ok((Self { inner: async_io::Async::new(a)? }, Self { inner: async_io::Async::new(b)? }))
+    }
+
+    async fn send(&self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.write_with(|socket| super::seqpacket::send(socket, buf)).await
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read_with(|socket| super::seqpacket::recv(socket, buf)).await
+    }
+
+    fn peer_addr(&self) -> std::io::Result<Self::SocketAddr> {
+        self.inner.get_ref().peer_addr()
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::SocketAddr> {
+        self.inner.get_ref().local_addr()
+    }
+}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl WithSocket for AsyncStdUnixSeqpacketStream {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self.inner.get_ref()))
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for AsyncStdUnixSeqpacketStream {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for AsyncStdUnixSeqpacketStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+/// [`async_std`](https://docs.rs/async-std)'s abstraction of a [`UnixSeqpacketListener`].
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[derive(Debug)]
+pub struct AsyncStdUnixSeqpacketListener {
+    inner: async_io::Async<Socket>,
+}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[async_trait]
+impl UnixSeqpacketListener for AsyncStdUnixSeqpacketListener {
+    type UnixSeqpacketStream = AsyncStdUnixSeqpacketStream;
+    type SocketAddr = socket2::SockAddr;
+
+    async fn bind<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self> {
+        let socket = super::seqpacket::bind(path.as_ref())?;
+
+        Ok(Self { inner: async_io::Async::new(socket)? })
+    }
+
+    async fn accept(&self) -> std::io::Result<(Self::UnixSeqpacketStream, Self::SocketAddr)> {
+        let (socket, addr) = self.inner.read_with(super::seqpacket::accept).await?;
+
+        Ok((AsyncStdUnixSeqpacketStream { inner: async_io::Async::new(socket)? }, addr))
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::SocketAddr> {
+        self.inner.get_ref().local_addr()
+    }
+}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl WithSocket for AsyncStdUnixSeqpacketListener {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self.inner.get_ref()))
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for AsyncStdUnixSeqpacketListener {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for AsyncStdUnixSeqpacketListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+
+
+/// The error code returned when all instances of a named pipe server are busy.
+#[cfg(windows)]
+const ERROR_PIPE_BUSY: i32 = 231;
+
+/// The error code returned when a client connected before the server started waiting for it.
+#[cfg(windows)]
+const ERROR_PIPE_CONNECTED: i32 = 535;
+
+/// Converts a name into a nul terminated wide string as expected by the Windows API.
+#[cfg(windows)]
+fn to_wide(name: &OsStr) -> Vec<u16> {
+    use std::os::windows::ffi::OsStrExt;
+
+    name.encode_wide().chain(std::iter::once(0)).collect()
+}
+
+/// [`async_std`](https://docs.rs/async-std)'s abstraction of a [`NamedPipeServer`].
+///
+/// [`async_std`](https://docs.rs/async-std) has no native support for named pipes. This type
+/// therefore opens the pipe in overlapped mode and waits for its operations on the blocking
+/// thread pool. Reads and writes are independent of each other, so the pipe can be used in both
+/// directions at the same time, but every pending operation occupies a thread of the pool until it
+/// completes. Writes are completed by flushing, which should be done before dropping the pipe, as
+/// pending operations get cancelled then.
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+#[derive(Debug)]
+pub struct AsyncStdNamedPipeServer {
+    inner: OverlappedPipe,
+}
+
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+#[async_trait]
+impl NamedPipeServer for AsyncStdNamedPipeServer {
+    async fn create<N: AsRef<OsStr> + Send>(name: N) -> std::io::Result<Self> {
+        use std::os::windows::io::{FromRawHandle, OwnedHandle};
+        use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
+        use windows_sys::Win32::Storage::FileSystem::{FILE_FLAG_OVERLAPPED, PIPE_ACCESS_DUPLEX};
+        use windows_sys::Win32::System::Pipes::{
+            CreateNamedPipeW,
+            PIPE_TYPE_BYTE,
+            PIPE_READMODE_BYTE,
+            PIPE_WAIT,
+            PIPE_UNLIMITED_INSTANCES,
+        };
+
+        let name = to_wide(name.as_ref());
+
+        let handle = ::async_std::task::spawn_blocking(move || {
+            let handle = unsafe {
+                CreateNamedPipeW(
+                    name.as_ptr(),
+                    PIPE_ACCESS_DUPLEX | FILE_FLAG_OVERLAPPED,
+                    PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+                    PIPE_UNLIMITED_INSTANCES,
+                    65536,
+                    65536,
+                    0,
+                    std::ptr::null(),
+                )
+            };
+
+            if handle == INVALID_HANDLE_VALUE {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            Ok(unsafe { OwnedHandle::from_raw_handle(handle) })
+        }).await?;
+
+        Ok(Self {
+            inner: OverlappedPipe::new(handle),
+        })
+    }
+
+    async fn accept(&self) -> std::io::Result<()> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::System::Pipes::ConnectNamedPipe;
+
+        let handle = self.inner.handle().clone();
+
+        ::async_std::task::spawn_blocking(move || {
+            let result = wait_overlapped(handle.as_raw_handle(), |overlapped| unsafe {
+                ConnectNamedPipe(handle.as_raw_handle(), overlapped)
+            });
+
+            match result {
+                Ok(_) => Ok(()),
+                Err(err) if err.raw_os_error() == Some(ERROR_PIPE_CONNECTED) => Ok(()),
+                Err(err) => Err(err),
+            }
+        }).await
+    }
+
+    fn disconnect(&self) -> std::io::Result<()> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::System::Pipes::DisconnectNamedPipe;
+
+        let ok = unsafe { DisconnectNamedPipe(self.inner.handle().as_raw_handle()) };
+
+        if ok == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl AsyncRead for AsyncStdNamedPipeServer {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_read(cx, buf)
+    }
+}
+
+#[cfg(windows)]
+impl AsyncWrite for AsyncStdNamedPipeServer {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_close(cx)
+    }
+}
+
+
+
+/// [`async_std`](https://docs.rs/async-std)'s abstraction of a [`NamedPipeClient`].
+///
+/// Just like [`AsyncStdNamedPipeServer`] this type opens the pipe in overlapped mode and waits
+/// for its operations on the blocking thread pool.
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+#[derive(Debug)]
+pub struct AsyncStdNamedPipeClient {
+    inner: OverlappedPipe,
+}
+
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+#[async_trait]
+impl NamedPipeClient for AsyncStdNamedPipeClient {
+    async fn connect<N: AsRef<OsStr> + Send>(name: N) -> std::io::Result<Self> {
+        use std::os::windows::fs::OpenOptionsExt;
+        use windows_sys::Win32::Storage::FileSystem::FILE_FLAG_OVERLAPPED;
+
+        let name = name.as_ref().to_os_string();
+
+        let file = ::async_std::task::spawn_blocking(move || {
+            loop {
+                let result = std::fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .custom_flags(FILE_FLAG_OVERLAPPED)
+                    .open(&name);
+
+                match result {
+                    Ok(file) => return Ok(file),
+                    Err(err) if err.raw_os_error() == Some(ERROR_PIPE_BUSY) => {}
+                    Err(err) => return Err(err),
+                }
+
+                std::thread::sleep(std::time::Duration::from_millis(50));
+            }
+        }).await?;
+
+        Ok(Self {
+            inner: OverlappedPipe::new(file.into()),
+        })
+    }
+}
+
+#[cfg(windows)]
+impl AsyncRead for AsyncStdNamedPipeClient {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_read(cx, buf)
+    }
+}
+
+#[cfg(windows)]
+impl AsyncWrite for AsyncStdNamedPipeClient {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut Pin::into_inner(self).inner).poll_close(cx)
+    }
+}
+
+
+
+impl From<net::TcpStream> for BoxedStream {
+    fn from(stream: net::TcpStream) -> Self {
+        Self::new(stream)
+    }
+}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl From<::async_std::os::unix::net::UnixStream> for BoxedStream {
+    fn from(stream: ::async_std::os::unix::net::UnixStream) -> Self {
+        Self::new(stream)
+    }
+}
+
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+impl From<AsyncStdNamedPipeServer> for BoxedStream {
+    fn from(stream: AsyncStdNamedPipeServer) -> Self {
+        Self::new(stream)
+    }
+}
+
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+impl From<AsyncStdNamedPipeClient> for BoxedStream {
+    fn from(stream: AsyncStdNamedPipeClient) -> Self {
+        Self::new(stream)
+    }
+}
+
+
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Writes all of `data` while reading the same amount of data from the other end.
+    async fn exchange<S>(stream: S, data: &[u8]) -> Vec<u8>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let (mut reader, mut writer) = stream.split();
+        let mut received = vec![0; data.len()];
+
+        let write = async {
+            writer.write_all(data).await.unwrap();
+            writer.flush().await.unwrap();
+        };
+        let read = async {
+            reader.read_exact(&mut received).await.unwrap();
+        };
+
+        futures::join!(write, read);
+
+        received
+    }
+
+    #[test]
+    fn named_pipe_round_trip_in_both_directions() {
+        ::async_std::task::block_on(async {
+            let name = format!(r"\\.\pipe\fut-compat-test-{}", std::process::id());
+
+            // Both ends write more than fits into the pipe buffers before reading anything, which
+            // only completes if reads and writes do not wait for each other.
+            let to_client: Vec<u8> = (0..256 * 1024).map(|i| i as u8).collect();
+            let to_server: Vec<u8> = (0..256 * 1024).map(|i| (i / 7) as u8).collect();
+
+            let server = AsyncStdNamedPipeServer::create(&name).await.unwrap();
+            let (accepted, client) = futures::join!(server.accept(), AsyncStdNamedPipeClient::connect(&name));
+            accepted.unwrap();
+
+            let (at_client, at_server) = futures::join!(
+                exchange(client.unwrap(), &to_server),
+                exchange(server, &to_client),
+            );
+
+            assert_eq!(at_client, to_client);
+            assert_eq!(at_server, to_server);
+        });
+    }
+}
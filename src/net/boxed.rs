@@ -0,0 +1,259 @@
+use super::*;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite, IoSlice, IoSliceMut};
+
+
+
+/// Combines [`AsyncRead`] and [`AsyncWrite`] so both can be used through a single trait object.
+trait AsyncReadWrite: AsyncRead + AsyncWrite {}
+
+impl<T: AsyncRead + AsyncWrite + ?Sized> AsyncReadWrite for T {}
+
+/// A type-erased connection.
+///
+/// Any stream implementing [`AsyncRead`] and [`AsyncWrite`] can be turned into a `BoxedStream`,
+/// which makes it possible to store connections of different runtimes or transports in the
+/// same data structure. All the stream types of the supported runtimes can be converted with
+/// [`From`], other types with [`BoxedStream::new`].
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// #
+/// use fut_compat::io::TokioCompat;
+/// use fut_compat::net::{TcpStream, BoxedStream};
+///
+/// let tokio_stream = <TokioCompat<tokio::net::TcpStream> as TcpStream>::connect("127.0.0.1:8080").await?;
+/// let async_std_stream = <async_std::net::TcpStream as TcpStream>::connect("127.0.0.1:8080").await?;
+///
+/// let streams: Vec<BoxedStream> = vec![tokio_stream.into(), async_std_stream.into()];
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct BoxedStream {
+    inner: Pin<Box<dyn AsyncReadWrite + Send>>,
+}
+
+impl BoxedStream {
+    /// Creates a new instance by boxing the `inner` stream.
+    pub fn new<S>(inner: S) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        Self {
+            inner: Box::pin(inner),
+        }
+    }
+}
+
+impl std::fmt::Debug for BoxedStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxedStream").finish_non_exhaustive()
+    }
+}
+
+impl AsyncRead for BoxedStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::into_inner(self).inner.as_mut().poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::into_inner(self).inner.as_mut().poll_read_vectored(cx, bufs)
+    }
+}
+
+impl AsyncWrite for BoxedStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::into_inner(self).inner.as_mut().poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::into_inner(self).inner.as_mut().poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::into_inner(self).inner.as_mut().poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::into_inner(self).inner.as_mut().poll_close(cx)
+    }
+}
+
+
+
+type AcceptFuture<'a> = Pin<Box<dyn Future<Output = std::io::Result<(BoxedStream, SocketAddr)>> + Send + 'a>>;
+
+/// An object-safe version of [`TcpListener`] which yields [`BoxedStream`]s.
+trait DynTcpListener {
+    fn accept(&self) -> AcceptFuture<'_>;
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr>;
+}
+
+impl<L> DynTcpListener for L
+where
+    L: TcpListener + Sync,
+    L::TcpStream: Into<BoxedStream>,
+{
+    fn accept(&self) -> AcceptFuture<'_> {
+        Box::pin(async move {
+            let (stream, addr) = TcpListener::accept(self).await?;
+
+            Ok((stream.into(), addr))
+        })
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        TcpListener::local_addr(self)
+    }
+}
+
+/// A type-erased [`TcpListener`] which yields [`BoxedStream`]s.
+///
+/// This makes it possible to keep a single listener type while choosing the runtime at startup.
+pub struct BoxedListener {
+    inner: Box<dyn DynTcpListener + Send + Sync>,
+}
+
+impl BoxedListener {
+    /// Creates a new instance by boxing the `inner` listener.
+    pub fn new<L>(inner: L) -> Self
+    where
+        L: TcpListener + Send + Sync + 'static,
+        L::TcpStream: Into<BoxedStream>,
+    {
+        Self {
+            inner: Box::new(inner),
+        }
+    }
+
+    /// Accepts a new incoming connection to this listener.
+    ///
+    /// When a connection is established, the corresponding stream and address will be returned.
+    pub async fn accept(&self) -> std::io::Result<(BoxedStream, SocketAddr)> {
+        self.inner.accept().await
+    }
+
+    /// Returns the local address that this listener is bound to.
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+impl std::fmt::Debug for BoxedListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxedListener").finish_non_exhaustive()
+    }
+}
+
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Connects to a [`BoxedListener`] wrapping an `L` and returns both ends of the connection.
+    async fn connected<L>() -> (BoxedStream, BoxedStream)
+    where
+        L: TcpListener + Send + Sync + 'static,
+        L::TcpStream: Into<BoxedStream>,
+    {
+        let listener = BoxedListener::new(L::bind("127.0.0.1:0").await.unwrap());
+        let addr = listener.local_addr().unwrap();
+
+        let (accepted, client) = futures::join!(listener.accept(), L::TcpStream::connect(addr));
+        let (server, peer_addr) = accepted.unwrap();
+        let client = client.unwrap();
+
+        assert_eq!(peer_addr, client.local_addr().unwrap());
+
+        (client.into(), server)
+    }
+
+    /// Sends data both ways, partly through vectored writes, and drops the client side.
+    async fn round_trip(mut client: BoxedStream, mut server: BoxedStream) {
+        client.write_all(b"ping").await.unwrap();
+        client.flush().await.unwrap();
+        let mut buf = [0u8; 4];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        let bufs = [IoSlice::new(b"po"), IoSlice::new(b"ng")];
+        let mut written = server.write_vectored(&bufs).await.unwrap();
+        if written < 4 {
+            server.write_all(&b"pong"[written..]).await.unwrap();
+            written = 4;
+        }
+        let mut buf = vec![0u8; written];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, b"pong");
+
+        // Closing does not shut down the async-std streams, dropping them does.
+        client.close().await.unwrap();
+        drop(client);
+        let mut rest = Vec::new();
+        server.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_round_trip() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let (client, server) = connected::<::tokio::net::TcpListener>().await;
+            round_trip(client, server).await;
+        });
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_round_trip() {
+        ::async_std::task::block_on(async {
+            let (client, server) = connected::<::async_std::net::TcpListener>().await;
+            round_trip(client, server).await;
+        });
+    }
+
+    /// The async-std streams are driven by their own reactor, so they work inside of tokio.
+    #[cfg(all(feature = "tokio-rt", feature = "async-std-rt"))]
+    #[test]
+    fn streams_of_both_runtimes_share_a_vec() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let (tokio_client, tokio_server) = connected::<::tokio::net::TcpListener>().await;
+            let (async_std_client, async_std_server) = connected::<::async_std::net::TcpListener>().await;
+
+            let streams: Vec<BoxedStream> = vec![tokio_client, tokio_server, async_std_client, async_std_server];
+
+            let mut streams = streams.into_iter();
+            while let (Some(client), Some(server)) = (streams.next(), streams.next()) {
+                round_trip(client, server).await;
+            }
+        });
+    }
+}
@@ -0,0 +1,309 @@
+use super::*;
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::stream::Stream;
+use futures::sink::Sink;
+
+
+
+/// The default size of the receive buffer, large enough for any UDP datagram.
+const DEFAULT_RECV_BUFFER_SIZE: usize = 65536;
+
+/// The default number of datagrams which can be queued before the sink applies backpressure.
+const DEFAULT_SEND_QUEUE_CAPACITY: usize = 16;
+
+type RecvFuture = Pin<Box<dyn Future<Output = std::io::Result<(Vec<u8>, SocketAddr)>> + Send>>;
+type SendFuture = Pin<Box<dyn Future<Output = std::io::Result<usize>> + Send>>;
+
+/// A [`Stream`] and [`Sink`] of datagrams on top of a [`UdpSocket`].
+///
+/// Every item produced by the stream is a received datagram together with its origin. Every item
+/// fed into the sink is a datagram together with its destination. Datagrams fed into the sink
+/// are queued and only sent out when the sink gets flushed or the queue is full.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// #
+/// use futures::{SinkExt, StreamExt};
+/// use fut_compat::net::DatagramFramed;
+///
+/// let socket = tokio::net::UdpSocket::bind("127.0.0.1:8080").await?;
+/// let mut framed = DatagramFramed::new(socket);
+///
+/// // Echo every received datagram back to its origin.
+/// while let Some(result) = framed.next().await {
+///     let (data, addr) = result?;
+///     framed.send((data, addr)).await?;
+/// }
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct DatagramFramed<U> {
+    socket: Arc<U>,
+    recv_buffer_size: usize,
+    recv: Option<RecvFuture>,
+    send_queue: VecDeque<(Vec<u8>, SocketAddr)>,
+    send_queue_capacity: usize,
+    send: Option<SendFuture>,
+}
+
+impl<U> DatagramFramed<U>
+where
+    U: UdpSocket + Send + Sync + 'static,
+{
+    /// Creates a new instance by wrapping the `socket` object.
+    pub fn new(socket: U) -> Self {
+        Self::with_capacity(socket, DEFAULT_RECV_BUFFER_SIZE)
+    }
+
+    /// Creates a new instance which receives datagrams of at most `recv_buffer_size` bytes.
+    ///
+    /// Bytes exceeding the size of the receive buffer get discarded by the operating system.
+    pub fn with_capacity(socket: U, recv_buffer_size: usize) -> Self {
+        Self {
+            socket: Arc::new(socket),
+            recv_buffer_size,
+            recv: None,
+            send_queue: VecDeque::new(),
+            send_queue_capacity: DEFAULT_SEND_QUEUE_CAPACITY,
+            send: None,
+        }
+    }
+
+    /// Sets the number of datagrams which can be queued before the sink applies backpressure.
+    ///
+    /// The capacity must be at least 1.
+    pub fn set_send_queue_capacity(&mut self, capacity: usize) {
+        self.send_queue_capacity = capacity.max(1);
+    }
+
+    /// Get a reference to the wrapped socket.
+    pub fn get_ref(&self) -> &U {
+        &self.socket
+    }
+
+    /// Consumes the `DatagramFramed` object and returns the wrapped socket.
+    ///
+    /// Datagrams which are still queued for sending get discarded.
+    pub fn into_inner(self) -> U {
+        let Self { socket, recv, send, .. } = self;
+
+        // The pending operations hold the only other references to the socket.
+        drop(recv);
+        drop(send);
+
+        match Arc::try_unwrap(socket) {
+            Ok(socket) => socket,
+            Err(_) => unreachable!("no other references to the socket exist"),
+        }
+    }
+
+    /// Sends the queued datagrams until at most `remaining` datagrams are left in the queue.
+    fn poll_send_queue(
+        &mut self,
+        cx: &mut Context<'_>,
+        remaining: usize,
+    ) -> Poll<std::io::Result<()>> {
+        loop {
+            if let Some(send) = self.send.as_mut() {
+                let result = futures::ready!(send.as_mut().poll(cx));
+                self.send = None;
+                result?;
+            }
+
+            if self.send_queue.len() <= remaining {
+                return Poll::Ready(Ok(()));
+            }
+
+            if let Some((data, addr)) = self.send_queue.pop_front() {
+                let socket = self.socket.clone();
+
+                self.send = Some(Box::pin(async move {
+                    socket.send_to(&data, addr).await
+                }));
+            }
+        }
+    }
+}
+
+impl<U> Stream for DatagramFramed<U>
+where
+    U: UdpSocket + Send + Sync + 'static,
+{
+    type Item = std::io::Result<(Vec<u8>, SocketAddr)>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = Pin::into_inner(self);
+
+        let recv = this.recv.get_or_insert_with(|| {
+            let socket = this.socket.clone();
+            let mut buf = vec![0u8; this.recv_buffer_size];
+
+            Box::pin(async move {
+                let (len, addr) = socket.recv_from(&mut buf).await?;
+                buf.truncate(len);
+
+                Ok((buf, addr))
+            })
+        });
+
+        let result = futures::ready!(recv.as_mut().poll(cx));
+        this.recv = None;
+
+        Poll::Ready(Some(result))
+    }
+}
+
+impl<U> Sink<(Vec<u8>, SocketAddr)> for DatagramFramed<U>
+where
+    U: UdpSocket + Send + Sync + 'static,
+{
+    type Error = std::io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = Pin::into_inner(self);
+        let remaining = this.send_queue_capacity - 1;
+
+        this.poll_send_queue(cx, remaining)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: (Vec<u8>, SocketAddr)) -> std::io::Result<()> {
+        Pin::into_inner(self).send_queue.push_back(item);
+
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::into_inner(self).poll_send_queue(cx, 0)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::into_inner(self).poll_send_queue(cx, 0)
+    }
+}
+
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use futures::{FutureExt, SinkExt, StreamExt};
+
+    /// Echoes datagrams between two framed sockets, in both directions.
+    async fn echo<U: UdpSocket + Send + Sync + 'static>() {
+        let mut client = DatagramFramed::new(U::bind("127.0.0.1:0").await.unwrap());
+        let mut server = DatagramFramed::new(U::bind("127.0.0.1:0").await.unwrap());
+        let client_addr = client.get_ref().local_addr().unwrap();
+        let server_addr = server.get_ref().local_addr().unwrap();
+
+        for i in 0..8 {
+            let data = format!("datagram {}", i).into_bytes();
+            client.send((data.clone(), server_addr)).await.unwrap();
+
+            let (received, from) = server.next().await.unwrap().unwrap();
+            assert_eq!(received, data);
+            assert_eq!(from, client_addr);
+
+            server.send((received, from)).await.unwrap();
+
+            let (echoed, from) = client.next().await.unwrap().unwrap();
+            assert_eq!(echoed, data);
+            assert_eq!(from, server_addr);
+        }
+    }
+
+    /// Datagrams fed into the sink only go out once it gets flushed or the queue is full.
+    async fn sink_queues_until_flushed<U: UdpSocket + Send + Sync + 'static>() {
+        let mut sender = DatagramFramed::new(U::bind("127.0.0.1:0").await.unwrap());
+        let mut receiver = DatagramFramed::new(U::bind("127.0.0.1:0").await.unwrap());
+        let to = receiver.get_ref().local_addr().unwrap();
+
+        for i in 0u8..3 {
+            sender.feed((vec![i], to)).await.unwrap();
+        }
+        assert!(receiver.next().now_or_never().is_none());
+
+        sender.flush().await.unwrap();
+        for i in 0u8..3 {
+            assert_eq!(receiver.next().await.unwrap().unwrap().0, vec![i]);
+        }
+
+        // With room for two datagrams, feeding a third sends the first one out.
+        sender.set_send_queue_capacity(2);
+        for i in 3u8..6 {
+            sender.feed((vec![i], to)).await.unwrap();
+        }
+        assert_eq!(receiver.next().await.unwrap().unwrap().0, vec![3]);
+        assert!(receiver.next().now_or_never().is_none());
+
+        sender.close().await.unwrap();
+        assert_eq!(receiver.next().await.unwrap().unwrap().0, vec![4]);
+        assert_eq!(receiver.next().await.unwrap().unwrap().0, vec![5]);
+    }
+
+    /// Bytes beyond the receive buffer get cut off. Windows reports an error instead.
+    #[cfg(unix)]
+    async fn receive_buffer_truncates<U: UdpSocket + Send + Sync + 'static>() {
+        let sender = U::bind("127.0.0.1:0").await.unwrap();
+        let mut receiver = DatagramFramed::with_capacity(U::bind("127.0.0.1:0").await.unwrap(), 4);
+
+        sender.send_to(b"abcdefgh", receiver.get_ref().local_addr().unwrap()).await.unwrap();
+        assert_eq!(receiver.next().await.unwrap().unwrap().0, b"abcd");
+
+        // The socket is still usable once unwrapped.
+        let receiver = receiver.into_inner();
+        sender.send_to(b"ij", receiver.local_addr().unwrap()).await.unwrap();
+        let mut buf = [0u8; 8];
+        assert_eq!(receiver.recv_from(&mut buf).await.unwrap().0, 2);
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_echo() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(echo::<::tokio::net::UdpSocket>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_sink_queues_until_flushed() {
+        ::tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(sink_queues_until_flushed::<::tokio::net::UdpSocket>());
+    }
+
+    #[cfg(all(unix, feature = "tokio-rt"))]
+    #[test]
+    fn tokio_receive_buffer_truncates() {
+        ::tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(receive_buffer_truncates::<::tokio::net::UdpSocket>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_echo() {
+        ::async_std::task::block_on(echo::<::async_std::net::UdpSocket>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_sink_queues_until_flushed() {
+        ::async_std::task::block_on(sink_queues_until_flushed::<::async_std::net::UdpSocket>());
+    }
+
+    #[cfg(all(unix, feature = "async-std-rt"))]
+    #[test]
+    fn async_std_receive_buffer_truncates() {
+        ::async_std::task::block_on(receive_buffer_truncates::<::async_std::net::UdpSocket>());
+    }
+}
@@ -0,0 +1,131 @@
+use std::net::SocketAddr;
+use std::os::unix::io::RawFd;
+
+use socket2::{SockAddr, SockAddrStorage};
+
+use super::ReceivedDatagram;
+
+
+
+/// The maximum number of messages the kernel accepts in a single call.
+const MAX_BATCH: usize = 1024;
+
+/// Sends the datagrams with a single non-blocking `sendmmsg` call.
+///
+/// Returns the number of datagrams which were sent.
+pub(super) fn sendmmsg(fd: RawFd, msgs: &[(&[u8], SocketAddr)]) -> std::io::Result<usize> {
+    let msgs = &msgs[..msgs.len().min(MAX_BATCH)];
+
+    let addrs: Vec<SockAddr> = msgs.iter().map(|(_, addr)| SockAddr::from(*addr)).collect();
+    let mut iovecs: Vec<libc::iovec> = msgs
+        .iter()
+        .map(|(buf, _)| libc::iovec {
+            iov_base: buf.as_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        })
+        .collect();
+
+    let mut hdrs: Vec<libc::mmsghdr> = iovecs
+        .iter_mut()
+        .zip(addrs.iter())
+        .map(|(iovec, addr)| {
+            let mut hdr: libc::mmsghdr = unsafe { std::mem::zeroed() };
+            hdr.msg_hdr.msg_name = addr.as_ptr() as *mut libc::c_void;
+            hdr.msg_hdr.msg_namelen = addr.len();
+            hdr.msg_hdr.msg_iov = iovec;
+            hdr.msg_hdr.msg_iovlen = 1;
+
+            hdr
+        })
+        .collect();
+
+    let sent = unsafe {
+        libc::sendmmsg(fd, hdrs.as_mut_ptr(), hdrs.len() as libc::c_uint, libc::MSG_DONTWAIT)
+    };
+
+    if sent < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(sent as usize)
+}
+
+/// Receives already queued datagrams with a single non-blocking `recvmmsg` call.
+///
+/// Every used buffer gets cleared and the datagram is received into its capacity, after which its
+/// length is set to the length of the datagram.
+pub(super) fn recvmmsg(
+    fd: RawFd,
+    bufs: &mut [Vec<u8>],
+) -> std::io::Result<Vec<ReceivedDatagram>> {
+    let len = bufs.len().min(MAX_BATCH);
+
+    let mut storages: Vec<SockAddrStorage> = (0..len).map(|_| SockAddrStorage::zeroed()).collect();
+    let mut iovecs: Vec<libc::iovec> = bufs[..len]
+        .iter_mut()
+        .map(|buf| {
+            buf.clear();
+            let spare = buf.spare_capacity_mut();
+
+            libc::iovec {
+                iov_base: spare.as_mut_ptr() as *mut libc::c_void,
+                iov_len: spare.len(),
+            }
+        })
+        .collect();
+
+    let mut hdrs: Vec<libc::mmsghdr> = iovecs
+        .iter_mut()
+        .zip(storages.iter_mut())
+        .map(|(iovec, storage)| {
+            let mut hdr: libc::mmsghdr = unsafe { std::mem::zeroed() };
+            hdr.msg_hdr.msg_name = storage as *mut SockAddrStorage as *mut libc::c_void;
+            hdr.msg_hdr.msg_namelen = storage.size_of();
+            hdr.msg_hdr.msg_iov = iovec;
+            hdr.msg_hdr.msg_iovlen = 1;
+
+            hdr
+        })
+        .collect();
+
+    let received = unsafe {
+        libc::recvmmsg(
+            fd,
+            hdrs.as_mut_ptr(),
+            hdrs.len() as libc::c_uint,
+            libc::MSG_DONTWAIT,
+            std::ptr::null_mut(),
+        )
+    };
+
+    if received < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    hdrs.iter()
+        .zip(storages)
+        .zip(bufs.iter_mut())
+        .take(received as usize)
+        .map(|((hdr, storage), buf)| {
+            let len = hdr.msg_len as usize;
+
+            // SAFETY: The kernel has initialized the first `msg_len` bytes of the capacity, which
+            // never exceeds the `iov_len` passed in.
+            unsafe { buf.set_len(len) };
+
+            let addr = unsafe { SockAddr::new(storage, hdr.msg_hdr.msg_namelen) };
+
+            match addr.as_socket() {
+                Some(addr) => Ok(ReceivedDatagram {
+                    len,
+                    addr,
+                    truncated: hdr.msg_hdr.msg_flags & libc::MSG_TRUNC != 0,
+                }),
+                None => Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "received a datagram from a non IP address",
+                )),
+            }
+        })
+        .collect()
+}
@@ -6,11 +6,61 @@ use std::net::{
 };
 use std::str::FromStr;
 use std::path::Path;
+#[cfg(windows)]
+use std::ffi::OsStr;
+
+use futures::future::FutureExt;
+#[cfg(unix)]
+use futures::io::{AsyncRead, AsyncWrite};
 
 use async_trait::async_trait;
 
 
 
+/// Batched sending and receiving of datagrams through `sendmmsg` and `recvmmsg`.
+#[cfg(all(target_os = "linux", any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod mmsg;
+
+/// Creating, sending and receiving on unix sockets of type `SOCK_SEQPACKET`.
+#[cfg(all(unix, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod seqpacket;
+
+/// Overlapped io on Windows pipe handles, waited for on the blocking pool.
+#[cfg(all(windows, feature = "async-std-rt"))]
+mod overlapped;
+
+/// Type-erased connections and listeners.
+mod boxed;
+pub use self::boxed::*;
+
+/// A [`Stream`](futures::stream::Stream) and [`Sink`](futures::sink::Sink) of datagrams.
+mod datagram;
+pub use self::datagram::*;
+
+/// Connecting with retries and exponential backoff.
+mod retry;
+pub use self::retry::*;
+
+/// Accepting connections in a loop, with a task for each of them.
+mod accept_loop;
+pub use self::accept_loop::*;
+
+/// Wrapping connections and listeners in tracing spans.
+#[cfg(feature = "tracing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+mod traced;
+#[cfg(feature = "tracing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+pub use self::traced::*;
+
+/// TCP connections and listeners of a runtime chosen at runtime.
+#[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "tokio-rt", feature = "async-std-rt"))))]
+mod any;
+#[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "tokio-rt", feature = "async-std-rt"))))]
+pub use self::any::*;
+
 /// Contains the compatibility objects for the [`tokio`](https://docs.rs/tokio) runtime.
 #[cfg(feature = "tokio-rt")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
@@ -52,6 +102,142 @@ impl UnixSocketAddr for std::os::unix::net::SocketAddr {
     }
 }
 
+#[cfg(all(unix, feature = "socket2"))]
+#[cfg_attr(docsrs, doc(cfg(all(unix, feature = "socket2"))))]
+impl UnixSocketAddr for socket2::SockAddr {
+    fn is_unnamed(&self) -> bool {
+        self.is_unnamed()
+    }
+
+    fn as_pathname(&self) -> Option<&Path> {
+        self.as_pathname()
+    }
+}
+
+
+
+/// The error returned when an address did not resolve to any socket address.
+#[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+fn no_addresses_error() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "could not resolve to any address",
+    )
+}
+
+
+
+/// Creates a new non-blocking unix stream socket and starts connecting it to `addr`.
+///
+/// Returns the socket and whether the connection is still in progress, in which case the socket
+/// becomes writable once it got established or failed.
+#[cfg(all(unix, any(feature = "tokio-rt", feature = "async-std-rt")))]
+fn connect_unix(addr: &std::os::unix::net::SocketAddr) -> std::io::Result<(socket2::Socket, bool)> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    use socket2::{Domain, SockAddr, Socket, Type};
+
+    let sock_addr = match addr.as_pathname() {
+        Some(path) => SockAddr::unix(path)?,
+        None => {
+            // Abstract names are passed on with the leading NUL byte marking them.
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            let name = {
+                #[cfg(target_os = "linux")]
+                use std::os::linux::net::SocketAddrExt;
+                #[cfg(target_os = "android")]
+                use std::os::android::net::SocketAddrExt;
+
+                addr.as_abstract_name().map(|name| [&[0], name].concat())
+            };
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            let name: Option<Vec<u8>> = None;
+
+            match name {
+                Some(name) => SockAddr::unix(OsStr::from_bytes(&name))?,
+                None => return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "can not connect to an unnamed socket address",
+                )),
+            }
+        }
+    };
+
+    let socket = Socket::new(Domain::UNIX, Type::STREAM, None)?;
+    socket.set_nonblocking(true)?;
+
+    match socket.connect(&sock_addr) {
+        Ok(()) => Ok((socket, false)),
+        Err(err) if err.raw_os_error() == Some(libc::EINPROGRESS) => Ok((socket, true)),
+        Err(err) => Err(err),
+    }
+}
+
+
+
+/// Returns the value of the `IP_TOS` option or for IPv6 sockets the `IPV6_TCLASS` option.
+#[cfg(feature = "socket2")]
+fn socket_tos(socket: socket2::SockRef<'_>) -> std::io::Result<u32> {
+    if socket.local_addr()?.is_ipv6() {
+        #[cfg(any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+            target_os = "openbsd",
+        ))]
+        return socket.tclass_v6();
+
+        #[cfg(not(any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+            target_os = "openbsd",
+        )))]
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "IPV6_TCLASS is not supported on this platform",
+        ));
+    }
+
+    socket.tos_v4()
+}
+
+/// Sets the value of the `IP_TOS` option or for IPv6 sockets the `IPV6_TCLASS` option.
+#[cfg(feature = "socket2")]
+fn set_socket_tos(socket: socket2::SockRef<'_>, tos: u32) -> std::io::Result<()> {
+    if socket.local_addr()?.is_ipv6() {
+        #[cfg(any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+            target_os = "openbsd",
+        ))]
+        return socket.set_tclass_v6(tos);
+
+        #[cfg(not(any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+            target_os = "openbsd",
+        )))]
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "IPV6_TCLASS is not supported on this platform",
+        ));
+    }
+
+    socket.set_tos_v4(tos)
+}
+
 
 
 /// An async abstraction over [`std::net::ToSocketAddrs`].
@@ -78,6 +264,15 @@ where
     }
 }
 
+#[async_trait]
+impl ToSocketAddrs for SocketAddr {
+    type Iter = std::array::IntoIter<SocketAddr, 1>;
+
+    async fn to_socket_addrs(self) -> Self::Iter {
+        IntoIterator::into_iter([self])
+    }
+}
+
 #[async_trait]
 impl ToSocketAddrs for SocketAddrV4 {
     type Iter = std::array::IntoIter<SocketAddr, 1>;
@@ -154,6 +349,20 @@ pub trait TcpStream: Sized {
     /// connection attempt (the last address) is returned.
     async fn connect<A: ToSocketAddrs + Send>(addrs: A) -> std::io::Result<Self>;
 
+    /// Opens a TCP connection to a remote host from the given local address.
+    ///
+    /// The socket gets bound to `local` before connecting, which makes it possible to choose the
+    /// source address and thereby the interface used for the outbound connection on multi-homed
+    /// hosts. Binding with a port number of 0 will request that the OS assigns a port.
+    ///
+    /// `remote` is handled just like the `addrs` argument of [`connect`].
+    ///
+    /// [`connect`]: #tymethod.connect
+    async fn connect_from<A: ToSocketAddrs + Send>(
+        local: SocketAddr,
+        remote: A,
+    ) -> std::io::Result<Self>;
+
     /// Receives data on the socket from the remote address to which it is connected, without
     /// removing that data from the queue.
     ///
@@ -197,6 +406,73 @@ pub trait TcpStream: Sized {
     /// This value sets the time-to-live field that is used in every packet sent
     /// from this socket.
     fn set_ttl(&self, ttl: u32) -> std::io::Result<()>;
+
+    /// Gets the value of the `IP_TOS` option for this socket.
+    ///
+    /// For IPv6 sockets the value of the `IPV6_TCLASS` option is returned instead.
+    ///
+    /// For more information about this option, see [`set_tos`].
+    ///
+    /// [`set_tos`]: #tymethod.set_tos
+    fn tos(&self) -> std::io::Result<u32>;
+
+    /// Sets the value for the `IP_TOS` option on this socket.
+    ///
+    /// This value sets the type-of-service field that is used in every packet sent from this
+    /// socket, which among other things carries the DSCP bits. For IPv6 sockets the
+    /// `IPV6_TCLASS` option is set instead.
+    fn set_tos(&self, tos: u32) -> std::io::Result<()>;
+
+    /// Waits for the socket to become readable.
+    ///
+    /// This function is usually paired with [`try_read`]. It can be used to drain the socket
+    /// without creating a new future for every read: wait until the socket is readable, then call
+    /// [`try_read`] in a loop until it returns an error of kind [`WouldBlock`].
+    ///
+    /// The readiness may be a false positive, in which case [`try_read`] returns
+    /// [`WouldBlock`] and this function must be called again.
+    ///
+    /// [`try_read`]: #tymethod.try_read
+    /// [`WouldBlock`]: std::io::ErrorKind::WouldBlock
+    async fn readable(&self) -> std::io::Result<()>;
+
+    /// Waits for the socket to become writable.
+    ///
+    /// This function is usually paired with [`try_write`].
+    ///
+    /// The readiness may be a false positive, in which case [`try_write`] returns
+    /// [`WouldBlock`] and this function must be called again.
+    ///
+    /// # Runtime specific behavior
+    ///
+    /// [`async_std`](https://docs.rs/async-std) does not expose the write readiness of its
+    /// sockets. Its implementation therefore registers a duplicate of the socket with the reactor
+    /// on every call, which costs a few additional system calls.
+    ///
+    /// [`try_write`]: #tymethod.try_write
+    /// [`WouldBlock`]: std::io::ErrorKind::WouldBlock
+    async fn writable(&self) -> std::io::Result<()>;
+
+    /// Tries to read data from the stream into the provided buffer, returning how many bytes were
+    /// read.
+    ///
+    /// This function never waits. If no data is available, an error of kind [`WouldBlock`] is
+    /// returned and the caller should wait with [`readable`] before trying again. A return
+    /// value of `Ok(0)` means the remote end has closed the connection.
+    ///
+    /// [`readable`]: #tymethod.readable
+    /// [`WouldBlock`]: std::io::ErrorKind::WouldBlock
+    fn try_read(&self, buf: &mut [u8]) -> std::io::Result<usize>;
+
+    /// Tries to write a buffer to the stream, returning how many bytes were written.
+    ///
+    /// This function never waits. If the socket is not ready for writing, an error of kind
+    /// [`WouldBlock`] is returned and the caller should wait with [`writable`] before trying
+    /// again.
+    ///
+    /// [`writable`]: #tymethod.writable
+    /// [`WouldBlock`]: std::io::ErrorKind::WouldBlock
+    fn try_write(&self, buf: &[u8]) -> std::io::Result<usize>;
 }
 
 
@@ -231,16 +507,212 @@ pub trait TcpListener: Sized {
 
 
 
+/// An async abstraction over [`std::net::UdpSocket`].
+#[async_trait]
+pub trait UdpSocket: Sized {
+    /// Creates a UDP socket from the given address.
+    ///
+    /// Binding with a port number of 0 will request that the OS assigns a port to this socket.
+    /// The port allocated can be queried via the [`local_addr`] method.
+    ///
+    /// [`local_addr`]: #tymethod.local_addr
+    async fn bind<A: ToSocketAddrs + Send>(addrs: A) -> std::io::Result<Self>;
+
+    /// Connects the UDP socket to a remote address.
+    ///
+    /// When connected, the [`send`] and [`recv`] methods will use the specified address for
+    /// sending and receiving messages. Additionally, a filter will be applied to [`recv_from`]
+    /// so that it only receives messages from that same address.
+    ///
+    /// [`send`]: #tymethod.send
+    /// [`recv`]: #tymethod.recv
+    /// [`recv_from`]: #tymethod.recv_from
+    async fn connect<A: ToSocketAddrs + Send>(&self, addrs: A) -> std::io::Result<()>;
+
+    /// Sends data on the socket to the remote address to which it is connected.
+    ///
+    /// On success, returns the number of bytes written.
+    async fn send(&self, buf: &[u8]) -> std::io::Result<usize>;
+
+    /// Receives data from the socket from the remote address to which it is connected.
+    ///
+    /// On success, returns the number of bytes read.
+    async fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize>;
+
+    /// Sends data on the socket to the given address.
+    ///
+    /// On success, returns the number of bytes written.
+    async fn send_to<A: ToSocketAddrs + Send>(
+        &self,
+        buf: &[u8],
+        addrs: A,
+    ) -> std::io::Result<usize>;
+
+    /// Receives data from the socket.
+    ///
+    /// On success, returns the number of bytes read and the origin.
+    async fn recv_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddr)>;
+
+    /// Receives data from the socket, without removing it from the input queue.
+    ///
+    /// On success, returns the number of bytes peeked and the origin.
+    async fn peek_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddr)>;
+
+    /// Sends a batch of datagrams, each to its own address.
+    ///
+    /// On success, returns the number of datagrams sent. This number may be smaller than the
+    /// number of given datagrams, in which case the remaining ones must be sent again.
+    ///
+    /// On Linux the datagrams are sent with a single `sendmmsg` system call where possible. On
+    /// other platforms they are sent one by one with [`send_to`].
+    ///
+    /// [`send_to`]: #tymethod.send_to
+    async fn send_batch(&self, msgs: &[(&[u8], SocketAddr)]) -> std::io::Result<usize> {
+        for (buf, addr) in msgs {
+            self.send_to(buf, *addr).await?;
+        }
+
+        Ok(msgs.len())
+    }
+
+    /// Receives a batch of datagrams, one into each of the given buffers.
+    ///
+    /// This waits until at least one datagram has arrived and then receives as many of the
+    /// already queued datagrams as there are buffers, without waiting for further datagrams.
+    /// On success, returns a [`ReceivedDatagram`] for every received datagram, in the order of
+    /// the used buffers.
+    ///
+    /// Every used buffer gets cleared and the datagram is received into its capacity, so buffers
+    /// can be created with [`Vec::with_capacity`]. Its length is then set to the length of the
+    /// datagram. A datagram larger than the capacity of its buffer gets truncated, which is
+    /// reported through [`ReceivedDatagram::truncated`]. The contents of unused buffers are left
+    /// untouched.
+    ///
+    /// On Linux the datagrams are received with a single `recvmmsg` system call where possible.
+    /// On other platforms they are received one by one with [`recv_from`]. As it does not report
+    /// truncation, datagrams filling their whole buffer are reported as truncated there.
+    ///
+    /// [`recv_from`]: #tymethod.recv_from
+    async fn recv_batch(
+        &self,
+        bufs: &mut [Vec<u8>],
+    ) -> std::io::Result<Vec<ReceivedDatagram>> {
+        let mut received = Vec::new();
+
+        for buf in bufs.iter_mut() {
+            let len = buf.len();
+            buf.clear();
+            buf.resize(buf.capacity(), 0);
+
+            let result = match received.is_empty() {
+                true => Some(self.recv_from(&mut buf[..]).await),
+                false => FutureExt::now_or_never(self.recv_from(&mut buf[..])),
+            };
+
+            match result {
+                Some(Ok((len, addr))) => {
+                    let truncated = len == buf.len();
+                    buf.truncate(len);
+
+                    received.push(ReceivedDatagram { len, addr, truncated });
+                }
+                Some(Err(err)) if received.is_empty() => {
+                    buf.clear();
+
+                    return Err(err);
+                }
+                // The buffer did not get used, so it keeps its contents.
+                _ => {
+                    buf.truncate(len);
+
+                    break;
+                }
+            }
+        }
+
+        Ok(received)
+    }
+
+    /// Returns the local address that this socket is bound to.
+    fn local_addr(&self) -> std::io::Result<SocketAddr>;
+
+    /// Returns the address of the remote peer this socket was connected to.
+    fn peer_addr(&self) -> std::io::Result<SocketAddr>;
+
+    /// Gets the value of the `SO_BROADCAST` option for this socket.
+    ///
+    /// For more information about this option, see [`set_broadcast`].
+    ///
+    /// [`set_broadcast`]: #tymethod.set_broadcast
+    fn broadcast(&self) -> std::io::Result<bool>;
+
+    /// Sets the value of the `SO_BROADCAST` option for this socket.
+    ///
+    /// When enabled, this socket is allowed to send packets to a broadcast address.
+    fn set_broadcast(&self, broadcast: bool) -> std::io::Result<()>;
+
+    /// Gets the value of the `IP_TTL` option for this socket.
+    ///
+    /// For more information about this option, see [`set_ttl`].
+    ///
+    /// [`set_ttl`]: #tymethod.set_ttl
+    fn ttl(&self) -> std::io::Result<u32>;
+
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This value sets the time-to-live field that is used in every packet sent
+    /// from this socket.
+    fn set_ttl(&self, ttl: u32) -> std::io::Result<()>;
+
+    /// Gets the value of the `IP_TOS` option for this socket.
+    ///
+    /// For IPv6 sockets the value of the `IPV6_TCLASS` option is returned instead.
+    ///
+    /// For more information about this option, see [`set_tos`].
+    ///
+    /// [`set_tos`]: #tymethod.set_tos
+    fn tos(&self) -> std::io::Result<u32>;
+
+    /// Sets the value for the `IP_TOS` option on this socket.
+    ///
+    /// This value sets the type-of-service field that is used in every packet sent from this
+    /// socket, which among other things carries the DSCP bits. For IPv6 sockets the
+    /// `IPV6_TCLASS` option is set instead.
+    fn set_tos(&self, tos: u32) -> std::io::Result<()>;
+}
+
+/// A datagram received by [`recv_batch`](UdpSocket::recv_batch).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ReceivedDatagram {
+    /// The number of bytes received into the buffer.
+    pub len: usize,
+    /// The address the datagram was sent from.
+    pub addr: SocketAddr,
+    /// Whether the datagram was larger than the capacity of the buffer and has been truncated.
+    pub truncated: bool,
+}
+
+
+
 /// An async abstraction over [`std::os::unix::net::UnixStream`].
 #[cfg(unix)]
 #[cfg_attr(docsrs, doc(cfg(unix)))]
 #[async_trait]
 pub trait UnixStream: Sized {
-    type SocketAddr: UnixSocketAddr;
+    type SocketAddr: UnixSocketAddr + Send + Sync;
 
     /// Connects to the socket to the specified address.
     async fn connect<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self>;
 
+    /// Connects to the socket at the specified socket address.
+    ///
+    /// Unlike [`connect`], this also works with addresses which are not backed by a path, e.g.
+    /// an address in the abstract namespace or one obtained from
+    /// [`UnixListener::local_addr`].
+    ///
+    /// [`connect`]: #tymethod.connect
+    async fn connect_addr(addr: &Self::SocketAddr) -> std::io::Result<Self>;
+
     /// Creates an unnamed pair of connected sockets.
     ///
     /// Returns two streams which are connected to each other.
@@ -261,11 +733,19 @@ pub trait UnixStream: Sized {
 #[async_trait]
 pub trait UnixListener: Sized {
     type UnixStream: UnixStream;
-    type SocketAddr: UnixSocketAddr;
+    type SocketAddr: UnixSocketAddr + Send + Sync;
 
     /// Creates a new unix listener bound to the specified path.
     async fn bind<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self>;
 
+    /// Creates a new unix listener bound to the specified socket address.
+    ///
+    /// Unlike [`bind`], this also works with addresses which are not backed by a path, e.g. an
+    /// address in the abstract namespace.
+    ///
+    /// [`bind`]: #tymethod.bind
+    async fn bind_addr(addr: &Self::SocketAddr) -> std::io::Result<Self>;
+
     /// Accepts a new incoming connection to this listener.
     ///
     /// When a connection is established, the corresponding stream and address will be returned.
@@ -274,3 +754,625 @@ pub trait UnixListener: Sized {
     /// Returns the local socket address of this listener.
     fn local_addr(&self) -> std::io::Result<Self::SocketAddr>;
 }
+
+
+
+/// An async abstraction over a connected unix socket of type `SOCK_SEQPACKET`.
+///
+/// Like a [`UnixStream`] the socket is connection-oriented, but like a datagram socket it
+/// preserves message boundaries: every message sent with [`send`] is received as a whole by a
+/// single [`recv`] on the other end. Some IPC protocols rely on this, e.g. the ones of systemd.
+///
+/// Neither runtime provides such sockets, so the implementations of this crate register the
+/// socket with the reactor of the runtime themselves. Not all unix platforms support the socket
+/// type, e.g. macOS does not, in which case creating a socket fails.
+///
+/// [`send`]: #tymethod.send
+/// [`recv`]: #tymethod.recv
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(all(unix, feature = "tokio-rt"))]
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::net::{TokioUnixSeqpacketStream, UnixSeqpacketStream};
+///
+/// let (a, b) = TokioUnixSeqpacketStream::pair()?;
+///
+/// a.send(b"first").await?;
+/// a.send(b"second").await?;
+///
+/// let mut buf = [0u8; 64];
+/// let n = b.recv(&mut buf).await?;
+/// assert_eq!(&buf[..n], b"first");
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[async_trait]
+pub trait UnixSeqpacketStream: Sized {
+    type SocketAddr: UnixSocketAddr + Send + Sync;
+
+    /// Connects to the socket bound to the specified path.
+    async fn connect<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self>;
+
+    /// Creates an unnamed pair of connected sockets.
+    fn pair() -> std::io::Result<(Self, Self)>;
+
+    /// Sends `buf` as a single message, returning the number of bytes sent.
+    ///
+    /// Messages larger than the send buffer of the socket fail with an error instead of being
+    /// split up.
+    async fn send(&self, buf: &[u8]) -> std::io::Result<usize>;
+
+    /// Receives a single message into `buf`, returning its length.
+    ///
+    /// If `buf` is too small to hold the message, the remainder of the message gets discarded. A
+    /// return value of `Ok(0)` means the remote end has closed the connection, unless an empty
+    /// message has been sent.
+    async fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize>;
+
+    /// Returns the socket address of the remote half of this connection.
+    fn peer_addr(&self) -> std::io::Result<Self::SocketAddr>;
+
+    /// Returns the socket address of the local half of this connection.
+    fn local_addr(&self) -> std::io::Result<Self::SocketAddr>;
+}
+
+
+
+/// An async abstraction over a listening unix socket of type `SOCK_SEQPACKET`, which accepts
+/// connections as [`UnixSeqpacketStream`]s.
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[async_trait]
+pub trait UnixSeqpacketListener: Sized {
+    type UnixSeqpacketStream: UnixSeqpacketStream;
+    type SocketAddr: UnixSocketAddr + Send + Sync;
+
+    /// Creates a new listener bound to the specified path.
+    async fn bind<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self>;
+
+    /// Accepts a new incoming connection to this listener.
+    ///
+    /// When a connection is established, the corresponding stream and address will be returned.
+    async fn accept(&self) -> std::io::Result<(Self::UnixSeqpacketStream, Self::SocketAddr)>;
+
+    /// Returns the local socket address of this listener.
+    fn local_addr(&self) -> std::io::Result<Self::SocketAddr>;
+}
+
+
+
+/// An async abstraction over anonymous OS pipes.
+///
+/// A pipe is a unidirectional channel: bytes written to the [`Writer`] can be read from the
+/// [`Reader`]. This is useful for communicating with child processes or for the self-pipe trick
+/// used in signal handling.
+///
+/// Only unix platforms are supported, as anonymous pipes on Windows do not support overlapped
+/// io. Use a [`NamedPipeServer`] and [`NamedPipeClient`] pair there instead.
+///
+/// [`Writer`]: #associatedtype.Writer
+/// [`Reader`]: #associatedtype.Reader
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(all(unix, feature = "tokio-rt"))]
+/// # async fn example() -> std::io::Result<()> {
+/// use futures::io::{AsyncReadExt, AsyncWriteExt};
+/// use fut_compat::net::{Pipe, TokioPipe};
+///
+/// let (mut reader, mut writer) = TokioPipe::pipe()?;
+///
+/// writer.write_all(b"ping").await?;
+///
+/// let mut buf = [0u8; 4];
+/// reader.read_exact(&mut buf).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+pub trait Pipe {
+    /// The reading end of the pipe.
+    type Reader: AsyncRead + Send + Unpin;
+    /// The writing end of the pipe.
+    type Writer: AsyncWrite + Send + Unpin;
+
+    /// Creates a new anonymous pipe.
+    ///
+    /// Both ends are set to nonblocking mode and registered with the runtime.
+    fn pipe() -> std::io::Result<(Self::Reader, Self::Writer)>;
+}
+
+
+
+/// An async abstraction over the server end of a Windows named pipe.
+///
+/// Unlike a [`TcpListener`] a named pipe server does not hand out new connections. Instead every
+/// instance of the server serves exactly one client. After a client has been accepted, a new
+/// instance must be created with [`create`] in order to accept the next client.
+///
+/// [`create`]: #tymethod.create
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+#[async_trait]
+pub trait NamedPipeServer: Sized {
+    /// Creates a new instance of the named pipe server with the given name.
+    ///
+    /// The name must have the form `\\.\pipe\<name>`.
+    async fn create<N: AsRef<OsStr> + Send>(name: N) -> std::io::Result<Self>;
+
+    /// Waits for a client to connect to this instance of the named pipe server.
+    ///
+    /// Afterwards data can be exchanged with the client through the futures io traits.
+    async fn accept(&self) -> std::io::Result<()>;
+
+    /// Disconnects the current client from this instance of the named pipe server.
+    fn disconnect(&self) -> std::io::Result<()>;
+}
+
+
+
+/// An async abstraction over the client end of a Windows named pipe.
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+#[async_trait]
+pub trait NamedPipeClient: Sized {
+    /// Connects to the named pipe server with the given name.
+    ///
+    /// The name must have the form `\\.\pipe\<name>`. If all instances of the server are busy,
+    /// the connection attempt is repeated until an instance becomes available.
+    async fn connect<N: AsRef<OsStr> + Send>(name: N) -> std::io::Result<Self>;
+}
+
+
+
+/// Access to the underlying socket of the network objects through [`socket2`].
+///
+/// Implemented by the runtime types implementing [`TcpStream`], [`TcpListener`], [`UdpSocket`]
+/// and [`UnixStream`]. It is kept out of these traits, so their definitions do not depend on the
+/// enabled features, and they can be implemented without `socket2`.
+#[cfg(feature = "socket2")]
+#[cfg_attr(docsrs, doc(cfg(feature = "socket2")))]
+pub trait WithSocket {
+    /// Calls `f` with a [`SockRef`](socket2::SockRef) to the underlying socket.
+    ///
+    /// This is an escape hatch for configuring socket options which are not covered by the
+    /// traits of this module.
+    ///
+    /// # Examples
+    ///
+    /// Setting the `TCP_USER_TIMEOUT` option on Linux:
+    ///
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// #
+    /// use std::time::Duration;
+    /// use fut_compat::net::{TcpStream, WithSocket};
+    ///
+    /// let stream = <tokio::net::TcpStream as TcpStream>::connect("127.0.0.1:8080").await?;
+    ///
+    /// # #[cfg(target_os = "linux")]
+    /// stream.with_socket(|socket| socket.set_tcp_user_timeout(Some(Duration::from_secs(10))))?;
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R;
+}
+
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    /// Sends 64 datagrams in batches and receives them into buffers which only have capacity.
+    async fn batch_round_trip<U: UdpSocket + Sync>() {
+        let sender = U::bind("127.0.0.1:0").await.unwrap();
+        let receiver = U::bind("127.0.0.1:0").await.unwrap();
+        let (from, to) = (sender.local_addr().unwrap(), receiver.local_addr().unwrap());
+
+        let payloads: Vec<Vec<u8>> = (0..64).map(|i| format!("datagram {}", i).into_bytes()).collect();
+        let msgs: Vec<(&[u8], SocketAddr)> = payloads.iter().map(|payload| (&payload[..], to)).collect();
+
+        let mut sent = 0;
+        while sent < msgs.len() {
+            sent += sender.send_batch(&msgs[sent..]).await.unwrap();
+        }
+
+        let mut received = Vec::new();
+        while received.len() < payloads.len() {
+            let mut bufs: Vec<Vec<u8>> = (received.len()..payloads.len()).map(|_| Vec::with_capacity(64)).collect();
+
+            for (datagram, buf) in receiver.recv_batch(&mut bufs).await.unwrap().into_iter().zip(bufs) {
+                assert_eq!(datagram.addr, from);
+                assert_eq!(datagram.len, buf.len());
+                assert!(!datagram.truncated);
+
+                received.push(buf);
+            }
+        }
+
+        assert_eq!(received, payloads);
+    }
+
+    /// Receives a datagram into a buffer which is too small for it.
+    async fn batch_truncation<U: UdpSocket + Sync>() {
+        let sender = U::bind("127.0.0.1:0").await.unwrap();
+        let receiver = U::bind("127.0.0.1:0").await.unwrap();
+
+        let payload: Vec<u8> = (0..100).collect();
+        sender.send_to(&payload, receiver.local_addr().unwrap()).await.unwrap();
+
+        let mut bufs = vec![vec![0xff; 3]];
+        bufs[0].reserve_exact(10);
+        let capacity = bufs[0].capacity();
+
+        let received = receiver.recv_batch(&mut bufs).await.unwrap();
+
+        assert_eq!(received.len(), 1);
+        assert!(received[0].truncated);
+        // The previous contents get replaced and the whole capacity is used.
+        assert_eq!(received[0].len, capacity);
+        assert_eq!(bufs[0], payload[..capacity]);
+    }
+
+    /// Sends 4 MiB, more than fits into the socket buffers, with the readiness based operations
+    /// in a drain loop on both ends.
+    async fn drain_loop<L>()
+    where
+        L: TcpListener + Sync,
+        L::TcpStream: Sync,
+    {
+        let listener = L::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (accepted, writer) = futures::join!(listener.accept(), L::TcpStream::connect(addr));
+        let (reader, writer) = (accepted.unwrap().0, writer.unwrap());
+
+        // Nothing has been sent yet, which must be reported instead of waiting.
+        let err = reader.try_read(&mut [0u8; 16]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+
+        let data: Vec<u8> = (0..4 * 1024 * 1024).map(|i: usize| (i % 251) as u8).collect();
+
+        let write = async {
+            let (mut written, mut would_block) = (0, false);
+
+            while written < data.len() {
+                writer.writable().await.unwrap();
+
+                while written < data.len() {
+                    match writer.try_write(&data[written..]) {
+                        Ok(n) => written += n,
+                        Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                            would_block = true;
+
+                            break;
+                        }
+                        Err(err) => panic!("{}", err),
+                    }
+                }
+            }
+
+            would_block
+        };
+        let read = async {
+            let mut received = Vec::with_capacity(data.len());
+            let mut buf = vec![0u8; 64 * 1024];
+
+            while received.len() < data.len() {
+                reader.readable().await.unwrap();
+
+                loop {
+                    match reader.try_read(&mut buf) {
+                        Ok(0) => panic!("the connection was closed early"),
+                        Ok(n) => received.extend_from_slice(&buf[..n]),
+                        Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                        Err(err) => panic!("{}", err),
+                    }
+                }
+            }
+
+            received
+        };
+
+        let (would_block, received) = futures::join!(write, read);
+
+        // The writer filled the socket buffers, so it had to wait for write readiness.
+        assert!(would_block);
+        assert!(received == data);
+    }
+
+    /// Writes more than fits into the pipe buffer while reading it on the other end, and reads
+    /// the end of the data once the writer is gone.
+    #[cfg(unix)]
+    async fn pipe_round_trip<P: Pipe>() {
+        use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut reader, mut writer) = P::pipe().unwrap();
+        let data: Vec<u8> = (0..1024 * 1024).map(|i: usize| (i % 251) as u8).collect();
+
+        let write = async {
+            writer.write_all(&data).await.unwrap();
+            writer.close().await.unwrap();
+            drop(writer);
+        };
+        let read = async {
+            let mut received = Vec::new();
+            reader.read_to_end(&mut received).await.unwrap();
+
+            received
+        };
+
+        let ((), received) = futures::join!(write, read);
+        assert!(received == data);
+
+        // Once the reader is gone, writing fails.
+        let (reader, mut writer) = P::pipe().unwrap();
+        drop(reader);
+        let err = writer.write_all(b"ping").await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    }
+
+    #[cfg(all(unix, feature = "tokio-rt"))]
+    #[test]
+    fn tokio_pipe_round_trip() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(pipe_round_trip::<TokioPipe>());
+    }
+
+    #[cfg(all(unix, feature = "async-std-rt"))]
+    #[test]
+    fn async_std_pipe_round_trip() {
+        ::async_std::task::block_on(pipe_round_trip::<AsyncStdPipe>());
+    }
+
+    /// Sets the type-of-service field on TCP and UDP sockets and reads it back, over IPv4 and over
+    /// IPv6 where the `IPV6_TCLASS` option is used instead.
+    async fn tos_round_trip<L, U>()
+    where
+        L: TcpListener + Sync,
+        L::TcpStream: Sync,
+        U: UdpSocket,
+    {
+        // AF41, which leaves the ECN bits alone.
+        const TOS: u32 = 0x88;
+
+        async fn check<L, U>(addr: &str)
+        where
+            L: TcpListener + Sync,
+            L::TcpStream: Sync,
+            U: UdpSocket,
+        {
+            let listener = L::bind(addr).await.unwrap();
+            let (accepted, stream) = futures::join!(
+                listener.accept(),
+                L::TcpStream::connect(listener.local_addr().unwrap()),
+            );
+            let (peer, stream) = (accepted.unwrap().0, stream.unwrap());
+
+            assert_eq!(stream.tos().unwrap(), 0);
+            stream.set_tos(TOS).unwrap();
+            assert_eq!(stream.tos().unwrap(), TOS);
+            assert_eq!(peer.tos().unwrap(), 0);
+
+            let socket = U::bind(addr).await.unwrap();
+            assert_eq!(socket.tos().unwrap(), 0);
+            socket.set_tos(TOS).unwrap();
+            assert_eq!(socket.tos().unwrap(), TOS);
+        }
+
+        check::<L, U>("127.0.0.1:0").await;
+
+        #[cfg(target_os = "linux")]
+        match U::bind("[::1]:0").await {
+            Ok(_) => check::<L, U>("[::1]:0").await,
+            Err(err) => eprintln!("skipping the IPv6 checks, ::1 is not available: {}", err),
+        }
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_tos_round_trip() {
+        ::tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(tos_round_trip::<::tokio::net::TcpListener, ::tokio::net::UdpSocket>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_tos_round_trip() {
+        ::async_std::task::block_on(tos_round_trip::<::async_std::net::TcpListener, ::async_std::net::UdpSocket>());
+    }
+
+    /// Sets `TCP_USER_TIMEOUT`, which none of the traits cover, through [`WithSocket`] on both ends
+    /// of a connection and on the listener, and reads it back.
+    #[cfg(target_os = "linux")]
+    async fn with_socket_sets_tcp_user_timeout<L>()
+    where
+        L: TcpListener + WithSocket + Sync,
+        L::TcpStream: WithSocket + Sync,
+    {
+        use std::time::Duration;
+
+        fn check<S: WithSocket>(socket: &S, timeout: Duration) {
+            assert_eq!(socket.with_socket(|socket| socket.tcp_user_timeout()).unwrap(), None);
+            socket.with_socket(|socket| socket.set_tcp_user_timeout(Some(timeout))).unwrap();
+            assert_eq!(socket.with_socket(|socket| socket.tcp_user_timeout()).unwrap(), Some(timeout));
+        }
+
+        let listener = L::bind("127.0.0.1:0").await.unwrap();
+        let (accepted, stream) = futures::join!(
+            listener.accept(),
+            L::TcpStream::connect(listener.local_addr().unwrap()),
+        );
+        let (peer, stream) = (accepted.unwrap().0, stream.unwrap());
+
+        check(&stream, Duration::from_secs(10));
+        check(&peer, Duration::from_millis(2500));
+        check(&listener, Duration::from_secs(30));
+    }
+
+    #[cfg(all(target_os = "linux", feature = "tokio-rt"))]
+    #[test]
+    fn tokio_with_socket_sets_tcp_user_timeout() {
+        let rt = ::tokio::runtime::Runtime::new().unwrap();
+
+        rt.block_on(with_socket_sets_tcp_user_timeout::<::tokio::net::TcpListener>());
+        rt.block_on(with_socket_sets_tcp_user_timeout::<crate::io::TokioCompat<::tokio::net::TcpListener>>());
+    }
+
+    #[cfg(all(target_os = "linux", feature = "async-std-rt"))]
+    #[test]
+    fn async_std_with_socket_sets_tcp_user_timeout() {
+        ::async_std::task::block_on(with_socket_sets_tcp_user_timeout::<::async_std::net::TcpListener>());
+    }
+
+    /// Connects from 127.0.0.2, which is a loopback address on Linux but not on every platform.
+    #[cfg(target_os = "linux")]
+    async fn connect_from_binds_the_local_address<L>()
+    where
+        L: TcpListener + Sync,
+        L::TcpStream: Sync + std::fmt::Debug,
+    {
+        let listener = L::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let local: SocketAddr = "127.0.0.2:0".parse().unwrap();
+
+        let (accepted, stream) = futures::join!(listener.accept(), L::TcpStream::connect_from(local, addr));
+        let (peer, peer_addr) = accepted.unwrap();
+        let stream = stream.unwrap();
+
+        assert_eq!(peer.peer_addr().unwrap().ip(), local.ip());
+        assert_eq!(peer_addr, stream.local_addr().unwrap());
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+
+        // Nothing listens on the port any more, which gets reported once the connection fails.
+        drop((listener, peer, stream));
+        let err = L::TcpStream::connect_from(local, addr).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+    }
+
+    #[cfg(all(target_os = "linux", feature = "tokio-rt"))]
+    #[test]
+    fn tokio_connect_from_binds_the_local_address() {
+        ::tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(connect_from_binds_the_local_address::<::tokio::net::TcpListener>());
+    }
+
+    #[cfg(all(target_os = "linux", feature = "async-std-rt"))]
+    #[test]
+    fn async_std_connect_from_binds_the_local_address() {
+        ::async_std::task::block_on(connect_from_binds_the_local_address::<::async_std::net::TcpListener>());
+    }
+
+    /// Binds a listener in the abstract namespace and connects to the address it reports, as well
+    /// as to one backed by a path.
+    #[cfg(target_os = "linux")]
+    async fn connect_addr_reaches_abstract_names<L>()
+    where
+        L: UnixListener,
+        L::UnixStream: UnixStream<SocketAddr = L::SocketAddr>,
+        L::SocketAddr: From<std::os::unix::net::SocketAddr> + Into<std::os::unix::net::SocketAddr>,
+    {
+        use std::os::linux::net::SocketAddrExt;
+
+        async fn connect<L: UnixListener>(listener: &L, addr: &L::SocketAddr) -> L::UnixStream
+        where
+            L::UnixStream: UnixStream<SocketAddr = L::SocketAddr>,
+        {
+            let (accepted, client) = futures::join!(listener.accept(), L::UnixStream::connect_addr(addr));
+            let (server, client) = (accepted.unwrap().0, client.unwrap());
+
+            // The client connected to this listener and not to some other socket.
+            assert!(server.local_addr().unwrap().as_pathname() == addr.as_pathname());
+            assert!(client.peer_addr().unwrap().as_pathname() == addr.as_pathname());
+
+            client
+        }
+
+        let name = format!("fut-compat-connect-addr-{}", std::process::id());
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes()).unwrap();
+        let listener = L::bind_addr(&addr.into()).await.unwrap();
+        let local = listener.local_addr().unwrap();
+
+        assert!(local.as_pathname().is_none());
+        assert!(!local.is_unnamed());
+        let client = connect(&listener, &local).await;
+        let peer: std::os::unix::net::SocketAddr = client.peer_addr().unwrap().into();
+        assert_eq!(peer.as_abstract_name(), Some(name.as_bytes()));
+
+        let path = std::env::temp_dir().join(format!("{}.sock", name));
+        let _ = std::fs::remove_file(&path);
+        let listener = L::bind(&path).await.unwrap();
+        connect(&listener, &listener.local_addr().unwrap()).await;
+        std::fs::remove_file(&path).unwrap();
+
+        // Nothing listens on the path any more.
+        let err = L::UnixStream::connect_addr(&listener.local_addr().unwrap()).await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+        // The ends of a socket pair have no name to connect to.
+        let (unnamed, _other) = L::UnixStream::pair().unwrap();
+        let err = L::UnixStream::connect_addr(&unnamed.local_addr().unwrap()).await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(all(target_os = "linux", feature = "tokio-rt"))]
+    #[test]
+    fn tokio_connect_addr_reaches_abstract_names() {
+        ::tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(connect_addr_reaches_abstract_names::<::tokio::net::UnixListener>());
+    }
+
+    #[cfg(all(target_os = "linux", feature = "async-std-rt"))]
+    #[test]
+    fn async_std_connect_addr_reaches_abstract_names() {
+        ::async_std::task::block_on(
+            connect_addr_reaches_abstract_names::<::async_std::os::unix::net::UnixListener>(),
+        );
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_drain_loop() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(drain_loop::<::tokio::net::TcpListener>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_drain_loop() {
+        ::async_std::task::block_on(drain_loop::<::async_std::net::TcpListener>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_recv_batch_receives_64_datagrams() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(batch_round_trip::<::tokio::net::UdpSocket>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_recv_batch_reports_truncation() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(batch_truncation::<::tokio::net::UdpSocket>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_recv_batch_receives_64_datagrams() {
+        ::async_std::task::block_on(batch_round_trip::<::async_std::net::UdpSocket>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_recv_batch_reports_truncation() {
+        ::async_std::task::block_on(batch_truncation::<::async_std::net::UdpSocket>());
+    }
+}
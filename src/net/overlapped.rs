@@ -0,0 +1,248 @@
+use std::io::{Error, ErrorKind};
+use std::os::windows::io::{AsRawHandle, OwnedHandle, RawHandle};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::future::FutureExt;
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::ready;
+
+use ::async_std::task::{spawn_blocking, JoinHandle};
+
+use windows_sys::Win32::Foundation::{CloseHandle, GetLastError, ERROR_BROKEN_PIPE, ERROR_IO_PENDING};
+use windows_sys::Win32::Storage::FileSystem::{ReadFile, WriteFile};
+use windows_sys::Win32::System::IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED};
+use windows_sys::Win32::System::Threading::CreateEventW;
+
+
+
+/// The maximum amount of data transferred by a single read or write.
+const MAX_CHUNK: usize = 64 * 1024;
+
+/// Starts an overlapped operation with `op` and blocks until it has completed.
+///
+/// Every operation waits on its own event, so reads, writes and connects can be in flight on the
+/// same handle at the same time.
+pub(super) fn wait_overlapped(
+    handle: RawHandle,
+    op: impl FnOnce(*mut OVERLAPPED) -> i32,
+) -> std::io::Result<u32> {
+    // SAFETY: All pointers may be null, which creates an unnamed, manual-reset event.
+    let event = unsafe { CreateEventW(std::ptr::null(), 1, 0, std::ptr::null()) };
+    if event.is_null() {
+        return Err(Error::last_os_error());
+    }
+
+    // SAFETY: `OVERLAPPED` is a plain C struct for which all zeroes is a valid value.
+    let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+    overlapped.hEvent = event;
+
+    let result = match op(&mut overlapped) {
+        // SAFETY: `GetLastError` has no preconditions.
+        0 if unsafe { GetLastError() } != ERROR_IO_PENDING => Err(Error::last_os_error()),
+        _ => {
+            let mut transferred = 0;
+
+            // SAFETY: `overlapped` outlives the operation, as we wait for it to complete.
+            match unsafe { GetOverlappedResult(handle, &overlapped, &mut transferred, 1) } {
+                0 => Err(Error::last_os_error()),
+                _ => Ok(transferred),
+            }
+        }
+    };
+
+    // SAFETY: The event was created above and is no longer used by the completed operation.
+    unsafe { CloseHandle(event) };
+
+    result
+}
+
+/// The state of the read side of an [`OverlappedPipe`].
+#[derive(Debug)]
+enum ReadState {
+    /// The data of the last read together with the amount of it already returned to the caller.
+    Idle(Vec<u8>, usize),
+    Busy(JoinHandle<std::io::Result<Vec<u8>>>),
+}
+
+/// The state of the write side of an [`OverlappedPipe`].
+#[derive(Debug)]
+enum WriteState {
+    Idle(Vec<u8>),
+    Busy(JoinHandle<(Vec<u8>, std::io::Result<()>)>),
+}
+
+/// A pipe handle opened with `FILE_FLAG_OVERLAPPED`, whose reads and writes are performed on the
+/// blocking thread pool.
+///
+/// Reads and writes are tracked separately, so a pending read never holds up a write and the pipe
+/// can be used in both directions at the same time. Like with
+/// [`async_std::fs::File`](::async_std::fs::File), a write reports success once its data has been
+/// handed to the pool, and [`poll_flush`](AsyncWrite::poll_flush) waits for it to complete.
+/// Operations which are still pending when the pipe gets dropped are cancelled.
+#[derive(Debug)]
+pub(super) struct OverlappedPipe {
+    handle: Arc<OwnedHandle>,
+    read: ReadState,
+    write: WriteState,
+}
+
+impl OverlappedPipe {
+    /// Wraps a handle which has been opened in overlapped mode.
+    pub(super) fn new(handle: OwnedHandle) -> Self {
+        Self {
+            handle: Arc::new(handle),
+            read: ReadState::Idle(Vec::new(), 0),
+            write: WriteState::Idle(Vec::new()),
+        }
+    }
+
+    /// Returns the handle, for operations performed outside of this type.
+    pub(super) fn handle(&self) -> &Arc<OwnedHandle> {
+        &self.handle
+    }
+}
+
+impl Drop for OverlappedPipe {
+    fn drop(&mut self) {
+        // SAFETY: The handle is valid, and cancelling makes the blocked threads of the pool return.
+        unsafe { CancelIoEx(self.handle.as_raw_handle(), std::ptr::null()) };
+    }
+}
+
+impl AsyncRead for OverlappedPipe {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = Pin::into_inner(self);
+
+        loop {
+            match &mut this.read {
+                ReadState::Idle(data, pos) => {
+                    if *pos < data.len() {
+                        let n = (data.len() - *pos).min(buf.len());
+
+                        buf[..n].copy_from_slice(&data[*pos..*pos + n]);
+                        *pos += n;
+
+                        return Poll::Ready(Ok(n));
+                    }
+
+                    if buf.is_empty() {
+                        return Poll::Ready(Ok(0));
+                    }
+
+                    let mut data = std::mem::take(data);
+                    let len = buf.len().min(MAX_CHUNK);
+                    let handle = this.handle.clone();
+
+                    this.read = ReadState::Busy(spawn_blocking(move || {
+                        data.clear();
+                        data.resize(len, 0);
+
+                        // SAFETY: `data` is valid for writes of `len` bytes until the read completes.
+                        let result = wait_overlapped(handle.as_raw_handle(), |overlapped| unsafe {
+                            ReadFile(handle.as_raw_handle(), data.as_mut_ptr(), len as u32, std::ptr::null_mut(), overlapped)
+                        });
+
+                        match result {
+                            Ok(n) => data.truncate(n as usize),
+                            // The other end closed the pipe, which is the end of the stream.
+                            Err(err) if err.raw_os_error() == Some(ERROR_BROKEN_PIPE as i32) => data.clear(),
+                            Err(err) => return Err(err),
+                        }
+
+                        Ok(data)
+                    }));
+                }
+                ReadState::Busy(task) => {
+                    let result = ready!(task.poll_unpin(cx));
+
+                    match result {
+                        Ok(data) if data.is_empty() => {
+                            this.read = ReadState::Idle(data, 0);
+
+                            return Poll::Ready(Ok(0));
+                        }
+                        Ok(data) => this.read = ReadState::Idle(data, 0),
+                        Err(err) => {
+                            this.read = ReadState::Idle(Vec::new(), 0);
+
+                            return Poll::Ready(Err(err));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl AsyncWrite for OverlappedPipe {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = Pin::into_inner(self);
+
+        // Report the outcome of the previous write before accepting new data.
+        ready!(Pin::new(&mut *this).poll_flush(cx))?;
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let mut data = match &mut this.write {
+            WriteState::Idle(data) => std::mem::take(data),
+            WriteState::Busy(_) => unreachable!("the pending write has been flushed"),
+        };
+        let n = buf.len().min(MAX_CHUNK);
+        let handle = this.handle.clone();
+
+        data.clear();
+        data.extend_from_slice(&buf[..n]);
+
+        this.write = WriteState::Busy(spawn_blocking(move || {
+            let mut written = 0;
+
+            while written < data.len() {
+                let chunk = &data[written..];
+
+                // SAFETY: `chunk` is valid for reads of its length until the write completes.
+                let result = wait_overlapped(handle.as_raw_handle(), |overlapped| unsafe {
+                    WriteFile(handle.as_raw_handle(), chunk.as_ptr(), chunk.len() as u32, std::ptr::null_mut(), overlapped)
+                });
+
+                match result {
+                    Ok(0) => return (data, Err(Error::from(ErrorKind::WriteZero))),
+                    Ok(n) => written += n as usize,
+                    Err(err) => return (data, Err(err)),
+                }
+            }
+
+            (data, Ok(()))
+        }));
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = Pin::into_inner(self);
+
+        if let WriteState::Busy(task) = &mut this.write {
+            let (data, result) = ready!(task.poll_unpin(cx));
+            this.write = WriteState::Idle(data);
+
+            return Poll::Ready(result);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
@@ -0,0 +1,334 @@
+use super::*;
+use crate::task::Timer;
+
+use std::time::Duration;
+
+use futures::future::{self, Either};
+
+
+
+/// Describes how often and how fast [`connect_with_retry`] retries a failed connection attempt.
+///
+/// The delay before the first retry is [`initial_delay`](RetryPolicy::initial_delay). After each
+/// failed attempt it gets multiplied by the [`multiplier`](RetryPolicy::multiplier), but never
+/// exceeds the [`max_delay`](RetryPolicy::max_delay).
+///
+/// By default the policy starts with a delay of 100ms, doubles it after each attempt up to a
+/// maximum of 10s and retries without any limit on the attempts or on the overall duration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    initial_delay: Duration,
+    multiplier: f64,
+    max_delay: Duration,
+    max_attempts: Option<u32>,
+    deadline: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(100),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(10),
+            max_attempts: None,
+            deadline: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a new policy with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the delay between the first and the second attempt.
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Sets the factor by which the delay grows after each failed attempt.
+    ///
+    /// Values below `1.0` are treated as `1.0`, i.e. a constant delay.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Sets the upper bound for the delay between two attempts.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Sets the maximum number of connection attempts, including the first one.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Sets the overall time after which no further attempts are made.
+    ///
+    /// A connection attempt still in progress when the deadline passes gets cancelled.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns the delay following the given one.
+    fn next_delay(&self, delay: Duration) -> Duration {
+        let multiplier = self.multiplier.max(1.0);
+
+        delay.mul_f64(multiplier).min(self.max_delay)
+    }
+}
+
+/// Returns `true` if a failed connection attempt with this error is worth retrying.
+fn is_retryable(err: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+
+    matches!(
+        err.kind(),
+        ErrorKind::ConnectionRefused
+            | ErrorKind::TimedOut
+            | ErrorKind::HostUnreachable
+            | ErrorKind::NetworkUnreachable
+    )
+}
+
+fn deadline_error() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        "deadline elapsed while connecting",
+    )
+}
+
+/// Connects to `addrs`, retrying with an exponential backoff according to `policy`.
+///
+/// Only errors indicating that the remote is not (yet) reachable get retried, i.e. connection
+/// refused, timed out, host unreachable and network unreachable. Any other error is returned
+/// immediately. When the attempts are exhausted, the error of the last attempt is returned.
+///
+/// The waiting between the attempts is done with the [`Timer`] `T`.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use std::time::Duration;
+///
+/// use fut_compat::io::TokioCompat;
+/// use fut_compat::net::{connect_with_retry, RetryPolicy};
+/// use fut_compat::task::TokioExecutor;
+///
+/// let policy = RetryPolicy::new()
+///     .initial_delay(Duration::from_millis(50))
+///     .deadline(Duration::from_secs(30));
+///
+/// let stream: TokioCompat<tokio::net::TcpStream> =
+///     connect_with_retry::<_, TokioExecutor, _>("127.0.0.1:5432", policy).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn connect_with_retry<S, T, A>(addrs: A, policy: RetryPolicy) -> std::io::Result<S>
+where
+    S: TcpStream,
+    T: Timer,
+    A: ToSocketAddrs + Clone + Send,
+{
+    let deadline = policy.deadline.map(|deadline| T::now() + deadline);
+    let remaining = || deadline.map(|deadline| deadline.saturating_duration_since(T::now()));
+
+    let mut delay = policy.initial_delay;
+    let mut attempts = 0u32;
+
+    loop {
+        attempts += 1;
+
+        let connect = S::connect(addrs.clone());
+        let result = match remaining() {
+            Some(remaining) => {
+                let sleep = T::sleep(remaining);
+                futures::pin_mut!(sleep);
+
+                match future::select(connect, sleep).await {
+                    Either::Left((result, _)) => result,
+                    Either::Right(((), _)) => return Err(deadline_error()),
+                }
+            }
+            None => connect.await,
+        };
+
+        let err = match result {
+            Ok(stream) => return Ok(stream),
+            Err(err) => err,
+        };
+
+        if !is_retryable(&err) {
+            return Err(err);
+        }
+        if let Some(max_attempts) = policy.max_attempts {
+            if attempts >= max_attempts {
+                return Err(err);
+            }
+        }
+
+        let wait = match remaining() {
+            Some(remaining) if remaining <= delay => return Err(err),
+            Some(_) | None => delay,
+        };
+
+        T::sleep(wait).await;
+
+        delay = policy.next_delay(delay);
+    }
+}
+
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Resolves to the given addresses and counts how often it got resolved, which is once per
+    /// connection attempt.
+    #[derive(Clone)]
+    struct CountingAddrs {
+        addrs: Vec<SocketAddr>,
+        resolved: Arc<AtomicUsize>,
+    }
+
+    impl CountingAddrs {
+        fn new(addrs: Vec<SocketAddr>) -> Self {
+            Self {
+                addrs,
+                resolved: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+
+        fn attempts(&self) -> usize {
+            self.resolved.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl ToSocketAddrs for CountingAddrs {
+        type Iter = std::vec::IntoIter<SocketAddr>;
+
+        async fn to_socket_addrs(self) -> Self::Iter {
+            self.resolved.fetch_add(1, Ordering::SeqCst);
+
+            self.addrs.into_iter()
+        }
+    }
+
+    /// Returns an address on which nothing listens, at least for a moment.
+    async fn unused_addr<L: TcpListener>() -> SocketAddr {
+        L::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap()
+    }
+
+    /// A refused connection gets retried until the attempts are exhausted.
+    async fn refused_until_out_of_attempts<L: TcpListener, T: Timer>() {
+        let addrs = CountingAddrs::new(vec![unused_addr::<L>().await]);
+        let policy = RetryPolicy::new().initial_delay(Duration::from_millis(1)).max_attempts(4);
+
+        let err = connect_with_retry::<L::TcpStream, T, _>(addrs.clone(), policy).await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+        assert_eq!(addrs.attempts(), 4);
+    }
+
+    /// Errors which retrying does not fix are returned after the first attempt.
+    async fn other_errors_are_not_retried<L: TcpListener, T: Timer>() {
+        let addrs = CountingAddrs::new(Vec::new());
+        let policy = RetryPolicy::new().initial_delay(Duration::from_millis(1)).max_attempts(4);
+
+        assert!(connect_with_retry::<L::TcpStream, T, _>(addrs.clone(), policy).await.is_err());
+        assert_eq!(addrs.attempts(), 1);
+    }
+
+    /// No attempt gets started once the next one would begin after the deadline.
+    async fn refused_until_the_deadline<L: TcpListener, T: Timer>() {
+        let addrs = CountingAddrs::new(vec![unused_addr::<L>().await]);
+        let policy = RetryPolicy::new()
+            .initial_delay(Duration::from_millis(20))
+            .multiplier(1.0)
+            .deadline(Duration::from_millis(100));
+
+        let start = std::time::Instant::now();
+        let err = connect_with_retry::<L::TcpStream, T, _>(addrs.clone(), policy).await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!((2..=6).contains(&addrs.attempts()), "{} attempts", addrs.attempts());
+    }
+
+    /// Connects to a listener which only starts listening after a few attempts.
+    async fn connects_once_the_listener_is_up<L: TcpListener, T: Timer>() {
+        let addr = unused_addr::<L>().await;
+        let addrs = CountingAddrs::new(vec![addr]);
+        let policy = RetryPolicy::new()
+            .initial_delay(Duration::from_millis(10))
+            .max_delay(Duration::from_millis(20))
+            .deadline(Duration::from_secs(10));
+
+        let listen = async {
+            T::sleep(Duration::from_millis(100)).await;
+
+            let listener = L::bind(addr).await.unwrap();
+            listener.accept().await.unwrap()
+        };
+
+        let (stream, (_, peer_addr)) = futures::join!(
+            connect_with_retry::<L::TcpStream, T, _>(addrs.clone(), policy),
+            listen,
+        );
+        assert_eq!(stream.unwrap().local_addr().unwrap(), peer_addr);
+        assert!(addrs.attempts() > 1);
+    }
+
+    #[test]
+    fn delays_grow_up_to_the_maximum() {
+        let policy = RetryPolicy::new();
+        let delays: Vec<Duration> = std::iter::successors(Some(Duration::from_millis(100)), |delay| {
+            Some(policy.next_delay(*delay))
+        })
+        .take(9)
+        .collect();
+        assert_eq!(delays.last(), Some(&Duration::from_secs(10)));
+        assert_eq!(delays[..4], [100, 200, 400, 800].map(Duration::from_millis));
+
+        // A shrinking delay would make no sense, so it stays constant instead.
+        let policy = RetryPolicy::new().multiplier(0.5);
+        assert_eq!(policy.next_delay(Duration::from_millis(100)), Duration::from_millis(100));
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_retries() {
+        use crate::task::TokioExecutor;
+
+        let rt = ::tokio::runtime::Runtime::new().unwrap();
+
+        rt.block_on(refused_until_out_of_attempts::<::tokio::net::TcpListener, TokioExecutor>());
+        rt.block_on(other_errors_are_not_retried::<::tokio::net::TcpListener, TokioExecutor>());
+        rt.block_on(refused_until_the_deadline::<::tokio::net::TcpListener, TokioExecutor>());
+        rt.block_on(connects_once_the_listener_is_up::<::tokio::net::TcpListener, TokioExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_retries() {
+        use crate::task::AsyncStdExecutor;
+
+        ::async_std::task::block_on(async {
+            refused_until_out_of_attempts::<::async_std::net::TcpListener, AsyncStdExecutor>().await;
+            other_errors_are_not_retried::<::async_std::net::TcpListener, AsyncStdExecutor>().await;
+            refused_until_the_deadline::<::async_std::net::TcpListener, AsyncStdExecutor>().await;
+            connects_once_the_listener_is_up::<::async_std::net::TcpListener, AsyncStdExecutor>().await;
+        });
+    }
+}
@@ -0,0 +1,88 @@
+use std::io::Read;
+use std::path::Path;
+
+use socket2::{Domain, SockAddr, Socket, Type};
+
+
+
+/// The flags passed to `send`, which keep a closed connection from raising `SIGPIPE` on the
+/// platforms supporting it.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+))]
+const SEND_FLAGS: libc::c_int = libc::MSG_NOSIGNAL;
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+)))]
+const SEND_FLAGS: libc::c_int = 0;
+
+/// The backlog of listening sockets, the same as the one of tokio's listeners.
+const BACKLOG: i32 = 1024;
+
+/// Creates a new non-blocking socket.
+fn socket() -> std::io::Result<Socket> {
+    let socket = Socket::new(Domain::UNIX, Type::SEQPACKET, None)?;
+    socket.set_nonblocking(true)?;
+
+    Ok(socket)
+}
+
+/// Creates a new non-blocking socket and starts connecting it to `path`.
+///
+/// Returns the socket and whether the connection is still in progress, in which case the socket
+/// becomes writable once it got established or failed.
+pub(super) fn connect(path: &Path) -> std::io::Result<(Socket, bool)> {
+    let socket = socket()?;
+
+    match socket.connect(&SockAddr::unix(path)?) {
+        Ok(()) => Ok((socket, false)),
+        Err(err) if err.raw_os_error() == Some(libc::EINPROGRESS) => Ok((socket, true)),
+        Err(err) => Err(err),
+    }
+}
+
+/// Creates a new non-blocking socket listening on `path`.
+pub(super) fn bind(path: &Path) -> std::io::Result<Socket> {
+    let socket = socket()?;
+    socket.bind(&SockAddr::unix(path)?)?;
+    socket.listen(BACKLOG)?;
+
+    Ok(socket)
+}
+
+/// Creates a pair of connected non-blocking sockets.
+pub(super) fn pair() -> std::io::Result<(Socket, Socket)> {
+    let (a, b) = Socket::pair(Domain::UNIX, Type::SEQPACKET, None)?;
+    a.set_nonblocking(true)?;
+    b.set_nonblocking(true)?;
+
+    Ok((a, b))
+}
+
+/// Accepts a connection of the listening `socket`, returning a non-blocking socket.
+pub(super) fn accept(socket: &Socket) -> std::io::Result<(Socket, SockAddr)> {
+    let (socket, addr) = socket.accept()?;
+    socket.set_nonblocking(true)?;
+
+    Ok((socket, addr))
+}
+
+/// Sends `buf` as a single message.
+pub(super) fn send(socket: &Socket, buf: &[u8]) -> std::io::Result<usize> {
+    socket.send_with_flags(buf, SEND_FLAGS)
+}
+
+/// Receives a single message into `buf`.
+pub(super) fn recv(mut socket: &Socket, buf: &mut [u8]) -> std::io::Result<usize> {
+    socket.read(buf)
+}
@@ -3,6 +3,15 @@ use crate::io::TokioCompat;
 
 use ::tokio::net;
 
+use socket2::SockRef;
+
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, AsFd, BorrowedFd, RawFd};
+#[cfg(unix)]
+use ::tokio::io::Interest;
+#[cfg(unix)]
+use ::tokio::io::unix::AsyncFd;
+
 
 
 #[cfg(unix)]
@@ -27,6 +36,34 @@ impl TcpStream for net::TcpStream {
         Self::connect(&addrs[..]).await
     }
 
+    async fn connect_from<A: ToSocketAddrs + Send>(
+        local: SocketAddr,
+        remote: A,
+    ) -> std::io::Result<Self> {
+        let addrs: Vec<SocketAddr> = ToSocketAddrs::to_socket_addrs(remote).await.collect();
+
+        let mut last_err = None;
+
+        for addr in addrs {
+            let socket = match addr {
+                SocketAddr::V4(_) => net::TcpSocket::new_v4()?,
+                SocketAddr::V6(_) => net::TcpSocket::new_v6()?,
+            };
+
+            let result = match socket.bind(local) {
+                Ok(()) => socket.connect(addr).await,
+                Err(err) => Err(err),
+            };
+
+            match result {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        Err(last_err.unwrap_or_else(no_addresses_error))
+    }
+
     async fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
         self.peek(buf).await
     }
@@ -54,6 +91,36 @@ impl TcpStream for net::TcpStream {
     fn set_ttl(&self, ttl: u32) -> std::io::Result<()> {
         self.set_ttl(ttl)
     }
+
+    fn tos(&self) -> std::io::Result<u32> {
+        socket_tos(SockRef::from(self))
+    }
+
+    fn set_tos(&self, tos: u32) -> std::io::Result<()> {
+        set_socket_tos(SockRef::from(self), tos)
+    }
+
+    async fn readable(&self) -> std::io::Result<()> {
+        self.readable().await
+    }
+
+    async fn writable(&self) -> std::io::Result<()> {
+        self.writable().await
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.try_read(buf)
+    }
+
+    fn try_write(&self, buf: &[u8]) -> std::io::Result<usize> {
+        self.try_write(buf)
+    }
+}
+
+impl WithSocket for net::TcpStream {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self))
+    }
 }
 
 #[async_trait]
@@ -66,6 +133,15 @@ impl TcpStream for TokioCompat<net::TcpStream> {
         Ok(Self::new(inner))
     }
 
+    async fn connect_from<A: ToSocketAddrs + Send>(
+        local: SocketAddr,
+        remote: A,
+    ) -> std::io::Result<Self> {
+        let inner = <net::TcpStream as TcpStream>::connect_from(local, remote).await?;
+
+        Ok(Self::new(inner))
+    }
+
     async fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
         self.get_ref().peek(buf).await
     }
@@ -93,6 +169,36 @@ impl TcpStream for TokioCompat<net::TcpStream> {
     fn set_ttl(&self, ttl: u32) -> std::io::Result<()> {
         self.get_ref().set_ttl(ttl)
     }
+
+    fn tos(&self) -> std::io::Result<u32> {
+        socket_tos(SockRef::from(self.get_ref()))
+    }
+
+    fn set_tos(&self, tos: u32) -> std::io::Result<()> {
+        set_socket_tos(SockRef::from(self.get_ref()), tos)
+    }
+
+    async fn readable(&self) -> std::io::Result<()> {
+        self.get_ref().readable().await
+    }
+
+    async fn writable(&self) -> std::io::Result<()> {
+        self.get_ref().writable().await
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.get_ref().try_read(buf)
+    }
+
+    fn try_write(&self, buf: &[u8]) -> std::io::Result<usize> {
+        self.get_ref().try_write(buf)
+    }
+}
+
+impl WithSocket for TokioCompat<net::TcpStream> {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self.get_ref()))
+    }
 }
 
 
@@ -116,6 +222,145 @@ impl TcpListener for net::TcpListener {
     }
 }
 
+impl WithSocket for net::TcpListener {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self))
+    }
+}
+
+/// Accepts the connections as [`TokioCompat`] objects, so they implement the io traits of
+/// [`futures`].
+#[async_trait]
+impl TcpListener for TokioCompat<net::TcpListener> {
+    type TcpStream = TokioCompat<net::TcpStream>;
+
+    async fn bind<A: ToSocketAddrs + Send>(addrs: A) -> std::io::Result<Self> {
+        <net::TcpListener as TcpListener>::bind(addrs).await.map(Self::new)
+    }
+
+    async fn accept(&self) -> std::io::Result<(Self::TcpStream, SocketAddr)> {
+        let (stream, addr) = self.get_ref().accept().await?;
+
+        Ok((TokioCompat::new(stream), addr))
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.get_ref().local_addr()
+    }
+}
+
+impl WithSocket for TokioCompat<net::TcpListener> {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self.get_ref()))
+    }
+}
+
+
+
+#[async_trait]
+impl UdpSocket for net::UdpSocket {
+    async fn bind<A: ToSocketAddrs + Send>(addrs: A) -> std::io::Result<Self> {
+        let addrs: Vec<SocketAddr> = ToSocketAddrs::to_socket_addrs(addrs).await.collect();
+
+        Self::bind(&addrs[..]).await
+    }
+
+    async fn connect<A: ToSocketAddrs + Send>(&self, addrs: A) -> std::io::Result<()> {
+        let addrs: Vec<SocketAddr> = ToSocketAddrs::to_socket_addrs(addrs).await.collect();
+
+        self.connect(&addrs[..]).await
+    }
+
+    async fn send(&self, buf: &[u8]) -> std::io::Result<usize> {
+        self.send(buf).await
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.recv(buf).await
+    }
+
+    async fn send_to<A: ToSocketAddrs + Send>(
+        &self,
+        buf: &[u8],
+        addrs: A,
+    ) -> std::io::Result<usize> {
+        let addrs: Vec<SocketAddr> = ToSocketAddrs::to_socket_addrs(addrs).await.collect();
+
+        self.send_to(buf, &addrs[..]).await
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+        self.recv_from(buf).await
+    }
+
+    async fn peek_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+        self.peek_from(buf).await
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn send_batch(&self, msgs: &[(&[u8], SocketAddr)]) -> std::io::Result<usize> {
+        if msgs.is_empty() {
+            return Ok(0);
+        }
+
+        let fd = self.as_raw_fd();
+
+        self.async_io(Interest::WRITABLE, || super::mmsg::sendmmsg(fd, msgs)).await
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn recv_batch(
+        &self,
+        bufs: &mut [Vec<u8>],
+    ) -> std::io::Result<Vec<ReceivedDatagram>> {
+        if bufs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let fd = self.as_raw_fd();
+
+        self.async_io(Interest::READABLE, || super::mmsg::recvmmsg(fd, bufs)).await
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.local_addr()
+    }
+
+    fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+        self.peer_addr()
+    }
+
+    fn broadcast(&self) -> std::io::Result<bool> {
+        self.broadcast()
+    }
+
+    fn set_broadcast(&self, broadcast: bool) -> std::io::Result<()> {
+        self.set_broadcast(broadcast)
+    }
+
+    fn ttl(&self) -> std::io::Result<u32> {
+        self.ttl()
+    }
+
+    fn set_ttl(&self, ttl: u32) -> std::io::Result<()> {
+        self.set_ttl(ttl)
+    }
+
+    fn tos(&self) -> std::io::Result<u32> {
+        socket_tos(SockRef::from(self))
+    }
+
+    fn set_tos(&self, tos: u32) -> std::io::Result<()> {
+        set_socket_tos(SockRef::from(self), tos)
+    }
+}
+
+impl WithSocket for net::UdpSocket {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self))
+    }
+}
+
 
 
 #[cfg(unix)]
@@ -128,6 +373,24 @@ impl UnixStream for net::UnixStream {
         Self::connect(path).await
     }
 
+    async fn connect_addr(addr: &Self::SocketAddr) -> std::io::Result<Self> {
+        let addr: std::os::unix::net::SocketAddr = addr.clone().into();
+
+        // tokio can only connect to paths, so the socket gets connected through socket2.
+        let (socket, in_progress) = connect_unix(&addr)?;
+        let stream = Self::from_std(socket.into())?;
+
+        if in_progress {
+            stream.writable().await?;
+
+            if let Some(err) = stream.take_error()? {
+                return Err(err);
+            }
+        }
+
+        Ok(stream)
+    }
+
     fn pair() -> std::io::Result<(Self, Self)> {
         Self::pair()
     }
@@ -141,6 +404,14 @@ impl UnixStream for net::UnixStream {
     }
 }
 
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl WithSocket for net::UnixStream {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self))
+    }
+}
+
 #[cfg(unix)]
 #[cfg_attr(docsrs, doc(cfg(unix)))]
 #[async_trait]
@@ -153,6 +424,12 @@ impl UnixStream for TokioCompat<net::UnixStream> {
         Ok(Self::new(inner))
     }
 
+    async fn connect_addr(addr: &Self::SocketAddr) -> std::io::Result<Self> {
+        let inner = <net::UnixStream as UnixStream>::connect_addr(addr).await?;
+
+        Ok(Self::new(inner))
+    }
+
     fn pair() -> std::io::Result<(Self, Self)> {
         net::UnixStream::pair().map(|(inner1, inner2)| (Self::new(inner1), Self::new(inner2)))
     }
@@ -166,6 +443,14 @@ impl UnixStream for TokioCompat<net::UnixStream> {
     }
 }
 
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl WithSocket for TokioCompat<net::UnixStream> {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self.get_ref()))
+    }
+}
+
 
 
 #[cfg(unix)]
@@ -179,6 +464,15 @@ impl UnixListener for net::UnixListener {
         Self::bind(path)
     }
 
+    async fn bind_addr(addr: &Self::SocketAddr) -> std::io::Result<Self> {
+        let addr: std::os::unix::net::SocketAddr = addr.clone().into();
+
+        let listener = std::os::unix::net::UnixListener::bind_addr(&addr)?;
+        listener.set_nonblocking(true)?;
+
+        Self::from_std(listener)
+    }
+
     async fn accept(&self) -> std::io::Result<(Self::UnixStream, Self::SocketAddr)> {
         self.accept().await
     }
@@ -187,3 +481,275 @@ impl UnixListener for net::UnixListener {
         self.local_addr()
     }
 }
+
+/// Accepts the connections as [`TokioCompat`] objects, so they implement the io traits of
+/// [`futures`].
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[async_trait]
+impl UnixListener for TokioCompat<net::UnixListener> {
+    type UnixStream = TokioCompat<net::UnixStream>;
+    type SocketAddr = net::unix::SocketAddr;
+
+    async fn bind<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self> {
+        <net::UnixListener as UnixListener>::bind(path).await.map(Self::new)
+    }
+
+    async fn bind_addr(addr: &Self::SocketAddr) -> std::io::Result<Self> {
+        <net::UnixListener as UnixListener>::bind_addr(addr).await.map(Self::new)
+    }
+
+    async fn accept(&self) -> std::io::Result<(Self::UnixStream, Self::SocketAddr)> {
+        let (stream, addr) = self.get_ref().accept().await?;
+
+        Ok((TokioCompat::new(stream), addr))
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::SocketAddr> {
+        self.get_ref().local_addr()
+    }
+}
+
+
+
+/// [`tokio`](https://docs.rs/tokio)'s abstraction of a [`Pipe`].
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TokioPipe {}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl Pipe for TokioPipe {
+    type Reader = TokioCompat<net::unix::pipe::Receiver>;
+    type Writer = TokioCompat<net::unix::pipe::Sender>;
+
+    fn pipe() -> std::io::Result<(Self::Reader, Self::Writer)> {
+        let (sender, receiver) = net::unix::pipe::pipe()?;
+
+        Ok((TokioCompat::new(receiver), TokioCompat::new(sender)))
+    }
+}
+
+
+
+/// [`tokio`](https://docs.rs/tokio)'s abstraction of a [`UnixSeqpacketStream`].
+///
+/// Tokio provides no such sockets, so the socket gets registered with its reactor directly. It
+/// must therefore be created within the context of a tokio runtime.
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[derive(Debug)]
+pub struct TokioUnixSeqpacketStream {
+    inner: AsyncFd<socket2::Socket>,
+}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[async_trait]
+impl UnixSeqpacketStream for TokioUnixSeqpacketStream {
+    type SocketAddr = socket2::SockAddr;
+
+    async fn connect<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self> {
+        let (socket, in_progress) = super::seqpacket::connect(path.as_ref())?;
+        let inner = AsyncFd::new(socket)?;
+
+        if in_progress {
+            let _ = inner.writable().await?;
+
+            if let Some(err) = inner.get_ref().take_error()? {
+                return Err(err);
+            }
+        }
+
+        Ok(Self { inner })
+    }
+
+    fn pair() -> std::io::Result<(Self, Self)> {
+        let (a, b) = super::seqpacket::pair()?;
+
+        Ok((Self { inner: AsyncFd::new(a)? }, Self { inner: AsyncFd::new(b)? }))
+    }
+
+    async fn send(&self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.async_io(Interest::WRITABLE, |socket| super::seqpacket::send(socket, buf)).await
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.async_io(Interest::READABLE, |socket| super::seqpacket::recv(socket, buf)).await
+    }
+
+    fn peer_addr(&self) -> std::io::Result<Self::SocketAddr> {
+        self.inner.get_ref().peer_addr()
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::SocketAddr> {
+        self.inner.get_ref().local_addr()
+    }
+}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl WithSocket for TokioUnixSeqpacketStream {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self.inner.get_ref()))
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for TokioUnixSeqpacketStream {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.get_ref().as_fd()
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for TokioUnixSeqpacketStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+/// [`tokio`](https://docs.rs/tokio)'s abstraction of a [`UnixSeqpacketListener`].
+///
+/// Like [`TokioUnixSeqpacketStream`], it must be created within the context of a tokio runtime.
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[derive(Debug)]
+pub struct TokioUnixSeqpacketListener {
+    inner: AsyncFd<socket2::Socket>,
+}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[async_trait]
+impl UnixSeqpacketListener for TokioUnixSeqpacketListener {
+    type UnixSeqpacketStream = TokioUnixSeqpacketStream;
+    type SocketAddr = socket2::SockAddr;
+
+    async fn bind<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Self> {
+        let socket = super::seqpacket::bind(path.as_ref())?;
+
+        Ok(Self { inner: AsyncFd::new(socket)? })
+    }
+
+    async fn accept(&self) -> std::io::Result<(Self::UnixSeqpacketStream, Self::SocketAddr)> {
+        let (socket, addr) = self.inner.async_io(Interest::READABLE, super::seqpacket::accept).await?;
+
+        Ok((TokioUnixSeqpacketStream { inner: AsyncFd::new(socket)? }, addr))
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::SocketAddr> {
+        self.inner.get_ref().local_addr()
+    }
+}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl WithSocket for TokioUnixSeqpacketListener {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        f(SockRef::from(self.inner.get_ref()))
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for TokioUnixSeqpacketListener {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.get_ref().as_fd()
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for TokioUnixSeqpacketListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+
+
+/// The error code returned when all instances of a named pipe server are busy.
+#[cfg(windows)]
+const ERROR_PIPE_BUSY: i32 = 231;
+
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+#[async_trait]
+impl NamedPipeServer for TokioCompat<net::windows::named_pipe::NamedPipeServer> {
+    async fn create<N: AsRef<OsStr> + Send>(name: N) -> std::io::Result<Self> {
+        let inner = net::windows::named_pipe::ServerOptions::new().create(name)?;
+
+        Ok(Self::new(inner))
+    }
+
+    async fn accept(&self) -> std::io::Result<()> {
+        self.get_ref().connect().await
+    }
+
+    fn disconnect(&self) -> std::io::Result<()> {
+        self.get_ref().disconnect()
+    }
+}
+
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+#[async_trait]
+impl NamedPipeClient for TokioCompat<net::windows::named_pipe::NamedPipeClient> {
+    async fn connect<N: AsRef<OsStr> + Send>(name: N) -> std::io::Result<Self> {
+        loop {
+            match net::windows::named_pipe::ClientOptions::new().open(name.as_ref()) {
+                Ok(inner) => return Ok(Self::new(inner)),
+                Err(err) if err.raw_os_error() == Some(ERROR_PIPE_BUSY) => {}
+                Err(err) => return Err(err),
+            }
+
+            ::tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+    }
+}
+
+
+
+impl From<TokioCompat<net::TcpStream>> for BoxedStream {
+    fn from(stream: TokioCompat<net::TcpStream>) -> Self {
+        Self::new(stream)
+    }
+}
+
+impl From<net::TcpStream> for BoxedStream {
+    fn from(stream: net::TcpStream) -> Self {
+        Self::new(TokioCompat::new(stream))
+    }
+}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl From<TokioCompat<net::UnixStream>> for BoxedStream {
+    fn from(stream: TokioCompat<net::UnixStream>) -> Self {
+        Self::new(stream)
+    }
+}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl From<net::UnixStream> for BoxedStream {
+    fn from(stream: net::UnixStream) -> Self {
+        Self::new(TokioCompat::new(stream))
+    }
+}
+
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+impl From<TokioCompat<net::windows::named_pipe::NamedPipeServer>> for BoxedStream {
+    fn from(stream: TokioCompat<net::windows::named_pipe::NamedPipeServer>) -> Self {
+        Self::new(stream)
+    }
+}
+
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+impl From<TokioCompat<net::windows::named_pipe::NamedPipeClient>> for BoxedStream {
+    fn from(stream: TokioCompat<net::windows::named_pipe::NamedPipeClient>) -> Self {
+        Self::new(stream)
+    }
+}
@@ -0,0 +1,490 @@
+use super::*;
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use futures::io::{AsyncRead, AsyncWrite, IoSlice, IoSliceMut};
+
+use tracing::Instrument;
+
+
+
+/// Returns the span of a connection, carrying its addresses if they are known.
+fn connection_span(peer_addr: Option<SocketAddr>, local_addr: Option<SocketAddr>) -> tracing::Span {
+    let span = tracing::info_span!(
+        target: "fut_compat::net",
+        "connection",
+        net.peer_addr = tracing::field::Empty,
+        net.local_addr = tracing::field::Empty,
+    );
+
+    if let Some(addr) = peer_addr {
+        span.record("net.peer_addr", tracing::field::display(addr));
+    }
+    if let Some(addr) = local_addr {
+        span.record("net.local_addr", tracing::field::display(addr));
+    }
+
+    span
+}
+
+/// Wraps a [`TcpStream`] and runs all of its polls within a [`tracing`](https://docs.rs/tracing)
+/// span covering the lifetime of the connection.
+///
+/// The span is at the `INFO` level with the target `fut_compat::net`, named `connection`. It
+/// carries the following fields:
+///
+/// - `net.peer_addr`: the address of the remote end.
+/// - `net.local_addr`: the address of the local end.
+///
+/// The span gets closed once the stream is dropped, so subscribers which record the duration of
+/// spans record the duration of the connection. The events emitted while reading or writing, e.g.
+/// by [`Traced`](crate::io::Traced), are recorded within the span.
+///
+/// Connecting with [`TcpStream::connect`] or [`TcpStream::connect_from`] runs within another
+/// `INFO` span, named `connect`, with the following fields:
+///
+/// - `net.target`: the addresses which get connected to.
+/// - `net.duration_us`: the microseconds it took to connect or to fail.
+///
+/// Accepted connections are wrapped by [`TracedListener`].
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::io::TokioCompat;
+/// use fut_compat::net::{TcpStream, TracedStream};
+///
+/// let stream = TracedStream::<TokioCompat<tokio::net::TcpStream>>::connect("127.0.0.1:8080").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TracedStream<S> {
+    inner: S,
+    span: tracing::Span,
+}
+
+impl<S: TcpStream> TracedStream<S> {
+    /// Creates a new instance by wrapping `stream`, within a new span carrying its addresses.
+    pub fn new(stream: S) -> Self {
+        let span = connection_span(stream.peer_addr().ok(), stream.local_addr().ok());
+
+        Self::with_span(stream, span)
+    }
+}
+
+impl<S> TracedStream<S> {
+    /// Creates a new instance by wrapping `stream`, within the given span.
+    pub fn with_span(stream: S, span: tracing::Span) -> Self {
+        Self {
+            inner: stream,
+            span,
+        }
+    }
+
+    /// Returns the span of the connection.
+    pub fn span(&self) -> &tracing::Span {
+        &self.span
+    }
+
+    /// Get a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Consumes the `TracedStream` object and returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: TcpStream + Send> TracedStream<S> {
+    /// Runs `connect` within a `connect` span for the given addresses, recording its duration and
+    /// outcome.
+    async fn connect_traced<A, F, Fut>(addrs: A, connect: F) -> std::io::Result<Self>
+    where
+        A: ToSocketAddrs + Send,
+        F: FnOnce(Vec<SocketAddr>) -> Fut,
+        Fut: std::future::Future<Output = std::io::Result<S>>,
+    {
+        let addrs: Vec<SocketAddr> = addrs.to_socket_addrs().await.collect();
+
+        let span = tracing::info_span!(
+            target: "fut_compat::net",
+            "connect",
+            net.target = ?addrs,
+            net.duration_us = tracing::field::Empty,
+        );
+
+        let start = Instant::now();
+        let result = connect(addrs).instrument(span.clone()).await;
+
+        let duration = u64::try_from(start.elapsed().as_micros()).unwrap_or(u64::MAX);
+        span.record("net.duration_us", duration);
+
+        let _enter = span.enter();
+        match result {
+            Ok(stream) => {
+                tracing::debug!(target: "fut_compat::net", "connected");
+
+                Ok(Self::new(stream))
+            }
+            Err(err) => {
+                tracing::debug!(target: "fut_compat::net", error = %err, "failed to connect");
+
+                Err(err)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<S> TcpStream for TracedStream<S>
+where
+    S: TcpStream + Send + Sync,
+{
+    async fn connect<A: ToSocketAddrs + Send>(addrs: A) -> std::io::Result<Self> {
+        Self::connect_traced(addrs, |addrs| async move {
+            S::connect(&addrs[..]).await
+        }).await
+    }
+
+    async fn connect_from<A: ToSocketAddrs + Send>(
+        local: SocketAddr,
+        remote: A,
+    ) -> std::io::Result<Self> {
+        Self::connect_traced(remote, |addrs| async move {
+            S::connect_from(local, &addrs[..]).await
+        }).await
+    }
+
+    async fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.peek(buf).instrument(self.span.clone()).await
+    }
+
+    fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    fn nodelay(&self) -> std::io::Result<bool> {
+        self.inner.nodelay()
+    }
+
+    fn set_nodelay(&self, nodelay: bool) -> std::io::Result<()> {
+        self.inner.set_nodelay(nodelay)
+    }
+
+    fn ttl(&self) -> std::io::Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn set_ttl(&self, ttl: u32) -> std::io::Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn tos(&self) -> std::io::Result<u32> {
+        self.inner.tos()
+    }
+
+    fn set_tos(&self, tos: u32) -> std::io::Result<()> {
+        self.inner.set_tos(tos)
+    }
+
+    async fn readable(&self) -> std::io::Result<()> {
+        self.inner.readable().instrument(self.span.clone()).await
+    }
+
+    async fn writable(&self) -> std::io::Result<()> {
+        self.inner.writable().instrument(self.span.clone()).await
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.span.in_scope(|| self.inner.try_read(buf))
+    }
+
+    fn try_write(&self, buf: &[u8]) -> std::io::Result<usize> {
+        self.span.in_scope(|| self.inner.try_write(buf))
+    }
+}
+
+#[cfg(feature = "socket2")]
+impl<S: WithSocket> WithSocket for TracedStream<S> {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        self.inner.with_socket(f)
+    }
+}
+
+impl<S> AsyncRead for TracedStream<S>
+where
+    S: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = Pin::into_inner(self);
+        let _enter = this.span.enter();
+
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = Pin::into_inner(self);
+        let _enter = this.span.enter();
+
+        Pin::new(&mut this.inner).poll_read_vectored(cx, bufs)
+    }
+}
+
+impl<S> AsyncWrite for TracedStream<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = Pin::into_inner(self);
+        let _enter = this.span.enter();
+
+        Pin::new(&mut this.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = Pin::into_inner(self);
+        let _enter = this.span.enter();
+
+        Pin::new(&mut this.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = Pin::into_inner(self);
+        let _enter = this.span.enter();
+
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = Pin::into_inner(self);
+        let _enter = this.span.enter();
+
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+
+
+/// Wraps a [`TcpListener`] and hands out its connections as [`TracedStream`]s, each within its
+/// own `connection` span carrying the peer address.
+///
+/// The spans get created when accepting, so they are children of the span `accept` got called
+/// from. Failing to accept a connection emits a `DEBUG` event with the target `fut_compat::net`.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use tracing::Instrument;
+/// use fut_compat::io::TokioCompat;
+/// use fut_compat::net::{TcpListener, TracedListener};
+///
+/// let listener = TracedListener::<TokioCompat<tokio::net::TcpListener>>::bind("127.0.0.1:8080").await?;
+///
+/// loop {
+///     let (stream, _) = listener.accept().await?;
+///     let span = stream.span().clone();
+///
+///     tokio::spawn(async move {
+///         tracing::info!("handling the connection");
+///         drop(stream);
+///     }.instrument(span));
+/// }
+/// # }
+/// ```
+#[derive(Default, Debug)]
+pub struct TracedListener<L> {
+    inner: L,
+}
+
+impl<L> TracedListener<L> {
+    /// Creates a new instance by wrapping `listener`.
+    pub fn new(listener: L) -> Self {
+        Self {
+            inner: listener,
+        }
+    }
+
+    /// Get a reference to the wrapped listener.
+    pub fn get_ref(&self) -> &L {
+        &self.inner
+    }
+
+    /// Consumes the `TracedListener` object and returns the wrapped listener.
+    pub fn into_inner(self) -> L {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<L> TcpListener for TracedListener<L>
+where
+    L: TcpListener + Send + Sync,
+    L::TcpStream: Send + Sync,
+{
+    type TcpStream = TracedStream<L::TcpStream>;
+
+    async fn bind<A: ToSocketAddrs + Send>(addrs: A) -> std::io::Result<Self> {
+        Ok(Self::new(L::bind(addrs).await?))
+    }
+
+    async fn accept(&self) -> std::io::Result<(Self::TcpStream, SocketAddr)> {
+        match self.inner.accept().await {
+            Ok((stream, addr)) => {
+                let span = connection_span(Some(addr), stream.local_addr().ok());
+
+                Ok((TracedStream::with_span(stream, span), addr))
+            }
+            Err(err) => {
+                tracing::debug!(target: "fut_compat::net", error = %err, "failed to accept a connection");
+
+                Err(err)
+            }
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+#[cfg(feature = "socket2")]
+impl<L: WithSocket> WithSocket for TracedListener<L> {
+    fn with_socket<R, F: FnOnce(socket2::SockRef<'_>) -> R>(&self, f: F) -> R {
+        self.inner.with_socket(f)
+    }
+}
+
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use crate::capture::Capture;
+
+    /// Connects to a [`TracedListener`] and checks the spans of both ends.
+    async fn connect_and_accept<L>(capture: Capture)
+    where
+        L: TcpListener + Send + Sync,
+        L::TcpStream: Send + Sync,
+    {
+        let listener = TracedListener::<L>::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (accepted, client) = futures::join!(
+            listener.accept(),
+            TracedStream::<L::TcpStream>::connect(addr),
+        );
+        let (server, client) = (accepted.unwrap().0, client.unwrap());
+        let client_addr = client.local_addr().unwrap();
+
+        let (connect_index, connect) = capture.span("connect").unwrap();
+        assert_eq!(connect.target, "fut_compat::net");
+        assert_eq!(connect.fields["net.target"], format!("{:?}", vec![addr]));
+        assert!(connect.fields["net.duration_us"].parse::<u64>().is_ok());
+
+        let connected = capture.events().into_iter().find(|event| event.fields["message"] == "connected").unwrap();
+        assert_eq!(connected.target, "fut_compat::net");
+        assert_eq!(connected.span, Some(connect_index));
+
+        // Both ends got a span of their own, the one of the server carrying the client address.
+        let spans = capture.spans();
+        let accepted = spans.iter()
+            .find(|span| span.name == "connection" && span.fields["net.peer_addr"] == client_addr.to_string())
+            .unwrap();
+        assert_eq!(accepted.fields["net.local_addr"], addr.to_string());
+        assert_eq!(server.span().metadata().unwrap().name(), "connection");
+    }
+
+    /// Connects to a closed port and checks that the failure is recorded within the span.
+    async fn failed_connect<L>(capture: Capture)
+    where
+        L: TcpListener + Send + Sync,
+        L::TcpStream: Send + Sync,
+    {
+        let addr = L::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+
+        assert!(TracedStream::<L::TcpStream>::connect(addr).await.is_err());
+
+        let (connect_index, _) = capture.span("connect").unwrap();
+        let failed = capture.events().into_iter().find(|event| event.fields["message"] == "failed to connect").unwrap();
+        assert_eq!(failed.span, Some(connect_index));
+        assert!(failed.fields.contains_key("error"));
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_connections_within_spans() {
+        let capture = Capture::default();
+        let runtime = ::tokio::runtime::Runtime::new().unwrap();
+
+        tracing::subscriber::with_default(capture.clone(), || {
+            runtime.block_on(connect_and_accept::<::tokio::net::TcpListener>(capture.clone()));
+        });
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_failed_connects_within_spans() {
+        let capture = Capture::default();
+        let runtime = ::tokio::runtime::Runtime::new().unwrap();
+
+        tracing::subscriber::with_default(capture.clone(), || {
+            runtime.block_on(failed_connect::<::tokio::net::TcpListener>(capture.clone()));
+        });
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_connections_within_spans() {
+        let capture = Capture::default();
+
+        tracing::subscriber::with_default(capture.clone(), || {
+            ::async_std::task::block_on(connect_and_accept::<::async_std::net::TcpListener>(capture.clone()));
+        });
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_failed_connects_within_spans() {
+        let capture = Capture::default();
+
+        tracing::subscriber::with_default(capture.clone(), || {
+            ::async_std::task::block_on(failed_connect::<::async_std::net::TcpListener>(capture.clone()));
+        });
+    }
+}
@@ -0,0 +1,78 @@
+pub use crate::io::{
+    AsyncRead,
+    AsyncReadExt,
+    AsyncBufRead,
+    AsyncBufReadExt,
+    AsyncWrite,
+    AsyncWriteExt,
+    AsyncSeek,
+    AsyncSeekExt,
+    AsyncReadUtilExt,
+    AsyncWriteUtilExt,
+    Stdio as StdioExt,
+};
+#[cfg(feature = "bytes")]
+pub use crate::io::{AsyncReadBufExt, AsyncWriteBufExt};
+#[cfg(all(unix, feature = "zero-copy"))]
+pub use crate::io::SendfileSocket;
+#[cfg(feature = "tokio-rt")]
+pub use crate::io::TokioSharedIo;
+
+pub use crate::fs::{
+    Filesystem as FsExt,
+    File as FileExt,
+    OpenOptions as OpenOptionsExt,
+    DirBuilder as DirBuilderExt,
+    DirEntry as DirEntryExt,
+    ReadDirExt,
+};
+#[cfg(unix)]
+pub use crate::fs::SecureOpen;
+
+pub use crate::net::{
+    ToSocketAddrs as ToSocketAddrsExt,
+    TcpStream as TcpStreamExt,
+    TcpListener as TcpListenerExt,
+    TcpListenerUtilExt,
+    UdpSocket as UdpSocketExt,
+};
+#[cfg(feature = "socket2")]
+pub use crate::net::WithSocket;
+#[cfg(unix)]
+pub use crate::net::{
+    UnixSocketAddr as UnixSocketAddrExt,
+    UnixStream as UnixStreamExt,
+    UnixListener as UnixListenerExt,
+    UnixSeqpacketStream as UnixSeqpacketStreamExt,
+    UnixSeqpacketListener as UnixSeqpacketListenerExt,
+    Pipe as PipeExt,
+};
+#[cfg(windows)]
+pub use crate::net::{
+    NamedPipeServer as NamedPipeServerExt,
+    NamedPipeClient as NamedPipeClientExt,
+};
+
+pub use crate::process::{
+    Command as CommandExt,
+    Child as ChildExt,
+};
+
+pub use crate::task::{
+    Spawn,
+    SpawnExt,
+    SpawnBlocking,
+    DynSpawnBlocking,
+    SpawnHandle,
+    Timer,
+    BlockOn,
+};
+#[cfg(feature = "tracing")]
+pub use crate::task::SpawnNamedExt;
+
+pub use crate::channel::{
+    Sender as SenderExt,
+    Receiver as ReceiverExt,
+};
+
+pub use crate::runtime::Runtime as RuntimeExt;
@@ -0,0 +1,385 @@
+use super::*;
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::cell::UnsafeCell;
+#[cfg(unix)]
+use std::fmt;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+#[cfg(unix)]
+use std::sync::Arc;
+
+use ::async_std::process;
+
+
+
+/// A [`Command`] for the [`async_std`](https://docs.rs/async-std) runtime.
+///
+/// The command of async-std lacks some of the configuration of [`std::process::Command`], like
+/// the process group. This type records the configuration instead, and builds an async-std
+/// command from it every time a child process gets spawned.
+#[derive(Clone, Debug)]
+pub struct AsyncStdCommand {
+    program: OsString,
+    args: Vec<OsString>,
+    env_clear: bool,
+    /// The changed environment variables, `None` for removed ones.
+    envs: BTreeMap<OsString, Option<OsString>>,
+    current_dir: Option<PathBuf>,
+    stdin: Option<Stdio>,
+    stdout: Option<Stdio>,
+    stderr: Option<Stdio>,
+    kill_on_drop: bool,
+    #[cfg(unix)]
+    process_group: Option<i32>,
+    #[cfg(unix)]
+    arg0: Option<OsString>,
+    #[cfg(unix)]
+    uid: Option<u32>,
+    #[cfg(unix)]
+    gid: Option<u32>,
+    #[cfg(unix)]
+    pre_exec: Vec<PreExec>,
+}
+
+/// A hook added with [`Command::pre_exec`], which gets shared by all commands built from an
+/// [`AsyncStdCommand`].
+#[cfg(unix)]
+#[derive(Clone)]
+struct PreExec(Arc<HookCell>);
+
+#[cfg(unix)]
+struct HookCell(UnsafeCell<Box<dyn FnMut() -> std::io::Result<()> + Send + Sync>>);
+
+// SAFETY: The hook only gets called within the forked child process, which has its own copy of
+// the memory and only a single thread. So it never gets called concurrently.
+#[cfg(unix)]
+unsafe impl Sync for HookCell {}
+
+#[cfg(unix)]
+impl PreExec {
+    fn call(&self) -> std::io::Result<()> {
+        // SAFETY: See the implementation of `Sync` for `HookCell`.
+        let hook = unsafe { &mut *self.0.0.get() };
+
+        hook()
+    }
+}
+
+#[cfg(unix)]
+impl fmt::Debug for PreExec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreExec").finish_non_exhaustive()
+    }
+}
+
+impl AsyncStdCommand {
+    /// Builds the command of async-std with the recorded configuration.
+    fn build(&self) -> process::Command {
+        let mut command = std::process::Command::new(&self.program);
+
+        command.args(&self.args);
+
+        if self.env_clear {
+            command.env_clear();
+        }
+        for (key, val) in &self.envs {
+            match val {
+                Some(val) => command.env(key, val),
+                None => command.env_remove(key),
+            };
+        }
+
+        if let Some(dir) = &self.current_dir {
+            command.current_dir(dir);
+        }
+
+        #[cfg(unix)]
+        {
+            if let Some(pgroup) = self.process_group {
+                command.process_group(pgroup);
+            }
+            if let Some(arg0) = &self.arg0 {
+                command.arg0(arg0);
+            }
+            if let Some(uid) = self.uid {
+                command.uid(uid);
+            }
+            if let Some(gid) = self.gid {
+                command.gid(gid);
+            }
+            for hook in &self.pre_exec {
+                let hook = hook.clone();
+
+                // SAFETY: The caller of `Command::pre_exec` upholds its requirements.
+                unsafe { command.pre_exec(move || hook.call()) };
+            }
+        }
+
+        // The standard io streams must be set on the command of async-std, as it only uses the
+        // ones configured through itself and its own defaults otherwise.
+        let mut command = process::Command::from(command);
+
+        if let Some(cfg) = self.stdin {
+            command.stdin(cfg);
+        }
+        if let Some(cfg) = self.stdout {
+            command.stdout(cfg);
+        }
+        if let Some(cfg) = self.stderr {
+            command.stderr(cfg);
+        }
+
+        command.kill_on_drop(self.kill_on_drop);
+
+        command
+    }
+}
+
+#[async_trait]
+impl Command for AsyncStdCommand {
+    type Child = process::Child;
+
+    fn new<S: AsRef<OsStr>>(program: S) -> Self {
+        Self {
+            program: program.as_ref().to_owned(),
+            args: Vec::new(),
+            env_clear: false,
+            envs: BTreeMap::new(),
+            current_dir: None,
+            stdin: None,
+            stdout: None,
+            stderr: None,
+            kill_on_drop: false,
+            #[cfg(unix)]
+            process_group: None,
+            #[cfg(unix)]
+            arg0: None,
+            #[cfg(unix)]
+            uid: None,
+            #[cfg(unix)]
+            gid: None,
+            #[cfg(unix)]
+            pre_exec: Vec::new(),
+        }
+    }
+
+    fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args.extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    fn env<K, V>(&mut self, key: K, val: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.envs.insert(key.as_ref().to_owned(), Some(val.as_ref().to_owned()));
+        self
+    }
+
+    fn envs<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        for (key, val) in vars {
+            self.env(key, val);
+        }
+        self
+    }
+
+    fn env_remove<K: AsRef<OsStr>>(&mut self, key: K) -> &mut Self {
+        self.envs.insert(key.as_ref().to_owned(), None);
+        self
+    }
+
+    fn env_clear(&mut self) -> &mut Self {
+        self.env_clear = true;
+        self.envs.clear();
+        self
+    }
+
+    fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.current_dir = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    fn stdin(&mut self, cfg: Stdio) -> &mut Self {
+        self.stdin = Some(cfg);
+        self
+    }
+
+    fn stdout(&mut self, cfg: Stdio) -> &mut Self {
+        self.stdout = Some(cfg);
+        self
+    }
+
+    fn stderr(&mut self, cfg: Stdio) -> &mut Self {
+        self.stderr = Some(cfg);
+        self
+    }
+
+    fn kill_on_drop(&mut self, kill_on_drop: bool) -> &mut Self {
+        self.kill_on_drop = kill_on_drop;
+        self
+    }
+
+    #[cfg(unix)]
+    fn process_group(&mut self, pgroup: i32) -> &mut Self {
+        self.process_group = Some(pgroup);
+        self
+    }
+
+    #[cfg(unix)]
+    fn arg0<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.arg0 = Some(arg.as_ref().to_owned());
+        self
+    }
+
+    #[cfg(unix)]
+    fn uid(&mut self, id: u32) -> &mut Self {
+        self.uid = Some(id);
+        self
+    }
+
+    #[cfg(unix)]
+    fn gid(&mut self, id: u32) -> &mut Self {
+        self.gid = Some(id);
+        self
+    }
+
+    #[cfg(unix)]
+    unsafe fn pre_exec<F>(&mut self, f: F) -> &mut Self
+    where
+        F: FnMut() -> std::io::Result<()> + Send + Sync + 'static,
+    {
+        self.pre_exec.push(PreExec(Arc::new(HookCell(UnsafeCell::new(Box::new(f))))));
+        self
+    }
+
+    fn spawn(&mut self) -> std::io::Result<Self::Child> {
+        self.build().spawn()
+    }
+
+    async fn status(&mut self) -> std::io::Result<ExitStatus> {
+        self.build().status().await
+    }
+
+    async fn output(&mut self) -> std::io::Result<Output> {
+        self.build().output().await
+    }
+}
+
+#[async_trait]
+impl Child for process::Child {
+    type Stdin = process::ChildStdin;
+    type Stdout = process::ChildStdout;
+    type Stderr = process::ChildStderr;
+
+    fn id(&self) -> Option<u32> {
+        Some(self.id())
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        self.kill()
+    }
+
+    async fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        self.status().await
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        self.try_status()
+    }
+
+    #[cfg(unix)]
+    fn kill_group(&mut self) -> std::io::Result<()> {
+        send_signal(Some(self.id()), true, libc::SIGKILL)
+    }
+
+    #[cfg(unix)]
+    fn terminate(&mut self) -> std::io::Result<()> {
+        send_signal(Some(self.id()), false, libc::SIGTERM)
+    }
+
+    fn take_stdin(&mut self) -> Option<Self::Stdin> {
+        self.stdin.take()
+    }
+
+    fn take_stdout(&mut self) -> Option<Self::Stdout> {
+        self.stdout.take()
+    }
+
+    fn take_stderr(&mut self) -> Option<Self::Stderr> {
+        self.stderr.take()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    #[test]
+    fn echo_output_gets_captured() {
+        ::async_std::task::block_on(echo_round_trip::<AsyncStdCommand>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn limited_output_reads_both_streams_concurrently() {
+        ::async_std::task::block_on(flooding_output_gets_truncated::<AsyncStdCommand>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cancelling_kills_the_child() {
+        ::async_std::task::block_on(cancelled_child_gets_killed::<AsyncStdCommand, crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn group_kill_reaches_grandchildren() {
+        ::async_std::task::block_on(group_kill_with_grandchild::<AsyncStdCommand, crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn shutdown_kills_children_ignoring_sigterm() {
+        ::async_std::task::block_on(shutdown_escalates_to_kill::<AsyncStdCommand, crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pipe_through_transforms_the_input() {
+        ::async_std::task::block_on(pipe_through_transforms::<AsyncStdCommand>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pipe_through_handles_stderr() {
+        ::async_std::task::block_on(pipe_through_stderr_modes::<AsyncStdCommand>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pre_exec_runs_in_the_child() {
+        ::async_std::task::block_on(pre_exec_sets_rlimit::<AsyncStdCommand>());
+    }
+}
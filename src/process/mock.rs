@@ -0,0 +1,601 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::Instant;
+
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt;
+#[cfg(windows)]
+use std::os::windows::process::ExitStatusExt;
+
+use futures::io::{Cursor, Sink};
+
+use super::*;
+use crate::task::MockTimer;
+
+
+
+/// A fake process backend for testing code written against [`Command`] and [`Child`] without
+/// executing any programs.
+///
+/// Tests register the invocations they expect with [`expect`](Self::expect), together with the
+/// behavior of the child. Spawning a [`MockCommand`] consumes the first expectation matching its
+/// program and arguments, and returns a [`MockChild`] which plays back the scripted output and
+/// exit code. Spawning a command nobody expected panics, which fails the test.
+///
+/// The expectations are global to the process, as [`Command::new`] has no receiver to carry
+/// them, so tests which use it should not run in parallel with each other. The delays of the
+/// children are driven by the clock of [`MockTimer`] on the thread spawning them.
+///
+/// # Example
+///
+/// ```
+/// use std::process::ExitStatus;
+///
+/// use fut_compat::process::{Command, MockCommand, MockInvocation, MockProcess};
+///
+/// /// Runs `program` until it succeeds, at most `attempts` times.
+/// async fn run_with_retry<C: Command>(program: &str, attempts: usize) -> std::io::Result<ExitStatus> {
+///     let mut status = C::new(program).status().await?;
+///
+///     for _ in 1..attempts {
+///         if status.success() {
+///             break;
+///         }
+///
+///         status = C::new(program).status().await?;
+///     }
+///
+///     Ok(status)
+/// }
+///
+/// MockProcess::expect(MockInvocation::new("flaky").exit_code(1));
+/// MockProcess::expect(MockInvocation::new("flaky").exit_code(0));
+///
+/// let status = futures::executor::block_on(run_with_retry::<MockCommand>("flaky", 3)).unwrap();
+///
+/// assert!(status.success());
+/// assert_eq!(MockProcess::pending_expectation_count(), 0);
+/// ```
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MockProcess {}
+
+struct Registry {
+    expectations: Vec<MockInvocation>,
+    next_id: u32,
+}
+
+fn registry() -> MutexGuard<'static, Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+
+    REGISTRY.get_or_init(|| Mutex::new(Registry {
+        expectations: Vec::new(),
+        next_id: 1,
+    })).lock().unwrap_or_else(|err| err.into_inner())
+}
+
+impl MockProcess {
+    /// Registers an expected invocation, which gets consumed by the first spawn matching it.
+    ///
+    /// Expectations get matched in the order they have been registered.
+    pub fn expect(invocation: MockInvocation) {
+        registry().expectations.push(invocation);
+    }
+
+    /// Returns the number of expectations which have not been consumed yet.
+    pub fn pending_expectation_count() -> usize {
+        registry().expectations.len()
+    }
+
+    /// Removes all expectations which have not been consumed yet.
+    pub fn reset() {
+        registry().expectations.clear();
+    }
+}
+
+/// An invocation expected by [`MockProcess`], together with the scripted behavior of the child.
+///
+/// By default, it matches any arguments, and the child exits right away with the exit code `0`
+/// and without any output.
+#[derive(Clone)]
+pub struct MockInvocation {
+    program: OsString,
+    args: ArgsMatcher,
+    exit_code: i32,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    delay: Duration,
+}
+
+/// Decides whether the arguments of an invocation match.
+type ArgsMatcherFn = dyn Fn(&[OsString]) -> bool + Send + Sync + 'static;
+
+#[derive(Clone)]
+enum ArgsMatcher {
+    Any,
+    Exact(Vec<OsString>),
+    Custom(Arc<ArgsMatcherFn>),
+}
+
+impl MockInvocation {
+    /// Creates a new expectation for spawning `program`.
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Self {
+        Self {
+            program: program.as_ref().to_owned(),
+            args: ArgsMatcher::Any,
+            exit_code: 0,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// Only matches invocations with exactly these arguments.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args = ArgsMatcher::Exact(args.into_iter().map(|arg| arg.as_ref().to_owned()).collect());
+        self
+    }
+
+    /// Only matches invocations whose arguments satisfy `matcher`.
+    pub fn args_matching<F>(mut self, matcher: F) -> Self
+    where
+        F: Fn(&[OsString]) -> bool + Send + Sync + 'static,
+    {
+        self.args = ArgsMatcher::Custom(Arc::new(matcher));
+        self
+    }
+
+    /// Sets the exit code of the child.
+    pub fn exit_code(mut self, code: i32) -> Self {
+        self.exit_code = code;
+        self
+    }
+
+    /// Sets the bytes the child writes to its standard output.
+    pub fn stdout<B: Into<Vec<u8>>>(mut self, bytes: B) -> Self {
+        self.stdout = bytes.into();
+        self
+    }
+
+    /// Sets the bytes the child writes to its standard error.
+    pub fn stderr<B: Into<Vec<u8>>>(mut self, bytes: B) -> Self {
+        self.stderr = bytes.into();
+        self
+    }
+
+    /// Makes the child exit only once [`MockTimer`] has been advanced by `delay` after spawning
+    /// it.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    fn matches(&self, program: &OsStr, args: &[OsString]) -> bool {
+        if self.program != program {
+            return false;
+        }
+
+        match &self.args {
+            ArgsMatcher::Any => true,
+            ArgsMatcher::Exact(expected) => expected == args,
+            ArgsMatcher::Custom(matcher) => matcher(args),
+        }
+    }
+}
+
+impl fmt::Debug for MockInvocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("MockInvocation");
+
+        f.field("program", &self.program);
+        match &self.args {
+            ArgsMatcher::Any => f.field("args", &"any"),
+            ArgsMatcher::Exact(args) => f.field("args", args),
+            ArgsMatcher::Custom(_) => f.field("args", &"custom"),
+        };
+
+        f.field("exit_code", &self.exit_code)
+            .field("stdout", &self.stdout.len())
+            .field("stderr", &self.stderr.len())
+            .field("delay", &self.delay)
+            .finish()
+    }
+}
+
+/// A [`Command`] which spawns the children expected by [`MockProcess`].
+///
+/// The environment, the working directory and the unix specific options get ignored. Hooks
+/// added with [`pre_exec`](Command::pre_exec) never run.
+#[derive(Clone, Debug)]
+pub struct MockCommand {
+    program: OsString,
+    args: Vec<OsString>,
+    stdin: Option<Stdio>,
+    stdout: Option<Stdio>,
+    stderr: Option<Stdio>,
+}
+
+impl MockCommand {
+    /// Spawns the child with the given standard io streams, which are piped if `true`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no expectation matches the invocation.
+    fn spawn_with(&self, stdin: bool, stdout: bool, stderr: bool) -> MockChild {
+        let mut registry = registry();
+
+        let index = registry.expectations.iter()
+            .position(|invocation| invocation.matches(&self.program, &self.args));
+        let invocation = match index {
+            Some(index) => registry.expectations.remove(index),
+            None => panic!(
+                "unexpected invocation of {:?} with the arguments {:?}, expected one of {:?}",
+                self.program,
+                self.args,
+                registry.expectations,
+            ),
+        };
+
+        let id = registry.next_id;
+        registry.next_id += 1;
+        drop(registry);
+
+        MockChild {
+            id,
+            exit_at: MockTimer::now() + invocation.delay,
+            exit_code: invocation.exit_code,
+            status: None,
+            stdin: stdin.then(futures::io::sink),
+            stdout: stdout.then(|| Cursor::new(invocation.stdout)),
+            stderr: stderr.then(|| Cursor::new(invocation.stderr)),
+        }
+    }
+}
+
+#[async_trait]
+impl Command for MockCommand {
+    type Child = MockChild;
+
+    fn new<S: AsRef<OsStr>>(program: S) -> Self {
+        Self {
+            program: program.as_ref().to_owned(),
+            args: Vec::new(),
+            stdin: None,
+            stdout: None,
+            stderr: None,
+        }
+    }
+
+    fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args.extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    fn env<K, V>(&mut self, _key: K, _val: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self
+    }
+
+    fn envs<I, K, V>(&mut self, _vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self
+    }
+
+    fn env_remove<K: AsRef<OsStr>>(&mut self, _key: K) -> &mut Self {
+        self
+    }
+
+    fn env_clear(&mut self) -> &mut Self {
+        self
+    }
+
+    fn current_dir<P: AsRef<Path>>(&mut self, _dir: P) -> &mut Self {
+        self
+    }
+
+    fn stdin(&mut self, cfg: Stdio) -> &mut Self {
+        self.stdin = Some(cfg);
+        self
+    }
+
+    fn stdout(&mut self, cfg: Stdio) -> &mut Self {
+        self.stdout = Some(cfg);
+        self
+    }
+
+    fn stderr(&mut self, cfg: Stdio) -> &mut Self {
+        self.stderr = Some(cfg);
+        self
+    }
+
+    fn kill_on_drop(&mut self, _kill_on_drop: bool) -> &mut Self {
+        self
+    }
+
+    #[cfg(unix)]
+    fn process_group(&mut self, _pgroup: i32) -> &mut Self {
+        self
+    }
+
+    #[cfg(unix)]
+    fn arg0<S: AsRef<OsStr>>(&mut self, _arg: S) -> &mut Self {
+        self
+    }
+
+    #[cfg(unix)]
+    fn uid(&mut self, _id: u32) -> &mut Self {
+        self
+    }
+
+    #[cfg(unix)]
+    fn gid(&mut self, _id: u32) -> &mut Self {
+        self
+    }
+
+    #[cfg(unix)]
+    unsafe fn pre_exec<F>(&mut self, _f: F) -> &mut Self
+    where
+        F: FnMut() -> std::io::Result<()> + Send + Sync + 'static,
+    {
+        self
+    }
+
+    /// Spawns the child expected by [`MockProcess`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if no expectation matches the invocation.
+    fn spawn(&mut self) -> std::io::Result<Self::Child> {
+        let piped = |cfg: Option<Stdio>| cfg == Some(Stdio::Piped);
+
+        Ok(self.spawn_with(piped(self.stdin), piped(self.stdout), piped(self.stderr)))
+    }
+
+    async fn status(&mut self) -> std::io::Result<ExitStatus> {
+        self.spawn()?.wait().await
+    }
+
+    async fn output(&mut self) -> std::io::Result<Output> {
+        let mut child = self.spawn_with(false, true, true);
+        let status = child.wait().await?;
+
+        Ok(Output {
+            status,
+            stdout: child.stdout.take().map(Cursor::into_inner).unwrap_or_default(),
+            stderr: child.stderr.take().map(Cursor::into_inner).unwrap_or_default(),
+        })
+    }
+}
+
+/// A [`Child`] spawned by a [`MockCommand`], which plays back the behavior scripted with
+/// [`MockInvocation`].
+///
+/// Its output is available right away. It exits once [`MockTimer`] reached its exit time, or
+/// when it gets killed.
+#[derive(Debug)]
+pub struct MockChild {
+    id: u32,
+    exit_at: Instant,
+    exit_code: i32,
+    /// The exit status, once it has exited.
+    status: Option<ExitStatus>,
+    stdin: Option<Sink>,
+    stdout: Option<Cursor<Vec<u8>>>,
+    stderr: Option<Cursor<Vec<u8>>>,
+}
+
+impl MockChild {
+    fn signal(&mut self, signal: i32) -> std::io::Result<()> {
+        if self.try_wait()?.is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the child has already exited",
+            ));
+        }
+
+        self.status = Some(signal_status(signal));
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Child for MockChild {
+    type Stdin = Sink;
+    type Stdout = Cursor<Vec<u8>>;
+    type Stderr = Cursor<Vec<u8>>;
+
+    fn id(&self) -> Option<u32> {
+        Some(self.id)
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        self.signal(9)
+    }
+
+    async fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        self.stdin = None;
+
+        if self.status.is_none() {
+            MockTimer::sleep_until(self.exit_at).await;
+        }
+
+        Ok(*self.status.get_or_insert_with(|| exit_status(self.exit_code)))
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        if self.status.is_none() && MockTimer::now() >= self.exit_at {
+            self.status = Some(exit_status(self.exit_code));
+        }
+
+        Ok(self.status)
+    }
+
+    #[cfg(unix)]
+    fn kill_group(&mut self) -> std::io::Result<()> {
+        self.signal(9)
+    }
+
+    #[cfg(unix)]
+    fn terminate(&mut self) -> std::io::Result<()> {
+        self.signal(15)
+    }
+
+    fn take_stdin(&mut self) -> Option<Self::Stdin> {
+        self.stdin.take()
+    }
+
+    fn take_stdout(&mut self) -> Option<Self::Stdout> {
+        self.stdout.take()
+    }
+
+    fn take_stderr(&mut self) -> Option<Self::Stderr> {
+        self.stderr.take()
+    }
+}
+
+/// Returns the status of a child which exited with `code`.
+fn exit_status(code: i32) -> ExitStatus {
+    #[cfg(unix)]
+    return ExitStatus::from_raw(code << 8);
+
+    #[cfg(windows)]
+    return ExitStatus::from_raw(code as u32);
+}
+
+/// Returns the status of a child which got killed by `signal`.
+fn signal_status(signal: i32) -> ExitStatus {
+    #[cfg(unix)]
+    return ExitStatus::from_raw(signal);
+
+    // Killed processes exit with the code 1 on windows.
+    #[cfg(windows)]
+    return {
+        let _ = signal;
+        ExitStatus::from_raw(1)
+    };
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::FutureExt;
+
+    /// Serializes the tests, as they share the expectations, and clears them beforehand.
+    fn serial() -> MutexGuard<'static, ()> {
+        static LOCK: Mutex<()> = Mutex::new(());
+
+        let guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        MockProcess::reset();
+
+        guard
+    }
+
+    /// Runs `program` until it exits successfully, at most `attempts` times, and returns the last
+    /// exit status together with the number of attempts.
+    async fn run_with_retry<C: Command>(program: &str, attempts: usize) -> std::io::Result<(ExitStatus, usize)> {
+        let mut attempt = 1;
+        let mut status = C::new(program).arg("--now").status().await?;
+
+        while !status.success() && attempt < attempts {
+            attempt += 1;
+            status = C::new(program).arg("--now").status().await?;
+        }
+
+        Ok((status, attempt))
+    }
+
+    #[test]
+    fn retry_wrapper_stops_at_the_first_success() {
+        let _serial = serial();
+
+        MockProcess::expect(MockInvocation::new("flaky").args(["--now"]).exit_code(1));
+        MockProcess::expect(MockInvocation::new("flaky").args(["--now"]).exit_code(2));
+        MockProcess::expect(MockInvocation::new("flaky").args(["--now"]).exit_code(0));
+        MockProcess::expect(MockInvocation::new("flaky").args(["--now"]).exit_code(0));
+
+        let (status, attempts) = block_on(run_with_retry::<MockCommand>("flaky", 5)).unwrap();
+
+        assert!(status.success());
+        assert_eq!(attempts, 3);
+        assert_eq!(MockProcess::pending_expectation_count(), 1);
+    }
+
+    #[test]
+    fn retry_wrapper_gives_up_after_the_last_attempt() {
+        let _serial = serial();
+
+        for _ in 0..3 {
+            MockProcess::expect(MockInvocation::new("broken").exit_code(7));
+        }
+
+        let (status, attempts) = block_on(run_with_retry::<MockCommand>("broken", 3)).unwrap();
+
+        assert_eq!(status.code(), Some(7));
+        assert_eq!(attempts, 3);
+        assert_eq!(MockProcess::pending_expectation_count(), 0);
+    }
+
+    #[test]
+    fn output_and_delay_are_played_back() {
+        let _serial = serial();
+
+        MockProcess::expect(
+            MockInvocation::new("slow")
+                .stdout("out")
+                .stderr("err")
+                .exit_code(4)
+                .delay(Duration::from_secs(10)),
+        );
+
+        let mut command = MockCommand::new("slow");
+        let mut output = command.output();
+        assert!((&mut output).now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_secs(10));
+
+        let output = block_on(output).unwrap();
+        assert_eq!(output.status.code(), Some(4));
+        assert_eq!((&output.stdout[..], &output.stderr[..]), (&b"out"[..], &b"err"[..]));
+    }
+
+    #[test]
+    fn unexpected_invocations_fail_with_a_clear_message() {
+        let _serial = serial();
+
+        MockProcess::expect(MockInvocation::new("ls").args(["-a"]));
+
+        let payload = std::panic::catch_unwind(|| MockCommand::new("ls").arg("-l").spawn()).unwrap_err();
+        let message = payload.downcast::<String>().unwrap();
+
+        assert!(message.starts_with(r#"unexpected invocation of "ls" with the arguments ["-l"]"#), "{}", message);
+        assert!(message.contains(r#"program: "ls", args: ["-a"]"#), "{}", message);
+
+        // The expectation which did not match is still there.
+        assert_eq!(MockProcess::pending_expectation_count(), 1);
+    }
+}
@@ -0,0 +1,707 @@
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::{ExitStatus, Output};
+use std::time::Duration;
+
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+
+use async_trait::async_trait;
+
+use crate::task::{timeout, Timer};
+
+
+
+/// Contains the implementations for the [`tokio`](https://docs.rs/tokio) runtime.
+#[cfg(feature = "tokio-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
+mod tokio;
+
+/// Contains the implementations for the [`async_std`](https://docs.rs/async-std) runtime.
+#[cfg(feature = "async-std-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
+mod async_std;
+#[cfg(feature = "async-std-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
+pub use self::async_std::*;
+
+/// Piping data through child processes.
+mod pipe_through;
+pub use self::pipe_through::*;
+
+/// A fake process backend for tests.
+#[cfg(all(feature = "test-util", any(unix, windows)))]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+mod mock;
+#[cfg(all(feature = "test-util", any(unix, windows)))]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+pub use self::mock::*;
+
+
+
+/// Describes what to do with a standard io stream of a child process, see
+/// [`Command::stdin`], [`Command::stdout`] and [`Command::stderr`].
+///
+/// It gets mapped to [`std::process::Stdio`], which is used by every runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stdio {
+    /// The child inherits the stream from the parent.
+    Inherit,
+    /// A new pipe gets arranged to connect the parent and the child.
+    Piped,
+    /// The stream gets ignored, like when attached to `/dev/null`.
+    Null,
+}
+
+impl From<Stdio> for std::process::Stdio {
+    fn from(stdio: Stdio) -> Self {
+        match stdio {
+            Stdio::Inherit => Self::inherit(),
+            Stdio::Piped => Self::piped(),
+            Stdio::Null => Self::null(),
+        }
+    }
+}
+
+
+
+/// An async abstraction over [`std::process::Command`].
+///
+/// # Examples
+///
+/// Using the [`tokio`](https://docs.rs/tokio) runtime:
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// #
+/// use fut_compat::process::Command;
+///
+/// async fn greet<C: Command>() -> std::io::Result<Vec<u8>> {
+///     let output = C::new("echo").arg("hello").output().await?;
+///
+///     Ok(output.stdout)
+/// }
+///
+/// let stdout = greet::<tokio::process::Command>().await?;
+/// assert_eq!(stdout, b"hello\n");
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[async_trait]
+pub trait Command: Sized {
+    /// The child process which gets returned by the [`spawn`](#tymethod.spawn) method.
+    type Child: Child;
+
+    /// Creates a new command for launching the program at path `program`.
+    ///
+    /// By default, the program inherits the environment, the working directory and the standard
+    /// io streams of the current process.
+    fn new<S: AsRef<OsStr>>(program: S) -> Self;
+
+    /// Adds a single argument to pass to the program.
+    fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self;
+
+    /// Adds multiple arguments to pass to the program.
+    fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>;
+
+    /// Adds or updates an environment variable of the child process.
+    fn env<K, V>(&mut self, key: K, val: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>;
+
+    /// Adds or updates multiple environment variables of the child process.
+    fn envs<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>;
+
+    /// Removes an environment variable from the environment of the child process, whether it has
+    /// been inherited or added before.
+    fn env_remove<K: AsRef<OsStr>>(&mut self, key: K) -> &mut Self;
+
+    /// Clears the entire environment of the child process, including the variables added before.
+    fn env_clear(&mut self) -> &mut Self;
+
+    /// Sets the working directory of the child process.
+    fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self;
+
+    /// Configures the standard input of the child process.
+    fn stdin(&mut self, cfg: Stdio) -> &mut Self;
+
+    /// Configures the standard output of the child process.
+    fn stdout(&mut self, cfg: Stdio) -> &mut Self;
+
+    /// Configures the standard error of the child process.
+    fn stderr(&mut self, cfg: Stdio) -> &mut Self;
+
+    /// Controls whether the child process gets killed when its [`Child`] handle gets dropped.
+    ///
+    /// This prevents leaking the process when the future holding the handle gets cancelled. Only
+    /// the child itself gets killed, use [`process_group`](#tymethod.process_group) and
+    /// [`Child::kill_group`] to also stop the processes it started.
+    ///
+    /// Defaults to `false`.
+    fn kill_on_drop(&mut self, kill_on_drop: bool) -> &mut Self;
+
+    /// Sets the process group of the child process.
+    ///
+    /// A value of `0` puts the child into a new process group whose id equals the id of the
+    /// child, so it and all its descendants can be killed with [`Child::kill_group`]. Other values
+    /// make the child join the existing process group with that id.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    fn process_group(&mut self, pgroup: i32) -> &mut Self;
+
+    /// Sets the first argument of the child process, which is the program name by default.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    fn arg0<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self;
+
+    /// Sets the user id of the child process, like with `setuid`.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    fn uid(&mut self, id: u32) -> &mut Self;
+
+    /// Sets the group id of the child process, like with `setgid`.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    fn gid(&mut self, id: u32) -> &mut Self;
+
+    /// Schedules `f` to run in the child process after forking and before executing the program,
+    /// e.g. for setting resource limits. An error returned by `f` fails the spawn.
+    ///
+    /// Multiple hooks run in the order they have been added.
+    ///
+    /// # Safety
+    ///
+    /// `f` runs in a forked copy of the process with only a single thread, so it must only call
+    /// async-signal-safe functions, see [`CommandExt::pre_exec`] for the details. In particular
+    /// it must not allocate or take locks.
+    ///
+    /// [`CommandExt::pre_exec`]: std::os::unix::process::CommandExt::pre_exec
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    unsafe fn pre_exec<F>(&mut self, f: F) -> &mut Self
+    where
+        F: FnMut() -> std::io::Result<()> + Send + Sync + 'static;
+
+    /// Executes the command as a child process, returning a handle to it.
+    fn spawn(&mut self) -> std::io::Result<Self::Child>;
+
+    /// Executes the command as a child process, waits for it to exit and returns its exit status.
+    ///
+    /// The standard io streams default to [`Stdio::Inherit`].
+    async fn status(&mut self) -> std::io::Result<ExitStatus>;
+
+    /// Executes the command as a child process, waits for it to exit and collects its output.
+    ///
+    /// The standard output and standard error get captured and default to [`Stdio::Piped`]. The
+    /// standard input defaults to [`Stdio::Null`].
+    async fn output(&mut self) -> std::io::Result<Output>;
+
+    /// Like [`output`](#tymethod.output), but captures at most `max_bytes` of the standard output
+    /// and of the standard error each, so a child writing excessive amounts can not exhaust the
+    /// memory.
+    ///
+    /// The output beyond the limit gets read and discarded, so the child does not block on a full
+    /// pipe. Both streams get read concurrently while waiting for the child to exit.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn example<C: fut_compat::process::Command + Send>() -> std::io::Result<()>
+    /// # where
+    /// #     C::Child: Send,
+    /// # {
+    /// let output = C::new("journalctl").output_limited(1024 * 1024).await?;
+    ///
+    /// if output.stdout_truncated {
+    ///     println!("only showing the first MiB");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn output_limited(&mut self, max_bytes: usize) -> std::io::Result<LimitedOutput>
+    where
+        Self::Child: Send,
+    {
+        self.stdin(Stdio::Null);
+        self.stdout(Stdio::Piped);
+        self.stderr(Stdio::Piped);
+
+        let mut child = self.spawn()?;
+        let stdout = child.take_stdout();
+        let stderr = child.take_stderr();
+
+        let ((stdout, stdout_truncated), (stderr, stderr_truncated), status) = futures::try_join!(
+            read_limited(stdout, max_bytes),
+            read_limited(stderr, max_bytes),
+            child.wait(),
+        )?;
+
+        Ok(LimitedOutput {
+            status,
+            stdout,
+            stderr,
+            stdout_truncated,
+            stderr_truncated,
+        })
+    }
+}
+
+/// The output of a finished child process, as returned by
+/// [`output_limited`](Command::output_limited).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LimitedOutput {
+    /// The exit status of the child.
+    pub status: ExitStatus,
+    /// The captured standard output, up to the limit.
+    pub stdout: Vec<u8>,
+    /// The captured standard error, up to the limit.
+    pub stderr: Vec<u8>,
+    /// Whether the standard output exceeded the limit and has been truncated.
+    pub stdout_truncated: bool,
+    /// Whether the standard error exceeded the limit and has been truncated.
+    pub stderr_truncated: bool,
+}
+
+/// Sends `signal` to the process with the id `pid`, or to the process group with that id if
+/// `group` is set.
+#[cfg(all(unix, any(feature = "tokio-rt", feature = "async-std-rt")))]
+fn send_signal(pid: Option<u32>, group: bool, signal: libc::c_int) -> std::io::Result<()> {
+    let pid = pid.and_then(|pid| libc::pid_t::try_from(pid).ok()).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "the child has already been waited for",
+        )
+    })?;
+    let target = if group { -pid } else { pid };
+
+    // SAFETY: `kill` has no memory safety requirements. A negative id addresses a group.
+    match unsafe { libc::kill(target, signal) } {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+/// Reads `reader` until EOF, keeping at most `limit` bytes. Returns whether any bytes have been
+/// discarded.
+async fn read_limited<R>(reader: Option<R>, limit: usize) -> std::io::Result<(Vec<u8>, bool)>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = match reader {
+        Some(reader) => reader,
+        None => return Ok((Vec::new(), false)),
+    };
+
+    let mut captured = Vec::new();
+    let mut truncated = false;
+    let mut buf = [0u8; 8 * 1024];
+
+    loop {
+        let len = match reader.read(&mut buf).await {
+            Ok(0) => return Ok((captured, truncated)),
+            Ok(len) => len,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+
+        let keep = len.min(limit - captured.len());
+        captured.extend_from_slice(&buf[..keep]);
+        truncated |= keep < len;
+    }
+}
+
+/// An async abstraction over [`std::process::Child`].
+#[async_trait]
+pub trait Child {
+    /// The handle for writing to the standard input of the child.
+    type Stdin: AsyncWrite + Unpin + Send;
+    /// The handle for reading from the standard output of the child.
+    type Stdout: AsyncRead + Unpin + Send;
+    /// The handle for reading from the standard error of the child.
+    type Stderr: AsyncRead + Unpin + Send;
+
+    /// Returns the OS-assigned process identifier of the child.
+    ///
+    /// Depending on the runtime, `None` gets returned once the child has been waited for.
+    fn id(&self) -> Option<u32>;
+
+    /// Forces the child process to exit, without waiting for it to do so.
+    ///
+    /// Returns an error if the child already exited. Use [`wait`](#tymethod.wait) to reap it
+    /// afterwards.
+    fn kill(&mut self) -> std::io::Result<()>;
+
+    /// Waits for the child to exit completely and returns its exit status.
+    ///
+    /// The standard input of the child gets closed before waiting, so it does not wait for input
+    /// forever.
+    async fn wait(&mut self) -> std::io::Result<ExitStatus>;
+
+    /// Returns the exit status of the child if it has exited, without blocking.
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>>;
+
+    /// Forces all processes of the process group led by the child to exit, by sending them
+    /// `SIGKILL`, without waiting for them to do so.
+    ///
+    /// The child must have been spawned with [`process_group(0)`](Command::process_group), so it
+    /// leads its own group. Otherwise there is no such group and an error gets returned.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    fn kill_group(&mut self) -> std::io::Result<()>;
+
+    /// Asks the child process to exit by sending it `SIGTERM`, without waiting for it to do so.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    fn terminate(&mut self) -> std::io::Result<()>;
+
+    /// Waits for the child to exit for at most `dur`, measured with the timer `Tm`.
+    ///
+    /// Returns `None` if the child is still running afterwards.
+    async fn wait_timeout<Tm: Timer>(&mut self, dur: Duration) -> std::io::Result<Option<ExitStatus>> {
+        match timeout::<Tm, _>(dur, self.wait()).await {
+            Ok(result) => result.map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Stops the child by asking it to exit first and forcing it to exit afterwards, and returns
+    /// its exit status.
+    ///
+    /// On unix the child gets sent `SIGTERM` and is waited for up to `term_wait`. Elsewhere the
+    /// child is only waited for, e.g. for exiting after its standard input has been closed. If it
+    /// is still running afterwards, it gets killed with [`kill`](#tymethod.kill) and waited for
+    /// up to `kill_wait`, after which an error of the kind
+    /// [`TimedOut`](std::io::ErrorKind::TimedOut) gets returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "tokio-rt")]
+    /// # async fn example(mut server: tokio::process::Child) -> std::io::Result<()> {
+    /// use std::time::Duration;
+    ///
+    /// use fut_compat::process::Child;
+    /// use fut_compat::task::TokioExecutor;
+    ///
+    /// let status = server.shutdown_gracefully::<TokioExecutor>(
+    ///     Duration::from_secs(10),
+    ///     Duration::from_secs(5),
+    /// ).await?;
+    /// println!("server stopped with {}", status);
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn shutdown_gracefully<Tm: Timer>(
+        &mut self,
+        term_wait: Duration,
+        kill_wait: Duration,
+    ) -> std::io::Result<ExitStatus> {
+        if let Some(status) = self.try_wait()? {
+            return Ok(status);
+        }
+
+        #[cfg(unix)]
+        if let Err(err) = self.terminate() {
+            // The child may have exited in the meantime.
+            return self.try_wait()?.ok_or(err);
+        }
+
+        if let Some(status) = self.wait_timeout::<Tm>(term_wait).await? {
+            return Ok(status);
+        }
+
+        if let Err(err) = self.kill() {
+            return self.try_wait()?.ok_or(err);
+        }
+
+        self.wait_timeout::<Tm>(kill_wait).await?.ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "the child did not exit after being killed",
+            )
+        })
+    }
+
+    /// Takes the handle for writing to the standard input of the child.
+    ///
+    /// Returns `None` unless the standard input has been configured as [`Stdio::Piped`], or if it
+    /// has been taken before. Dropping the handle closes the standard input.
+    fn take_stdin(&mut self) -> Option<Self::Stdin>;
+
+    /// Takes the handle for reading from the standard output of the child.
+    ///
+    /// Returns `None` unless the standard output has been configured as [`Stdio::Piped`], or if
+    /// it has been taken before.
+    fn take_stdout(&mut self) -> Option<Self::Stdout>;
+
+    /// Takes the handle for reading from the standard error of the child.
+    ///
+    /// Returns `None` unless the standard error has been configured as [`Stdio::Piped`], or if it
+    /// has been taken before.
+    fn take_stderr(&mut self) -> Option<Self::Stderr>;
+}
+
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    /// What [`echo`] prints.
+    pub(super) const HELLO: &[u8] = if cfg!(windows) { b"hello\r\n" } else { b"hello\n" };
+
+    /// Returns a command printing [`HELLO`].
+    pub(super) fn echo<C: Command>() -> C {
+        #[cfg(windows)]
+        let command = {
+            let mut command = C::new("cmd");
+            command.args(["/C", "echo hello"]);
+            command
+        };
+        #[cfg(not(windows))]
+        let command = {
+            let mut command = C::new("echo");
+            command.arg("hello");
+            command
+        };
+
+        command
+    }
+
+    /// Captures the output of [`echo`] with [`Command::output`] and by reading the standard output
+    /// of the spawned child.
+    pub(super) async fn echo_round_trip<C: Command>() {
+        let output = echo::<C>().output().await.unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, HELLO);
+        assert!(output.stderr.is_empty());
+
+        let mut child = echo::<C>().stdout(Stdio::Piped).spawn().unwrap();
+        let mut stdout = Vec::new();
+        child.take_stdout().unwrap().read_to_end(&mut stdout).await.unwrap();
+
+        assert!(child.take_stdout().is_none());
+        assert!(Child::wait(&mut child).await.unwrap().success());
+        assert_eq!(stdout, HELLO);
+
+        let status = echo::<C>().stdout(Stdio::Null).status().await.unwrap();
+        assert!(status.success());
+    }
+    /// Returns a command running `script` with the shell.
+    #[cfg(unix)]
+    pub(super) fn sh<C: Command>(script: &str) -> C {
+        let mut command = C::new("sh");
+        command.args(["-c", script]);
+        command
+    }
+
+    /// Captures the output of a child writing far more than fits into a pipe to both streams.
+    #[cfg(unix)]
+    pub(super) async fn flooding_output_gets_truncated<C: Command + Send>()
+    where
+        C::Child: Send,
+    {
+        let script = "head -c 1048576 /dev/zero; head -c 1048576 /dev/zero >&2; echo done >&2";
+        let output = sh::<C>(script).output_limited(1000).await.unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, [0; 1000]);
+        assert_eq!(output.stderr, [0; 1000]);
+        assert!(output.stdout_truncated && output.stderr_truncated);
+
+        // Output of exactly the limit is complete.
+        let output = echo::<C>().output_limited(HELLO.len()).await.unwrap();
+
+        assert_eq!(output.stdout, HELLO);
+        assert!(!output.stdout_truncated && !output.stderr_truncated);
+    }
+    /// Returns whether the process with the id `pid` has exited, i.e. `kill(pid, 0)` fails with
+    /// `ESRCH`. On Linux zombies count as exited as well, as orphans are not reaped in containers
+    /// whose init process does not do so.
+    #[cfg(unix)]
+    fn has_exited(pid: u32) -> bool {
+        // SAFETY: `kill` has no memory safety requirements, the signal `0` only checks whether the
+        // process exists.
+        if unsafe { libc::kill(pid as libc::pid_t, 0) } != 0 {
+            return std::io::Error::last_os_error().raw_os_error() == Some(libc::ESRCH);
+        }
+
+        std::fs::read_to_string(format!("/proc/{}/stat", pid))
+            .map(|stat| stat.rsplit(')').next().unwrap_or_default().trim_start().starts_with('Z'))
+            .unwrap_or(false)
+    }
+
+    /// Waits up to 10 seconds for the process with the id `pid` to exit and returns whether it did.
+    #[cfg(unix)]
+    pub(super) async fn exits<Tm: Timer>(pid: u32) -> bool {
+        for _ in 0..1000 {
+            if has_exited(pid) {
+                return true;
+            }
+
+            Tm::sleep(Duration::from_millis(10)).await;
+        }
+
+        false
+    }
+
+    /// Cancels a future owning a sleeping child spawned with `kill_on_drop`.
+    #[cfg(unix)]
+    pub(super) async fn cancelled_child_gets_killed<C: Command, Tm: Timer>()
+    where
+        C::Child: Send + 'static,
+    {
+        let mut child = C::new("sleep").arg("60").kill_on_drop(true).spawn().unwrap();
+        let pid = child.id().unwrap();
+
+        let waiting = async move { Child::wait(&mut child).await };
+        assert!(timeout::<Tm, _>(Duration::from_millis(50), waiting).await.is_err());
+
+        assert!(exits::<Tm>(pid).await);
+    }
+
+    /// Kills the process group of a shell which started a grandchild.
+    #[cfg(unix)]
+    pub(super) async fn group_kill_with_grandchild<C: Command, Tm: Timer>() {
+        use std::os::unix::process::ExitStatusExt;
+
+        use futures::io::AsyncBufReadExt;
+
+        let mut child = sh::<C>("sleep 60 & echo $!; wait").process_group(0).stdout(Stdio::Piped).spawn().unwrap();
+
+        let mut line = String::new();
+        futures::io::BufReader::new(child.take_stdout().unwrap()).read_line(&mut line).await.unwrap();
+        let grandchild: u32 = line.trim().parse().unwrap();
+
+        child.kill_group().unwrap();
+
+        assert_eq!(Child::wait(&mut child).await.unwrap().signal(), Some(libc::SIGKILL));
+        assert!(exits::<Tm>(grandchild).await);
+    }
+    /// Shuts down a child which ignores `SIGTERM`, so it has to be killed.
+    #[cfg(target_os = "linux")]
+    pub(super) async fn shutdown_escalates_to_kill<C: Command, Tm: Timer>()
+    where
+        C::Child: Send,
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        let mut child = sh::<C>("trap '' TERM; exec sleep 60").spawn().unwrap();
+
+        // Gives the shell time to install the trap.
+        Tm::sleep(Duration::from_millis(100)).await;
+
+        let status = child.shutdown_gracefully::<Tm>(Duration::from_millis(200), Duration::from_secs(10)).await.unwrap();
+
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+    }
+    /// Spawns `command` with all standard io streams piped.
+    #[cfg(unix)]
+    fn spawn_piped<C: Command>(command: &mut C) -> C::Child {
+        command.stdin(Stdio::Piped).stdout(Stdio::Piped).stderr(Stdio::Piped).spawn().unwrap()
+    }
+
+    /// Pipes data through `tr` and `sort`, including more than fits into a pipe.
+    #[cfg(unix)]
+    pub(super) async fn pipe_through_transforms<C: Command>() {
+        let mut output = Vec::new();
+        let child = spawn_piped(C::new("tr").args(["a-z", "A-Z"]));
+        let status = pipe_through(&b"hello world"[..], child, &mut output).await.unwrap();
+
+        assert!(status.success());
+        assert_eq!(output, b"HELLO WORLD");
+
+        let input = b"abcdefghijklmnopqrstuvwxyz\n".repeat(40_000);
+        let mut output = Vec::new();
+        let child = spawn_piped(C::new("tr").args(["a-z", "A-Z"]));
+        pipe_through(&input[..], child, &mut output).await.unwrap();
+
+        assert_eq!(output, input.to_ascii_uppercase());
+
+        // `sort` only writes once its input has been closed.
+        let lines: Vec<String> = (0..100_000).rev().map(|n| format!("{:08}\n", n)).collect();
+        let mut output = Vec::new();
+        let child = spawn_piped(C::new("sort").env("LC_ALL", "C"));
+        pipe_through(lines.concat().as_bytes(), child, &mut output).await.unwrap();
+
+        let mut sorted = lines;
+        sorted.sort();
+        assert_eq!(output, sorted.concat().as_bytes());
+
+        // The child fills the output pipe before reading its input.
+        let input = vec![b'x'; 1024 * 1024];
+        let mut output = Vec::new();
+        let child = spawn_piped(&mut sh::<C>("head -c 1048576 /dev/zero; cat"));
+        pipe_through(&input[..], child, &mut output).await.unwrap();
+
+        assert_eq!(output.len(), 2 * 1024 * 1024);
+        assert!(output[..1024 * 1024].iter().all(|byte| *byte == 0));
+        assert_eq!(&output[1024 * 1024..], &input[..]);
+    }
+
+    /// Handles the standard error of a child with every [`StderrMode`].
+    #[cfg(unix)]
+    pub(super) async fn pipe_through_stderr_modes<C: Command>() {
+        let script = "cat; head -c 1048576 /dev/zero >&2; echo oops >&2; exit 3";
+        let mut expected_stderr = vec![0; 1024 * 1024];
+        expected_stderr.extend_from_slice(b"oops\n");
+
+        let mut output = Vec::new();
+        let child = spawn_piped(&mut sh::<C>(script));
+        let result = pipe_through_with(&b"in"[..], child, &mut output, StderrMode::<Vec<u8>>::Discard).await.unwrap();
+
+        assert_eq!(result.status.code(), Some(3));
+        assert!(result.stderr.is_empty());
+        assert_eq!(output, b"in");
+
+        let mut output = Vec::new();
+        let child = spawn_piped(&mut sh::<C>(script));
+        let result = pipe_through_with(&b"in"[..], child, &mut output, StderrMode::<Vec<u8>>::Capture).await.unwrap();
+
+        assert_eq!(result.stderr, expected_stderr);
+        assert_eq!(output, b"in");
+
+        let (mut output, mut forwarded) = (Vec::new(), Vec::new());
+        let child = spawn_piped(&mut sh::<C>(script));
+        let result = pipe_through_with(&b"in"[..], child, &mut output, StderrMode::Forward(&mut forwarded)).await.unwrap();
+
+        assert!(result.stderr.is_empty());
+        assert_eq!(forwarded, expected_stderr);
+        assert_eq!(output, b"in");
+    }
+    /// Lowers the limit of open files of the child in a `pre_exec` hook.
+    #[cfg(unix)]
+    pub(super) async fn pre_exec_sets_rlimit<C: Command>() {
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // SAFETY: `limit` is a valid pointer to write the limit to.
+        assert_eq!(unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) }, 0);
+        limit.rlim_cur = limit.rlim_cur.min(123);
+
+        let mut command = sh::<C>("ulimit -n");
+        // SAFETY: `setrlimit` is async-signal-safe and the hook neither allocates nor locks.
+        unsafe {
+            command.pre_exec(move || match libc::setrlimit(libc::RLIMIT_NOFILE, &limit) {
+                0 => Ok(()),
+                _ => Err(std::io::Error::last_os_error()),
+            });
+        }
+
+        let output = command.output().await.unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), limit.rlim_cur.to_string());
+    }
+}
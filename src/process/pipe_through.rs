@@ -0,0 +1,148 @@
+use std::process::ExitStatus;
+
+use futures::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+
+use super::Child;
+
+
+
+/// Describes what [`pipe_through_with`] does with the standard error of the child.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StderrMode<W> {
+    /// Reads and discards the output.
+    Discard,
+    /// Collects the output into [`PipeOutput::stderr`], without limiting its size.
+    Capture,
+    /// Copies the output into the writer.
+    Forward(W),
+}
+
+/// The result of [`pipe_through_with`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PipeOutput {
+    /// The exit status of the child.
+    pub status: ExitStatus,
+    /// The standard error of the child if it got captured, empty otherwise.
+    pub stderr: Vec<u8>,
+}
+
+/// Pipes `input` through `child` into `output`, like `input | child > output` in a shell, and
+/// returns the exit status of the child.
+///
+/// The standard error gets discarded, see [`pipe_through_with`] for the details.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use fut_compat::process::{pipe_through, Command, Stdio};
+///
+/// let child = <tokio::process::Command as Command>::new("tr")
+///     .args(["a-z", "A-Z"])
+///     .stdin(Stdio::Piped)
+///     .stdout(Stdio::Piped)
+///     .spawn()?;
+///
+/// let mut output = Vec::new();
+/// pipe_through(&b"hello"[..], child, &mut output).await?;
+///
+/// assert_eq!(output, b"HELLO");
+/// # Ok(())
+/// # }
+/// ```
+pub async fn pipe_through<R, W, C>(input: R, child: C, output: W) -> std::io::Result<ExitStatus>
+where
+    R: AsyncRead,
+    W: AsyncWrite + Unpin,
+    C: Child,
+{
+    let output = pipe_through_with(input, child, output, StderrMode::<futures::io::Sink>::Discard).await?;
+
+    Ok(output.status)
+}
+
+/// Pipes `input` through `child` into `output`, handling the standard error as chosen by
+/// `stderr`.
+///
+/// The standard input and output of the child must have been configured as
+/// [`Stdio::Piped`](super::Stdio::Piped). The standard error only gets handled if it has been
+/// piped as well.
+///
+/// All streams get copied concurrently while waiting for the child to exit, so the child can not
+/// block on a full pipe. The standard input gets closed once `input` reached its end, so the
+/// child sees the end of its input. If the child exits without reading all of its input, the
+/// remaining input gets ignored. `output` gets flushed, but not closed.
+pub async fn pipe_through_with<R, W, E, C>(
+    input: R,
+    mut child: C,
+    mut output: W,
+    stderr: StderrMode<E>,
+) -> std::io::Result<PipeOutput>
+where
+    R: AsyncRead,
+    W: AsyncWrite + Unpin,
+    E: AsyncWrite + Unpin,
+    C: Child,
+{
+    let mut stdin = child.take_stdin().ok_or_else(|| not_piped("input"))?;
+    let mut stdout = child.take_stdout().ok_or_else(|| not_piped("output"))?;
+    let child_stderr = child.take_stderr();
+
+    let copy_input = async move {
+        let result = match futures::io::copy(input, &mut stdin).await {
+            Ok(_) => stdin.close().await,
+            Err(err) => Err(err),
+        };
+
+        match result {
+            // The child stopped reading its input.
+            Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+            result => result,
+        }
+    };
+
+    let copy_output = futures::io::copy(&mut stdout, &mut output);
+
+    let drain_stderr = async move {
+        let mut captured = Vec::new();
+
+        let mut child_stderr = match child_stderr {
+            Some(child_stderr) => child_stderr,
+            None => return Ok(captured),
+        };
+
+        match stderr {
+            StderrMode::Discard => {
+                futures::io::copy(&mut child_stderr, &mut futures::io::sink()).await?;
+            }
+            StderrMode::Capture => {
+                futures::io::copy(&mut child_stderr, &mut captured).await?;
+            }
+            StderrMode::Forward(mut writer) => {
+                futures::io::copy(&mut child_stderr, &mut writer).await?;
+            }
+        }
+
+        Ok(captured)
+    };
+
+    let ((), _, stderr, status) = futures::try_join!(
+        copy_input,
+        copy_output,
+        drain_stderr,
+        child.wait(),
+    )?;
+
+    Ok(PipeOutput {
+        status,
+        stderr,
+    })
+}
+
+fn not_piped(stream: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("the standard {} of the child is not piped", stream),
+    )
+}
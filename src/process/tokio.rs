@@ -0,0 +1,213 @@
+use super::*;
+use crate::io::TokioCompat;
+
+use ::tokio::process;
+
+
+
+#[async_trait]
+impl Command for process::Command {
+    type Child = process::Child;
+
+    fn new<S: AsRef<OsStr>>(program: S) -> Self {
+        Self::new(program)
+    }
+
+    fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.arg(arg)
+    }
+
+    fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args(args)
+    }
+
+    fn env<K, V>(&mut self, key: K, val: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.env(key, val)
+    }
+
+    fn envs<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.envs(vars)
+    }
+
+    fn env_remove<K: AsRef<OsStr>>(&mut self, key: K) -> &mut Self {
+        self.env_remove(key)
+    }
+
+    fn env_clear(&mut self) -> &mut Self {
+        self.env_clear()
+    }
+
+    fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.current_dir(dir)
+    }
+
+    fn stdin(&mut self, cfg: Stdio) -> &mut Self {
+        self.stdin(cfg)
+    }
+
+    fn stdout(&mut self, cfg: Stdio) -> &mut Self {
+        self.stdout(cfg)
+    }
+
+    fn stderr(&mut self, cfg: Stdio) -> &mut Self {
+        self.stderr(cfg)
+    }
+
+    fn kill_on_drop(&mut self, kill_on_drop: bool) -> &mut Self {
+        self.kill_on_drop(kill_on_drop)
+    }
+
+    #[cfg(unix)]
+    fn process_group(&mut self, pgroup: i32) -> &mut Self {
+        self.process_group(pgroup)
+    }
+
+    #[cfg(unix)]
+    fn arg0<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.arg0(arg)
+    }
+
+    #[cfg(unix)]
+    fn uid(&mut self, id: u32) -> &mut Self {
+        self.uid(id)
+    }
+
+    #[cfg(unix)]
+    fn gid(&mut self, id: u32) -> &mut Self {
+        self.gid(id)
+    }
+
+    #[cfg(unix)]
+    unsafe fn pre_exec<F>(&mut self, f: F) -> &mut Self
+    where
+        F: FnMut() -> std::io::Result<()> + Send + Sync + 'static,
+    {
+        // SAFETY: The caller upholds the requirements of `pre_exec`, which are the same.
+        unsafe { self.pre_exec(f) }
+    }
+
+    fn spawn(&mut self) -> std::io::Result<Self::Child> {
+        self.spawn()
+    }
+
+    async fn status(&mut self) -> std::io::Result<ExitStatus> {
+        self.status().await
+    }
+
+    async fn output(&mut self) -> std::io::Result<Output> {
+        self.output().await
+    }
+}
+
+#[async_trait]
+impl Child for process::Child {
+    type Stdin = TokioCompat<process::ChildStdin>;
+    type Stdout = TokioCompat<process::ChildStdout>;
+    type Stderr = TokioCompat<process::ChildStderr>;
+
+    fn id(&self) -> Option<u32> {
+        self.id()
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        self.start_kill()
+    }
+
+    async fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        self.wait().await
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        self.try_wait()
+    }
+
+    #[cfg(unix)]
+    fn kill_group(&mut self) -> std::io::Result<()> {
+        send_signal(self.id(), true, libc::SIGKILL)
+    }
+
+    #[cfg(unix)]
+    fn terminate(&mut self) -> std::io::Result<()> {
+        send_signal(self.id(), false, libc::SIGTERM)
+    }
+
+    fn take_stdin(&mut self) -> Option<Self::Stdin> {
+        self.stdin.take().map(TokioCompat::new)
+    }
+
+    fn take_stdout(&mut self) -> Option<Self::Stdout> {
+        self.stdout.take().map(TokioCompat::new)
+    }
+
+    fn take_stderr(&mut self) -> Option<Self::Stderr> {
+        self.stderr.take().map(TokioCompat::new)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    #[test]
+    fn echo_output_gets_captured() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(echo_round_trip::<process::Command>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn limited_output_reads_both_streams_concurrently() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(flooding_output_gets_truncated::<process::Command>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cancelling_kills_the_child() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(cancelled_child_gets_killed::<process::Command, crate::task::TokioExecutor>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn group_kill_reaches_grandchildren() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(group_kill_with_grandchild::<process::Command, crate::task::TokioExecutor>());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn shutdown_kills_children_ignoring_sigterm() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(shutdown_escalates_to_kill::<process::Command, crate::task::TokioExecutor>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pipe_through_transforms_the_input() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(pipe_through_transforms::<process::Command>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pipe_through_handles_stderr() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(pipe_through_stderr_modes::<process::Command>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pre_exec_runs_in_the_child() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(pre_exec_sets_rlimit::<process::Command>());
+    }
+}
@@ -0,0 +1,34 @@
+use super::*;
+
+use crate::fs::AsyncStdFs;
+use crate::process::AsyncStdCommand;
+use crate::task::AsyncStdExecutor;
+
+
+
+/// The [`Runtime`] of [`async_std`](https://docs.rs/async-std).
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AsyncStdRuntime {}
+
+impl Runtime for AsyncStdRuntime {
+    type Fs = AsyncStdFs;
+    type File = ::async_std::fs::File;
+    type TcpStream = ::async_std::net::TcpStream;
+    type TcpListener = ::async_std::net::TcpListener;
+    type UdpSocket = ::async_std::net::UdpSocket;
+    #[cfg(unix)]
+    type UnixStream = ::async_std::os::unix::net::UnixStream;
+    #[cfg(unix)]
+    type UnixListener = ::async_std::os::unix::net::UnixListener;
+    type Command = AsyncStdCommand;
+    type Executor = AsyncStdExecutor;
+    type Timer = AsyncStdExecutor;
+
+    fn fs() -> Self::Fs {
+        AsyncStdFs::default()
+    }
+
+    fn executor() -> Self::Executor {
+        AsyncStdExecutor::default()
+    }
+}
@@ -0,0 +1,254 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use futures::io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use crate::fs::{File, Filesystem};
+use crate::net::{TcpListener, TcpStream, UdpSocket};
+#[cfg(unix)]
+use crate::net::{UnixListener, UnixStream};
+use crate::process::Command;
+use crate::task::{Spawn, SpawnBlocking, SpawnHandle, Timer};
+
+
+
+#[cfg(feature = "tokio-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
+mod tokio;
+#[cfg(feature = "tokio-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
+pub use self::tokio::*;
+
+
+#[cfg(feature = "async-std-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
+mod async_std;
+#[cfg(feature = "async-std-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
+pub use self::async_std::*;
+
+
+
+/// Ties the abstractions of the other modules together, so code can be generic over a single
+/// runtime instead of one type per abstraction.
+///
+/// All io objects implement the io traits of [`futures`], wrapping the ones of
+/// [`tokio`](https://docs.rs/tokio) in [`TokioCompat`](crate::io::TokioCompat) where needed.
+///
+/// # Example
+///
+/// A small service, written once and used with both runtimes:
+///
+/// ```
+/// # #[cfg(all(feature = "tokio-rt", feature = "async-std-rt"))]
+/// # {
+/// use futures::io::{AsyncReadExt, AsyncWriteExt};
+/// use fut_compat::net::{TcpListener, TcpStream};
+/// use fut_compat::runtime::{AsyncStdRuntime, Runtime, TokioRuntime};
+/// use fut_compat::task::SpawnHandle;
+///
+/// /// Accepts a single connection and answers a ping with a pong.
+/// async fn ping_pong<R: Runtime>() -> std::io::Result<[u8; 4]> {
+///     let listener = R::TcpListener::bind("127.0.0.1:0").await?;
+///     let addr = listener.local_addr()?;
+///
+///     let server = R::executor().spawn_handle(async move {
+///         let (mut stream, _) = listener.accept().await?;
+///
+///         let mut ping = [0; 4];
+///         stream.read_exact(&mut ping).await?;
+///         assert_eq!(&ping, b"ping");
+///
+///         stream.write_all(b"pong").await
+///     });
+///
+///     let mut stream = R::TcpStream::connect(addr).await?;
+///     stream.write_all(b"ping").await?;
+///
+///     let mut pong = [0; 4];
+///     stream.read_exact(&mut pong).await?;
+///
+///     server.await??;
+///
+///     Ok(pong)
+/// }
+///
+/// let pong = tokio::runtime::Runtime::new()?.block_on(ping_pong::<TokioRuntime>())?;
+/// assert_eq!(&pong, b"pong");
+///
+/// let pong = async_std::task::block_on(ping_pong::<AsyncStdRuntime>())?;
+/// assert_eq!(&pong, b"pong");
+/// # }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub trait Runtime: Send + Sync + 'static {
+    /// The filesystem, see [`fs`](Self::fs).
+    type Fs: Filesystem + Send + Sync;
+    /// The files.
+    type File: File + AsyncRead + AsyncWrite + AsyncSeek + Send + Unpin;
+    /// The TCP connections.
+    type TcpStream: TcpStream + AsyncRead + AsyncWrite + Send + Unpin;
+    /// The TCP listeners, accepting connections as [`TcpStream`](Self::TcpStream)s.
+    type TcpListener: TcpListener<TcpStream = Self::TcpStream> + Send + Sync;
+    /// The UDP sockets.
+    type UdpSocket: UdpSocket + Send + Sync;
+    /// The Unix domain socket connections.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    type UnixStream: UnixStream + AsyncRead + AsyncWrite + Send + Unpin;
+    /// The Unix domain socket listeners, accepting connections as
+    /// [`UnixStream`](Self::UnixStream)s.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    type UnixListener: UnixListener<UnixStream = Self::UnixStream> + Send + Sync;
+    /// The commands for spawning child processes.
+    type Command: Command + Send;
+    /// The executor, see [`executor`](Self::executor).
+    type Executor: Spawn + SpawnHandle + SpawnBlocking + Clone + Send + Sync + 'static;
+    /// The timer.
+    type Timer: Timer;
+
+    /// Returns the filesystem of the runtime.
+    fn fs() -> Self::Fs;
+
+    /// Returns an executor which spawns tasks onto the runtime.
+    ///
+    /// Some runtimes, like the one of [`tokio`](https://docs.rs/tokio), must be entered for
+    /// this.
+    fn executor() -> Self::Executor;
+}
+
+
+
+/// The runtimes whose feature is enabled, e.g. for choosing one at startup.
+///
+/// It can be parsed from the name of the runtime, which is `tokio`, `async-std` or `smol`. Parsing
+/// fails for the names of runtimes whose feature is not enabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RuntimeKind {
+    /// The runtime of [`tokio`](https://docs.rs/tokio).
+    #[cfg(feature = "tokio-rt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
+    Tokio,
+    /// The runtime of [`async_std`](https://docs.rs/async-std).
+    #[cfg(feature = "async-std-rt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
+    AsyncStd,
+    /// The runtime of [`smol`](https://docs.rs/smol).
+    #[cfg(feature = "smol-rt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "smol-rt")))]
+    Smol,
+}
+
+impl RuntimeKind {
+    /// Returns the runtime to use on the current thread, as picked by
+    /// [`DefaultExecutor`](crate::task::DefaultExecutor).
+    ///
+    /// The result must not be cached across threads, as only some threads may be in the context
+    /// of a tokio runtime.
+    pub fn current() -> Option<Self> {
+        #[cfg(feature = "tokio-rt")]
+        if ::tokio::runtime::Handle::try_current().is_ok() {
+            return Some(Self::Tokio);
+        }
+
+        Self::global()
+    }
+
+    /// Returns the first enabled runtime with a global executor.
+    ///
+    /// Unlike the context of tokio this does not depend on the calling thread, so the result of
+    /// the first detection is cached.
+    fn global() -> Option<Self> {
+        // Every thread detects the same runtime, so racing detections store the same value.
+        let cached = match GLOBAL_RUNTIME.load(Ordering::Relaxed) {
+            GLOBAL_UNDETECTED => {
+                let detected = Self::detect_global();
+                GLOBAL_RUNTIME.store(detected, Ordering::Relaxed);
+
+                detected
+            }
+            cached => cached,
+        };
+
+        match cached {
+            #[cfg(feature = "async-std-rt")]
+            GLOBAL_ASYNC_STD => Some(Self::AsyncStd),
+            #[cfg(feature = "smol-rt")]
+            GLOBAL_SMOL => Some(Self::Smol),
+            _ => None,
+        }
+    }
+
+    /// Detects the runtime returned by [`global`](Self::global), encoded for its cache.
+    #[allow(unreachable_code)]
+    fn detect_global() -> u8 {
+        #[cfg(feature = "async-std-rt")]
+        return GLOBAL_ASYNC_STD;
+
+        #[cfg(feature = "smol-rt")]
+        return GLOBAL_SMOL;
+
+        GLOBAL_NONE
+    }
+}
+
+/// The cached result of [`RuntimeKind::global`].
+static GLOBAL_RUNTIME: AtomicU8 = AtomicU8::new(GLOBAL_UNDETECTED);
+
+/// The encodings of the results of [`RuntimeKind::global`] in its cache.
+const GLOBAL_UNDETECTED: u8 = 0;
+const GLOBAL_NONE: u8 = 1;
+#[cfg(feature = "async-std-rt")]
+const GLOBAL_ASYNC_STD: u8 = 2;
+#[cfg(feature = "smol-rt")]
+const GLOBAL_SMOL: u8 = 3;
+
+impl FromStr for RuntimeKind {
+    type Err = UnknownBackendError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            #[cfg(feature = "tokio-rt")]
+            "tokio" => Ok(Self::Tokio),
+            #[cfg(feature = "async-std-rt")]
+            "async-std" | "async_std" => Ok(Self::AsyncStd),
+            #[cfg(feature = "smol-rt")]
+            "smol" => Ok(Self::Smol),
+            _ => Err(UnknownBackendError::new(name)),
+        }
+    }
+}
+
+
+/// The error returned when parsing the name of a backend which is unknown, or whose feature is
+/// not enabled, e.g. by [`AnyFs::from_str`](crate::fs::AnyFs).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UnknownBackendError {
+    name: String,
+}
+
+impl UnknownBackendError {
+    /// Creates a new error for the given name.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+
+    /// Returns the name which could not be parsed.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for UnknownBackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown backend `{}`, or its feature is not enabled", self.name)
+    }
+}
+
+impl Error for UnknownBackendError {}
@@ -0,0 +1,38 @@
+use super::*;
+
+use crate::fs::TokioFs;
+use crate::io::TokioCompat;
+use crate::task::TokioExecutor;
+
+
+
+/// The [`Runtime`] of [`tokio`](https://docs.rs/tokio).
+///
+/// The io objects of tokio get wrapped in [`TokioCompat`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TokioRuntime {}
+
+impl Runtime for TokioRuntime {
+    type Fs = TokioFs;
+    type File = TokioCompat<::tokio::fs::File>;
+    type TcpStream = TokioCompat<::tokio::net::TcpStream>;
+    type TcpListener = TokioCompat<::tokio::net::TcpListener>;
+    type UdpSocket = ::tokio::net::UdpSocket;
+    #[cfg(unix)]
+    type UnixStream = TokioCompat<::tokio::net::UnixStream>;
+    #[cfg(unix)]
+    type UnixListener = TokioCompat<::tokio::net::UnixListener>;
+    type Command = ::tokio::process::Command;
+    type Executor = TokioExecutor;
+    type Timer = TokioExecutor;
+
+    fn fs() -> Self::Fs {
+        TokioFs::default()
+    }
+
+    /// Returns an executor for the tokio runtime the current thread runs on, see
+    /// [`TokioExecutor::default`].
+    fn executor() -> Self::Executor {
+        TokioExecutor::default()
+    }
+}
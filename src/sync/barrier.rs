@@ -0,0 +1,166 @@
+use super::*;
+
+
+
+/// Lets a number of tasks wait until all of them reached the same point, without depending on a
+/// runtime.
+///
+/// The async counterpart of [`std::sync::Barrier`]. It can be reused: Once all tasks have been
+/// released, the next [`wait`](Self::wait) calls belong to a new generation.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use futures::future::join_all;
+/// use fut_compat::sync::Barrier;
+///
+/// # futures::executor::block_on(async {
+/// let barrier = Arc::new(Barrier::new(3));
+///
+/// let results = join_all((0..3).map(|_| {
+///     let barrier = barrier.clone();
+///
+///     async move { barrier.wait().await }
+/// })).await;
+///
+/// // Exactly one of the tasks gets chosen as the leader.
+/// assert_eq!(results.iter().filter(|result| result.is_leader()).count(), 1);
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct Barrier {
+    n: usize,
+    state: Mutex<BarrierState>,
+    notify: Notify,
+}
+
+#[derive(Debug)]
+struct BarrierState {
+    /// The number of tasks waiting in the current generation.
+    arrived: usize,
+}
+
+impl Barrier {
+    /// Creates a new barrier which releases the waiting tasks once `n` of them called
+    /// [`wait`](Self::wait).
+    ///
+    /// A barrier for `0` tasks behaves like one for a single task, like the one of the standard
+    /// library.
+    pub fn new(n: usize) -> Self {
+        Self {
+            n: n.max(1),
+            state: Mutex::new(BarrierState {
+                arrived: 0,
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Waits until all tasks have called this method.
+    ///
+    /// The last task to arrive does not wait and becomes the leader of the generation, see
+    /// [`BarrierWaitResult::is_leader`].
+    ///
+    /// # Cancel safety
+    ///
+    /// The returned future is not cancel safe. If it gets dropped before completing, it still
+    /// counts as arrived, so the generation completes with one task less.
+    pub async fn wait(&self) -> BarrierWaitResult {
+        let notified = {
+            let mut state = lock(&self.state);
+
+            state.arrived += 1;
+
+            if state.arrived == self.n {
+                state.arrived = 0;
+
+                // Notified while holding the lock, so the tasks of the next generation can not
+                // get woken up.
+                self.notify.notify_waiters();
+
+                return BarrierWaitResult {
+                    is_leader: true,
+                };
+            }
+
+            // Created while holding the lock, so it can not miss the notification.
+            self.notify.notified()
+        };
+
+        notified.await;
+
+        BarrierWaitResult {
+            is_leader: false,
+        }
+    }
+}
+
+
+
+/// The result of [`Barrier::wait`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BarrierWaitResult {
+    is_leader: bool,
+}
+
+impl BarrierWaitResult {
+    /// Returns `true` if this task released the others, which is the case for exactly one task
+    /// of every generation.
+    pub fn is_leader(&self) -> bool {
+        self.is_leader
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::hash_map::RandomState;
+    use std::hash::BuildHasher;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use futures::executor::block_on;
+
+    #[test]
+    fn every_generation_has_one_leader() {
+        const TASKS: usize = 8;
+        const GENERATIONS: usize = 2;
+
+        let barrier = Arc::new(Barrier::new(TASKS));
+        let arrived: Arc<Vec<AtomicUsize>> = Arc::new((0..GENERATIONS).map(|_| AtomicUsize::new(0)).collect());
+
+        let tasks: Vec<_> = (0..TASKS).map(|task| {
+            let (barrier, arrived) = (barrier.clone(), arrived.clone());
+
+            std::thread::spawn(move || {
+                let random = RandomState::new();
+
+                (0..GENERATIONS).map(|generation| {
+                    // The tasks arrive in a random order.
+                    let delay = random.hash_one((task, generation)) % 20;
+                    std::thread::sleep(Duration::from_millis(delay));
+
+                    arrived[generation].fetch_add(1, Ordering::SeqCst);
+                    let result = block_on(barrier.wait());
+
+                    // Nobody gets released before all tasks arrived.
+                    assert_eq!(arrived[generation].load(Ordering::SeqCst), TASKS);
+
+                    result.is_leader()
+                }).collect::<Vec<bool>>()
+            })
+        }).collect();
+
+        let results: Vec<Vec<bool>> = tasks.into_iter().map(|task| task.join().unwrap()).collect();
+
+        for generation in 0..GENERATIONS {
+            let leaders = results.iter().filter(|leaders| leaders[generation]).count();
+
+            assert_eq!(leaders, 1, "generation {}", generation);
+        }
+    }
+}
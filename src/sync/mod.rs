@@ -0,0 +1,29 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+
+
+/// Notifying tasks, the async counterpart of a condition variable.
+mod notify;
+pub use self::notify::*;
+
+/// Waiting until a number of tasks reached the same point.
+mod barrier;
+pub use self::barrier::*;
+
+/// Initializing a value once, possibly by async code.
+mod once_cell;
+pub use self::once_cell::*;
+
+
+
+/// Locks `mutex`, ignoring poisoning.
+///
+/// The primitives of this module never panic while holding a lock, so the protected state is
+/// always consistent.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
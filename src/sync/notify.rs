@@ -0,0 +1,396 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use super::*;
+
+
+
+/// Set in [`Notify::state`] while a permit is stored.
+const PERMIT: usize = 1;
+/// Added to [`Notify::state`] by every call of [`Notify::notify_waiters`].
+const GENERATION: usize = 2;
+
+/// The waiter has not been notified yet.
+const WAITING: u8 = 0;
+/// The waiter got notified by [`Notify::notify_one`], so it consumed a permit.
+const NOTIFIED_ONE: u8 = 1;
+/// The waiter got notified by [`Notify::notify_waiters`].
+const NOTIFIED_ALL: u8 = 2;
+
+/// Notifies tasks waiting for an event, without depending on a runtime.
+///
+/// Behaves like the `Notify` of [`tokio`](https://docs.rs/tokio): A [`Notify`] can store a single
+/// permit. [`notify_one`](Self::notify_one) wakes the task waiting the longest, or stores the
+/// permit if no task is waiting, so the next call of [`notified`](Self::notified) completes
+/// immediately. [`notify_waiters`](Self::notify_waiters) wakes all tasks waiting at the time of
+/// the call, without storing a permit.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use fut_compat::sync::Notify;
+///
+/// # futures::executor::block_on(async {
+/// let notify = Arc::new(Notify::new());
+///
+/// let waiter = {
+///     let notify = notify.clone();
+///
+///     async move {
+///         notify.notified().await;
+///         "notified"
+///     }
+/// };
+///
+/// // The permit is not lost, even though nobody waits yet.
+/// notify.notify_one();
+///
+/// assert_eq!(waiter.await, "notified");
+/// # });
+/// ```
+#[derive(Default)]
+pub struct Notify {
+    /// The [`PERMIT`] flag and the number of [`notify_waiters`](Self::notify_waiters) calls.
+    ///
+    /// Only changed while holding the lock of `waiters`, but read without it for the fast paths.
+    state: AtomicUsize,
+    /// The waiting tasks in the order they started waiting.
+    waiters: Mutex<VecDeque<Waiter>>,
+}
+
+struct Waiter {
+    status: Arc<AtomicU8>,
+    waker: Waker,
+}
+
+impl Notify {
+    /// Creates a new instance without a stored permit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wakes the task waiting the longest, or stores a permit if no task is waiting.
+    ///
+    /// At most one permit gets stored, no matter how often this gets called.
+    pub fn notify_one(&self) {
+        let mut waiters = lock(&self.waiters);
+
+        match waiters.pop_front() {
+            Some(waiter) => {
+                waiter.status.store(NOTIFIED_ONE, Ordering::Release);
+                drop(waiters);
+
+                waiter.waker.wake();
+            }
+            None => {
+                self.state.fetch_or(PERMIT, Ordering::AcqRel);
+            }
+        }
+    }
+
+    /// Wakes all tasks which are waiting.
+    ///
+    /// This includes every [`Notified`] future created before this call, even if it has not been
+    /// polled yet. No permit gets stored, so later calls of [`notified`](Self::notified) wait
+    /// for the next notification.
+    pub fn notify_waiters(&self) {
+        let mut waiters = lock(&self.waiters);
+
+        self.state.fetch_add(GENERATION, Ordering::AcqRel);
+
+        let wakers: Vec<Waker> = waiters
+            .drain(..)
+            .map(|waiter| {
+                waiter.status.store(NOTIFIED_ALL, Ordering::Release);
+                waiter.waker
+            })
+            .collect();
+        drop(waiters);
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Waits for a notification.
+    ///
+    /// Completes immediately if a permit is stored, consuming it. The returned future is cancel
+    /// safe: If it gets dropped after being woken by [`notify_one`](Self::notify_one), the
+    /// notification is passed on to the next waiting task, or stored as a permit.
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: self,
+            generation: self.state.load(Ordering::Acquire) / GENERATION,
+            state: NotifiedState::Init,
+        }
+    }
+
+    /// Takes the stored permit, returning `true` if there was one.
+    fn take_permit(&self) -> bool {
+        self.state.fetch_and(!PERMIT, Ordering::AcqRel) & PERMIT != 0
+    }
+
+    /// Returns `true` if [`notify_waiters`](Self::notify_waiters) has been called since
+    /// `generation` got recorded.
+    fn notified_all(&self, generation: usize) -> bool {
+        self.state.load(Ordering::Acquire) / GENERATION != generation
+    }
+}
+
+impl fmt::Debug for Notify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Notify")
+            .field("permit", &(self.state.load(Ordering::Acquire) & PERMIT != 0))
+            .finish_non_exhaustive()
+    }
+}
+
+
+
+/// The future returned by [`Notify::notified`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    /// The number of [`Notify::notify_waiters`] calls when this future got created.
+    generation: usize,
+    state: NotifiedState,
+}
+
+enum NotifiedState {
+    Init,
+    Waiting(Arc<AtomicU8>),
+    Done,
+}
+
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = Pin::into_inner(self);
+        let notify = this.notify;
+
+        match &this.state {
+            NotifiedState::Init => {
+                if notify.notified_all(this.generation) || notify.take_permit() {
+                    this.state = NotifiedState::Done;
+                    return Poll::Ready(());
+                }
+
+                let mut waiters = lock(&notify.waiters);
+
+                // Check again, as the notifications change the state while holding the lock.
+                if notify.notified_all(this.generation) || notify.take_permit() {
+                    this.state = NotifiedState::Done;
+                    return Poll::Ready(());
+                }
+
+                let status = Arc::new(AtomicU8::new(WAITING));
+
+                waiters.push_back(Waiter {
+                    status: status.clone(),
+                    waker: cx.waker().clone(),
+                });
+
+                this.state = NotifiedState::Waiting(status);
+
+                Poll::Pending
+            }
+            NotifiedState::Waiting(status) => {
+                if status.load(Ordering::Acquire) != WAITING {
+                    this.state = NotifiedState::Done;
+                    return Poll::Ready(());
+                }
+
+                let mut waiters = lock(&notify.waiters);
+
+                // The notifications remove the waiter while holding the lock.
+                match waiters.iter_mut().find(|waiter| Arc::ptr_eq(&waiter.status, status)) {
+                    Some(waiter) => {
+                        if !waiter.waker.will_wake(cx.waker()) {
+                            waiter.waker = cx.waker().clone();
+                        }
+
+                        Poll::Pending
+                    }
+                    None => {
+                        drop(waiters);
+
+                        this.state = NotifiedState::Done;
+                        Poll::Ready(())
+                    }
+                }
+            }
+            NotifiedState::Done => Poll::Ready(()),
+        }
+    }
+}
+
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        let status = match &self.state {
+            NotifiedState::Waiting(status) => status,
+            _ => return,
+        };
+
+        let mut waiters = lock(&self.notify.waiters);
+
+        match status.load(Ordering::Acquire) {
+            WAITING => waiters.retain(|waiter| !Arc::ptr_eq(&waiter.status, status)),
+            NOTIFIED_ONE => {
+                // The notification would be lost otherwise.
+                drop(waiters);
+
+                self.notify.notify_one();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Debug for Notified<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Notified").finish_non_exhaustive()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::{Duration, Instant};
+
+    use futures::executor::block_on;
+    use futures::FutureExt;
+
+    #[test]
+    fn stored_permit_is_consumed_once() {
+        let notify = Notify::new();
+
+        // A single permit gets stored, no matter how often `notify_one` gets called.
+        notify.notify_one();
+        notify.notify_one();
+
+        assert!(notify.notified().now_or_never().is_some());
+        assert!(notify.notified().now_or_never().is_none());
+
+        // Waking a waiting task does not store a permit.
+        let mut waiting = notify.notified();
+        assert!((&mut waiting).now_or_never().is_none());
+        notify.notify_one();
+
+        assert!(waiting.now_or_never().is_some());
+        assert!(notify.notified().now_or_never().is_none());
+    }
+
+    #[test]
+    fn notify_waiters_wakes_the_earlier_waiters_only() {
+        let notify = Notify::new();
+
+        let mut polled = [notify.notified(), notify.notified()];
+        for notified in &mut polled {
+            assert!(notified.now_or_never().is_none());
+        }
+        // Created before the call, but never polled.
+        let unpolled = notify.notified();
+
+        notify.notify_waiters();
+
+        let mut later = notify.notified();
+        assert!((&mut later).now_or_never().is_none());
+
+        for notified in polled {
+            assert!(notified.now_or_never().is_some());
+        }
+        assert!(unpolled.now_or_never().is_some());
+
+        // No permit got stored, so only the next notification completes the later waiter.
+        assert!((&mut later).now_or_never().is_none());
+        notify.notify_one();
+        assert!(later.now_or_never().is_some());
+    }
+
+    #[test]
+    fn dropping_a_woken_waiter_passes_the_notification_on() {
+        let notify = Notify::new();
+
+        let (mut first, mut second) = (notify.notified(), notify.notified());
+        assert!((&mut first).now_or_never().is_none());
+        assert!((&mut second).now_or_never().is_none());
+
+        notify.notify_one();
+        drop(first);
+
+        assert!(second.now_or_never().is_some());
+    }
+
+    #[test]
+    fn no_wakeup_gets_lost_under_contention() {
+        const PRODUCERS: usize = 4;
+        const CONSUMERS: usize = 4;
+        const ITEMS: usize = 2_000;
+
+        let notify = Arc::new(Notify::new());
+        let items = Arc::new(AtomicUsize::new(0));
+        let consumed = Arc::new(AtomicUsize::new(0));
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        let consumers: Vec<_> = (0..CONSUMERS).map(|_| {
+            let (notify, items, consumed, done) = (notify.clone(), items.clone(), consumed.clone(), done.clone());
+
+            std::thread::spawn(move || {
+                for round in 0usize.. {
+                    // Created before checking for items, so no notification in between gets missed.
+                    let mut notified = notify.notified();
+
+                    if done.load(Ordering::Acquire) {
+                        return;
+                    }
+                    if items.fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1)).is_ok() {
+                        consumed.fetch_add(1, Ordering::AcqRel);
+                        continue;
+                    }
+
+                    if (&mut notified).now_or_never().is_some() {
+                        continue;
+                    }
+
+                    // Every third waiter gives up after registering, maybe after being woken.
+                    match round % 3 {
+                        0 => drop(notified),
+                        _ => block_on(notified),
+                    }
+                }
+            })
+        }).collect();
+
+        let producers: Vec<_> = (0..PRODUCERS).map(|_| {
+            let (notify, items) = (notify.clone(), items.clone());
+
+            std::thread::spawn(move || {
+                for _ in 0..ITEMS {
+                    items.fetch_add(1, Ordering::AcqRel);
+                    notify.notify_one();
+                }
+            })
+        }).collect();
+
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while consumed.load(Ordering::Acquire) < PRODUCERS * ITEMS && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(consumed.load(Ordering::Acquire), PRODUCERS * ITEMS);
+
+        done.store(true, Ordering::Release);
+        notify.notify_waiters();
+        for consumer in consumers {
+            consumer.join().unwrap();
+        }
+    }
+}
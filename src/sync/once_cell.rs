@@ -0,0 +1,315 @@
+use std::cell::UnsafeCell;
+use std::convert::Infallible;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use super::*;
+
+
+
+/// No value has been stored and no initialization is running.
+const EMPTY: u8 = 0;
+/// A task runs the initialization.
+const INITIALIZING: u8 = 1;
+/// The value has been stored.
+const INITIALIZED: u8 = 2;
+
+/// A cell which gets initialized at most once, possibly by async code, without depending on a
+/// runtime.
+///
+/// If multiple tasks call [`get_or_init`](Self::get_or_init) concurrently, only the future of
+/// one of them runs. The others wait for it to finish and drop their futures without polling
+/// them.
+///
+/// # Example
+///
+/// ```
+/// use fut_compat::sync::OnceCell;
+///
+/// static POOL: std::sync::OnceLock<OnceCell<String>> = std::sync::OnceLock::new();
+///
+/// async fn pool() -> &'static String {
+///     POOL.get_or_init(OnceCell::new)
+///         .get_or_init(async { String::from("connected") })
+///         .await
+/// }
+///
+/// # futures::executor::block_on(async {
+/// assert_eq!(pool().await, "connected");
+/// # });
+/// ```
+pub struct OnceCell<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+    /// Notified whenever an initialization finishes or gets aborted.
+    notify: Notify,
+}
+
+// SAFETY: The value only gets written by the single task which moved the state to
+// `INITIALIZING`, and only gets read once the state is `INITIALIZED`. Sharing the cell shares the
+// value, so it must be `Sync`, and sending the value between threads requires it to be `Send`.
+unsafe impl<T: Send + Sync> Sync for OnceCell<T> {}
+
+// SAFETY: The cell owns the value.
+unsafe impl<T: Send> Send for OnceCell<T> {}
+
+impl<T> OnceCell<T> {
+    /// Creates a new, empty cell.
+    pub fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Returns `true` if the cell holds a value.
+    pub fn initialized(&self) -> bool {
+        self.state.load(Ordering::Acquire) == INITIALIZED
+    }
+
+    /// Returns a reference to the value, or `None` if the cell has not been initialized yet.
+    pub fn get(&self) -> Option<&T> {
+        match self.initialized() {
+            // SAFETY: The value has been written and never changes again.
+            true => Some(unsafe { (*self.value.get()).assume_init_ref() }),
+            false => None,
+        }
+    }
+
+    /// Returns a mutable reference to the value, or `None` if the cell has not been initialized
+    /// yet.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        match *self.state.get_mut() == INITIALIZED {
+            // SAFETY: The value has been written and the cell is borrowed exclusively.
+            true => Some(unsafe { self.value.get_mut().assume_init_mut() }),
+            false => None,
+        }
+    }
+
+    /// Stores `value` if the cell is empty.
+    ///
+    /// Fails if the cell has been initialized, or if an initialization is running, returning the
+    /// value.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        if self.state.compare_exchange(EMPTY, INITIALIZING, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            return Err(value);
+        }
+
+        self.complete(value);
+
+        Ok(())
+    }
+
+    /// Returns the value, initializing the cell with the output of `init` if it is empty.
+    ///
+    /// If another task is initializing the cell, this waits for it to finish. `init` only gets
+    /// polled if this task has to initialize the cell, otherwise it gets dropped. If this future
+    /// gets dropped during the initialization, the cell stays empty and one of the other waiting
+    /// tasks takes over.
+    pub async fn get_or_init<F>(&self, init: F) -> &T
+    where
+        F: Future<Output = T>,
+    {
+        let init = async {
+            Ok::<T, Infallible>(init.await)
+        };
+
+        match self.get_or_try_init(init).await {
+            Ok(value) => value,
+            Err(err) => match err {},
+        }
+    }
+
+    /// Returns the value, initializing the cell with the output of `init` if it is empty.
+    ///
+    /// Behaves like [`get_or_init`](Self::get_or_init), but the initialization may fail. In this
+    /// case the error gets returned and the cell stays empty, so a later call can retry. One of
+    /// the other waiting tasks takes over right away.
+    pub async fn get_or_try_init<F, E>(&self, init: F) -> Result<&T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        loop {
+            if let Some(value) = self.get() {
+                return Ok(value);
+            }
+
+            // Created before checking the state, so the end of a running initialization can not
+            // be missed.
+            let notified = self.notify.notified();
+
+            match self.state.compare_exchange(EMPTY, INITIALIZING, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => break,
+                Err(INITIALIZED) => {}
+                Err(_) => notified.await,
+            }
+        }
+
+        // Empties the cell again if the initialization fails or gets cancelled.
+        let guard = ResetOnDrop(self);
+
+        let value = init.await?;
+
+        std::mem::forget(guard);
+
+        Ok(self.complete(value))
+    }
+
+    /// Consumes the `OnceCell` object and returns the value, if the cell has been initialized.
+    pub fn into_inner(mut self) -> Option<T> {
+        match std::mem::replace(self.state.get_mut(), EMPTY) == INITIALIZED {
+            // SAFETY: The value has been written, and it does not get dropped again as the state
+            // got reset.
+            true => Some(unsafe { self.value.get_mut().assume_init_read() }),
+            false => None,
+        }
+    }
+
+    /// Stores `value` and wakes the waiting tasks. Must only be called by the task which moved
+    /// the state to `INITIALIZING`.
+    fn complete(&self, value: T) -> &T {
+        // SAFETY: Only this task may access the value while the state is `INITIALIZING`.
+        let value = unsafe { (*self.value.get()).write(value) };
+
+        self.state.store(INITIALIZED, Ordering::Release);
+        self.notify.notify_waiters();
+
+        value
+    }
+}
+
+/// Resets a cell to `EMPTY` if its initialization does not complete.
+struct ResetOnDrop<'a, T>(&'a OnceCell<T>);
+
+impl<T> Drop for ResetOnDrop<'_, T> {
+    fn drop(&mut self) {
+        self.0.state.store(EMPTY, Ordering::Release);
+        self.0.notify.notify_waiters();
+    }
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<T> for OnceCell<T> {
+    fn from(value: T) -> Self {
+        let cell = Self::new();
+
+        cell.complete(value);
+
+        cell
+    }
+}
+
+impl<T> Drop for OnceCell<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == INITIALIZED {
+            // SAFETY: The value has been written and never gets accessed again.
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnceCell")
+            .field("value", &self.get())
+            .finish()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    use futures::executor::block_on;
+    use futures::FutureExt;
+
+    /// Counts how often it got dropped.
+    struct DropCounter(Arc<AtomicUsize>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn concurrent_initializations_run_once() {
+        const TASKS: usize = 8;
+
+        let cell = Arc::new(OnceCell::new());
+        let (polled, dropped) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let start = Arc::new(std::sync::Barrier::new(TASKS));
+
+        let tasks: Vec<_> = (0..TASKS).map(|task| {
+            let (cell, polled, dropped, start) = (cell.clone(), polled.clone(), dropped.clone(), start.clone());
+
+            std::thread::spawn(move || {
+                let counter = DropCounter(dropped);
+                let init = async move {
+                    let _counter = counter;
+                    polled.fetch_add(1, Ordering::SeqCst);
+
+                    // Gives the other tasks time to find the initialization running.
+                    std::thread::sleep(Duration::from_millis(50));
+
+                    task
+                };
+
+                start.wait();
+
+                *block_on(cell.get_or_init(init))
+            })
+        }).collect();
+
+        let values: Vec<usize> = tasks.into_iter().map(|task| task.join().unwrap()).collect();
+
+        assert_eq!(polled.load(Ordering::SeqCst), 1);
+        assert_eq!(dropped.load(Ordering::SeqCst), TASKS);
+        assert!(values.iter().all(|value| Some(value) == cell.get()));
+    }
+
+    #[test]
+    fn failed_initialization_leaves_the_cell_empty() {
+        let cell = OnceCell::new();
+
+        let result = block_on(cell.get_or_try_init(async { Err::<u32, _>("unreachable") }));
+
+        assert_eq!(result, Err("unreachable"));
+        assert!(!cell.initialized());
+        assert_eq!(cell.get(), None);
+
+        let result = block_on(cell.get_or_try_init(async { Ok::<_, &str>(3) }));
+
+        assert_eq!(result, Ok(&3));
+        assert_eq!(cell.set(4), Err(4));
+        assert_eq!(cell.into_inner(), Some(3));
+    }
+
+    #[test]
+    fn cancelled_initialization_is_taken_over() {
+        let cell = OnceCell::new();
+
+        let mut first = Box::pin(cell.get_or_init(futures::future::pending()));
+        assert!((&mut first).now_or_never().is_none());
+
+        let mut second = Box::pin(cell.get_or_init(async { 7 }));
+        assert!((&mut second).now_or_never().is_none());
+        assert_eq!(cell.set(1), Err(1));
+
+        drop(first);
+
+        assert_eq!(second.now_or_never(), Some(&7));
+        assert_eq!(cell.get(), Some(&7));
+    }
+}
@@ -0,0 +1,249 @@
+use futures::task::{FutureObj, SpawnError};
+
+use super::*;
+
+
+
+/// Wraps an executor, so dropping the [`JoinHandle`]s of the tasks it spawns aborts them instead
+/// of detaching them.
+///
+/// This matches the semantics of tasks in older versions of async-std and avoids leaking tasks
+/// whose result nobody waits for anymore, e.g. after a `select!`. A task can still be kept running
+/// with [`JoinHandle::detach`].
+///
+/// Blocking jobs can only be aborted before they started running, see [`JoinHandle::abort`].
+/// Jobs spawned with [`spawn_blocking_cancellable`](SpawnBlocking::spawn_blocking_cancellable)
+/// get their [`CancelToken`] cancelled instead.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() {
+/// use std::time::Duration;
+///
+/// use fut_compat::task::{AbortOnDrop, SpawnHandle, TokioExecutor};
+///
+/// let executor = AbortOnDrop::new(TokioExecutor::default());
+///
+/// let query = executor.spawn_handle(async {
+///     tokio::time::sleep(Duration::from_secs(60)).await;
+/// });
+///
+/// // Stops the task, as the handle gets dropped.
+/// let _ = tokio::time::timeout(Duration::from_secs(1), query).await;
+/// # }
+/// ```
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AbortOnDrop<E> {
+    inner: E,
+}
+
+impl<E> AbortOnDrop<E> {
+    /// Creates a new instance by wrapping `executor`.
+    pub fn new(executor: E) -> Self {
+        Self {
+            inner: executor,
+        }
+    }
+
+    /// Get a reference to the wrapped executor.
+    pub fn get_ref(&self) -> &E {
+        &self.inner
+    }
+
+    /// Consumes the `AbortOnDrop` object and returns the wrapped executor.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+}
+
+/// Tasks spawned through [`Spawn`] have no handle, so they are not affected.
+impl<E: Spawn> Spawn for AbortOnDrop<E> {
+    fn spawn_obj(
+        &self,
+        future: FutureObj<'static, ()>
+    ) -> Result<(), SpawnError> {
+        self.inner.spawn_obj(future)
+    }
+
+    fn status(&self) -> Result<(), SpawnError> {
+        self.inner.status()
+    }
+}
+
+impl<E: SpawnHandle> SpawnHandle for AbortOnDrop<E> {
+    fn spawn_handle<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.inner.spawn_handle(fut).abort_on_drop()
+    }
+
+    fn spawn_handle_with_name<F>(&self, name: &str, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.inner.spawn_handle_with_name(name, fut).abort_on_drop()
+    }
+}
+
+impl<E: SpawnBlocking> SpawnBlocking for AbortOnDrop<E> {
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.inner.spawn_blocking(f).abort_on_drop()
+    }
+
+    fn spawn_blocking_with_name<F, T>(&self, name: &str, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.inner.spawn_blocking_with_name(name, f).abort_on_drop()
+    }
+
+    fn spawn_blocking_cancellable<F, T>(&self, f: F) -> (JoinHandle<T>, CancelToken)
+    where
+        F: FnOnce(&CancelGuard) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (handle, token) = self.inner.spawn_blocking_cancellable(f);
+
+        (handle.abort_on_drop(), token)
+    }
+}
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use std::time::{Duration, Instant};
+
+    use futures::channel::oneshot;
+
+    use crate::task::tracker::Tracker;
+
+    async fn dropping_detaches_by_default<E>()
+    where
+        E: SpawnHandle + Timer + Default,
+    {
+        let tracker = Tracker::default();
+
+        drop(E::default().spawn_handle(tracker.task::<E, _>(Duration::from_millis(20), ())));
+
+        tracker.wait_until::<E>(|tracker| tracker.finished() == 1).await;
+        assert_eq!((tracker.live(), tracker.finished()), (0, 1));
+    }
+
+    async fn dropping_aborts_the_task<E>()
+    where
+        E: SpawnHandle + Timer + Default,
+    {
+        let tracker = Tracker::default();
+        let executor = AbortOnDrop::new(E::default());
+
+        drop(executor.spawn_handle(tracker.task::<E, _>(Duration::from_secs(60), ())));
+        drop(executor.spawn_handle_with_name("named", tracker.task::<E, _>(Duration::from_secs(60), ())));
+
+        tracker.wait_until::<E>(|tracker| tracker.live() == 0).await;
+        assert_eq!((tracker.live(), tracker.finished()), (0, 0));
+    }
+
+    async fn detach_keeps_the_task_running<E>()
+    where
+        E: SpawnHandle + Timer + Default,
+    {
+        let tracker = Tracker::default();
+        let executor = AbortOnDrop::new(E::default());
+
+        executor.spawn_handle(tracker.task::<E, _>(Duration::from_millis(20), ())).detach();
+
+        tracker.wait_until::<E>(|tracker| tracker.finished() == 1).await;
+        assert_eq!((tracker.live(), tracker.finished()), (0, 1));
+    }
+
+    async fn dropping_cancels_blocking_jobs<E>()
+    where
+        E: SpawnBlocking + Default,
+    {
+        let (started_tx, started_rx) = oneshot::channel();
+        let (done_tx, done_rx) = oneshot::channel();
+
+        let (handle, token) = AbortOnDrop::new(E::default()).spawn_blocking_cancellable(move |guard| {
+            started_tx.send(()).unwrap();
+
+            let start = Instant::now();
+            while !guard.is_cancelled() && start.elapsed() < Duration::from_secs(5) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+
+            done_tx.send(guard.is_cancelled()).unwrap();
+        });
+
+        // Only dropping the handle of a running job shows that the job itself notices.
+        started_rx.await.unwrap();
+        drop(handle);
+
+        assert!(token.is_cancelled());
+        assert!(done_rx.await.unwrap());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    fn tokio_run<Fut: Future>(test: Fut) {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(test);
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_dropping_detaches_by_default() {
+        tokio_run(dropping_detaches_by_default::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_dropping_aborts_the_task() {
+        tokio_run(dropping_aborts_the_task::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_detach_keeps_the_task_running() {
+        tokio_run(detach_keeps_the_task_running::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_dropping_cancels_blocking_jobs() {
+        tokio_run(dropping_cancels_blocking_jobs::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_dropping_detaches_by_default() {
+        ::async_std::task::block_on(dropping_detaches_by_default::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_dropping_aborts_the_task() {
+        ::async_std::task::block_on(dropping_aborts_the_task::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_detach_keeps_the_task_running() {
+        ::async_std::task::block_on(detach_keeps_the_task_running::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_dropping_cancels_blocking_jobs() {
+        ::async_std::task::block_on(dropping_cancels_blocking_jobs::<crate::task::AsyncStdExecutor>());
+    }
+}
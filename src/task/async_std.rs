@@ -1,8 +1,11 @@
 use super::*;
 
+use std::panic::AssertUnwindSafe;
+
 use futures::task::{Spawn, LocalSpawn};
 use futures::task::{SpawnError, FutureObj, LocalFutureObj};
 use futures::FutureExt;
+use futures::future::{Abortable, BoxFuture};
 
 
 
@@ -34,19 +37,188 @@ impl LocalSpawn for AsyncStdExecutor {
     }
 }
 
+impl SpawnHandle for AsyncStdExecutor {
+    fn spawn_handle<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        // async-std can only cancel a task by consuming its handle, so we make the future itself
+        // abortable instead.
+        let (abort, registration) = ::futures::future::AbortHandle::new_pair();
+
+        // Like with blocking jobs, panics are caught so they get returned as an error.
+        let fut = AssertUnwindSafe(fut).catch_unwind();
+
+        let handle = ::async_std::task::spawn(Abortable::new(fut, registration));
+        let fut = FutureExt::map(handle, |result| match result {
+            Ok(Ok(output)) => Ok(output),
+            Ok(Err(payload)) => Err(JoinError::panic(payload)),
+            Err(_) => Err(JoinError::cancelled()),
+        });
+
+        JoinHandle::with_canceller(fut, move || abort.abort())
+    }
+}
+
+impl AsyncStdExecutor {
+    /// Runs `f` on the blocking thread pool of the runtime.
+    #[deprecated(note = "use `SpawnBlocking::spawn_blocking` on an executor value, e.g. `AsyncStdExecutor::default().spawn_blocking(f)`")]
+    pub fn spawn_blocking<F, T>(f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        SpawnBlocking::spawn_blocking(&Self::default(), f)
+    }
+}
+
 impl SpawnBlocking for AsyncStdExecutor {
-    fn spawn_blocking<F, T>(f: F) -> JoinHandle<T>
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
     where
         F: FnOnce() -> T + Send + 'static,
         T: Send + 'static,
     {
-        let fut = ::async_std::task::spawn_blocking::<F, T>(f);
-        let fut = FutureExt::map(fut, |ret| {
-            let result: Result<T, Box<dyn Error>> = Ok(ret);
+        // async-std resumes the panic of a blocking job in the awaiting task, while tokio returns
+        // it as an error. We catch it to behave the same way.
+        let f = move || std::panic::catch_unwind(AssertUnwindSafe(f));
 
-            result
-        });
+        // async-std can not cancel blocking jobs, so aborting only detaches it.
+        let fut = ::async_std::task::spawn_blocking(f);
+        let fut = FutureExt::map(fut, |result| result.map_err(JoinError::panic));
 
         JoinHandle::new(fut)
     }
 }
+
+impl BlockOn for AsyncStdExecutor {
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        ::async_std::task::block_on(future)
+    }
+}
+
+impl Timer for AsyncStdExecutor {
+    type Sleep = BoxFuture<'static, ()>;
+
+    fn sleep(dur: Duration) -> Self::Sleep {
+        Box::pin(::async_std::task::sleep(dur))
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_local_runs_non_send_futures() {
+        use futures::task::LocalSpawnExt;
+
+        let answer = ::async_std::task::block_on(async {
+            let shared = std::rc::Rc::new(42);
+
+            AsyncStdExecutor::default().spawn_local_with_handle(async move { *shared }).unwrap().await
+        });
+
+        assert_eq!(answer, 42);
+    }
+
+    #[test]
+    fn handles_return_the_outputs_of_their_tasks() {
+        let outputs = ::async_std::task::block_on(async {
+            let executor = AsyncStdExecutor::default();
+            let handles: Vec<_> = (0..10u64)
+                .map(|i| executor.spawn_handle(async move { i * i }))
+                .collect();
+
+            futures::future::try_join_all(handles).await.unwrap()
+        });
+
+        assert_eq!(outputs, (0..10u64).map(|i| i * i).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn panicking_task_resolves_to_panic_error() {
+        let err = ::async_std::task::block_on(async {
+            AsyncStdExecutor::default().spawn_handle(async { panic!("boom") }).await.unwrap_err()
+        });
+
+        assert!(err.is_panic());
+        assert_eq!(*err.try_into_panic().unwrap().downcast::<&str>().unwrap(), "boom");
+    }
+
+    #[test]
+    fn panicking_blocking_job_resolves_to_panic_error() {
+        let err = ::async_std::task::block_on(async {
+            AsyncStdExecutor::default().spawn_blocking(|| panic!("boom")).await.unwrap_err()
+        });
+
+        assert!(err.is_panic());
+        assert_eq!(*err.try_into_panic().unwrap().downcast::<&str>().unwrap(), "boom");
+    }
+
+    #[test]
+    fn aborted_task_resolves_to_cancelled_error() {
+        let err = ::async_std::task::block_on(async {
+            let handle = AsyncStdExecutor::default().spawn_handle(futures::future::pending::<()>());
+            handle.abort();
+
+            handle.await.unwrap_err()
+        });
+
+        assert!(err.is_cancelled());
+    }
+
+    #[test]
+    fn completed_handle_is_skipped_by_select() {
+        ::async_std::task::block_on(async {
+            let mut handle = AsyncStdExecutor::default().spawn_handle(async { 7 });
+
+            // Once the handle resolved, only the `complete` branch is left.
+            let mut results = Vec::new();
+            for _ in 0..3 {
+                futures::select! {
+                    result = handle => results.push(Some(result.unwrap())),
+                    complete => results.push(None),
+                }
+            }
+
+            assert_eq!(results, [Some(7), None, None]);
+            assert!(handle.is_terminated());
+        });
+    }
+
+    #[test]
+    fn sleeps_take_at_least_their_duration() {
+        ::async_std::task::block_on(async {
+            let start = Instant::now();
+            AsyncStdExecutor::sleep(Duration::from_millis(50)).await;
+            assert!(start.elapsed() >= Duration::from_millis(50));
+
+            let deadline = Instant::now() + Duration::from_millis(50);
+            AsyncStdExecutor::sleep_until(deadline).await;
+            assert!(Instant::now() >= deadline);
+        });
+    }
+
+    #[test]
+    fn reset_replaces_the_sleep() {
+        ::async_std::task::block_on(async {
+            let mut sleep = AsyncStdExecutor::sleep(Duration::from_secs(60));
+
+            let deadline = Instant::now() + Duration::from_millis(50);
+            AsyncStdExecutor::reset(Pin::new(&mut sleep), deadline);
+            (&mut sleep).await;
+
+            let now = Instant::now();
+            assert!(now >= deadline && now < deadline + Duration::from_secs(30));
+
+            // A completed sleep waits again once reset.
+            let deadline = Instant::now() + Duration::from_millis(50);
+            AsyncStdExecutor::reset(Pin::new(&mut sleep), deadline);
+            sleep.await;
+            assert!(Instant::now() >= deadline);
+        });
+    }
+}
@@ -0,0 +1,164 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+
+
+/// Requests the cancellation of a blocking job started with
+/// [`SpawnBlocking::spawn_blocking_cancellable`](super::SpawnBlocking::spawn_blocking_cancellable).
+///
+/// Threads can not be interrupted, so the cancellation is cooperative: the job has to check its
+/// [`CancelGuard`] regularly and return early once it got cancelled.
+///
+/// Cloned tokens cancel the same job.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Creates a new token which has not been cancelled yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the cancellation of the job.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Returns `true` if [`cancel`](Self::cancel) has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Returns the guard passed to the job.
+    pub(crate) fn guard(&self) -> CancelGuard {
+        CancelGuard {
+            cancelled: self.cancelled.clone(),
+        }
+    }
+}
+
+/// Lets a blocking job check whether it got cancelled through its [`CancelToken`].
+#[derive(Debug)]
+pub struct CancelGuard {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelGuard {
+    /// Returns `true` if the job got cancelled and should return early.
+    ///
+    /// This is a single atomic load, so it is cheap enough to be checked in tight loops.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+}
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use std::time::{Duration, Instant};
+
+    use futures::channel::oneshot;
+
+    use crate::task::{SpawnBlocking, Timer};
+
+    /// How long the jobs keep running if they do not get cancelled.
+    const TOO_LONG: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn clones_cancel_the_same_job() {
+        let token = CancelToken::new();
+        let guard = token.guard();
+        assert!(!guard.is_cancelled());
+
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        assert!(guard.is_cancelled());
+    }
+
+    async fn cancel_returns_the_partial_result<E>()
+    where
+        E: SpawnBlocking + Timer + Default,
+    {
+        let (started_tx, started_rx) = oneshot::channel();
+
+        let (handle, token) = E::default().spawn_blocking_cancellable(move |guard| {
+            started_tx.send(()).unwrap();
+
+            let start = Instant::now();
+            let mut rounds = 0u64;
+            while !guard.is_cancelled() && start.elapsed() < TOO_LONG {
+                rounds += 1;
+                std::thread::sleep(Duration::from_millis(1));
+            }
+
+            (rounds, start.elapsed())
+        });
+
+        started_rx.await.unwrap();
+        E::sleep(Duration::from_millis(20)).await;
+        token.cancel();
+
+        let (rounds, elapsed) = handle.await.unwrap();
+        assert!(rounds > 0);
+        assert!(elapsed < TOO_LONG);
+    }
+
+    async fn abort_cancels_the_token<E>()
+    where
+        E: SpawnBlocking + Default,
+    {
+        let (started_tx, started_rx) = oneshot::channel();
+        let (done_tx, done_rx) = oneshot::channel();
+
+        let (handle, token) = E::default().spawn_blocking_cancellable(move |guard| {
+            started_tx.send(()).unwrap();
+
+            let start = Instant::now();
+            while !guard.is_cancelled() && start.elapsed() < TOO_LONG {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+
+            done_tx.send(guard.is_cancelled()).unwrap();
+        });
+
+        started_rx.await.unwrap();
+        handle.abort();
+        assert!(token.is_cancelled());
+
+        assert!(handle.await.unwrap_err().is_cancelled());
+        assert!(done_rx.await.unwrap());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    fn tokio_run<Fut: std::future::Future>(test: Fut) {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(test);
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_cancel_returns_the_partial_result() {
+        tokio_run(cancel_returns_the_partial_result::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_abort_cancels_the_token() {
+        tokio_run(abort_cancels_the_token::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_cancel_returns_the_partial_result() {
+        ::async_std::task::block_on(cancel_returns_the_partial_result::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_abort_cancels_the_token() {
+        ::async_std::task::block_on(abort_cancels_the_token::<crate::task::AsyncStdExecutor>());
+    }
+}
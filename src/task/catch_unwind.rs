@@ -0,0 +1,275 @@
+use std::panic::AssertUnwindSafe;
+
+use futures::task::{FutureObj, SpawnError};
+
+use super::*;
+
+
+
+/// The hook called with the payload of a panic, see [`SpawnCatchUnwind::on_panic`].
+type PanicHook = Arc<dyn Fn(&(dyn Any + Send)) + Send + Sync + 'static>;
+
+/// Wraps an executor, so panics of the tasks and blocking jobs it spawns get caught within the
+/// task, regardless of how the runtime treats them.
+///
+/// The [`JoinHandle`]s of panicked tasks resolve to a [`JoinError`] which
+/// [`is_panic`](JoinError::is_panic), carrying the payload. Tasks spawned through [`Spawn`] have
+/// no handle, so their panics can only be observed with a hook set by
+/// [`on_panic`](Self::on_panic), which gets called for every caught panic.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() {
+/// use futures::task::SpawnExt;
+/// use fut_compat::task::{SpawnCatchUnwind, SpawnHandle, TokioExecutor};
+///
+/// let executor = SpawnCatchUnwind::new(TokioExecutor::default())
+///     .on_panic(|payload| {
+///         let message = payload.downcast_ref::<&str>().copied().unwrap_or("unknown");
+///         eprintln!("a task panicked: {}", message);
+///     });
+///
+/// // Gets logged by the hook.
+/// executor.spawn(async { panic!("boom") }).unwrap();
+///
+/// let err = executor.spawn_handle(async { panic!("boom") }).await.unwrap_err();
+/// assert!(err.is_panic());
+/// # }
+/// ```
+#[derive(Default, Clone)]
+pub struct SpawnCatchUnwind<E> {
+    inner: E,
+    hook: Option<PanicHook>,
+}
+
+impl<E> SpawnCatchUnwind<E> {
+    /// Creates a new instance by wrapping `executor`.
+    pub fn new(executor: E) -> Self {
+        Self {
+            inner: executor,
+            hook: None,
+        }
+    }
+
+    /// Sets the hook which gets called with the payload of every caught panic, e.g. for logging
+    /// it.
+    ///
+    /// The hook runs within the panicked task, before its [`JoinHandle`] resolves. It is shared
+    /// between the clones of the executor.
+    pub fn on_panic<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(&(dyn Any + Send)) + Send + Sync + 'static,
+    {
+        self.hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Get a reference to the wrapped executor.
+    pub fn get_ref(&self) -> &E {
+        &self.inner
+    }
+
+    /// Consumes the `SpawnCatchUnwind` object and returns the wrapped executor.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+}
+
+/// Wraps `fut`, so it returns the payload of a panic after passing it to `hook`.
+fn catch_future<F>(hook: Option<PanicHook>, fut: F) -> impl Future<Output = Result<F::Output, Box<dyn Any + Send>>>
+where
+    F: Future,
+{
+    AssertUnwindSafe(fut).catch_unwind().map(move |result| {
+        result.map_err(|payload| report(hook.as_ref(), payload))
+    })
+}
+
+/// Wraps `f`, so it returns the payload of a panic after passing it to `hook`.
+fn catch_blocking<F, T>(hook: Option<PanicHook>, f: F) -> impl FnOnce() -> Result<T, Box<dyn Any + Send>>
+where
+    F: FnOnce() -> T,
+{
+    move || {
+        std::panic::catch_unwind(AssertUnwindSafe(f))
+            .map_err(|payload| report(hook.as_ref(), payload))
+    }
+}
+
+/// Passes `payload` to the hook, if any, and returns it.
+fn report(hook: Option<&PanicHook>, payload: Box<dyn Any + Send>) -> Box<dyn Any + Send> {
+    if let Some(hook) = hook {
+        hook(&*payload);
+    }
+
+    payload
+}
+
+/// Turns the caught panic of the task of `handle` into a [`JoinError`].
+fn resolve_panic<T: Send + 'static>(handle: JoinHandle<Result<T, Box<dyn Any + Send>>>) -> JoinHandle<T> {
+    let abort = handle.abort_handle();
+
+    let fut = FutureExt::map(handle, |result| match result {
+        Ok(Ok(output)) => Ok(output),
+        Ok(Err(payload)) => Err(JoinError::panic(payload)),
+        Err(err) => Err(err),
+    });
+
+    JoinHandle::with_canceller(fut, move || abort.abort())
+}
+
+impl<E: Spawn> Spawn for SpawnCatchUnwind<E> {
+    fn spawn_obj(
+        &self,
+        future: FutureObj<'static, ()>
+    ) -> Result<(), SpawnError> {
+        let future = catch_future(self.hook.clone(), future).map(|_| ());
+
+        self.inner.spawn_obj(FutureObj::new(Box::new(future)))
+    }
+
+    fn status(&self) -> Result<(), SpawnError> {
+        self.inner.status()
+    }
+}
+
+impl<E: SpawnHandle> SpawnHandle for SpawnCatchUnwind<E> {
+    fn spawn_handle<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        resolve_panic(self.inner.spawn_handle(catch_future(self.hook.clone(), fut)))
+    }
+
+    fn spawn_handle_with_name<F>(&self, name: &str, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        resolve_panic(self.inner.spawn_handle_with_name(name, catch_future(self.hook.clone(), fut)))
+    }
+}
+
+impl<E: SpawnBlocking> SpawnBlocking for SpawnCatchUnwind<E> {
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        resolve_panic(self.inner.spawn_blocking(catch_blocking(self.hook.clone(), f)))
+    }
+
+    fn spawn_blocking_with_name<F, T>(&self, name: &str, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        resolve_panic(self.inner.spawn_blocking_with_name(name, catch_blocking(self.hook.clone(), f)))
+    }
+}
+
+impl<E: fmt::Debug> fmt::Debug for SpawnCatchUnwind<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpawnCatchUnwind")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use futures::task::SpawnExt;
+
+    /// Returns the message of a panic payload.
+    fn message(payload: &(dyn Any + Send)) -> &str {
+        payload.downcast_ref::<&str>().copied().unwrap_or("unknown")
+    }
+
+    async fn handles_report_panics<E>()
+    where
+        E: SpawnHandle + SpawnBlocking + Default,
+    {
+        let executor = SpawnCatchUnwind::new(E::default());
+
+        assert_eq!(executor.spawn_handle(async { 1 }).await.unwrap(), 1);
+        assert_eq!(executor.spawn_blocking(|| 2).await.unwrap(), 2);
+
+        let err = executor.spawn_handle(async { panic!("task") }).await.unwrap_err();
+        assert!(err.is_panic());
+        assert_eq!(message(&*err.try_into_panic().unwrap()), "task");
+
+        let err = executor.spawn_blocking_with_name("job", || panic!("job")).await.unwrap_err();
+        assert!(err.is_panic());
+        assert_eq!(message(&*err.try_into_panic().unwrap()), "job");
+
+        let handle = executor.spawn_handle(futures::future::pending::<()>());
+        handle.abort();
+        assert!(handle.await.unwrap_err().is_cancelled());
+    }
+
+    async fn hook_sees_every_panic<E>()
+    where
+        E: Spawn + SpawnHandle + Timer + Default + Clone,
+    {
+        let panics = Arc::new(Mutex::new(Vec::new()));
+
+        let executor = SpawnCatchUnwind::new(E::default()).on_panic({
+            let panics = panics.clone();
+            move |payload| panics.lock().unwrap().push(message(payload).to_owned())
+        });
+
+        // Tasks without a handle can only be observed by the hook.
+        executor.spawn(async { panic!("detached") }).unwrap();
+        for _ in 0..500 {
+            if !panics.lock().unwrap().is_empty() {
+                break;
+            }
+
+            E::sleep(Duration::from_millis(10)).await;
+        }
+
+        // The clones of the executor share the hook.
+        let err = executor.clone().spawn_handle(async { panic!("awaited") }).await.unwrap_err();
+        assert!(err.is_panic());
+
+        assert_eq!(*panics.lock().unwrap(), ["detached", "awaited"]);
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    fn tokio_run<Fut: Future>(test: Fut) {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(test);
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_handles_report_panics() {
+        tokio_run(handles_report_panics::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_hook_sees_every_panic() {
+        tokio_run(hook_sees_every_panic::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_handles_report_panics() {
+        ::async_std::task::block_on(handles_report_panics::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_hook_sees_every_panic() {
+        ::async_std::task::block_on(hook_sees_every_panic::<crate::task::AsyncStdExecutor>());
+    }
+}
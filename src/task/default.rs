@@ -0,0 +1,176 @@
+use super::*;
+use crate::runtime::RuntimeKind;
+
+use futures::task::{FutureObj, SpawnError};
+
+
+
+/// An executor which spawns onto the runtime the current thread runs on.
+///
+/// The runtime gets detected on every call, which is cheap, as only the context of tokio gets
+/// checked each time and the choice among the global executors is cached:
+///
+/// 1. If the thread is within the context of a [`tokio`](https://docs.rs/tokio) runtime, it
+///    gets used. Requires the `tokio-rt` feature.
+/// 2. Otherwise the global executor of [`async_std`](https://docs.rs/async-std) gets used, which
+///    needs no context. Requires the `async-std-rt` feature.
+/// 3. Otherwise the global executor of [`smol`](https://docs.rs/smol) gets used, which needs no
+///    context either. Requires the `smol-rt` feature.
+///
+/// If none of these apply, [`Spawn`] returns [`SpawnError::shutdown`] and the [`JoinHandle`]s of
+/// [`SpawnHandle`] and [`SpawnBlocking`] resolve to a cancelled [`JoinError`], like the ones of
+/// `TokioExecutor` outside of a runtime.
+///
+/// This lets libraries spawn tasks without asking their users for an executor.
+///
+/// # Example
+///
+/// ```no_run
+/// use fut_compat::task::{DefaultExecutor, SpawnHandle};
+///
+/// async fn checksum(data: Vec<u8>) -> u64 {
+///     DefaultExecutor::default()
+///         .spawn_handle(async move { data.iter().map(|byte| *byte as u64).sum() })
+///         .await
+///         .expect("the task panicked")
+/// }
+/// ```
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DefaultExecutor {}
+
+/// Returns a join handle for a task which could not be spawned, as no runtime is available.
+fn no_runtime<T: Send + 'static>() -> JoinHandle<T> {
+    JoinHandle::new(futures::future::ready(Err(JoinError::cancelled())))
+}
+
+impl Spawn for DefaultExecutor {
+    fn spawn_obj(
+        &self,
+        future: FutureObj<'static, ()>
+    ) -> Result<(), SpawnError> {
+        match RuntimeKind::current() {
+            #[cfg(feature = "tokio-rt")]
+            Some(RuntimeKind::Tokio) => TokioExecutor::default().spawn_obj(future),
+            #[cfg(feature = "async-std-rt")]
+            Some(RuntimeKind::AsyncStd) => AsyncStdExecutor::default().spawn_obj(future),
+            #[cfg(feature = "smol-rt")]
+            Some(RuntimeKind::Smol) => SmolExecutor::default().spawn_obj(future),
+            None => {
+                drop(future);
+
+                Err(SpawnError::shutdown())
+            }
+        }
+    }
+}
+
+impl SpawnHandle for DefaultExecutor {
+    fn spawn_handle<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match RuntimeKind::current() {
+            #[cfg(feature = "tokio-rt")]
+            Some(RuntimeKind::Tokio) => TokioExecutor::default().spawn_handle(fut),
+            #[cfg(feature = "async-std-rt")]
+            Some(RuntimeKind::AsyncStd) => AsyncStdExecutor::default().spawn_handle(fut),
+            #[cfg(feature = "smol-rt")]
+            Some(RuntimeKind::Smol) => SmolExecutor::default().spawn_handle(fut),
+            None => {
+                drop(fut);
+
+                no_runtime()
+            }
+        }
+    }
+
+    fn spawn_handle_with_name<F>(&self, name: &str, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match RuntimeKind::current() {
+            #[cfg(feature = "tokio-rt")]
+            Some(RuntimeKind::Tokio) => TokioExecutor::default().spawn_handle_with_name(name, fut),
+            #[cfg(feature = "async-std-rt")]
+            Some(RuntimeKind::AsyncStd) => AsyncStdExecutor::default().spawn_handle_with_name(name, fut),
+            #[cfg(feature = "smol-rt")]
+            Some(RuntimeKind::Smol) => SmolExecutor::default().spawn_handle_with_name(name, fut),
+            None => {
+                drop(fut);
+                let _ = name;
+
+                no_runtime()
+            }
+        }
+    }
+}
+
+impl SpawnBlocking for DefaultExecutor {
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        match RuntimeKind::current() {
+            #[cfg(feature = "tokio-rt")]
+            Some(RuntimeKind::Tokio) => SpawnBlocking::spawn_blocking(&TokioExecutor::default(), f),
+            #[cfg(feature = "async-std-rt")]
+            Some(RuntimeKind::AsyncStd) => SpawnBlocking::spawn_blocking(&AsyncStdExecutor::default(), f),
+            #[cfg(feature = "smol-rt")]
+            Some(RuntimeKind::Smol) => SpawnBlocking::spawn_blocking(&SmolExecutor::default(), f),
+            None => {
+                drop(f);
+
+                no_runtime()
+            }
+        }
+    }
+
+    fn spawn_blocking_with_name<F, T>(&self, name: &str, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        match RuntimeKind::current() {
+            #[cfg(feature = "tokio-rt")]
+            Some(RuntimeKind::Tokio) => TokioExecutor::default().spawn_blocking_with_name(name, f),
+            #[cfg(feature = "async-std-rt")]
+            Some(RuntimeKind::AsyncStd) => AsyncStdExecutor::default().spawn_blocking_with_name(name, f),
+            #[cfg(feature = "smol-rt")]
+            Some(RuntimeKind::Smol) => SmolExecutor::default().spawn_blocking_with_name(name, f),
+            None => {
+                drop(f);
+                let _ = name;
+
+                no_runtime()
+            }
+        }
+    }
+}
+
+
+
+#[cfg(all(test, not(any(feature = "async-std-rt", feature = "smol-rt"))))]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+
+    // Outside of the context of a tokio runtime, no runtime is available in this configuration.
+
+    #[test]
+    fn spawn_handle_without_runtime_is_cancelled() {
+        let err = block_on(DefaultExecutor::default().spawn_handle(async { 42 })).unwrap_err();
+
+        assert!(err.is_cancelled());
+    }
+
+    #[test]
+    fn spawn_blocking_without_runtime_is_cancelled() {
+        let err = block_on(DefaultExecutor::default().spawn_blocking(|| 42)).unwrap_err();
+
+        assert!(err.is_cancelled());
+    }
+}
@@ -0,0 +1,310 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::stream::Stream;
+use futures::future::poll_fn;
+use futures::ready;
+
+use super::Timer;
+
+
+
+/// Defines what an [`Interval`] does after ticks have been missed, e.g. because the executor was
+/// busy or the ticks were not consumed in time.
+///
+/// The behaviors match the ones of tokio's `Interval`, but are implemented within this crate, so
+/// they are the same with every [`Timer`].
+///
+/// For example, with a period of 10ms and a handler which runs for 25ms after the tick at 10ms,
+/// the ticks get consumed at the following times. Each tick yields the instant it was scheduled
+/// for, which is the time shown first.
+///
+/// | Behavior | 1st | 2nd | 3rd | 4th | 5th |
+/// |----------|-----|-----|-----|-----|-----|
+/// | `Burst` | 0 | 10 | 20 at 35 | 30 at 35 | 40 |
+/// | `Delay` | 0 | 10 | 20 at 35 | 45 | 55 |
+/// | `Skip` | 0 | 10 | 20 at 35 | 40 | 50 |
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MissedTickBehavior {
+    /// Yields the missed ticks right away, until the schedule has been caught up with.
+    ///
+    /// The ticks stay anchored to the start time, so the average period is kept.
+    #[default]
+    Burst,
+    /// Yields one tick right away and schedules the following ones from then on.
+    ///
+    /// The schedule gets shifted by the delay, while the period between ticks is kept.
+    Delay,
+    /// Yields one tick right away and drops the other missed ones, continuing with the original
+    /// schedule.
+    Skip,
+}
+
+/// Creates a new [`Interval`] which ticks every `period`, starting right away.
+///
+/// # Panics
+///
+/// Panics if `period` is zero.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() {
+/// use std::time::Duration;
+///
+/// use fut_compat::task::{interval, TokioExecutor};
+///
+/// let mut heartbeat = interval::<TokioExecutor>(Duration::from_secs(10));
+///
+/// loop {
+///     heartbeat.tick().await;
+///     println!("still alive");
+/// }
+/// # }
+/// ```
+pub fn interval<Tm: Timer>(period: Duration) -> Interval<Tm> {
+    interval_at(Tm::now(), period)
+}
+
+/// Creates a new [`Interval`] which ticks every `period`, with the first tick at `start`.
+///
+/// # Panics
+///
+/// Panics if `period` is zero.
+pub fn interval_at<Tm: Timer>(start: Instant, period: Duration) -> Interval<Tm> {
+    assert!(!period.is_zero(), "the period of an interval must be non-zero");
+
+    Interval {
+        next: start,
+        period,
+        missed_tick_behavior: MissedTickBehavior::default(),
+        sleep: None,
+    }
+}
+
+/// A stream of ticks at a fixed period, created by [`interval`] or [`interval_at`].
+///
+/// The ticks are anchored to the start time: the `n`-th tick is scheduled `n` periods after the
+/// first one, regardless of how late the previous ticks were. Only the chosen
+/// [`MissedTickBehavior`] shifts the schedule.
+///
+/// Every tick yields the instant it was scheduled for.
+pub struct Interval<Tm: Timer> {
+    /// The instant of the next tick.
+    next: Instant,
+    period: Duration,
+    missed_tick_behavior: MissedTickBehavior,
+    sleep: Option<Pin<Box<Tm::Sleep>>>,
+}
+
+impl<Tm: Timer> Interval<Tm> {
+    /// Completes at the next tick and returns the instant it was scheduled for.
+    pub async fn tick(&mut self) -> Instant {
+        poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    /// Polls for the next tick, see [`tick`](Self::tick).
+    pub fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<Instant> {
+        while Tm::now() < self.next {
+            match &mut self.sleep {
+                Some(sleep) => {
+                    ready!(sleep.as_mut().poll(cx));
+
+                    // Timers may wake up early, e.g. due to a coarse resolution.
+                    if Tm::now() < self.next {
+                        Tm::reset(sleep.as_mut(), self.next);
+                    }
+                }
+                None => self.sleep = Some(Box::pin(Tm::sleep_until(self.next))),
+            }
+        }
+
+        let tick = self.next;
+        let now = Tm::now();
+
+        let next = match self.missed_tick_behavior {
+            _ if now < tick + self.period => tick + self.period,
+            MissedTickBehavior::Burst => tick + self.period,
+            MissedTickBehavior::Delay => now + self.period,
+            MissedTickBehavior::Skip => {
+                let missed = (now - tick).as_nanos() / self.period.as_nanos();
+                let missed = u32::try_from(missed).unwrap_or(u32::MAX - 1);
+
+                tick + self.period * (missed + 1)
+            }
+        };
+        self.set_next(next);
+
+        Poll::Ready(tick)
+    }
+
+    /// Restarts the schedule, so the next tick happens one period from now.
+    pub fn reset(&mut self) {
+        self.set_next(Tm::now() + self.period);
+    }
+
+    fn set_next(&mut self, next: Instant) {
+        self.next = next;
+
+        if let Some(sleep) = &mut self.sleep {
+            Tm::reset(sleep.as_mut(), next);
+        }
+    }
+
+    /// Returns the period between ticks.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns the behavior after missed ticks.
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    /// Sets the behavior after missed ticks.
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior;
+    }
+}
+
+impl<Tm: Timer> Stream for Interval<Tm> {
+    type Item = Instant;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::into_inner(self).poll_tick(cx).map(Some)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<Tm: Timer> fmt::Debug for Interval<Tm> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interval")
+            .field("next", &self.next)
+            .field("period", &self.period)
+            .field("missed_tick_behavior", &self.missed_tick_behavior)
+            .finish_non_exhaustive()
+    }
+}
+
+
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::*;
+
+    use futures::task::noop_waker;
+
+    use crate::task::MockTimer;
+
+    const PERIOD: Duration = Duration::from_millis(10);
+
+    /// Consumes five ticks, with a handler which runs for 25ms after the tick at 10ms, and returns
+    /// for each tick the times it was scheduled for and consumed at, in ms since the start.
+    fn ticks(behavior: MissedTickBehavior) -> Vec<(u128, u128)> {
+        let start = MockTimer::now();
+        let elapsed = |instant: Instant| (instant - start).as_millis();
+
+        let mut interval = interval::<MockTimer>(PERIOD);
+        interval.set_missed_tick_behavior(behavior);
+
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut ticks = Vec::new();
+
+        while ticks.len() < 5 {
+            match interval.poll_tick(&mut cx) {
+                Poll::Ready(tick) => {
+                    ticks.push((elapsed(tick), elapsed(MockTimer::now())));
+
+                    if elapsed(tick) == 10 {
+                        MockTimer::advance(Duration::from_millis(25));
+                    }
+                }
+                Poll::Pending => MockTimer::advance(Duration::from_millis(1)),
+            }
+        }
+
+        ticks
+    }
+
+    #[test]
+    fn burst_catches_up_with_the_schedule() {
+        assert_eq!(ticks(MissedTickBehavior::Burst), [(0, 0), (10, 10), (20, 35), (30, 35), (40, 40)]);
+    }
+
+    #[test]
+    fn delay_shifts_the_schedule() {
+        assert_eq!(ticks(MissedTickBehavior::Delay), [(0, 0), (10, 10), (20, 35), (45, 45), (55, 55)]);
+    }
+
+    #[test]
+    fn skip_drops_missed_ticks() {
+        assert_eq!(ticks(MissedTickBehavior::Skip), [(0, 0), (10, 10), (20, 35), (40, 40), (50, 50)]);
+    }
+
+    #[test]
+    fn reset_restarts_the_schedule() {
+        let start = MockTimer::now();
+        let mut interval = interval::<MockTimer>(PERIOD);
+
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        assert_eq!(interval.poll_tick(&mut cx), Poll::Ready(start));
+
+        MockTimer::advance(Duration::from_millis(5));
+        interval.reset();
+
+        MockTimer::advance(Duration::from_millis(9));
+        assert!(interval.poll_tick(&mut cx).is_pending());
+
+        MockTimer::advance(Duration::from_millis(1));
+        assert_eq!(interval.poll_tick(&mut cx), Poll::Ready(start + Duration::from_millis(15)));
+    }
+
+    /// Consumes six ticks of a real interval, blocking the executor for a while after the second
+    /// one, and checks that the schedule stays anchored to the start.
+    #[cfg(any(feature = "tokio-rt", feature = "async-std-rt"))]
+    async fn real_ticks_follow_the_schedule<Tm: Timer>() {
+        const PERIOD: Duration = Duration::from_millis(20);
+
+        let start = Instant::now();
+        let mut interval = interval::<Tm>(PERIOD);
+
+        let mut ticks = Vec::new();
+        for i in 0..6 {
+            ticks.push(interval.tick().await);
+
+            if i == 1 {
+                std::thread::sleep(PERIOD * 2);
+            }
+        }
+
+        assert!(ticks[0] - start < PERIOD);
+        for (i, tick) in ticks.iter().enumerate() {
+            assert_eq!(*tick - ticks[0], PERIOD * i as u32);
+        }
+
+        let elapsed = start.elapsed();
+        assert!(elapsed >= PERIOD * 5 && elapsed < Duration::from_secs(2), "took {:?}", elapsed);
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_real_ticks_follow_the_schedule() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(real_ticks_follow_the_schedule::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_real_ticks_follow_the_schedule() {
+        ::async_std::task::block_on(real_ticks_follow_the_schedule::<crate::task::AsyncStdExecutor>());
+    }
+}
@@ -0,0 +1,304 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::stream::{FuturesUnordered, Stream};
+use futures::future::poll_fn;
+
+use super::{AbortHandle, JoinError, JoinHandle, SpawnHandle};
+
+
+
+/// A set of tasks spawned with the executor `E`, which can be awaited in the order they complete.
+///
+/// Dropping the set aborts all tasks in it, see [`detach_all`](Self::detach_all) for keeping them
+/// running instead.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() {
+/// use fut_compat::task::{JoinSet, TokioExecutor};
+///
+/// let mut set = JoinSet::<_, TokioExecutor>::new();
+///
+/// for i in 0..10u64 {
+///     set.spawn(async move { i * i });
+/// }
+///
+/// let mut total = 0;
+/// while let Some(result) = set.join_next().await {
+///     total += result.expect("the task failed");
+/// }
+/// # }
+/// ```
+pub struct JoinSet<T, E> {
+    executor: E,
+    tasks: FuturesUnordered<JoinHandle<T>>,
+}
+
+impl<T, E: Default> JoinSet<T, E> {
+    /// Creates a new, empty set.
+    pub fn new() -> Self {
+        Self::with_executor(E::default())
+    }
+}
+
+impl<T, E: Default> Default for JoinSet<T, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, E> JoinSet<T, E> {
+    /// Creates a new, empty set, spawning the tasks with `executor`.
+    pub fn with_executor(executor: E) -> Self {
+        Self {
+            executor,
+            tasks: FuturesUnordered::new(),
+        }
+    }
+
+    /// Returns the number of tasks in the set, including the ones which completed but have not
+    /// been joined yet.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns `true` if the set holds no tasks.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Aborts all tasks in the set.
+    ///
+    /// They stay in the set, so [`join_next`](Self::join_next) returns a cancelled [`JoinError`]
+    /// for each of them which did not complete before.
+    pub fn abort_all(&mut self) {
+        for task in self.tasks.iter() {
+            task.abort();
+        }
+    }
+
+    /// Removes all tasks from the set without aborting them, so they keep running in the
+    /// background.
+    pub fn detach_all(&mut self) {
+        for task in std::mem::take(&mut self.tasks) {
+            task.detach();
+        }
+    }
+}
+
+impl<T, E> JoinSet<T, E>
+where
+    T: Send + 'static,
+    E: SpawnHandle,
+{
+    /// Spawns `future` with the executor and adds it to the set.
+    ///
+    /// Returns a handle for aborting the task.
+    pub fn spawn<F>(&mut self, future: F) -> AbortHandle
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        let task = self.executor.spawn_handle(future);
+        let abort = task.abort_handle();
+
+        self.tasks.push(task);
+
+        abort
+    }
+}
+
+impl<T, E> JoinSet<T, E> {
+    /// Waits for the next task to complete and returns its result.
+    ///
+    /// Returns `None` if the set is empty.
+    pub async fn join_next(&mut self) -> Option<Result<T, JoinError>> {
+        poll_fn(|cx| self.poll_join_next(cx)).await
+    }
+
+    /// Polls for the next task to complete, see [`join_next`](Self::join_next).
+    pub fn poll_join_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<T, JoinError>>> {
+        Pin::new(&mut self.tasks).poll_next(cx)
+    }
+
+    /// Aborts all tasks and waits for them to finish.
+    pub async fn shutdown(&mut self) {
+        self.abort_all();
+
+        while self.join_next().await.is_some() {}
+    }
+}
+
+impl<T, E> Drop for JoinSet<T, E> {
+    fn drop(&mut self) {
+        self.abort_all();
+    }
+}
+
+impl<T, E: fmt::Debug> fmt::Debug for JoinSet<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinSet")
+            .field("executor", &self.executor)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::task::Timer;
+    use crate::task::tracker::Tracker;
+
+    async fn drains_mixed_results<E>()
+    where
+        E: SpawnHandle + Timer + Default,
+    {
+        let mut set = JoinSet::<u64, E>::new();
+
+        for i in 1..=3 {
+            set.spawn(async move {
+                E::sleep(Duration::from_millis(10 * i)).await;
+
+                i
+            });
+        }
+        set.spawn(async { panic!("boom") });
+        set.spawn(futures::future::pending()).abort();
+        assert_eq!(set.len(), 5);
+
+        let (mut values, mut panics, mut cancelled) = (Vec::new(), 0, 0);
+        while let Some(result) = set.join_next().await {
+            match result {
+                Ok(value) => values.push(value),
+                Err(err) if err.is_panic() => panics += 1,
+                Err(err) if err.is_cancelled() => cancelled += 1,
+                Err(err) => panic!("unexpected error {:?}", err),
+            }
+        }
+
+        values.sort();
+        assert_eq!((values, panics, cancelled), (vec![1, 2, 3], 1, 1));
+        assert!(set.is_empty());
+        assert!(set.join_next().await.is_none());
+    }
+
+    async fn drop_aborts_the_tasks<E>()
+    where
+        E: SpawnHandle + Timer + Default,
+    {
+        let tracker = Tracker::default();
+
+        let mut set = JoinSet::<(), E>::new();
+        for _ in 0..3 {
+            set.spawn(tracker.task::<E, _>(Duration::from_secs(60), ()));
+        }
+        drop(set);
+
+        tracker.wait_until::<E>(|tracker| tracker.live() == 0).await;
+        assert_eq!((tracker.live(), tracker.finished()), (0, 0));
+    }
+
+    async fn detach_all_keeps_the_tasks_running<E>()
+    where
+        E: SpawnHandle + Timer + Default,
+    {
+        let tracker = Tracker::default();
+
+        let mut set = JoinSet::<(), E>::new();
+        for _ in 0..3 {
+            set.spawn(tracker.task::<E, _>(Duration::from_millis(20), ()));
+        }
+        set.detach_all();
+        assert!(set.is_empty());
+        drop(set);
+
+        tracker.wait_until::<E>(|tracker| tracker.finished() == 3).await;
+        assert_eq!((tracker.live(), tracker.finished()), (0, 3));
+    }
+
+    async fn shutdown_drains_the_set<E>()
+    where
+        E: SpawnHandle + Timer + Default,
+    {
+        let tracker = Tracker::default();
+
+        let mut set = JoinSet::<(), E>::new();
+        for _ in 0..3 {
+            set.spawn(tracker.task::<E, _>(Duration::from_secs(60), ()));
+        }
+        set.spawn(async {});
+
+        set.shutdown().await;
+        assert!(set.is_empty());
+
+        // The handles resolve on abort, while some runtimes drop the tasks shortly after.
+        tracker.wait_until::<E>(|tracker| tracker.live() == 0).await;
+        assert_eq!((tracker.live(), tracker.finished()), (0, 0));
+
+        // The set can be reused afterwards.
+        set.spawn(async {});
+        assert!(set.join_next().await.unwrap().is_ok());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    fn tokio_run<Fut: Future>(test: Fut) {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(test);
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_drains_mixed_results() {
+        tokio_run(drains_mixed_results::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_drop_aborts_the_tasks() {
+        tokio_run(drop_aborts_the_tasks::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_detach_all_keeps_the_tasks_running() {
+        tokio_run(detach_all_keeps_the_tasks_running::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_shutdown_drains_the_set() {
+        tokio_run(shutdown_drains_the_set::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_drains_mixed_results() {
+        ::async_std::task::block_on(drains_mixed_results::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_drop_aborts_the_tasks() {
+        ::async_std::task::block_on(drop_aborts_the_tasks::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_detach_all_keeps_the_tasks_running() {
+        ::async_std::task::block_on(detach_all_keeps_the_tasks_running::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_shutdown_drains_the_set() {
+        ::async_std::task::block_on(shutdown_drains_the_set::<crate::task::AsyncStdExecutor>());
+    }
+}
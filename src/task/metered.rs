@@ -0,0 +1,363 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::task::{FutureObj, SpawnError};
+
+use super::*;
+
+
+
+/// Wraps an executor and counts the tasks and blocking jobs spawned through it.
+///
+/// The counts can be read at any time with [`metrics`](Self::metrics). They are shared between
+/// the clones of the executor.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() {
+/// use fut_compat::task::{MeteredExecutor, SpawnHandle, TokioExecutor};
+///
+/// let executor = MeteredExecutor::new(TokioExecutor::default());
+///
+/// executor.spawn_handle(async { 1 + 1 }).await.unwrap();
+///
+/// let metrics = executor.metrics();
+/// assert_eq!(metrics.tasks.spawned, 1);
+/// assert_eq!(metrics.tasks.completed, 1);
+/// assert_eq!(metrics.tasks.running(), 0);
+/// # }
+/// ```
+#[derive(Default, Clone, Debug)]
+pub struct MeteredExecutor<E> {
+    inner: E,
+    counters: Arc<Counters>,
+}
+
+#[derive(Default, Debug)]
+struct Counters {
+    tasks: Counter,
+    blocking_jobs: Counter,
+}
+
+#[derive(Default, Debug)]
+struct Counter {
+    spawned: AtomicU64,
+    completed: AtomicU64,
+    panicked: AtomicU64,
+    cancelled: AtomicU64,
+}
+
+impl Counter {
+    fn snapshot(&self) -> TaskCounts {
+        // The finished counts are read first, as they never exceed the spawned count.
+        let completed = self.completed.load(Ordering::Acquire);
+        let panicked = self.panicked.load(Ordering::Acquire);
+        let cancelled = self.cancelled.load(Ordering::Acquire);
+        let spawned = self.spawned.load(Ordering::Acquire);
+
+        TaskCounts {
+            spawned,
+            completed,
+            panicked,
+            cancelled,
+        }
+    }
+}
+
+/// A snapshot of the counts of a [`MeteredExecutor`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ExecutorMetrics {
+    /// The async tasks, spawned with [`Spawn`] or [`SpawnHandle`].
+    pub tasks: TaskCounts,
+    /// The blocking jobs, spawned with [`SpawnBlocking`].
+    pub blocking_jobs: TaskCounts,
+}
+
+/// The counts of one kind of task, see [`ExecutorMetrics`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TaskCounts {
+    /// The number of spawned tasks.
+    pub spawned: u64,
+    /// The number of tasks which ran to completion.
+    pub completed: u64,
+    /// The number of tasks which panicked.
+    pub panicked: u64,
+    /// The number of tasks which got dropped before completing, e.g. because they were aborted or
+    /// the runtime shut down.
+    pub cancelled: u64,
+}
+
+impl TaskCounts {
+    /// Returns the number of tasks which have been spawned, but did not finish yet. This includes
+    /// tasks which are waiting to be run.
+    pub fn running(&self) -> u64 {
+        self.spawned.saturating_sub(self.completed + self.panicked + self.cancelled)
+    }
+}
+
+impl<E> MeteredExecutor<E> {
+    /// Creates a new instance by wrapping `executor`, with all counts at zero.
+    pub fn new(executor: E) -> Self {
+        Self {
+            inner: executor,
+            counters: Arc::default(),
+        }
+    }
+
+    /// Returns a snapshot of the current counts.
+    pub fn metrics(&self) -> ExecutorMetrics {
+        ExecutorMetrics {
+            tasks: self.counters.tasks.snapshot(),
+            blocking_jobs: self.counters.blocking_jobs.snapshot(),
+        }
+    }
+
+    /// Get a reference to the wrapped executor.
+    pub fn get_ref(&self) -> &E {
+        &self.inner
+    }
+
+    /// Consumes the `MeteredExecutor` object and returns the wrapped executor.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    /// Counts a spawned task of the kind selected by `counter`, returning the guard which counts
+    /// how it finished.
+    fn track(&self, counter: fn(&Counters) -> &Counter) -> Tracker {
+        counter(&self.counters).spawned.fetch_add(1, Ordering::Release);
+
+        Tracker {
+            counters: self.counters.clone(),
+            counter,
+            state: TrackerState::Idle,
+        }
+    }
+
+    /// Wraps the blocking job `f`, so it gets counted.
+    fn track_blocking<F, T>(&self, f: F) -> impl FnOnce() -> T + Send + 'static
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let mut tracker = self.track(|counters| &counters.blocking_jobs);
+
+        move || {
+            tracker.state = TrackerState::Running;
+            let output = f();
+            tracker.complete();
+
+            output
+        }
+    }
+}
+
+impl<E: Spawn> Spawn for MeteredExecutor<E> {
+    fn spawn_obj(
+        &self,
+        future: FutureObj<'static, ()>
+    ) -> Result<(), SpawnError> {
+        let future = Metered {
+            inner: future,
+            tracker: self.track(|counters| &counters.tasks),
+        };
+
+        // If spawning fails, the future gets dropped and counted as cancelled.
+        self.inner.spawn_obj(FutureObj::new(Box::new(future)))
+    }
+
+    fn status(&self) -> Result<(), SpawnError> {
+        self.inner.status()
+    }
+}
+
+impl<E: SpawnHandle> SpawnHandle for MeteredExecutor<E> {
+    fn spawn_handle<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.inner.spawn_handle(Metered {
+            inner: fut,
+            tracker: self.track(|counters| &counters.tasks),
+        })
+    }
+
+    fn spawn_handle_with_name<F>(&self, name: &str, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.inner.spawn_handle_with_name(name, Metered {
+            inner: fut,
+            tracker: self.track(|counters| &counters.tasks),
+        })
+    }
+}
+
+impl<E: SpawnBlocking> SpawnBlocking for MeteredExecutor<E> {
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.inner.spawn_blocking(self.track_blocking(f))
+    }
+
+    fn spawn_blocking_with_name<F, T>(&self, name: &str, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.inner.spawn_blocking_with_name(name, self.track_blocking(f))
+    }
+}
+
+
+
+/// Counts how a task finished when dropped, depending on its state.
+struct Tracker {
+    counters: Arc<Counters>,
+    counter: fn(&Counters) -> &Counter,
+    state: TrackerState,
+}
+
+enum TrackerState {
+    /// The task is not running, so dropping it cancels it.
+    Idle,
+    /// The task is running, so it can only get dropped if it panicked. Executors which catch the
+    /// panic drop the task afterwards, which is why this is tracked explicitly.
+    Running,
+    Completed,
+}
+
+impl Tracker {
+    /// Counts the task as completed.
+    fn complete(mut self) {
+        self.state = TrackerState::Completed;
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        let counter = (self.counter)(&self.counters);
+
+        let count = match self.state {
+            TrackerState::Idle => &counter.cancelled,
+            TrackerState::Running => &counter.panicked,
+            TrackerState::Completed => &counter.completed,
+        };
+
+        count.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// A future whose completion gets counted by its [`Tracker`].
+struct Metered<F> {
+    inner: F,
+    tracker: Tracker,
+}
+
+impl<F: Future> Future for Metered<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `inner` is structurally pinned, it never gets moved out of a pinned `Metered`.
+        // `tracker` is not pinned.
+        let (inner, tracker) = unsafe {
+            let this = self.get_unchecked_mut();
+
+            (Pin::new_unchecked(&mut this.inner), &mut this.tracker)
+        };
+
+        tracker.state = TrackerState::Running;
+        let poll = inner.poll(cx);
+        tracker.state = TrackerState::Idle;
+
+        if poll.is_ready() {
+            tracker.state = TrackerState::Completed;
+        }
+
+        poll
+    }
+}
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use futures::channel::oneshot;
+    use futures::task::SpawnExt;
+
+    /// Waits up to five seconds for all tasks and blocking jobs of `executor` to finish.
+    async fn settle<E: Timer>(executor: &MeteredExecutor<E>) -> ExecutorMetrics {
+        for _ in 0..500 {
+            let metrics = executor.metrics();
+            if metrics.tasks.running() == 0 && metrics.blocking_jobs.running() == 0 {
+                return metrics;
+            }
+
+            E::sleep(Duration::from_millis(10)).await;
+        }
+
+        executor.metrics()
+    }
+
+    async fn counts_a_mix_of_tasks<E>()
+    where
+        E: Spawn + SpawnHandle + SpawnBlocking + Timer + Default + Clone,
+    {
+        let executor = MeteredExecutor::new(E::default());
+
+        for i in 0..3 {
+            assert_eq!(executor.spawn_handle(async move { i }).await.unwrap(), i);
+        }
+        assert!(executor.spawn_handle_with_name("panics", async { panic!("boom") }).await.unwrap_err().is_panic());
+
+        let (tx, rx) = oneshot::channel();
+        executor.spawn(async move { tx.send(()).unwrap() }).unwrap();
+        rx.await.unwrap();
+
+        // The tasks only get counted once the runtime dropped them.
+        assert_eq!(settle(&executor).await.tasks.completed, 4);
+
+        let pending = executor.spawn_handle(futures::future::pending::<()>());
+        assert_eq!(executor.metrics().tasks.running(), 1);
+        pending.abort();
+
+        // The clones of the executor share the counts.
+        let clone = executor.clone();
+        assert_eq!(clone.spawn_blocking(|| 1).await.unwrap(), 1);
+        assert_eq!(clone.spawn_blocking_with_name("job", || 2).await.unwrap(), 2);
+        assert!(clone.spawn_blocking(|| panic!("boom")).await.unwrap_err().is_panic());
+
+        let metrics = settle(&executor).await;
+        assert_eq!(metrics.tasks, TaskCounts {
+            spawned: 6,
+            completed: 4,
+            panicked: 1,
+            cancelled: 1,
+        });
+        assert_eq!(metrics.blocking_jobs, TaskCounts {
+            spawned: 3,
+            completed: 2,
+            panicked: 1,
+            cancelled: 0,
+        });
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_counts_a_mix_of_tasks() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(counts_a_mix_of_tasks::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_counts_a_mix_of_tasks() {
+        ::async_std::task::block_on(counts_a_mix_of_tasks::<crate::task::AsyncStdExecutor>());
+    }
+}
@@ -0,0 +1,406 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Waker;
+
+use super::*;
+
+
+
+thread_local! {
+    /// The clock used by [`MockTimer`] on this thread, created on first use.
+    static CURRENT: RefCell<Option<MockClock>> = const { RefCell::new(None) };
+}
+
+/// A [`Timer`] whose clock only moves when told to, for testing code written against [`Timer`]
+/// deterministically and without waiting.
+///
+/// [`Timer`] has no receiver to carry a clock, so `MockTimer` uses the [`MockClock`] of the
+/// current thread. Every thread starts out with a clock of its own, so tests running in parallel
+/// do not affect each other. A clock can be shared with other threads through
+/// [`MockClock::enter`], e.g. by tasks running on a thread pool. Every sleep holds a handle to the
+/// clock it was created with, so it may be polled on any thread.
+///
+/// The clock starts at an arbitrary instant and is moved forward with [`advance`](Self::advance),
+/// which completes all sleeps whose deadline has been reached by waking their tasks.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use futures::FutureExt;
+/// use fut_compat::task::{timeout, MockTimer};
+///
+/// let mut slow = Box::pin(timeout::<MockTimer, _>(Duration::from_secs(30), futures::future::pending::<()>()));
+/// assert!((&mut slow).now_or_never().is_none());
+///
+/// MockTimer::advance(Duration::from_secs(30));
+///
+/// assert!(futures::executor::block_on(slow).is_err());
+/// ```
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MockTimer {}
+
+impl MockTimer {
+    /// Moves the clock of the current thread forward by `dur`, see [`MockClock::advance`].
+    pub fn advance(dur: Duration) {
+        MockClock::current().advance(dur)
+    }
+
+    /// Returns the number of sleeps of the clock of the current thread which have been created
+    /// but not completed or dropped yet.
+    pub fn pending_sleep_count() -> usize {
+        MockClock::current().pending_sleep_count()
+    }
+}
+
+impl Timer for MockTimer {
+    type Sleep = MockSleep;
+
+    fn sleep(dur: Duration) -> Self::Sleep {
+        let clock = MockClock::current();
+        let deadline = clock.now() + dur;
+
+        clock.sleep_until(deadline)
+    }
+
+    fn sleep_until(deadline: Instant) -> Self::Sleep {
+        MockClock::current().sleep_until(deadline)
+    }
+
+    fn now() -> Instant {
+        MockClock::current().now()
+    }
+}
+
+/// The clock of [`MockTimer`].
+///
+/// Clones share the same clock. Use [`current`](Self::current) to get the clock of the current
+/// thread, and [`enter`](Self::enter) to make a clock the current one.
+#[derive(Clone, Debug, Default)]
+pub struct MockClock {
+    inner: Arc<Mutex<Clock>>,
+}
+
+#[derive(Debug)]
+struct Clock {
+    /// The instant the clock started at.
+    start: Instant,
+    /// How far the clock has been advanced.
+    elapsed: Duration,
+    /// The sleeps which have not been completed yet, by their id.
+    sleeps: HashMap<u64, Entry>,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    deadline: Instant,
+    waker: Option<Waker>,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed: Duration::ZERO,
+            sleeps: HashMap::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl Clock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed
+    }
+}
+
+impl MockClock {
+    /// Creates a new clock, which is independent of all others.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the clock used by [`MockTimer`] on the current thread.
+    pub fn current() -> Self {
+        CURRENT.with(|current| current.borrow_mut().get_or_insert_with(Self::new).clone())
+    }
+
+    /// Makes this the clock used by [`MockTimer`] on the current thread, until the returned guard
+    /// gets dropped.
+    pub fn enter(&self) -> MockClockGuard {
+        let prev = CURRENT.with(|current| current.replace(Some(self.clone())));
+
+        MockClockGuard {
+            prev,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Returns the current instant of this clock.
+    pub fn now(&self) -> Instant {
+        self.lock().now()
+    }
+
+    /// Moves the clock forward by `dur` and wakes the tasks of all sleeps whose deadline has been
+    /// reached.
+    pub fn advance(&self, dur: Duration) {
+        let wakers: Vec<Waker> = {
+            let mut clock = self.lock();
+
+            clock.elapsed += dur;
+            let now = clock.now();
+
+            let mut wakers = Vec::new();
+            clock.sleeps.retain(|_, entry| {
+                if entry.deadline > now {
+                    return true;
+                }
+
+                wakers.extend(entry.waker.take());
+
+                false
+            });
+
+            wakers
+        };
+
+        // The tasks get woken outside of the lock, as they may be polled right away.
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Returns the number of sleeps which have been created but not completed or dropped yet.
+    pub fn pending_sleep_count(&self) -> usize {
+        self.lock().sleeps.len()
+    }
+
+    /// Returns a sleep on this clock, which completes once it has been advanced to `deadline`.
+    pub fn sleep_until(&self, deadline: Instant) -> MockSleep {
+        let mut clock = self.lock();
+
+        if deadline <= clock.now() {
+            return MockSleep {
+                clock: self.clone(),
+                id: None,
+            };
+        }
+
+        let id = clock.next_id;
+        clock.next_id += 1;
+
+        clock.sleeps.insert(id, Entry {
+            deadline,
+            waker: None,
+        });
+
+        MockSleep {
+            clock: self.clone(),
+            id: Some(id),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Clock> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Restores the previous clock of the current thread when dropped. Returned by
+/// [`MockClock::enter`].
+#[must_use = "the clock is only used until the guard gets dropped"]
+#[derive(Debug)]
+pub struct MockClockGuard {
+    prev: Option<MockClock>,
+    /// The guard restores the clock of the thread it was created on.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for MockClockGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+
+        CURRENT.with(|current| *current.borrow_mut() = prev);
+    }
+}
+
+/// The future returned by [`MockTimer`], which completes once its clock has been advanced to its
+/// deadline.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct MockSleep {
+    clock: MockClock,
+    /// The id of the entry in the clock, `None` once completed.
+    id: Option<u64>,
+}
+
+impl Future for MockSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = Pin::into_inner(self);
+
+        let id = match this.id {
+            Some(id) => id,
+            None => return Poll::Ready(()),
+        };
+
+        let mut clock = this.clock.lock();
+
+        match clock.sleeps.get_mut(&id) {
+            Some(entry) => {
+                entry.waker = Some(cx.waker().clone());
+
+                Poll::Pending
+            }
+            // Removed by `advance`, as the deadline has been reached.
+            None => {
+                this.id = None;
+
+                Poll::Ready(())
+            }
+        }
+    }
+}
+
+impl Drop for MockSleep {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.clock.lock().sleeps.remove(&id);
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::task::{waker, ArcWake};
+
+    /// Counts how often it has been woken.
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl ArcWake for CountingWaker {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    impl CountingWaker {
+        fn count(&self) -> usize {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    fn poll(sleep: &mut MockSleep, wake: &Arc<CountingWaker>) -> Poll<()> {
+        let waker = waker(wake.clone());
+
+        Pin::new(sleep).poll(&mut Context::from_waker(&waker))
+    }
+
+    #[test]
+    fn advance_completes_the_sleeps_it_covers() {
+        let (early_wake, late_wake) = (Arc::new(CountingWaker::default()), Arc::new(CountingWaker::default()));
+
+        let mut early = MockTimer::sleep(Duration::from_secs(1));
+        let mut late = MockTimer::sleep(Duration::from_secs(2));
+        assert!(poll(&mut early, &early_wake).is_pending());
+        assert!(poll(&mut late, &late_wake).is_pending());
+        assert_eq!(MockTimer::pending_sleep_count(), 2);
+
+        MockTimer::advance(Duration::from_secs(1));
+
+        // Only the covered sleep gets woken, exactly once.
+        assert_eq!((early_wake.count(), late_wake.count()), (1, 0));
+        assert!(poll(&mut early, &early_wake).is_ready());
+        assert!(poll(&mut late, &late_wake).is_pending());
+        assert_eq!(MockTimer::pending_sleep_count(), 1);
+
+        MockTimer::advance(Duration::from_secs(1));
+
+        assert_eq!(late_wake.count(), 1);
+        assert!(poll(&mut late, &late_wake).is_ready());
+        assert_eq!(MockTimer::pending_sleep_count(), 0);
+    }
+
+    #[test]
+    fn sleeps_created_after_advance_are_not_covered_by_it() {
+        let wake = Arc::new(CountingWaker::default());
+
+        MockTimer::advance(Duration::from_secs(5));
+        let mut sleep = MockTimer::sleep(Duration::from_secs(1));
+
+        assert!(poll(&mut sleep, &wake).is_pending());
+
+        MockTimer::advance(Duration::from_millis(999));
+        assert!(poll(&mut sleep, &wake).is_pending());
+        assert_eq!(wake.count(), 0);
+
+        MockTimer::advance(Duration::from_millis(1));
+        assert_eq!(wake.count(), 1);
+        assert!(poll(&mut sleep, &wake).is_ready());
+    }
+
+    #[test]
+    fn expired_deadlines_complete_right_away() {
+        let wake = Arc::new(CountingWaker::default());
+
+        let mut sleep = MockTimer::sleep_until(MockTimer::now());
+
+        assert!(poll(&mut sleep, &wake).is_ready());
+        assert_eq!(MockTimer::pending_sleep_count(), 0);
+    }
+
+    #[test]
+    fn dropped_sleeps_are_unregistered() {
+        let sleep = MockTimer::sleep(Duration::from_secs(1));
+        assert_eq!(MockTimer::pending_sleep_count(), 1);
+
+        drop(sleep);
+
+        assert_eq!(MockTimer::pending_sleep_count(), 0);
+    }
+
+    #[test]
+    fn threads_have_their_own_clock_unless_entered() {
+        let clock = MockClock::current();
+        let start = clock.now();
+
+        std::thread::spawn(|| MockTimer::advance(Duration::from_secs(60))).join().unwrap();
+        assert_eq!(MockTimer::now(), start);
+
+        let shared = clock.clone();
+        std::thread::spawn(move || {
+            let _guard = shared.enter();
+
+            MockTimer::advance(Duration::from_secs(60));
+        }).join().unwrap();
+        assert_eq!(MockTimer::now(), start + Duration::from_secs(60));
+    }
+
+    #[test]
+    fn sleeps_keep_their_clock_across_threads() {
+        let wake = Arc::new(CountingWaker::default());
+        let mut sleep = MockTimer::sleep(Duration::from_secs(1));
+
+        // Polled on another thread, the sleep still waits for the clock it was created with.
+        let (mut sleep, wake) = std::thread::spawn(move || {
+            assert!(poll(&mut sleep, &wake).is_pending());
+
+            (sleep, wake)
+        }).join().unwrap();
+
+        MockTimer::advance(Duration::from_secs(1));
+
+        assert_eq!(wake.count(), 1);
+        assert!(poll(&mut sleep, &wake).is_ready());
+    }
+}
@@ -1,7 +1,14 @@
+use std::any::Any;
+use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::error::Error;
+use std::time::{Duration, Instant};
+
+use futures::channel::oneshot;
+use futures::future::{AbortHandle as FutAbortHandle, Abortable, Aborted, FusedFuture, FutureExt};
 
 pub use futures::task::{Spawn, SpawnExt};
 
@@ -23,40 +30,731 @@ mod async_std;
 #[cfg_attr(docsrs, doc(cfg(feature = "async-std-rt")))]
 pub use self::async_std::*;
 
+/// Contains the compatibility objects for the [`smol`](https://docs.rs/smol) runtime.
+#[cfg(feature = "smol-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol-rt")))]
+mod smol;
+#[cfg(feature = "smol-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol-rt")))]
+pub use self::smol::*;
+
+/// Contains the compatibility objects for the event loop of JavaScript hosts on `wasm32`.
+#[cfg(all(feature = "wasm-rt", target_arch = "wasm32"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "wasm-rt", target_arch = "wasm32"))))]
+mod wasm;
+#[cfg(all(feature = "wasm-rt", target_arch = "wasm32"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "wasm-rt", target_arch = "wasm32"))))]
+pub use self::wasm::*;
+
+/// Spawning onto the runtime of the current thread.
+mod default;
+pub use self::default::*;
+
+/// Choosing what happens when a [`JoinHandle`] gets dropped.
+mod abort_on_drop;
+pub use self::abort_on_drop::*;
+
+/// Counting the tasks spawned by an executor.
+mod metered;
+pub use self::metered::*;
+
+/// Catching the panics of spawned tasks.
+mod catch_unwind;
+pub use self::catch_unwind::*;
+
+/// Cooperative cancellation of blocking jobs.
+mod cancel;
+pub use self::cancel::*;
+
+/// Limiting the time a future may take.
+mod timeout;
+pub use self::timeout::*;
+
+/// Ticking at a fixed period.
+mod interval;
+pub use self::interval::*;
+
+/// Managing groups of spawned tasks.
+mod join_set;
+pub use self::join_set::*;
+
+/// Structured concurrency, waiting for all spawned tasks.
+mod scope;
+pub use self::scope::*;
+
+/// Running many futures as tasks with a concurrency limit.
+mod spawn_all;
+pub use self::spawn_all::*;
+
+/// Spawning tasks with names and tracing spans.
+#[cfg(feature = "tracing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+mod named;
+#[cfg(feature = "tracing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+pub use self::named::*;
+
+/// Wrapping all tasks of an executor in tracing spans.
+#[cfg(feature = "tracing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+mod traced;
+#[cfg(feature = "tracing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+pub use self::traced::*;
+
+/// Tracking the lifetime of tasks, for testing.
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tracker;
+
+/// A timer for tests, which is advanced manually.
+#[cfg(feature = "test-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+mod mock_timer;
+#[cfg(feature = "test-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+pub use self::mock_timer::*;
+
 
 
 /// An abstraction over executing a sync task in a new blocking thread and optionally awaiting
 /// it's completion in an async fashion.
+///
+/// See [`DynSpawnBlocking`] for choosing the executor at runtime.
 pub trait SpawnBlocking {
-    fn spawn_blocking<F, T>(f: F) -> JoinHandle<T>
+    /// Runs `f` on the blocking thread pool of the executor and returns a handle for awaiting its
+    /// result.
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
     where
         F: FnOnce() -> T + Send + 'static,
         T: Send + 'static;
+
+    /// Like [`spawn_blocking`](Self::spawn_blocking), but names the job for runtimes which
+    /// support it. The default implementation ignores the name.
+    ///
+    /// Tokio names the job when built with the `tokio_unstable` cfg and the `tracing` feature.
+    fn spawn_blocking_with_name<F, T>(&self, name: &str, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let _ = name;
+
+        self.spawn_blocking(f)
+    }
+
+    /// Like [`spawn_blocking`](Self::spawn_blocking), but passes a [`CancelGuard`] to `f`, which
+    /// it can check for returning early once the returned [`CancelToken`] got cancelled.
+    ///
+    /// Unlike with [`JoinHandle::abort`], the handle still resolves to the result of `f`, e.g.
+    /// the partial result of a cancelled job. Aborting the handle cancels the token as well, so
+    /// does dropping it if it has been turned into [`abort_on_drop`](JoinHandle::abort_on_drop).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "tokio-rt")]
+    /// # async fn example() {
+    /// use fut_compat::task::{SpawnBlocking, TokioExecutor};
+    ///
+    /// let (handle, token) = TokioExecutor::default().spawn_blocking_cancellable(|guard| {
+    ///     let mut files = Vec::new();
+    ///
+    ///     for entry in walk_directory_tree("/") {
+    ///         if guard.is_cancelled() {
+    ///             break;
+    ///         }
+    ///
+    ///         files.push(entry);
+    ///     }
+    ///
+    ///     files
+    /// });
+    ///
+    /// // Later on, e.g. after the user gave up.
+    /// token.cancel();
+    ///
+    /// let files_found_so_far = handle.await.unwrap();
+    /// # }
+    /// # fn walk_directory_tree(_: &str) -> Vec<std::path::PathBuf> { Vec::new() }
+    /// ```
+    fn spawn_blocking_cancellable<F, T>(&self, f: F) -> (JoinHandle<T>, CancelToken)
+    where
+        F: FnOnce(&CancelGuard) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let token = CancelToken::new();
+
+        let guard = token.guard();
+        let handle = self.spawn_blocking(move || f(&guard));
+
+        let abort = handle.abort_handle();
+        let cancel = token.clone();
+        let handle = JoinHandle::with_canceller(handle, move || {
+            cancel.cancel();
+            abort.abort();
+        });
+
+        (handle, token)
+    }
+}
+
+impl<S: SpawnBlocking + ?Sized> SpawnBlocking for &S {
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        (**self).spawn_blocking(f)
+    }
+
+    fn spawn_blocking_with_name<F, T>(&self, name: &str, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        (**self).spawn_blocking_with_name(name, f)
+    }
+
+    fn spawn_blocking_cancellable<F, T>(&self, f: F) -> (JoinHandle<T>, CancelToken)
+    where
+        F: FnOnce(&CancelGuard) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        (**self).spawn_blocking_cancellable(f)
+    }
+}
+
+impl<S: SpawnBlocking + ?Sized> SpawnBlocking for Box<S> {
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        (**self).spawn_blocking(f)
+    }
+
+    fn spawn_blocking_with_name<F, T>(&self, name: &str, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        (**self).spawn_blocking_with_name(name, f)
+    }
+
+    fn spawn_blocking_cancellable<F, T>(&self, f: F) -> (JoinHandle<T>, CancelToken)
+    where
+        F: FnOnce(&CancelGuard) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        (**self).spawn_blocking_cancellable(f)
+    }
+}
+
+impl<S: SpawnBlocking + ?Sized> SpawnBlocking for Arc<S> {
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        (**self).spawn_blocking(f)
+    }
+
+    fn spawn_blocking_with_name<F, T>(&self, name: &str, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        (**self).spawn_blocking_with_name(name, f)
+    }
+
+    fn spawn_blocking_cancellable<F, T>(&self, f: F) -> (JoinHandle<T>, CancelToken)
+    where
+        F: FnOnce(&CancelGuard) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        (**self).spawn_blocking_cancellable(f)
+    }
+}
+
+
+/// The object-safe counterpart of [`SpawnBlocking`], implemented by all of its implementors.
+///
+/// A `Box<dyn DynSpawnBlocking>` or `&dyn DynSpawnBlocking` implements [`SpawnBlocking`] again,
+/// passing the result of the task back through a channel.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(all(feature = "tokio-rt", feature = "async-std-rt"))]
+/// # async fn example(use_tokio: bool) {
+/// use fut_compat::task::{AsyncStdExecutor, DynSpawnBlocking, SpawnBlocking, TokioExecutor};
+///
+/// let executor: Box<dyn DynSpawnBlocking> = match use_tokio {
+///     true => Box::new(TokioExecutor::default()),
+///     false => Box::new(AsyncStdExecutor::default()),
+/// };
+///
+/// let sum = executor.spawn_blocking(|| (1..=100u64).sum::<u64>()).await.unwrap();
+/// # }
+/// ```
+pub trait DynSpawnBlocking: Send + Sync {
+    /// Runs `f` on the blocking thread pool of the executor and returns a handle for awaiting its
+    /// completion.
+    fn spawn_blocking_obj(&self, f: Box<dyn FnOnce() + Send + 'static>) -> JoinHandle<()>;
+}
+
+impl<S: SpawnBlocking + Send + Sync> DynSpawnBlocking for S {
+    fn spawn_blocking_obj(&self, f: Box<dyn FnOnce() + Send + 'static>) -> JoinHandle<()> {
+        self.spawn_blocking(f)
+    }
+}
+
+impl SpawnBlocking for dyn DynSpawnBlocking + '_ {
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+
+        let handle = self.spawn_blocking_obj(Box::new(move || {
+            let _ = sender.send(f());
+        }));
+        let abort = handle.abort_handle();
+
+        let result = Box::pin(async move {
+            handle.await?;
+
+            // The task completed, so the result has been sent.
+            receiver.await.map_err(|_| JoinError::cancelled())
+        });
+
+        JoinHandle::with_canceller(result, move || abort.abort())
+    }
+}
+
+
+/// An abstraction over spawning a future and awaiting its output through a [`JoinHandle`].
+///
+/// Unlike [`Spawn`], which only accepts futures returning `()`, this hands the output of the
+/// future back without setting up a channel.
+///
+/// # Example
+///
+/// ```no_run
+/// use fut_compat::task::SpawnHandle;
+///
+/// async fn sum_in_parallel<E: SpawnHandle>(executor: &E, chunks: Vec<Vec<u64>>) -> u64 {
+///     let handles: Vec<_> = chunks.into_iter()
+///         .map(|chunk| executor.spawn_handle(async move { chunk.iter().sum::<u64>() }))
+///         .collect();
+///
+///     let mut total = 0;
+///     for handle in handles {
+///         total += handle.await.expect("the task panicked");
+///     }
+///
+///     total
+/// }
+/// ```
+pub trait SpawnHandle {
+    /// Spawns `fut` onto the executor and returns a handle for awaiting its output.
+    fn spawn_handle<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static;
+
+    /// Like [`spawn_handle`](Self::spawn_handle), but names the task for runtimes which support
+    /// it. The default implementation ignores the name.
+    ///
+    /// Tokio names the task when built with the `tokio_unstable` cfg and the `tracing` feature.
+    fn spawn_handle_with_name<F>(&self, name: &str, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let _ = name;
+
+        self.spawn_handle(fut)
+    }
+}
+
+
+/// An abstraction over the timer of a runtime.
+pub trait Timer {
+    /// The future returned by [`sleep`](Timer::sleep).
+    type Sleep: Future<Output = ()> + Send;
+
+    /// Returns a future which completes after `dur` has elapsed.
+    fn sleep(dur: Duration) -> Self::Sleep;
+
+    /// Returns a future which completes once `deadline` has been reached.
+    fn sleep_until(deadline: Instant) -> Self::Sleep {
+        Self::sleep(deadline.saturating_duration_since(Self::now()))
+    }
+
+    /// Changes the instant at which `sleep` completes to `deadline`, even if it already completed.
+    ///
+    /// Runtimes which support it reset the timer in place. Otherwise `sleep` gets replaced by a
+    /// new one from [`sleep_until`](Timer::sleep_until).
+    fn reset(mut sleep: Pin<&mut Self::Sleep>, deadline: Instant) {
+        sleep.set(Self::sleep_until(deadline))
+    }
+
+    /// Returns the current instant according to this timer.
+    ///
+    /// Utilities measuring elapsed time should use this instead of [`Instant::now`], so they
+    /// keep working with timers which do not follow the system clock.
+    fn now() -> Instant {
+        Instant::now()
+    }
 }
 
 
-/// A handle that awaits the result of a task. Gets returned by [`SpawnBlocking`].
+/// An abstraction over blocking the current thread until a future completes.
+///
+/// This is the entry point from sync into async code, e.g. for `main` without a macro, FFI
+/// callbacks or plain `#[test]` functions. Inside of a runtime it must only be used from threads
+/// which are allowed to block, e.g. inside of [`SpawnBlocking::spawn_blocking`]. Blocking a thread
+/// of the async executor stalls every task scheduled on it and can deadlock, if the future depends
+/// on one of these tasks.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "async-std-rt")]
+/// # fn example() {
+/// use fut_compat::task::{AsyncStdExecutor, BlockOn};
+///
+/// let answer = AsyncStdExecutor::default().block_on(async { 42 });
+///
+/// assert_eq!(answer, 42);
+/// # }
+/// ```
+pub trait BlockOn {
+    /// Runs `future` to completion on the current thread and returns its output.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread must not block, see [`try_block_on`](Self::try_block_on).
+    fn block_on<F: Future>(&self, future: F) -> F::Output;
+
+    /// Like [`block_on`](Self::block_on), but returns an error instead of panicking if the runtime
+    /// does not allow blocking the current thread. The future gets dropped in that case.
+    fn try_block_on<F: Future>(&self, future: F) -> Result<F::Output, BlockOnError> {
+        Ok(self.block_on(future))
+    }
+}
+
+/// The error returned by [`BlockOn::try_block_on`] if the current thread must not block.
+#[derive(Debug, Default)]
+pub struct BlockOnError {
+    _priv: (),
+}
+
+impl BlockOnError {
+    /// Creates a new instance, for implementations of [`BlockOn`] outside of this crate.
+    pub fn new() -> Self {
+        Self {
+            _priv: (),
+        }
+    }
+}
+
+impl fmt::Display for BlockOnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the current thread drives the tasks of an async runtime and must not block")
+    }
+}
+
+impl Error for BlockOnError {}
+
+
+/// The error returned by a [`JoinHandle`] if the task did not complete.
+pub struct JoinError {
+    repr: JoinErrorRepr,
+}
+
+enum JoinErrorRepr {
+    Cancelled,
+    Panic(Box<dyn Any + Send + 'static>),
+}
+
+impl JoinError {
+    /// Creates an error for a task which has been cancelled.
+    pub fn cancelled() -> Self {
+        Self {
+            repr: JoinErrorRepr::Cancelled,
+        }
+    }
+
+    /// Creates an error for a task which panicked with `payload`.
+    pub fn panic(payload: Box<dyn Any + Send + 'static>) -> Self {
+        Self {
+            repr: JoinErrorRepr::Panic(payload),
+        }
+    }
+
+    /// Returns `true` if the task has been cancelled, e.g. with [`JoinHandle::abort`].
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.repr, JoinErrorRepr::Cancelled)
+    }
+
+    /// Returns `true` if the task panicked.
+    pub fn is_panic(&self) -> bool {
+        matches!(self.repr, JoinErrorRepr::Panic(_))
+    }
+
+    /// Returns the payload of the panic of the task, or the error itself if the task did not
+    /// panic.
+    ///
+    /// The payload can be passed to [`std::panic::resume_unwind`] to propagate the panic.
+    pub fn try_into_panic(self) -> Result<Box<dyn Any + Send + 'static>, Self> {
+        match self.repr {
+            JoinErrorRepr::Panic(payload) => Ok(payload),
+            repr => Err(Self { repr }),
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.repr {
+            JoinErrorRepr::Cancelled => write!(f, "task was cancelled"),
+            JoinErrorRepr::Panic(payload) => match panic_message(payload.as_ref()) {
+                Some(msg) => write!(f, "task panicked with message {:?}", msg),
+                None => write!(f, "task panicked"),
+            },
+        }
+    }
+}
+
+impl fmt::Debug for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.repr {
+            JoinErrorRepr::Cancelled => write!(f, "JoinError::Cancelled"),
+            JoinErrorRepr::Panic(payload) => match panic_message(payload.as_ref()) {
+                Some(msg) => write!(f, "JoinError::Panic({:?}, ...)", msg),
+                None => write!(f, "JoinError::Panic(...)"),
+            },
+        }
+    }
+}
+
+impl Error for JoinError {}
+
+impl From<JoinError> for std::io::Error {
+    fn from(err: JoinError) -> Self {
+        std::io::Error::other(err.to_string())
+    }
+}
+
+/// Returns the message of a panic payload created by `panic!`.
+fn panic_message(payload: &(dyn Any + Send)) -> Option<&str> {
+    match payload.downcast_ref::<&'static str>() {
+        Some(msg) => Some(msg),
+        None => payload.downcast_ref::<String>().map(|msg| msg.as_str()),
+    }
+}
+
+
+/// A handle which cancels a task. Gets returned by [`JoinHandle::abort_handle`].
+///
+/// Unlike the [`JoinHandle`] it can be cloned and does not need to be awaited.
+#[derive(Clone)]
+pub struct AbortHandle {
+    join: FutAbortHandle,
+    canceller: Option<Arc<dyn Fn() + Send + Sync + 'static>>,
+}
+
+impl AbortHandle {
+    /// Cancels the task, see [`JoinHandle::abort`].
+    pub fn abort(&self) {
+        self.join.abort();
+
+        if let Some(canceller) = &self.canceller {
+            canceller();
+        }
+    }
+
+    /// Returns `true` if [`abort`](Self::abort) has been called.
+    pub fn is_aborted(&self) -> bool {
+        self.join.is_aborted()
+    }
+}
+
+impl fmt::Debug for AbortHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AbortHandle")
+            .field("is_aborted", &self.is_aborted())
+            .finish()
+    }
+}
+
+
+/// A handle that awaits the result of a task. Gets returned by [`SpawnHandle`] and
+/// [`SpawnBlocking`].
+///
+/// The task can be cancelled with [`abort`](Self::abort). Dropping the handle detaches the task
+/// instead, unless it has been turned into [`abort_on_drop`](Self::abort_on_drop), e.g. by
+/// spawning it with [`AbortOnDrop`]. Use [`detach`](Self::detach) to make clear that the result
+/// of the task is not needed.
+///
+/// The handle is fused: once it resolved, polling it again returns [`Poll::Pending`] and
+/// [`is_terminated`](FusedFuture::is_terminated) returns `true`, so it can be used within
+/// `select!` loops.
+#[must_use = "dropping a JoinHandle detaches the task, call `detach` if that is intended"]
 pub struct JoinHandle<T> {
-    inner: Box<dyn Future<Output = Result<T, Box<dyn Error>>> + Unpin +'static>
+    /// The result of the task, `None` once it has been returned.
+    inner: Option<Box<dyn Future<Output = Result<T, JoinError>> + Send + Unpin + 'static>>,
+    abort: AbortHandle,
+    abort_on_drop: bool,
 }
 
-impl<T> JoinHandle<T>
+impl<T: Send + 'static> JoinHandle<T>
 {
+    /// Creates a new handle awaiting `inner`, which can not cancel the task itself.
+    ///
+    /// [`abort`](Self::abort) still makes the handle resolve to a [`JoinError`], but the task
+    /// keeps running in the background.
     pub fn new<J>(inner: J) -> Self
     where
-        J: Future<Output = Result<T, Box<dyn Error>>> + Unpin + 'static,
+        J: Future<Output = Result<T, JoinError>> + Send + Unpin + 'static,
     {
+        Self::new_inner(inner, None)
+    }
+
+    /// Creates a new handle awaiting `inner`, calling `canceller` to cancel the task when it gets
+    /// aborted.
+    pub fn with_canceller<J, C>(inner: J, canceller: C) -> Self
+    where
+        J: Future<Output = Result<T, JoinError>> + Send + Unpin + 'static,
+        C: Fn() + Send + Sync + 'static,
+    {
+        Self::new_inner(inner, Some(Arc::new(canceller)))
+    }
+
+    fn new_inner<J>(inner: J, canceller: Option<Arc<dyn Fn() + Send + Sync + 'static>>) -> Self
+    where
+        J: Future<Output = Result<T, JoinError>> + Send + Unpin + 'static,
+    {
+        let (join, registration) = FutAbortHandle::new_pair();
+
+        let inner = Abortable::new(inner, registration).map(|result| match result {
+            Ok(result) => result,
+            Err(Aborted) => Err(JoinError::cancelled()),
+        });
+
         Self {
-            inner: Box::new(inner),
+            inner: Some(Box::new(inner)),
+            abort: AbortHandle {
+                join,
+                canceller,
+            },
+            abort_on_drop: false,
+        }
+    }
+}
+
+impl<T> JoinHandle<T>
+{
+    /// Cancels the task.
+    ///
+    /// Awaiting the handle afterwards resolves to a [`JoinError`] for which
+    /// [`is_cancelled`](JoinError::is_cancelled) returns `true`, unless it already completed.
+    ///
+    /// Async tasks get dropped the next time they would be polled. Blocking jobs can only be
+    /// cancelled before they started running, as threads can not be interrupted. A blocking job
+    /// which already runs is abandoned instead: it runs to completion in the background and its
+    /// result gets discarded.
+    pub fn abort(&self) {
+        self.abort.abort()
+    }
+
+    /// Returns a handle which cancels the task, without having to keep the `JoinHandle` around.
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.abort.clone()
+    }
+
+    /// Makes dropping the handle [`abort`](Self::abort) the task, instead of detaching it.
+    pub fn abort_on_drop(mut self) -> Self {
+        self.abort_on_drop = true;
+
+        self
+    }
+
+    /// Drops the handle, letting the task run to completion in the background. Its result gets
+    /// discarded.
+    ///
+    /// This also applies to handles which have been turned into
+    /// [`abort_on_drop`](Self::abort_on_drop).
+    pub fn detach(mut self) {
+        self.abort_on_drop = false;
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        if self.abort_on_drop {
+            self.abort.abort();
         }
     }
 }
 
 impl<T> Future for JoinHandle<T>
 {
-    type Output = Result<T, Box<dyn Error>>;
+    type Output = Result<T, JoinError>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        Future::poll(Pin::new(&mut Pin::into_inner(self).inner), cx)
+        let this = Pin::into_inner(self);
+
+        let inner = match &mut this.inner {
+            Some(inner) => inner,
+            None => return Poll::Pending,
+        };
+
+        let result = futures::ready!(Future::poll(Pin::new(inner), cx));
+        // Some inner handles, like the one of tokio, panic when polled after completion.
+        this.inner = None;
+
+        Poll::Ready(result)
+    }
+}
+
+impl<T> FusedFuture for JoinHandle<T> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_none()
+    }
+}
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    async fn dyn_executors_run_typed_jobs(executor: Box<dyn DynSpawnBlocking>) {
+        let sum = executor.spawn_blocking(|| (1..=100u64).sum::<u64>()).await.unwrap();
+        assert_eq!(sum, 5050);
+
+        let executor: &dyn DynSpawnBlocking = &*executor;
+        assert_eq!(executor.spawn_blocking_with_name("named", || "done").await.unwrap(), "done");
+
+        let err = executor.spawn_blocking(|| panic!("boom")).await.unwrap_err();
+        assert!(err.is_panic());
+        assert_eq!(*err.try_into_panic().unwrap().downcast::<&str>().unwrap(), "boom");
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_dyn_executors_run_typed_jobs() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            dyn_executors_run_typed_jobs(Box::new(TokioExecutor::default())).await;
+        });
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_dyn_executors_run_typed_jobs() {
+        ::async_std::task::block_on(dyn_executors_run_typed_jobs(Box::new(AsyncStdExecutor::default())));
     }
 }
@@ -0,0 +1,130 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tracing::Instrument;
+
+use super::*;
+
+
+
+/// Returns a new id for a task, unique within the process.
+pub(super) fn next_task_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// An extension trait for spawning tasks which carry a name and a [`tracing`](https://docs.rs/tracing)
+/// span.
+///
+/// The tasks run within an `INFO` span with the target `fut_compat::task`, named `task` for async
+/// tasks and `blocking_task` for blocking jobs. It carries the following fields:
+///
+/// - `task.name`: the given name.
+/// - `task.id`: an id generated for the task, unique within the process.
+///
+/// The events of the task are recorded within the span, which makes it easy to tell tasks apart
+/// and to find the ones which never complete. The name is passed to the runtime as well, see
+/// [`SpawnHandle::spawn_handle_with_name`].
+///
+/// # Example
+///
+/// ```no_run
+/// use fut_compat::task::{SpawnHandle, SpawnNamedExt};
+///
+/// async fn refresh_cache<E: SpawnHandle>(executor: &E) {
+///     executor.spawn_named("cache-refresh", async {
+///         tracing::info!("refreshing the cache");
+///     }).detach();
+/// }
+/// ```
+pub trait SpawnNamedExt {
+    /// Spawns `fut` like [`SpawnHandle::spawn_handle`], within a span carrying `name`.
+    fn spawn_named<F>(&self, name: &str, fut: F) -> JoinHandle<F::Output>
+    where
+        Self: SpawnHandle,
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let span = tracing::info_span!(target: "fut_compat::task", "task", task.name = name, task.id = next_task_id());
+
+        self.spawn_handle_with_name(name, fut.instrument(span))
+    }
+
+    /// Runs `f` like [`SpawnBlocking::spawn_blocking`], within a span carrying `name`.
+    fn spawn_blocking_named<F, T>(&self, name: &str, f: F) -> JoinHandle<T>
+    where
+        Self: SpawnBlocking,
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let span = tracing::info_span!(target: "fut_compat::task", "blocking_task", task.name = name, task.id = next_task_id());
+
+        self.spawn_blocking_with_name(name, move || span.in_scope(f))
+    }
+}
+
+impl<E: ?Sized> SpawnNamedExt for E {}
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use crate::capture::Capture;
+
+    /// Records `message` with `capture`, which works on the threads of the runtime as well.
+    fn log(capture: &Capture, message: &'static str) {
+        tracing::subscriber::with_default(capture.clone(), || tracing::info!("{}", message));
+    }
+
+    async fn tasks_run_within_their_span<E>()
+    where
+        E: SpawnHandle + SpawnBlocking + Default,
+    {
+        let capture = Capture::default();
+        let _guard = tracing::subscriber::set_default(capture.clone());
+
+        let executor = E::default();
+
+        let logger = capture.clone();
+        executor.spawn_named("first", async move { log(&logger, "async") }).await.unwrap();
+        executor.spawn_named("second", async {}).await.unwrap();
+        let logger = capture.clone();
+        executor.spawn_blocking_named("job", move || log(&logger, "blocking")).await.unwrap();
+
+        let spans = capture.spans();
+        let tasks: Vec<_> = spans.iter()
+            .filter(|span| span.target == "fut_compat::task")
+            .map(|span| (span.name, span.fields["task.name"].as_str()))
+            .collect();
+        assert_eq!(tasks, [("task", "first"), ("task", "second"), ("blocking_task", "job")]);
+
+        let mut ids: Vec<u64> = spans.iter()
+            .filter_map(|span| span.fields.get("task.id"))
+            .map(|id| id.parse().unwrap())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+
+        let span_of = |message: &str| {
+            let event = capture.events().into_iter().find(|event| event.fields["message"] == message).unwrap();
+
+            spans[event.span.unwrap()].fields["task.name"].clone()
+        };
+        assert_eq!(span_of("async"), "first");
+        assert_eq!(span_of("blocking"), "job");
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_tasks_run_within_their_span() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(tasks_run_within_their_span::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_tasks_run_within_their_span() {
+        ::async_std::task::block_on(tasks_run_within_their_span::<crate::task::AsyncStdExecutor>());
+    }
+}
@@ -0,0 +1,435 @@
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::Poll;
+
+use futures::channel::mpsc;
+use futures::stream::{FuturesUnordered, StreamExt};
+use futures::future::poll_fn;
+use futures::task::AtomicWaker;
+
+use super::{AbortHandle, JoinHandle, SpawnHandle};
+
+
+
+/// Runs `body` with a [`Scope`] for spawning tasks with `executor`, and waits for all of them to
+/// complete before returning.
+///
+/// `body` itself runs within the calling task, so it may borrow from the caller. The spawned tasks
+/// must be `'static`, as nothing can stop the caller from leaking the returned future, which
+/// would let them outlive any borrow.
+///
+/// If `body` or any task returns an error, or a task panics, all other tasks get aborted and
+/// awaited. Then the first error gets returned, or the panic gets resumed. If the returned future
+/// gets dropped before completion, all tasks get aborted, but can not be awaited.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example(urls: &[String]) -> std::io::Result<()> {
+/// use fut_compat::task::{scope, TokioExecutor};
+///
+/// scope(TokioExecutor::default(), |s| async move {
+///     for url in urls {
+///         let url = url.clone();
+///
+///         s.spawn(async move {
+///             println!("fetching {}", url);
+///             Ok::<_, std::io::Error>(())
+///         });
+///     }
+///
+///     Ok(())
+/// }).await?;
+///
+/// // All tasks completed successfully here.
+/// # Ok(())
+/// # }
+/// ```
+pub async fn scope<E, Er, T, B, Fut>(executor: E, body: B) -> Result<T, Er>
+where
+    E: SpawnHandle,
+    Er: Send + 'static,
+    B: FnOnce(Scope<E, Er>) -> Fut,
+    Fut: Future<Output = Result<T, Er>>,
+{
+    let (sender, mut receiver) = mpsc::unbounded();
+
+    let shared = Arc::new(Shared {
+        executor,
+        sender,
+        live: AtomicUsize::new(0),
+        waker: AtomicWaker::new(),
+    });
+
+    let body = body(Scope {
+        shared: shared.clone(),
+    });
+    let mut body = std::pin::pin!(body);
+
+    let mut tasks = FuturesUnordered::new();
+    let mut output = None;
+
+    let result = poll_fn(|cx| {
+        loop {
+            if output.is_none() {
+                match body.as_mut().poll(cx) {
+                    Poll::Ready(Ok(value)) => output = Some(value),
+                    Poll::Ready(Err(err)) => return Poll::Ready(Err(Failure::Error(err))),
+                    Poll::Pending => {}
+                }
+            }
+
+            // The scope holds a sender, so the channel never ends. Tasks get sent before the
+            // task spawning them completes, so they show up here before the set can run empty.
+            while let Poll::Ready(Some(task)) = receiver.poll_next_unpin(cx) {
+                tasks.push(task);
+            }
+
+            match tasks.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(Ok(())))) => {}
+                Poll::Ready(Some(Ok(Err(err)))) => return Poll::Ready(Err(Failure::Error(err))),
+                // Tasks aborted through their `AbortHandle` count as done.
+                Poll::Ready(Some(Err(err))) => if let Ok(payload) = err.try_into_panic() {
+                    return Poll::Ready(Err(Failure::Panic(payload)));
+                },
+                Poll::Ready(None) if output.is_some() => return Poll::Ready(Ok(())),
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        }
+    }).await;
+
+    let failure = match result {
+        Ok(()) => {
+            shared.wait_for_tasks().await;
+
+            return Ok(output.expect("the body completed"));
+        }
+        Err(failure) => failure,
+    };
+
+    // Stops the tasks spawned so far. Later ones get aborted right away, see `Scope::spawn`.
+    receiver.close();
+    while let Some(task) = receiver.next().await {
+        tasks.push(task);
+    }
+    for task in tasks.iter() {
+        task.abort();
+    }
+    while tasks.next().await.is_some() {}
+
+    // The handles of some runtimes resolve on abort before the task has been dropped.
+    shared.wait_for_tasks().await;
+
+    match failure {
+        Failure::Error(err) => Err(err),
+        Failure::Panic(payload) => std::panic::resume_unwind(payload),
+    }
+}
+
+/// Why a scope stopped before all of its tasks completed.
+enum Failure<Er> {
+    Error(Er),
+    Panic(Box<dyn std::any::Any + Send + 'static>),
+}
+
+/// Spawns tasks which are awaited by [`scope`] before it returns.
+///
+/// It can be cloned and moved into the tasks, so they can spawn further tasks within the scope.
+pub struct Scope<E, Er> {
+    shared: Arc<Shared<E, Er>>,
+}
+
+struct Shared<E, Er> {
+    executor: E,
+    sender: mpsc::UnboundedSender<JoinHandle<Result<(), Er>>>,
+    /// The number of tasks which have not been dropped yet.
+    live: AtomicUsize,
+    /// Woken when `live` drops to zero.
+    waker: AtomicWaker,
+}
+
+impl<E, Er> Shared<E, Er> {
+    /// Waits until all tasks have been dropped, which happens after they completed or have been
+    /// aborted.
+    async fn wait_for_tasks(&self) {
+        poll_fn(|cx| {
+            self.waker.register(cx.waker());
+
+            match self.live.load(Ordering::Acquire) {
+                0 => Poll::Ready(()),
+                _ => Poll::Pending,
+            }
+        }).await
+    }
+}
+
+/// Counts a task as live until dropped together with it.
+struct LiveGuard<E, Er> {
+    shared: Arc<Shared<E, Er>>,
+}
+
+impl<E, Er> LiveGuard<E, Er> {
+    fn new(shared: &Arc<Shared<E, Er>>) -> Self {
+        shared.live.fetch_add(1, Ordering::AcqRel);
+
+        Self {
+            shared: shared.clone(),
+        }
+    }
+}
+
+impl<E, Er> Drop for LiveGuard<E, Er> {
+    fn drop(&mut self) {
+        if self.shared.live.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.waker.wake();
+        }
+    }
+}
+
+impl<E, Er> Scope<E, Er>
+where
+    E: SpawnHandle,
+    Er: Send + 'static,
+{
+    /// Spawns `future` within the scope.
+    ///
+    /// Returns a handle for aborting the task, which does not count as an error. If the scope
+    /// already completed, the task gets aborted right away.
+    pub fn spawn<F>(&self, future: F) -> AbortHandle
+    where
+        F: Future<Output = Result<(), Er>> + Send + 'static,
+        E: Send + Sync + 'static,
+    {
+        let guard = LiveGuard::new(&self.shared);
+        let future = async move {
+            let _guard = guard;
+
+            future.await
+        };
+
+        // Dropping the handle aborts the task, e.g. when the scope gets dropped.
+        let task = self.shared.executor.spawn_handle(future).abort_on_drop();
+        let abort = task.abort_handle();
+
+        // Fails once the scope completed, which drops and thereby aborts the task.
+        let _ = self.shared.sender.unbounded_send(task);
+
+        abort
+    }
+}
+
+impl<E, Er> Clone for Scope<E, Er> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<E: fmt::Debug, Er> fmt::Debug for Scope<E, Er> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("executor", &self.shared.executor)
+            .finish_non_exhaustive()
+    }
+}
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use std::panic::AssertUnwindSafe;
+    use std::time::{Duration, Instant};
+
+    use futures::FutureExt;
+
+    use crate::task::{timeout, Timer};
+    use crate::task::tracker::Tracker;
+
+    const FOREVER: Duration = Duration::from_secs(60);
+
+    async fn waits_for_all_tasks<E>()
+    where
+        E: SpawnHandle + Timer + Default + Send + Sync + 'static,
+    {
+        let tracker = &Tracker::default();
+
+        let value = scope(E::default(), |s| async move {
+            for i in 0..5 {
+                s.spawn(tracker.task::<E, Result<(), ()>>(Duration::from_millis(10 * i), Ok(())));
+            }
+
+            // Tasks may spawn further tasks within the scope.
+            let inner = s.clone();
+            let task = tracker.task::<E, Result<(), ()>>(Duration::from_millis(30), Ok(()));
+            s.spawn(async move {
+                inner.spawn(task);
+
+                Ok(())
+            });
+
+            Ok::<_, ()>(42)
+        }).await.unwrap();
+
+        assert_eq!(value, 42);
+        assert_eq!((tracker.live(), tracker.finished()), (0, 6));
+    }
+
+    async fn first_error_aborts_the_siblings<E>()
+    where
+        E: SpawnHandle + Timer + Default + Send + Sync + 'static,
+    {
+        let tracker = &Tracker::default();
+        let start = Instant::now();
+
+        let err = scope(E::default(), |s| async move {
+            for _ in 0..3 {
+                s.spawn(tracker.task::<E, _>(FOREVER, Ok(())));
+            }
+            s.spawn(tracker.task::<E, _>(Duration::from_millis(10), Err("failed")));
+            s.spawn(tracker.task::<E, _>(FOREVER, Err("too late")));
+
+            Ok(())
+        }).await.unwrap_err();
+
+        assert_eq!(err, "failed");
+        // Only the failed task completed, and none of the others outlived the scope.
+        assert_eq!((tracker.live(), tracker.finished()), (0, 1));
+        assert!(start.elapsed() < FOREVER / 2);
+    }
+
+    async fn body_error_aborts_the_tasks<E>()
+    where
+        E: SpawnHandle + Timer + Default + Send + Sync + 'static,
+    {
+        let tracker = &Tracker::default();
+
+        let err = scope(E::default(), |s| async move {
+            for _ in 0..3 {
+                s.spawn(tracker.task::<E, _>(FOREVER, Ok(())));
+            }
+            E::sleep(Duration::from_millis(10)).await;
+
+            Err::<(), _>("body failed")
+        }).await.unwrap_err();
+
+        assert_eq!(err, "body failed");
+        assert_eq!((tracker.live(), tracker.finished()), (0, 0));
+    }
+
+    async fn panic_is_resumed<E>()
+    where
+        E: SpawnHandle + Timer + Default + Send + Sync + 'static,
+    {
+        let tracker = &Tracker::default();
+
+        let result = AssertUnwindSafe(scope(E::default(), |s| async move {
+            s.spawn(tracker.task::<E, Result<(), ()>>(FOREVER, Ok(())));
+            s.spawn(async {
+                E::sleep(Duration::from_millis(10)).await;
+
+                panic!("boom")
+            });
+
+            Ok(())
+        })).catch_unwind().await;
+
+        assert_eq!(*result.unwrap_err().downcast::<&str>().unwrap(), "boom");
+        assert_eq!((tracker.live(), tracker.finished()), (0, 0));
+    }
+
+    async fn dropping_the_scope_aborts_the_tasks<E>()
+    where
+        E: SpawnHandle + Timer + Default + Send + Sync + 'static,
+    {
+        let tracker = &Tracker::default();
+
+        let scoped = scope(E::default(), |s| async move {
+            for _ in 0..3 {
+                s.spawn(tracker.task::<E, Result<(), ()>>(FOREVER, Ok(())));
+            }
+
+            futures::future::pending::<Result<(), ()>>().await
+        });
+        assert!(timeout::<E, _>(Duration::from_millis(20), scoped).await.is_err());
+
+        // The tasks got aborted, but could not be awaited, so they get dropped shortly after.
+        for _ in 0..500 {
+            if tracker.live() == 0 {
+                break;
+            }
+
+            E::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!((tracker.live(), tracker.finished()), (0, 0));
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    fn tokio_run<Fut: Future>(test: Fut) {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(test);
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_waits_for_all_tasks() {
+        tokio_run(waits_for_all_tasks::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_first_error_aborts_the_siblings() {
+        tokio_run(first_error_aborts_the_siblings::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_body_error_aborts_the_tasks() {
+        tokio_run(body_error_aborts_the_tasks::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_panic_is_resumed() {
+        tokio_run(panic_is_resumed::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_dropping_the_scope_aborts_the_tasks() {
+        tokio_run(dropping_the_scope_aborts_the_tasks::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_waits_for_all_tasks() {
+        ::async_std::task::block_on(waits_for_all_tasks::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_first_error_aborts_the_siblings() {
+        ::async_std::task::block_on(first_error_aborts_the_siblings::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_body_error_aborts_the_tasks() {
+        ::async_std::task::block_on(body_error_aborts_the_tasks::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_panic_is_resumed() {
+        ::async_std::task::block_on(panic_is_resumed::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_dropping_the_scope_aborts_the_tasks() {
+        ::async_std::task::block_on(dropping_the_scope_aborts_the_tasks::<crate::task::AsyncStdExecutor>());
+    }
+}
@@ -0,0 +1,260 @@
+use super::*;
+
+use std::panic::AssertUnwindSafe;
+
+use futures::task::{Spawn, LocalSpawn};
+use futures::task::{SpawnError, FutureObj, LocalFutureObj};
+use futures::FutureExt;
+use futures::future::{Abortable, BoxFuture};
+
+use ::smol::{LocalExecutor, Task};
+
+
+
+thread_local! {
+    /// The executor of the tasks spawned with [`LocalSpawn`], driven by [`BlockOn::block_on`].
+    static LOCAL: LocalExecutor<'static> = const { LocalExecutor::new() };
+}
+
+/// An executor for the [`smol`](https://docs.rs/smol) runtime.
+///
+/// Tasks get spawned onto the global executor of `smol`, which runs on a pool of background
+/// threads, configured with the `SMOL_THREADS` environment variable.
+///
+/// `smol` has no global executor for `!Send` futures, so each thread gets its own one for the
+/// tasks spawned with [`LocalSpawn`]. These tasks only make progress while the thread is inside
+/// of [`BlockOn::block_on`] of this executor.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "smol-rt")]
+/// # fn example() {
+/// use std::rc::Rc;
+///
+/// use futures::task::LocalSpawnExt;
+///
+/// use fut_compat::task::{BlockOn, SmolExecutor};
+///
+/// SmolExecutor::default().block_on(async {
+///     let shared = Rc::new(42);
+///
+///     let task = SmolExecutor::default().spawn_local_with_handle(async move { *shared }).unwrap();
+///
+///     assert_eq!(task.await, 42);
+/// });
+/// # }
+/// ```
+#[cfg(feature = "smol-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol-rt")))]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SmolExecutor {}
+
+impl Spawn for SmolExecutor {
+    fn spawn_obj(
+        &self,
+        future: FutureObj<'static, ()>
+    ) -> Result<(), SpawnError> {
+        ::smol::spawn(future).detach();
+
+        Ok(())
+    }
+}
+
+impl LocalSpawn for SmolExecutor {
+    fn spawn_local_obj(
+        &self,
+        future: LocalFutureObj<'static, ()>
+    ) -> Result<(), SpawnError> {
+        LOCAL.try_with(|local| local.spawn(future).detach())
+            .map_err(|_| SpawnError::shutdown())
+    }
+}
+
+impl SpawnHandle for SmolExecutor {
+    fn spawn_handle<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        // smol can only cancel a task by dropping or consuming its handle, so we make the future
+        // itself abortable instead.
+        let (abort, registration) = ::futures::future::AbortHandle::new_pair();
+
+        // Panics are caught so they get returned as an error, like with the other runtimes.
+        let fut = AssertUnwindSafe(fut).catch_unwind();
+
+        let task = ::smol::spawn(Abortable::new(fut, registration));
+        let fut = FutureExt::map(Detached(Some(task)), |result| match result {
+            Ok(Ok(output)) => Ok(output),
+            Ok(Err(payload)) => Err(JoinError::panic(payload)),
+            Err(_) => Err(JoinError::cancelled()),
+        });
+
+        JoinHandle::with_canceller(fut, move || abort.abort())
+    }
+}
+
+impl SpawnBlocking for SmolExecutor {
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let f = move || std::panic::catch_unwind(AssertUnwindSafe(f));
+
+        // Blocking jobs can not be aborted through their handle without consuming it, so aborting
+        // only detaches it.
+        let task = ::smol::unblock(f);
+        let fut = FutureExt::map(Detached(Some(task)), |result| result.map_err(JoinError::panic));
+
+        JoinHandle::new(fut)
+    }
+}
+
+impl BlockOn for SmolExecutor {
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        // Drives the tasks spawned with `LocalSpawn` alongside the future.
+        LOCAL.with(|local| ::smol::block_on(local.run(future)))
+    }
+}
+
+impl Timer for SmolExecutor {
+    type Sleep = BoxFuture<'static, ()>;
+
+    fn sleep(dur: Duration) -> Self::Sleep {
+        Box::pin(::smol::Timer::after(dur).map(|_| ()))
+    }
+
+    fn sleep_until(deadline: Instant) -> Self::Sleep {
+        Box::pin(::smol::Timer::at(deadline).map(|_| ()))
+    }
+}
+
+
+
+/// A [`Task`] which gets detached instead of cancelled when dropped, like the handles of the other
+/// runtimes.
+struct Detached<T>(Option<Task<T>>);
+
+impl<T> Future for Detached<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let task = Pin::into_inner(self).0.as_mut().expect("the task is only taken when dropped");
+
+        Pin::new(task).poll(cx)
+    }
+}
+
+impl<T> Drop for Detached<T> {
+    fn drop(&mut self) {
+        if let Some(task) = self.0.take() {
+            task.detach();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::task::LocalSpawnExt;
+
+    #[test]
+    fn handles_return_the_outputs_of_their_tasks() {
+        let outputs = SmolExecutor::default().block_on(async {
+            let executor = SmolExecutor::default();
+            let handles: Vec<_> = (0..10u64)
+                .map(|i| executor.spawn_handle(async move { i * i }))
+                .collect();
+
+            futures::future::try_join_all(handles).await.unwrap()
+        });
+
+        assert_eq!(outputs, (0..10u64).map(|i| i * i).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn spawn_local_runs_non_send_futures() {
+        let answer = SmolExecutor::default().block_on(async {
+            let shared = std::rc::Rc::new(42);
+
+            SmolExecutor::default().spawn_local_with_handle(async move { *shared }).unwrap().await
+        });
+
+        assert_eq!(answer, 42);
+    }
+
+    #[test]
+    fn blocking_jobs_return_their_output() {
+        let output = SmolExecutor::default().block_on(SmolExecutor::default().spawn_blocking(|| 6 * 7));
+
+        assert_eq!(output.unwrap(), 42);
+    }
+
+    #[test]
+    fn panicking_task_resolves_to_panic_error() {
+        let err = SmolExecutor::default().block_on(async {
+            SmolExecutor::default().spawn_handle(async { panic!("boom") }).await.unwrap_err()
+        });
+
+        assert!(err.is_panic());
+        assert_eq!(*err.try_into_panic().unwrap().downcast::<&str>().unwrap(), "boom");
+    }
+
+    #[test]
+    fn panicking_blocking_job_resolves_to_panic_error() {
+        let err = SmolExecutor::default().block_on(async {
+            SmolExecutor::default().spawn_blocking(|| panic!("boom")).await.unwrap_err()
+        });
+
+        assert!(err.is_panic());
+        assert_eq!(*err.try_into_panic().unwrap().downcast::<&str>().unwrap(), "boom");
+    }
+
+    #[test]
+    fn aborted_task_resolves_to_cancelled_error() {
+        let err = SmolExecutor::default().block_on(async {
+            let handle = SmolExecutor::default().spawn_handle(futures::future::pending::<()>());
+            handle.abort();
+
+            handle.await.unwrap_err()
+        });
+
+        assert!(err.is_cancelled());
+    }
+
+    #[test]
+    fn completed_handle_is_skipped_by_select() {
+        SmolExecutor::default().block_on(async {
+            let mut handle = SmolExecutor::default().spawn_handle(async { 7 });
+
+            // Once the handle resolved, only the `complete` branch is left.
+            let mut results = Vec::new();
+            for _ in 0..3 {
+                futures::select! {
+                    result = handle => results.push(Some(result.unwrap())),
+                    complete => results.push(None),
+                }
+            }
+
+            assert_eq!(results, [Some(7), None, None]);
+            assert!(handle.is_terminated());
+        });
+    }
+
+    #[test]
+    fn sleeps_take_at_least_their_duration() {
+        SmolExecutor::default().block_on(async {
+            let start = Instant::now();
+            SmolExecutor::sleep(Duration::from_millis(50)).await;
+            assert!(start.elapsed() >= Duration::from_millis(50));
+
+            let deadline = Instant::now() + Duration::from_millis(50);
+            SmolExecutor::sleep_until(deadline).await;
+            assert!(Instant::now() >= deadline);
+        });
+    }
+}
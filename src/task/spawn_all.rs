@@ -0,0 +1,309 @@
+use std::future::Future;
+
+use futures::stream::{FuturesUnordered, StreamExt};
+use futures::FutureExt;
+
+use super::{JoinError, SpawnHandle};
+
+
+
+/// Spawns each of `futs` as its own task, with at most `limit` tasks running at a time, and
+/// returns their results in the order of `futs`.
+///
+/// Unlike [`buffer_unordered`](futures::stream::StreamExt::buffer_unordered), the futures run on
+/// the executor, so they run in parallel on multi-threaded runtimes. The next future gets spawned
+/// as soon as a running task completes.
+///
+/// Dropping the returned future aborts the running tasks.
+///
+/// # Panics
+///
+/// Panics if `limit` is zero.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example(paths: Vec<std::path::PathBuf>) {
+/// use fut_compat::task::{spawn_all, TokioExecutor};
+///
+/// let sizes = spawn_all(&TokioExecutor::default(), paths.into_iter().map(|path| async move {
+///     tokio::fs::metadata(path).await.map(|metadata| metadata.len())
+/// }), 8).await;
+/// # }
+/// ```
+pub async fn spawn_all<E, I, F>(executor: &E, futs: I, limit: usize) -> Vec<Result<F::Output, JoinError>>
+where
+    E: SpawnHandle + ?Sized,
+    I: IntoIterator<Item = F>,
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    assert!(limit > 0, "the limit of spawn_all must be non-zero");
+
+    let mut futs = futs.into_iter().enumerate();
+    let mut running = FuturesUnordered::new();
+    let mut results = Vec::new();
+
+    loop {
+        while running.len() < limit {
+            let (index, fut) = match futs.next() {
+                Some(next) => next,
+                None => break,
+            };
+
+            let task = executor.spawn_handle(fut).abort_on_drop();
+            running.push(task.map(move |result| (index, result)));
+            results.push(None);
+        }
+
+        match running.next().await {
+            Some((index, result)) => results[index] = Some(result),
+            None => break,
+        }
+    }
+
+    results.into_iter()
+        .map(|result| result.expect("all tasks completed"))
+        .collect()
+}
+
+/// Like [`spawn_all`], but for futures returning a [`Result`], stopping at the first error.
+///
+/// Returns the outputs in the order of `futs`, or the first error, which may be the
+/// [`JoinError`] of a task which panicked or has been aborted. The remaining tasks get aborted
+/// on error.
+///
+/// # Panics
+///
+/// Panics if `limit` is zero.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example(paths: Vec<std::path::PathBuf>) -> std::io::Result<()> {
+/// use fut_compat::task::{try_spawn_all, TokioExecutor};
+///
+/// let contents = try_spawn_all(&TokioExecutor::default(), paths.into_iter().map(|path| async move {
+///     tokio::fs::read(path).await
+/// }), 8).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn try_spawn_all<E, I, F, T, Er>(executor: &E, futs: I, limit: usize) -> Result<Vec<T>, Er>
+where
+    E: SpawnHandle + ?Sized,
+    I: IntoIterator<Item = F>,
+    F: Future<Output = Result<T, Er>> + Send + 'static,
+    T: Send + 'static,
+    Er: From<JoinError> + Send + 'static,
+{
+    assert!(limit > 0, "the limit of try_spawn_all must be non-zero");
+
+    let mut futs = futs.into_iter().enumerate();
+    let mut running = FuturesUnordered::new();
+    let mut outputs = Vec::new();
+
+    loop {
+        while running.len() < limit {
+            let (index, fut) = match futs.next() {
+                Some(next) => next,
+                None => break,
+            };
+
+            let task = executor.spawn_handle(fut).abort_on_drop();
+            running.push(task.map(move |result| (index, result)));
+            outputs.push(None);
+        }
+
+        // Returning early drops and thereby aborts the running tasks.
+        match running.next().await {
+            Some((index, result)) => outputs[index] = Some(result??),
+            None => break,
+        }
+    }
+
+    Ok(outputs.into_iter()
+        .map(|output| output.expect("all tasks completed"))
+        .collect())
+}
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use crate::task::Timer;
+
+    /// Some CPU-bound work, taking longer for larger `n`.
+    fn work(n: u64) -> u64 {
+        (0..n).fold(0u64, |acc, i| acc.wrapping_add(i.wrapping_mul(i)))
+    }
+
+    /// Sizes of work for which the later ones tend to complete first.
+    const SIZES: [u64; 8] = [3_000_000, 1, 1_000_000, 10, 300_000, 100, 30_000, 1_000];
+
+    /// Counts the tasks running at the same time.
+    #[derive(Clone, Default)]
+    struct Concurrency {
+        current: Arc<AtomicUsize>,
+        max: Arc<AtomicUsize>,
+        dropped: Arc<AtomicUsize>,
+    }
+
+    /// Counts a task as running until dropped.
+    struct Running(Concurrency);
+
+    impl Drop for Running {
+        fn drop(&mut self) {
+            self.0.current.fetch_sub(1, Ordering::SeqCst);
+            self.0.dropped.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    impl Concurrency {
+        /// Returns a task which sleeps for `dur` with the timer `Tm` and then returns `output`.
+        fn task<Tm: Timer, T: Send + 'static>(&self, dur: Duration, output: T) -> impl Future<Output = T> + Send + 'static {
+            let this = self.clone();
+
+            async move {
+                let current = this.current.fetch_add(1, Ordering::SeqCst) + 1;
+                this.max.fetch_max(current, Ordering::SeqCst);
+                let _running = Running(this);
+
+                Tm::sleep(dur).await;
+
+                output
+            }
+        }
+    }
+
+    async fn results_keep_the_input_order<E>()
+    where
+        E: SpawnHandle + Default,
+    {
+        let futs = SIZES.iter().map(|n| async move { work(*n) });
+        let results = spawn_all(&E::default(), futs, 3).await;
+
+        let results: Vec<u64> = results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(results, SIZES.iter().map(|n| work(*n)).collect::<Vec<_>>());
+
+        // A panic only affects the result of its own task.
+        let futs = (0..4u64).map(|i| async move {
+            assert_ne!(i, 2, "boom");
+
+            i
+        });
+        let results = spawn_all(&E::default(), futs, 2).await;
+
+        assert!(results[2].as_ref().unwrap_err().is_panic());
+        assert_eq!([&results[0], &results[1], &results[3]].map(|result| *result.as_ref().unwrap()), [0, 1, 3]);
+    }
+
+    async fn limit_bounds_the_running_tasks<E>()
+    where
+        E: SpawnHandle + Timer + Default,
+    {
+        let concurrency = Concurrency::default();
+
+        let futs = (0..10).map(|i| concurrency.task::<E, _>(Duration::from_millis(10 + 5 * (i % 3)), i));
+        let results = spawn_all(&E::default(), futs, 3).await;
+
+        assert_eq!(results.into_iter().map(Result::unwrap).collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        assert_eq!(concurrency.max.load(Ordering::SeqCst), 3);
+    }
+
+    async fn try_spawn_all_stops_at_the_first_error<E>()
+    where
+        E: SpawnHandle + Timer + Default,
+    {
+        let futs = SIZES.iter().map(|n| async move { Ok::<_, std::io::Error>(work(*n)) });
+        let outputs = try_spawn_all(&E::default(), futs, 3).await.unwrap();
+        assert_eq!(outputs, SIZES.iter().map(|n| work(*n)).collect::<Vec<_>>());
+
+        let concurrency = Concurrency::default();
+
+        let mut futs: Vec<_> = (0..5)
+            .map(|_| concurrency.task::<E, _>(Duration::from_secs(60), Ok(())).boxed())
+            .collect();
+        futs.insert(1, concurrency.task::<E, _>(Duration::from_millis(10), Err(std::io::Error::other("failed"))).boxed());
+
+        let err = try_spawn_all(&E::default(), futs, 3).await.unwrap_err();
+        assert_eq!(err.to_string(), "failed");
+
+        // The running tasks got aborted, and the remaining ones never started.
+        for _ in 0..500 {
+            if concurrency.current.load(Ordering::SeqCst) == 0 {
+                break;
+            }
+
+            E::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(concurrency.current.load(Ordering::SeqCst), 0);
+        assert_eq!(concurrency.dropped.load(Ordering::SeqCst), 3);
+
+        // Panics are returned as errors as well.
+        let futs = (0..3).map(|i| async move {
+            assert_ne!(i, 1, "boom");
+
+            Ok::<_, std::io::Error>(i)
+        });
+        let err = try_spawn_all(&E::default(), futs, 3).await.unwrap_err();
+        assert!(err.to_string().contains("boom"));
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    fn tokio_run<Fut: Future>(test: Fut) {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(test);
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_results_keep_the_input_order() {
+        tokio_run(results_keep_the_input_order::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_limit_bounds_the_running_tasks() {
+        tokio_run(limit_bounds_the_running_tasks::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_try_spawn_all_stops_at_the_first_error() {
+        tokio_run(try_spawn_all_stops_at_the_first_error::<crate::task::TokioExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_results_keep_the_input_order() {
+        ::async_std::task::block_on(results_keep_the_input_order::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_limit_bounds_the_running_tasks() {
+        ::async_std::task::block_on(limit_bounds_the_running_tasks::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_try_spawn_all_stops_at_the_first_error() {
+        ::async_std::task::block_on(try_spawn_all_stops_at_the_first_error::<crate::task::AsyncStdExecutor>());
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    #[should_panic(expected = "must be non-zero")]
+    fn zero_limit_panics() {
+        let futs = std::iter::empty::<futures::future::Ready<()>>();
+
+        futures::executor::block_on(spawn_all(&crate::task::AsyncStdExecutor::default(), futs, 0));
+    }
+}
@@ -0,0 +1,363 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use super::Timer;
+
+
+
+/// The error returned by [`timeout`] if the future did not complete in time.
+///
+/// It converts into an [`std::io::Error`] of kind [`TimedOut`](std::io::ErrorKind::TimedOut), so
+/// it can be propagated with `?` from functions returning [`std::io::Result`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Elapsed {
+    _priv: (),
+}
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "deadline has elapsed")
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+impl From<Elapsed> for std::io::Error {
+    fn from(err: Elapsed) -> Self {
+        std::io::Error::new(std::io::ErrorKind::TimedOut, err)
+    }
+}
+
+/// Requires `future` to complete within `dur`, measured with the timer `Tm`.
+///
+/// If the duration elapses first, the future gets dropped right away and [`Elapsed`] is
+/// returned. See [`timeout_at`] for a deadline shared by several futures.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use std::time::Duration;
+///
+/// use fut_compat::task::{timeout, TokioExecutor};
+///
+/// let connect = tokio::net::TcpStream::connect("127.0.0.1:8080");
+/// let stream = timeout::<TokioExecutor, _>(Duration::from_secs(5), connect).await??;
+/// # Ok(())
+/// # }
+/// ```
+pub fn timeout<Tm, F>(dur: Duration, future: F) -> Timeout<F, Tm>
+where
+    Tm: Timer,
+    F: Future,
+{
+    Timeout {
+        future: Some(future),
+        sleep: Tm::sleep(dur),
+    }
+}
+
+/// Requires `future` to complete before `deadline`, measured with the timer `Tm`.
+///
+/// If the deadline is reached first, the future gets dropped right away and [`Elapsed`] is
+/// returned. The future always gets polled at least once, so it may still complete if the
+/// deadline has already passed when the returned future is polled for the first time.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() -> std::io::Result<()> {
+/// use std::time::Duration;
+///
+/// use fut_compat::task::{timeout_at, Deadline, TokioExecutor};
+///
+/// // The whole request may take 5 seconds, no matter how many steps it takes.
+/// let deadline = Deadline::after::<TokioExecutor>(Duration::from_secs(5));
+///
+/// let stream = timeout_at::<TokioExecutor, _>(deadline.instant(), tokio::net::TcpStream::connect("127.0.0.1:8080")).await??;
+/// timeout_at::<TokioExecutor, _>(deadline.instant(), stream.writable()).await??;
+/// # Ok(())
+/// # }
+/// ```
+pub fn timeout_at<Tm, F>(deadline: Instant, future: F) -> Timeout<F, Tm>
+where
+    Tm: Timer,
+    F: Future,
+{
+    Timeout {
+        future: Some(future),
+        sleep: Tm::sleep_until(deadline),
+    }
+}
+
+/// The future returned by [`timeout`] and [`timeout_at`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Timeout<F, Tm: Timer> {
+    future: Option<F>,
+    sleep: Tm::Sleep,
+}
+
+impl<F, Tm: Timer> Timeout<F, Tm> {
+    /// Projects the pinned future onto its pinned fields.
+    fn project(self: Pin<&mut Self>) -> (Pin<&mut Option<F>>, Pin<&mut Tm::Sleep>) {
+        // SAFETY: Both fields are structurally pinned. They are never moved out of a pinned
+        // `Timeout`, which neither implements `Drop` nor `Unpin` unless both fields do. The
+        // future only gets dropped in place.
+        unsafe {
+            let this = self.get_unchecked_mut();
+
+            (Pin::new_unchecked(&mut this.future), Pin::new_unchecked(&mut this.sleep))
+        }
+    }
+}
+
+impl<F, Tm> Future for Timeout<F, Tm>
+where
+    F: Future,
+    Tm: Timer,
+{
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let (mut future, sleep) = self.project();
+
+        let inner = future.as_mut().as_pin_mut().expect("Timeout polled after completion");
+
+        if let Poll::Ready(output) = inner.poll(cx) {
+            future.set(None);
+
+            return Poll::Ready(Ok(output));
+        }
+
+        match sleep.poll(cx) {
+            Poll::Ready(()) => {
+                future.set(None);
+
+                Poll::Ready(Err(Elapsed { _priv: () }))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<F: fmt::Debug, Tm: Timer> fmt::Debug for Timeout<F, Tm> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timeout")
+            .field("future", &self.future)
+            .finish_non_exhaustive()
+    }
+}
+
+
+
+/// An instant by which some work has to be done, e.g. handling a request.
+///
+/// Passing the deadline down instead of a duration keeps the time budget of nested operations
+/// within the budget of the whole, see [`timeout_at`]. The earlier one of two deadlines is
+/// returned by [`Ord::min`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deadline {
+    instant: Instant,
+}
+
+impl Deadline {
+    /// Creates a deadline at `instant`.
+    pub fn at(instant: Instant) -> Self {
+        Self {
+            instant,
+        }
+    }
+
+    /// Creates a deadline `dur` from now, according to the timer `Tm`.
+    pub fn after<Tm: Timer>(dur: Duration) -> Self {
+        Self::at(Tm::now() + dur)
+    }
+
+    /// Returns the instant of the deadline.
+    pub fn instant(&self) -> Instant {
+        self.instant
+    }
+
+    /// Returns the time left until the deadline, or zero if it has passed, according to the timer
+    /// `Tm`.
+    pub fn remaining<Tm: Timer>(&self) -> Duration {
+        self.instant.saturating_duration_since(Tm::now())
+    }
+
+    /// Returns `true` if the deadline has passed, according to the timer `Tm`.
+    pub fn is_expired<Tm: Timer>(&self) -> bool {
+        Tm::now() >= self.instant
+    }
+
+    /// Returns this deadline, but no later than `max` from now, according to the timer `Tm`.
+    ///
+    /// This limits the time a single step may take out of the whole budget.
+    pub fn clamp_to<Tm: Timer>(self, max: Duration) -> Self {
+        self.min(Self::after::<Tm>(max))
+    }
+}
+
+impl From<Instant> for Deadline {
+    fn from(instant: Instant) -> Self {
+        Self::at(instant)
+    }
+}
+
+impl From<Deadline> for Instant {
+    fn from(deadline: Deadline) -> Self {
+        deadline.instant
+    }
+}
+
+
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::*;
+
+    use futures::channel::oneshot;
+    use futures::FutureExt;
+
+    use crate::task::MockTimer;
+
+    #[test]
+    fn timeout_elapses_once_the_clock_reaches_it() {
+        let mut slow = Box::pin(timeout::<MockTimer, _>(Duration::from_secs(5), futures::future::pending::<()>()));
+
+        assert!((&mut slow).now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_secs(4));
+        assert!((&mut slow).now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_secs(1));
+        assert_eq!((&mut slow).now_or_never(), Some(Err(Elapsed { _priv: () })));
+        assert_eq!(MockTimer::pending_sleep_count(), 0);
+    }
+
+    #[test]
+    fn output_before_the_timeout_is_returned() {
+        let (sender, receiver) = oneshot::channel();
+        let mut fast = Box::pin(timeout::<MockTimer, _>(Duration::from_secs(5), receiver));
+
+        assert!((&mut fast).now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_secs(4));
+        sender.send(42).unwrap();
+
+        assert_eq!((&mut fast).now_or_never(), Some(Ok(Ok(42))));
+    }
+
+    #[test]
+    fn future_is_dropped_on_timeout() {
+        let (sender, receiver) = oneshot::channel::<()>();
+        let mut slow = Box::pin(timeout::<MockTimer, _>(Duration::from_secs(5), async move {
+            let _sender = sender;
+
+            futures::future::pending::<()>().await
+        }));
+
+        assert!((&mut slow).now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_secs(5));
+        assert!((&mut slow).now_or_never().unwrap().is_err());
+
+        // The timeout itself is still alive, but the sender is gone already.
+        assert_eq!(receiver.now_or_never(), Some(Err(oneshot::Canceled)));
+    }
+
+    #[test]
+    fn nested_timeouts_elapse_independently() {
+        let pending = futures::future::pending::<()>;
+
+        let mut inner_first = Box::pin(timeout::<MockTimer, _>(
+            Duration::from_secs(10),
+            timeout::<MockTimer, _>(Duration::from_secs(3), pending()),
+        ));
+        let mut outer_first = Box::pin(timeout::<MockTimer, _>(
+            Duration::from_secs(3),
+            timeout::<MockTimer, _>(Duration::from_secs(10), pending()),
+        ));
+
+        assert!((&mut inner_first).now_or_never().is_none());
+        assert!((&mut outer_first).now_or_never().is_none());
+
+        MockTimer::advance(Duration::from_secs(3));
+        assert_eq!((&mut inner_first).now_or_never(), Some(Ok(Err(Elapsed { _priv: () }))));
+        assert_eq!((&mut outer_first).now_or_never(), Some(Err(Elapsed { _priv: () })));
+    }
+
+    #[test]
+    fn elapsed_converts_into_timed_out() {
+        let err = std::io::Error::from(Elapsed { _priv: () });
+
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn elapsed_propagates_with_the_question_mark() {
+        async fn read<F: Future<Output = u32>>(deadline: Instant, future: F) -> std::io::Result<u32> {
+            let value = timeout_at::<MockTimer, _>(deadline, future).await?;
+
+            Ok(value + 1)
+        }
+
+        let deadline = MockTimer::now();
+        MockTimer::advance(Duration::from_secs(1));
+
+        assert_eq!(read(deadline, async { 41 }).now_or_never().unwrap().unwrap(), 42);
+
+        let err = read(deadline, futures::future::pending()).now_or_never().unwrap().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn expired_deadline_still_polls_the_future_once() {
+        let deadline = MockTimer::now();
+        MockTimer::advance(Duration::from_secs(1));
+
+        let ready = timeout_at::<MockTimer, _>(deadline, async { 42 });
+        assert_eq!(ready.now_or_never(), Some(Ok(42)));
+
+        let polled = std::cell::Cell::new(0);
+        let pending = timeout_at::<MockTimer, _>(deadline, futures::future::poll_fn(|_| {
+            polled.set(polled.get() + 1);
+
+            Poll::<()>::Pending
+        }));
+        assert_eq!(pending.now_or_never(), Some(Err(Elapsed { _priv: () })));
+        assert_eq!(polled.get(), 1);
+    }
+
+    #[test]
+    fn timeout_at_elapses_at_the_deadline() {
+        let deadline = Deadline::after::<MockTimer>(Duration::from_secs(10));
+        let mut slow = Box::pin(timeout_at::<MockTimer, _>(deadline.instant(), futures::future::pending::<()>()));
+
+        MockTimer::advance(Duration::from_secs(6));
+        assert!((&mut slow).now_or_never().is_none());
+        assert_eq!(deadline.remaining::<MockTimer>(), Duration::from_secs(4));
+        assert!(!deadline.is_expired::<MockTimer>());
+
+        MockTimer::advance(Duration::from_secs(4));
+        assert!(deadline.is_expired::<MockTimer>());
+        assert_eq!((&mut slow).now_or_never(), Some(Err(Elapsed { _priv: () })));
+    }
+
+    #[test]
+    fn deadlines_share_their_budget() {
+        let deadline = Deadline::after::<MockTimer>(Duration::from_secs(10));
+
+        // A step limited to 3 seconds gets the earlier deadline, a step limited to 30 seconds the
+        // one of the whole.
+        assert_eq!(deadline.clamp_to::<MockTimer>(Duration::from_secs(3)).remaining::<MockTimer>(), Duration::from_secs(3));
+        assert_eq!(deadline.clamp_to::<MockTimer>(Duration::from_secs(30)), deadline);
+
+        MockTimer::advance(Duration::from_secs(20));
+        assert_eq!(deadline.remaining::<MockTimer>(), Duration::ZERO);
+    }
+}
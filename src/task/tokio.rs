@@ -1,5 +1,8 @@
 use super::*;
 
+use std::cell::Cell;
+use std::sync::OnceLock;
+
 use futures::task::{Spawn, LocalSpawn};
 use futures::task::{SpawnError, FutureObj, LocalFutureObj};
 use futures::FutureExt;
@@ -7,22 +10,97 @@ use futures::FutureExt;
 
 
 /// An executor for the [`tokio`](https://docs.rs/tokio) runtime.
+///
+/// It spawns onto the runtime whose [`Handle`](::tokio::runtime::Handle) it holds, so it can be
+/// moved to and used from threads outside of the runtime, e.g. ones started with
+/// [`std::thread::spawn`]. [`Default`] captures the handle of the current runtime, if any, and
+/// [`from_handle`](Self::from_handle) takes an explicit one.
+///
+/// Without a handle, the runtime of the calling context gets used. If there is none,
+/// [`Spawn`] returns a [`SpawnError`] and the [`JoinHandle`]s of [`SpawnHandle`] and
+/// [`SpawnBlocking`] resolve to a cancelled [`JoinError`], instead of panicking like tokio does.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() {
+/// use fut_compat::task::{SpawnHandle, TokioExecutor};
+///
+/// let executor = TokioExecutor::default();
+///
+/// let answer = std::thread::spawn(move || {
+///     executor.spawn_handle(async { 42 })
+/// }).join().unwrap();
+///
+/// assert_eq!(answer.await.unwrap(), 42);
+/// # }
+/// ```
 #[cfg(feature = "tokio-rt")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
-#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub struct TokioExecutor {}
+#[derive(Clone, Debug)]
+pub struct TokioExecutor {
+    handle: Option<::tokio::runtime::Handle>,
+}
+
+impl Default for TokioExecutor {
+    /// Creates a new instance with the handle of the current runtime, or without one outside of
+    /// the context of a runtime.
+    fn default() -> Self {
+        Self {
+            handle: ::tokio::runtime::Handle::try_current().ok(),
+        }
+    }
+}
+
+impl TokioExecutor {
+    /// Creates a new instance which spawns onto the runtime of `handle`.
+    pub fn from_handle(handle: ::tokio::runtime::Handle) -> Self {
+        Self {
+            handle: Some(handle),
+        }
+    }
+
+    /// Get a reference to the wrapped runtime handle, if any.
+    pub fn handle(&self) -> Option<&::tokio::runtime::Handle> {
+        self.handle.as_ref()
+    }
+
+    /// Returns the handle to spawn onto, falling back to the one of the current runtime.
+    fn current_handle(&self) -> Option<::tokio::runtime::Handle> {
+        match &self.handle {
+            Some(handle) => Some(handle.clone()),
+            None => ::tokio::runtime::Handle::try_current().ok(),
+        }
+    }
+}
 
 impl Spawn for TokioExecutor {
     fn spawn_obj(
         &self,
         future: FutureObj<'static, ()>
     ) -> Result<(), SpawnError> {
-        ::tokio::task::spawn(future);
+        let handle = self.current_handle().ok_or_else(SpawnError::shutdown)?;
+
+        handle.spawn(future);
 
         Ok(())
     }
+
+    fn status(&self) -> Result<(), SpawnError> {
+        match self.current_handle() {
+            Some(_) => Ok(()),
+            None => Err(SpawnError::shutdown()),
+        }
+    }
 }
 
+/// Spawns the future with [`tokio::task::spawn_local`].
+///
+/// # Panics
+///
+/// Panics unless called from within a [`LocalSet`](::tokio::task::LocalSet). Use
+/// [`TokioLocalExecutor`] to get one.
 impl LocalSpawn for TokioExecutor {
     fn spawn_local_obj(
         &self,
@@ -34,19 +112,488 @@ impl LocalSpawn for TokioExecutor {
     }
 }
 
+/// An executor for `!Send` futures on the [`tokio`](https://docs.rs/tokio) runtime.
+///
+/// It owns a [`LocalSet`](::tokio::task::LocalSet), onto which [`LocalSpawn`] spawns the futures.
+/// They only make progress while the set is driven by [`run`](Self::run),
+/// [`run_until`](Self::run_until) or [`block_on`](Self::block_on), which must happen within the
+/// context of a tokio runtime, except for `block_on`.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # fn example() {
+/// use std::rc::Rc;
+///
+/// use futures::task::LocalSpawnExt;
+/// use fut_compat::task::TokioLocalExecutor;
+///
+/// let executor = TokioLocalExecutor::new();
+///
+/// let shared = Rc::new(42);
+/// let handle = executor.spawn_local_with_handle(async move { *shared }).unwrap();
+///
+/// let answer = executor.block_on(handle);
+/// assert_eq!(answer, 42);
+/// # }
+/// ```
+#[cfg(feature = "tokio-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
+#[derive(Default, Debug)]
+pub struct TokioLocalExecutor {
+    local: ::tokio::task::LocalSet,
+}
+
+impl TokioLocalExecutor {
+    /// Creates a new executor with an empty set of tasks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drives the spawned futures until all of them completed.
+    pub async fn run(self) {
+        self.local.await
+    }
+
+    /// Drives the spawned futures until `future` completed, and returns its output.
+    pub async fn run_until<F: Future>(&self, future: F) -> F::Output {
+        self.local.run_until(future).await
+    }
+
+    /// Blocks the current thread while driving the spawned futures until `future` completed, and
+    /// returns its output.
+    ///
+    /// The runtime gets chosen like with [`TokioExecutor`]'s implementation of [`BlockOn`] for
+    /// [`TokioExecutor::default`].
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`TokioExecutor::block_on`](BlockOn::block_on).
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        BlockOn::block_on(&TokioExecutor::default(), self.local.run_until(future))
+    }
+}
+
+impl LocalSpawn for TokioLocalExecutor {
+    fn spawn_local_obj(
+        &self,
+        future: LocalFutureObj<'static, ()>
+    ) -> Result<(), SpawnError> {
+        self.local.spawn_local(future);
+
+        Ok(())
+    }
+}
+
+impl SpawnHandle for TokioExecutor {
+    fn spawn_handle<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match self.current_handle() {
+            Some(handle) => join_handle(handle.spawn(fut)),
+            None => no_runtime(),
+        }
+    }
+
+    #[cfg(all(tokio_unstable, feature = "tracing"))]
+    fn spawn_handle_with_name<F>(&self, name: &str, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let handle = match self.current_handle() {
+            Some(handle) => handle,
+            None => return no_runtime(),
+        };
+
+        let handle = ::tokio::task::Builder::new()
+            .name(name)
+            .spawn_on(fut, &handle)
+            .expect("failed to spawn the task");
+
+        join_handle(handle)
+    }
+}
+
+impl TokioExecutor {
+    /// Runs `f` on the blocking thread pool of the runtime.
+    #[deprecated(note = "use `SpawnBlocking::spawn_blocking` on an executor value, e.g. `TokioExecutor::default().spawn_blocking(f)`")]
+    pub fn spawn_blocking<F, T>(f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        SpawnBlocking::spawn_blocking(&Self::default(), f)
+    }
+}
+
 impl SpawnBlocking for TokioExecutor {
-    fn spawn_blocking<F, T>(f: F) -> JoinHandle<T>
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let handle = match self.current_handle() {
+            Some(handle) => handle,
+            None => return no_runtime(),
+        };
+
+        join_handle(handle.spawn_blocking(move || {
+            let _guard = BlockingJobGuard::enter();
+
+            f()
+        }))
+    }
+
+    #[cfg(all(tokio_unstable, feature = "tracing"))]
+    fn spawn_blocking_with_name<F, T>(&self, name: &str, f: F) -> JoinHandle<T>
     where
         F: FnOnce() -> T + Send + 'static,
         T: Send + 'static,
     {
-        let fut = ::tokio::task::spawn_blocking::<F, T>(f);
-        let fut = FutureExt::map(fut, |result| result.map_err(|err| {
-            let box_err: Box<dyn Error> = Box::new(err);
+        let handle = match self.current_handle() {
+            Some(handle) => handle,
+            None => return no_runtime(),
+        };
+
+        let handle = ::tokio::task::Builder::new()
+            .name(name)
+            .spawn_blocking_on(move || {
+                let _guard = BlockingJobGuard::enter();
+
+                f()
+            }, &handle)
+            .expect("failed to spawn the blocking job");
+
+        join_handle(handle)
+    }
+}
+
+/// Wraps the join handle of tokio into ours, which aborts the task through it.
+fn join_handle<T: Send + 'static>(handle: ::tokio::task::JoinHandle<T>) -> JoinHandle<T> {
+    let abort = handle.abort_handle();
+
+    let fut = FutureExt::map(handle, |result| result.map_err(join_error));
+
+    JoinHandle::with_canceller(fut, move || abort.abort())
+}
+
+/// Returns a join handle for a task which could not be spawned, as there is no runtime.
+fn no_runtime<T: Send + 'static>() -> JoinHandle<T> {
+    JoinHandle::new(futures::future::ready(Err(JoinError::cancelled())))
+}
+
+/// Converts the join error of tokio into ours.
+fn join_error(err: ::tokio::task::JoinError) -> JoinError {
+    match err.try_into_panic() {
+        Ok(payload) => JoinError::panic(payload),
+        Err(_) => JoinError::cancelled(),
+    }
+}
+
+thread_local! {
+    /// Set while a job spawned with `SpawnBlocking` runs on the current thread.
+    static IN_BLOCKING_JOB: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the current thread as running a blocking job until dropped.
+struct BlockingJobGuard {
+    prev: bool,
+}
+
+impl BlockingJobGuard {
+    fn enter() -> Self {
+        Self {
+            prev: IN_BLOCKING_JOB.with(|flag| flag.replace(true)),
+        }
+    }
+}
+
+impl Drop for BlockingJobGuard {
+    fn drop(&mut self) {
+        IN_BLOCKING_JOB.with(|flag| flag.set(self.prev));
+    }
+}
+
+/// Returns the runtime used by [`BlockOn`] outside of the context of a runtime.
+fn fallback_runtime() -> &'static ::tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<::tokio::runtime::Runtime> = OnceLock::new();
+
+    RUNTIME.get_or_init(|| {
+        ::tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("fut-compat-block-on")
+            .build()
+            .expect("failed to build the tokio runtime for BlockOn")
+    })
+}
+
+impl BlockOn for TokioExecutor {
+    /// Blocks on `future` with the handle held by this executor, falling back to the one of the
+    /// current runtime.
+    ///
+    /// Without either of them, a multi-threaded runtime gets created on first use and shared by
+    /// all later calls, so tasks spawned by `future` keep running afterwards. The held handle of a
+    /// `current_thread` runtime only drives its io and timers while the runtime itself is driven,
+    /// e.g. by [`Runtime::block_on`](::tokio::runtime::Runtime::block_on) on another thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within a task of a `current_thread` runtime, see
+    /// [`try_block_on`](BlockOn::try_block_on).
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        match self.try_block_on(future) {
+            Ok(output) => output,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Blocks on `future` like [`block_on`](BlockOn::block_on), unless the current thread
+    /// belongs to a `current_thread` runtime.
+    ///
+    /// Jobs spawned with [`SpawnBlocking`] are allowed to block on any runtime. On other threads
+    /// of a `current_thread` runtime this returns an error, as tokio does not tell its own thread
+    /// apart from the ones of its blocking pool.
+    fn try_block_on<F: Future>(&self, future: F) -> Result<F::Output, BlockOnError> {
+        let current = ::tokio::runtime::Handle::try_current().ok();
+
+        // Within the context of a runtime, its flavor decides whether the thread may block.
+        let in_place = match current.as_ref().map(|handle| handle.runtime_flavor()) {
+            None => false,
+            Some(::tokio::runtime::RuntimeFlavor::MultiThread) => true,
+            Some(_) if IN_BLOCKING_JOB.with(|flag| flag.get()) => false,
+            Some(_) => return Err(BlockOnError::new()),
+        };
+
+        let handle = match self.handle.clone().or(current) {
+            Some(handle) => handle,
+            None => return Ok(fallback_runtime().block_on(future)),
+        };
+
+        if in_place {
+            Ok(::tokio::task::block_in_place(|| handle.block_on(future)))
+        } else {
+            Ok(handle.block_on(future))
+        }
+    }
+}
+
+impl Timer for TokioExecutor {
+    type Sleep = ::tokio::time::Sleep;
+
+    fn sleep(dur: Duration) -> Self::Sleep {
+        ::tokio::time::sleep(dur)
+    }
+
+    fn sleep_until(deadline: Instant) -> Self::Sleep {
+        ::tokio::time::sleep_until(deadline.into())
+    }
+
+    fn reset(sleep: Pin<&mut Self::Sleep>, deadline: Instant) {
+        sleep.reset(deadline.into())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_on_uses_the_held_handle() {
+        let runtime = ::tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("held-runtime")
+            .enable_all()
+            .build()
+            .unwrap();
+        let executor = TokioExecutor::from_handle(runtime.handle().clone());
+
+        // Called outside of any runtime, so only the held handle can provide the context.
+        let name = std::thread::spawn(move || {
+            executor.block_on(async {
+                ::tokio::time::sleep(Duration::from_millis(1)).await;
+
+                ::tokio::spawn(async { std::thread::current().name().map(String::from) }).await.unwrap()
+            })
+        }).join().unwrap();
+
+        assert_eq!(name.as_deref(), Some("held-runtime"));
+    }
+
+    #[test]
+    fn captured_handle_spawns_from_plain_threads() {
+        use futures::task::SpawnExt;
+
+        let runtime = ::tokio::runtime::Runtime::new().unwrap();
+        let executor = runtime.block_on(async { TokioExecutor::default() });
+        assert!(executor.handle().is_some());
+
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        let (task, job) = std::thread::spawn(move || {
+            executor.spawn(async move { sender.send(1).unwrap() }).unwrap();
+
+            (executor.spawn_handle(async { 2 }), executor.spawn_blocking(|| 3))
+        }).join().unwrap();
+
+        let outputs = runtime.block_on(async { (receiver.await.unwrap(), task.await.unwrap(), job.await.unwrap()) });
+        assert_eq!(outputs, (1, 2, 3));
+    }
+
+    #[test]
+    fn missing_runtime_fails_instead_of_panicking() {
+        use futures::task::SpawnExt;
+
+        let executor = TokioExecutor::default();
+        assert!(executor.handle().is_none());
+
+        assert!(executor.spawn(async {}).unwrap_err().is_shutdown());
+
+        let task = executor.spawn_handle(async { 2 });
+        let job = executor.spawn_blocking(|| 3);
+        assert!(futures::executor::block_on(task).unwrap_err().is_cancelled());
+        assert!(futures::executor::block_on(job).unwrap_err().is_cancelled());
+    }
+
+    #[test]
+    fn try_block_on_refuses_current_thread_runtimes() {
+        let runtime = ::tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        let result = runtime.block_on(async { TokioExecutor::default().try_block_on(async { 42 }) });
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn local_executor_runs_non_send_futures() {
+        use futures::task::LocalSpawnExt;
+
+        let executor = TokioLocalExecutor::new();
+        let counter = std::rc::Rc::new(Cell::new(0));
+
+        for _ in 0..3 {
+            let counter = counter.clone();
+            executor.spawn_local(async move {
+                ::tokio::task::yield_now().await;
+                counter.set(counter.get() + 1);
+            }).unwrap();
+        }
+
+        // Blocks without a surrounding runtime.
+        let shared = std::rc::Rc::new(42);
+        let handle = executor.spawn_local_with_handle(async move { *shared }).unwrap();
+        assert_eq!(executor.block_on(handle), 42);
+
+        // `run` drives the rest until all of them completed.
+        ::tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(executor.run());
+        assert_eq!(counter.get(), 3);
+    }
+
+    #[test]
+    fn handles_return_the_outputs_of_their_tasks() {
+        let outputs = ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let executor = TokioExecutor::default();
+            let handles: Vec<_> = (0..10u64)
+                .map(|i| executor.spawn_handle(async move { i * i }))
+                .collect();
+
+            futures::future::try_join_all(handles).await.unwrap()
+        });
+
+        assert_eq!(outputs, (0..10u64).map(|i| i * i).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn panicking_task_resolves_to_panic_error() {
+        let runtime = ::tokio::runtime::Runtime::new().unwrap();
+
+        let err = runtime.block_on(async {
+            TokioExecutor::default().spawn_handle(async { panic!("boom") }).await.unwrap_err()
+        });
+
+        assert!(err.is_panic());
+        assert_eq!(*err.try_into_panic().unwrap().downcast::<&str>().unwrap(), "boom");
+    }
+
+    #[test]
+    fn panicking_blocking_job_resolves_to_panic_error() {
+        let runtime = ::tokio::runtime::Runtime::new().unwrap();
+
+        let err = runtime.block_on(async {
+            TokioExecutor::default().spawn_blocking(|| panic!("boom")).await.unwrap_err()
+        });
+
+        assert!(err.is_panic());
+        assert_eq!(*err.try_into_panic().unwrap().downcast::<&str>().unwrap(), "boom");
+    }
+
+    #[test]
+    fn aborted_task_resolves_to_cancelled_error() {
+        let runtime = ::tokio::runtime::Runtime::new().unwrap();
+
+        let err = runtime.block_on(async {
+            let handle = TokioExecutor::default().spawn_handle(futures::future::pending::<()>());
+            handle.abort();
+
+            handle.await.unwrap_err()
+        });
+
+        assert!(err.is_cancelled());
+    }
+
+    #[test]
+    fn completed_handle_is_skipped_by_select() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut handle = TokioExecutor::default().spawn_handle(async { 7 });
+
+            // Once the handle resolved, only the `complete` branch is left.
+            let mut results = Vec::new();
+            for _ in 0..3 {
+                futures::select! {
+                    result = handle => results.push(Some(result.unwrap())),
+                    complete => results.push(None),
+                }
+            }
+
+            assert_eq!(results, [Some(7), None, None]);
+            assert!(handle.is_terminated());
+        });
+    }
+
+    #[test]
+    fn sleeps_take_at_least_their_duration() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let start = Instant::now();
+            TokioExecutor::sleep(Duration::from_millis(50)).await;
+            assert!(start.elapsed() >= Duration::from_millis(50));
+
+            let deadline = Instant::now() + Duration::from_millis(50);
+            TokioExecutor::sleep_until(deadline).await;
+            assert!(Instant::now() >= deadline);
+        });
+    }
+
+    #[test]
+    fn reset_moves_the_deadline_in_place() {
+        ::tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut sleep = Box::pin(TokioExecutor::sleep(Duration::from_secs(60)));
+
+            let deadline = Instant::now() + Duration::from_millis(50);
+            TokioExecutor::reset(sleep.as_mut(), deadline);
+            sleep.as_mut().await;
 
-            box_err
-        }));
+            let now = Instant::now();
+            assert!(now >= deadline && now < deadline + Duration::from_secs(30));
 
-        JoinHandle::new(fut)
+            // A completed sleep waits again once reset.
+            let deadline = Instant::now() + Duration::from_millis(50);
+            TokioExecutor::reset(sleep.as_mut(), deadline);
+            sleep.await;
+            assert!(Instant::now() >= deadline);
+        });
     }
 }
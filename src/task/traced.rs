@@ -0,0 +1,200 @@
+use futures::task::{FutureObj, SpawnError};
+
+use tracing::Instrument;
+
+use super::*;
+use super::named::next_task_id;
+
+
+
+/// Wraps an executor and runs every task and blocking job spawned through it within a
+/// [`tracing`](https://docs.rs/tracing) span.
+///
+/// The spans are the same as the ones of [`SpawnNamedExt`]: they are at the `INFO` level with the
+/// target `fut_compat::task`, named `task` for async tasks and `blocking_task` for blocking jobs.
+/// They carry the following fields:
+///
+/// - `task.id`: an id generated for the task, unique within the process.
+/// - `task.name`: the name passed to [`spawn_handle_with_name`](SpawnHandle::spawn_handle_with_name)
+///   or [`spawn_blocking_with_name`](SpawnBlocking::spawn_blocking_with_name), if any.
+/// - `task.queue_time_us`: for blocking jobs, the microseconds between spawning the job and it
+///   starting to run, e.g. while waiting for a free thread of the blocking pool.
+///
+/// The spans get created when spawning, so they are children of the span the task got spawned
+/// from. Unlike [`SpawnNamedExt`] no code has to be changed at the call sites, the executor
+/// handed to them just has to be wrapped.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-rt")]
+/// # async fn example() {
+/// use fut_compat::task::{SpawnBlocking, TokioExecutor, TracedExecutor};
+///
+/// let executor = TracedExecutor::new(TokioExecutor::default());
+///
+/// executor.spawn_blocking(|| {
+///     tracing::info!("recorded within the `blocking_task` span");
+/// }).await.unwrap();
+/// # }
+/// ```
+#[derive(Default, Clone, Debug)]
+pub struct TracedExecutor<E> {
+    inner: E,
+}
+
+impl<E> TracedExecutor<E> {
+    /// Creates a new instance by wrapping `executor`.
+    pub fn new(executor: E) -> Self {
+        Self {
+            inner: executor,
+        }
+    }
+
+    /// Get a reference to the wrapped executor.
+    pub fn get_ref(&self) -> &E {
+        &self.inner
+    }
+
+    /// Consumes the `TracedExecutor` object and returns the wrapped executor.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+}
+
+/// Returns the span of an async task, optionally carrying `name`.
+fn task_span(name: Option<&str>) -> tracing::Span {
+    tracing::info_span!(target: "fut_compat::task", "task", task.id = next_task_id(), task.name = name)
+}
+
+/// Wraps the blocking job `f`, so it runs within a span which records how long the job got
+/// queued.
+fn traced_blocking<F, T>(name: Option<&str>, f: F) -> impl FnOnce() -> T + Send + 'static
+where
+    F: FnOnce() -> T + Send + 'static,
+{
+    let span = tracing::info_span!(
+        target: "fut_compat::task",
+        "blocking_task",
+        task.id = next_task_id(),
+        task.name = name,
+        task.queue_time_us = tracing::field::Empty,
+    );
+    let queued = Instant::now();
+
+    move || {
+        let queue_time = u64::try_from(queued.elapsed().as_micros()).unwrap_or(u64::MAX);
+        span.record("task.queue_time_us", queue_time);
+
+        span.in_scope(f)
+    }
+}
+
+impl<E: Spawn> Spawn for TracedExecutor<E> {
+    fn spawn_obj(
+        &self,
+        future: FutureObj<'static, ()>
+    ) -> Result<(), SpawnError> {
+        let future = future.instrument(task_span(None));
+
+        self.inner.spawn_obj(FutureObj::new(Box::new(future)))
+    }
+
+    fn status(&self) -> Result<(), SpawnError> {
+        self.inner.status()
+    }
+}
+
+impl<E: SpawnHandle> SpawnHandle for TracedExecutor<E> {
+    fn spawn_handle<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.inner.spawn_handle(fut.instrument(task_span(None)))
+    }
+
+    fn spawn_handle_with_name<F>(&self, name: &str, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.inner.spawn_handle_with_name(name, fut.instrument(task_span(Some(name))))
+    }
+}
+
+impl<E: SpawnBlocking> SpawnBlocking for TracedExecutor<E> {
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.inner.spawn_blocking(traced_blocking(None, f))
+    }
+
+    fn spawn_blocking_with_name<F, T>(&self, name: &str, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.inner.spawn_blocking_with_name(name, traced_blocking(Some(name), f))
+    }
+}
+
+
+
+#[cfg(all(test, any(feature = "tokio-rt", feature = "async-std-rt")))]
+mod tests {
+    use super::*;
+
+    use futures::future::BoxFuture;
+
+    use crate::capture::Capture;
+
+    /// Spawns a named task and a blocking job through `executor` and checks their spans.
+    fn check_spans<E, B>(executor: TracedExecutor<E>, block_on: B)
+    where
+        E: SpawnHandle + SpawnBlocking,
+        B: FnOnce(BoxFuture<'static, ()>),
+    {
+        let capture = Capture::default();
+
+        tracing::subscriber::with_default(capture.clone(), || {
+            let task = executor.spawn_handle_with_name("worker", async {});
+            let job = executor.spawn_blocking(|| ());
+
+            block_on(Box::pin(async move {
+                task.await.unwrap();
+                job.await.unwrap();
+            }));
+        });
+
+        let (_, task) = capture.span("task").unwrap();
+        assert_eq!(task.target, "fut_compat::task");
+        assert_eq!(task.fields["task.name"], "worker");
+        assert!(task.fields.contains_key("task.id"));
+
+        let (_, job) = capture.span("blocking_task").unwrap();
+        assert!(!job.fields.contains_key("task.name"));
+        assert_ne!(job.fields["task.id"], task.fields["task.id"]);
+        // Recorded on the thread of the blocking pool, once the job started.
+        assert!(job.fields["task.queue_time_us"].parse::<u64>().is_ok());
+    }
+
+    #[cfg(feature = "tokio-rt")]
+    #[test]
+    fn tokio_spawns_within_spans() {
+        let runtime = ::tokio::runtime::Runtime::new().unwrap();
+        let executor = TracedExecutor::new(TokioExecutor::from_handle(runtime.handle().clone()));
+
+        check_spans(executor, |fut| runtime.block_on(fut));
+    }
+
+    #[cfg(feature = "async-std-rt")]
+    #[test]
+    fn async_std_spawns_within_spans() {
+        let executor = TracedExecutor::new(AsyncStdExecutor::default());
+
+        check_spans(executor, ::async_std::task::block_on);
+    }
+}
@@ -0,0 +1,71 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use super::Timer;
+
+
+
+/// Counts the tasks which are alive and the ones which ran to completion, so tests can check
+/// whether tasks got aborted, detached or awaited.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Tracker {
+    live: Arc<AtomicUsize>,
+    finished: Arc<AtomicUsize>,
+}
+
+/// Counts a task as alive until dropped.
+struct Live(Arc<AtomicUsize>);
+
+impl Drop for Live {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Tracker {
+    /// Returns a task which sleeps for `dur` with the timer `Tm` and then returns `output`.
+    ///
+    /// The task counts as alive from now on until it gets dropped.
+    pub(crate) fn task<Tm, T>(&self, dur: Duration, output: T) -> impl Future<Output = T> + Send + 'static
+    where
+        Tm: Timer,
+        T: Send + 'static,
+    {
+        self.live.fetch_add(1, Ordering::SeqCst);
+        let live = Live(self.live.clone());
+        let finished = self.finished.clone();
+
+        async move {
+            let _live = live;
+
+            Tm::sleep(dur).await;
+            finished.fetch_add(1, Ordering::SeqCst);
+
+            output
+        }
+    }
+
+    /// Waits up to five seconds for `done` to return `true`, e.g. for aborted tasks to get
+    /// dropped by the runtime.
+    pub(crate) async fn wait_until<Tm: Timer>(&self, done: impl Fn(&Self) -> bool) {
+        for _ in 0..500 {
+            if done(self) {
+                return;
+            }
+
+            Tm::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    /// Returns the number of tasks which have not been dropped yet.
+    pub(crate) fn live(&self) -> usize {
+        self.live.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of tasks which ran to completion.
+    pub(crate) fn finished(&self) -> usize {
+        self.finished.load(Ordering::SeqCst)
+    }
+}
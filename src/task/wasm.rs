@@ -0,0 +1,196 @@
+use super::*;
+
+use std::panic::AssertUnwindSafe;
+
+use futures::task::{Spawn, LocalSpawn};
+use futures::task::{SpawnError, FutureObj, LocalFutureObj};
+use futures::FutureExt;
+use futures::future::{Abortable, Either};
+
+use gloo_timers::future::TimeoutFuture;
+
+
+
+/// An executor for the event loop of the browser or of any other JavaScript host, using
+/// [`wasm_bindgen_futures`](https://docs.rs/wasm-bindgen-futures).
+///
+/// All tasks run on the current thread, as `wasm32-unknown-unknown` has no threads. Because of
+/// this, [`SpawnBlocking`] runs the job inline, and [`BlockOn`] is not implemented, as blocking the
+/// only thread would never let the event loop make progress.
+///
+/// [`Timer`] uses `setTimeout`, so sleeping has a granularity of one millisecond. Note that
+/// [`Instant::now`] panics on `wasm32-unknown-unknown`, which makes
+/// [`Timer::sleep_until`](Timer::sleep_until) and [`Timer::now`] unusable there.
+#[cfg(all(feature = "wasm-rt", target_arch = "wasm32"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "wasm-rt", target_arch = "wasm32"))))]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WasmExecutor {}
+
+impl Spawn for WasmExecutor {
+    fn spawn_obj(
+        &self,
+        future: FutureObj<'static, ()>
+    ) -> Result<(), SpawnError> {
+        ::wasm_bindgen_futures::spawn_local(future);
+
+        Ok(())
+    }
+}
+
+impl LocalSpawn for WasmExecutor {
+    fn spawn_local_obj(
+        &self,
+        future: LocalFutureObj<'static, ()>
+    ) -> Result<(), SpawnError> {
+        ::wasm_bindgen_futures::spawn_local(future);
+
+        Ok(())
+    }
+}
+
+impl SpawnHandle for WasmExecutor {
+    fn spawn_handle<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        // wasm-bindgen-futures returns no handle at all, so the output gets passed back through a
+        // channel and the future itself is made abortable.
+        let (abort, registration) = ::futures::future::AbortHandle::new_pair();
+        let (tx, rx) = oneshot::channel();
+
+        // Panics usually abort on wasm32, but are caught where unwinding is supported.
+        let fut = AssertUnwindSafe(fut).catch_unwind();
+
+        ::wasm_bindgen_futures::spawn_local(async move {
+            let _ = tx.send(Abortable::new(fut, registration).await);
+        });
+
+        let fut = FutureExt::map(rx, |result| match result {
+            Ok(Ok(Ok(output))) => Ok(output),
+            Ok(Ok(Err(payload))) => Err(JoinError::panic(payload)),
+            Ok(Err(_)) | Err(_) => Err(JoinError::cancelled()),
+        });
+
+        JoinHandle::with_canceller(fut, move || abort.abort())
+    }
+}
+
+impl SpawnBlocking for WasmExecutor {
+    /// Runs `f` inline on the current thread, as there is no thread to run it on.
+    ///
+    /// The event loop is blocked until `f` returns, so it should only be used for short jobs.
+    /// The returned handle is already completed.
+    fn spawn_blocking<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let result = std::panic::catch_unwind(AssertUnwindSafe(f)).map_err(JoinError::panic);
+
+        JoinHandle::new(futures::future::ready(result))
+    }
+}
+
+impl Timer for WasmExecutor {
+    type Sleep = WasmSleep;
+
+    fn sleep(dur: Duration) -> Self::Sleep {
+        // `setTimeout` takes whole milliseconds, which get rounded up so we never wake too early.
+        let millis = dur.as_nanos().div_ceil(1_000_000);
+        let millis = u32::try_from(millis).unwrap_or(u32::MAX);
+
+        let (mut tx, rx) = oneshot::channel();
+
+        // The future of gloo-timers is not `Send`, so it gets driven by a local task instead,
+        // which stops once the `WasmSleep` has been dropped.
+        ::wasm_bindgen_futures::spawn_local(async move {
+            if let Either::Left(_) = futures::future::select(TimeoutFuture::new(millis), tx.cancellation()).await {
+                let _ = tx.send(());
+            }
+        });
+
+        WasmSleep { done: rx }
+    }
+}
+
+
+
+/// The future returned by [`WasmExecutor::sleep`](Timer::sleep).
+#[cfg(all(feature = "wasm-rt", target_arch = "wasm32"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "wasm-rt", target_arch = "wasm32"))))]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct WasmSleep {
+    done: oneshot::Receiver<()>,
+}
+
+impl Future for WasmSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // The sender only gets dropped without sending if the event loop shuts down.
+        Pin::into_inner(self).done.poll_unpin(cx).map(|_| ())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::task::SpawnExt;
+
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    async fn spawned_tasks_run_on_the_event_loop() {
+        let (tx, rx) = oneshot::channel();
+
+        WasmExecutor::default().spawn(async move {
+            let _ = tx.send(42);
+        }).unwrap();
+
+        assert_eq!(rx.await.unwrap(), 42);
+    }
+
+    #[wasm_bindgen_test]
+    async fn handles_resolve_to_the_output() {
+        let handle = WasmExecutor::default().spawn_handle(async { 42 });
+
+        assert_eq!(handle.await.unwrap(), 42);
+    }
+
+    #[wasm_bindgen_test]
+    async fn aborted_tasks_resolve_as_cancelled() {
+        let handle = WasmExecutor::default().spawn_handle(futures::future::pending::<()>());
+        handle.abort();
+
+        assert!(handle.await.unwrap_err().is_cancelled());
+    }
+
+    #[wasm_bindgen_test]
+    async fn blocking_jobs_run_inline() {
+        let handle = WasmExecutor::default().spawn_blocking(|| 42);
+
+        assert_eq!(handle.now_or_never().unwrap().unwrap(), 42);
+    }
+
+    #[wasm_bindgen_test]
+    async fn sleeps_complete_after_the_timeout() {
+        let (tx, mut rx) = oneshot::channel();
+
+        WasmExecutor::default().spawn(async move {
+            WasmExecutor::sleep(Duration::from_millis(20)).await;
+
+            let _ = tx.send(());
+        }).unwrap();
+
+        // The sleep has not completed before a shorter one.
+        WasmExecutor::sleep(Duration::from_millis(1)).await;
+        assert_eq!(rx.try_recv().unwrap(), None);
+
+        rx.await.unwrap();
+    }
+}
@@ -0,0 +1,123 @@
+// Checks that every feature makes the items it promises available, so a mistyped `cfg` predicate
+// fails the build instead of silently dropping an implementation. Run it with the feature
+// combinations to check, e.g. `cargo test --test features --features tokio-rt`.
+
+#![allow(dead_code)]
+
+use fut_compat::fs::Filesystem;
+use fut_compat::io::Stdio;
+use fut_compat::net::{TcpListener, TcpStream, UdpSocket};
+use fut_compat::process::Command;
+use fut_compat::runtime::Runtime;
+use fut_compat::task::{BlockOn, SpawnBlocking, SpawnHandle, Timer};
+
+fn filesystem<T: Filesystem>() {}
+
+fn net<S: TcpStream, L: TcpListener<TcpStream = S>, U: UdpSocket>() {}
+
+fn stdio<T: Stdio>() {}
+
+fn command<T: Command>() {}
+
+fn runtime<T: Runtime>() {}
+
+fn executor<T: SpawnHandle + SpawnBlocking + BlockOn + Timer + Default>() {}
+
+#[cfg(feature = "tokio-rt")]
+#[test]
+fn tokio_rt() {
+    filesystem::<fut_compat::fs::TokioFs>();
+    net::<::tokio::net::TcpStream, ::tokio::net::TcpListener, ::tokio::net::UdpSocket>();
+    command::<::tokio::process::Command>();
+    runtime::<fut_compat::runtime::TokioRuntime>();
+    executor::<fut_compat::task::TokioExecutor>();
+
+    let _ = fut_compat::io::TokioCompat::<::tokio::net::TcpStream>::new;
+    let _ = fut_compat::io::FuturesCompat::<futures::io::Cursor<Vec<u8>>>::new;
+    stdio::<fut_compat::io::TokioStdio>();
+}
+
+#[cfg(feature = "async-std-rt")]
+#[test]
+fn async_std_rt() {
+    filesystem::<fut_compat::fs::AsyncStdFs>();
+    net::<::async_std::net::TcpStream, ::async_std::net::TcpListener, ::async_std::net::UdpSocket>();
+    command::<fut_compat::process::AsyncStdCommand>();
+    runtime::<fut_compat::runtime::AsyncStdRuntime>();
+    executor::<fut_compat::task::AsyncStdExecutor>();
+
+    stdio::<fut_compat::io::AsyncStdStdio>();
+}
+
+#[cfg(feature = "smol-rt")]
+#[test]
+fn smol_rt() {
+    executor::<fut_compat::task::SmolExecutor>();
+}
+
+#[cfg(all(feature = "wasm-rt", target_arch = "wasm32"))]
+#[test]
+fn wasm_rt() {
+    fn spawn<T: SpawnHandle + Timer + Default>() {}
+
+    spawn::<fut_compat::task::WasmExecutor>();
+}
+
+// The deprecated aliases have to keep enabling the runtime features they stand for.
+#[cfg(all(feature = "tokio", not(feature = "tokio-rt")))]
+compile_error!("the `tokio` feature does not enable `tokio-rt`");
+#[cfg(all(feature = "async-std", not(feature = "async-std-rt")))]
+compile_error!("the `async-std` feature does not enable `async-std-rt`");
+#[cfg(all(feature = "smol", not(feature = "smol-rt")))]
+compile_error!("the `smol` feature does not enable `smol-rt`");
+
+#[cfg(feature = "codec")]
+#[test]
+fn codec() {
+    let _ = fut_compat::io::framed::<futures::io::Cursor<Vec<u8>>, ::tokio_util::codec::LinesCodec>;
+}
+
+#[cfg(all(feature = "uring", target_os = "linux"))]
+#[test]
+fn uring() {
+    filesystem::<fut_compat::fs::UringFs>();
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn bytes() {
+    #[allow(unused_imports)]
+    use fut_compat::io::{AsyncReadBufExt, AsyncWriteBufExt};
+}
+
+#[cfg(feature = "digest")]
+#[test]
+fn digest() {
+    #[allow(unused_imports)]
+    use fut_compat::io::{HashingReader, HashingWriter};
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing() {
+    #[allow(unused_imports)]
+    use fut_compat::io::Traced;
+    #[allow(unused_imports)]
+    use fut_compat::net::{TracedListener, TracedStream};
+    #[allow(unused_imports)]
+    use fut_compat::task::TracedExecutor;
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_util() {
+    #[allow(unused_imports)]
+    use fut_compat::fs::FaultFs;
+    #[allow(unused_imports)]
+    use fut_compat::io::ScriptedIo;
+    #[allow(unused_imports)]
+    use fut_compat::task::{MockClock, MockTimer};
+
+    #[cfg(any(unix, windows))]
+    command::<fut_compat::process::MockCommand>();
+}
@@ -0,0 +1,275 @@
+// Implements the abstraction traits against the bare crate, so libraries can depend on the traits
+// alone and leave the choice of the backend to the application. Run it without any feature with
+// `cargo test --test no_features --no-default-features`.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::{FileType, Metadata, Permissions};
+use std::io::IoSlice;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::executor::block_on;
+
+use fut_compat::fs::{DirEntry, Filesystem, ReflinkOutcome};
+use fut_compat::net::{connect_with_retry, RetryPolicy, TcpStream, ToSocketAddrs};
+use fut_compat::task::Timer;
+
+fn unsupported<T>() -> std::io::Result<T> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+/// The files of [`MemoryFs`], shared by all tests.
+static FILES: Mutex<Option<HashMap<PathBuf, Vec<u8>>>> = Mutex::new(None);
+
+fn with_files<R>(f: impl FnOnce(&mut HashMap<PathBuf, Vec<u8>>) -> R) -> R {
+    f(FILES.lock().unwrap_or_else(|err| err.into_inner()).get_or_insert_with(HashMap::new))
+}
+
+/// A filesystem which only supports reading and writing files held in memory.
+struct MemoryFs;
+
+struct MemoryDirEntry;
+
+#[async_trait]
+impl DirEntry for MemoryDirEntry {
+    fn path(&self) -> PathBuf {
+        PathBuf::new()
+    }
+
+    fn file_name(&self) -> OsString {
+        OsString::new()
+    }
+
+    async fn metadata(&self) -> std::io::Result<Metadata> {
+        unsupported()
+    }
+
+    async fn file_type(&self) -> std::io::Result<FileType> {
+        unsupported()
+    }
+}
+
+#[async_trait]
+impl Filesystem for MemoryFs {
+    type ReadDir = futures::stream::Empty<std::io::Result<MemoryDirEntry>>;
+    type DirEntry = MemoryDirEntry;
+
+    async fn canonicalize<P: AsRef<Path> + Send>(path: P) -> std::io::Result<PathBuf> {
+        Ok(path.as_ref().to_path_buf())
+    }
+
+    async fn copy<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        from: S,
+        to: D,
+    ) -> std::io::Result<u64> {
+        let contents = Self::read(from).await?;
+        let len = contents.len() as u64;
+
+        Self::write(to, contents).await?;
+
+        Ok(len)
+    }
+
+    async fn create_dir<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+        unsupported()
+    }
+
+    async fn create_dir_all<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+        unsupported()
+    }
+
+    async fn hard_link<S: AsRef<Path> + Send, D: AsRef<Path> + Send>(
+        _from: S,
+        _to: D,
+    ) -> std::io::Result<()> {
+        unsupported()
+    }
+
+    async fn metadata<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<Metadata> {
+        unsupported()
+    }
+
+    async fn read<P: AsRef<Path> + Send>(path: P) -> std::io::Result<Vec<u8>> {
+        with_files(|files| files.get(path.as_ref()).cloned())
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))
+    }
+
+    async fn read_dir<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<Self::ReadDir> {
+        Ok(futures::stream::empty())
+    }
+
+    async fn read_link<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<PathBuf> {
+        unsupported()
+    }
+
+    async fn read_to_string<P: AsRef<Path> + Send>(path: P) -> std::io::Result<String> {
+        String::from_utf8(Self::read(path).await?)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    async fn remove_dir<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+        unsupported()
+    }
+
+    async fn remove_dir_all<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<()> {
+        unsupported()
+    }
+
+    async fn remove_file<P: AsRef<Path> + Send>(path: P) -> std::io::Result<()> {
+        with_files(|files| files.remove(path.as_ref()))
+            .map(|_| ())
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))
+    }
+
+    async fn rename<O: AsRef<Path> + Send, N: AsRef<Path> + Send>(
+        from: O,
+        to: N,
+    ) -> std::io::Result<()> {
+        let contents = Self::read(from.as_ref().to_path_buf()).await?;
+
+        with_files(|files| {
+            files.remove(from.as_ref());
+            files.insert(to.as_ref().to_path_buf(), contents);
+        });
+
+        Ok(())
+    }
+
+    async fn set_permissions<P: AsRef<Path> + Send>(
+        _path: P,
+        _perm: Permissions,
+    ) -> std::io::Result<()> {
+        unsupported()
+    }
+
+    async fn symlink_metadata<P: AsRef<Path> + Send>(_path: P) -> std::io::Result<Metadata> {
+        unsupported()
+    }
+
+    async fn write<P: AsRef<Path> + Send, C: AsRef<[u8]> + Send>(
+        path: P,
+        contents: C,
+    ) -> std::io::Result<()> {
+        with_files(|files| files.insert(path.as_ref().to_path_buf(), contents.as_ref().to_vec()));
+
+        Ok(())
+    }
+}
+
+/// How often [`RefusedStream::connect`] has been called.
+static CONNECT_ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+/// A stream whose connects get refused twice before they succeed.
+struct RefusedStream;
+
+#[async_trait]
+impl TcpStream for RefusedStream {
+    async fn connect<A: ToSocketAddrs + Send>(_addrs: A) -> std::io::Result<Self> {
+        match CONNECT_ATTEMPTS.fetch_add(1, Ordering::SeqCst) {
+            0 | 1 => Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused)),
+            _ => Ok(Self),
+        }
+    }
+
+    async fn connect_from<A: ToSocketAddrs + Send>(
+        _local: SocketAddr,
+        addrs: A,
+    ) -> std::io::Result<Self> {
+        Self::connect(addrs).await
+    }
+
+    async fn peek(&self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(0)
+    }
+
+    fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+        unsupported()
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        unsupported()
+    }
+
+    fn nodelay(&self) -> std::io::Result<bool> {
+        Ok(false)
+    }
+
+    fn set_nodelay(&self, _nodelay: bool) -> std::io::Result<()> {
+        unsupported()
+    }
+
+    fn ttl(&self) -> std::io::Result<u32> {
+        Ok(64)
+    }
+
+    fn set_ttl(&self, _ttl: u32) -> std::io::Result<()> {
+        unsupported()
+    }
+
+    fn tos(&self) -> std::io::Result<u32> {
+        Ok(0)
+    }
+
+    fn set_tos(&self, _tos: u32) -> std::io::Result<()> {
+        unsupported()
+    }
+
+    async fn readable(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    async fn writable(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn try_read(&self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(0)
+    }
+
+    fn try_write(&self, _buf: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::from(std::io::ErrorKind::WouldBlock))
+    }
+}
+
+/// A timer whose sleeps complete right away.
+struct ImmediateTimer;
+
+impl Timer for ImmediateTimer {
+    type Sleep = futures::future::Ready<()>;
+
+    fn sleep(_dur: Duration) -> Self::Sleep {
+        futures::future::ready(())
+    }
+}
+
+#[test]
+fn default_filesystem_methods_use_the_required_ones() {
+    block_on(async {
+        let bufs = [IoSlice::new(b"hello "), IoSlice::new(b"world")];
+        MemoryFs::write_vectored("greeting", &bufs).await.unwrap();
+
+        assert_eq!(MemoryFs::read_to_string("greeting").await.unwrap(), "hello world");
+
+        let outcome = MemoryFs::copy_reflink("greeting", "copy").await.unwrap();
+
+        assert_eq!(outcome, ReflinkOutcome::Copied);
+        assert_eq!(MemoryFs::read("copy").await.unwrap(), b"hello world");
+    });
+}
+
+#[test]
+fn generic_utilities_work_with_custom_streams() {
+    block_on(async {
+        let addr: SocketAddr = "127.0.0.1:1".parse().unwrap();
+        let policy = RetryPolicy::new().max_attempts(3);
+
+        connect_with_retry::<RefusedStream, ImmediateTimer, _>(addr, policy).await.unwrap();
+
+        assert_eq!(CONNECT_ATTEMPTS.load(Ordering::SeqCst), 3);
+    });
+}